@@ -0,0 +1,38 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use thiserror::Error;
+
+use solana_client::client_error::ClientError as RpcClientError;
+
+#[derive(Error, Debug)]
+pub enum ApiError {
+    /// Database query failed
+    #[error("Database error: {0}")]
+    Database(#[from] tokio_postgres::Error),
+
+    /// RPC request failed
+    #[error("RPC error: {0}")]
+    Rpc(#[from] RpcClientError),
+
+    /// A blocking RPC task panicked or was cancelled
+    #[error("Task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+
+    /// The query needs the indexer's database
+    #[error("{0} requires the indexer database")]
+    Unavailable(&'static str),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            ApiError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
@@ -0,0 +1,96 @@
+mod error;
+mod models;
+mod store;
+mod stream;
+
+use std::{env, net::SocketAddr, process::exit, str::FromStr, sync::Arc};
+
+use axum::{
+    extract::{Extension, Query},
+    routing::get,
+    AddExtensionLayer, Json, Router,
+};
+use solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use tokio_postgres::NoTls;
+
+use crate::{
+    error::ApiResult,
+    models::{Bid, BidFilter, Listing, ListingFilter, Sale, SaleFilter},
+    store::Store,
+    stream::ActivityFeed,
+};
+
+const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:8080";
+const DEFAULT_RPC_URL: &str = "http://localhost:8899";
+
+async fn listings(Extension(store): Extension<Arc<Store>>, Query(filter): Query<ListingFilter>) -> ApiResult<Json<Vec<Listing>>> {
+    Ok(Json(store.listings(filter).await?))
+}
+
+async fn bids(Extension(store): Extension<Arc<Store>>, Query(filter): Query<BidFilter>) -> ApiResult<Json<Vec<Bid>>> {
+    Ok(Json(store.bids(filter).await?))
+}
+
+async fn sales(Extension(store): Extension<Arc<Store>>, Query(filter): Query<SaleFilter>) -> ApiResult<Json<Vec<Sale>>> {
+    Ok(Json(store.sales(filter).await?))
+}
+
+/// Serves the indexer's database when `DATABASE_URL` is set, and scans the
+/// program's accounts over `RPC_URL` otherwise, which needs `PROGRAM_ID`.
+/// The `/stream` activity feed needs the database. `LISTEN_ADDR` picks the
+/// bind address.
+#[tokio::main]
+async fn main() {
+    let mut feed = None;
+    let store = match env::var("DATABASE_URL") {
+        Ok(database_url) => {
+            feed = Some(ActivityFeed::listen(&database_url).await.unwrap_or_else(|err| {
+                eprintln!("error: {}", err);
+                exit(1);
+            }));
+            let (client, connection) = tokio_postgres::connect(&database_url, NoTls).await.unwrap_or_else(|err| {
+                eprintln!("error: {}", err);
+                exit(1);
+            });
+            tokio::spawn(async move {
+                if let Err(err) = connection.await {
+                    eprintln!("error: database connection closed: {}", err);
+                    exit(1);
+                }
+            });
+            Store::Database(client)
+        }
+        Err(_) => {
+            let program_id = env::var("PROGRAM_ID")
+                .ok()
+                .and_then(|program_id| Pubkey::from_str(&program_id).ok())
+                .unwrap_or_else(|| {
+                    eprintln!("error: PROGRAM_ID must be set when DATABASE_URL is not");
+                    exit(1);
+                });
+            let rpc_url = env::var("RPC_URL").unwrap_or_else(|_| DEFAULT_RPC_URL.to_string());
+            Store::Rpc { rpc: Arc::new(RpcClient::new(rpc_url)), program_id }
+        }
+    };
+
+    let app = Router::new()
+        .route("/listings", get(listings))
+        .route("/bids", get(bids))
+        .route("/sales", get(sales))
+        .route("/stream", get(stream::stream))
+        .layer(AddExtensionLayer::new(Arc::new(store)))
+        .layer(AddExtensionLayer::new(feed));
+
+    let addr: SocketAddr = env::var("LISTEN_ADDR")
+        .unwrap_or_else(|_| DEFAULT_LISTEN_ADDR.to_string())
+        .parse()
+        .unwrap_or_else(|err| {
+            eprintln!("error: invalid LISTEN_ADDR: {}", err);
+            exit(1);
+        });
+    if let Err(err) = axum::Server::bind(&addr).serve(app.into_make_service()).await {
+        eprintln!("error: {}", err);
+        exit(1);
+    }
+}
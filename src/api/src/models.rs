@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use solana_program::pubkey::Pubkey;
+
+use nft_trading::state::{BidEscrowState, ListEscrowState};
+
+/// Most rows a query returns unless it asks for fewer.
+pub const MAX_LIMIT: i64 = 1_000;
+
+#[derive(Debug, Serialize)]
+pub struct Listing {
+    pub address: String,
+    pub mint: String,
+    pub lister: String,
+    pub price: u64,
+    pub auction: bool,
+    pub highest_bid: u64,
+    pub collection: Option<String>,
+}
+
+impl Listing {
+    pub fn from_state(address: &Pubkey, listing: &ListEscrowState, collection: Option<Pubkey>) -> Self {
+        Self {
+            address: address.to_string(),
+            mint: listing.mint.to_string(),
+            lister: listing.lister.to_string(),
+            price: listing.amount,
+            auction: listing.auction,
+            highest_bid: listing.highest_bid,
+            collection: collection.map(|collection| collection.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Bid {
+    pub address: String,
+    pub mint: String,
+    pub bidder: String,
+    pub amount: u64,
+    pub wrapped: bool,
+}
+
+impl Bid {
+    pub fn from_state(address: &Pubkey, bid: &BidEscrowState) -> Self {
+        Self {
+            address: address.to_string(),
+            mint: bid.mint.to_string(),
+            bidder: bid.bidder.to_string(),
+            amount: bid.amount,
+            wrapped: bid.wrapped,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Sale {
+    pub signature: String,
+    pub mint: String,
+    pub price: u64,
+    pub slot: u64,
+    pub seller: Option<String>,
+    pub buyer: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListingFilter {
+    pub collection: Option<String>,
+    pub mint: Option<String>,
+    pub lister: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BidFilter {
+    pub mint: Option<String>,
+    pub bidder: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// `wallet` matches either side of the sale.
+#[derive(Debug, Default, Deserialize)]
+pub struct SaleFilter {
+    pub wallet: Option<String>,
+    pub mint: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Clamps a requested row limit to `1..=MAX_LIMIT`.
+pub fn limit(requested: Option<i64>) -> i64 {
+    requested.unwrap_or(MAX_LIMIT).max(1).min(MAX_LIMIT)
+}
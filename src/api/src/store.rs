@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use borsh::BorshDeserialize;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+};
+use solana_program::pubkey::Pubkey;
+use tokio_postgres::Client;
+
+use nft_trading::{
+    metadata::{self, Metadata},
+    state::{self, BidEscrowState, Discriminator, ListEscrowState},
+};
+use nft_trading_indexer::indexer;
+
+use crate::{
+    error::{ApiError, ApiResult},
+    models::{self, Bid, BidFilter, Listing, ListingFilter, Sale, SaleFilter},
+};
+
+/// Largest batch `getMultipleAccounts` accepts.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Where queries are answered from: the indexer's database, or scans of
+/// the program's accounts over RPC when no database is configured. Scans
+/// only see accounts written since the Borsh migration and cannot answer
+/// sale history.
+pub enum Store {
+    Database(Client),
+    Rpc { rpc: Arc<RpcClient>, program_id: Pubkey },
+}
+
+impl Store {
+    pub async fn listings(&self, filter: ListingFilter) -> ApiResult<Vec<Listing>> {
+        match self {
+            Store::Database(client) => {
+                let rows = client
+                    .query(
+                        "SELECT address, mint, lister, price, auction, highest_bid, collection FROM listings
+                         WHERE ($1::TEXT IS NULL OR collection = $1)
+                           AND ($2::TEXT IS NULL OR mint = $2)
+                           AND ($3::TEXT IS NULL OR lister = $3)
+                         ORDER BY slot DESC LIMIT $4",
+                        &[&filter.collection, &filter.mint, &filter.lister, &models::limit(filter.limit)],
+                    )
+                    .await?;
+                Ok(rows
+                    .iter()
+                    .map(|row| Listing {
+                        address: row.get(0),
+                        mint: row.get(1),
+                        lister: row.get(2),
+                        price: row.get::<_, i64>(3) as u64,
+                        auction: row.get(4),
+                        highest_bid: row.get::<_, i64>(5) as u64,
+                        collection: row.get(6),
+                    })
+                    .collect())
+            }
+            Store::Rpc { rpc, program_id } => {
+                let (rpc, program_id) = (rpc.clone(), *program_id);
+                tokio::task::spawn_blocking(move || scan_listings(&rpc, &program_id, filter)).await?
+            }
+        }
+    }
+
+    pub async fn bids(&self, filter: BidFilter) -> ApiResult<Vec<Bid>> {
+        match self {
+            Store::Database(client) => {
+                let rows = client
+                    .query(
+                        "SELECT address, mint, bidder, amount, wrapped FROM bids
+                         WHERE ($1::TEXT IS NULL OR mint = $1)
+                           AND ($2::TEXT IS NULL OR bidder = $2)
+                         ORDER BY amount DESC LIMIT $3",
+                        &[&filter.mint, &filter.bidder, &models::limit(filter.limit)],
+                    )
+                    .await?;
+                Ok(rows
+                    .iter()
+                    .map(|row| Bid {
+                        address: row.get(0),
+                        mint: row.get(1),
+                        bidder: row.get(2),
+                        amount: row.get::<_, i64>(3) as u64,
+                        wrapped: row.get(4),
+                    })
+                    .collect())
+            }
+            Store::Rpc { rpc, program_id } => {
+                let (rpc, program_id) = (rpc.clone(), *program_id);
+                tokio::task::spawn_blocking(move || scan_bids(&rpc, &program_id, filter)).await?
+            }
+        }
+    }
+
+    pub async fn sales(&self, filter: SaleFilter) -> ApiResult<Vec<Sale>> {
+        let client = match self {
+            Store::Database(client) => client,
+            Store::Rpc { .. } => return Err(ApiError::Unavailable("Sale history")),
+        };
+        let rows = client
+            .query(
+                "SELECT signature, mint, price, slot, seller, buyer FROM sales
+                 WHERE ($1::TEXT IS NULL OR seller = $1 OR buyer = $1)
+                   AND ($2::TEXT IS NULL OR mint = $2)
+                 ORDER BY slot DESC LIMIT $3",
+                &[&filter.wallet, &filter.mint, &models::limit(filter.limit)],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| Sale {
+                signature: row.get(0),
+                mint: row.get(1),
+                price: row.get::<_, i64>(2) as u64,
+                slot: row.get::<_, i64>(3) as u64,
+                seller: row.get(4),
+                buyer: row.get(5),
+            })
+            .collect())
+    }
+}
+
+/// Fetches every program account whose data starts with `T`'s
+/// discriminator.
+fn program_accounts<T: Discriminator>(rpc: &RpcClient, program_id: &Pubkey) -> ApiResult<Vec<(Pubkey, Vec<u8>)>> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp {
+            offset: 0,
+            bytes: MemcmpEncodedBytes::Binary(bs58::encode(T::DISCRIMINATOR).into_string()),
+            encoding: None,
+        })]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+    let accounts = rpc.get_program_accounts_with_config(program_id, config)?;
+    Ok(accounts.into_iter().map(|(address, account)| (address, account.data)).collect())
+}
+
+fn scan_listings(rpc: &RpcClient, program_id: &Pubkey, filter: ListingFilter) -> ApiResult<Vec<Listing>> {
+    let mut listings: Vec<(Pubkey, ListEscrowState)> = program_accounts::<ListEscrowState>(rpc, program_id)?
+        .into_iter()
+        .filter_map(|(address, data)| Some((address, state::unpack_compat::<ListEscrowState>(&data).ok()?)))
+        .filter(|(_, listing)| indexer::is_public_listing(listing))
+        .filter(|(_, listing)| matches(&filter.mint, &listing.mint) && matches(&filter.lister, &listing.lister))
+        .collect();
+    let limit = models::limit(filter.limit) as usize;
+    if filter.collection.is_none() {
+        listings.truncate(limit);
+    }
+
+    let mut result = vec![];
+    for chunk in listings.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let metadata_addresses: Vec<Pubkey> = chunk
+            .iter()
+            .map(|(_, listing)| metadata::find_metadata_address(&listing.mint).0)
+            .collect();
+        let accounts = rpc.get_multiple_accounts(&metadata_addresses)?;
+        for ((address, listing), account) in chunk.iter().zip(accounts) {
+            let collection = account
+                .and_then(|account| Metadata::deserialize(&mut &account.data[..]).ok())
+                .and_then(|metadata| metadata.verified_collection());
+            if filter.collection.is_some() && filter.collection != collection.map(|collection| collection.to_string()) {
+                continue;
+            }
+            result.push(Listing::from_state(address, listing, collection));
+        }
+        if result.len() >= limit {
+            result.truncate(limit);
+            break;
+        }
+    }
+    Ok(result)
+}
+
+fn scan_bids(rpc: &RpcClient, program_id: &Pubkey, filter: BidFilter) -> ApiResult<Vec<Bid>> {
+    let mut bids: Vec<Bid> = program_accounts::<BidEscrowState>(rpc, program_id)?
+        .into_iter()
+        .filter_map(|(address, data)| Some((address, state::unpack_compat::<BidEscrowState>(&data).ok()?)))
+        .filter(|(_, bid)| bid.is_initialized)
+        .filter(|(_, bid)| matches(&filter.mint, &bid.mint) && matches(&filter.bidder, &bid.bidder))
+        .map(|(address, bid)| Bid::from_state(&address, &bid))
+        .collect();
+    bids.sort_by(|a, b| b.amount.cmp(&a.amount));
+    bids.truncate(models::limit(filter.limit) as usize);
+    Ok(bids)
+}
+
+fn matches(filter: &Option<String>, key: &Pubkey) -> bool {
+    filter.as_ref().map_or(true, |filter| *filter == key.to_string())
+}
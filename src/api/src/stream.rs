@@ -0,0 +1,110 @@
+use std::{process::exit, sync::Arc};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Extension, Query,
+    },
+    response::{IntoResponse, Response},
+};
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_postgres::{AsyncMessage, Client, NoTls};
+
+use nft_trading_indexer::events::EVENTS_CHANNEL;
+
+use crate::error::{ApiError, ApiResult};
+
+/// Events buffered per subscriber before a slow one starts missing them.
+const EVENT_BUFFER: usize = 1_024;
+
+/// Fans the indexer's event notifications out to WebSocket subscribers.
+#[derive(Clone)]
+pub struct ActivityFeed {
+    events: broadcast::Sender<String>,
+    // Dropping the client would close the connection it listens on.
+    _listener: Arc<Client>,
+}
+
+impl ActivityFeed {
+    /// Opens a dedicated connection to `database_url` and listens for the
+    /// indexer's events on it.
+    pub async fn listen(database_url: &str) -> Result<Self, tokio_postgres::Error> {
+        let (client, mut connection) = tokio_postgres::connect(database_url, NoTls).await?;
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        let sender = events.clone();
+        let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+        tokio::spawn(async move {
+            while let Some(message) = messages.next().await {
+                match message {
+                    Ok(AsyncMessage::Notification(notification)) => {
+                        // Sending only fails while nobody is subscribed.
+                        let _ = sender.send(notification.payload().to_string());
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        eprintln!("error: event connection failed: {}", err);
+                        exit(1);
+                    }
+                }
+            }
+            eprintln!("error: event connection closed");
+            exit(1);
+        });
+        client.batch_execute(&format!("LISTEN {}", EVENTS_CHANNEL)).await?;
+        Ok(Self { events, _listener: Arc::new(client) })
+    }
+}
+
+/// Subscribers only receive events for `mint` and `collection` when set.
+#[derive(Debug, Default, Deserialize)]
+pub struct StreamFilter {
+    pub mint: Option<String>,
+    pub collection: Option<String>,
+}
+
+/// The fields of an indexer event that subscribers can filter on.
+#[derive(Deserialize)]
+struct EventKeys {
+    mint: String,
+    #[serde(default)]
+    collection: Option<String>,
+}
+
+impl StreamFilter {
+    fn matches(&self, event: &str) -> bool {
+        let keys = match serde_json::from_str::<EventKeys>(event) {
+            Ok(keys) => keys,
+            Err(_) => return false,
+        };
+        self.mint.as_ref().map_or(true, |mint| *mint == keys.mint)
+            && self.collection.as_ref().map_or(true, |collection| keys.collection.as_ref() == Some(collection))
+    }
+}
+
+/// Upgrades to a WebSocket that receives every matching event as a JSON
+/// text message.
+pub async fn stream(
+    ws: WebSocketUpgrade,
+    Extension(feed): Extension<Option<ActivityFeed>>,
+    Query(filter): Query<StreamFilter>,
+) -> ApiResult<Response> {
+    let feed = feed.ok_or(ApiError::Unavailable("Activity stream"))?;
+    let events = feed.events.subscribe();
+    Ok(ws.on_upgrade(move |socket| forward(socket, events, filter)).into_response())
+}
+
+async fn forward(mut socket: WebSocket, mut events: broadcast::Receiver<String>, filter: StreamFilter) {
+    loop {
+        match events.recv().await {
+            Ok(event) => {
+                if filter.matches(&event) && socket.send(Message::Text(event)).await.is_err() {
+                    return;
+                }
+            }
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
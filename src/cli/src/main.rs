@@ -0,0 +1,291 @@
+use clap::{crate_description, crate_name, crate_version, value_t_or_exit, App, AppSettings, Arg, ArgMatches, SubCommand};
+use solana_clap_utils::{
+    input_parsers::{lamports_of_sol, pubkey_of},
+    input_validators::{is_amount, is_pubkey, is_url},
+};
+use solana_sdk::signature::{read_keypair_file, Signer};
+
+use nft_trading_client::{error::ClientError, nft_trading::state, NFTTradingClient};
+use spl_associated_token_account::get_associated_token_address;
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Maps a `pause` argument to the `state::PAUSE_*` bits it stands for.
+fn pause_bits(group: &str) -> CliResult<u64> {
+    match group {
+        "none" => Ok(0),
+        "list" => Ok(state::PAUSE_LIST),
+        "delist" => Ok(state::PAUSE_DELIST),
+        "bid" => Ok(state::PAUSE_BID),
+        "sale" => Ok(state::PAUSE_SALE),
+        "all" => Ok(state::PAUSE_ALL),
+        _ => Err(format!("unknown pause group `{}`", group).into()),
+    }
+}
+
+fn token_account_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("token_account")
+        .long("token-account")
+        .value_name("PUBKEY")
+        .takes_value(true)
+        .validator(is_pubkey)
+        .help("NFT token account [default: the signer's associated token account]")
+}
+
+fn app<'a, 'b>() -> App<'a, 'b> {
+    App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("config_file")
+                .short("C")
+                .long("config")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("Solana CLI configuration file to read the RPC URL and keypair from"),
+        )
+        .arg(
+            Arg::with_name("json_rpc_url")
+                .short("u")
+                .long("url")
+                .value_name("URL")
+                .takes_value(true)
+                .global(true)
+                .validator(is_url)
+                .help("JSON RPC URL, overriding the configuration file"),
+        )
+        .arg(
+            Arg::with_name("keypair")
+                .short("k")
+                .long("keypair")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("Signing keypair, overriding the configuration file"),
+        )
+        .arg(
+            Arg::with_name("program_id")
+                .long("program-id")
+                .value_name("PUBKEY")
+                .takes_value(true)
+                .required(true)
+                .validator(is_pubkey)
+                .help("Address of the deployed NFT trading program"),
+        )
+        .subcommand(
+            SubCommand::with_name("init-platform")
+                .about("Create the platform state")
+                .arg(Arg::with_name("maker_bps").value_name("MAKER_BPS").required(true).index(1))
+                .arg(Arg::with_name("taker_bps").value_name("TAKER_BPS").required(true).index(2))
+                .arg(
+                    Arg::with_name("authority")
+                        .long("authority")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .validator(is_pubkey)
+                        .help("Platform authority [default: the signing keypair]"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("change-fee")
+                .about("Change the maker and taker fees")
+                .arg(Arg::with_name("maker_bps").value_name("MAKER_BPS").required(true).index(1))
+                .arg(Arg::with_name("taker_bps").value_name("TAKER_BPS").required(true).index(2)),
+        )
+        .subcommand(
+            SubCommand::with_name("change-authority")
+                .about("Hand the platform over to a new authority")
+                .arg(Arg::with_name("new_authority").value_name("PUBKEY").required(true).index(1).validator(is_pubkey)),
+        )
+        .subcommand(
+            SubCommand::with_name("pause")
+                .about("Replace the pause mask; `none` resumes everything")
+                .arg(
+                    Arg::with_name("groups")
+                        .value_name("GROUP")
+                        .required(true)
+                        .multiple(true)
+                        .possible_values(&["none", "list", "delist", "bid", "sale", "all"]),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("List an NFT for sale")
+                .arg(Arg::with_name("mint").value_name("MINT").required(true).index(1).validator(is_pubkey))
+                .arg(Arg::with_name("price").value_name("SOL").required(true).index(2).validator(is_amount))
+                .arg(token_account_arg())
+                .arg(Arg::with_name("auction").long("auction").help("Run an auction with PRICE as the reserve"))
+                .arg(
+                    Arg::with_name("allowed_buyer")
+                        .long("allowed-buyer")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .validator(is_pubkey)
+                        .help("Only let this wallet buy"),
+                )
+                .arg(
+                    Arg::with_name("starts_at")
+                        .long("starts-at")
+                        .value_name("UNIX_TIMESTAMP")
+                        .takes_value(true)
+                        .help("Hold the sale until this time"),
+                )
+                .arg(
+                    Arg::with_name("royalty_bps")
+                        .long("royalty-bps")
+                        .value_name("BPS")
+                        .takes_value(true)
+                        .help("Pay creators this royalty if above the metadata's, up to the platform cap"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("delist")
+                .about("Cancel a listing and take the NFT back")
+                .arg(Arg::with_name("mint").value_name("MINT").required(true).index(1).validator(is_pubkey))
+                .arg(token_account_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("bid")
+                .about("Escrow a bid on an NFT")
+                .arg(Arg::with_name("mint").value_name("MINT").required(true).index(1).validator(is_pubkey))
+                .arg(Arg::with_name("amount").value_name("SOL").required(true).index(2).validator(is_amount))
+                .arg(Arg::with_name("wrapped").long("wrapped").help("Escrow the bid as wSOL"))
+                .arg(
+                    Arg::with_name("referrer")
+                        .long("referrer")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .validator(is_pubkey)
+                        .help("Share the taker fee with this referrer"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("accept-bid")
+                .about("Sell a listed NFT to a bidder")
+                .arg(Arg::with_name("mint").value_name("MINT").required(true).index(1).validator(is_pubkey))
+                .arg(Arg::with_name("bidder").value_name("BIDDER").required(true).index(2).validator(is_pubkey))
+                .arg(
+                    Arg::with_name("deliver")
+                        .long("deliver")
+                        .help("Send the NFT straight to the bidder's associated token account"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("withdraw-bid")
+                .about("Withdraw a bid and its escrowed lamports")
+                .arg(Arg::with_name("mint").value_name("MINT").required(true).index(1).validator(is_pubkey)),
+        )
+        .subcommand(
+            SubCommand::with_name("refund-user")
+                .about("Refund a bidder's escrowed bid")
+                .arg(Arg::with_name("mint").value_name("MINT").required(true).index(1).validator(is_pubkey))
+                .arg(Arg::with_name("bidder").value_name("BIDDER").required(true).index(2).validator(is_pubkey)),
+        )
+}
+
+fn run(matches: &ArgMatches) -> CliResult<()> {
+    let config = match matches.value_of("config_file").or_else(|| solana_cli_config::CONFIG_FILE.as_deref()) {
+        Some(config_file) => solana_cli_config::Config::load(config_file).unwrap_or_default(),
+        None => solana_cli_config::Config::default(),
+    };
+    let json_rpc_url = matches.value_of("json_rpc_url").map(str::to_string).unwrap_or(config.json_rpc_url);
+    let keypair_path = matches.value_of("keypair").map(str::to_string).unwrap_or(config.keypair_path);
+    let signer = read_keypair_file(&keypair_path)
+        .map_err(|err| format!("failed to read keypair {}: {}", keypair_path, err))?;
+    let program_id = pubkey_of(matches, "program_id").unwrap();
+    let client = NFTTradingClient::new(json_rpc_url, program_id);
+
+    let signature = match matches.subcommand() {
+        ("init-platform", Some(matches)) => {
+            let authority = pubkey_of(matches, "authority").unwrap_or_else(|| signer.pubkey());
+            let maker_bps = value_t_or_exit!(matches, "maker_bps", u64);
+            let taker_bps = value_t_or_exit!(matches, "taker_bps", u64);
+            client.initialize_platform(&signer, &authority, maker_bps, taker_bps)?
+        }
+        ("change-fee", Some(matches)) => {
+            let maker_bps = value_t_or_exit!(matches, "maker_bps", u64);
+            let taker_bps = value_t_or_exit!(matches, "taker_bps", u64);
+            client.change_fees(&signer, maker_bps, taker_bps)?
+        }
+        ("change-authority", Some(matches)) => {
+            client.change_authority(&signer, &pubkey_of(matches, "new_authority").unwrap())?
+        }
+        ("pause", Some(matches)) => {
+            let mut paused = 0;
+            for group in matches.values_of("groups").unwrap() {
+                paused |= pause_bits(group)?;
+            }
+            client.set_paused(&signer, paused)?
+        }
+        ("list", Some(matches)) => {
+            let mint = pubkey_of(matches, "mint").unwrap();
+            let token_account = pubkey_of(matches, "token_account")
+                .unwrap_or_else(|| get_associated_token_address(&signer.pubkey(), &mint));
+            let price = lamports_of_sol(matches, "price").unwrap();
+            let allowed_buyer = pubkey_of(matches, "allowed_buyer");
+            let starts_at = if matches.is_present("starts_at") {
+                value_t_or_exit!(matches, "starts_at", i64)
+            } else {
+                0
+            };
+            let royalty_bps = if matches.is_present("royalty_bps") {
+                value_t_or_exit!(matches, "royalty_bps", u16)
+            } else {
+                0
+            };
+            let signature = client.list(&signer, &token_account, &mint, price, matches.is_present("auction"), allowed_buyer.as_ref(), starts_at, royalty_bps)?;
+            println!("{:#?}", client.get_list_escrow_state(&mint, &signer.pubkey())?);
+            signature
+        }
+        ("delist", Some(matches)) => {
+            let mint = pubkey_of(matches, "mint").unwrap();
+            let token_account = pubkey_of(matches, "token_account")
+                .unwrap_or_else(|| get_associated_token_address(&signer.pubkey(), &mint));
+            client.delist(&signer, &token_account, &mint)?
+        }
+        ("bid", Some(matches)) => {
+            let mint = pubkey_of(matches, "mint").unwrap();
+            let amount = lamports_of_sol(matches, "amount").unwrap();
+            let referrer = pubkey_of(matches, "referrer");
+            let signature = client.bid(&signer, &mint, amount, matches.is_present("wrapped"), referrer.as_ref())?;
+            println!("{:#?}", client.get_bid_escrow_state(&mint, &signer.pubkey())?);
+            signature
+        }
+        ("accept-bid", Some(matches)) => {
+            let mint = pubkey_of(matches, "mint").unwrap();
+            let bidder = pubkey_of(matches, "bidder").unwrap();
+            let bidder_token = if matches.is_present("deliver") {
+                Some(get_associated_token_address(&bidder, &mint))
+            } else {
+                None
+            };
+            client.accept_bid(&signer, &mint, &bidder, bidder_token.as_ref())?
+        }
+        ("withdraw-bid", Some(matches)) => {
+            client.withdraw_bid(&signer, &pubkey_of(matches, "mint").unwrap())?
+        }
+        ("refund-user", Some(matches)) => {
+            let mint = pubkey_of(matches, "mint").unwrap();
+            let bidder = pubkey_of(matches, "bidder").unwrap();
+            client.refund_user(&signer, &mint, &bidder)?
+        }
+        _ => unreachable!(),
+    };
+    println!("Signature: {}", signature);
+    Ok(())
+}
+
+fn main() {
+    let matches = app().get_matches();
+    if let Err(err) = run(&matches) {
+        eprintln!("error: {}", err);
+        if let Some(ClientError::SimulationFailed(_, logs)) = err.downcast_ref::<ClientError>() {
+            for log in logs {
+                eprintln!("  {}", log);
+            }
+        }
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,30 @@
+use thiserror::Error;
+
+use solana_client::client_error::ClientError as RpcClientError;
+use solana_program::program_error::ProgramError;
+use solana_sdk::{signature::Signature, transaction::TransactionError};
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    /// RPC request failed
+    #[error("RPC error: {0}")]
+    Rpc(#[from] RpcClientError),
+
+    /// Account data could not be deserialized
+    #[error("Failed to deserialize account data: {0}")]
+    Deserialize(#[from] ProgramError),
+
+    /// Transaction failed its preflight simulation
+    #[error("Simulation failed: {0}")]
+    SimulationFailed(TransactionError, Vec<String>),
+
+    /// Transaction was processed but failed
+    #[error("Transaction {0} failed: {1}")]
+    TransactionFailed(Signature, TransactionError),
+
+    /// Transaction was not confirmed in time
+    #[error("Transaction {0} was not confirmed in time")]
+    ConfirmationTimeout(Signature),
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
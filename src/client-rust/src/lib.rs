@@ -0,0 +1,443 @@
+pub mod error;
+
+use std::{thread::sleep, time::{Duration, Instant}};
+
+use solana_client::{rpc_client::RpcClient, rpc_config::RpcSendTransactionConfig};
+use borsh::BorshDeserialize;
+use solana_program::{instruction::Instruction, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+
+use nft_trading::{
+    instruction::{self, Bumps},
+    metadata::{self, Metadata},
+    fraction::{self, FractionState},
+    order,
+    otc::{self, OtcState},
+    pool::{self, Pool},
+    raffle::{self, RaffleState},
+    rental::{self, RentalState},
+    staking::{self, StakeAccount, StakePool},
+    state::{self, BidEscrowState, BookSide, FeeDiscount, ListEscrowState, OrderBook, PlatformState},
+};
+
+use crate::error::{ClientError, ClientResult};
+
+pub use nft_trading;
+
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct NFTTradingClient {
+    pub rpc: RpcClient,
+    pub program_id: Pubkey,
+}
+
+impl NFTTradingClient {
+    pub fn new(rpc_url: String, program_id: Pubkey) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed()),
+            program_id,
+        }
+    }
+
+    pub fn get_platform_state(&self) -> ClientResult<PlatformState> {
+        let (pubkey, _) = state::find_platform_state_address(&self.program_id);
+        self.get_packed(&pubkey)
+    }
+
+    pub fn get_list_escrow_state(&self, mint: &Pubkey, lister: &Pubkey) -> ClientResult<ListEscrowState> {
+        let (pubkey, _) = state::find_list_state_address(&self.program_id, mint, lister);
+        self.get_packed(&pubkey)
+    }
+
+    pub fn get_bid_escrow_state(&self, mint: &Pubkey, bidder: &Pubkey) -> ClientResult<BidEscrowState> {
+        let (pubkey, _) = state::find_bid_state_address(&self.program_id, mint, bidder);
+        self.get_packed(&pubkey)
+    }
+
+    pub fn get_fee_discount(&self) -> ClientResult<FeeDiscount> {
+        let (pubkey, _) = state::find_fee_discount_address(&self.program_id);
+        self.get_packed(&pubkey)
+    }
+
+    /// Creates the platform state with `authority` and the given fees,
+    /// paid for by `payer`.
+    pub fn initialize_platform(&self, payer: &Keypair, authority: &Pubkey, maker_bps: u64, taker_bps: u64) -> ClientResult<Signature> {
+        let ix = instruction::initialize(&self.program_id, &payer.pubkey(), authority, maker_bps, taker_bps);
+        self.send_and_confirm(&[ix], payer, &[])
+    }
+
+    /// Changes the platform fees. With a timelock the change is only
+    /// queued.
+    pub fn change_fees(&self, authority: &Keypair, maker_bps: u64, taker_bps: u64) -> ClientResult<Signature> {
+        let ix = instruction::change_fees(&self.program_id, &authority.pubkey(), maker_bps, taker_bps);
+        self.send_and_confirm(&[ix], authority, &[])
+    }
+
+    /// Hands the platform over to `new_authority`. With a timelock the
+    /// change is only queued.
+    pub fn change_authority(&self, authority: &Keypair, new_authority: &Pubkey) -> ClientResult<Signature> {
+        let ix = instruction::change_authority(&self.program_id, &authority.pubkey(), new_authority);
+        self.send_and_confirm(&[ix], authority, &[])
+    }
+
+    /// Replaces the platform's pause mask with `paused`, a set of
+    /// `state::PAUSE_*` bits.
+    pub fn set_paused(&self, authority: &Keypair, paused: u64) -> ClientResult<Signature> {
+        let ix = instruction::set_paused(&self.program_id, &authority.pubkey(), paused);
+        self.send_and_confirm(&[ix], authority, &[])
+    }
+
+    /// Refunds `bidder`'s bid on `mint` as the platform authority.
+    pub fn refund_user(&self, authority: &Keypair, mint: &Pubkey, bidder: &Pubkey) -> ClientResult<Signature> {
+        let ix = instruction::refund_user(&self.program_id, &authority.pubkey(), mint, bidder);
+        self.send_and_confirm(&[ix], authority, &[])
+    }
+
+    /// Lists the NFT held in `token_account` for `amount` lamports. In
+    /// `auction` mode `amount` is the reserve price. An `allowed_buyer`
+    /// makes the listing private to that wallet, and `starts_at` delays
+    /// the sale until that unix timestamp. A nonzero `royalty_bps_override`
+    /// pays creators more than the metadata asks, up to the platform cap.
+    #[allow(clippy::too_many_arguments)]
+    pub fn list(&self, lister: &Keypair, token_account: &Pubkey, mint: &Pubkey, amount: u64, auction: bool, allowed_buyer: Option<&Pubkey>, starts_at: i64, royalty_bps_override: u16) -> ClientResult<Signature> {
+        let collection = if self.get_platform_state()?.curated {
+            self.get_metadata(mint)?.and_then(|metadata| metadata.verified_collection())
+        } else {
+            None
+        };
+        let ix = instruction::list(&self.program_id, &lister.pubkey(), token_account, mint, amount, auction, collection.as_ref(), allowed_buyer, starts_at, royalty_bps_override);
+        self.send_and_confirm(&[ix], lister, &[])
+    }
+
+    /// Mints a new NFT at `mint` and lists it at `price` in one
+    /// transaction, creating its Token Metadata when `metadata` is given.
+    pub fn mint_and_list(&self, seller: &Keypair, mint: &Keypair, price: u64, metadata: Option<instruction::NewMetadata>) -> ClientResult<Signature> {
+        let ix = instruction::mint_and_list(&self.program_id, &seller.pubkey(), &mint.pubkey(), price, metadata);
+        self.send_and_confirm(&[ix], seller, &[mint])
+    }
+
+    /// Delists `mint`, returning the NFT to `token_account`.
+    pub fn delist(&self, lister: &Keypair, token_account: &Pubkey, mint: &Pubkey) -> ClientResult<Signature> {
+        let ix = instruction::with_bumps(
+            instruction::delist(&self.program_id, &lister.pubkey(), token_account, mint),
+            Bumps::find(&self.program_id, mint, Some(&lister.pubkey()), None),
+        );
+        self.send_and_confirm(&[ix], lister, &[])
+    }
+
+    /// Escrows a bid of `amount` lamports on `mint`, as wSOL if `wrapped`.
+    pub fn bid(&self, bidder: &Keypair, mint: &Pubkey, amount: u64, wrapped: bool, referrer: Option<&Pubkey>) -> ClientResult<Signature> {
+        let ix = instruction::bid(&self.program_id, &bidder.pubkey(), mint, amount, wrapped, referrer);
+        self.send_and_confirm(&[ix], bidder, &[])
+    }
+
+    /// Withdraws `bidder`'s bid on `mint`, returning the escrowed lamports.
+    pub fn withdraw_bid(&self, bidder: &Keypair, mint: &Pubkey) -> ClientResult<Signature> {
+        let ix = instruction::with_bumps(
+            instruction::withdraw_bid(&self.program_id, &bidder.pubkey(), mint),
+            Bumps::find(&self.program_id, mint, None, Some(&bidder.pubkey())),
+        );
+        self.send_and_confirm(&[ix], bidder, &[])
+    }
+
+    /// Bids `amount` lamports on `lister`'s auction listing, refunding the
+    /// current highest bidder.
+    pub fn auction_bid(&self, bidder: &Keypair, mint: &Pubkey, lister: &Pubkey, amount: u64, referrer: Option<&Pubkey>) -> ClientResult<Signature> {
+        let list_state = self.get_list_escrow_state(mint, lister)?;
+        let leader = Some(list_state.highest_bidder).filter(|leader| *leader != Pubkey::default());
+        let ix = instruction::auction_bid(&self.program_id, &bidder.pubkey(), mint, lister, amount, leader.as_ref(), referrer);
+        self.send_and_confirm(&[ix], bidder, &[])
+    }
+
+    /// Accepts `bidder`'s bid on a listing owned by `lister`, delivering
+    /// the NFT straight to `bidder_token` when given.
+    pub fn accept_bid(&self, lister: &Keypair, mint: &Pubkey, bidder: &Pubkey, bidder_token: Option<&Pubkey>) -> ClientResult<Signature> {
+        let platform_state = self.get_platform_state()?;
+        let bid_state = self.get_bid_escrow_state(mint, bidder)?;
+        let referrer = Some(bid_state.referrer).filter(|referrer| *referrer != Pubkey::default());
+        let ix = instruction::accept_bid(
+            &self.program_id,
+            &lister.pubkey(),
+            mint,
+            bidder,
+            &platform_state.fee_recipient,
+            referrer.as_ref(),
+            &self.get_verified_creators(mint)?,
+            bidder_token,
+        );
+        let list_state = self.get_list_escrow_state(mint, &lister.pubkey())?;
+        let ix = if list_state.curator == Pubkey::default() {
+            ix
+        } else {
+            instruction::with_curator(ix, &list_state.curator)
+        };
+        let ix = instruction::with_bumps(ix, Bumps::find(&self.program_id, mint, Some(&lister.pubkey()), Some(bidder)));
+        self.send_and_confirm(&[ix], lister, &[])
+    }
+
+    /// Approves `lister`'s pending listing of `mint` as the platform's
+    /// curator.
+    pub fn approve_listing(&self, curator: &Keypair, mint: &Pubkey, lister: &Pubkey) -> ClientResult<Signature> {
+        let ix = instruction::approve_listing(&self.program_id, &curator.pubkey(), mint, lister);
+        self.send_and_confirm(&[ix], curator, &[])
+    }
+
+    /// Fills `seller`'s off-chain `order` for `mint`, paying from `buyer`.
+    /// `signature` is the seller's ed25519 signature over
+    /// `order::order_message` for the same terms.
+    #[allow(clippy::too_many_arguments)]
+    pub fn fill_order(
+        &self,
+        buyer: &Keypair,
+        buyer_token_account: &Pubkey,
+        seller: &Pubkey,
+        seller_token_account: &Pubkey,
+        mint: &Pubkey,
+        fill: instruction::FillOrder,
+        signature: &[u8; 64],
+    ) -> ClientResult<Signature> {
+        let platform_state = self.get_platform_state()?;
+        let collection = if platform_state.curated {
+            self.get_metadata(mint)?.and_then(|metadata| metadata.verified_collection())
+        } else {
+            None
+        };
+        let message = order::order_message(&self.program_id, seller, mint, fill.price, fill.expiry, fill.nonce);
+        let ixs = [
+            order::ed25519_instruction(seller, signature, &message),
+            instruction::fill_order(
+                &self.program_id,
+                &buyer.pubkey(),
+                buyer_token_account,
+                seller,
+                seller_token_account,
+                mint,
+                &platform_state.fee_recipient,
+                fill,
+                collection.as_ref(),
+                &self.get_verified_creators(mint)?,
+            ),
+        ];
+        self.send_and_confirm(&ixs, buyer, &[])
+    }
+
+    pub fn get_order_book(&self, collection: &Pubkey) -> ClientResult<OrderBook> {
+        let (pubkey, _) = state::find_order_book_address(&self.program_id, collection);
+        self.get_packed(&pubkey)
+    }
+
+    /// Opens the order book for `collection`, paid for by `payer`.
+    pub fn create_order_book(&self, payer: &Keypair, collection: &Pubkey) -> ClientResult<Signature> {
+        let ix = instruction::create_order_book(&self.program_id, &payer.pubkey(), collection);
+        self.send_and_confirm(&[ix], payer, &[])
+    }
+
+    /// Refreshes `maker`'s ask or bid on `mint` in its collection's order
+    /// book, e.g. after a listing or bid sent without the book update.
+    pub fn update_order_book(&self, payer: &Keypair, mint: &Pubkey, side: BookSide, maker: &Pubkey) -> ClientResult<Signature> {
+        let collection = self.get_metadata(mint)?
+            .and_then(|metadata| metadata.verified_collection())
+            .ok_or(ProgramError::InvalidAccountData)?;
+        let ix = instruction::update_order_book(&self.program_id, &collection, mint, side, maker);
+        self.send_and_confirm(&[ix], payer, &[])
+    }
+
+    pub fn get_pool(&self, owner: &Pubkey, collection: &Pubkey) -> ClientResult<Pool> {
+        let (pubkey, _) = pool::find_pool_address(&self.program_id, owner, collection);
+        self.get_packed(&pubkey)
+    }
+
+    /// Buys `mint` from `pool` at its current curve price, paying at most
+    /// `max_price` lamports including fees.
+    pub fn buy_from_pool(&self, buyer: &Keypair, pool: &Pubkey, buyer_token_account: &Pubkey, mint: &Pubkey, max_price: u64) -> ClientResult<Signature> {
+        let platform_state = self.get_platform_state()?;
+        let ix = instruction::buy_from_pool(&self.program_id, &buyer.pubkey(), pool, buyer_token_account, mint, &platform_state.fee_recipient, max_price);
+        self.send_and_confirm(&[ix], buyer, &[])
+    }
+
+    /// Sells `mint` into `pool` at its current curve price, receiving at
+    /// least `min_price` lamports after fees.
+    pub fn sell_to_pool(&self, seller: &Keypair, pool: &Pubkey, seller_token_account: &Pubkey, mint: &Pubkey, min_price: u64) -> ClientResult<Signature> {
+        let platform_state = self.get_platform_state()?;
+        let ix = instruction::sell_to_pool(&self.program_id, &seller.pubkey(), pool, seller_token_account, mint, &platform_state.fee_recipient, min_price);
+        self.send_and_confirm(&[ix], seller, &[])
+    }
+
+    pub fn get_stake_pool(&self) -> ClientResult<StakePool> {
+        let (pubkey, _) = staking::find_stake_pool_address(&self.program_id);
+        self.get_packed(&pubkey)
+    }
+
+    pub fn get_stake_account(&self, owner: &Pubkey) -> ClientResult<StakeAccount> {
+        let (pubkey, _) = staking::find_stake_account_address(&self.program_id, owner);
+        self.get_packed(&pubkey)
+    }
+
+    /// Stakes `amount` platform tokens from `token_account`.
+    pub fn stake(&self, owner: &Keypair, token_account: &Pubkey, amount: u64) -> ClientResult<Signature> {
+        let ix = instruction::stake(&self.program_id, &owner.pubkey(), token_account, amount);
+        self.send_and_confirm(&[ix], owner, &[])
+    }
+
+    /// Unstakes `amount` platform tokens back into `token_account`.
+    pub fn unstake(&self, owner: &Keypair, token_account: &Pubkey, amount: u64) -> ClientResult<Signature> {
+        let ix = instruction::unstake(&self.program_id, &owner.pubkey(), token_account, amount);
+        self.send_and_confirm(&[ix], owner, &[])
+    }
+
+    pub fn claim_rewards(&self, owner: &Keypair) -> ClientResult<Signature> {
+        let ix = instruction::claim_rewards(&self.program_id, &owner.pubkey());
+        self.send_and_confirm(&[ix], owner, &[])
+    }
+
+    pub fn get_raffle_state(&self, mint: &Pubkey, creator: &Pubkey) -> ClientResult<RaffleState> {
+        let (pubkey, _) = raffle::find_raffle_state_address(&self.program_id, mint, creator);
+        self.get_packed(&pubkey)
+    }
+
+    pub fn buy_raffle_ticket(&self, buyer: &Keypair, mint: &Pubkey, creator: &Pubkey) -> ClientResult<Signature> {
+        let ix = instruction::buy_raffle_ticket(&self.program_id, &buyer.pubkey(), mint, creator);
+        self.send_and_confirm(&[ix], buyer, &[])
+    }
+
+    /// Draws `creator`'s raffle for `mint`, with `payer` covering the fee.
+    pub fn draw_raffle(&self, payer: &Keypair, mint: &Pubkey, creator: &Pubkey) -> ClientResult<Signature> {
+        let platform_state = self.get_platform_state()?;
+        let ix = instruction::draw_raffle(&self.program_id, mint, creator, &platform_state.fee_recipient);
+        self.send_and_confirm(&[ix], payer, &[])
+    }
+
+    pub fn get_fraction_state(&self, mint: &Pubkey) -> ClientResult<FractionState> {
+        let (pubkey, _) = fraction::find_fraction_state_address(&self.program_id, mint);
+        self.get_packed(&pubkey)
+    }
+
+    /// Buys out the fractionalized `mint` into `buyer_token_account`.
+    pub fn buyout(&self, buyer: &Keypair, buyer_token_account: &Pubkey, mint: &Pubkey) -> ClientResult<Signature> {
+        let platform_state = self.get_platform_state()?;
+        let fraction_state = self.get_fraction_state(mint)?;
+        let ix = instruction::buyout(&self.program_id, &buyer.pubkey(), buyer_token_account, mint, &fraction_state.curator, &platform_state.fee_recipient);
+        self.send_and_confirm(&[ix], buyer, &[])
+    }
+
+    pub fn redeem_shares(&self, holder: &Keypair, share_account: &Pubkey, mint: &Pubkey, amount: u64) -> ClientResult<Signature> {
+        let ix = instruction::redeem_shares(&self.program_id, &holder.pubkey(), share_account, mint, amount);
+        self.send_and_confirm(&[ix], holder, &[])
+    }
+
+    pub fn get_rental_state(&self, mint: &Pubkey, owner: &Pubkey) -> ClientResult<RentalState> {
+        let (pubkey, _) = rental::find_rental_state_address(&self.program_id, mint, owner);
+        self.get_packed(&pubkey)
+    }
+
+    pub fn rent(&self, renter: &Keypair, mint: &Pubkey, owner: &Pubkey) -> ClientResult<Signature> {
+        let platform_state = self.get_platform_state()?;
+        let ix = instruction::rent(&self.program_id, &renter.pubkey(), mint, owner, &platform_state.fee_recipient);
+        self.send_and_confirm(&[ix], renter, &[])
+    }
+
+    /// Cranks an expired rental of `owner`'s `mint` back into
+    /// `owner_token_account`, with `payer` covering the fee.
+    pub fn reclaim_rental(&self, payer: &Keypair, owner: &Pubkey, owner_token_account: &Pubkey, mint: &Pubkey) -> ClientResult<Signature> {
+        let ix = instruction::reclaim_rental(&self.program_id, owner, owner_token_account, mint, false);
+        self.send_and_confirm(&[ix], payer, &[])
+    }
+
+    pub fn get_otc_state(&self, mint: &Pubkey, maker: &Pubkey) -> ClientResult<OtcState> {
+        let (pubkey, _) = otc::find_otc_state_address(&self.program_id, mint, maker);
+        self.get_packed(&pubkey)
+    }
+
+    /// Pays `maker`'s OTC price for `mint` and receives it into
+    /// `taker_token_account`.
+    pub fn accept_otc(&self, taker: &Keypair, taker_token_account: &Pubkey, mint: &Pubkey, maker: &Pubkey) -> ClientResult<Signature> {
+        let platform_state = self.get_platform_state()?;
+        let ix = instruction::accept_otc(&self.program_id, &taker.pubkey(), taker_token_account, mint, maker, &platform_state.fee_recipient);
+        self.send_and_confirm(&[ix], taker, &[])
+    }
+
+    /// Signs `instructions` with `payer` (plus any extra `signers`),
+    /// simulates the transaction, submits it and waits for it to be
+    /// confirmed. A failed simulation is returned with its program logs.
+    pub fn send_and_confirm(
+        &self,
+        instructions: &[Instruction],
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> ClientResult<Signature> {
+        let (recent_blockhash, _) = self.rpc.get_recent_blockhash()?;
+        let mut all_signers = vec![payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &all_signers,
+            recent_blockhash,
+        );
+        let simulation = self.rpc.simulate_transaction(&transaction)?.value;
+        if let Some(err) = simulation.err {
+            return Err(ClientError::SimulationFailed(err, simulation.logs.unwrap_or_default()));
+        }
+        // The simulation above was the preflight check.
+        let config = RpcSendTransactionConfig {
+            skip_preflight: true,
+            ..RpcSendTransactionConfig::default()
+        };
+        let signature = self.rpc.send_transaction_with_config(&transaction, config)?;
+        self.poll_for_confirmation(&signature, CONFIRMATION_TIMEOUT)?;
+        Ok(signature)
+    }
+
+    /// Polls the signature status until the transaction lands, fails, or
+    /// `timeout` elapses.
+    pub fn poll_for_confirmation(&self, signature: &Signature, timeout: Duration) -> ClientResult<()> {
+        let start = Instant::now();
+        while start.elapsed() < timeout {
+            match self.rpc.get_signature_status(signature)? {
+                Some(Ok(())) => return Ok(()),
+                Some(Err(err)) => return Err(ClientError::TransactionFailed(*signature, err)),
+                None => sleep(CONFIRMATION_POLL_INTERVAL),
+            }
+        }
+        Err(ClientError::ConfirmationTimeout(*signature))
+    }
+
+    /// Returns the verified creators from `mint`'s metadata, in the order
+    /// `AcceptBid` expects them.
+    pub fn get_verified_creators(&self, mint: &Pubkey) -> ClientResult<Vec<Pubkey>> {
+        let metadata = match self.get_metadata(mint)? {
+            Some(metadata) => metadata,
+            None => return Ok(vec![]),
+        };
+        Ok(metadata
+            .verified_creators()
+            .into_iter()
+            .map(|creator| Pubkey::new_from_array(creator.address))
+            .collect())
+    }
+
+    /// Fetches the Metaplex metadata for `mint`, if it has any.
+    pub fn get_metadata(&self, mint: &Pubkey) -> ClientResult<Option<Metadata>> {
+        let (metadata_pubkey, _) = metadata::find_metadata_address(mint);
+        let account = self.rpc
+            .get_account_with_commitment(&metadata_pubkey, CommitmentConfig::confirmed())?
+            .value;
+        match account {
+            Some(account) => Ok(Some(
+                Metadata::deserialize(&mut &account.data[..])
+                    .map_err(|_| ProgramError::InvalidAccountData)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn get_packed<T: Pack>(&self, pubkey: &Pubkey) -> ClientResult<T> {
+        let data = self.rpc.get_account_data(pubkey)?;
+        Ok(T::unpack_unchecked(&data)?)
+    }
+}
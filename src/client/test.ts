@@ -52,13 +52,18 @@ const PROGRAM_PATH = path.resolve(__dirname, '../../dist/program');
  *   - `npm run build:program-c`
  *   - `npm run build:program-rust`
  */
-const PROGRAM_SO_PATH = path.join(PROGRAM_PATH, 'test.so');
+const PROGRAM_SO_PATH = path.join(PROGRAM_PATH, 'nft_trading.so');
 
 /**
  * Path to the keypair of the deployed program.
- * This file is created when running `solana program deploy dist/program/test.so`
+ * This file is created when running `solana program deploy dist/program/nft_trading.so`
  */
-const PROGRAM_KEYPAIR_PATH = path.join(PROGRAM_PATH, 'test-keypair.json');
+const PROGRAM_KEYPAIR_PATH = path.join(PROGRAM_PATH, 'nft_trading-keypair.json');
+
+/**
+ * Metaplex Token Metadata program id
+ */
+const TOKEN_METADATA_PROGRAM_ID = new PublicKey('metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s');
 
 /**
  * Establish a connection to the cluster
@@ -79,7 +84,7 @@ export async function establishPayer(): Promise<void> {
     const {feeCalculator} = await connection.getRecentBlockhash();
 
     // Calculate the cost to fund the greeter account
-    fees += await connection.getMinimumBalanceForRentExemption(49);
+    fees += await connection.getMinimumBalanceForRentExemption(98);
 
     // Calculate the cost of sending transactions
     fees += feeCalculator.lamportsPerSignature * 100; // wag
@@ -123,7 +128,7 @@ export async function checkProgram(): Promise<void> {
   } catch (err) {
     const errMsg = (err as Error).message;
     throw new Error(
-      `Failed to read program keypair at '${PROGRAM_KEYPAIR_PATH}' due to error: ${errMsg}. Program may need to be deployed with \`solana program deploy dist/program/test.so\``,
+      `Failed to read program keypair at '${PROGRAM_KEYPAIR_PATH}' due to error: ${errMsg}. Program may need to be deployed with \`solana program deploy dist/program/nft_trading.so\``,
     );
   }
 
@@ -132,7 +137,7 @@ export async function checkProgram(): Promise<void> {
   if (programInfo === null) {
     if (fs.existsSync(PROGRAM_SO_PATH)) {
       throw new Error(
-        'Program needs to be deployed with `solana program deploy dist/program/test.so`',
+        'Program needs to be deployed with `solana program deploy dist/program/nft_trading.so`',
       );
     } else {
       throw new Error('Program needs to be built and deployed');
@@ -150,9 +155,9 @@ export async function initializeProgram(): Promise<void> {
   const byteArray = [0];
   const instrunctionBuffer = Buffer.from(byteArray);
   const authorityBuffer = Buffer.from(payerAccount.publicKey.toBytes());
-  const feeBytes = longToByteArray(10000000);
-  const feeBuffer = Buffer.from(feeBytes)
-  const list = [instrunctionBuffer, authorityBuffer, feeBuffer];
+  const makerFeeBuffer = Buffer.from(longToByteArray(250));
+  const takerFeeBuffer = Buffer.from(longToByteArray(100));
+  const list = [instrunctionBuffer, authorityBuffer, makerFeeBuffer, takerFeeBuffer];
   const buffer = Buffer.concat(list);
 
   const accountPubKey = await PublicKey.findProgramAddress([Buffer.from("Platform"), Buffer.from("State")], programId);
@@ -205,7 +210,8 @@ export async function list(): Promise<void> {
 
   const byteArray = [3];
   const amount = longToByteArray(1000000000);
-  const list = [Buffer.from(byteArray), Buffer.from(amount)];
+  const auction = [0];
+  const list = [Buffer.from(byteArray), Buffer.from(amount), Buffer.from(auction)];
   const buffer = Buffer.concat(list);
 
   const keyPairPath = path.resolve(__dirname, '../../dist/lister/lister-keypair.json')
@@ -214,6 +220,10 @@ export async function list(): Promise<void> {
 
   const tokenMintPubKey = new PublicKey('Fn386evLgVty7pBneoYF1shVWGZE8eqrA6fw9j8xLfDU');
   const tokenAccountPubKey = await findAssociatedTokenAddress(listerPubKey, tokenMintPubKey);
+  const programStatePubkey = (await PublicKey.findProgramAddress([
+    Buffer.from("Platform"),
+    Buffer.from("State")
+  ], programId))[0];
   const escrowStatePubkey = (await PublicKey.findProgramAddress([
     tokenMintPubKey.toBuffer(), 
     listerPubKey.toBuffer(),
@@ -237,7 +247,8 @@ export async function list(): Promise<void> {
       {pubkey: programId, isSigner: false, isWritable: false},
       {pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false},
       {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
-      {pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false}
+      {pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false},
+      {pubkey: programStatePubkey, isSigner: false, isWritable: true}
     ],
     programId,
     data: buffer,
@@ -274,6 +285,10 @@ export async function deList(): Promise<void> {
     Buffer.from("List"),
     Buffer.from("Vault")
   ], programId))[0];
+  const programStatePubkey = (await PublicKey.findProgramAddress([
+    Buffer.from("Platform"),
+    Buffer.from("State")
+  ], programId))[0];
 
   const instruction = new TransactionInstruction({
     keys: [
@@ -283,7 +298,8 @@ export async function deList(): Promise<void> {
       {pubkey: escrowStatePubkey, isSigner: false, isWritable: true},
       {pubkey: escrowVaultPubkey, isSigner: false, isWritable: true},
       {pubkey: programId, isSigner: false, isWritable: false},
-      {pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false}
+      {pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false},
+      {pubkey: programStatePubkey, isSigner: false, isWritable: true}
     ],
     programId,
     data: buffer,
@@ -300,7 +316,8 @@ export async function bid(): Promise<void> {
 
   const byteArray = [5];
   const amount = longToByteArray(1000000000);
-  const list = [Buffer.from(byteArray), Buffer.from(amount)];
+  const wrapped = [0];
+  const list = [Buffer.from(byteArray), Buffer.from(amount), Buffer.from(wrapped)];
   const buffer = Buffer.concat(list);
 
   const keyPairPath = path.resolve(__dirname, '../../dist/bidder/bidder-keypair.json')
@@ -308,7 +325,10 @@ export async function bid(): Promise<void> {
   const bidderPubKey = bidderAccount.publicKey
 
   const tokenMintPubKey = new PublicKey('Fn386evLgVty7pBneoYF1shVWGZE8eqrA6fw9j8xLfDU');
-  const tokenAccountPubKey = await findAssociatedTokenAddress(bidderPubKey, tokenMintPubKey);
+  const programStatePubkey = (await PublicKey.findProgramAddress([
+    Buffer.from("Platform"),
+    Buffer.from("State")
+  ], programId))[0];
   const escrowStatePubkey = (await PublicKey.findProgramAddress([
     tokenMintPubKey.toBuffer(), 
     bidderPubKey.toBuffer(),
@@ -330,7 +350,8 @@ export async function bid(): Promise<void> {
       {pubkey: escrowVaultPubkey, isSigner: false, isWritable: true},
       {pubkey: programId, isSigner: false, isWritable: false},
       {pubkey: SystemProgram.programId, isSigner: false, isWritable: false},
-      {pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false}
+      {pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false},
+      {pubkey: programStatePubkey, isSigner: false, isWritable: false}
     ],
     programId,
     data: buffer,
@@ -373,7 +394,8 @@ export async function withdrawBid(): Promise<void> {
       {pubkey: tokenMintPubKey, isSigner: false, isWritable: true},
       {pubkey: escrowStatePubkey, isSigner: false, isWritable: true},
       {pubkey: escrowVaultPubkey, isSigner: false, isWritable: true},
-      {pubkey: programId, isSigner: false, isWritable: false}
+      {pubkey: programId, isSigner: false, isWritable: false},
+      {pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false}
     ],
     programId,
     data: buffer,
@@ -401,6 +423,10 @@ export async function acceptBid(): Promise<void> {
   const bidderPubKey = bidderAccount.publicKey
 
   const tokenMintPubKey = new PublicKey('Fn386evLgVty7pBneoYF1shVWGZE8eqrA6fw9j8xLfDU');
+  const programStatePubkey = (await PublicKey.findProgramAddress([
+    Buffer.from("Platform"),
+    Buffer.from("State")
+  ], programId))[0];
   const listEscrowStatePubkey = (await PublicKey.findProgramAddress([
     tokenMintPubKey.toBuffer(), 
     listerPubKey.toBuffer(),
@@ -413,6 +439,11 @@ export async function acceptBid(): Promise<void> {
     Buffer.from("List"),
     Buffer.from("Vault")
   ], programId))[0];
+  const metadataPubkey = (await PublicKey.findProgramAddress([
+    Buffer.from("metadata"),
+    TOKEN_METADATA_PROGRAM_ID.toBuffer(),
+    tokenMintPubKey.toBuffer()
+  ], TOKEN_METADATA_PROGRAM_ID))[0];
   const bidEscrowStatePubkey = (await PublicKey.findProgramAddress([
     tokenMintPubKey.toBuffer(), 
     bidderPubKey.toBuffer(),
@@ -425,6 +456,16 @@ export async function acceptBid(): Promise<void> {
     Buffer.from("Bid"),
     Buffer.from("Vault")
   ], programId))[0];
+  const bidderStatsPubkey = (await PublicKey.findProgramAddress([
+    bidderPubKey.toBuffer(),
+    Buffer.from("User"),
+    Buffer.from("Stats")
+  ], programId))[0];
+  const listerStatsPubkey = (await PublicKey.findProgramAddress([
+    listerPubKey.toBuffer(),
+    Buffer.from("User"),
+    Buffer.from("Stats")
+  ], programId))[0];
 
   const instruction = new TransactionInstruction({
     keys: [
@@ -434,7 +475,13 @@ export async function acceptBid(): Promise<void> {
       {pubkey: bidEscrowStatePubkey, isSigner: false, isWritable: true},
       {pubkey: bidEscrowVaultPubkey, isSigner: false, isWritable: true},
       {pubkey: listEscrowStatePubkey, isSigner: false, isWritable: true},
-      {pubkey: listEscrowVaultPubkey, isSigner: false, isWritable: true}
+      {pubkey: listEscrowVaultPubkey, isSigner: false, isWritable: true},
+      {pubkey: programStatePubkey, isSigner: false, isWritable: true},
+      {pubkey: payerAccount.publicKey, isSigner: false, isWritable: true},
+      {pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false},
+      {pubkey: metadataPubkey, isSigner: false, isWritable: false},
+      {pubkey: bidderStatsPubkey, isSigner: false, isWritable: true},
+      {pubkey: listerStatsPubkey, isSigner: false, isWritable: true}
     ],
     programId,
     data: buffer,
@@ -534,7 +581,8 @@ export async function refund(): Promise<void> {
       {pubkey: bidderPubKey, isSigner: false, isWritable: true},
       {pubkey: programStatePubkey, isSigner: false, isWritable: false},
       {pubkey: escrowStatePubkey, isSigner: false, isWritable: true},
-      {pubkey: escrowVaultPubkey, isSigner: false, isWritable: true}
+      {pubkey: escrowVaultPubkey, isSigner: false, isWritable: true},
+      {pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false}
     ],
     programId,
     data: buffer,
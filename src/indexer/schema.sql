@@ -0,0 +1,44 @@
+-- Applied by the indexer at startup, so every statement must be idempotent.
+
+CREATE TABLE IF NOT EXISTS listings (
+    address     TEXT PRIMARY KEY,
+    mint        TEXT NOT NULL,
+    lister      TEXT NOT NULL,
+    price       BIGINT NOT NULL,
+    auction     BOOLEAN NOT NULL,
+    highest_bid BIGINT NOT NULL,
+    slot        BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS listings_mint ON listings (mint);
+ALTER TABLE listings ADD COLUMN IF NOT EXISTS collection TEXT;
+CREATE INDEX IF NOT EXISTS listings_collection ON listings (collection);
+
+CREATE TABLE IF NOT EXISTS bids (
+    address TEXT PRIMARY KEY,
+    mint    TEXT NOT NULL,
+    bidder  TEXT NOT NULL,
+    amount  BIGINT NOT NULL,
+    wrapped BOOLEAN NOT NULL,
+    slot    BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS bids_mint ON bids (mint);
+
+CREATE TABLE IF NOT EXISTS sales (
+    signature TEXT NOT NULL,
+    mint      TEXT NOT NULL,
+    price     BIGINT NOT NULL,
+    slot      BIGINT NOT NULL,
+    PRIMARY KEY (signature, mint)
+);
+CREATE INDEX IF NOT EXISTS sales_mint ON sales (mint);
+-- Parties of the sale, when the escrows it closed were indexed.
+ALTER TABLE sales ADD COLUMN IF NOT EXISTS seller TEXT;
+ALTER TABLE sales ADD COLUMN IF NOT EXISTS buyer TEXT;
+CREATE INDEX IF NOT EXISTS sales_seller ON sales (seller);
+CREATE INDEX IF NOT EXISTS sales_buyer ON sales (buyer);
+
+-- Newest transaction signature the indexer has processed.
+CREATE TABLE IF NOT EXISTS cursor (
+    id        BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    signature TEXT NOT NULL
+);
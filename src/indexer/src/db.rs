@@ -0,0 +1,176 @@
+use postgres::{Client, NoTls};
+use solana_program::pubkey::Pubkey;
+
+use nft_trading::state::{BidEscrowState, ListEscrowState};
+
+use crate::{
+    error::IndexerResult,
+    events::{Event, EVENTS_CHANNEL},
+};
+
+const SCHEMA: &str = include_str!("../schema.sql");
+
+/// Everything one transaction changed, written to the database at once.
+#[derive(Debug, Default)]
+pub struct Update {
+    pub signature: String,
+    pub slot: u64,
+    /// Open public listings, with the mint's verified collection if it has
+    /// one.
+    pub listings: Vec<(Pubkey, ListEscrowState, Option<Pubkey>)>,
+    pub bids: Vec<(Pubkey, BidEscrowState)>,
+    /// Escrow accounts the transaction closed, or listings it sold or
+    /// unlisted.
+    pub closed: Vec<Pubkey>,
+    /// `(mint, price)` of every sale the program recorded.
+    pub sales: Vec<(Pubkey, u64)>,
+}
+
+pub struct Db {
+    client: Client,
+}
+
+impl Db {
+    /// Connects to `url` and creates any missing tables.
+    pub fn connect(url: &str) -> IndexerResult<Self> {
+        let mut client = Client::connect(url, NoTls)?;
+        client.batch_execute(SCHEMA)?;
+        Ok(Self { client })
+    }
+
+    /// The newest signature already indexed, if any.
+    pub fn cursor(&mut self) -> IndexerResult<Option<String>> {
+        let row = self.client.query_opt("SELECT signature FROM cursor", &[])?;
+        Ok(row.map(|row| row.get(0)))
+    }
+
+    /// Writes `update` and advances the cursor past its transaction.
+    /// Lamport amounts are stored as `BIGINT`, which holds any realistic
+    /// price.
+    pub fn apply(&mut self, update: &Update) -> IndexerResult<()> {
+        let slot = update.slot as i64;
+        let mut transaction = self.client.transaction()?;
+
+        // Sales are matched to the escrows they closed, so their parties
+        // have to be read before those rows go.
+        let closed: Vec<String> = update.closed.iter().map(Pubkey::to_string).collect();
+        let mut sales = Vec::with_capacity(update.sales.len());
+        for (mint, price) in &update.sales {
+            let mint = mint.to_string();
+            let (seller, collection): (Option<String>, Option<String>) = transaction
+                .query_opt(
+                    "SELECT lister, collection FROM listings WHERE mint = $1 AND address = ANY($2) LIMIT 1",
+                    &[&mint, &closed],
+                )?
+                .map_or((None, None), |row| (row.get(0), row.get(1)));
+            let buyer: Option<String> = transaction
+                .query_opt(
+                    "SELECT bidder FROM bids WHERE mint = $1 AND address = ANY($2) LIMIT 1",
+                    &[&mint, &closed],
+                )?
+                .map(|row| row.get(0));
+            sales.push(Event::SaleExecuted {
+                signature: update.signature.clone(),
+                mint,
+                price: *price,
+                seller,
+                buyer,
+                collection,
+                slot: update.slot,
+            });
+        }
+
+        let mut events = vec![];
+        for (address, listing, collection) in &update.listings {
+            let collection = collection.map(|collection| collection.to_string());
+            // `xmax` is zero only for a row this statement inserted.
+            let created: bool = transaction
+                .query_one(
+                    "INSERT INTO listings (address, mint, lister, price, auction, highest_bid, slot, collection)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                     ON CONFLICT (address) DO UPDATE SET
+                         price = EXCLUDED.price, highest_bid = EXCLUDED.highest_bid, slot = EXCLUDED.slot,
+                         collection = EXCLUDED.collection
+                     RETURNING xmax = 0",
+                    &[
+                        &address.to_string(),
+                        &listing.mint.to_string(),
+                        &listing.lister.to_string(),
+                        &(listing.amount as i64),
+                        &listing.auction,
+                        &(listing.highest_bid as i64),
+                        &slot,
+                        &collection,
+                    ],
+                )?
+                .get(0);
+            if created {
+                events.push(Event::ListingCreated {
+                    address: address.to_string(),
+                    mint: listing.mint.to_string(),
+                    lister: listing.lister.to_string(),
+                    price: listing.amount,
+                    auction: listing.auction,
+                    collection,
+                    slot: update.slot,
+                });
+            }
+        }
+        for (address, bid) in &update.bids {
+            // Unchanged bids come back as no row, so only new or changed
+            // bids are announced.
+            let placed = transaction.query_opt(
+                "INSERT INTO bids (address, mint, bidder, amount, wrapped, slot)
+                 VALUES ($1, $2, $3, $4, $5, $6)
+                 ON CONFLICT (address) DO UPDATE SET amount = EXCLUDED.amount, slot = EXCLUDED.slot
+                     WHERE bids.amount <> EXCLUDED.amount
+                 RETURNING address",
+                &[
+                    &address.to_string(),
+                    &bid.mint.to_string(),
+                    &bid.bidder.to_string(),
+                    &(bid.amount as i64),
+                    &bid.wrapped,
+                    &slot,
+                ],
+            )?;
+            if placed.is_some() {
+                let mint = bid.mint.to_string();
+                let collection: Option<String> = transaction
+                    .query_opt("SELECT collection FROM listings WHERE mint = $1 LIMIT 1", &[&mint])?
+                    .and_then(|row| row.get(0));
+                events.push(Event::BidPlaced {
+                    address: address.to_string(),
+                    mint,
+                    bidder: bid.bidder.to_string(),
+                    amount: bid.amount,
+                    collection,
+                    slot: update.slot,
+                });
+            }
+        }
+        for sale in sales {
+            if let Event::SaleExecuted { signature, mint, price, seller, buyer, .. } = &sale {
+                transaction.execute(
+                    "INSERT INTO sales (signature, mint, price, slot, seller, buyer) VALUES ($1, $2, $3, $4, $5, $6)
+                     ON CONFLICT DO NOTHING",
+                    &[signature, mint, &(*price as i64), &slot, seller, buyer],
+                )?;
+            }
+            events.push(sale);
+        }
+        // Notifications are only delivered once the transaction commits.
+        for event in &events {
+            transaction.execute("SELECT pg_notify($1, $2)", &[&EVENTS_CHANNEL, &event.to_json()])?;
+        }
+        transaction.execute("DELETE FROM listings WHERE address = ANY($1)", &[&closed])?;
+        transaction.execute("DELETE FROM bids WHERE address = ANY($1)", &[&closed])?;
+        transaction.execute(
+            "INSERT INTO cursor (signature) VALUES ($1)
+             ON CONFLICT (id) DO UPDATE SET signature = EXCLUDED.signature",
+            &[&update.signature],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+}
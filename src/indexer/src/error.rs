@@ -0,0 +1,25 @@
+use thiserror::Error;
+
+use solana_client::client_error::ClientError as RpcClientError;
+use solana_sdk::signature::{ParseSignatureError, Signature};
+
+#[derive(Error, Debug)]
+pub enum IndexerError {
+    /// RPC request failed
+    #[error("RPC error: {0}")]
+    Rpc(#[from] RpcClientError),
+
+    /// Database query failed
+    #[error("Database error: {0}")]
+    Database(#[from] postgres::Error),
+
+    /// A stored or returned signature could not be parsed
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(#[from] ParseSignatureError),
+
+    /// The RPC node returned a transaction that could not be decoded
+    #[error("Transaction {0} could not be decoded")]
+    UndecodableTransaction(Signature),
+}
+
+pub type IndexerResult<T> = Result<T, IndexerError>;
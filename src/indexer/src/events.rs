@@ -0,0 +1,45 @@
+use serde::Serialize;
+
+/// Postgres channel the indexer publishes `Event`s on as JSON.
+pub const EVENTS_CHANNEL: &str = "marketplace_events";
+
+/// Marketplace activity, announced once it is written to the database.
+/// Every event carries the mint, and the collection when known, so
+/// subscribers can filter on either.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+pub enum Event {
+    ListingCreated {
+        address: String,
+        mint: String,
+        lister: String,
+        price: u64,
+        auction: bool,
+        collection: Option<String>,
+        slot: u64,
+    },
+    BidPlaced {
+        address: String,
+        mint: String,
+        bidder: String,
+        amount: u64,
+        /// Known when the mint is listed.
+        collection: Option<String>,
+        slot: u64,
+    },
+    SaleExecuted {
+        signature: String,
+        mint: String,
+        price: u64,
+        seller: Option<String>,
+        buyer: Option<String>,
+        collection: Option<String>,
+        slot: u64,
+    },
+}
+
+impl Event {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("events serialize to JSON")
+    }
+}
@@ -0,0 +1,181 @@
+use std::{collections::BTreeSet, str::FromStr, thread::sleep, time::Duration};
+
+use solana_client::{
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
+use solana_transaction_status::UiTransactionEncoding;
+
+use borsh::BorshDeserialize;
+use nft_trading::{
+    instruction::NFTInstruction,
+    metadata::{self, Metadata},
+    state::{self, BidEscrowState, ListEscrowState},
+};
+
+use crate::{
+    db::{Db, Update},
+    error::{IndexerError, IndexerResult},
+    logs,
+};
+
+/// Largest batch `getMultipleAccounts` accepts.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Whether `listing` belongs in public feeds: open, and not unlisted by its
+/// lister. A sold listing only waits for its NFT to be withdrawn, so it is
+/// no longer open.
+pub fn is_public_listing(listing: &ListEscrowState) -> bool {
+    listing.is_initialized && !listing.success && !listing.is_unlisted()
+}
+
+/// Follows the program's transactions and mirrors their effects into the
+/// database.
+///
+/// Escrow rows are refreshed from the accounts' current data rather than
+/// replayed, so catching up after downtime converges on the live state
+/// without decoding every historical instruction's effects.
+pub struct Indexer {
+    rpc: RpcClient,
+    program_id: Pubkey,
+    db: Db,
+}
+
+impl Indexer {
+    pub fn new(rpc_url: String, program_id: Pubkey, db: Db) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed()),
+            program_id,
+            db,
+        }
+    }
+
+    /// Polls for new transactions every `interval`, forever.
+    pub fn run(&mut self, interval: Duration) -> IndexerResult<()> {
+        loop {
+            self.poll()?;
+            sleep(interval);
+        }
+    }
+
+    /// Indexes every transaction since the cursor, oldest first. Returns
+    /// how many were processed.
+    pub fn poll(&mut self) -> IndexerResult<usize> {
+        let pending = self.pending_signatures()?;
+        for status in pending.iter().rev() {
+            let signature = Signature::from_str(&status.signature)?;
+            let update = if status.err.is_some() {
+                Update {
+                    signature: status.signature.clone(),
+                    slot: status.slot,
+                    ..Update::default()
+                }
+            } else {
+                self.index_transaction(&signature, status.slot)?
+            };
+            self.db.apply(&update)?;
+        }
+        Ok(pending.len())
+    }
+
+    /// Signatures newer than the cursor, newest first.
+    fn pending_signatures(&mut self) -> IndexerResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        let until = self.db.cursor()?.map(|signature| Signature::from_str(&signature)).transpose()?;
+        let mut pending = vec![];
+        let mut before = None;
+        loop {
+            let page = self.rpc.get_confirmed_signatures_for_address2_with_config(
+                &self.program_id,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: None,
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )?;
+            match page.last() {
+                Some(oldest) => before = Some(Signature::from_str(&oldest.signature)?),
+                None => break,
+            }
+            pending.extend(page);
+        }
+        Ok(pending)
+    }
+
+    fn index_transaction(&self, signature: &Signature, slot: u64) -> IndexerResult<Update> {
+        let confirmed = self.rpc.get_confirmed_transaction(signature, UiTransactionEncoding::Base64)?;
+        let transaction = confirmed.transaction.transaction
+            .decode()
+            .ok_or(IndexerError::UndecodableTransaction(*signature))?;
+        let log_messages = confirmed.transaction.meta
+            .and_then(|meta| meta.log_messages)
+            .unwrap_or_default();
+
+        let message = &transaction.message;
+        let mut touched = BTreeSet::new();
+        for ix in &message.instructions {
+            if message.account_keys[ix.program_id_index as usize] != self.program_id {
+                continue;
+            }
+            if NFTInstruction::unpack(&ix.data).is_err() {
+                continue;
+            }
+            touched.extend(ix.accounts.iter().map(|&index| message.account_keys[index as usize]));
+        }
+
+        let mut update = Update {
+            signature: signature.to_string(),
+            slot,
+            sales: logs::recorded_sales(&log_messages, &self.program_id),
+            ..Update::default()
+        };
+        let touched: Vec<Pubkey> = touched.into_iter().collect();
+        for chunk in touched.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let accounts = self.rpc.get_multiple_accounts(chunk)?;
+            for (address, account) in chunk.iter().zip(accounts) {
+                let account = match account {
+                    Some(account) if account.owner == self.program_id => account,
+                    Some(_) => continue,
+                    None => {
+                        update.closed.push(*address);
+                        continue;
+                    }
+                };
+                if let Ok(listing) = state::unpack_compat::<ListEscrowState>(&account.data) {
+                    if is_public_listing(&listing) {
+                        update.listings.push((*address, listing, None));
+                    } else if listing.is_initialized {
+                        update.closed.push(*address);
+                    }
+                    continue;
+                }
+                if let Ok(bid) = state::unpack_compat::<BidEscrowState>(&account.data) {
+                    if bid.is_initialized {
+                        update.bids.push((*address, bid));
+                    }
+                }
+            }
+        }
+        self.fill_collections(&mut update.listings)?;
+        Ok(update)
+    }
+
+    /// Sets each listing's verified collection from its mint's metadata.
+    fn fill_collections(&self, listings: &mut [(Pubkey, ListEscrowState, Option<Pubkey>)]) -> IndexerResult<()> {
+        for chunk in listings.chunks_mut(MAX_MULTIPLE_ACCOUNTS) {
+            let metadata_addresses: Vec<Pubkey> = chunk
+                .iter()
+                .map(|(_, listing, _)| metadata::find_metadata_address(&listing.mint).0)
+                .collect();
+            let accounts = self.rpc.get_multiple_accounts(&metadata_addresses)?;
+            for ((_, _, collection), account) in chunk.iter_mut().zip(accounts) {
+                *collection = account
+                    .and_then(|account| Metadata::deserialize(&mut &account.data[..]).ok())
+                    .and_then(|metadata| metadata.verified_collection());
+            }
+        }
+        Ok(())
+    }
+}
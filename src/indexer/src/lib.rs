@@ -0,0 +1,5 @@
+pub mod db;
+pub mod error;
+pub mod events;
+pub mod indexer;
+pub mod logs;
@@ -0,0 +1,59 @@
+use std::str::FromStr;
+
+use solana_program::pubkey::Pubkey;
+
+use nft_trading::memo::spl_memo_program;
+
+/// Returns the `(mint, price)` of every `sale:<mint>:<price>` memo that
+/// `program_id` itself wrote through the Memo program. Memos a
+/// transaction sent directly, or through another program, are ignored,
+/// so a transaction cannot fake a sale by adding its own memo.
+pub fn recorded_sales(logs: &[String], program_id: &Pubkey) -> Vec<(Pubkey, u64)> {
+    let memo_program_id = spl_memo_program::id();
+    let mut stack: Vec<Pubkey> = vec![];
+    let mut sales = vec![];
+    for log in logs {
+        if let Some(program) = log.strip_prefix("Program ").and_then(|rest| rest.split(' ').next()) {
+            if log.contains(" invoke [") {
+                if let Ok(program) = Pubkey::from_str(program) {
+                    stack.push(program);
+                }
+                continue;
+            }
+            if log.ends_with(" success") || log.contains(" failed: ") {
+                stack.pop();
+                continue;
+            }
+        }
+        let memo = match log.strip_prefix("Program log: Memo (len ") {
+            Some(memo) => memo,
+            None => continue,
+        };
+        let depth = stack.len();
+        let written_by_program = depth >= 2
+            && stack[depth - 1] == memo_program_id
+            && stack[depth - 2] == *program_id;
+        if !written_by_program {
+            continue;
+        }
+        if let Some(sale) = parse_sale(memo) {
+            sales.push(sale);
+        }
+    }
+    sales
+}
+
+/// Parses the quoted `sale:<mint>:<price>` out of the tail of a Memo
+/// program log line.
+fn parse_sale(memo: &str) -> Option<(Pubkey, u64)> {
+    let start = memo.find("\"sale:")? + "\"sale:".len();
+    let body = &memo[start..];
+    let body = &body[..body.find('"')?];
+    let mut parts = body.split(':');
+    let mint = Pubkey::from_str(parts.next()?).ok()?;
+    let price = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((mint, price))
+}
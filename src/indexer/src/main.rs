@@ -0,0 +1,40 @@
+use std::{env, process::exit, str::FromStr, time::Duration};
+
+use solana_program::pubkey::Pubkey;
+
+use nft_trading_indexer::{db::Db, error::IndexerResult, indexer::Indexer};
+
+const DEFAULT_RPC_URL: &str = "http://localhost:8899";
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 5;
+
+/// Reads its settings from the environment: `DATABASE_URL` and
+/// `PROGRAM_ID` are required, `RPC_URL` and `POLL_INTERVAL_SECS` optional.
+fn main() {
+    let database_url = required_var("DATABASE_URL");
+    let program_id = Pubkey::from_str(&required_var("PROGRAM_ID")).unwrap_or_else(|err| {
+        eprintln!("error: invalid PROGRAM_ID: {}", err);
+        exit(1);
+    });
+    let rpc_url = env::var("RPC_URL").unwrap_or_else(|_| DEFAULT_RPC_URL.to_string());
+    let poll_interval = env::var("POLL_INTERVAL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_POLL_INTERVAL_SECS);
+
+    if let Err(err) = run(&database_url, rpc_url, program_id, Duration::from_secs(poll_interval)) {
+        eprintln!("error: {}", err);
+        exit(1);
+    }
+}
+
+fn run(database_url: &str, rpc_url: String, program_id: Pubkey, poll_interval: Duration) -> IndexerResult<()> {
+    let db = Db::connect(database_url)?;
+    Indexer::new(rpc_url, program_id, db).run(poll_interval)
+}
+
+fn required_var(name: &str) -> String {
+    env::var(name).unwrap_or_else(|_| {
+        eprintln!("error: {} is not set", name);
+        exit(1);
+    })
+}
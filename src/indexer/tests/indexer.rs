@@ -0,0 +1,11 @@
+use nft_trading::state::{self, ListEscrowState};
+use nft_trading_indexer::indexer::is_public_listing;
+
+#[test]
+fn test_only_open_public_listings_are_indexed() {
+    let open = ListEscrowState { is_initialized: true, ..ListEscrowState::default() };
+    assert!(is_public_listing(&open));
+    assert!(!is_public_listing(&ListEscrowState { success: true, ..open }));
+    assert!(!is_public_listing(&ListEscrowState { visibility: state::VISIBILITY_UNLISTED, ..open }));
+    assert!(!is_public_listing(&ListEscrowState::default()));
+}
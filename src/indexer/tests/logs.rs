@@ -0,0 +1,32 @@
+use solana_program::pubkey::Pubkey;
+
+use nft_trading::memo::{self, spl_memo_program};
+use nft_trading_indexer::logs::recorded_sales;
+
+fn memo_log(memo: &str) -> String {
+    format!("Program log: Memo (len {}): {:?}", memo.len(), memo)
+}
+
+#[test]
+fn test_sales_come_only_from_program_memos() {
+    let program_id = Pubkey::new_unique();
+    let memo_program_id = spl_memo_program::id();
+    let mint = Pubkey::new_unique();
+    let forged = Pubkey::new_unique();
+    let logs: Vec<String> = vec![
+        format!("Program {} invoke [1]", memo_program_id),
+        memo_log(&memo::sale_memo(&forged, 1)),
+        format!("Program {} success", memo_program_id),
+        format!("Program {} invoke [1]", program_id),
+        "Program log: Instruction: AcceptBid".to_string(),
+        format!("Program {} invoke [2]", memo_program_id),
+        memo_log(&memo::sale_memo(&mint, 42)),
+        format!("Program {} consumed 500 of 200000 compute units", memo_program_id),
+        format!("Program {} success", memo_program_id),
+        format!("Program {} invoke [2]", memo_program_id),
+        memo_log("thanks!"),
+        format!("Program {} success", memo_program_id),
+        format!("Program {} success", program_id),
+    ];
+    assert_eq!(recorded_sales(&logs, &program_id), vec![(mint, 42)]);
+}
@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use nft_trading::instruction::NFTInstruction;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(instruction) = NFTInstruction::unpack(data) {
+        let packed = instruction.pack();
+        assert_eq!(NFTInstruction::unpack(&packed).unwrap(), instruction);
+    }
+});
@@ -0,0 +1,67 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use solana_program::program_pack::Pack;
+
+use nft_trading::core_listing::CoreListing;
+use nft_trading::fraction::FractionState;
+use nft_trading::index::EscrowIndex;
+use nft_trading::leaderboard::Leaderboard;
+use nft_trading::otc::OtcState;
+use nft_trading::payment::{PaymentMint, TokenBid, TokenListing};
+use nft_trading::pool::Pool;
+use nft_trading::raffle::RaffleState;
+use nft_trading::rental::RentalState;
+use nft_trading::rewards::RewardsConfig;
+use nft_trading::session::Session;
+use nft_trading::staking::{StakeAccount, StakePool};
+use nft_trading::state::{BidEscrowState, CollectionState, CollectionStats, FeeDiscount, ListEscrowState, OrderBook, PlatformState, UserStats};
+use nft_trading::swap::SwapState;
+use nft_trading::trait_offer::TraitOffer;
+
+fn check<T: Pack>(data: &[u8]) {
+    if let Ok(state) = T::unpack_from_slice(data) {
+        let mut packed = vec![0u8; T::LEN];
+        state.pack_into_slice(&mut packed);
+        assert_eq!(&packed[..], &data[..T::LEN]);
+    }
+}
+
+// Slots past a stored length (order book entries, raffle tickets, the
+// platform's pending change) are not preserved, so compare the decoded
+// values instead of the raw bytes.
+fn check_decoded<T: Pack + PartialEq + std::fmt::Debug>(data: &[u8]) {
+    if let Ok(state) = T::unpack_from_slice(data) {
+        let mut packed = vec![0u8; T::LEN];
+        state.pack_into_slice(&mut packed);
+        assert_eq!(T::unpack_from_slice(&packed), Ok(state));
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    check::<ListEscrowState>(data);
+    check::<BidEscrowState>(data);
+    check::<CollectionState>(data);
+    check::<UserStats>(data);
+    check::<Pool>(data);
+    check::<StakePool>(data);
+    check::<StakeAccount>(data);
+    check::<FractionState>(data);
+    check::<RentalState>(data);
+    check::<FeeDiscount>(data);
+    check::<CollectionStats>(data);
+    check::<OtcState>(data);
+    check::<CoreListing>(data);
+    check::<PaymentMint>(data);
+    check::<TokenListing>(data);
+    check::<TokenBid>(data);
+    check::<Session>(data);
+    check::<TraitOffer>(data);
+    check::<RewardsConfig>(data);
+    check_decoded::<PlatformState>(data);
+    check_decoded::<OrderBook>(data);
+    check_decoded::<EscrowIndex>(data);
+    check_decoded::<RaffleState>(data);
+    check_decoded::<Leaderboard>(data);
+    check_decoded::<SwapState>(data);
+});
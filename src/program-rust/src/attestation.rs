@@ -0,0 +1,216 @@
+//! Compliance mode: listing and bidding only for attested wallets.
+//!
+//! The platform authority names an attestor program, such as a KYC
+//! provider's, and turns the requirement on with `SetAttestation`. While it
+//! is on, `List` and `Bid`, and the entry points built on them, only go
+//! through for a lister or bidder holding an attestation: the attestor's
+//! account at `find_attestation_address`, owned by the attestor program
+//! and holding data. `ClaimGift` likewise needs the recipient's. What the
+//! attestation says is up to the attestor, who revokes it by closing the
+//! account. The instruction's accounts then end with the attestation
+//! config and the trader's attestation, ahead of any fee discount, matched
+//! sale or session accounts.
+//!
+//! Entry points that hand an NFT over without either party presenting an
+//! attestation, like `FillOrder`, `BuyCore`, pool trades, OTC deals, swaps
+//! and trait offers, are refused while the requirement is on.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::{error::NFTError, processor::Processor, state::{self, Discriminator, PlatformState, RESERVED_WORDS}};
+
+pub const ATTESTATIONCONFIGSIZE: usize = 106usize;
+
+pub fn find_attestation_config_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"Attestation", b"Config"], program_id)
+}
+
+/// Attestation `attestor` issues `wallet`, derived under the attestor
+/// program.
+pub fn find_attestation_address(attestor: &Pubkey, wallet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"Attestation", wallet.as_ref()], attestor)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct AttestationConfig {
+    pub layout_version: u8,
+    pub is_initialized: bool,
+    /// Program whose attestations traders present.
+    pub attestor: Pubkey,
+    pub reserved: [u64; RESERVED_WORDS],
+}
+
+impl Sealed for AttestationConfig{}
+
+impl Discriminator for AttestationConfig{
+    const DISCRIMINATOR: [u8; 8] = *b"attestor";
+}
+
+impl IsInitialized for AttestationConfig{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for AttestationConfig {
+    const LEN: usize = ATTESTATIONCONFIGSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        state::unpack_borsh(src)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        state::pack_borsh(self, dst)
+    }
+}
+
+/// Names the attestor and turns the requirement on or off, creating the
+/// config on first use. Requiring attestations needs an attestor.
+pub fn process_set_attestation(
+    accounts: &[AccountInfo],
+    attestor: Pubkey,
+    required: bool,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let authority_info = next_account_info(account_info_iter)?;
+    if !authority_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if !state_info.authority.eq(authority_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+    if required && attestor == Pubkey::default() {
+        return Err(NFTError::InvalidInstructionData.into());
+    }
+
+    let config_account_info = next_account_info(account_info_iter)?;
+    let (config_pubkey, nonce) = find_attestation_config_address(program_id);
+    if !(config_account_info.key.eq(&config_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if config_account_info.data_is_empty() {
+        let program_info = next_account_info(account_info_iter)?;
+        if !(program_info.key.eq(program_id)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let system_program_info = next_account_info(account_info_iter)?;
+        if !(system_program_info.key.eq(&system_program::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Processor::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+        let rent = &Rent::get()?;
+        let create_config_account_ix = system_instruction::create_account(
+            authority_info.key,
+            &config_pubkey,
+            rent.minimum_balance(ATTESTATIONCONFIGSIZE),
+            ATTESTATIONCONFIGSIZE as u64,
+            program_id);
+        msg!("submitting tx to create program derived attestation config account");
+        invoke_signed(
+            &create_config_account_ix,
+            &[
+                authority_info.clone(),
+                config_account_info.clone(),
+                system_program_info.clone(),
+                program_info.clone(),
+            ],
+            &[&[
+                &b"Attestation"[..],
+                &b"Config"[..],
+                &[nonce]
+            ]],
+        )?;
+    } else {
+        Processor::assert_owned_by(config_account_info, program_id)?;
+    }
+
+    AttestationConfig::pack(
+        AttestationConfig{
+            layout_version: state::LAYOUT_VERSION,
+            is_initialized: true,
+            attestor,
+            reserved: Default::default(),
+        },
+        &mut config_account_info.data.borrow_mut(),
+    )?;
+    state_info.attestation_required = required;
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())
+}
+
+/// Strips the attestation config and attestation ending an instruction's
+/// accounts, if the second to last is the config. Returns the remaining
+/// accounts and the two attestation accounts for `check_attested`.
+pub(crate) fn split_attestation<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+    program_id: &Pubkey,
+) -> (&'a [AccountInfo<'b>], Option<&'a [AccountInfo<'b>]>) {
+    let (config_pubkey, _) = find_attestation_config_address(program_id);
+    match accounts.len().checked_sub(2).map(|len| accounts.split_at(len)) {
+        Some((rest, attestation_accounts)) if attestation_accounts[0].key.eq(&config_pubkey) => (rest, Some(attestation_accounts)),
+        _ => (accounts, None),
+    }
+}
+
+/// Refuses an entry point that skips attestations while the platform
+/// requires them.
+pub(crate) fn check_not_required(state_info: &PlatformState) -> ProgramResult {
+    if state_info.attestation_required {
+        return Err(NFTError::AttestationRequired.into());
+    }
+    Ok(())
+}
+
+/// Checks that `wallet` presented an attestation from the configured
+/// attestor, if the platform requires one.
+pub(crate) fn check_attested(
+    attestation_accounts: Option<&[AccountInfo]>,
+    state_info: &PlatformState,
+    wallet: &Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if !state_info.attestation_required {
+        return Ok(());
+    }
+    let (config_account_info, attestation_info) = match attestation_accounts {
+        Some([config_account_info, attestation_info]) => (config_account_info, attestation_info),
+        _ => return Err(NFTError::AttestationRequired.into()),
+    };
+    Processor::assert_owned_by(config_account_info, program_id)?;
+    let config = AttestationConfig::unpack(&config_account_info.data.borrow())?;
+    let (attestation_pubkey, _) = find_attestation_address(&config.attestor, wallet);
+    if !attestation_info.key.eq(&attestation_pubkey)
+        || !attestation_info.owner.eq(&config.attestor)
+        || attestation_info.data_is_empty()
+    {
+        return Err(NFTError::AttestationRequired.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,486 @@
+//! Fixed-price listings of Metaplex Core assets.
+//!
+//! Core assets are single accounts with no token account, so there is no
+//! vault: the listing PDA becomes the asset's owner while it is listed and
+//! signs the Core transfer out, either to the buyer in `BuyCore` or back to
+//! the seller in `DelistCore`. `BuyCore` pays the seller and releases the
+//! asset in the same instruction. Fees match `AcceptOtc`: the maker fee
+//! comes out of the price and the taker fee is charged on top. Core royalty
+//! plugins are not read, so no royalties are paid on these sales.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{attestation, error::NFTError, mpl_core::{self, mpl_core_program, AssetV1}, processor::Processor, state::{self, Discriminator, PlatformState}};
+
+pub const CORELISTINGSIZE: usize = 81usize;
+
+pub fn find_core_listing_address(program_id: &Pubkey, asset: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[asset.as_ref(), b"Core", b"List"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CoreListing {
+    pub is_initialized: bool,
+    pub seller: Pubkey,
+    pub asset: Pubkey,
+    pub price: u64,
+}
+
+impl Sealed for CoreListing{}
+
+impl Discriminator for CoreListing{
+    const DISCRIMINATOR: [u8; 8] = *b"corelist";
+}
+
+impl IsInitialized for CoreListing{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for CoreListing {
+    const LEN: usize = CORELISTINGSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < CoreListing::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, CoreListing::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            seller,
+            asset,
+            price,
+        ) = array_refs![src, 8, 1, 32, 32, 8];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(CoreListing{
+            is_initialized,
+            seller: Pubkey::new_from_array(*seller),
+            asset: Pubkey::new_from_array(*asset),
+            price: u64::from_be_bytes(*price),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, CoreListing::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            seller_dst,
+            asset_dst,
+            price_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 32, 8];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+        is_initialized_dst[0] = self.is_initialized as u8;
+        seller_dst.copy_from_slice(self.seller.as_ref());
+        asset_dst.copy_from_slice(self.asset.as_ref());
+        *price_dst = self.price.to_be_bytes();
+    }
+}
+
+pub fn process_list_core(
+    accounts: &[AccountInfo],
+    price: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let seller_info = next_account_info(account_info_iter)?;
+    if !seller_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let asset_info = next_account_info(account_info_iter)?;
+    let asset = mpl_core::load_asset(asset_info)?;
+    if !(asset.owner.eq(seller_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let listing_account_info = next_account_info(account_info_iter)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Processor::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::get()?;
+
+    let core_program_info = next_account_info(account_info_iter)?;
+    if !(core_program_info.key.eq(&mpl_core_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if state_info.is_paused(state::PAUSE_LIST) {
+        return Err(NFTError::InstructionPaused.into());
+    }
+    if price < state_info.min_listing_lamports {
+        return Err(NFTError::BelowMinimumPrice.into());
+    }
+    if state_info.strict && asset.collection().is_none() {
+        return Err(NFTError::UnverifiedCollection.into());
+    }
+
+    let collection_info = next_collection_info(account_info_iter, &asset, core_program_info)?;
+    if state_info.curated {
+        let collection_state_account_info = next_account_info(account_info_iter)?;
+        Processor::check_collection_allowed(asset.collection(), collection_state_account_info, program_id)?;
+    }
+
+    let (listing_pubkey, nonce) = find_core_listing_address(program_id, asset_info.key);
+    if !(listing_account_info.key.eq(&listing_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !listing_account_info.data_is_empty() {
+        return Err(NFTError::ListingAlreadyExists.into());
+    }
+    let create_listing_account_ix = system_instruction::create_account(
+        seller_info.key,
+        &listing_pubkey,
+        rent.minimum_balance(CORELISTINGSIZE),
+        CORELISTINGSIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived core listing account");
+    invoke_signed(
+        &create_listing_account_ix,
+        &[
+            seller_info.clone(),
+            listing_account_info.clone(),
+            system_program_info.clone(),
+            program_info.clone(),
+        ],
+        &[&[
+            asset_info.key.as_ref(),
+            &b"Core"[..],
+            &b"List"[..],
+            &[nonce]
+        ]],
+    )?;
+
+    transfer_asset(
+        asset_info,
+        collection_info,
+        seller_info,
+        seller_info,
+        listing_account_info,
+        core_program_info,
+        &[],
+    )?;
+
+    CoreListing::pack(
+        CoreListing{
+            is_initialized: true,
+            seller: *seller_info.key,
+            asset: *asset_info.key,
+            price,
+        },
+        &mut listing_account_info.data.borrow_mut()
+    )?;
+
+    state_info.active_listings = state_info.active_listings
+        .checked_add(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_delist_core(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let seller_info = next_account_info(account_info_iter)?;
+    if !seller_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let asset_info = next_account_info(account_info_iter)?;
+    let asset = mpl_core::load_asset(asset_info)?;
+
+    let listing_account_info = next_account_info(account_info_iter)?;
+    let listing = load_core_listing(listing_account_info, asset_info.key, program_id)?;
+    if !(listing.seller.eq(seller_info.key)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if state_info.is_paused(state::PAUSE_DELIST) {
+        return Err(NFTError::InstructionPaused.into());
+    }
+
+    let core_program_info = next_account_info(account_info_iter)?;
+    if !(core_program_info.key.eq(&mpl_core_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let collection_info = next_collection_info(account_info_iter, &asset, core_program_info)?;
+
+    release_asset(
+        &listing,
+        &asset,
+        asset_info,
+        collection_info,
+        seller_info,
+        seller_info,
+        listing_account_info,
+        core_program_info,
+        program_id,
+    )?;
+    Processor::close_account(listing_account_info, seller_info, program_id)?;
+
+    state_info.active_listings = state_info.active_listings.saturating_sub(1);
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+/// Pays the listing price, if no more than `max_price`, and takes the asset.
+pub fn process_buy_core(
+    accounts: &[AccountInfo],
+    max_price: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let buyer_info = next_account_info(account_info_iter)?;
+    if !buyer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let seller_info = next_account_info(account_info_iter)?;
+    if seller_info.key.eq(buyer_info.key) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let asset_info = next_account_info(account_info_iter)?;
+    let asset = mpl_core::load_asset(asset_info)?;
+
+    let listing_account_info = next_account_info(account_info_iter)?;
+    let listing = load_core_listing(listing_account_info, asset_info.key, program_id)?;
+    if !(listing.seller.eq(seller_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if listing.price > max_price {
+        return Err(NFTError::PriceMismatch.into());
+    }
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if state_info.is_paused(state::PAUSE_SALE) {
+        return Err(NFTError::InstructionPaused.into());
+    }
+    attestation::check_not_required(&state_info)?;
+
+    let fee_recipient_info = next_account_info(account_info_iter)?;
+    if !state_info.fee_recipient.eq(fee_recipient_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let core_program_info = next_account_info(account_info_iter)?;
+    if !(core_program_info.key.eq(&mpl_core_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let collection_info = next_collection_info(account_info_iter, &asset, core_program_info)?;
+
+    let maker_fee = Processor::calculate_fee(listing.price, state_info.maker_fee_bps)?;
+    let taker_fee = Processor::calculate_fee(listing.price, state_info.taker_fee_bps)?;
+    let platform_fee = maker_fee
+        .checked_add(taker_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let seller_proceeds = listing.price
+        .checked_sub(maker_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let payouts = vec![(seller_info, seller_proceeds), (fee_recipient_info, platform_fee)];
+    for (recipient_info, lamports) in payouts {
+        if lamports == 0 {
+            continue;
+        }
+        invoke(
+            &system_instruction::transfer(buyer_info.key, recipient_info.key, lamports),
+            &[
+                buyer_info.clone(),
+                recipient_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
+    release_asset(
+        &listing,
+        &asset,
+        asset_info,
+        collection_info,
+        buyer_info,
+        buyer_info,
+        listing_account_info,
+        core_program_info,
+        program_id,
+    )?;
+    // The listing's rent goes back to the seller, not the buyer.
+    Processor::close_account(listing_account_info, seller_info, program_id)?;
+
+    state_info.sale_count = state_info.sale_count
+        .checked_add(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.total_volume = state_info.total_volume
+        .checked_add(listing.price)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.total_fees = state_info.total_fees
+        .checked_add(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.active_listings = state_info.active_listings.saturating_sub(1);
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn load_core_listing(listing_account_info: &AccountInfo, asset: &Pubkey, program_id: &Pubkey) -> Result<CoreListing, ProgramError> {
+    Processor::assert_owned_by(listing_account_info, program_id)?;
+    let listing = CoreListing::unpack(&listing_account_info.data.borrow())?;
+    let (listing_pubkey, _) = find_core_listing_address(program_id, &listing.asset);
+    if !(listing_account_info.key.eq(&listing_pubkey)) || !(listing.asset.eq(asset)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(listing)
+}
+
+/// Reads the asset's collection account when it has one. Otherwise Core
+/// expects its own program id in the collection slot.
+fn next_collection_info<'a, 'b, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    account_info_iter: &mut I,
+    asset: &AssetV1,
+    core_program_info: &'a AccountInfo<'b>,
+) -> Result<&'a AccountInfo<'b>, ProgramError> {
+    match asset.collection() {
+        Some(collection) => {
+            let collection_info = next_account_info(account_info_iter)?;
+            if !(collection_info.key.eq(&collection)) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            Ok(collection_info)
+        }
+        None => Ok(core_program_info),
+    }
+}
+
+fn transfer_asset<'a>(
+    asset_info: &AccountInfo<'a>,
+    collection_info: &AccountInfo<'a>,
+    payer_info: &AccountInfo<'a>,
+    authority_info: &AccountInfo<'a>,
+    new_owner_info: &AccountInfo<'a>,
+    core_program_info: &AccountInfo<'a>,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let collection = Some(collection_info.key).filter(|key| !key.eq(&core_program_info.key));
+    let transfer_ix = mpl_core::transfer(
+        asset_info.key,
+        collection,
+        payer_info.key,
+        authority_info.key,
+        new_owner_info.key,
+    );
+    invoke_signed(
+        &transfer_ix,
+        &[
+            asset_info.clone(),
+            collection_info.clone(),
+            payer_info.clone(),
+            authority_info.clone(),
+            new_owner_info.clone(),
+            core_program_info.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+/// Transfers the listed asset to `recipient_info`, signed by the listing
+/// PDA.
+#[allow(clippy::too_many_arguments)]
+fn release_asset<'a>(
+    listing: &CoreListing,
+    asset: &AssetV1,
+    asset_info: &AccountInfo<'a>,
+    collection_info: &AccountInfo<'a>,
+    payer_info: &AccountInfo<'a>,
+    recipient_info: &AccountInfo<'a>,
+    listing_account_info: &AccountInfo<'a>,
+    core_program_info: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if !(asset.owner.eq(listing_account_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let (_, nonce) = find_core_listing_address(program_id, &listing.asset);
+    transfer_asset(
+        asset_info,
+        collection_info,
+        payer_info,
+        listing_account_info,
+        recipient_info,
+        core_program_info,
+        &[&[
+            listing.asset.as_ref(),
+            &b"Core"[..],
+            &b"List"[..],
+            &[nonce]
+        ]],
+    )
+}
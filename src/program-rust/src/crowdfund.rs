@@ -0,0 +1,783 @@
+//! Crowdfunded purchases of a listed NFT.
+//!
+//! Anyone opens a crowdfund on a fixed-price listing with
+//! `CreateCrowdfund`, which fixes the target at the ask plus the taker fee
+//! in force. Wallets `Contribute` SOL into the crowdfund account until the
+//! target is met, each tracked by a contribution PDA. `ExecuteCrowdfund`
+//! can then be sent by anyone: it settles the listing like a sale, locks
+//! the NFT in a fraction vault (see `fraction`) and leaves one share per
+//! contributed lamport to be claimed with `ClaimCrowdfundShares`. Shares
+//! redeem like any fraction, so the vault's buyout price is the group's
+//! exit. While unpurchased, contributors take their SOL back with
+//! `RefundContribution` once the listing is gone, sold or repriced.
+//! Royalties are not paid, as with fraction buyouts.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{
+    attestation,
+    error::NFTError,
+    fraction::{self, FractionState},
+    processor::Processor,
+    state::{self, Discriminator, ListEscrowState, PlatformState},
+};
+
+pub const CROWDFUNDSIZE: usize = 146usize;
+pub const CONTRIBUTIONSIZE: usize = 81usize;
+
+pub fn find_crowdfund_address(program_id: &Pubkey, mint: &Pubkey, lister: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), lister.as_ref(), b"Crowdfund"], program_id)
+}
+
+pub fn find_contribution_address(program_id: &Pubkey, crowdfund: &Pubkey, contributor: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[crowdfund.as_ref(), contributor.as_ref(), b"Contribution"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Crowdfund {
+    pub is_initialized: bool,
+    /// Paid the crowdfund's rent; it goes back here on close.
+    pub creator: Pubkey,
+    pub mint: Pubkey,
+    pub lister: Pubkey,
+    /// The listing's ask when the crowdfund opened.
+    pub price: u64,
+    /// `price` plus the taker fee, in lamports.
+    pub target: u64,
+    pub raised: u64,
+    /// Buyout price of the fraction vault the NFT is locked in.
+    pub buyout_price: u64,
+    pub purchased: bool,
+    /// Contribution accounts not yet claimed or refunded.
+    pub contributors: u64,
+}
+
+impl Sealed for Crowdfund{}
+
+impl Discriminator for Crowdfund{
+    const DISCRIMINATOR: [u8; 8] = *b"crowdfnd";
+}
+
+impl IsInitialized for Crowdfund{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Crowdfund {
+    const LEN: usize = CROWDFUNDSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Crowdfund::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, Crowdfund::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            creator,
+            mint,
+            lister,
+            price,
+            target,
+            raised,
+            buyout_price,
+            purchased,
+            contributors,
+        ) = array_refs![src, 8, 1, 32, 32, 32, 8, 8, 8, 8, 1, 8];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let purchased = match purchased {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(Crowdfund{
+            is_initialized,
+            creator: Pubkey::new_from_array(*creator),
+            mint: Pubkey::new_from_array(*mint),
+            lister: Pubkey::new_from_array(*lister),
+            price: u64::from_be_bytes(*price),
+            target: u64::from_be_bytes(*target),
+            raised: u64::from_be_bytes(*raised),
+            buyout_price: u64::from_be_bytes(*buyout_price),
+            purchased,
+            contributors: u64::from_be_bytes(*contributors),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Crowdfund::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            creator_dst,
+            mint_dst,
+            lister_dst,
+            price_dst,
+            target_dst,
+            raised_dst,
+            buyout_price_dst,
+            purchased_dst,
+            contributors_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 32, 32, 8, 8, 8, 8, 1, 8];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+        is_initialized_dst[0] = self.is_initialized as u8;
+        creator_dst.copy_from_slice(self.creator.as_ref());
+        mint_dst.copy_from_slice(self.mint.as_ref());
+        lister_dst.copy_from_slice(self.lister.as_ref());
+        *price_dst = self.price.to_be_bytes();
+        *target_dst = self.target.to_be_bytes();
+        *raised_dst = self.raised.to_be_bytes();
+        *buyout_price_dst = self.buyout_price.to_be_bytes();
+        purchased_dst[0] = self.purchased as u8;
+        *contributors_dst = self.contributors.to_be_bytes();
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Contribution {
+    pub is_initialized: bool,
+    pub crowdfund: Pubkey,
+    pub contributor: Pubkey,
+    pub amount: u64,
+}
+
+impl Sealed for Contribution{}
+
+impl Discriminator for Contribution{
+    const DISCRIMINATOR: [u8; 8] = *b"contrib\0";
+}
+
+impl IsInitialized for Contribution{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Contribution {
+    const LEN: usize = CONTRIBUTIONSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Contribution::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, Contribution::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            crowdfund,
+            contributor,
+            amount,
+        ) = array_refs![src, 8, 1, 32, 32, 8];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(Contribution{
+            is_initialized,
+            crowdfund: Pubkey::new_from_array(*crowdfund),
+            contributor: Pubkey::new_from_array(*contributor),
+            amount: u64::from_be_bytes(*amount),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Contribution::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            crowdfund_dst,
+            contributor_dst,
+            amount_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 32, 8];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+        is_initialized_dst[0] = self.is_initialized as u8;
+        crowdfund_dst.copy_from_slice(self.crowdfund.as_ref());
+        contributor_dst.copy_from_slice(self.contributor.as_ref());
+        *amount_dst = self.amount.to_be_bytes();
+    }
+}
+
+/// Opens a crowdfund on `lister`'s fixed-price listing of the mint.
+pub fn process_create_crowdfund(
+    accounts: &[AccountInfo],
+    buyout_price: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let creator_info = next_account_info(account_info_iter)?;
+    if !creator_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mint_account_info = next_account_info(account_info_iter)?;
+    let lister_info = next_account_info(account_info_iter)?;
+    let list_state_account_info = next_account_info(account_info_iter)?;
+    let (list_state, _) = load_listing(list_state_account_info, mint_account_info.key, lister_info.key, program_id)?;
+    let crowdfund_account_info = next_account_info(account_info_iter)?;
+    let (crowdfund_pubkey, crowdfund_nonce) = find_crowdfund_address(program_id, mint_account_info.key, lister_info.key);
+    if !(crowdfund_account_info.key.eq(&crowdfund_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    check_listing_buyable(&list_state, &crowdfund_pubkey)?;
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let state_info = load_platform_state(state_account_info, program_id)?;
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+    let rent = &Rent::get()?;
+
+    let taker_fee = Processor::calculate_fee(list_state.amount, state_info.taker_fee_bps)?;
+    let target = list_state.amount
+        .checked_add(taker_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+
+    let create_crowdfund_ix = system_instruction::create_account(
+        creator_info.key,
+        &crowdfund_pubkey,
+        rent.minimum_balance(CROWDFUNDSIZE),
+        CROWDFUNDSIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived crowdfund account");
+    invoke_signed(
+        &create_crowdfund_ix,
+        &[
+            creator_info.clone(),
+            crowdfund_account_info.clone(),
+            system_program_info.clone(),
+        ],
+        &[&[
+            mint_account_info.key.as_ref(),
+            lister_info.key.as_ref(),
+            &b"Crowdfund"[..],
+            &[crowdfund_nonce]
+        ]],
+    )?;
+
+    Crowdfund::pack(
+        Crowdfund{
+            is_initialized: true,
+            creator: *creator_info.key,
+            mint: *mint_account_info.key,
+            lister: *lister_info.key,
+            price: list_state.amount,
+            target,
+            raised: 0,
+            buyout_price,
+            purchased: false,
+            contributors: 0,
+        },
+        &mut crowdfund_account_info.data.borrow_mut()
+    )?;
+
+    Ok(())
+}
+
+/// Adds `amount` lamports to the crowdfund, up to its target.
+pub fn process_contribute(
+    accounts: &[AccountInfo],
+    amount: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if amount == 0 {
+        return Err(NFTError::InvalidInstructionData.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let contributor_info = next_account_info(account_info_iter)?;
+    if !contributor_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let crowdfund_account_info = next_account_info(account_info_iter)?;
+    let mut crowdfund = load_crowdfund(crowdfund_account_info, program_id)?;
+    if crowdfund.purchased {
+        return Err(NFTError::CrowdfundPurchased.into());
+    }
+    crowdfund.raised = crowdfund.raised
+        .checked_add(amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    if crowdfund.raised > crowdfund.target {
+        return Err(NFTError::ContributionExceedsTarget.into());
+    }
+
+    let contribution_account_info = next_account_info(account_info_iter)?;
+    let (contribution_pubkey, contribution_nonce) = find_contribution_address(program_id, crowdfund_account_info.key, contributor_info.key);
+    if !(contribution_account_info.key.eq(&contribution_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+    let rent = &Rent::get()?;
+
+    let mut contribution = if contribution_account_info.data_is_empty() {
+        let create_contribution_ix = system_instruction::create_account(
+            contributor_info.key,
+            &contribution_pubkey,
+            rent.minimum_balance(CONTRIBUTIONSIZE),
+            CONTRIBUTIONSIZE as u64,
+            program_id);
+        invoke_signed(
+            &create_contribution_ix,
+            &[
+                contributor_info.clone(),
+                contribution_account_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[&[
+                crowdfund_account_info.key.as_ref(),
+                contributor_info.key.as_ref(),
+                &b"Contribution"[..],
+                &[contribution_nonce]
+            ]],
+        )?;
+        crowdfund.contributors = crowdfund.contributors
+            .checked_add(1)
+            .ok_or(NFTError::NumericalOverflow)?;
+        Contribution{
+            is_initialized: true,
+            crowdfund: *crowdfund_account_info.key,
+            contributor: *contributor_info.key,
+            amount: 0,
+        }
+    } else {
+        Contribution::unpack(&contribution_account_info.data.borrow())?
+    };
+    contribution.amount = contribution.amount
+        .checked_add(amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+
+    invoke(
+        &system_instruction::transfer(contributor_info.key, crowdfund_account_info.key, amount),
+        &[
+            contributor_info.clone(),
+            crowdfund_account_info.clone(),
+            system_program_info.clone(),
+        ],
+    )?;
+
+    Contribution::pack(contribution, &mut contribution_account_info.data.borrow_mut())?;
+    Crowdfund::pack(crowdfund, &mut crowdfund_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+/// Buys the listing with the raised SOL and locks the NFT in a fraction
+/// vault whose shares belong to the contributors. The executor funds the
+/// vault accounts and is their curator, so it gets the vault rent back on
+/// a buyout.
+pub fn process_execute_crowdfund(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let executor_info = next_account_info(account_info_iter)?;
+    if !executor_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let crowdfund_account_info = next_account_info(account_info_iter)?;
+    let mut crowdfund = load_crowdfund(crowdfund_account_info, program_id)?;
+    if crowdfund.purchased {
+        return Err(NFTError::CrowdfundPurchased.into());
+    }
+    if crowdfund.raised < crowdfund.target {
+        return Err(NFTError::CrowdfundTargetNotMet.into());
+    }
+
+    let mint_account_info = next_account_info(account_info_iter)?;
+    if !(mint_account_info.key.eq(&crowdfund.mint)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let lister_info = next_account_info(account_info_iter)?;
+    if !(lister_info.key.eq(&crowdfund.lister)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let list_rent_payer_info = next_account_info(account_info_iter)?;
+    let list_state_account_info = next_account_info(account_info_iter)?;
+    let (list_state, list_state_nonce) = load_listing(list_state_account_info, &crowdfund.mint, &crowdfund.lister, program_id)?;
+    check_listing_buyable(&list_state, crowdfund_account_info.key)?;
+    if list_state.amount != crowdfund.price {
+        return Err(NFTError::ListingChanged.into());
+    }
+    if !list_state.payer.eq(list_rent_payer_info.key) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let list_vault_account_info = next_account_info(account_info_iter)?;
+    Processor::check_list_vault(list_vault_account_info, list_state_account_info.key, &crowdfund.mint, list_state.vault_bump)?;
+
+    let fraction_state_account_info = next_account_info(account_info_iter)?;
+    let fraction_vault_account_info = next_account_info(account_info_iter)?;
+    let share_mint_account_info = next_account_info(account_info_iter)?;
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let mut state_info = load_platform_state(state_account_info, program_id)?;
+    if state_info.is_paused(state::PAUSE_SALE) {
+        return Err(NFTError::InstructionPaused.into());
+    }
+    attestation::check_not_required(&state_info)?;
+    let fee_recipient_info = next_account_info(account_info_iter)?;
+    if !state_info.fee_recipient.eq(fee_recipient_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+
+    let curator_info = if list_state.curator == Pubkey::default() {
+        None
+    } else {
+        let curator_info = next_account_info(account_info_iter)?;
+        if !list_state.curator.eq(curator_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Some(curator_info)
+    };
+    let charity_info = if list_state.has_charity() {
+        let charity_info = next_account_info(account_info_iter)?;
+        if !list_state.charity.eq(charity_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Some(charity_info)
+    } else {
+        None
+    };
+
+    // The taker fee is the one locked into the target at creation.
+    let taker_fee = crowdfund.target
+        .checked_sub(crowdfund.price)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let maker_fee = Processor::calculate_fee(crowdfund.price, state_info.maker_fee_bps)?;
+    let platform_fee = maker_fee
+        .checked_add(taker_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let curator_fee = match curator_info {
+        Some(_) => Processor::calculate_fee(platform_fee, state_info.curator_fee_bps)?,
+        None => 0,
+    };
+    let proceeds = crowdfund.price
+        .checked_sub(maker_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let charity_share = match charity_info {
+        Some(_) => Processor::calculate_fee(proceeds, list_state.charity_bps as u64)?,
+        None => 0,
+    };
+    let seller_proceeds = proceeds
+        .checked_sub(charity_share)
+        .ok_or(NFTError::NumericalOverflow)?;
+
+    fraction::create_fraction(
+        executor_info,
+        mint_account_info,
+        fraction_state_account_info,
+        fraction_vault_account_info,
+        share_mint_account_info,
+        system_program_info,
+        token_program_info,
+        rent_account_info,
+        program_id,
+    )?;
+    Processor::release_list_vault(
+        token_program_info,
+        list_vault_account_info,
+        list_state_account_info,
+        mint_account_info,
+        fraction_vault_account_info,
+        &crowdfund.lister,
+        list_state_nonce,
+    )?;
+    Processor::close_account(list_state_account_info, list_rent_payer_info, program_id)?;
+
+    let mut payouts = vec![
+        (lister_info, seller_proceeds),
+        (fee_recipient_info, platform_fee.checked_sub(curator_fee).ok_or(NFTError::NumericalOverflow)?),
+    ];
+    if let Some(curator_info) = curator_info {
+        payouts.push((curator_info, curator_fee));
+    }
+    if let Some(charity_info) = charity_info {
+        payouts.push((charity_info, charity_share));
+    }
+    for (recipient_info, lamports) in payouts {
+        let crowdfund_lamports = crowdfund_account_info.lamports()
+            .checked_sub(lamports)
+            .ok_or(NFTError::NumericalOverflow)?;
+        let recipient_lamports = recipient_info.lamports()
+            .checked_add(lamports)
+            .ok_or(NFTError::NumericalOverflow)?;
+        **crowdfund_account_info.try_borrow_mut_lamports()? = crowdfund_lamports;
+        **recipient_info.try_borrow_mut_lamports()? = recipient_lamports;
+    }
+
+    FractionState::pack(
+        FractionState{
+            is_initialized: true,
+            curator: *executor_info.key,
+            mint: crowdfund.mint,
+            share_mint: *share_mint_account_info.key,
+            share_supply: crowdfund.raised,
+            buyout_price: crowdfund.buyout_price,
+            bought_out: false,
+            outstanding_shares: crowdfund.raised,
+            proceeds: 0,
+        },
+        &mut fraction_state_account_info.data.borrow_mut()
+    )?;
+
+    crowdfund.purchased = true;
+    Crowdfund::pack(crowdfund, &mut crowdfund_account_info.data.borrow_mut())?;
+
+    state_info.sale_count = state_info.sale_count
+        .checked_add(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.total_volume = state_info.total_volume
+        .checked_add(crowdfund.price)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.total_fees = state_info.total_fees
+        .checked_add(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.active_listings = state_info.active_listings.saturating_sub(1);
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+/// Mints a contributor one share per contributed lamport once the
+/// crowdfund has bought the NFT, closing the contribution.
+pub fn process_claim_crowdfund_shares(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let contributor_info = next_account_info(account_info_iter)?;
+    let contributor_share_account_info = next_account_info(account_info_iter)?;
+    let creator_info = next_account_info(account_info_iter)?;
+
+    let crowdfund_account_info = next_account_info(account_info_iter)?;
+    let mut crowdfund = load_crowdfund(crowdfund_account_info, program_id)?;
+    if !crowdfund.purchased {
+        return Err(NFTError::CrowdfundNotPurchased.into());
+    }
+    if !(creator_info.key.eq(&crowdfund.creator)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let contribution_account_info = next_account_info(account_info_iter)?;
+    let contribution = load_contribution(contribution_account_info, crowdfund_account_info.key, contributor_info.key, program_id)?;
+
+    let share_mint_account_info = next_account_info(account_info_iter)?;
+    let fraction_state_account_info = next_account_info(account_info_iter)?;
+    let fraction_state = fraction::load_fraction_state(fraction_state_account_info, program_id)?;
+    if !(fraction_state.mint.eq(&crowdfund.mint)) || !(share_mint_account_info.key.eq(&fraction_state.share_mint)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let share_account = spl_token::state::Account::unpack(&contributor_share_account_info.data.borrow())?;
+    if !(share_account.owner.eq(contributor_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (_, state_nonce) = fraction::find_fraction_state_address(program_id, &crowdfund.mint);
+    fraction::mint_shares(
+        &crowdfund.mint,
+        share_mint_account_info,
+        contributor_share_account_info,
+        fraction_state_account_info,
+        token_program_info,
+        contribution.amount,
+        state_nonce,
+    )?;
+
+    close_contribution(&mut crowdfund, crowdfund_account_info, contribution_account_info, contributor_info, creator_info, program_id)
+}
+
+/// Returns a contribution to an unpurchased crowdfund whose listing was
+/// cancelled, sold elsewhere or relisted at another price.
+pub fn process_refund_contribution(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let contributor_info = next_account_info(account_info_iter)?;
+    let creator_info = next_account_info(account_info_iter)?;
+
+    let crowdfund_account_info = next_account_info(account_info_iter)?;
+    let mut crowdfund = load_crowdfund(crowdfund_account_info, program_id)?;
+    if crowdfund.purchased {
+        return Err(NFTError::CrowdfundPurchased.into());
+    }
+    if !(creator_info.key.eq(&crowdfund.creator)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let contribution_account_info = next_account_info(account_info_iter)?;
+    let contribution = load_contribution(contribution_account_info, crowdfund_account_info.key, contributor_info.key, program_id)?;
+
+    let list_state_account_info = next_account_info(account_info_iter)?;
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, &crowdfund.mint, &crowdfund.lister);
+    if !(list_state_account_info.key.eq(&list_state_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let listing_available = list_state_account_info.owner.eq(program_id)
+        && !list_state_account_info.data_is_empty()
+        && match ListEscrowState::unpack(&list_state_account_info.data.borrow()) {
+            Ok(list_state) => !list_state.success && list_state.amount == crowdfund.price,
+            Err(_) => false,
+        };
+    if listing_available {
+        return Err(NFTError::ListingStillAvailable.into());
+    }
+
+    let crowdfund_lamports = crowdfund_account_info.lamports()
+        .checked_sub(contribution.amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let contributor_lamports = contributor_info.lamports()
+        .checked_add(contribution.amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    **crowdfund_account_info.try_borrow_mut_lamports()? = crowdfund_lamports;
+    **contributor_info.try_borrow_mut_lamports()? = contributor_lamports;
+    crowdfund.raised = crowdfund.raised
+        .checked_sub(contribution.amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+
+    close_contribution(&mut crowdfund, crowdfund_account_info, contribution_account_info, contributor_info, creator_info, program_id)
+}
+
+fn load_crowdfund(crowdfund_account_info: &AccountInfo, program_id: &Pubkey) -> Result<Crowdfund, ProgramError> {
+    Processor::assert_owned_by(crowdfund_account_info, program_id)?;
+    let crowdfund = Crowdfund::unpack(&crowdfund_account_info.data.borrow())?;
+    let (crowdfund_pubkey, _) = find_crowdfund_address(program_id, &crowdfund.mint, &crowdfund.lister);
+    if !(crowdfund_account_info.key.eq(&crowdfund_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(crowdfund)
+}
+
+fn load_contribution(
+    contribution_account_info: &AccountInfo,
+    crowdfund: &Pubkey,
+    contributor: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<Contribution, ProgramError> {
+    Processor::assert_owned_by(contribution_account_info, program_id)?;
+    let contribution = Contribution::unpack(&contribution_account_info.data.borrow())?;
+    if !(contribution.crowdfund.eq(crowdfund)) || !(contribution.contributor.eq(contributor)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(contribution)
+}
+
+fn load_listing(
+    list_state_account_info: &AccountInfo,
+    mint: &Pubkey,
+    lister: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<(ListEscrowState, u8), ProgramError> {
+    Processor::assert_owned_by(list_state_account_info, program_id)?;
+    let list_state = ListEscrowState::unpack(&list_state_account_info.data.borrow())?;
+    let nonce = Processor::check_program_address(
+        list_state_account_info,
+        &[mint.as_ref(), lister.as_ref(), b"List", b"State"],
+        list_state.state_bump,
+        program_id,
+    )?;
+    if !(list_state.lister.eq(lister)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok((list_state, nonce))
+}
+
+/// Checks that the crowdfund at `buyer` could buy the listing now.
+fn check_listing_buyable(list_state: &ListEscrowState, buyer: &Pubkey) -> ProgramResult {
+    if list_state.auction || list_state.success || list_state.has_delayed_settlement() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if list_state.is_pending() {
+        return Err(NFTError::ListingPendingApproval.into());
+    }
+    if !list_state.allows_buyer(buyer) {
+        return Err(NFTError::BuyerNotAllowed.into());
+    }
+    if !list_state.has_started(Clock::get()?.unix_timestamp) {
+        return Err(NFTError::ListingNotStarted.into());
+    }
+    Ok(())
+}
+
+fn load_platform_state(state_account_info: &AccountInfo, program_id: &Pubkey) -> Result<PlatformState, ProgramError> {
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(state_info)
+}
+
+/// Closes a settled contribution, returning its rent to the contributor,
+/// and closes the crowdfund to its creator once no contributions remain.
+fn close_contribution(
+    crowdfund: &mut Crowdfund,
+    crowdfund_account_info: &AccountInfo,
+    contribution_account_info: &AccountInfo,
+    contributor_info: &AccountInfo,
+    creator_info: &AccountInfo,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    Processor::close_account(contribution_account_info, contributor_info, program_id)?;
+    crowdfund.contributors = crowdfund.contributors.saturating_sub(1);
+    if crowdfund.contributors == 0 {
+        return Processor::close_account(crowdfund_account_info, creator_info, program_id);
+    }
+    Crowdfund::pack(*crowdfund, &mut crowdfund_account_info.data.borrow_mut())
+}
@@ -23,6 +23,246 @@ pub enum NFTError {
     /// Failed to unpack U64
     #[error("Failed to unpack U64")]
     FailedToUnpackU64,
+
+    /// Numerical overflow
+    #[error("Numerical Overflow")]
+    NumericalOverflow,
+
+    /// Collection is not on the curated allowlist
+    #[error("Collection Not Allowed")]
+    CollectionNotAllowed,
+
+    /// Auction bid does not beat the reserve or the current highest bid
+    #[error("Bid Too Low")]
+    BidTooLow,
+
+    /// Auction listings can only be sold to the highest bidder
+    #[error("Not Highest Bid")]
+    NotHighestBid,
+
+    /// Signed order is missing or does not match its ed25519 verification
+    #[error("Invalid Order Signature")]
+    InvalidOrderSignature,
+
+    /// Signed order is past its expiry
+    #[error("Order Expired")]
+    OrderExpired,
+
+    /// Signed order nonce was already filled or cancelled
+    #[error("Order Already Filled")]
+    OrderAlreadyFilled,
+
+    /// Pool price moved past the trader's limit
+    #[error("Pool Price Slippage")]
+    PoolPriceSlippage,
+
+    /// Pool does not hold enough SOL for the trade or withdrawal
+    #[error("Insufficient Pool Funds")]
+    InsufficientPoolFunds,
+
+    /// Staking rewards cannot be funded while nothing is staked
+    #[error("Nothing Staked")]
+    NothingStaked,
+
+    /// Raffle has ended, sold out or already been drawn
+    #[error("Raffle Closed")]
+    RaffleClosed,
+
+    /// Raffle is still selling tickets or has not been drawn
+    #[error("Raffle Not Ended")]
+    RaffleNotEnded,
+
+    /// Fractionalized NFT has already been bought out
+    #[error("Fraction Bought Out")]
+    FractionBoughtOut,
+
+    /// Shares can only be redeemed for proceeds after a buyout
+    #[error("Fraction Not Bought Out")]
+    FractionNotBoughtOut,
+
+    /// NFT is rented out until its rental expires
+    #[error("Rental Active")]
+    RentalActive,
+
+    /// Private listing can only be bought by its allowed buyer
+    #[error("Buyer Not Allowed")]
+    BuyerNotAllowed,
+
+    /// Listing cannot be bought before its start time
+    #[error("Listing Not Started")]
+    ListingNotStarted,
+
+    /// Mint has no verified collection or creator
+    #[error("Unverified Collection")]
+    UnverifiedCollection,
+
+    /// The escrow index has no free slots
+    #[error("Index Full")]
+    IndexFull,
+
+    /// The listing price or bid is below the platform minimum
+    #[error("Below Minimum Price")]
+    BelowMinimumPrice,
+
+    /// Parameter change is scheduled sooner than the timelock allows
+    #[error("Timelock Too Short")]
+    TimelockTooShort,
+
+    /// Scheduled parameter change cannot execute before its slot
+    #[error("Timelock Not Elapsed")]
+    TimelockNotElapsed,
+
+    /// No parameter change is scheduled
+    #[error("No Pending Change")]
+    NoPendingChange,
+
+    /// The signer already has an open listing for this mint
+    #[error("Listing Already Exists")]
+    ListingAlreadyExists,
+
+    /// The signer already has an open bid on this mint
+    #[error("Bid Already Exists")]
+    BidAlreadyExists,
+
+    /// The quote mint is not in the payment-mint registry or is disabled
+    #[error("Payment Mint Not Allowed")]
+    PaymentMintNotAllowed,
+
+    /// The listing and the bid are quoted in different mints
+    #[error("Quote Mint Mismatch")]
+    QuoteMintMismatch,
+
+    /// The session has expired
+    #[error("Session Expired")]
+    SessionExpired,
+
+    /// The session does not allow this instruction
+    #[error("Session Not Allowed")]
+    SessionNotAllowed,
+
+    /// The bid cannot be withdrawn before its cooldown has elapsed
+    #[error("Bid Cooldown Active")]
+    BidCooldownActive,
+
+    /// The platform authority has paused this instruction
+    #[error("Instruction Paused")]
+    InstructionPaused,
+
+    /// The memo is longer than the program allows
+    #[error("Memo Too Long")]
+    MemoTooLong,
+
+    /// The signer is not the platform's registered automation thread
+    #[error("Invalid Thread Authority")]
+    InvalidThreadAuthority,
+
+    /// The bid has not been withdrawable long enough to count as stale
+    #[error("Bid Not Stale")]
+    BidNotStale,
+
+    /// A token transfer delivered less than the amount sent
+    #[error("Transfer Shortfall")]
+    TransferShortfall,
+
+    /// The listing has not been approved by its curator yet
+    #[error("Listing Pending Approval")]
+    ListingPendingApproval,
+
+    /// The crowdfund has not raised its target yet
+    #[error("Crowdfund Target Not Met")]
+    CrowdfundTargetNotMet,
+
+    /// The contribution would raise more than the crowdfund's target
+    #[error("Contribution Exceeds Target")]
+    ContributionExceedsTarget,
+
+    /// The crowdfund has already bought its NFT
+    #[error("Crowdfund Purchased")]
+    CrowdfundPurchased,
+
+    /// The crowdfund has not bought its NFT yet
+    #[error("Crowdfund Not Purchased")]
+    CrowdfundNotPurchased,
+
+    /// The listing no longer matches the one the crowdfund opened on
+    #[error("Listing Changed")]
+    ListingChanged,
+
+    /// The crowdfund's listing can still be bought at its price
+    #[error("Listing Still Available")]
+    ListingStillAvailable,
+
+    /// The listing's price is above what the buyer agreed to pay
+    #[error("Price Mismatch")]
+    PriceMismatch,
+
+    /// The mint is not a single token with no decimals
+    #[error("Not An NFT")]
+    NotAnNFT,
+
+    /// The seller's token account does not hold the token
+    #[error("Insufficient Token Balance")]
+    InsufficientTokenBalance,
+
+    /// The seller's token account is frozen
+    #[error("Token Account Frozen")]
+    TokenAccountFrozen,
+
+    /// The bid still leads an open auction
+    #[error("Bid Leads Auction")]
+    BidLeadsAuction,
+
+    /// Account is not owned by the expected program
+    #[error("Incorrect Owner")]
+    IncorrectOwner,
+
+    /// The Merkle proof does not lead to the expected root
+    #[error("Invalid Merkle Proof")]
+    InvalidMerkleProof,
+
+    /// A creator tip was sent for a mint without a verified creator
+    #[error("No Creator To Tip")]
+    NoCreatorToTip,
+
+    /// A listing's charity share is above the platform's cap
+    #[error("Charity Share Too High")]
+    CharityShareTooHigh,
+
+    /// The swap's taker has not escrowed its side
+    #[error("Swap Not Funded")]
+    SwapNotFunded,
+
+    /// The gift's reclaim time has not come yet
+    #[error("Gift Not Reclaimable")]
+    GiftNotReclaimable,
+
+    /// The price feed is stale, not trading or too uncertain to sell at
+    #[error("Unreliable Price")]
+    UnreliablePrice,
+
+    /// USD prices need the platform's price feed to be set
+    #[error("Price Feed Not Set")]
+    PriceFeedNotSet,
+
+    /// The bid has no expiry, or has not reached it or its withdrawal slot
+    #[error("Bid Not Expired")]
+    BidNotExpired,
+
+    /// The listing is sold and its token awaits delivery or its dispute window
+    #[error("Settlement Pending")]
+    SettlementPending,
+
+    /// The sale's dispute window has closed
+    #[error("Dispute Window Closed")]
+    DisputeWindowClosed,
+
+    /// The platform requires an attestation the trader did not present
+    #[error("Attestation Required")]
+    AttestationRequired,
+
+    /// The mint is a non-transferable Token-2022 mint
+    #[error("Non-Transferable Mint")]
+    NonTransferableMint,
 }
 
 impl From<NFTError> for ProgramError {
@@ -0,0 +1,755 @@
+//! NFT fractionalization.
+//!
+//! An owner locks an NFT in a program vault and receives `share_supply`
+//! zero-decimal share tokens minted by a PDA. Anyone can then buy the NFT
+//! out for `buyout_price` lamports. That payment stays in the fraction
+//! state account, and share holders burn their shares to redeem it pro
+//! rata. Before a buyout, anyone holding every share can burn them all to
+//! take the NFT back.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{attestation, error::NFTError, processor::Processor, state::{self, Discriminator, PlatformState}};
+
+pub const FRACTIONSIZE: usize = 138usize;
+
+pub fn find_fraction_state_address(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), b"Fraction", b"State"], program_id)
+}
+
+pub fn find_fraction_vault_address(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), b"Fraction", b"Vault"], program_id)
+}
+
+pub fn find_share_mint_address(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), b"Fraction", b"Shares"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FractionState {
+    pub is_initialized: bool,
+    pub curator: Pubkey,
+    pub mint: Pubkey,
+    pub share_mint: Pubkey,
+    pub share_supply: u64,
+    pub buyout_price: u64,
+    pub bought_out: bool,
+    /// Shares not yet redeemed against the buyout proceeds.
+    pub outstanding_shares: u64,
+    /// Buyout lamports not yet paid to share holders.
+    pub proceeds: u64,
+}
+
+impl Sealed for FractionState{}
+
+impl Discriminator for FractionState{
+    const DISCRIMINATOR: [u8; 8] = *b"fraction";
+}
+
+impl IsInitialized for FractionState{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for FractionState {
+    const LEN: usize = FRACTIONSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < FractionState::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, FractionState::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            curator,
+            mint,
+            share_mint,
+            share_supply,
+            buyout_price,
+            bought_out,
+            outstanding_shares,
+            proceeds,
+        ) = array_refs![src, 8, 1, 32, 32, 32, 8, 8, 1, 8, 8];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let bought_out = match bought_out {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(FractionState{
+            is_initialized,
+            curator: Pubkey::new_from_array(*curator),
+            mint: Pubkey::new_from_array(*mint),
+            share_mint: Pubkey::new_from_array(*share_mint),
+            share_supply: u64::from_be_bytes(*share_supply),
+            buyout_price: u64::from_be_bytes(*buyout_price),
+            bought_out,
+            outstanding_shares: u64::from_be_bytes(*outstanding_shares),
+            proceeds: u64::from_be_bytes(*proceeds),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, FractionState::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            curator_dst,
+            mint_dst,
+            share_mint_dst,
+            share_supply_dst,
+            buyout_price_dst,
+            bought_out_dst,
+            outstanding_shares_dst,
+            proceeds_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 32, 32, 8, 8, 1, 8, 8];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+
+        let FractionState {
+            is_initialized,
+            curator,
+            mint,
+            share_mint,
+            share_supply,
+            buyout_price,
+            bought_out,
+            outstanding_shares,
+            proceeds,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        curator_dst.copy_from_slice(curator.as_ref());
+        mint_dst.copy_from_slice(mint.as_ref());
+        share_mint_dst.copy_from_slice(share_mint.as_ref());
+        *share_supply_dst = share_supply.to_be_bytes();
+        *buyout_price_dst = buyout_price.to_be_bytes();
+        bought_out_dst[0] = *bought_out as u8;
+        *outstanding_shares_dst = outstanding_shares.to_be_bytes();
+        *proceeds_dst = proceeds.to_be_bytes();
+    }
+}
+
+/// Locks the NFT and mints `share_supply` shares into
+/// `owner_share_account`, which must already be allocated to the token
+/// program but not yet initialized.
+pub fn process_fractionalize(
+    accounts: &[AccountInfo],
+    share_supply: u64,
+    buyout_price: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if share_supply == 0 {
+        return Err(NFTError::InvalidInstructionData.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let owner_token_account_info = next_account_info(account_info_iter)?;
+    let mint_account_info = next_account_info(account_info_iter)?;
+    if !(mint_account_info.owner.eq(&spl_token::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let owner_token_account = spl_token::state::Account::unpack(&owner_token_account_info.data.borrow())?;
+    if !(owner_token_account.owner.eq(owner_info.key)) || !(owner_token_account.mint.eq(mint_account_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let fraction_state_account_info = next_account_info(account_info_iter)?;
+    let fraction_vault_account_info = next_account_info(account_info_iter)?;
+    let share_mint_account_info = next_account_info(account_info_iter)?;
+    let owner_share_account_info = next_account_info(account_info_iter)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+
+    let (share_mint_pubkey, state_nonce) = create_fraction(
+        owner_info,
+        mint_account_info,
+        fraction_state_account_info,
+        fraction_vault_account_info,
+        share_mint_account_info,
+        system_program_info,
+        token_program_info,
+        rent_account_info,
+        program_id,
+    )?;
+
+    let initialize_share_account_ix = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        owner_share_account_info.key,
+        &share_mint_pubkey,
+        owner_info.key
+    )?;
+    invoke(
+        &initialize_share_account_ix,
+        &[
+            token_program_info.clone(),
+            owner_share_account_info.clone(),
+            share_mint_account_info.clone(),
+            owner_info.clone(),
+            rent_account_info.clone()
+        ],
+    )?;
+
+    let transfer_token_ix = spl_token::instruction::transfer_checked(
+        &spl_token::id(),
+        owner_token_account_info.key,
+        mint_account_info.key,
+        fraction_vault_account_info.key,
+        owner_info.key,
+        &[
+            owner_info.key
+        ],
+        1,
+        0
+    )?;
+    invoke(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            owner_token_account_info.clone(),
+            mint_account_info.clone(),
+            fraction_vault_account_info.clone(),
+            owner_info.clone()
+        ],
+    )?;
+
+    mint_shares(
+        mint_account_info.key,
+        share_mint_account_info,
+        owner_share_account_info,
+        fraction_state_account_info,
+        token_program_info,
+        share_supply,
+        state_nonce,
+    )?;
+
+    FractionState::pack(
+        FractionState{
+            is_initialized: true,
+            curator: *owner_info.key,
+            mint: *mint_account_info.key,
+            share_mint: share_mint_pubkey,
+            share_supply,
+            buyout_price,
+            bought_out: false,
+            outstanding_shares: share_supply,
+            proceeds: 0,
+        },
+        &mut fraction_state_account_info.data.borrow_mut()
+    )?;
+
+    Ok(())
+}
+
+/// Buys the whole NFT for `buyout_price` plus the platform taker fee. The
+/// price is held for share holders to redeem.
+pub fn process_buyout(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let buyer_info = next_account_info(account_info_iter)?;
+    if !buyer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let buyer_token_account_info = next_account_info(account_info_iter)?;
+
+    let fraction_state_account_info = next_account_info(account_info_iter)?;
+    let mut fraction_state = load_fraction_state(fraction_state_account_info, program_id)?;
+    if fraction_state.bought_out {
+        return Err(NFTError::FractionBoughtOut.into());
+    }
+
+    let fraction_vault_account_info = next_account_info(account_info_iter)?;
+    let (fraction_vault_pubkey, _) = find_fraction_vault_address(program_id, &fraction_state.mint);
+    if !(fraction_vault_account_info.key.eq(&fraction_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let curator_info = next_account_info(account_info_iter)?;
+    if !(curator_info.key.eq(&fraction_state.curator)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    attestation::check_not_required(&state_info)?;
+
+    let fee_recipient_info = next_account_info(account_info_iter)?;
+    if !state_info.fee_recipient.eq(fee_recipient_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let price = fraction_state.buyout_price;
+    let platform_fee = Processor::calculate_fee(price, state_info.taker_fee_bps)?;
+    let payouts = vec![(fraction_state_account_info, price), (fee_recipient_info, platform_fee)];
+    for (recipient_info, lamports) in payouts {
+        if lamports == 0 {
+            continue;
+        }
+        invoke(
+            &system_instruction::transfer(buyer_info.key, recipient_info.key, lamports),
+            &[
+                buyer_info.clone(),
+                recipient_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
+    release_nft(
+        &fraction_state,
+        fraction_state_account_info,
+        fraction_vault_account_info,
+        buyer_token_account_info,
+        curator_info,
+        token_program_info,
+        program_id,
+    )?;
+
+    fraction_state.bought_out = true;
+    fraction_state.proceeds = price;
+    FractionState::pack(fraction_state, &mut fraction_state_account_info.data.borrow_mut())?;
+
+    state_info.sale_count = state_info.sale_count
+        .checked_add(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.total_volume = state_info.total_volume
+        .checked_add(price)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.total_fees = state_info.total_fees
+        .checked_add(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+/// Burns `amount` shares for their pro-rata part of the buyout proceeds.
+pub fn process_redeem_shares(
+    accounts: &[AccountInfo],
+    amount: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let holder_info = next_account_info(account_info_iter)?;
+    if !holder_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let holder_share_account_info = next_account_info(account_info_iter)?;
+    let share_mint_account_info = next_account_info(account_info_iter)?;
+
+    let fraction_state_account_info = next_account_info(account_info_iter)?;
+    let mut fraction_state = load_fraction_state(fraction_state_account_info, program_id)?;
+    if !fraction_state.bought_out {
+        return Err(NFTError::FractionNotBoughtOut.into());
+    }
+    if !(share_mint_account_info.key.eq(&fraction_state.share_mint)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    burn_shares(holder_info, holder_share_account_info, share_mint_account_info, token_program_info, amount)?;
+
+    let payout = (fraction_state.proceeds as u128)
+        .checked_mul(amount as u128)
+        .and_then(|scaled| scaled.checked_div(fraction_state.outstanding_shares as u128))
+        .ok_or(NFTError::NumericalOverflow)? as u64;
+    fraction_state.outstanding_shares = fraction_state.outstanding_shares
+        .checked_sub(amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    fraction_state.proceeds = fraction_state.proceeds
+        .checked_sub(payout)
+        .ok_or(NFTError::NumericalOverflow)?;
+    FractionState::pack(fraction_state, &mut fraction_state_account_info.data.borrow_mut())?;
+
+    let fraction_state_lamports = fraction_state_account_info.lamports()
+        .checked_sub(payout)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let holder_lamports = holder_info.lamports()
+        .checked_add(payout)
+        .ok_or(NFTError::NumericalOverflow)?;
+    **fraction_state_account_info.try_borrow_mut_lamports()? = fraction_state_lamports;
+    **holder_info.try_borrow_mut_lamports()? = holder_lamports;
+
+    Ok(())
+}
+
+/// Burns the entire share supply and returns the NFT to the holder.
+pub fn process_redeem_nft(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let holder_info = next_account_info(account_info_iter)?;
+    if !holder_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let holder_share_account_info = next_account_info(account_info_iter)?;
+    let share_mint_account_info = next_account_info(account_info_iter)?;
+    let holder_token_account_info = next_account_info(account_info_iter)?;
+
+    let fraction_state_account_info = next_account_info(account_info_iter)?;
+    let mut fraction_state = load_fraction_state(fraction_state_account_info, program_id)?;
+    if fraction_state.bought_out {
+        return Err(NFTError::FractionBoughtOut.into());
+    }
+    if !(share_mint_account_info.key.eq(&fraction_state.share_mint)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let fraction_vault_account_info = next_account_info(account_info_iter)?;
+    let (fraction_vault_pubkey, _) = find_fraction_vault_address(program_id, &fraction_state.mint);
+    if !(fraction_vault_account_info.key.eq(&fraction_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    burn_shares(holder_info, holder_share_account_info, share_mint_account_info, token_program_info, fraction_state.share_supply)?;
+
+    release_nft(
+        &fraction_state,
+        fraction_state_account_info,
+        fraction_vault_account_info,
+        holder_token_account_info,
+        holder_info,
+        token_program_info,
+        program_id,
+    )?;
+
+    fraction_state.outstanding_shares = 0;
+    FractionState::pack(fraction_state, &mut fraction_state_account_info.data.borrow_mut())?;
+
+    Processor::close_account(fraction_state_account_info, holder_info, program_id)?;
+
+    Ok(())
+}
+
+/// Creates the fraction state, the vault that locks `mint_account_info`'s
+/// NFT and the share mint, all funded by `payer_info`. Returns the share
+/// mint and the fraction state's bump, which signs for the share mint.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_fraction<'a>(
+    payer_info: &AccountInfo<'a>,
+    mint_account_info: &AccountInfo<'a>,
+    fraction_state_account_info: &AccountInfo<'a>,
+    fraction_vault_account_info: &AccountInfo<'a>,
+    share_mint_account_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    rent_account_info: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> Result<(Pubkey, u8), ProgramError> {
+    let rent = &Rent::get()?;
+
+    let (fraction_state_pubkey, state_nonce) = find_fraction_state_address(program_id, mint_account_info.key);
+    if !(fraction_state_account_info.key.eq(&fraction_state_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_state_account_ix = system_instruction::create_account(
+        payer_info.key,
+        &fraction_state_pubkey,
+        rent.minimum_balance(FRACTIONSIZE),
+        FRACTIONSIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived fraction state account");
+    invoke_signed(
+        &create_state_account_ix,
+        &[
+            payer_info.clone(),
+            fraction_state_account_info.clone(),
+            system_program_info.clone(),
+        ],
+        &[&[
+            mint_account_info.key.as_ref(),
+            &b"Fraction"[..],
+            &b"State"[..],
+            &[state_nonce]
+        ]],
+    )?;
+
+    let (fraction_vault_pubkey, vault_nonce) = find_fraction_vault_address(program_id, mint_account_info.key);
+    if !(fraction_vault_account_info.key.eq(&fraction_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_vault_account_ix = system_instruction::create_account(
+        payer_info.key,
+        &fraction_vault_pubkey,
+        rent.minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN as u64,
+        &spl_token::id());
+    invoke_signed(
+        &create_vault_account_ix,
+        &[
+            payer_info.clone(),
+            fraction_vault_account_info.clone(),
+            system_program_info.clone(),
+            token_program_info.clone(),
+        ],
+        &[&[
+            mint_account_info.key.as_ref(),
+            &b"Fraction"[..],
+            &b"Vault"[..],
+            &[vault_nonce]
+        ]],
+    )?;
+    let initialize_vault_account_ix = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &fraction_vault_pubkey,
+        mint_account_info.key,
+        &fraction_state_pubkey
+    )?;
+    invoke(
+        &initialize_vault_account_ix,
+        &[
+            token_program_info.clone(),
+            fraction_vault_account_info.clone(),
+            mint_account_info.clone(),
+            fraction_state_account_info.clone(),
+            rent_account_info.clone()
+        ],
+    )?;
+
+    let (share_mint_pubkey, share_mint_nonce) = find_share_mint_address(program_id, mint_account_info.key);
+    if !(share_mint_account_info.key.eq(&share_mint_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_share_mint_ix = system_instruction::create_account(
+        payer_info.key,
+        &share_mint_pubkey,
+        rent.minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN as u64,
+        &spl_token::id());
+    invoke_signed(
+        &create_share_mint_ix,
+        &[
+            payer_info.clone(),
+            share_mint_account_info.clone(),
+            system_program_info.clone(),
+            token_program_info.clone(),
+        ],
+        &[&[
+            mint_account_info.key.as_ref(),
+            &b"Fraction"[..],
+            &b"Shares"[..],
+            &[share_mint_nonce]
+        ]],
+    )?;
+    let initialize_share_mint_ix = spl_token::instruction::initialize_mint(
+        &spl_token::id(),
+        &share_mint_pubkey,
+        &fraction_state_pubkey,
+        None,
+        0
+    )?;
+    invoke(
+        &initialize_share_mint_ix,
+        &[
+            token_program_info.clone(),
+            share_mint_account_info.clone(),
+            rent_account_info.clone()
+        ],
+    )?;
+
+    Ok((share_mint_pubkey, state_nonce))
+}
+
+/// Mints `amount` shares of the fraction of `mint` into `destination_info`.
+pub(crate) fn mint_shares<'a>(
+    mint: &Pubkey,
+    share_mint_account_info: &AccountInfo<'a>,
+    destination_info: &AccountInfo<'a>,
+    fraction_state_account_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    amount: u64,
+    state_nonce: u8,
+) -> ProgramResult {
+    let mint_shares_ix = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        share_mint_account_info.key,
+        destination_info.key,
+        fraction_state_account_info.key,
+        &[fraction_state_account_info.key],
+        amount
+    )?;
+    invoke_signed(
+        &mint_shares_ix,
+        &[
+            token_program_info.clone(),
+            share_mint_account_info.clone(),
+            destination_info.clone(),
+            fraction_state_account_info.clone()
+        ],
+        &[&[
+            mint.as_ref(),
+            &b"Fraction"[..],
+            &b"State"[..],
+            &[state_nonce]
+        ]],
+    )
+}
+
+pub(crate) fn load_fraction_state(fraction_state_account_info: &AccountInfo, program_id: &Pubkey) -> Result<FractionState, ProgramError> {
+    Processor::assert_owned_by(fraction_state_account_info, program_id)?;
+    let fraction_state = FractionState::unpack(&fraction_state_account_info.data.borrow())?;
+    let (fraction_state_pubkey, _) = find_fraction_state_address(program_id, &fraction_state.mint);
+    if !(fraction_state_account_info.key.eq(&fraction_state_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(fraction_state)
+}
+
+fn burn_shares<'a>(
+    holder_info: &AccountInfo<'a>,
+    holder_share_account_info: &AccountInfo<'a>,
+    share_mint_account_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    amount: u64,
+) -> ProgramResult {
+    let burn_ix = spl_token::instruction::burn(
+        &spl_token::id(),
+        holder_share_account_info.key,
+        share_mint_account_info.key,
+        holder_info.key,
+        &[holder_info.key],
+        amount
+    )?;
+    invoke(
+        &burn_ix,
+        &[
+            token_program_info.clone(),
+            holder_share_account_info.clone(),
+            share_mint_account_info.clone(),
+            holder_info.clone()
+        ],
+    )
+}
+
+/// Moves the locked NFT into `token_account_info` and closes the vault,
+/// returning its rent to `recipient_info`.
+fn release_nft<'a>(
+    fraction_state: &FractionState,
+    fraction_state_account_info: &AccountInfo<'a>,
+    fraction_vault_account_info: &AccountInfo<'a>,
+    token_account_info: &AccountInfo<'a>,
+    recipient_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (fraction_state_pubkey, state_nonce) = find_fraction_state_address(program_id, &fraction_state.mint);
+    let state_seeds: &[&[u8]] = &[
+        fraction_state.mint.as_ref(),
+        &b"Fraction"[..],
+        &b"State"[..],
+        &[state_nonce]
+    ];
+
+    let transfer_token_ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        fraction_vault_account_info.key,
+        token_account_info.key,
+        &fraction_state_pubkey,
+        &[&fraction_state_pubkey],
+        1
+    )?;
+    invoke_signed(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            fraction_vault_account_info.clone(),
+            token_account_info.clone(),
+            fraction_state_account_info.clone()
+        ],
+        &[state_seeds],
+    )?;
+
+    let close_ix = spl_token::instruction::close_account(
+        &spl_token::id(),
+        fraction_vault_account_info.key,
+        recipient_info.key,
+        &fraction_state_pubkey,
+        &[&fraction_state_pubkey]
+    )?;
+    invoke_signed(
+        &close_ix,
+        &[
+            token_program_info.clone(),
+            fraction_vault_account_info.clone(),
+            recipient_info.clone(),
+            fraction_state_account_info.clone(),
+        ],
+        &[state_seeds],
+    )
+}
@@ -0,0 +1,273 @@
+//! Secondary indexes over open escrows.
+//!
+//! An owner index holds the list states a wallet has open and a mint index
+//! holds the bid states open on a mint, so wallets can answer "what do I
+//! have listed?" and "what offers exist on my NFT?" with one account lookup
+//! instead of a program-wide scan. Indexes are optional and the trading
+//! instructions never touch them. Anyone can `SyncIndex` one by passing
+//! escrow accounts: live escrows that belong in the index are added and
+//! closed ones are dropped.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{error::NFTError, processor::Processor, state::{self, BidEscrowState, Discriminator, ListEscrowState}};
+
+pub const INDEX_CAPACITY: usize = 16usize;
+pub const INDEXSIZE: usize = 43usize + INDEX_CAPACITY * 32;
+
+pub fn find_owner_index_address(program_id: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[owner.as_ref(), b"Owner", b"Index"], program_id)
+}
+
+pub fn find_mint_index_address(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), b"Mint", b"Index"], program_id)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndexKind {
+    /// List states opened by a wallet.
+    Owner,
+    /// Bid states open on a mint.
+    Mint,
+}
+
+impl IndexKind {
+    fn seed(&self) -> &'static [u8] {
+        match self {
+            IndexKind::Owner => b"Owner",
+            IndexKind::Mint => b"Mint",
+        }
+    }
+
+    pub fn find_address(&self, program_id: &Pubkey, key: &Pubkey) -> (Pubkey, u8) {
+        match self {
+            IndexKind::Owner => find_owner_index_address(program_id, key),
+            IndexKind::Mint => find_mint_index_address(program_id, key),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowIndex {
+    pub is_initialized: bool,
+    pub kind: IndexKind,
+    pub key: Pubkey,
+    pub entries: Vec<Pubkey>,
+}
+
+impl Sealed for EscrowIndex{}
+
+impl Discriminator for EscrowIndex{
+    const DISCRIMINATOR: [u8; 8] = *b"index\0\0\0";
+}
+
+impl IsInitialized for EscrowIndex{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for EscrowIndex {
+    const LEN: usize = INDEXSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < EscrowIndex::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, EscrowIndex::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            kind,
+            key,
+            count,
+            entries,
+        ) = array_refs![src, 8, 1, 1, 32, 1, INDEX_CAPACITY * 32];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let kind = match kind {
+            [0] => IndexKind::Owner,
+            [1] => IndexKind::Mint,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let count = count[0] as usize;
+        if count > INDEX_CAPACITY {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(EscrowIndex{
+            is_initialized,
+            kind,
+            key: Pubkey::new_from_array(*key),
+            entries: entries
+                .chunks_exact(32)
+                .take(count)
+                .map(|chunk| Pubkey::new_from_array(*array_ref![chunk, 0, 32]))
+                .collect(),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, EscrowIndex::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            kind_dst,
+            key_dst,
+            count_dst,
+            entries_dst,
+        ) = mut_array_refs![dst, 8, 1, 1, 32, 1, INDEX_CAPACITY * 32];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+        is_initialized_dst[0] = self.is_initialized as u8;
+        kind_dst[0] = self.kind as u8;
+        key_dst.copy_from_slice(self.key.as_ref());
+        count_dst[0] = self.entries.len() as u8;
+        for byte in entries_dst.iter_mut() {
+            *byte = 0;
+        }
+        for (entry, chunk) in self.entries.iter().zip(entries_dst.chunks_exact_mut(32)) {
+            chunk.copy_from_slice(entry.as_ref());
+        }
+    }
+}
+
+pub fn process_create_index(
+    accounts: &[AccountInfo],
+    kind: IndexKind,
+    key: Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let payer_info = next_account_info(account_info_iter)?;
+    if !payer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let index_account_info = next_account_info(account_info_iter)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Processor::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::get()?;
+
+    let (index_pubkey, nonce) = kind.find_address(program_id, &key);
+    if !(index_account_info.key.eq(&index_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_index_account_ix = system_instruction::create_account(
+        payer_info.key,
+        &index_pubkey,
+        rent.minimum_balance(INDEXSIZE),
+        INDEXSIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived index account");
+    invoke_signed(
+        &create_index_account_ix,
+        &[
+            payer_info.clone(),
+            index_account_info.clone(),
+            system_program_info.clone(),
+            program_info.clone(),
+        ],
+        &[&[
+            key.as_ref(),
+            kind.seed(),
+            &b"Index"[..],
+            &[nonce]
+        ]],
+    )?;
+
+    EscrowIndex::pack(
+        EscrowIndex{
+            is_initialized: true,
+            kind,
+            key,
+            entries: Vec::new(),
+        },
+        &mut index_account_info.data.borrow_mut()
+    )?;
+
+    Ok(())
+}
+
+pub fn process_sync_index(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let index_account_info = next_account_info(account_info_iter)?;
+    Processor::assert_owned_by(index_account_info, program_id)?;
+    let mut index = EscrowIndex::unpack(&index_account_info.data.borrow())?;
+    let (index_pubkey, _) = index.kind.find_address(program_id, &index.key);
+    if !(index_account_info.key.eq(&index_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    for escrow_info in account_info_iter {
+        if escrow_info.data_is_empty() {
+            index.entries.retain(|entry| !entry.eq(escrow_info.key));
+            continue;
+        }
+        Processor::assert_owned_by(escrow_info, program_id)?;
+
+        let live = match index.kind {
+            IndexKind::Owner => {
+                let list_state = ListEscrowState::unpack_unchecked(&escrow_info.data.borrow())?;
+                let (list_state_pubkey, _) = state::find_list_state_address(program_id, &list_state.mint, &list_state.lister);
+                if !(list_state.lister.eq(&index.key) && escrow_info.key.eq(&list_state_pubkey)) {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                !list_state.success
+            }
+            IndexKind::Mint => {
+                let bid_state = BidEscrowState::unpack_unchecked(&escrow_info.data.borrow())?;
+                let (bid_state_pubkey, _) = state::find_bid_state_address(program_id, &bid_state.mint, &bid_state.bidder);
+                if !(bid_state.mint.eq(&index.key) && escrow_info.key.eq(&bid_state_pubkey)) {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                true
+            }
+        };
+
+        let indexed = index.entries.contains(escrow_info.key);
+        if live && !indexed {
+            if index.entries.len() == INDEX_CAPACITY {
+                return Err(NFTError::IndexFull.into());
+            }
+            index.entries.push(*escrow_info.key);
+        } else if !live && indexed {
+            index.entries.retain(|entry| !entry.eq(escrow_info.key));
+        }
+    }
+
+    EscrowIndex::pack(index, &mut index_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
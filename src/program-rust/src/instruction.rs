@@ -1,17 +1,28 @@
 use std::convert::{TryInto};
+use std::mem::size_of;
 use solana_program::{
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
     program_error::ProgramError,
     pubkey::Pubkey,
+    system_program,
+    sysvar,
     msg
 };
 use arrayref::{array_ref};
-use crate::error::NFTError;
+use crate::{attestation, core_listing, crowdfund, error::NFTError, fraction, index, leaderboard, memo, merkle, metadata, mpl_core, otc, payment, pool, raffle, rental, rewards, session, staking, state, swap, trait_offer};
+
+/// Leads version 2 instruction data: this byte, the tag, then the
+/// instruction's fields. Data starting with anything else is version 1, a
+/// bare tag and its fields; tags stay below 0xF0 so the two never collide.
+pub const VERSION_2: u8 = 0xF2;
 
 #[repr(C)]
 #[derive(Debug, PartialEq)]
 pub struct Initialize {
     pub authority: Pubkey,
-    pub platform_fee: u64
+    pub maker_bps: u64,
+    pub taker_bps: u64,
 }
 
 #[repr(C)]
@@ -22,35 +33,123 @@ pub struct ChangeAuthority {
 
 #[repr(C)]
 #[derive(Debug, PartialEq)]
-pub struct ChangeFee {
-    pub platform_fee: u64
+pub struct ChangeFees {
+    pub maker_bps: u64,
+    pub taker_bps: u64,
 }
 
 #[repr(C)]
 #[derive(Debug, PartialEq)]
 pub struct List {
     pub amount: u64,
+    pub auction: bool,
+    pub allowed_buyer: Pubkey,
+    pub starts_at: i64,
+    /// Royalty, in basis points, to pay instead of the metadata's when it
+    /// is higher; capped by the platform's `max_royalty_bps`.
+    pub royalty_bps_override: u16,
+    /// Root of a `merkle` tree of the wallets allowed to buy; zeroed to
+    /// leave the listing open.
+    pub allowlist_root: [u8; 32],
+    /// Receives `charity_bps` of the lister's proceeds; the default key
+    /// for no charity split.
+    pub charity: Pubkey,
+    /// Capped by the platform's `max_charity_bps`.
+    pub charity_bps: u16,
+    /// Frontend to credit with the listing; the default key for none.
+    pub marketplace: Pubkey,
+    /// Asking price in millionths of a dollar instead of `amount`, which
+    /// is then zero; needs the platform's price feed set.
+    pub price_usd: u64,
+    /// Slots an accepted bid is held for before the sale can be finalized,
+    /// up to `state::MAX_SETTLEMENT_DELAY_SLOTS`; zero settles at once.
+    pub settlement_delay_slots: u64,
+    /// Can reverse a held sale besides the platform authority; the default
+    /// key for none.
+    pub arbiter: Pubkey,
+    /// `state::VISIBILITY_PUBLIC` or `state::VISIBILITY_UNLISTED`.
+    pub visibility: u8,
+}
+
+/// Bump seeds of the escrow accounts an instruction checks, sent so the
+/// program can verify each address with `create_program_address` instead
+/// of searching for it. Zero leaves that address to be derived on-chain.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bumps {
+    pub platform_state: u8,
+    pub list_state: u8,
+    /// Bump of the list vault under the associated token account program.
+    pub list_vault: u8,
+    pub bid_state: u8,
+    pub bid_vault: u8,
+}
+
+impl Bumps {
+    pub const LEN: usize = 5;
+
+    /// Finds the bumps of the platform state and of the escrows on `mint`
+    /// held for `lister` and `bidder`, where given.
+    pub fn find(program_id: &Pubkey, mint: &Pubkey, lister: Option<&Pubkey>, bidder: Option<&Pubkey>) -> Self {
+        let mut bumps = Bumps {
+            platform_state: state::find_platform_state_address(program_id).1,
+            ..Bumps::default()
+        };
+        if let Some(lister) = lister {
+            bumps.list_state = state::find_list_state_address(program_id, mint, lister).1;
+            bumps.list_vault = state::find_list_vault_address(program_id, mint, lister).1;
+        }
+        if let Some(bidder) = bidder {
+            bumps.bid_state = state::find_bid_state_address(program_id, mint, bidder).1;
+            bumps.bid_vault = state::find_bid_vault_address(program_id, mint, bidder).1;
+        }
+        bumps
+    }
+
+    fn pack(&self) -> [u8; Bumps::LEN] {
+        [self.platform_state, self.list_state, self.list_vault, self.bid_state, self.bid_vault]
+    }
 }
 
 #[repr(C)]
 #[derive(Debug, PartialEq)]
 pub struct DeList {
+    pub bumps: Bumps,
 }
 
 #[repr(C)]
 #[derive(Debug, PartialEq)]
 pub struct Bid {
-    pub amount: u64
+    pub amount: u64,
+    pub wrapped: bool,
+    /// Number of trailing accounts forming an `ExecuteSale` account list to
+    /// settle against; zero escrows the bid without matching.
+    pub match_accounts: u8,
+    /// Extra lamports sent from the bidder to the mint's first verified
+    /// creator when the bid settles against the listing straight away.
+    pub tip_lamports: u64,
+    /// Frontend to credit with the bid; the default key for none.
+    pub marketplace: Pubkey,
+    /// Proof that the bidder is on the allowlist of the listing matched or
+    /// bid on; empty for listings without one.
+    pub proof: Vec<[u8; 32]>,
 }
 
 #[repr(C)]
 #[derive(Debug, PartialEq)]
 pub struct WithdrawBid {
+    pub bumps: Bumps,
 }
 
 #[repr(C)]
 #[derive(Debug, PartialEq)]
 pub struct AcceptBid {
+    /// Client memo recorded with the sale; may be empty.
+    pub memo: Vec<u8>,
+    pub bumps: Bumps,
+    /// Least bid amount the lister accepts, or 0 for any. Like `bumps`, it
+    /// is only carried in version 2 data.
+    pub expected_amount: u64,
 }
 
 #[repr(C)]
@@ -65,97 +164,5238 @@ pub struct RefundUser {
 
 #[repr(C)]
 #[derive(Debug, PartialEq)]
-pub enum NFTInstruction {
-    Initialize(Initialize),
-    ChangeAuthority(ChangeAuthority),
-    ChangeFee(ChangeFee),
-    List(List),
-    DeList(DeList),
-    Bid(Bid),
-    WithdrawBid(WithdrawBid),
-    AcceptBid(AcceptBid),
-    WithdrawNFTOnSuccess(WithdrawNFTOnSuccess),
-    RefundUser(RefundUser)
+pub struct ChangeReferralFee {
+    pub referral_fee_bps: u64
 }
 
-impl NFTInstruction {
-    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        let (&tag, rest) = input.split_first().ok_or(NFTError::InvalidInstruction)?;
-        match tag {
-            0 => {
-                if rest.len() < 32usize {
-                    return Err(NFTError::InvalidAuthority.into());
-                }
-                let (authority_bytes_slice, rest) = rest.split_at(32);
-                if rest.len() == 8usize {
-                    let platform_fee = Self::unpack_amount(rest)?;
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetCuratedMode {
+    pub curated: bool
+}
 
-                    let authority_bytes = array_ref![authority_bytes_slice, 0 ,32];
-                    return Ok(Self::Initialize(Initialize{
-                        authority: Pubkey::new_from_array(*authority_bytes),
-                        platform_fee: platform_fee,
-                    }));
-                }
-                Err(NFTError::InvalidPlatformFee.into())
-            }
-            1 => {
-                if rest.len() == 32usize {
-                    let authority_bytes = array_ref![rest, 0 ,32];
-                    return Ok(Self::ChangeAuthority(ChangeAuthority{
-                        authority: Pubkey::new_from_array(*authority_bytes),
-                    }));
-                }
-                Err(NFTError::InvalidAuthority.into())
-            }
-            2 => {
-                if rest.len() == 8usize {
-                    return Ok(Self::ChangeFee(ChangeFee{
-                        platform_fee: Self::unpack_amount(rest)?,
-                    }));
-                }
-                return Err(NFTError::InvalidInstructionData.into());
-            }
-            3 => {
-                if rest.len() == 8usize {
-                    return Ok(Self::List(List{
-                        amount: Self::unpack_amount(rest)?,
-                    }));
-                }
-                return Err(NFTError::InvalidInstructionData.into());
-            }
-            4 => {
-                Ok(Self::DeList(DeList{}))
-            }
-            5 => {
-                if rest.len() == 8usize {
-                    return Ok(Self::Bid(Bid{
-                        amount: Self::unpack_amount(rest)?,
-                    }));
-                }
-                return Err(NFTError::InvalidInstructionData.into());
-            }
-            6 => {
-                Ok(Self::WithdrawBid(WithdrawBid{}))
-            }
-            7 => {
-                Ok(Self::AcceptBid(AcceptBid{}))
-            }
-            8 => {
-                Ok(Self::WithdrawNFTOnSuccess(WithdrawNFTOnSuccess{}))
-            }
-            9 => {
-                Ok(Self::RefundUser(RefundUser{}))
-            }
-            _ => Err(NFTError::InvalidInstruction.into()),
-        }
-    }
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct AllowCollection {
+    pub collection: Pubkey
+}
 
-    fn unpack_amount(input: &[u8]) -> Result<u64, ProgramError> {
-        let amount = input
-            .get(..8)
-            .and_then(|slice| slice.try_into().ok())
-            .map(u64::from_be_bytes)
-            .ok_or(NFTError::FailedToUnpackU64)?;
-        Ok(amount)
-    }
-}
\ No newline at end of file
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct RevokeCollection {
+    pub collection: Pubkey
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CreateUserStats {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct AuctionBid {
+    pub amount: u64,
+    /// Proof that the bidder is on the auction's allowlist; empty for
+    /// auctions without one.
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct FillOrder {
+    pub price: u64,
+    pub expiry: i64,
+    pub nonce: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CancelOrder {
+    pub nonce: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CreateOrderBook {
+    pub collection: Pubkey,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct UpdateOrderBook {
+    pub side: state::BookSide,
+    pub maker: Pubkey,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CreatePool {
+    pub collection: Pubkey,
+    pub curve: pool::Curve,
+    pub spot_price: u64,
+    pub delta: u64,
+    pub fee_bps: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct DepositPoolNFT {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct DepositPoolSol {
+    pub amount: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct WithdrawPoolNFT {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct WithdrawPoolSol {
+    pub amount: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct BuyFromPool {
+    pub max_price: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SellToPool {
+    pub min_price: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct InitStakePool {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct Stake {
+    pub amount: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct Unstake {
+    pub amount: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ClaimRewards {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct FundStakingRewards {
+    pub amount: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CreateRaffle {
+    pub ticket_price: u64,
+    pub total_tickets: u64,
+    pub end_time: i64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct BuyRaffleTicket {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct DrawRaffle {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ClaimRaffle {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct Fractionalize {
+    pub share_supply: u64,
+    pub buyout_price: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct Buyout {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct RedeemShares {
+    pub amount: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct RedeemNFT {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ListForRent {
+    pub price: u64,
+    pub duration_slots: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct Rent {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ReclaimRental {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetFeeDiscount {
+    pub discount_mint: Pubkey,
+    pub threshold: u64,
+    pub discount_bps: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CreateOtc {
+    pub price: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct AcceptOtc {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CancelOtc {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ListMany {
+    pub amounts: Vec<u64>,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct DelistMany {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct BatchExecuteSale {
+    pub account_counts: Vec<u8>,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ChangeFeeRecipient {
+    pub fee_recipient: Pubkey,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetStrictMode {
+    pub strict: bool,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetMinimums {
+    pub min_listing_lamports: u64,
+    pub min_bid_lamports: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ScheduleParamChange {
+    pub change: state::ParamChange,
+    pub execute_slot: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ExecuteParamChange {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ExecuteSale {
+    /// Client memo recorded with the sale; may be empty.
+    pub memo: Vec<u8>,
+    pub bumps: Bumps,
+    /// Least bid amount the lister accepts, or 0 for any. Like `bumps`, it
+    /// is only carried in version 2 data.
+    pub expected_amount: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ListCore {
+    pub price: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct DelistCore {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct BuyCore {
+    /// Most the buyer pays for the asset, before fees.
+    pub max_price: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetPaymentMint {
+    pub enabled: bool,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ListForToken {
+    pub price: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CancelTokenListing {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct BidWithToken {
+    pub amount: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct WithdrawTokenBid {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct AcceptTokenBid {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CreateSession {
+    pub session_key: Pubkey,
+    pub expires_at: i64,
+    pub allowed: u8,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct RevokeSession {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetBidCooldown {
+    pub slots: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetPaused {
+    pub paused: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetAutomationThread {
+    pub thread: Pubkey,
+    pub stale_bid_slots: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ThreadRefundBid {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct MintAndList {
+    pub price: u64,
+    /// Token Metadata to create for the new mint; `None` skips it.
+    pub metadata: Option<NewMetadata>,
+}
+
+/// Metadata `MintAndList` creates, with the seller as update authority and
+/// sole verified creator.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetMaxRoyalty {
+    pub max_royalty_bps: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetCurator {
+    pub curator: Pubkey,
+    pub curator_fee_bps: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ApproveListing {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CreateCrowdfund {
+    pub buyout_price: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct Contribute {
+    pub amount: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ExecuteCrowdfund {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ClaimCrowdfundShares {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct RefundContribution {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SweepBuy {
+    pub max_spend: u64,
+    pub purchases: Vec<SweepPurchase>,
+}
+
+/// One purchase in a `SweepBuy`.
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SweepPurchase {
+    pub account_count: u8,
+    /// How many of the trailing accounts are the matched sale's.
+    pub match_accounts: u8,
+    /// Most the buyer pays for the listing, before fees.
+    pub max_price: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct MakeTraitOffer {
+    pub amount: u64,
+    pub root: [u8; 32],
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct AcceptTraitOffer {
+    /// Sibling hashes from the seller's mint up to the offer's root.
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CancelTraitOffer {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetRewards {
+    pub buyer_reward: u64,
+    pub seller_reward: u64,
+    pub epoch_cap: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetMaxCharity {
+    pub max_charity_bps: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetMarketplaceFee {
+    pub marketplace_fee_bps: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CreateSwap {
+    pub id: u64,
+    pub maker_lamports: u64,
+    pub taker_lamports: u64,
+    /// NFTs the taker has to escrow, at most `swap::SWAP_MAX_ASSETS`.
+    pub taker_mints: Vec<Pubkey>,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct DepositSwap {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ExecuteSwap {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CancelSwap {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct Gift {
+    pub recipient: Pubkey,
+    /// Unix timestamp from which the recipient can claim the gift.
+    pub reveal_at: i64,
+    /// Unix timestamp from which the sender can take back an unclaimed
+    /// gift; later than `reveal_at`.
+    pub reclaimable_at: i64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ClaimGift {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetBidExpiry {
+    /// Unix timestamp from which anyone can refund the bid; zero for never.
+    pub expires_at: i64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ExpireAndRefund {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct ReverseSettlement {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct FinalizeSettlement {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetPriceFeed {
+    /// Pyth SOL/USD price account; the default key turns USD pricing off.
+    pub price_feed: Pubkey,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetAttestation {
+    /// Program whose attestations listers and bidders present.
+    pub attestor: Pubkey,
+    /// Whether `List` and `Bid` need one.
+    pub required: bool,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetVisibility {
+    /// `state::VISIBILITY_PUBLIC` or `state::VISIBILITY_UNLISTED`.
+    pub visibility: u8,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CreateLeaderboard {
+    pub season: u64,
+    pub starts_at: i64,
+    pub ends_at: i64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CreateCollectionStats {
+    pub collection: Pubkey,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct CreateIndex {
+    pub kind: index::IndexKind,
+    pub key: Pubkey,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SyncIndex {
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub enum NFTInstruction {
+    Initialize(Initialize),
+    ChangeAuthority(ChangeAuthority),
+    ChangeFees(ChangeFees),
+    List(List),
+    DeList(DeList),
+    Bid(Bid),
+    WithdrawBid(WithdrawBid),
+    AcceptBid(AcceptBid),
+    WithdrawNFTOnSuccess(WithdrawNFTOnSuccess),
+    RefundUser(RefundUser),
+    ChangeReferralFee(ChangeReferralFee),
+    SetCuratedMode(SetCuratedMode),
+    AllowCollection(AllowCollection),
+    RevokeCollection(RevokeCollection),
+    CreateUserStats(CreateUserStats),
+    AuctionBid(AuctionBid),
+    FillOrder(FillOrder),
+    CancelOrder(CancelOrder),
+    CreateOrderBook(CreateOrderBook),
+    UpdateOrderBook(UpdateOrderBook),
+    CreatePool(CreatePool),
+    DepositPoolNFT(DepositPoolNFT),
+    DepositPoolSol(DepositPoolSol),
+    WithdrawPoolNFT(WithdrawPoolNFT),
+    WithdrawPoolSol(WithdrawPoolSol),
+    BuyFromPool(BuyFromPool),
+    SellToPool(SellToPool),
+    InitStakePool(InitStakePool),
+    Stake(Stake),
+    Unstake(Unstake),
+    ClaimRewards(ClaimRewards),
+    FundStakingRewards(FundStakingRewards),
+    CreateRaffle(CreateRaffle),
+    BuyRaffleTicket(BuyRaffleTicket),
+    DrawRaffle(DrawRaffle),
+    ClaimRaffle(ClaimRaffle),
+    Fractionalize(Fractionalize),
+    Buyout(Buyout),
+    RedeemShares(RedeemShares),
+    RedeemNFT(RedeemNFT),
+    ListForRent(ListForRent),
+    Rent(Rent),
+    ReclaimRental(ReclaimRental),
+    SetFeeDiscount(SetFeeDiscount),
+    CreateOtc(CreateOtc),
+    AcceptOtc(AcceptOtc),
+    CancelOtc(CancelOtc),
+    ListMany(ListMany),
+    DelistMany(DelistMany),
+    BatchExecuteSale(BatchExecuteSale),
+    ChangeFeeRecipient(ChangeFeeRecipient),
+    SetStrictMode(SetStrictMode),
+    CreateCollectionStats(CreateCollectionStats),
+    CreateIndex(CreateIndex),
+    SyncIndex(SyncIndex),
+    SetMinimums(SetMinimums),
+    ScheduleParamChange(ScheduleParamChange),
+    ExecuteParamChange(ExecuteParamChange),
+    ListCore(ListCore),
+    DelistCore(DelistCore),
+    BuyCore(BuyCore),
+    ExecuteSale(ExecuteSale),
+    SetPaymentMint(SetPaymentMint),
+    ListForToken(ListForToken),
+    CancelTokenListing(CancelTokenListing),
+    BidWithToken(BidWithToken),
+    WithdrawTokenBid(WithdrawTokenBid),
+    AcceptTokenBid(AcceptTokenBid),
+    CreateSession(CreateSession),
+    RevokeSession(RevokeSession),
+    SetBidCooldown(SetBidCooldown),
+    SetPaused(SetPaused),
+    SetAutomationThread(SetAutomationThread),
+    ThreadRefundBid(ThreadRefundBid),
+    MintAndList(MintAndList),
+    SetMaxRoyalty(SetMaxRoyalty),
+    SetCurator(SetCurator),
+    ApproveListing(ApproveListing),
+    CreateCrowdfund(CreateCrowdfund),
+    Contribute(Contribute),
+    ExecuteCrowdfund(ExecuteCrowdfund),
+    ClaimCrowdfundShares(ClaimCrowdfundShares),
+    RefundContribution(RefundContribution),
+    SweepBuy(SweepBuy),
+    MakeTraitOffer(MakeTraitOffer),
+    AcceptTraitOffer(AcceptTraitOffer),
+    CancelTraitOffer(CancelTraitOffer),
+    SetRewards(SetRewards),
+    CreateLeaderboard(CreateLeaderboard),
+    SetMaxCharity(SetMaxCharity),
+    SetMarketplaceFee(SetMarketplaceFee),
+    CreateSwap(CreateSwap),
+    DepositSwap(DepositSwap),
+    ExecuteSwap(ExecuteSwap),
+    CancelSwap(CancelSwap),
+    Gift(Gift),
+    ClaimGift(ClaimGift),
+    SetPriceFeed(SetPriceFeed),
+    SetBidExpiry(SetBidExpiry),
+    ExpireAndRefund(ExpireAndRefund),
+    ReverseSettlement(ReverseSettlement),
+    FinalizeSettlement(FinalizeSettlement),
+    SetAttestation(SetAttestation),
+    SetVisibility(SetVisibility),
+}
+
+impl NFTInstruction {
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        match input.split_first() {
+            Some((&VERSION_2, rest)) => Self::unpack_v2(rest),
+            _ => Self::unpack_v1(input),
+        }
+    }
+
+    /// Decodes version 2 data. Fields added to an instruction after its
+    /// first release are optional here and default when left off, so
+    /// clients only send what they use. Instructions whose fields have not
+    /// changed decode as in version 1. `DeList`, `WithdrawBid`, `AcceptBid`
+    /// and `ExecuteSale` lead with their bumps, which the first two may
+    /// leave off; the sales need them to tell where the memo starts.
+    fn unpack_v2(input: &[u8]) -> Result<Self, ProgramError> {
+        let (&tag, rest) = input.split_first().ok_or(NFTError::InvalidInstruction)?;
+        let mut fields = FieldReader::new(rest);
+        let instruction = match tag {
+            3 => Self::List(List{
+                amount: fields.u64()?,
+                auction: fields.bool()?,
+                allowed_buyer: fields.pubkey_or_default()?,
+                starts_at: fields.u64_or_default()? as i64,
+                royalty_bps_override: fields.u16_or_default()?,
+                allowlist_root: fields.hash_or_default()?,
+                charity: fields.pubkey_or_default()?,
+                charity_bps: fields.u16_or_default()?,
+                marketplace: fields.pubkey_or_default()?,
+                price_usd: fields.u64_or_default()?,
+                settlement_delay_slots: fields.u64_or_default()?,
+                arbiter: fields.pubkey_or_default()?,
+                visibility: fields.u8_or_default()?,
+            }),
+            4 => Self::DeList(DeList{bumps: fields.bumps_or_default()?}),
+            5 => Self::Bid(Bid{
+                amount: fields.u64()?,
+                wrapped: fields.bool()?,
+                match_accounts: fields.u8_or_default()?,
+                tip_lamports: fields.u64_or_default()?,
+                marketplace: fields.pubkey_or_default()?,
+                proof: NFTInstruction::unpack_proof(fields.rest())?,
+            }),
+            6 => Self::WithdrawBid(WithdrawBid{bumps: fields.bumps_or_default()?}),
+            7 | 61 => {
+                let bumps = fields.bumps()?;
+                let expected_amount = fields.u64()?;
+                let memo = fields.rest();
+                memo::check_memo(memo)?;
+                if tag == 7 {
+                    Self::AcceptBid(AcceptBid{memo: memo.to_vec(), bumps, expected_amount})
+                } else {
+                    Self::ExecuteSale(ExecuteSale{memo: memo.to_vec(), bumps, expected_amount})
+                }
+            }
+            _ => return Self::unpack_v1(input),
+        };
+        fields.finish()?;
+        Ok(instruction)
+    }
+
+    fn unpack_v1(input: &[u8]) -> Result<Self, ProgramError> {
+        let (&tag, rest) = input.split_first().ok_or(NFTError::InvalidInstruction)?;
+        match tag {
+            0 => {
+                if rest.len() < 32usize {
+                    return Err(NFTError::InvalidAuthority.into());
+                }
+                let (authority_bytes_slice, rest) = rest.split_at(32);
+                if rest.len() == 16usize {
+                    let maker_bps = Self::unpack_amount(&rest[..8])?;
+                    let taker_bps = Self::unpack_amount(&rest[8..])?;
+
+                    let authority_bytes = array_ref![authority_bytes_slice, 0 ,32];
+                    return Ok(Self::Initialize(Initialize{
+                        authority: Pubkey::new_from_array(*authority_bytes),
+                        maker_bps: maker_bps,
+                        taker_bps: taker_bps,
+                    }));
+                }
+                Err(NFTError::InvalidPlatformFee.into())
+            }
+            1 => {
+                if rest.len() == 32usize {
+                    let authority_bytes = array_ref![rest, 0 ,32];
+                    return Ok(Self::ChangeAuthority(ChangeAuthority{
+                        authority: Pubkey::new_from_array(*authority_bytes),
+                    }));
+                }
+                Err(NFTError::InvalidAuthority.into())
+            }
+            2 => {
+                if rest.len() == 16usize {
+                    return Ok(Self::ChangeFees(ChangeFees{
+                        maker_bps: Self::unpack_amount(&rest[..8])?,
+                        taker_bps: Self::unpack_amount(&rest[8..])?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            3 => {
+                // The allowed buyer, start time, royalty override,
+                // allowlist root, charity split, marketplace, USD price,
+                // settlement delay, arbiter and visibility are optional
+                // trailing fields so older clients keep working.
+                if rest.len() == 9usize || rest.len() == 41usize || rest.len() == 49usize || rest.len() == 51usize || rest.len() == 83usize || rest.len() == 117usize || rest.len() == 149usize || rest.len() == 157usize || rest.len() == 165usize || rest.len() == 197usize || rest.len() == 198usize {
+                    let auction = match rest[8] {
+                        0 => false,
+                        1 => true,
+                        _ => return Err(NFTError::InvalidInstructionData.into()),
+                    };
+                    let allowed_buyer = match rest.get(9..41) {
+                        Some(buyer) => Pubkey::new_from_array(*array_ref![buyer, 0, 32]),
+                        None => Pubkey::default(),
+                    };
+                    let starts_at = match rest.get(41..) {
+                        Some(starts_at) if !starts_at.is_empty() => Self::unpack_amount(starts_at)? as i64,
+                        _ => 0,
+                    };
+                    let royalty_bps_override = match rest.get(49..) {
+                        Some(royalty) if !royalty.is_empty() => u16::from_be_bytes(*array_ref![royalty, 0, 2]),
+                        _ => 0,
+                    };
+                    let allowlist_root = match rest.get(51..83) {
+                        Some(root) => *array_ref![root, 0, 32],
+                        None => [0u8; 32],
+                    };
+                    let (charity, charity_bps) = match rest.get(83..117) {
+                        Some(split) => (
+                            Pubkey::new_from_array(*array_ref![split, 0, 32]),
+                            u16::from_be_bytes(*array_ref![split, 32, 2]),
+                        ),
+                        None => (Pubkey::default(), 0),
+                    };
+                    let marketplace = match rest.get(117..149) {
+                        Some(marketplace) => Pubkey::new_from_array(*array_ref![marketplace, 0, 32]),
+                        None => Pubkey::default(),
+                    };
+                    let price_usd = match rest.get(149..) {
+                        Some(price_usd) if !price_usd.is_empty() => Self::unpack_amount(price_usd)?,
+                        _ => 0,
+                    };
+                    let settlement_delay_slots = match rest.get(157..) {
+                        Some(delay) if !delay.is_empty() => Self::unpack_amount(delay)?,
+                        _ => 0,
+                    };
+                    let arbiter = match rest.get(165..197) {
+                        Some(arbiter) => Pubkey::new_from_array(*array_ref![arbiter, 0, 32]),
+                        None => Pubkey::default(),
+                    };
+                    let visibility = rest.get(197).copied().unwrap_or_default();
+                    return Ok(Self::List(List{
+                        amount: Self::unpack_amount(rest)?,
+                        auction: auction,
+                        allowed_buyer,
+                        starts_at,
+                        royalty_bps_override,
+                        allowlist_root,
+                        charity,
+                        charity_bps,
+                        marketplace,
+                        price_usd,
+                        settlement_delay_slots,
+                        arbiter,
+                        visibility,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            4 => {
+                Ok(Self::DeList(DeList{bumps: Bumps::default()}))
+            }
+            5 => {
+                // The match account count is an optional trailing byte so
+                // older clients keep working. A creator tip may follow it,
+                // then the marketplace and an allowlist proof.
+                if rest.len() == 9usize || rest.len() == 10usize || rest.len() == 18usize || rest.len() >= 50usize {
+                    let wrapped = match rest[8] {
+                        0 => false,
+                        1 => true,
+                        _ => return Err(NFTError::InvalidInstructionData.into()),
+                    };
+                    return Ok(Self::Bid(Bid{
+                        amount: Self::unpack_amount(rest)?,
+                        wrapped: wrapped,
+                        match_accounts: rest.get(9).copied().unwrap_or(0),
+                        tip_lamports: match rest.get(10..) {
+                            Some(tip) if !tip.is_empty() => Self::unpack_amount(tip)?,
+                            _ => 0,
+                        },
+                        marketplace: match rest.get(18..50) {
+                            Some(marketplace) => Pubkey::new_from_array(*array_ref![marketplace, 0, 32]),
+                            None => Pubkey::default(),
+                        },
+                        proof: Self::unpack_proof(rest.get(50..).unwrap_or(&[]))?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            6 => {
+                Ok(Self::WithdrawBid(WithdrawBid{bumps: Bumps::default()}))
+            }
+            7 => {
+                memo::check_memo(rest)?;
+                Ok(Self::AcceptBid(AcceptBid{memo: rest.to_vec(), bumps: Bumps::default(), expected_amount: 0}))
+            }
+            8 => {
+                Ok(Self::WithdrawNFTOnSuccess(WithdrawNFTOnSuccess{}))
+            }
+            9 => {
+                Ok(Self::RefundUser(RefundUser{}))
+            }
+            10 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::ChangeReferralFee(ChangeReferralFee{
+                        referral_fee_bps: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            11 => {
+                match rest {
+                    [0] => Ok(Self::SetCuratedMode(SetCuratedMode{curated: false})),
+                    [1] => Ok(Self::SetCuratedMode(SetCuratedMode{curated: true})),
+                    _ => Err(NFTError::InvalidInstructionData.into()),
+                }
+            }
+            12 => {
+                if rest.len() == 32usize {
+                    let collection_bytes = array_ref![rest, 0 ,32];
+                    return Ok(Self::AllowCollection(AllowCollection{
+                        collection: Pubkey::new_from_array(*collection_bytes),
+                    }));
+                }
+                Err(NFTError::InvalidInstructionData.into())
+            }
+            13 => {
+                if rest.len() == 32usize {
+                    let collection_bytes = array_ref![rest, 0 ,32];
+                    return Ok(Self::RevokeCollection(RevokeCollection{
+                        collection: Pubkey::new_from_array(*collection_bytes),
+                    }));
+                }
+                Err(NFTError::InvalidInstructionData.into())
+            }
+            14 => {
+                Ok(Self::CreateUserStats(CreateUserStats{}))
+            }
+            15 => {
+                if rest.len() >= 8usize {
+                    return Ok(Self::AuctionBid(AuctionBid{
+                        amount: Self::unpack_amount(rest)?,
+                        proof: Self::unpack_proof(&rest[8..])?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            16 => {
+                if rest.len() == 24usize {
+                    return Ok(Self::FillOrder(FillOrder{
+                        price: Self::unpack_amount(&rest[..8])?,
+                        expiry: Self::unpack_amount(&rest[8..16])? as i64,
+                        nonce: Self::unpack_amount(&rest[16..])?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            17 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::CancelOrder(CancelOrder{
+                        nonce: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            18 => {
+                if rest.len() == 32usize {
+                    let collection_bytes = array_ref![rest, 0 ,32];
+                    return Ok(Self::CreateOrderBook(CreateOrderBook{
+                        collection: Pubkey::new_from_array(*collection_bytes),
+                    }));
+                }
+                Err(NFTError::InvalidInstructionData.into())
+            }
+            19 => {
+                if rest.len() == 33usize {
+                    let side = match rest[0] {
+                        0 => state::BookSide::Ask,
+                        1 => state::BookSide::Bid,
+                        _ => return Err(NFTError::InvalidInstructionData.into()),
+                    };
+                    let maker_bytes = array_ref![rest, 1 ,32];
+                    return Ok(Self::UpdateOrderBook(UpdateOrderBook{
+                        side: side,
+                        maker: Pubkey::new_from_array(*maker_bytes),
+                    }));
+                }
+                Err(NFTError::InvalidInstructionData.into())
+            }
+            20 => {
+                if rest.len() == 57usize {
+                    let collection_bytes = array_ref![rest, 0 ,32];
+                    let curve = pool::Curve::from_u8(rest[32]).ok_or(NFTError::InvalidInstructionData)?;
+                    return Ok(Self::CreatePool(CreatePool{
+                        collection: Pubkey::new_from_array(*collection_bytes),
+                        curve: curve,
+                        spot_price: Self::unpack_amount(&rest[33..41])?,
+                        delta: Self::unpack_amount(&rest[41..49])?,
+                        fee_bps: Self::unpack_amount(&rest[49..])?,
+                    }));
+                }
+                Err(NFTError::InvalidInstructionData.into())
+            }
+            21 => {
+                Ok(Self::DepositPoolNFT(DepositPoolNFT{}))
+            }
+            22 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::DepositPoolSol(DepositPoolSol{
+                        amount: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            23 => {
+                Ok(Self::WithdrawPoolNFT(WithdrawPoolNFT{}))
+            }
+            24 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::WithdrawPoolSol(WithdrawPoolSol{
+                        amount: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            25 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::BuyFromPool(BuyFromPool{
+                        max_price: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            26 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::SellToPool(SellToPool{
+                        min_price: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            27 => {
+                Ok(Self::InitStakePool(InitStakePool{}))
+            }
+            28 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::Stake(Stake{
+                        amount: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            29 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::Unstake(Unstake{
+                        amount: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            30 => {
+                Ok(Self::ClaimRewards(ClaimRewards{}))
+            }
+            31 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::FundStakingRewards(FundStakingRewards{
+                        amount: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            32 => {
+                if rest.len() == 24usize {
+                    return Ok(Self::CreateRaffle(CreateRaffle{
+                        ticket_price: Self::unpack_amount(&rest[..8])?,
+                        total_tickets: Self::unpack_amount(&rest[8..16])?,
+                        end_time: Self::unpack_amount(&rest[16..])? as i64,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            33 => {
+                Ok(Self::BuyRaffleTicket(BuyRaffleTicket{}))
+            }
+            34 => {
+                Ok(Self::DrawRaffle(DrawRaffle{}))
+            }
+            35 => {
+                Ok(Self::ClaimRaffle(ClaimRaffle{}))
+            }
+            36 => {
+                if rest.len() == 16usize {
+                    return Ok(Self::Fractionalize(Fractionalize{
+                        share_supply: Self::unpack_amount(&rest[..8])?,
+                        buyout_price: Self::unpack_amount(&rest[8..])?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            37 => {
+                Ok(Self::Buyout(Buyout{}))
+            }
+            38 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::RedeemShares(RedeemShares{
+                        amount: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            39 => {
+                Ok(Self::RedeemNFT(RedeemNFT{}))
+            }
+            40 => {
+                if rest.len() == 16usize {
+                    return Ok(Self::ListForRent(ListForRent{
+                        price: Self::unpack_amount(&rest[..8])?,
+                        duration_slots: Self::unpack_amount(&rest[8..])?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            41 => {
+                Ok(Self::Rent(Rent{}))
+            }
+            42 => {
+                Ok(Self::ReclaimRental(ReclaimRental{}))
+            }
+            43 => {
+                if rest.len() == 48usize {
+                    return Ok(Self::SetFeeDiscount(SetFeeDiscount{
+                        discount_mint: Pubkey::new_from_array(*array_ref![rest, 0, 32]),
+                        threshold: Self::unpack_amount(&rest[32..40])?,
+                        discount_bps: Self::unpack_amount(&rest[40..])?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            44 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::CreateOtc(CreateOtc{
+                        price: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            45 => {
+                Ok(Self::AcceptOtc(AcceptOtc{}))
+            }
+            46 => {
+                Ok(Self::CancelOtc(CancelOtc{}))
+            }
+            47 => {
+                if !rest.is_empty() && rest.len() % 8 == 0 {
+                    let amounts = rest
+                        .chunks(8)
+                        .map(Self::unpack_amount)
+                        .collect::<Result<Vec<u64>, ProgramError>>()?;
+                    return Ok(Self::ListMany(ListMany{amounts}));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            48 => {
+                Ok(Self::DelistMany(DelistMany{}))
+            }
+            49 => {
+                if !rest.is_empty() {
+                    return Ok(Self::BatchExecuteSale(BatchExecuteSale{
+                        account_counts: rest.to_vec(),
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            50 => {
+                if rest.len() == 32usize {
+                    return Ok(Self::ChangeFeeRecipient(ChangeFeeRecipient{
+                        fee_recipient: Pubkey::new_from_array(*array_ref![rest, 0, 32]),
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            51 => {
+                match rest {
+                    [0] => Ok(Self::SetStrictMode(SetStrictMode{strict: false})),
+                    [1] => Ok(Self::SetStrictMode(SetStrictMode{strict: true})),
+                    _ => Err(NFTError::InvalidInstructionData.into()),
+                }
+            }
+            52 => {
+                if rest.len() == 32usize {
+                    return Ok(Self::CreateCollectionStats(CreateCollectionStats{
+                        collection: Pubkey::new_from_array(*array_ref![rest, 0, 32]),
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            53 => {
+                if rest.len() == 33usize {
+                    let kind = match rest[0] {
+                        0 => index::IndexKind::Owner,
+                        1 => index::IndexKind::Mint,
+                        _ => return Err(NFTError::InvalidInstructionData.into()),
+                    };
+                    return Ok(Self::CreateIndex(CreateIndex{
+                        kind,
+                        key: Pubkey::new_from_array(*array_ref![rest, 1, 32]),
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            54 => Ok(Self::SyncIndex(SyncIndex{})),
+            55 => {
+                if rest.len() == 16usize {
+                    return Ok(Self::SetMinimums(SetMinimums{
+                        min_listing_lamports: Self::unpack_amount(&rest[..8])?,
+                        min_bid_lamports: Self::unpack_amount(&rest[8..])?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            56 => {
+                if rest.len() < 9usize {
+                    return Err(NFTError::InvalidInstructionData.into());
+                }
+                let execute_slot = Self::unpack_amount(&rest[..8])?;
+                let change = match (rest[8], &rest[9..]) {
+                    (0, payload) if payload.len() == 16usize => state::ParamChange::Fees{
+                        maker_bps: Self::unpack_amount(&payload[..8])?,
+                        taker_bps: Self::unpack_amount(&payload[8..])?,
+                    },
+                    (1, payload) if payload.len() == 32usize => state::ParamChange::Authority{
+                        authority: Pubkey::new_from_array(*array_ref![payload, 0, 32]),
+                    },
+                    (2, payload) if payload.len() == 8usize => state::ParamChange::Timelock{
+                        slots: Self::unpack_amount(payload)?,
+                    },
+                    (3, payload) if payload.len() == 32usize => state::ParamChange::FeeRecipient{
+                        fee_recipient: Pubkey::new_from_array(*array_ref![payload, 0, 32]),
+                    },
+                    (4, payload) if payload.len() == 8usize => state::ParamChange::ReferralFee{
+                        referral_fee_bps: Self::unpack_amount(payload)?,
+                    },
+                    (5, payload) if payload.len() == 8usize => state::ParamChange::MaxRoyalty{
+                        max_royalty_bps: Self::unpack_amount(payload)?,
+                    },
+                    (6, payload) if payload.len() == 8usize => state::ParamChange::MarketplaceFee{
+                        marketplace_fee_bps: Self::unpack_amount(payload)?,
+                    },
+                    (7, payload) if payload.len() == 32usize => state::ParamChange::PriceFeed{
+                        price_feed: Pubkey::new_from_array(*array_ref![payload, 0, 32]),
+                    },
+                    (8, payload) if payload.len() == 40usize => state::ParamChange::Curator{
+                        curator: Pubkey::new_from_array(*array_ref![payload, 0, 32]),
+                        curator_fee_bps: Self::unpack_amount(&payload[32..])?,
+                    },
+                    _ => return Err(NFTError::InvalidInstructionData.into()),
+                };
+                Ok(Self::ScheduleParamChange(ScheduleParamChange{change, execute_slot}))
+            }
+            57 => Ok(Self::ExecuteParamChange(ExecuteParamChange{})),
+            58 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::ListCore(ListCore{
+                        price: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            59 => Ok(Self::DelistCore(DelistCore{})),
+            60 => {
+                // Without a price the buyer takes the listing's current one.
+                let max_price = match rest.len() {
+                    0 => u64::MAX,
+                    8 => Self::unpack_amount(rest)?,
+                    _ => return Err(NFTError::InvalidInstructionData.into()),
+                };
+                Ok(Self::BuyCore(BuyCore{max_price}))
+            }
+            61 => {
+                memo::check_memo(rest)?;
+                Ok(Self::ExecuteSale(ExecuteSale{memo: rest.to_vec(), bumps: Bumps::default(), expected_amount: 0}))
+            }
+            62 => {
+                match rest {
+                    [0] => Ok(Self::SetPaymentMint(SetPaymentMint{enabled: false})),
+                    [1] => Ok(Self::SetPaymentMint(SetPaymentMint{enabled: true})),
+                    _ => Err(NFTError::InvalidInstructionData.into()),
+                }
+            }
+            63 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::ListForToken(ListForToken{
+                        price: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            64 => Ok(Self::CancelTokenListing(CancelTokenListing{})),
+            65 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::BidWithToken(BidWithToken{
+                        amount: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            66 => Ok(Self::WithdrawTokenBid(WithdrawTokenBid{})),
+            67 => Ok(Self::AcceptTokenBid(AcceptTokenBid{})),
+            68 => {
+                if rest.len() == 41usize {
+                    return Ok(Self::CreateSession(CreateSession{
+                        session_key: Pubkey::new_from_array(*array_ref![rest, 0, 32]),
+                        expires_at: Self::unpack_amount(&rest[32..40])? as i64,
+                        allowed: rest[40],
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            69 => Ok(Self::RevokeSession(RevokeSession{})),
+            70 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::SetBidCooldown(SetBidCooldown{
+                        slots: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            71 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::SetPaused(SetPaused{
+                        paused: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            72 => {
+                if rest.len() == 40usize {
+                    return Ok(Self::SetAutomationThread(SetAutomationThread{
+                        thread: Pubkey::new_from_array(*array_ref![rest, 0, 32]),
+                        stale_bid_slots: Self::unpack_amount(&rest[32..])?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            73 => Ok(Self::ThreadRefundBid(ThreadRefundBid{})),
+            74 => {
+                // The metadata is optional and trailing: name, symbol and
+                // uri each behind a length byte, then the royalty.
+                if rest.len() < 8usize {
+                    return Err(NFTError::InvalidInstructionData.into());
+                }
+                let price = Self::unpack_amount(rest)?;
+                let mut fields = FieldReader::new(&rest[8..]);
+                let metadata = if rest.len() == 8usize {
+                    None
+                } else {
+                    Some(NewMetadata{
+                        name: fields.string()?,
+                        symbol: fields.string()?,
+                        uri: fields.string()?,
+                        seller_fee_basis_points: fields.u16()?,
+                    })
+                };
+                fields.finish()?;
+                Ok(Self::MintAndList(MintAndList{price, metadata}))
+            }
+            75 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::SetMaxRoyalty(SetMaxRoyalty{
+                        max_royalty_bps: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            76 => {
+                if rest.len() == 40usize {
+                    return Ok(Self::SetCurator(SetCurator{
+                        curator: Pubkey::new_from_array(*array_ref![rest, 0, 32]),
+                        curator_fee_bps: Self::unpack_amount(&rest[32..])?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            77 => Ok(Self::ApproveListing(ApproveListing{})),
+            78 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::CreateCrowdfund(CreateCrowdfund{
+                        buyout_price: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            79 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::Contribute(Contribute{
+                        amount: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            80 => Ok(Self::ExecuteCrowdfund(ExecuteCrowdfund{})),
+            81 => Ok(Self::ClaimCrowdfundShares(ClaimCrowdfundShares{})),
+            82 => Ok(Self::RefundContribution(RefundContribution{})),
+            83 => {
+                if rest.len() > 8usize && (rest.len() - 8) % 10 == 0 {
+                    let purchases = rest[8..]
+                        .chunks(10)
+                        .map(|purchase| Ok(SweepPurchase{
+                            account_count: purchase[0],
+                            match_accounts: purchase[1],
+                            max_price: Self::unpack_amount(&purchase[2..])?,
+                        }))
+                        .collect::<Result<Vec<_>, ProgramError>>()?;
+                    return Ok(Self::SweepBuy(SweepBuy{
+                        max_spend: Self::unpack_amount(&rest[..8])?,
+                        purchases,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            84 => {
+                if rest.len() == 40usize {
+                    return Ok(Self::MakeTraitOffer(MakeTraitOffer{
+                        amount: Self::unpack_amount(rest)?,
+                        root: *array_ref![rest, 8, 32],
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            85 => {
+                Ok(Self::AcceptTraitOffer(AcceptTraitOffer{proof: Self::unpack_proof(rest)?}))
+            }
+            86 => Ok(Self::CancelTraitOffer(CancelTraitOffer{})),
+            87 => {
+                if rest.len() == 24usize {
+                    return Ok(Self::SetRewards(SetRewards{
+                        buyer_reward: Self::unpack_amount(rest)?,
+                        seller_reward: Self::unpack_amount(&rest[8..])?,
+                        epoch_cap: Self::unpack_amount(&rest[16..])?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            88 => {
+                if rest.len() == 24usize {
+                    return Ok(Self::CreateLeaderboard(CreateLeaderboard{
+                        season: Self::unpack_amount(rest)?,
+                        starts_at: Self::unpack_amount(&rest[8..])? as i64,
+                        ends_at: Self::unpack_amount(&rest[16..])? as i64,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            89 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::SetMaxCharity(SetMaxCharity{
+                        max_charity_bps: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            90 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::SetMarketplaceFee(SetMarketplaceFee{
+                        marketplace_fee_bps: Self::unpack_amount(rest)?,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            91 => {
+                if rest.len() >= 24usize && (rest.len() - 24) % 32 == 0 && (rest.len() - 24) / 32 <= swap::SWAP_MAX_ASSETS {
+                    return Ok(Self::CreateSwap(CreateSwap{
+                        id: Self::unpack_amount(rest)?,
+                        maker_lamports: Self::unpack_amount(&rest[8..])?,
+                        taker_lamports: Self::unpack_amount(&rest[16..])?,
+                        taker_mints: rest[24..]
+                            .chunks(32)
+                            .map(|mint| Pubkey::new_from_array(*array_ref![mint, 0, 32]))
+                            .collect(),
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            92 => {
+                Ok(Self::DepositSwap(DepositSwap{}))
+            }
+            93 => {
+                Ok(Self::ExecuteSwap(ExecuteSwap{}))
+            }
+            94 => {
+                Ok(Self::CancelSwap(CancelSwap{}))
+            }
+            95 => {
+                if rest.len() == 48usize {
+                    return Ok(Self::Gift(Gift{
+                        recipient: Pubkey::new_from_array(*array_ref![rest, 0, 32]),
+                        reveal_at: Self::unpack_amount(&rest[32..])? as i64,
+                        reclaimable_at: Self::unpack_amount(&rest[40..])? as i64,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            96 => {
+                Ok(Self::ClaimGift(ClaimGift{}))
+            }
+            97 => {
+                if rest.len() == 32usize {
+                    return Ok(Self::SetPriceFeed(SetPriceFeed{
+                        price_feed: Pubkey::new_from_array(*array_ref![rest, 0, 32]),
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            98 => {
+                if rest.len() == 8usize {
+                    return Ok(Self::SetBidExpiry(SetBidExpiry{
+                        expires_at: Self::unpack_amount(rest)? as i64,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            99 => {
+                Ok(Self::ExpireAndRefund(ExpireAndRefund{}))
+            }
+            100 => {
+                Ok(Self::ReverseSettlement(ReverseSettlement{}))
+            }
+            101 => {
+                Ok(Self::FinalizeSettlement(FinalizeSettlement{}))
+            }
+            102 => {
+                if rest.len() == 33usize {
+                    let required = match rest[32] {
+                        0 => false,
+                        1 => true,
+                        _ => return Err(NFTError::InvalidInstructionData.into()),
+                    };
+                    return Ok(Self::SetAttestation(SetAttestation{
+                        attestor: Pubkey::new_from_array(*array_ref![rest, 0, 32]),
+                        required,
+                    }));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            103 => {
+                if rest.len() == 1usize {
+                    return Ok(Self::SetVisibility(SetVisibility{visibility: rest[0]}));
+                }
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+            _ => Err(NFTError::InvalidInstruction.into()),
+        }
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(size_of::<Self>());
+        match self {
+            Self::Initialize(Initialize{authority, maker_bps, taker_bps}) => {
+                buf.push(0);
+                buf.extend_from_slice(authority.as_ref());
+                buf.extend_from_slice(&maker_bps.to_be_bytes());
+                buf.extend_from_slice(&taker_bps.to_be_bytes());
+            }
+            Self::ChangeAuthority(ChangeAuthority{authority}) => {
+                buf.push(1);
+                buf.extend_from_slice(authority.as_ref());
+            }
+            Self::ChangeFees(ChangeFees{maker_bps, taker_bps}) => {
+                buf.push(2);
+                buf.extend_from_slice(&maker_bps.to_be_bytes());
+                buf.extend_from_slice(&taker_bps.to_be_bytes());
+            }
+            Self::List(List{amount, auction, allowed_buyer, starts_at, royalty_bps_override, allowlist_root, charity, charity_bps, marketplace, price_usd, settlement_delay_slots, arbiter, visibility}) => {
+                buf.push(3);
+                buf.extend_from_slice(&amount.to_be_bytes());
+                buf.push(*auction as u8);
+                buf.extend_from_slice(allowed_buyer.as_ref());
+                buf.extend_from_slice(&starts_at.to_be_bytes());
+                buf.extend_from_slice(&royalty_bps_override.to_be_bytes());
+                buf.extend_from_slice(allowlist_root);
+                buf.extend_from_slice(charity.as_ref());
+                buf.extend_from_slice(&charity_bps.to_be_bytes());
+                buf.extend_from_slice(marketplace.as_ref());
+                buf.extend_from_slice(&price_usd.to_be_bytes());
+                buf.extend_from_slice(&settlement_delay_slots.to_be_bytes());
+                buf.extend_from_slice(arbiter.as_ref());
+                buf.push(*visibility);
+            }
+            Self::DeList(DeList{..}) => buf.push(4),
+            Self::Bid(Bid{amount, wrapped, match_accounts, tip_lamports, marketplace, proof}) => {
+                buf.push(5);
+                buf.extend_from_slice(&amount.to_be_bytes());
+                buf.push(*wrapped as u8);
+                buf.push(*match_accounts);
+                buf.extend_from_slice(&tip_lamports.to_be_bytes());
+                buf.extend_from_slice(marketplace.as_ref());
+                for hash in proof {
+                    buf.extend_from_slice(hash);
+                }
+            }
+            Self::WithdrawBid(WithdrawBid{..}) => buf.push(6),
+            Self::AcceptBid(AcceptBid{memo, ..}) => {
+                buf.push(7);
+                buf.extend_from_slice(memo);
+            }
+            Self::WithdrawNFTOnSuccess(WithdrawNFTOnSuccess{}) => buf.push(8),
+            Self::RefundUser(RefundUser{}) => buf.push(9),
+            Self::ChangeReferralFee(ChangeReferralFee{referral_fee_bps}) => {
+                buf.push(10);
+                buf.extend_from_slice(&referral_fee_bps.to_be_bytes());
+            }
+            Self::SetCuratedMode(SetCuratedMode{curated}) => {
+                buf.push(11);
+                buf.push(*curated as u8);
+            }
+            Self::AllowCollection(AllowCollection{collection}) => {
+                buf.push(12);
+                buf.extend_from_slice(collection.as_ref());
+            }
+            Self::RevokeCollection(RevokeCollection{collection}) => {
+                buf.push(13);
+                buf.extend_from_slice(collection.as_ref());
+            }
+            Self::CreateUserStats(CreateUserStats{}) => buf.push(14),
+            Self::AuctionBid(AuctionBid{amount, proof}) => {
+                buf.push(15);
+                buf.extend_from_slice(&amount.to_be_bytes());
+                for hash in proof {
+                    buf.extend_from_slice(hash);
+                }
+            }
+            Self::FillOrder(FillOrder{price, expiry, nonce}) => {
+                buf.push(16);
+                buf.extend_from_slice(&price.to_be_bytes());
+                buf.extend_from_slice(&expiry.to_be_bytes());
+                buf.extend_from_slice(&nonce.to_be_bytes());
+            }
+            Self::CancelOrder(CancelOrder{nonce}) => {
+                buf.push(17);
+                buf.extend_from_slice(&nonce.to_be_bytes());
+            }
+            Self::CreateOrderBook(CreateOrderBook{collection}) => {
+                buf.push(18);
+                buf.extend_from_slice(collection.as_ref());
+            }
+            Self::UpdateOrderBook(UpdateOrderBook{side, maker}) => {
+                buf.push(19);
+                buf.push(*side as u8);
+                buf.extend_from_slice(maker.as_ref());
+            }
+            Self::CreatePool(CreatePool{collection, curve, spot_price, delta, fee_bps}) => {
+                buf.push(20);
+                buf.extend_from_slice(collection.as_ref());
+                buf.push(*curve as u8);
+                buf.extend_from_slice(&spot_price.to_be_bytes());
+                buf.extend_from_slice(&delta.to_be_bytes());
+                buf.extend_from_slice(&fee_bps.to_be_bytes());
+            }
+            Self::DepositPoolNFT(DepositPoolNFT{}) => buf.push(21),
+            Self::DepositPoolSol(DepositPoolSol{amount}) => {
+                buf.push(22);
+                buf.extend_from_slice(&amount.to_be_bytes());
+            }
+            Self::WithdrawPoolNFT(WithdrawPoolNFT{}) => buf.push(23),
+            Self::WithdrawPoolSol(WithdrawPoolSol{amount}) => {
+                buf.push(24);
+                buf.extend_from_slice(&amount.to_be_bytes());
+            }
+            Self::BuyFromPool(BuyFromPool{max_price}) => {
+                buf.push(25);
+                buf.extend_from_slice(&max_price.to_be_bytes());
+            }
+            Self::SellToPool(SellToPool{min_price}) => {
+                buf.push(26);
+                buf.extend_from_slice(&min_price.to_be_bytes());
+            }
+            Self::InitStakePool(InitStakePool{}) => buf.push(27),
+            Self::Stake(Stake{amount}) => {
+                buf.push(28);
+                buf.extend_from_slice(&amount.to_be_bytes());
+            }
+            Self::Unstake(Unstake{amount}) => {
+                buf.push(29);
+                buf.extend_from_slice(&amount.to_be_bytes());
+            }
+            Self::ClaimRewards(ClaimRewards{}) => buf.push(30),
+            Self::FundStakingRewards(FundStakingRewards{amount}) => {
+                buf.push(31);
+                buf.extend_from_slice(&amount.to_be_bytes());
+            }
+            Self::CreateRaffle(CreateRaffle{ticket_price, total_tickets, end_time}) => {
+                buf.push(32);
+                buf.extend_from_slice(&ticket_price.to_be_bytes());
+                buf.extend_from_slice(&total_tickets.to_be_bytes());
+                buf.extend_from_slice(&end_time.to_be_bytes());
+            }
+            Self::BuyRaffleTicket(BuyRaffleTicket{}) => buf.push(33),
+            Self::DrawRaffle(DrawRaffle{}) => buf.push(34),
+            Self::ClaimRaffle(ClaimRaffle{}) => buf.push(35),
+            Self::Fractionalize(Fractionalize{share_supply, buyout_price}) => {
+                buf.push(36);
+                buf.extend_from_slice(&share_supply.to_be_bytes());
+                buf.extend_from_slice(&buyout_price.to_be_bytes());
+            }
+            Self::Buyout(Buyout{}) => buf.push(37),
+            Self::RedeemShares(RedeemShares{amount}) => {
+                buf.push(38);
+                buf.extend_from_slice(&amount.to_be_bytes());
+            }
+            Self::RedeemNFT(RedeemNFT{}) => buf.push(39),
+            Self::ListForRent(ListForRent{price, duration_slots}) => {
+                buf.push(40);
+                buf.extend_from_slice(&price.to_be_bytes());
+                buf.extend_from_slice(&duration_slots.to_be_bytes());
+            }
+            Self::Rent(Rent{}) => buf.push(41),
+            Self::ReclaimRental(ReclaimRental{}) => buf.push(42),
+            Self::SetFeeDiscount(SetFeeDiscount{discount_mint, threshold, discount_bps}) => {
+                buf.push(43);
+                buf.extend_from_slice(discount_mint.as_ref());
+                buf.extend_from_slice(&threshold.to_be_bytes());
+                buf.extend_from_slice(&discount_bps.to_be_bytes());
+            }
+            Self::CreateOtc(CreateOtc{price}) => {
+                buf.push(44);
+                buf.extend_from_slice(&price.to_be_bytes());
+            }
+            Self::AcceptOtc(AcceptOtc{}) => buf.push(45),
+            Self::CancelOtc(CancelOtc{}) => buf.push(46),
+            Self::ListMany(ListMany{amounts}) => {
+                buf.push(47);
+                for amount in amounts {
+                    buf.extend_from_slice(&amount.to_be_bytes());
+                }
+            }
+            Self::DelistMany(DelistMany{}) => buf.push(48),
+            Self::BatchExecuteSale(BatchExecuteSale{account_counts}) => {
+                buf.push(49);
+                buf.extend_from_slice(account_counts);
+            }
+            Self::ChangeFeeRecipient(ChangeFeeRecipient{fee_recipient}) => {
+                buf.push(50);
+                buf.extend_from_slice(fee_recipient.as_ref());
+            }
+            Self::SetStrictMode(SetStrictMode{strict}) => {
+                buf.push(51);
+                buf.push(*strict as u8);
+            }
+            Self::CreateCollectionStats(CreateCollectionStats{collection}) => {
+                buf.push(52);
+                buf.extend_from_slice(collection.as_ref());
+            }
+            Self::CreateIndex(CreateIndex{kind, key}) => {
+                buf.push(53);
+                buf.push(*kind as u8);
+                buf.extend_from_slice(key.as_ref());
+            }
+            Self::SyncIndex(SyncIndex{}) => buf.push(54),
+            Self::SetMinimums(SetMinimums{min_listing_lamports, min_bid_lamports}) => {
+                buf.push(55);
+                buf.extend_from_slice(&min_listing_lamports.to_be_bytes());
+                buf.extend_from_slice(&min_bid_lamports.to_be_bytes());
+            }
+            Self::ScheduleParamChange(ScheduleParamChange{change, execute_slot}) => {
+                buf.push(56);
+                buf.extend_from_slice(&execute_slot.to_be_bytes());
+                match change {
+                    state::ParamChange::Fees{maker_bps, taker_bps} => {
+                        buf.push(0);
+                        buf.extend_from_slice(&maker_bps.to_be_bytes());
+                        buf.extend_from_slice(&taker_bps.to_be_bytes());
+                    }
+                    state::ParamChange::Authority{authority} => {
+                        buf.push(1);
+                        buf.extend_from_slice(authority.as_ref());
+                    }
+                    state::ParamChange::Timelock{slots} => {
+                        buf.push(2);
+                        buf.extend_from_slice(&slots.to_be_bytes());
+                    }
+                    state::ParamChange::FeeRecipient{fee_recipient} => {
+                        buf.push(3);
+                        buf.extend_from_slice(fee_recipient.as_ref());
+                    }
+                    state::ParamChange::ReferralFee{referral_fee_bps} => {
+                        buf.push(4);
+                        buf.extend_from_slice(&referral_fee_bps.to_be_bytes());
+                    }
+                    state::ParamChange::MaxRoyalty{max_royalty_bps} => {
+                        buf.push(5);
+                        buf.extend_from_slice(&max_royalty_bps.to_be_bytes());
+                    }
+                    state::ParamChange::MarketplaceFee{marketplace_fee_bps} => {
+                        buf.push(6);
+                        buf.extend_from_slice(&marketplace_fee_bps.to_be_bytes());
+                    }
+                    state::ParamChange::PriceFeed{price_feed} => {
+                        buf.push(7);
+                        buf.extend_from_slice(price_feed.as_ref());
+                    }
+                    state::ParamChange::Curator{curator, curator_fee_bps} => {
+                        buf.push(8);
+                        buf.extend_from_slice(curator.as_ref());
+                        buf.extend_from_slice(&curator_fee_bps.to_be_bytes());
+                    }
+                }
+            }
+            Self::ExecuteParamChange(ExecuteParamChange{}) => buf.push(57),
+            Self::ListCore(ListCore{price}) => {
+                buf.push(58);
+                buf.extend_from_slice(&price.to_be_bytes());
+            }
+            Self::DelistCore(DelistCore{}) => buf.push(59),
+            Self::BuyCore(BuyCore{max_price}) => {
+                buf.push(60);
+                buf.extend_from_slice(&max_price.to_be_bytes());
+            }
+            Self::ExecuteSale(ExecuteSale{memo, ..}) => {
+                buf.push(61);
+                buf.extend_from_slice(memo);
+            }
+            Self::SetPaymentMint(SetPaymentMint{enabled}) => {
+                buf.push(62);
+                buf.push(*enabled as u8);
+            }
+            Self::ListForToken(ListForToken{price}) => {
+                buf.push(63);
+                buf.extend_from_slice(&price.to_be_bytes());
+            }
+            Self::CancelTokenListing(CancelTokenListing{}) => buf.push(64),
+            Self::BidWithToken(BidWithToken{amount}) => {
+                buf.push(65);
+                buf.extend_from_slice(&amount.to_be_bytes());
+            }
+            Self::WithdrawTokenBid(WithdrawTokenBid{}) => buf.push(66),
+            Self::AcceptTokenBid(AcceptTokenBid{}) => buf.push(67),
+            Self::CreateSession(CreateSession{session_key, expires_at, allowed}) => {
+                buf.push(68);
+                buf.extend_from_slice(session_key.as_ref());
+                buf.extend_from_slice(&expires_at.to_be_bytes());
+                buf.push(*allowed);
+            }
+            Self::RevokeSession(RevokeSession{}) => buf.push(69),
+            Self::SetBidCooldown(SetBidCooldown{slots}) => {
+                buf.push(70);
+                buf.extend_from_slice(&slots.to_be_bytes());
+            }
+            Self::SetPaused(SetPaused{paused}) => {
+                buf.push(71);
+                buf.extend_from_slice(&paused.to_be_bytes());
+            }
+            Self::SetAutomationThread(SetAutomationThread{thread, stale_bid_slots}) => {
+                buf.push(72);
+                buf.extend_from_slice(thread.as_ref());
+                buf.extend_from_slice(&stale_bid_slots.to_be_bytes());
+            }
+            Self::ThreadRefundBid(ThreadRefundBid{}) => buf.push(73),
+            Self::MintAndList(MintAndList{price, metadata}) => {
+                buf.push(74);
+                buf.extend_from_slice(&price.to_be_bytes());
+                if let Some(NewMetadata{name, symbol, uri, seller_fee_basis_points}) = metadata {
+                    for field in &[name, symbol, uri] {
+                        buf.push(field.len() as u8);
+                        buf.extend_from_slice(field.as_bytes());
+                    }
+                    buf.extend_from_slice(&seller_fee_basis_points.to_be_bytes());
+                }
+            }
+            Self::SetMaxRoyalty(SetMaxRoyalty{max_royalty_bps}) => {
+                buf.push(75);
+                buf.extend_from_slice(&max_royalty_bps.to_be_bytes());
+            }
+            Self::SetCurator(SetCurator{curator, curator_fee_bps}) => {
+                buf.push(76);
+                buf.extend_from_slice(curator.as_ref());
+                buf.extend_from_slice(&curator_fee_bps.to_be_bytes());
+            }
+            Self::ApproveListing(ApproveListing{}) => buf.push(77),
+            Self::CreateCrowdfund(CreateCrowdfund{buyout_price}) => {
+                buf.push(78);
+                buf.extend_from_slice(&buyout_price.to_be_bytes());
+            }
+            Self::Contribute(Contribute{amount}) => {
+                buf.push(79);
+                buf.extend_from_slice(&amount.to_be_bytes());
+            }
+            Self::ExecuteCrowdfund(ExecuteCrowdfund{}) => buf.push(80),
+            Self::ClaimCrowdfundShares(ClaimCrowdfundShares{}) => buf.push(81),
+            Self::RefundContribution(RefundContribution{}) => buf.push(82),
+            Self::SweepBuy(SweepBuy{max_spend, purchases}) => {
+                buf.push(83);
+                buf.extend_from_slice(&max_spend.to_be_bytes());
+                for SweepPurchase{account_count, match_accounts, max_price} in purchases {
+                    buf.push(*account_count);
+                    buf.push(*match_accounts);
+                    buf.extend_from_slice(&max_price.to_be_bytes());
+                }
+            }
+            Self::MakeTraitOffer(MakeTraitOffer{amount, root}) => {
+                buf.push(84);
+                buf.extend_from_slice(&amount.to_be_bytes());
+                buf.extend_from_slice(root);
+            }
+            Self::AcceptTraitOffer(AcceptTraitOffer{proof}) => {
+                buf.push(85);
+                for hash in proof {
+                    buf.extend_from_slice(hash);
+                }
+            }
+            Self::CancelTraitOffer(CancelTraitOffer{}) => buf.push(86),
+            Self::SetRewards(SetRewards{buyer_reward, seller_reward, epoch_cap}) => {
+                buf.push(87);
+                buf.extend_from_slice(&buyer_reward.to_be_bytes());
+                buf.extend_from_slice(&seller_reward.to_be_bytes());
+                buf.extend_from_slice(&epoch_cap.to_be_bytes());
+            }
+            Self::CreateLeaderboard(CreateLeaderboard{season, starts_at, ends_at}) => {
+                buf.push(88);
+                buf.extend_from_slice(&season.to_be_bytes());
+                buf.extend_from_slice(&starts_at.to_be_bytes());
+                buf.extend_from_slice(&ends_at.to_be_bytes());
+            }
+            Self::SetMaxCharity(SetMaxCharity{max_charity_bps}) => {
+                buf.push(89);
+                buf.extend_from_slice(&max_charity_bps.to_be_bytes());
+            }
+            Self::SetMarketplaceFee(SetMarketplaceFee{marketplace_fee_bps}) => {
+                buf.push(90);
+                buf.extend_from_slice(&marketplace_fee_bps.to_be_bytes());
+            }
+            Self::CreateSwap(CreateSwap{id, maker_lamports, taker_lamports, taker_mints}) => {
+                buf.push(91);
+                buf.extend_from_slice(&id.to_be_bytes());
+                buf.extend_from_slice(&maker_lamports.to_be_bytes());
+                buf.extend_from_slice(&taker_lamports.to_be_bytes());
+                for mint in taker_mints {
+                    buf.extend_from_slice(mint.as_ref());
+                }
+            }
+            Self::DepositSwap(DepositSwap{}) => buf.push(92),
+            Self::ExecuteSwap(ExecuteSwap{}) => buf.push(93),
+            Self::CancelSwap(CancelSwap{}) => buf.push(94),
+            Self::Gift(Gift{recipient, reveal_at, reclaimable_at}) => {
+                buf.push(95);
+                buf.extend_from_slice(recipient.as_ref());
+                buf.extend_from_slice(&reveal_at.to_be_bytes());
+                buf.extend_from_slice(&reclaimable_at.to_be_bytes());
+            }
+            Self::ClaimGift(ClaimGift{}) => buf.push(96),
+            Self::SetPriceFeed(SetPriceFeed{price_feed}) => {
+                buf.push(97);
+                buf.extend_from_slice(price_feed.as_ref());
+            }
+            Self::SetBidExpiry(SetBidExpiry{expires_at}) => {
+                buf.push(98);
+                buf.extend_from_slice(&expires_at.to_be_bytes());
+            }
+            Self::ExpireAndRefund(ExpireAndRefund{}) => buf.push(99),
+            Self::ReverseSettlement(ReverseSettlement{}) => buf.push(100),
+            Self::FinalizeSettlement(FinalizeSettlement{}) => buf.push(101),
+            Self::SetAttestation(SetAttestation{attestor, required}) => {
+                buf.push(102);
+                buf.extend_from_slice(attestor.as_ref());
+                buf.push(*required as u8);
+            }
+            Self::SetVisibility(SetVisibility{visibility}) => {
+                buf.push(103);
+                buf.push(*visibility);
+            }
+        }
+        buf
+    }
+
+    /// Packs the instruction as version 2 data. Every field is written, so
+    /// the fields follow the tag exactly as in version 1, behind the bumps
+    /// for instructions that carry them and a sale's expected amount.
+    pub fn pack_v2(&self) -> Vec<u8> {
+        let fields = self.pack();
+        let mut buf = vec![VERSION_2, fields[0]];
+        match self {
+            Self::DeList(DeList{bumps})
+            | Self::WithdrawBid(WithdrawBid{bumps}) => buf.extend_from_slice(&bumps.pack()),
+            Self::AcceptBid(AcceptBid{bumps, expected_amount, ..})
+            | Self::ExecuteSale(ExecuteSale{bumps, expected_amount, ..}) => {
+                buf.extend_from_slice(&bumps.pack());
+                buf.extend_from_slice(&expected_amount.to_be_bytes());
+            }
+            _ => {}
+        }
+        buf.extend_from_slice(&fields[1..]);
+        buf
+    }
+
+    fn unpack_amount(input: &[u8]) -> Result<u64, ProgramError> {
+        let amount = input
+            .get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_be_bytes)
+            .ok_or(NFTError::FailedToUnpackU64)?;
+        Ok(amount)
+    }
+
+    /// Splits a trailing `merkle` proof into its hashes.
+    fn unpack_proof(input: &[u8]) -> Result<Vec<[u8; 32]>, ProgramError> {
+        if input.len() % 32 != 0 || input.len() / 32 > merkle::MAX_PROOF_LEN {
+            return Err(NFTError::InvalidInstructionData.into());
+        }
+        Ok(input.chunks(32).map(|hash| *array_ref![hash, 0, 32]).collect())
+    }
+}
+
+/// Reads version 2 instruction fields in order. The `_or_default`
+/// readers return the default once the data runs out, but a field cut off
+/// partway through is an error.
+struct FieldReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> FieldReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        FieldReader { data }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ProgramError> {
+        if self.data.len() < len {
+            return Err(NFTError::InvalidInstructionData.into());
+        }
+        let (field, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(field)
+    }
+
+    fn u64(&mut self) -> Result<u64, ProgramError> {
+        NFTInstruction::unpack_amount(self.take(8)?)
+    }
+
+    fn bool(&mut self) -> Result<bool, ProgramError> {
+        match self.take(1)? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(NFTError::InvalidInstructionData.into()),
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, ProgramError> {
+        let field = self.take(2)?;
+        Ok(u16::from_be_bytes(*array_ref![field, 0, 2]))
+    }
+
+    /// A UTF-8 string behind a one byte length.
+    fn string(&mut self) -> Result<String, ProgramError> {
+        let len = self.take(1)?[0] as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| NFTError::InvalidInstructionData.into())
+    }
+
+    fn u8_or_default(&mut self) -> Result<u8, ProgramError> {
+        if self.data.is_empty() {
+            return Ok(0);
+        }
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16_or_default(&mut self) -> Result<u16, ProgramError> {
+        if self.data.is_empty() {
+            return Ok(0);
+        }
+        self.u16()
+    }
+
+    fn u64_or_default(&mut self) -> Result<u64, ProgramError> {
+        if self.data.is_empty() {
+            return Ok(0);
+        }
+        self.u64()
+    }
+
+    fn pubkey_or_default(&mut self) -> Result<Pubkey, ProgramError> {
+        if self.data.is_empty() {
+            return Ok(Pubkey::default());
+        }
+        let pubkey = self.take(32)?;
+        Ok(Pubkey::new_from_array(*array_ref![pubkey, 0, 32]))
+    }
+
+    fn hash_or_default(&mut self) -> Result<[u8; 32], ProgramError> {
+        if self.data.is_empty() {
+            return Ok([0u8; 32]);
+        }
+        Ok(*array_ref![self.take(32)?, 0, 32])
+    }
+
+    fn bumps(&mut self) -> Result<Bumps, ProgramError> {
+        match *self.take(Bumps::LEN)? {
+            [platform_state, list_state, list_vault, bid_state, bid_vault] => {
+                Ok(Bumps{platform_state, list_state, list_vault, bid_state, bid_vault})
+            }
+            _ => Err(NFTError::InvalidInstructionData.into()),
+        }
+    }
+
+    fn bumps_or_default(&mut self) -> Result<Bumps, ProgramError> {
+        if self.data.is_empty() {
+            return Ok(Bumps::default());
+        }
+        self.bumps()
+    }
+
+    /// Takes everything left, for a trailing variable-length field.
+    fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.data)
+    }
+
+    /// Fails on bytes left over after the last known field.
+    fn finish(self) -> ProgramResult {
+        if !self.data.is_empty() {
+            return Err(NFTError::InvalidInstructionData.into());
+        }
+        Ok(())
+    }
+}
+
+/// Creates an `Initialize` instruction.
+pub fn initialize(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    authority: &Pubkey,
+    maker_bps: u64,
+    taker_bps: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*initializer, true),
+            AccountMeta::new(state_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::Initialize(Initialize{
+            authority: *authority,
+            maker_bps,
+            taker_bps,
+        }).pack(),
+    }
+}
+
+/// Creates a `ChangeAuthority` instruction. With a platform timelock the
+/// change is queued and applied by `execute_param_change`.
+pub fn change_authority(
+    program_id: &Pubkey,
+    current_authority: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*current_authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::ChangeAuthority(ChangeAuthority{
+            authority: *new_authority,
+        }).pack(),
+    }
+}
+
+/// Creates a `ChangeFeeRecipient` instruction.
+pub fn change_fee_recipient(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    fee_recipient: &Pubkey,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::ChangeFeeRecipient(ChangeFeeRecipient{
+            fee_recipient: *fee_recipient,
+        }).pack(),
+    }
+}
+
+/// Creates a `ChangeFees` instruction. With a platform timelock the change
+/// is queued and applied by `execute_param_change`.
+pub fn change_fees(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    maker_bps: u64,
+    taker_bps: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::ChangeFees(ChangeFees{maker_bps, taker_bps}).pack(),
+    }
+}
+
+/// Creates a `List` instruction. When the platform is in curated mode,
+/// `collection` must be the mint's verified collection. In `auction` mode
+/// `amount` is the reserve price. A listing with an `allowed_buyer` can
+/// only be sold to that wallet, and none can be sold before `starts_at`;
+/// `with_allowlist` opens a listing to a set of wallets instead.
+/// A nonzero `royalty_bps_override` pays creators that royalty when it is
+/// above the metadata's, up to the platform cap.
+#[allow(clippy::too_many_arguments)]
+pub fn list(
+    program_id: &Pubkey,
+    lister: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+    auction: bool,
+    collection: Option<&Pubkey>,
+    allowed_buyer: Option<&Pubkey>,
+    starts_at: i64,
+    royalty_bps_override: u16,
+) -> Instruction {
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, lister);
+    let (list_vault_pubkey, _) = state::find_list_vault_address(program_id, mint, lister);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(*lister, true),
+        AccountMeta::new(*lister, true),
+        AccountMeta::new(*token_account, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(list_state_pubkey, false),
+        AccountMeta::new(list_vault_pubkey, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new(state_pubkey, false),
+        AccountMeta::new_readonly(metadata::find_metadata_address(mint).0, false),
+    ];
+    if let Some(collection) = collection {
+        accounts.push(AccountMeta::new_readonly(state::find_collection_state_address(program_id, collection).0, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::List(List{
+            amount,
+            auction,
+            allowed_buyer: allowed_buyer.copied().unwrap_or_default(),
+            starts_at,
+            royalty_bps_override,
+            allowlist_root: [0u8; 32],
+            charity: Pubkey::default(),
+            charity_bps: 0,
+            marketplace: Pubkey::default(),
+            price_usd: 0,
+            settlement_delay_slots: 0,
+            arbiter: Pubkey::default(),
+            visibility: state::VISIBILITY_PUBLIC,
+        }).pack(),
+    }
+}
+
+/// Creates a `DeList` instruction returning `mint` to `token_account`. If
+/// `token_account` is the lister's associated token account and no longer
+/// exists, it is created at the lister's expense.
+pub fn delist(
+    program_id: &Pubkey,
+    lister: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, lister);
+    let (list_vault_pubkey, _) = state::find_list_vault_address(program_id, mint, lister);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*lister, true),
+            AccountMeta::new(*lister, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(list_state_pubkey, false),
+            AccountMeta::new(list_vault_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(state_pubkey, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        ],
+        data: NFTInstruction::DeList(DeList{bumps: Bumps::default()}).pack(),
+    }
+}
+
+/// Creates a `Gift` instruction escrowing `mint` from `sender`'s
+/// `token_account` for `recipient` to claim from `reveal_at`. The sender
+/// can delist it from `reclaimable_at` if it is still unclaimed.
+#[allow(clippy::too_many_arguments)]
+pub fn gift(
+    program_id: &Pubkey,
+    sender: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    recipient: &Pubkey,
+    reveal_at: i64,
+    reclaimable_at: i64,
+    collection: Option<&Pubkey>,
+) -> Instruction {
+    let mut ix = list(program_id, sender, token_account, mint, 0, false, collection, Some(recipient), reveal_at, 0);
+    ix.data = NFTInstruction::Gift(Gift{recipient: *recipient, reveal_at, reclaimable_at}).pack();
+    ix
+}
+
+/// Creates a `ClaimGift` instruction moving `sender`'s gift of `mint` into
+/// `recipient`'s `token_account`.
+pub fn claim_gift(
+    program_id: &Pubkey,
+    recipient: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    sender: &Pubkey,
+) -> Instruction {
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, sender);
+    let (list_vault_pubkey, _) = state::find_list_vault_address(program_id, mint, sender);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*recipient, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*sender, false),
+            AccountMeta::new(*sender, false),
+            AccountMeta::new(list_state_pubkey, false),
+            AccountMeta::new(list_vault_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::ClaimGift(ClaimGift{}).pack(),
+    }
+}
+
+/// Creates a `ListMany` instruction listing each `(token_account, mint,
+/// amount)` in `listings` as a fixed-price public listing. On a curated
+/// platform `collections` must hold each mint's verified collection, in
+/// the same order.
+pub fn list_many(
+    program_id: &Pubkey,
+    lister: &Pubkey,
+    listings: &[(Pubkey, Pubkey, u64)],
+    collections: Option<&[Pubkey]>,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(*lister, true),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new(state_pubkey, false),
+    ];
+    for (i, (token_account, mint, _)) in listings.iter().enumerate() {
+        accounts.push(AccountMeta::new(*token_account, false));
+        accounts.push(AccountMeta::new_readonly(*mint, false));
+        accounts.push(AccountMeta::new(state::find_list_state_address(program_id, mint, lister).0, false));
+        accounts.push(AccountMeta::new(state::find_list_vault_address(program_id, mint, lister).0, false));
+        accounts.push(AccountMeta::new_readonly(metadata::find_metadata_address(mint).0, false));
+        if let Some(collection) = collections.and_then(|collections| collections.get(i)) {
+            accounts.push(AccountMeta::new_readonly(state::find_collection_state_address(program_id, collection).0, false));
+        }
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::ListMany(ListMany{
+            amounts: listings.iter().map(|(_, _, amount)| *amount).collect(),
+        }).pack(),
+    }
+}
+
+/// Creates a `DelistMany` instruction returning each `(token_account,
+/// mint)` listing in `listings` to the lister. Listings whose rent another
+/// payer funded must be closed with `delist` instead.
+pub fn delist_many(
+    program_id: &Pubkey,
+    lister: &Pubkey,
+    listings: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(*lister, true),
+        AccountMeta::new_readonly(*program_id, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(state_pubkey, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+    ];
+    for (token_account, mint) in listings {
+        accounts.push(AccountMeta::new(*token_account, false));
+        accounts.push(AccountMeta::new_readonly(*mint, false));
+        accounts.push(AccountMeta::new(state::find_list_state_address(program_id, mint, lister).0, false));
+        accounts.push(AccountMeta::new(state::find_list_vault_address(program_id, mint, lister).0, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::DelistMany(DelistMany{}).pack(),
+    }
+}
+
+/// Creates a `Bid` instruction. `referrer`, if given, receives a share of
+/// the platform fee when the bid is accepted. `wrapped` bids are escrowed
+/// in a wSOL token account instead of a bare lamport vault.
+pub fn bid(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+    wrapped: bool,
+    referrer: Option<&Pubkey>,
+) -> Instruction {
+    let (bid_state_pubkey, _) = state::find_bid_state_address(program_id, mint, bidder);
+    let (bid_vault_pubkey, _) = state::find_bid_vault_address(program_id, mint, bidder);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(*bidder, true),
+        AccountMeta::new(*bidder, true),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(bid_state_pubkey, false),
+        AccountMeta::new(bid_vault_pubkey, false),
+        AccountMeta::new_readonly(*program_id, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(state_pubkey, false),
+    ];
+    if wrapped {
+        accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
+        accounts.push(AccountMeta::new_readonly(spl_token::native_mint::id(), false));
+    }
+    if let Some(referrer) = referrer {
+        accounts.push(AccountMeta::new_readonly(*referrer, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::Bid(Bid{amount, wrapped, match_accounts: 0, tip_lamports: 0, marketplace: Pubkey::default(), proof: vec![]}).pack(),
+    }
+}
+
+/// Creates an `AuctionBid` instruction on `lister`'s auction listing.
+/// `leader` must be the listing's current highest bidder, if any; their
+/// escrow is refunded in the same instruction.
+pub fn auction_bid(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    mint: &Pubkey,
+    lister: &Pubkey,
+    amount: u64,
+    leader: Option<&Pubkey>,
+    referrer: Option<&Pubkey>,
+) -> Instruction {
+    let (bid_state_pubkey, _) = state::find_bid_state_address(program_id, mint, bidder);
+    let (bid_vault_pubkey, _) = state::find_bid_vault_address(program_id, mint, bidder);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, lister);
+    let mut accounts = vec![
+        AccountMeta::new(*bidder, true),
+        AccountMeta::new(*bidder, true),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(bid_state_pubkey, false),
+        AccountMeta::new(bid_vault_pubkey, false),
+        AccountMeta::new_readonly(*program_id, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(state_pubkey, false),
+        AccountMeta::new(list_state_pubkey, false),
+    ];
+    if let Some(leader) = leader {
+        accounts.push(AccountMeta::new(*leader, false));
+        accounts.push(AccountMeta::new(*leader, false));
+        accounts.push(AccountMeta::new(state::find_bid_state_address(program_id, mint, leader).0, false));
+        accounts.push(AccountMeta::new(state::find_bid_vault_address(program_id, mint, leader).0, false));
+    }
+    if let Some(referrer) = referrer {
+        accounts.push(AccountMeta::new_readonly(*referrer, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::AuctionBid(AuctionBid{amount, proof: vec![]}).pack(),
+    }
+}
+
+/// Creates a `WithdrawBid` instruction.
+pub fn withdraw_bid(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let (bid_state_pubkey, _) = state::find_bid_state_address(program_id, mint, bidder);
+    let (bid_vault_pubkey, _) = state::find_bid_vault_address(program_id, mint, bidder);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*bidder, true),
+            AccountMeta::new(*bidder, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(bid_state_pubkey, false),
+            AccountMeta::new(bid_vault_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::WithdrawBid(WithdrawBid{bumps: Bumps::default()}).pack(),
+    }
+}
+
+/// Creates an `AcceptBid` instruction. `referrer` must be the referrer
+/// recorded on the bid, if any, and `creators` the verified creators from
+/// the mint's metadata, in metadata order. Both parties' `UserStats`
+/// accounts are always passed and are updated if they exist. Passing
+/// `bidder_token` delivers the NFT in the same instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn accept_bid(
+    program_id: &Pubkey,
+    lister: &Pubkey,
+    mint: &Pubkey,
+    bidder: &Pubkey,
+    fee_recipient: &Pubkey,
+    referrer: Option<&Pubkey>,
+    creators: &[Pubkey],
+    bidder_token: Option<&Pubkey>,
+) -> Instruction {
+    let (bid_state_pubkey, _) = state::find_bid_state_address(program_id, mint, bidder);
+    let (bid_vault_pubkey, _) = state::find_bid_vault_address(program_id, mint, bidder);
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, lister);
+    let (list_vault_pubkey, _) = state::find_list_vault_address(program_id, mint, lister);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(*lister, true),
+        AccountMeta::new(*lister, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(*bidder, false),
+        AccountMeta::new(*bidder, false),
+        AccountMeta::new(bid_state_pubkey, false),
+        AccountMeta::new(bid_vault_pubkey, false),
+        AccountMeta::new(list_state_pubkey, false),
+        AccountMeta::new(list_vault_pubkey, false),
+        AccountMeta::new(state_pubkey, false),
+        AccountMeta::new(*fee_recipient, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(memo::spl_memo_program::id(), false),
+    ];
+    if let Some(referrer) = referrer {
+        accounts.push(AccountMeta::new(*referrer, false));
+    }
+    accounts.push(AccountMeta::new_readonly(metadata::find_metadata_address(mint).0, false));
+    for creator in creators {
+        accounts.push(AccountMeta::new(*creator, false));
+    }
+    accounts.push(AccountMeta::new(state::find_user_stats_address(program_id, bidder).0, false));
+    accounts.push(AccountMeta::new(state::find_user_stats_address(program_id, lister).0, false));
+    if let Some(bidder_token) = bidder_token {
+        accounts.push(AccountMeta::new(*bidder_token, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::AcceptBid(AcceptBid{memo: vec![], bumps: Bumps::default(), expected_amount: 0}).pack(),
+    }
+}
+
+/// Creates an `ExecuteSale` instruction: an `AcceptBid` that must deliver
+/// the NFT to `bidder_token`, so the sale never sits half-settled waiting
+/// on `WithdrawNFTOnSuccess`.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_sale(
+    program_id: &Pubkey,
+    lister: &Pubkey,
+    mint: &Pubkey,
+    bidder: &Pubkey,
+    fee_recipient: &Pubkey,
+    referrer: Option<&Pubkey>,
+    creators: &[Pubkey],
+    bidder_token: &Pubkey,
+) -> Instruction {
+    let mut instruction = accept_bid(program_id, lister, mint, bidder, fee_recipient, referrer, creators, Some(bidder_token));
+    instruction.data = NFTInstruction::ExecuteSale(ExecuteSale{memo: vec![], bumps: Bumps::default(), expected_amount: 0}).pack();
+    instruction
+}
+
+/// Creates an `ExecuteSale` instruction that the lister does not sign. It
+/// settles only if the bid meets the listing's ask, and anyone can send it
+/// or invoke it by CPI, such as an aggregator crossing orders.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_matched_sale(
+    program_id: &Pubkey,
+    lister: &Pubkey,
+    mint: &Pubkey,
+    bidder: &Pubkey,
+    fee_recipient: &Pubkey,
+    referrer: Option<&Pubkey>,
+    creators: &[Pubkey],
+    bidder_token: &Pubkey,
+) -> Instruction {
+    let mut instruction = execute_sale(program_id, lister, mint, bidder, fee_recipient, referrer, creators, bidder_token);
+    instruction.accounts[0].is_signer = false;
+    instruction
+}
+
+/// Attaches `memo` to an `AcceptBid` or `ExecuteSale` instruction. It is
+/// recorded with the Memo program after the canonical sale memo.
+pub fn with_memo(mut instruction: Instruction, memo: &str) -> Instruction {
+    instruction.data.extend_from_slice(memo.as_bytes());
+    instruction
+}
+
+/// Re-encodes a `DeList`, `WithdrawBid`, `AcceptBid` or `ExecuteSale`
+/// instruction as version 2 data carrying `bumps`, sparing the program
+/// the address searches. Any memo is kept. Other instructions are
+/// returned unchanged.
+pub fn with_bumps(mut instruction: Instruction, bumps: Bumps) -> Instruction {
+    let mut decoded = match NFTInstruction::unpack(&instruction.data) {
+        Ok(decoded) => decoded,
+        Err(_) => return instruction,
+    };
+    match &mut decoded {
+        NFTInstruction::DeList(DeList{bumps: slot})
+        | NFTInstruction::WithdrawBid(WithdrawBid{bumps: slot})
+        | NFTInstruction::AcceptBid(AcceptBid{bumps: slot, ..})
+        | NFTInstruction::ExecuteSale(ExecuteSale{bumps: slot, ..}) => *slot = bumps,
+        _ => return instruction,
+    }
+    instruction.data = decoded.pack_v2();
+    instruction
+}
+
+/// Makes an `AcceptBid` or `ExecuteSale` instruction fail with
+/// `PriceMismatch` unless the bid is still at least `expected_amount`, the
+/// amount the lister saw when signing. The instruction is re-encoded as
+/// version 2 data; any memo and bumps are kept. Other instructions are
+/// returned unchanged.
+pub fn with_expected_amount(mut instruction: Instruction, expected_amount: u64) -> Instruction {
+    let mut decoded = match NFTInstruction::unpack(&instruction.data) {
+        Ok(decoded) => decoded,
+        Err(_) => return instruction,
+    };
+    match &mut decoded {
+        NFTInstruction::AcceptBid(AcceptBid{expected_amount: slot, ..})
+        | NFTInstruction::ExecuteSale(ExecuteSale{expected_amount: slot, ..}) => *slot = expected_amount,
+        _ => return instruction,
+    }
+    instruction.data = decoded.pack_v2();
+    instruction
+}
+
+/// Adds the curator of a curated listing to an `AcceptBid`, `ExecuteSale`
+/// or matched `ExecuteSale` instruction, to be paid its fee share. It goes
+/// right before the metadata account.
+pub fn with_curator(mut instruction: Instruction, curator: &Pubkey) -> Instruction {
+    let (metadata_pubkey, _) = metadata::find_metadata_address(&instruction.accounts[2].pubkey);
+    if let Some(position) = instruction.accounts.iter().position(|account| account.pubkey == metadata_pubkey) {
+        instruction.accounts.insert(position, AccountMeta::new(*curator, false));
+    }
+    instruction
+}
+
+/// Adds the charity of a listing with a charity split to an `AcceptBid`,
+/// `ExecuteSale` or matched `ExecuteSale` instruction, after any curator
+/// and right before the metadata account. `ExecuteCrowdfund` has no
+/// metadata account, so the charity goes last there.
+pub fn with_charity_payout(mut instruction: Instruction, charity: &Pubkey) -> Instruction {
+    let (metadata_pubkey, _) = metadata::find_metadata_address(&instruction.accounts[2].pubkey);
+    let position = instruction.accounts
+        .iter()
+        .position(|account| account.pubkey == metadata_pubkey)
+        .unwrap_or_else(|| instruction.accounts.len());
+    instruction.accounts.insert(position, AccountMeta::new(*charity, false));
+    instruction
+}
+
+/// Adds a marketplace credited with the listing or the bid to an
+/// `AcceptBid`, `ExecuteSale` or matched `ExecuteSale` instruction, to be
+/// paid its fee share. It goes right before the metadata account, after
+/// any curator and charity; add the listing's marketplace before the bid's.
+pub fn with_marketplace_payout(mut instruction: Instruction, marketplace: &Pubkey) -> Instruction {
+    let (metadata_pubkey, _) = metadata::find_metadata_address(&instruction.accounts[2].pubkey);
+    if let Some(position) = instruction.accounts.iter().position(|account| account.pubkey == metadata_pubkey) {
+        instruction.accounts.insert(position, AccountMeta::new(*marketplace, false));
+    }
+    instruction
+}
+
+/// Creates a `BatchExecuteSale` instruction settling every `AcceptBid`
+/// instruction in `accept_bids`, all signed by the same lister.
+pub fn batch_execute_sale(
+    program_id: &Pubkey,
+    accept_bids: &[Instruction],
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: accept_bids.iter().flat_map(|ix| ix.accounts.iter().cloned()).collect(),
+        data: NFTInstruction::BatchExecuteSale(BatchExecuteSale{
+            account_counts: accept_bids.iter().map(|ix| ix.accounts.len() as u8).collect(),
+        }).pack(),
+    }
+}
+
+/// Creates a `SweepBuy` instruction making each purchase in `purchases`,
+/// unwrapped `Bid` instructions signed by the buyer with `with_auto_match`
+/// applied, as long as the total spent, fees included, stays within
+/// `max_spend`. Each buys at its listing's ask, failing the sweep with
+/// `PriceMismatch` if that is above the bid's amount. Purchases whose
+/// listing has gone or that would overrun the budget are skipped. The program cannot meter compute, so size the sweep to fit the
+/// transaction's limit.
+pub fn sweep_buy(
+    program_id: &Pubkey,
+    max_spend: u64,
+    purchases: &[Instruction],
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![AccountMeta::new_readonly(state_pubkey, false)];
+    let mut sweep_purchases = Vec::with_capacity(purchases.len());
+    for purchase in purchases {
+        let (max_price, match_accounts) = match NFTInstruction::unpack(&purchase.data) {
+            Ok(NFTInstruction::Bid(Bid{amount, match_accounts, ..})) => (amount, match_accounts),
+            _ => (0, 0),
+        };
+        accounts.extend(purchase.accounts.iter().cloned());
+        sweep_purchases.push(SweepPurchase{
+            account_count: purchase.accounts.len() as u8,
+            match_accounts,
+            max_price,
+        });
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::SweepBuy(SweepBuy{max_spend, purchases: sweep_purchases}).pack(),
+    }
+}
+
+/// Creates a `WithdrawNFTOnSuccess` instruction. If `token_account` is
+/// the buyer's associated token account and does not exist yet, it is
+/// created during settlement at the buyer's expense.
+pub fn withdraw_nft_on_success(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    lister: &Pubkey,
+) -> Instruction {
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, lister);
+    let (list_vault_pubkey, _) = state::find_list_vault_address(program_id, mint, lister);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(*lister, false),
+            AccountMeta::new(*lister, false),
+            AccountMeta::new(list_state_pubkey, false),
+            AccountMeta::new(list_vault_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        ],
+        data: NFTInstruction::WithdrawNFTOnSuccess(WithdrawNFTOnSuccess{}).pack(),
+    }
+}
+
+/// Creates a `RefundUser` instruction.
+pub fn refund_user(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    bidder: &Pubkey,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (bid_state_pubkey, _) = state::find_bid_state_address(program_id, mint, bidder);
+    let (bid_vault_pubkey, _) = state::find_bid_vault_address(program_id, mint, bidder);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(*bidder, false),
+            AccountMeta::new(*bidder, false),
+            AccountMeta::new_readonly(state_pubkey, false),
+            AccountMeta::new(bid_state_pubkey, false),
+            AccountMeta::new(bid_vault_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::RefundUser(RefundUser{}).pack(),
+    }
+}
+
+/// Appends `lister`'s auction listing of `mint` to a `RefundUser`,
+/// `ThreadRefundBid` or `ExpireAndRefund` instruction. Bids placed through `AuctionBid` can only
+/// be refunded with it, and not while they lead the auction.
+pub fn with_auction_listing(mut instruction: Instruction, mint: &Pubkey, lister: &Pubkey) -> Instruction {
+    let (list_state_pubkey, _) = state::find_list_state_address(&instruction.program_id, mint, lister);
+    instruction.accounts.push(AccountMeta::new_readonly(list_state_pubkey, false));
+    instruction
+}
+
+/// Creates a `ChangeReferralFee` instruction.
+pub fn change_referral_fee(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    referral_fee_bps: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::ChangeReferralFee(ChangeReferralFee{referral_fee_bps}).pack(),
+    }
+}
+
+/// Creates a `SetCuratedMode` instruction.
+pub fn set_curated_mode(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    curated: bool,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::SetCuratedMode(SetCuratedMode{curated}).pack(),
+    }
+}
+
+/// Creates a `SetStrictMode` instruction.
+pub fn set_strict_mode(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    strict: bool,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::SetStrictMode(SetStrictMode{strict}).pack(),
+    }
+}
+
+/// Creates a `SetMinimums` instruction. Lists below `min_listing_lamports`
+/// and bids below `min_bid_lamports` are rejected.
+pub fn set_minimums(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    min_listing_lamports: u64,
+    min_bid_lamports: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::SetMinimums(SetMinimums{min_listing_lamports, min_bid_lamports}).pack(),
+    }
+}
+
+/// Creates a `SetBidCooldown` instruction. Bids placed afterwards cannot
+/// be withdrawn until `slots` slots have passed.
+pub fn set_bid_cooldown(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    slots: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::SetBidCooldown(SetBidCooldown{slots}).pack(),
+    }
+}
+
+/// Creates a `SetPaused` instruction replacing the platform's pause mask
+/// with `paused`, a set of `state::PAUSE_*` bits. Zero resumes everything.
+pub fn set_paused(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    paused: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::SetPaused(SetPaused{paused}).pack(),
+    }
+}
+
+/// Creates a `SetAutomationThread` instruction registering `thread` as the
+/// signer of the platform's recurring jobs. Bids count as stale once they
+/// have been withdrawable for `stale_bid_slots` slots.
+pub fn set_automation_thread(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    thread: &Pubkey,
+    stale_bid_slots: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::SetAutomationThread(SetAutomationThread{thread: *thread, stale_bid_slots}).pack(),
+    }
+}
+
+/// Creates a `ThreadRefundBid` instruction for the automation thread to
+/// refund a stale bid. The accounts are fixed, so a thread can be
+/// scheduled with them once and replayed every run.
+pub fn thread_refund_bid(
+    program_id: &Pubkey,
+    thread: &Pubkey,
+    mint: &Pubkey,
+    bidder: &Pubkey,
+    rent_payer: &Pubkey,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (bid_state_pubkey, _) = state::find_bid_state_address(program_id, mint, bidder);
+    let (bid_vault_pubkey, _) = state::find_bid_vault_address(program_id, mint, bidder);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*thread, true),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(*bidder, false),
+            AccountMeta::new(*rent_payer, false),
+            AccountMeta::new_readonly(state_pubkey, false),
+            AccountMeta::new(bid_state_pubkey, false),
+            AccountMeta::new(bid_vault_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::ThreadRefundBid(ThreadRefundBid{}).pack(),
+    }
+}
+
+/// Creates a `SetBidExpiry` instruction for `bidder`'s escrowed bid on
+/// `mint`. It can follow the `Bid` in the same transaction, unless the bid
+/// settles against a listing straight away.
+pub fn set_bid_expiry(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    mint: &Pubkey,
+    expires_at: i64,
+) -> Instruction {
+    let (bid_state_pubkey, _) = state::find_bid_state_address(program_id, mint, bidder);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*bidder, true),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(bid_state_pubkey, false),
+        ],
+        data: NFTInstruction::SetBidExpiry(SetBidExpiry{expires_at}).pack(),
+    }
+}
+
+/// Creates an `ExpireAndRefund` instruction refunding `bidder`'s expired
+/// bid on `mint`. Anyone can send it, and `caller` is paid
+/// `state::EXPIRE_REWARD_BPS` of the bid's rent for it.
+pub fn expire_and_refund(
+    program_id: &Pubkey,
+    caller: &Pubkey,
+    mint: &Pubkey,
+    bidder: &Pubkey,
+    rent_payer: &Pubkey,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (bid_state_pubkey, _) = state::find_bid_state_address(program_id, mint, bidder);
+    let (bid_vault_pubkey, _) = state::find_bid_vault_address(program_id, mint, bidder);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*caller, true),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(*bidder, false),
+            AccountMeta::new(*rent_payer, false),
+            AccountMeta::new_readonly(state_pubkey, false),
+            AccountMeta::new(bid_state_pubkey, false),
+            AccountMeta::new(bid_vault_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::ExpireAndRefund(ExpireAndRefund{}).pack(),
+    }
+}
+
+/// Creates a `ReverseSettlement` instruction for the platform authority or
+/// the listing's arbiter, refunding `bidder`'s held bid on `lister`'s
+/// listing of `mint` and opening the listing again.
+pub fn reverse_settlement(
+    program_id: &Pubkey,
+    arbiter: &Pubkey,
+    mint: &Pubkey,
+    lister: &Pubkey,
+    bidder: &Pubkey,
+    rent_payer: &Pubkey,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (bid_state_pubkey, _) = state::find_bid_state_address(program_id, mint, bidder);
+    let (bid_vault_pubkey, _) = state::find_bid_vault_address(program_id, mint, bidder);
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, lister);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*arbiter, true),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(*bidder, false),
+            AccountMeta::new(*rent_payer, false),
+            AccountMeta::new_readonly(state_pubkey, false),
+            AccountMeta::new(bid_state_pubkey, false),
+            AccountMeta::new(bid_vault_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(list_state_pubkey, false),
+        ],
+        data: NFTInstruction::ReverseSettlement(ReverseSettlement{}).pack(),
+    }
+}
+
+/// Creates a `FinalizeSettlement` instruction settling a held sale once its
+/// dispute window has closed. It takes the accounts of an
+/// `execute_matched_sale`, and anyone can send it.
+#[allow(clippy::too_many_arguments)]
+pub fn finalize_settlement(
+    program_id: &Pubkey,
+    lister: &Pubkey,
+    mint: &Pubkey,
+    bidder: &Pubkey,
+    fee_recipient: &Pubkey,
+    referrer: Option<&Pubkey>,
+    creators: &[Pubkey],
+    bidder_token: &Pubkey,
+) -> Instruction {
+    let mut instruction = execute_matched_sale(program_id, lister, mint, bidder, fee_recipient, referrer, creators, bidder_token);
+    instruction.data = NFTInstruction::FinalizeSettlement(FinalizeSettlement{}).pack();
+    instruction
+}
+
+/// Creates a `MintAndList` instruction that mints a new NFT at `mint`, a
+/// fresh keypair that also signs, into the seller's associated token
+/// account and lists it at `price`. With `metadata` set, Token Metadata
+/// creates its metadata account first.
+pub fn mint_and_list(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    mint: &Pubkey,
+    price: u64,
+    metadata: Option<NewMetadata>,
+) -> Instruction {
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, seller);
+    let (list_vault_pubkey, _) = state::find_list_vault_address(program_id, mint, seller);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(*seller, true),
+        AccountMeta::new(*mint, true),
+        AccountMeta::new(spl_associated_token_account::get_associated_token_address(seller, mint), false),
+        AccountMeta::new(list_state_pubkey, false),
+        AccountMeta::new(list_vault_pubkey, false),
+        AccountMeta::new(state_pubkey, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+    if metadata.is_some() {
+        accounts.push(AccountMeta::new(metadata::find_metadata_address(mint).0, false));
+        accounts.push(AccountMeta::new_readonly(metadata::token_metadata_program::id(), false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::MintAndList(MintAndList{price, metadata}).pack(),
+    }
+}
+
+/// Creates a `SetMaxRoyalty` instruction capping the royalty, in basis
+/// points, a listing may opt into paying. Zero disables overrides.
+pub fn set_max_royalty(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    max_royalty_bps: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::SetMaxRoyalty(SetMaxRoyalty{max_royalty_bps}).pack(),
+    }
+}
+
+/// Creates a `SetMaxCharity` instruction capping the share of their
+/// proceeds, in basis points, a lister may give to charity. Zero disables
+/// charity splits.
+pub fn set_max_charity(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    max_charity_bps: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::SetMaxCharity(SetMaxCharity{max_charity_bps}).pack(),
+    }
+}
+
+/// Creates a `SetMarketplaceFee` instruction paying each marketplace
+/// credited with a sale `marketplace_fee_bps` of the platform fee left
+/// after any referral and curator fee. At most half of it, since a sale can
+/// credit two marketplaces.
+pub fn set_marketplace_fee(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    marketplace_fee_bps: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::SetMarketplaceFee(SetMarketplaceFee{marketplace_fee_bps}).pack(),
+    }
+}
+
+/// Creates a `SetPriceFeed` instruction naming the Pyth SOL/USD price
+/// account new USD priced listings are filled against. Listings already
+/// open keep the feed they were listed under.
+pub fn set_price_feed(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    price_feed: &Pubkey,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::SetPriceFeed(SetPriceFeed{price_feed: *price_feed}).pack(),
+    }
+}
+
+/// Creates a `SetAttestation` instruction naming `attestor` and turning
+/// the attestation requirement on or off. Creates the config on first use,
+/// funded by the authority.
+pub fn set_attestation(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    attestor: &Pubkey,
+    required: bool,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (config_pubkey, _) = attestation::find_attestation_config_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+            AccountMeta::new(config_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::SetAttestation(SetAttestation{attestor: *attestor, required}).pack(),
+    }
+}
+
+/// Appends the attestation config and the attestation `attestor` issued
+/// `wallet`, the lister or bidder, to a `List`, `ListMany`, `MintAndList`,
+/// `Gift` or `Bid` instruction, or the recipient, to a `ClaimGift`
+/// instruction. Apply it after `with_collection_stats` and before
+/// `with_fee_discount`, `with_auto_match` and `with_session`.
+pub fn with_attestation(mut instruction: Instruction, attestor: &Pubkey, wallet: &Pubkey) -> Instruction {
+    let (config_pubkey, _) = attestation::find_attestation_config_address(&instruction.program_id);
+    let (attestation_pubkey, _) = attestation::find_attestation_address(attestor, wallet);
+    instruction.accounts.push(AccountMeta::new_readonly(config_pubkey, false));
+    instruction.accounts.push(AccountMeta::new_readonly(attestation_pubkey, false));
+    instruction
+}
+
+/// Creates a `SetVisibility` instruction for `lister` to show its listing
+/// of `mint` in public feeds or keep it unlisted. Unlisting a collection's
+/// floor listing clears the floor if the instruction is built
+/// `with_collection_stats`.
+pub fn set_visibility(
+    program_id: &Pubkey,
+    lister: &Pubkey,
+    mint: &Pubkey,
+    visibility: u8,
+) -> Instruction {
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, lister);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*lister, true),
+            AccountMeta::new(list_state_pubkey, false),
+        ],
+        data: NFTInstruction::SetVisibility(SetVisibility{visibility}).pack(),
+    }
+}
+
+/// Creates a `SetCurator` instruction. While `curator` is set, new
+/// listings cannot sell until it approves them, and it takes
+/// `curator_fee_bps` of the platform fee on their sales. The default key
+/// turns curation off for new listings.
+pub fn set_curator(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    curator: &Pubkey,
+    curator_fee_bps: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::SetCurator(SetCurator{curator: *curator, curator_fee_bps}).pack(),
+    }
+}
+
+/// Creates an `ApproveListing` instruction for the platform's curator to
+/// open `lister`'s pending listing of `mint` to sales.
+pub fn approve_listing(
+    program_id: &Pubkey,
+    curator: &Pubkey,
+    mint: &Pubkey,
+    lister: &Pubkey,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, lister);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*curator, true),
+            AccountMeta::new_readonly(state_pubkey, false),
+            AccountMeta::new(list_state_pubkey, false),
+        ],
+        data: NFTInstruction::ApproveListing(ApproveListing{}).pack(),
+    }
+}
+
+/// Creates a `ScheduleParamChange` instruction queueing `change` for
+/// `execute_slot`, which must be at least the platform timelock away. It
+/// replaces any change already queued.
+pub fn schedule_param_change(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    change: state::ParamChange,
+    execute_slot: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::ScheduleParamChange(ScheduleParamChange{change, execute_slot}).pack(),
+    }
+}
+
+/// Creates an `ExecuteParamChange` instruction applying the queued change.
+/// Anyone can send it once the change's slot is reached.
+pub fn execute_param_change(program_id: &Pubkey) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::ExecuteParamChange(ExecuteParamChange{}).pack(),
+    }
+}
+
+/// Creates an `AllowCollection` instruction.
+pub fn allow_collection(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    collection: &Pubkey,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (collection_state_pubkey, _) = state::find_collection_state_address(program_id, collection);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(state_pubkey, false),
+            AccountMeta::new(collection_state_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::AllowCollection(AllowCollection{collection: *collection}).pack(),
+    }
+}
+
+/// Creates a `RevokeCollection` instruction.
+pub fn revoke_collection(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    collection: &Pubkey,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (collection_state_pubkey, _) = state::find_collection_state_address(program_id, collection);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(state_pubkey, false),
+            AccountMeta::new(collection_state_pubkey, false),
+        ],
+        data: NFTInstruction::RevokeCollection(RevokeCollection{collection: *collection}).pack(),
+    }
+}
+
+/// Creates a `CreateUserStats` instruction.
+pub fn create_user_stats(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+) -> Instruction {
+    let (user_stats_pubkey, _) = state::find_user_stats_address(program_id, owner);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(user_stats_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::CreateUserStats(CreateUserStats{}).pack(),
+    }
+}
+
+/// Creates a `CreateCollectionStats` instruction, paid for by `payer`.
+pub fn create_collection_stats(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    collection: &Pubkey,
+) -> Instruction {
+    let (collection_stats_pubkey, _) = state::find_collection_stats_address(program_id, collection);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(collection_stats_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::CreateCollectionStats(CreateCollectionStats{
+            collection: *collection,
+        }).pack(),
+    }
+}
+
+/// Creates a `CreateIndex` instruction for the `kind` index of `key`,
+/// paid for by `payer`.
+pub fn create_index(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    kind: index::IndexKind,
+    key: &Pubkey,
+) -> Instruction {
+    let (index_pubkey, _) = kind.find_address(program_id, key);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(index_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::CreateIndex(CreateIndex{kind, key: *key}).pack(),
+    }
+}
+
+/// Creates a `SyncIndex` instruction reconciling the `kind` index of `key`
+/// against `escrows`: list states for an owner index, bid states for a
+/// mint index. Closed escrows are dropped from the index.
+pub fn sync_index(
+    program_id: &Pubkey,
+    kind: index::IndexKind,
+    key: &Pubkey,
+    escrows: &[Pubkey],
+) -> Instruction {
+    let (index_pubkey, _) = kind.find_address(program_id, key);
+    let mut accounts = vec![AccountMeta::new(index_pubkey, false)];
+    for escrow in escrows {
+        accounts.push(AccountMeta::new_readonly(*escrow, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::SyncIndex(SyncIndex{}).pack(),
+    }
+}
+
+/// Appends `collection`'s stats account to a `List`, `DeList`,
+/// `AcceptBid`, `ExecuteSale` or `SetVisibility` instruction. Apply it before `with_fee_discount`.
+pub fn with_collection_stats(mut instruction: Instruction, collection: &Pubkey) -> Instruction {
+    let (collection_stats_pubkey, _) = state::find_collection_stats_address(&instruction.program_id, collection);
+    instruction.accounts.push(AccountMeta::new(collection_stats_pubkey, false));
+    instruction
+}
+
+/// Lets a `Bid` instruction buy straight from the listing `execute_sale`
+/// settles against when the bid meets its ask; otherwise the bid is escrowed
+/// as usual. Leaving it off opts the bid out of matching. Apply it after
+/// `with_payer` and before `with_session`.
+pub fn with_auto_match(mut bid: Instruction, execute_sale: Instruction) -> Instruction {
+    let mut sale_accounts = execute_sale.accounts;
+    // The lister does not sign, and the bid's rent goes back to whoever
+    // funds it here.
+    if let Some(lister) = sale_accounts.get_mut(0) {
+        lister.is_signer = false;
+    }
+    if let (Some(bid_rent_payer), Some(payer)) = (sale_accounts.get_mut(4), bid.accounts.get(1)) {
+        bid_rent_payer.pubkey = payer.pubkey;
+    }
+    if let Ok(NFTInstruction::Bid(bid_data)) = NFTInstruction::unpack(&bid.data) {
+        bid.data = NFTInstruction::Bid(Bid{match_accounts: sale_accounts.len() as u8, ..bid_data}).pack();
+    }
+    bid.accounts.extend(sale_accounts);
+    bid
+}
+
+/// Restricts a `List` instruction's listing to the wallets under `root`,
+/// built with `merkle::root`. Such listings can't also name an allowed
+/// buyer, and sell only through `AuctionBid` or a `Bid` matched against
+/// them, which carry the buyer's proof.
+pub fn with_allowlist(mut list: Instruction, root: [u8; 32]) -> Instruction {
+    if let Ok(NFTInstruction::List(list_data)) = NFTInstruction::unpack(&list.data) {
+        list.data = NFTInstruction::List(List{allowlist_root: root, ..list_data}).pack();
+    }
+    list
+}
+
+/// Has a `List` instruction give `charity_bps` of the lister's proceeds to
+/// `charity` when the listing sells.
+pub fn with_charity(mut list: Instruction, charity: &Pubkey, charity_bps: u16) -> Instruction {
+    if let Ok(NFTInstruction::List(list_data)) = NFTInstruction::unpack(&list.data) {
+        list.data = NFTInstruction::List(List{charity: *charity, charity_bps, ..list_data}).pack();
+    }
+    list
+}
+
+/// Holds the sales of a `List` instruction's listing for `delay_slots`
+/// before they can be finalized, so the platform authority or `arbiter`
+/// can reverse them. Such listings only sell to bids their lister accepts.
+pub fn with_delayed_settlement(mut list: Instruction, delay_slots: u64, arbiter: Option<&Pubkey>) -> Instruction {
+    if let Ok(NFTInstruction::List(list_data)) = NFTInstruction::unpack(&list.data) {
+        list.data = NFTInstruction::List(List{
+            settlement_delay_slots: delay_slots,
+            arbiter: arbiter.copied().unwrap_or_default(),
+            ..list_data
+        }).pack();
+    }
+    list
+}
+
+/// Sets the visibility of a `List` instruction's listing, such as
+/// `state::VISIBILITY_UNLISTED` to share it only by its address.
+pub fn with_visibility(mut list: Instruction, visibility: u8) -> Instruction {
+    if let Ok(NFTInstruction::List(list_data)) = NFTInstruction::unpack(&list.data) {
+        list.data = NFTInstruction::List(List{visibility, ..list_data}).pack();
+    }
+    list
+}
+
+/// Prices a `List` instruction's listing at `price_usd` millionths of a
+/// dollar instead of lamports. It then only sells through an `ExecuteSale`
+/// built `with_price_feed`, for whatever that many dollars are worth at
+/// the time, or to a bid its lister accepts.
+pub fn with_usd_price(mut list: Instruction, price_usd: u64) -> Instruction {
+    if let Ok(NFTInstruction::List(list_data)) = NFTInstruction::unpack(&list.data) {
+        list.data = NFTInstruction::List(List{amount: 0, price_usd, ..list_data}).pack();
+    }
+    list
+}
+
+/// Appends the platform's `price_feed` to an `execute_matched_sale`
+/// instruction so it can fill a listing priced in USD. It has to come last,
+/// after any fee discount accounts.
+pub fn with_price_feed(mut sale: Instruction, price_feed: &Pubkey) -> Instruction {
+    sale.accounts.push(AccountMeta::new_readonly(*price_feed, false));
+    sale
+}
+
+/// Credits `marketplace` with a `List` or `Bid` instruction. Other
+/// instructions are returned unchanged.
+pub fn with_marketplace(mut instruction: Instruction, marketplace: &Pubkey) -> Instruction {
+    let marketplace = *marketplace;
+    instruction.data = match NFTInstruction::unpack(&instruction.data) {
+        Ok(NFTInstruction::List(list_data)) => NFTInstruction::List(List{marketplace, ..list_data}).pack(),
+        Ok(NFTInstruction::Bid(bid_data)) => NFTInstruction::Bid(Bid{marketplace, ..bid_data}).pack(),
+        _ => return instruction,
+    };
+    instruction
+}
+
+/// Has a `Bid` instruction tip the mint's first verified creator
+/// `tip_lamports` on top of the price if it buys the listing through
+/// `with_auto_match`. The tip is recorded in a memo after the sale's.
+pub fn with_creator_tip(mut bid: Instruction, tip_lamports: u64) -> Instruction {
+    if let Ok(NFTInstruction::Bid(bid_data)) = NFTInstruction::unpack(&bid.data) {
+        bid.data = NFTInstruction::Bid(Bid{tip_lamports, ..bid_data}).pack();
+    }
+    bid
+}
+
+/// Adds `proof`, from `merkle::proof`, that the bidder is on the allowlist
+/// of the listing a `Bid` or `AuctionBid` instruction buys or bids on.
+pub fn with_allowlist_proof(mut bid: Instruction, proof: Vec<[u8; 32]>) -> Instruction {
+    bid.data = match NFTInstruction::unpack(&bid.data) {
+        Ok(NFTInstruction::Bid(bid_data)) => NFTInstruction::Bid(Bid{proof, ..bid_data}).pack(),
+        Ok(NFTInstruction::AuctionBid(AuctionBid{amount, ..})) => NFTInstruction::AuctionBid(AuctionBid{amount, proof}).pack(),
+        _ => bid.data,
+    };
+    bid
+}
+
+/// Makes `payer` fund the escrow accounts `party` opens in a `List`, `Bid`
+/// or `AuctionBid` instruction, or take their rent back when `party`'s
+/// escrow is closed. Every such instruction has a payer slot right after
+/// `party`'s account, defaulting to `party`; a funding payer must also
+/// sign.
+pub fn with_payer(mut instruction: Instruction, party: &Pubkey, payer: &Pubkey) -> Instruction {
+    if let Some(index) = instruction.accounts.iter().position(|meta| meta.pubkey.eq(party)) {
+        if let Some(slot) = instruction.accounts.get_mut(index + 1) {
+            slot.pubkey = *payer;
+        }
+    }
+    instruction
+}
+
+/// Turns a `List`, `Bid` or `AuctionBid` instruction built for `owner`
+/// into one signed by `owner`'s `session_key` instead. The session key also
+/// takes over the default payer slot. Apply it after every other decorator.
+pub fn with_session(mut instruction: Instruction, owner: &Pubkey, session_key: &Pubkey) -> Instruction {
+    let (session_pubkey, _) = session::find_session_address(&instruction.program_id, owner, session_key);
+    if let Some(index) = instruction.accounts.iter().position(|meta| meta.pubkey.eq(owner)) {
+        if let Some(slot) = instruction.accounts.get_mut(index + 1).filter(|slot| slot.pubkey.eq(owner)) {
+            slot.pubkey = *session_key;
+        }
+    }
+    for meta in instruction.accounts.iter_mut().filter(|meta| meta.pubkey.eq(owner)) {
+        meta.is_signer = false;
+    }
+    instruction.accounts.push(AccountMeta::new_readonly(session_pubkey, false));
+    instruction.accounts.push(AccountMeta::new(*session_key, true));
+    instruction
+}
+
+/// Creates a `FillOrder` instruction buying `mint` from `seller` on the
+/// terms of their signed `order`. It must be preceded by
+/// `order::ed25519_instruction` over the same terms, and the seller must
+/// have approved the order delegate on `seller_token_account`.
+#[allow(clippy::too_many_arguments)]
+pub fn fill_order(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    buyer_token_account: &Pubkey,
+    seller: &Pubkey,
+    seller_token_account: &Pubkey,
+    mint: &Pubkey,
+    fee_recipient: &Pubkey,
+    order: FillOrder,
+    collection: Option<&Pubkey>,
+    creators: &[Pubkey],
+) -> Instruction {
+    let (delegate_pubkey, _) = state::find_order_delegate_address(program_id);
+    let (receipt_pubkey, _) = state::find_order_receipt_address(program_id, seller, order.nonce);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(*buyer, true),
+        AccountMeta::new(*seller, false),
+        AccountMeta::new(*seller_token_account, false),
+        AccountMeta::new(*buyer_token_account, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(delegate_pubkey, false),
+        AccountMeta::new(receipt_pubkey, false),
+        AccountMeta::new_readonly(*program_id, false),
+        AccountMeta::new(state_pubkey, false),
+        AccountMeta::new(*fee_recipient, false),
+        AccountMeta::new_readonly(sysvar::instructions::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(metadata::find_metadata_address(mint).0, false),
+    ];
+    if let Some(collection) = collection {
+        accounts.push(AccountMeta::new_readonly(state::find_collection_state_address(program_id, collection).0, false));
+    }
+    for creator in creators {
+        accounts.push(AccountMeta::new(*creator, false));
+    }
+    accounts.push(AccountMeta::new(state::find_user_stats_address(program_id, buyer).0, false));
+    accounts.push(AccountMeta::new(state::find_user_stats_address(program_id, seller).0, false));
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::FillOrder(order).pack(),
+    }
+}
+
+/// Creates a `CancelOrder` instruction that burns `nonce` so no order
+/// signed with it can be filled.
+pub fn cancel_order(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    nonce: u64,
+) -> Instruction {
+    let (receipt_pubkey, _) = state::find_order_receipt_address(program_id, seller, nonce);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*seller, true),
+            AccountMeta::new(receipt_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::CancelOrder(CancelOrder{nonce}).pack(),
+    }
+}
+
+/// Creates a `CreateOrderBook` instruction that opens the empty order book
+/// for `collection`, paid for by `payer`.
+pub fn create_order_book(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    collection: &Pubkey,
+) -> Instruction {
+    let (order_book_pubkey, _) = state::find_order_book_address(program_id, collection);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(order_book_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::CreateOrderBook(CreateOrderBook{collection: *collection}).pack(),
+    }
+}
+
+/// Creates an `UpdateOrderBook` instruction that re-reads `maker`'s listing
+/// (`BookSide::Ask`) or bid (`BookSide::Bid`) on `mint` and refreshes its
+/// entry in `collection`'s order book. Append it after any instruction that
+/// opens, closes or settles that escrow.
+pub fn update_order_book(
+    program_id: &Pubkey,
+    collection: &Pubkey,
+    mint: &Pubkey,
+    side: state::BookSide,
+    maker: &Pubkey,
+) -> Instruction {
+    let (order_book_pubkey, _) = state::find_order_book_address(program_id, collection);
+    let (escrow_state_pubkey, _) = match side {
+        state::BookSide::Ask => state::find_list_state_address(program_id, mint, maker),
+        state::BookSide::Bid => state::find_bid_state_address(program_id, mint, maker),
+    };
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(order_book_pubkey, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(escrow_state_pubkey, false),
+            AccountMeta::new_readonly(metadata::find_metadata_address(mint).0, false),
+        ],
+        data: NFTInstruction::UpdateOrderBook(UpdateOrderBook{side, maker: *maker}).pack(),
+    }
+}
+
+/// Creates a `CreatePool` instruction opening `owner`'s pool for
+/// `collection`. Exponential curves take `delta` in basis points.
+pub fn create_pool(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    collection: &Pubkey,
+    curve: pool::Curve,
+    spot_price: u64,
+    delta: u64,
+    fee_bps: u64,
+) -> Instruction {
+    let (pool_pubkey, _) = pool::find_pool_address(program_id, owner, collection);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(pool_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::CreatePool(CreatePool{collection: *collection, curve, spot_price, delta, fee_bps}).pack(),
+    }
+}
+
+/// Creates a `DepositPoolNFT` instruction moving `mint` from `owner`'s
+/// `token_account` into their pool for `collection`.
+pub fn deposit_pool_nft(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    collection: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let (pool_pubkey, _) = pool::find_pool_address(program_id, owner, collection);
+    let (pool_vault_pubkey, _) = pool::find_pool_vault_address(program_id, &pool_pubkey, mint);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(pool_pubkey, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(pool_vault_pubkey, false),
+            AccountMeta::new_readonly(metadata::find_metadata_address(mint).0, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::DepositPoolNFT(DepositPoolNFT{}).pack(),
+    }
+}
+
+/// Creates a `DepositPoolSol` instruction adding `amount` lamports to
+/// `owner`'s pool for `collection`.
+pub fn deposit_pool_sol(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    collection: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (pool_pubkey, _) = pool::find_pool_address(program_id, owner, collection);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(pool_pubkey, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: NFTInstruction::DepositPoolSol(DepositPoolSol{amount}).pack(),
+    }
+}
+
+/// Creates a `WithdrawPoolNFT` instruction moving `mint` out of `owner`'s
+/// pool for `collection` into `token_account`.
+pub fn withdraw_pool_nft(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    collection: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let (pool_pubkey, _) = pool::find_pool_address(program_id, owner, collection);
+    let (pool_vault_pubkey, _) = pool::find_pool_vault_address(program_id, &pool_pubkey, mint);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(pool_pubkey, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(pool_vault_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::WithdrawPoolNFT(WithdrawPoolNFT{}).pack(),
+    }
+}
+
+/// Creates a `WithdrawPoolSol` instruction returning `amount` lamports from
+/// `owner`'s pool for `collection`.
+pub fn withdraw_pool_sol(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    collection: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (pool_pubkey, _) = pool::find_pool_address(program_id, owner, collection);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(pool_pubkey, false),
+        ],
+        data: NFTInstruction::WithdrawPoolSol(WithdrawPoolSol{amount}).pack(),
+    }
+}
+
+/// Creates a `BuyFromPool` instruction buying `mint` from `pool` into
+/// `buyer_token_account`, paying at most `max_price` lamports including
+/// fees.
+pub fn buy_from_pool(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    pool: &Pubkey,
+    buyer_token_account: &Pubkey,
+    mint: &Pubkey,
+    fee_recipient: &Pubkey,
+    max_price: u64,
+) -> Instruction {
+    let (pool_vault_pubkey, _) = pool::find_pool_vault_address(program_id, pool, mint);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(*buyer_token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(pool_vault_pubkey, false),
+            AccountMeta::new(state_pubkey, false),
+            AccountMeta::new(*fee_recipient, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: NFTInstruction::BuyFromPool(BuyFromPool{max_price}).pack(),
+    }
+}
+
+/// Creates a `SellToPool` instruction selling `mint` from
+/// `seller_token_account` into `pool`, receiving at least `min_price`
+/// lamports after fees.
+pub fn sell_to_pool(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    pool: &Pubkey,
+    seller_token_account: &Pubkey,
+    mint: &Pubkey,
+    fee_recipient: &Pubkey,
+    min_price: u64,
+) -> Instruction {
+    let (pool_vault_pubkey, _) = pool::find_pool_vault_address(program_id, pool, mint);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*seller, true),
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(*seller_token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(pool_vault_pubkey, false),
+            AccountMeta::new_readonly(metadata::find_metadata_address(mint).0, false),
+            AccountMeta::new(state_pubkey, false),
+            AccountMeta::new(*fee_recipient, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::SellToPool(SellToPool{min_price}).pack(),
+    }
+}
+
+/// Creates an `InitStakePool` instruction that opens the stake pool and its
+/// vault for `stake_mint`.
+pub fn init_stake_pool(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    stake_mint: &Pubkey,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (stake_pool_pubkey, _) = staking::find_stake_pool_address(program_id);
+    let (stake_vault_pubkey, _) = staking::find_stake_vault_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(state_pubkey, false),
+            AccountMeta::new(stake_pool_pubkey, false),
+            AccountMeta::new(stake_vault_pubkey, false),
+            AccountMeta::new_readonly(*stake_mint, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::InitStakePool(InitStakePool{}).pack(),
+    }
+}
+
+/// Creates a `Stake` instruction moving `amount` tokens from `owner`'s
+/// `token_account` into the stake vault. Pays out pending rewards.
+pub fn stake(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (stake_account_pubkey, _) = staking::find_stake_account_address(program_id, owner);
+    let (stake_pool_pubkey, _) = staking::find_stake_pool_address(program_id);
+    let (stake_vault_pubkey, _) = staking::find_stake_vault_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(stake_account_pubkey, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(stake_pool_pubkey, false),
+            AccountMeta::new(stake_vault_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::Stake(Stake{amount}).pack(),
+    }
+}
+
+/// Creates an `Unstake` instruction returning `amount` staked tokens to
+/// `owner`'s `token_account`. Pays out pending rewards.
+pub fn unstake(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (stake_account_pubkey, _) = staking::find_stake_account_address(program_id, owner);
+    let (stake_pool_pubkey, _) = staking::find_stake_pool_address(program_id);
+    let (stake_vault_pubkey, _) = staking::find_stake_vault_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(stake_account_pubkey, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(stake_pool_pubkey, false),
+            AccountMeta::new(stake_vault_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::Unstake(Unstake{amount}).pack(),
+    }
+}
+
+/// Creates a `ClaimRewards` instruction paying `owner` their share of the
+/// fees funded since they last settled.
+pub fn claim_rewards(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+) -> Instruction {
+    let (stake_account_pubkey, _) = staking::find_stake_account_address(program_id, owner);
+    let (stake_pool_pubkey, _) = staking::find_stake_pool_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(stake_account_pubkey, false),
+            AccountMeta::new(stake_pool_pubkey, false),
+        ],
+        data: NFTInstruction::ClaimRewards(ClaimRewards{}).pack(),
+    }
+}
+
+/// Creates a `FundStakingRewards` instruction paying `amount` lamports from
+/// `funder` to current stakers.
+pub fn fund_staking_rewards(
+    program_id: &Pubkey,
+    funder: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (stake_pool_pubkey, _) = staking::find_stake_pool_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*funder, true),
+            AccountMeta::new(stake_pool_pubkey, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: NFTInstruction::FundStakingRewards(FundStakingRewards{amount}).pack(),
+    }
+}
+
+/// Creates a `CreateRaffle` instruction escrowing `mint` from `creator`'s
+/// `token_account` and selling up to `total_tickets` tickets at
+/// `ticket_price` lamports until `end_time`.
+pub fn create_raffle(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    ticket_price: u64,
+    total_tickets: u64,
+    end_time: i64,
+) -> Instruction {
+    let (raffle_state_pubkey, _) = raffle::find_raffle_state_address(program_id, mint, creator);
+    let (raffle_vault_pubkey, _) = raffle::find_raffle_vault_address(program_id, mint, creator);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*creator, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(raffle_state_pubkey, false),
+            AccountMeta::new(raffle_vault_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::CreateRaffle(CreateRaffle{ticket_price, total_tickets, end_time}).pack(),
+    }
+}
+
+/// Creates a `BuyRaffleTicket` instruction buying one ticket in `creator`'s
+/// raffle for `mint`.
+pub fn buy_raffle_ticket(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    mint: &Pubkey,
+    creator: &Pubkey,
+) -> Instruction {
+    let (raffle_state_pubkey, _) = raffle::find_raffle_state_address(program_id, mint, creator);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(raffle_state_pubkey, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: NFTInstruction::BuyRaffleTicket(BuyRaffleTicket{}).pack(),
+    }
+}
+
+/// Creates a `DrawRaffle` instruction picking the winner of `creator`'s
+/// raffle for `mint` and paying out the ticket proceeds.
+pub fn draw_raffle(
+    program_id: &Pubkey,
+    mint: &Pubkey,
+    creator: &Pubkey,
+    fee_recipient: &Pubkey,
+) -> Instruction {
+    let (raffle_state_pubkey, _) = raffle::find_raffle_state_address(program_id, mint, creator);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(raffle_state_pubkey, false),
+            AccountMeta::new(*creator, false),
+            AccountMeta::new(state_pubkey, false),
+            AccountMeta::new(*fee_recipient, false),
+            AccountMeta::new_readonly(sysvar::slot_hashes::id(), false),
+        ],
+        data: NFTInstruction::DrawRaffle(DrawRaffle{}).pack(),
+    }
+}
+
+/// Creates a `ClaimRaffle` instruction sending `mint` to the drawn
+/// `winner`'s `token_account`.
+pub fn claim_raffle(
+    program_id: &Pubkey,
+    winner: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    creator: &Pubkey,
+) -> Instruction {
+    let (raffle_state_pubkey, _) = raffle::find_raffle_state_address(program_id, mint, creator);
+    let (raffle_vault_pubkey, _) = raffle::find_raffle_vault_address(program_id, mint, creator);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*winner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(raffle_state_pubkey, false),
+            AccountMeta::new(raffle_vault_pubkey, false),
+            AccountMeta::new(*creator, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::ClaimRaffle(ClaimRaffle{}).pack(),
+    }
+}
+
+/// Creates a `Fractionalize` instruction locking `mint` from `owner`'s
+/// `token_account` and minting `share_supply` shares into `share_account`.
+/// `share_account` must already be allocated to the token program, e.g. by
+/// a `create_account` earlier in the same transaction.
+pub fn fractionalize(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    share_account: &Pubkey,
+    share_supply: u64,
+    buyout_price: u64,
+) -> Instruction {
+    let (fraction_state_pubkey, _) = fraction::find_fraction_state_address(program_id, mint);
+    let (fraction_vault_pubkey, _) = fraction::find_fraction_vault_address(program_id, mint);
+    let (share_mint_pubkey, _) = fraction::find_share_mint_address(program_id, mint);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(fraction_state_pubkey, false),
+            AccountMeta::new(fraction_vault_pubkey, false),
+            AccountMeta::new(share_mint_pubkey, false),
+            AccountMeta::new(*share_account, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::Fractionalize(Fractionalize{share_supply, buyout_price}).pack(),
+    }
+}
+
+/// Creates a `Buyout` instruction buying the fractionalized `mint` into
+/// `buyer`'s `token_account`.
+pub fn buyout(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    curator: &Pubkey,
+    fee_recipient: &Pubkey,
+) -> Instruction {
+    let (fraction_state_pubkey, _) = fraction::find_fraction_state_address(program_id, mint);
+    let (fraction_vault_pubkey, _) = fraction::find_fraction_vault_address(program_id, mint);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*buyer, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(fraction_state_pubkey, false),
+            AccountMeta::new(fraction_vault_pubkey, false),
+            AccountMeta::new(*curator, false),
+            AccountMeta::new(state_pubkey, false),
+            AccountMeta::new(*fee_recipient, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: NFTInstruction::Buyout(Buyout{}).pack(),
+    }
+}
+
+/// Creates a `RedeemShares` instruction burning `amount` of `holder`'s
+/// shares in `mint` for their part of the buyout proceeds.
+pub fn redeem_shares(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    share_account: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (fraction_state_pubkey, _) = fraction::find_fraction_state_address(program_id, mint);
+    let (share_mint_pubkey, _) = fraction::find_share_mint_address(program_id, mint);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*holder, true),
+            AccountMeta::new(*share_account, false),
+            AccountMeta::new(share_mint_pubkey, false),
+            AccountMeta::new(fraction_state_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::RedeemShares(RedeemShares{amount}).pack(),
+    }
+}
+
+/// Creates a `RedeemNFT` instruction burning every share of `mint` held in
+/// `share_account` and returning the NFT to `holder`'s `token_account`.
+pub fn redeem_nft(
+    program_id: &Pubkey,
+    holder: &Pubkey,
+    share_account: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let (fraction_state_pubkey, _) = fraction::find_fraction_state_address(program_id, mint);
+    let (fraction_vault_pubkey, _) = fraction::find_fraction_vault_address(program_id, mint);
+    let (share_mint_pubkey, _) = fraction::find_share_mint_address(program_id, mint);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*holder, true),
+            AccountMeta::new(*share_account, false),
+            AccountMeta::new(share_mint_pubkey, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(fraction_state_pubkey, false),
+            AccountMeta::new(fraction_vault_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::RedeemNFT(RedeemNFT{}).pack(),
+    }
+}
+
+/// Creates a `CreateCrowdfund` instruction opening a crowdfund on
+/// `lister`'s fixed-price listing of `mint`. Once bought, the NFT's
+/// fraction vault can be bought out for `buyout_price`.
+pub fn create_crowdfund(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    mint: &Pubkey,
+    lister: &Pubkey,
+    buyout_price: u64,
+) -> Instruction {
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, lister);
+    let (crowdfund_pubkey, _) = crowdfund::find_crowdfund_address(program_id, mint, lister);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*creator, true),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*lister, false),
+            AccountMeta::new_readonly(list_state_pubkey, false),
+            AccountMeta::new(crowdfund_pubkey, false),
+            AccountMeta::new_readonly(state_pubkey, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::CreateCrowdfund(CreateCrowdfund{buyout_price}).pack(),
+    }
+}
+
+/// Creates a `Contribute` instruction adding `amount` lamports from
+/// `contributor` to the crowdfund on `lister`'s listing of `mint`.
+pub fn contribute(
+    program_id: &Pubkey,
+    contributor: &Pubkey,
+    mint: &Pubkey,
+    lister: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (crowdfund_pubkey, _) = crowdfund::find_crowdfund_address(program_id, mint, lister);
+    let (contribution_pubkey, _) = crowdfund::find_contribution_address(program_id, &crowdfund_pubkey, contributor);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*contributor, true),
+            AccountMeta::new(crowdfund_pubkey, false),
+            AccountMeta::new(contribution_pubkey, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::Contribute(Contribute{amount}).pack(),
+    }
+}
+
+/// Creates an `ExecuteCrowdfund` instruction buying `lister`'s listing of
+/// `mint` with the crowdfund's SOL. `executor` funds the fraction vault
+/// accounts. `curator` must be the listing's curator, if it has one; a
+/// listing with a charity split also needs `with_charity_payout`.
+pub fn execute_crowdfund(
+    program_id: &Pubkey,
+    executor: &Pubkey,
+    mint: &Pubkey,
+    lister: &Pubkey,
+    list_rent_payer: &Pubkey,
+    fee_recipient: &Pubkey,
+    curator: Option<&Pubkey>,
+) -> Instruction {
+    let (crowdfund_pubkey, _) = crowdfund::find_crowdfund_address(program_id, mint, lister);
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, lister);
+    let (list_vault_pubkey, _) = state::find_list_vault_address(program_id, mint, lister);
+    let (fraction_state_pubkey, _) = fraction::find_fraction_state_address(program_id, mint);
+    let (fraction_vault_pubkey, _) = fraction::find_fraction_vault_address(program_id, mint);
+    let (share_mint_pubkey, _) = fraction::find_share_mint_address(program_id, mint);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(*executor, true),
+        AccountMeta::new(crowdfund_pubkey, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(*lister, false),
+        AccountMeta::new(*list_rent_payer, false),
+        AccountMeta::new(list_state_pubkey, false),
+        AccountMeta::new(list_vault_pubkey, false),
+        AccountMeta::new(fraction_state_pubkey, false),
+        AccountMeta::new(fraction_vault_pubkey, false),
+        AccountMeta::new(share_mint_pubkey, false),
+        AccountMeta::new(state_pubkey, false),
+        AccountMeta::new(*fee_recipient, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+    if let Some(curator) = curator {
+        accounts.push(AccountMeta::new(*curator, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::ExecuteCrowdfund(ExecuteCrowdfund{}).pack(),
+    }
+}
+
+/// Creates a `ClaimCrowdfundShares` instruction minting `contributor`'s
+/// shares into `share_account`, an initialized token account of the
+/// fraction's share mint. `creator` opened the crowdfund.
+pub fn claim_crowdfund_shares(
+    program_id: &Pubkey,
+    contributor: &Pubkey,
+    share_account: &Pubkey,
+    creator: &Pubkey,
+    mint: &Pubkey,
+    lister: &Pubkey,
+) -> Instruction {
+    let (crowdfund_pubkey, _) = crowdfund::find_crowdfund_address(program_id, mint, lister);
+    let (contribution_pubkey, _) = crowdfund::find_contribution_address(program_id, &crowdfund_pubkey, contributor);
+    let (share_mint_pubkey, _) = fraction::find_share_mint_address(program_id, mint);
+    let (fraction_state_pubkey, _) = fraction::find_fraction_state_address(program_id, mint);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*contributor, false),
+            AccountMeta::new(*share_account, false),
+            AccountMeta::new(*creator, false),
+            AccountMeta::new(crowdfund_pubkey, false),
+            AccountMeta::new(contribution_pubkey, false),
+            AccountMeta::new(share_mint_pubkey, false),
+            AccountMeta::new_readonly(fraction_state_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::ClaimCrowdfundShares(ClaimCrowdfundShares{}).pack(),
+    }
+}
+
+/// Creates a `RefundContribution` instruction returning `contributor`'s
+/// SOL from a crowdfund whose listing is no longer available.
+pub fn refund_contribution(
+    program_id: &Pubkey,
+    contributor: &Pubkey,
+    creator: &Pubkey,
+    mint: &Pubkey,
+    lister: &Pubkey,
+) -> Instruction {
+    let (crowdfund_pubkey, _) = crowdfund::find_crowdfund_address(program_id, mint, lister);
+    let (contribution_pubkey, _) = crowdfund::find_contribution_address(program_id, &crowdfund_pubkey, contributor);
+    let (list_state_pubkey, _) = state::find_list_state_address(program_id, mint, lister);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*contributor, false),
+            AccountMeta::new(*creator, false),
+            AccountMeta::new(crowdfund_pubkey, false),
+            AccountMeta::new(contribution_pubkey, false),
+            AccountMeta::new_readonly(list_state_pubkey, false),
+        ],
+        data: NFTInstruction::RefundContribution(RefundContribution{}).pack(),
+    }
+}
+
+/// Creates a `ListForRent` instruction escrowing `mint` from `owner`'s
+/// `token_account` and offering it for `duration_slots` slots at `price`
+/// lamports.
+pub fn list_for_rent(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    price: u64,
+    duration_slots: u64,
+) -> Instruction {
+    let (rental_state_pubkey, _) = rental::find_rental_state_address(program_id, mint, owner);
+    let (rental_vault_pubkey, _) = rental::find_rental_vault_address(program_id, mint, owner);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(rental_state_pubkey, false),
+            AccountMeta::new(rental_vault_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::ListForRent(ListForRent{price, duration_slots}).pack(),
+    }
+}
+
+/// Creates a `Rent` instruction renting `owner`'s `mint` to `renter`.
+pub fn rent(
+    program_id: &Pubkey,
+    renter: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    fee_recipient: &Pubkey,
+) -> Instruction {
+    let (rental_state_pubkey, _) = rental::find_rental_state_address(program_id, mint, owner);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*renter, true),
+            AccountMeta::new(rental_state_pubkey, false),
+            AccountMeta::new(*owner, false),
+            AccountMeta::new(state_pubkey, false),
+            AccountMeta::new(*fee_recipient, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: NFTInstruction::Rent(Rent{}).pack(),
+    }
+}
+
+/// Creates a `ReclaimRental` instruction returning `mint` to `owner`'s
+/// `token_account`. `owner` only needs to sign if `mint` was never rented.
+pub fn reclaim_rental(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    owner_signs: bool,
+) -> Instruction {
+    let (rental_state_pubkey, _) = rental::find_rental_state_address(program_id, mint, owner);
+    let (rental_vault_pubkey, _) = rental::find_rental_vault_address(program_id, mint, owner);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, owner_signs),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(rental_state_pubkey, false),
+            AccountMeta::new(rental_vault_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::ReclaimRental(ReclaimRental{}).pack(),
+    }
+}
+
+/// Creates a `SetFeeDiscount` instruction giving holders of at least
+/// `threshold` `discount_mint` tokens `discount_bps` off platform fees.
+pub fn set_fee_discount(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    discount_mint: &Pubkey,
+    threshold: u64,
+    discount_bps: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (fee_discount_pubkey, _) = state::find_fee_discount_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(state_pubkey, false),
+            AccountMeta::new(fee_discount_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::SetFeeDiscount(SetFeeDiscount{
+            discount_mint: *discount_mint,
+            threshold,
+            discount_bps,
+        }).pack(),
+    }
+}
+
+/// Appends the fee discount config and a trader's discount-mint
+/// `token_account` to a `Bid`, `AuctionBid`, `AcceptBid`, `ExecuteSale`
+/// or `FillOrder` instruction so the trader pays the discounted fee. Call once per
+/// trader claiming the discount.
+pub fn with_fee_discount(mut instruction: Instruction, token_account: &Pubkey) -> Instruction {
+    let (fee_discount_pubkey, _) = state::find_fee_discount_address(&instruction.program_id);
+    instruction.accounts.push(AccountMeta::new_readonly(fee_discount_pubkey, false));
+    instruction.accounts.push(AccountMeta::new_readonly(*token_account, false));
+    instruction
+}
+
+/// Creates a `CreateOtc` instruction escrowing `mint` from `maker`'s
+/// `token_account` for `counterparty` to buy at exactly `price` lamports.
+pub fn create_otc(
+    program_id: &Pubkey,
+    maker: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    counterparty: &Pubkey,
+    price: u64,
+) -> Instruction {
+    let (otc_state_pubkey, _) = otc::find_otc_state_address(program_id, mint, maker);
+    let (otc_vault_pubkey, _) = otc::find_otc_vault_address(program_id, mint, maker);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*maker, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*counterparty, false),
+            AccountMeta::new(otc_state_pubkey, false),
+            AccountMeta::new(otc_vault_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::CreateOtc(CreateOtc{price}).pack(),
+    }
+}
+
+/// Creates an `AcceptOtc` instruction paying `maker`'s OTC price for `mint`
+/// and receiving the NFT into `taker`'s `token_account`.
+pub fn accept_otc(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    maker: &Pubkey,
+    fee_recipient: &Pubkey,
+) -> Instruction {
+    let (otc_state_pubkey, _) = otc::find_otc_state_address(program_id, mint, maker);
+    let (otc_vault_pubkey, _) = otc::find_otc_vault_address(program_id, mint, maker);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*taker, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(otc_state_pubkey, false),
+            AccountMeta::new(otc_vault_pubkey, false),
+            AccountMeta::new(*maker, false),
+            AccountMeta::new(state_pubkey, false),
+            AccountMeta::new(*fee_recipient, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: NFTInstruction::AcceptOtc(AcceptOtc{}).pack(),
+    }
+}
+
+/// Creates a `CancelOtc` instruction, signed by either party, returning
+/// `mint` to `maker`'s `token_account`.
+pub fn cancel_otc(
+    program_id: &Pubkey,
+    signer: &Pubkey,
+    maker: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let (otc_state_pubkey, _) = otc::find_otc_state_address(program_id, mint, maker);
+    let (otc_vault_pubkey, _) = otc::find_otc_vault_address(program_id, mint, maker);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*signer, true),
+            AccountMeta::new(*maker, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(otc_state_pubkey, false),
+            AccountMeta::new(otc_vault_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::CancelOtc(CancelOtc{}).pack(),
+    }
+}
+
+/// Creates a `CreateSwap` instruction opening `maker`'s swap `id` with
+/// `taker`: each `(token_account, mint)` in `assets` and `maker_lamports`
+/// are escrowed for `taker_mints` and `taker_lamports`.
+#[allow(clippy::too_many_arguments)]
+pub fn create_swap(
+    program_id: &Pubkey,
+    maker: &Pubkey,
+    taker: &Pubkey,
+    id: u64,
+    assets: &[(Pubkey, Pubkey)],
+    maker_lamports: u64,
+    taker_mints: &[Pubkey],
+    taker_lamports: u64,
+) -> Instruction {
+    let (swap_state_pubkey, _) = swap::find_swap_state_address(program_id, maker, id);
+    let mut accounts = vec![
+        AccountMeta::new(*maker, true),
+        AccountMeta::new_readonly(*taker, false),
+        AccountMeta::new(swap_state_pubkey, false),
+        AccountMeta::new_readonly(*program_id, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+    push_swap_deposits(&mut accounts, program_id, &swap_state_pubkey, assets);
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::CreateSwap(CreateSwap{
+            id,
+            maker_lamports,
+            taker_lamports,
+            taker_mints: taker_mints.to_vec(),
+        }).pack(),
+    }
+}
+
+/// Creates a `DepositSwap` instruction escrowing `taker`'s side of
+/// `maker`'s swap `id`: each `(token_account, mint)` in `assets`, in the
+/// swap's order, and its lamports.
+pub fn deposit_swap(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    maker: &Pubkey,
+    id: u64,
+    assets: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    let (swap_state_pubkey, _) = swap::find_swap_state_address(program_id, maker, id);
+    let mut accounts = vec![
+        AccountMeta::new(*taker, true),
+        AccountMeta::new(swap_state_pubkey, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+    push_swap_deposits(&mut accounts, program_id, &swap_state_pubkey, assets);
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::DepositSwap(DepositSwap{}).pack(),
+    }
+}
+
+/// Creates an `ExecuteSwap` instruction, signed by either party, settling
+/// `maker`'s swap `id`. `maker_assets` holds a `(token_account, mint)` for
+/// each of the maker's mints, the token account being the taker's, and
+/// `taker_assets` one for each of the taker's, into the maker's.
+pub fn execute_swap(
+    program_id: &Pubkey,
+    signer: &Pubkey,
+    maker: &Pubkey,
+    taker: &Pubkey,
+    id: u64,
+    maker_assets: &[(Pubkey, Pubkey)],
+    taker_assets: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    let (swap_state_pubkey, _) = swap::find_swap_state_address(program_id, maker, id);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*signer, true),
+        AccountMeta::new(swap_state_pubkey, false),
+        AccountMeta::new(*maker, false),
+        AccountMeta::new(*taker, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(state_pubkey, false),
+    ];
+    push_swap_releases(&mut accounts, program_id, &swap_state_pubkey, maker_assets);
+    push_swap_releases(&mut accounts, program_id, &swap_state_pubkey, taker_assets);
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::ExecuteSwap(ExecuteSwap{}).pack(),
+    }
+}
+
+/// Creates a `CancelSwap` instruction for `maker`'s swap `id`, returning
+/// the mint of each `(token_account, mint)` to the owner's token account.
+/// The taker passes its own mints as `taker_assets` and no `maker_assets`;
+/// the maker passes its own and, once the taker has deposited, the
+/// taker's.
+pub fn cancel_swap(
+    program_id: &Pubkey,
+    signer: &Pubkey,
+    maker: &Pubkey,
+    taker: &Pubkey,
+    id: u64,
+    maker_assets: &[(Pubkey, Pubkey)],
+    taker_assets: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    let (swap_state_pubkey, _) = swap::find_swap_state_address(program_id, maker, id);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*signer, true),
+        AccountMeta::new(swap_state_pubkey, false),
+        AccountMeta::new(*maker, false),
+        AccountMeta::new(*taker, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    push_swap_releases(&mut accounts, program_id, &swap_state_pubkey, maker_assets);
+    push_swap_releases(&mut accounts, program_id, &swap_state_pubkey, taker_assets);
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::CancelSwap(CancelSwap{}).pack(),
+    }
+}
+
+fn push_swap_deposits(accounts: &mut Vec<AccountMeta>, program_id: &Pubkey, swap_state: &Pubkey, assets: &[(Pubkey, Pubkey)]) {
+    for (token_account, mint) in assets {
+        accounts.push(AccountMeta::new(*token_account, false));
+        accounts.push(AccountMeta::new_readonly(*mint, false));
+        accounts.push(AccountMeta::new(swap::find_swap_vault_address(program_id, swap_state, mint).0, false));
+    }
+}
+
+fn push_swap_releases(accounts: &mut Vec<AccountMeta>, program_id: &Pubkey, swap_state: &Pubkey, assets: &[(Pubkey, Pubkey)]) {
+    for (token_account, mint) in assets {
+        accounts.push(AccountMeta::new(swap::find_swap_vault_address(program_id, swap_state, mint).0, false));
+        accounts.push(AccountMeta::new(*token_account, false));
+    }
+}
+
+/// Creates a `MakeTraitOffer` instruction escrowing `amount` lamports from
+/// `bidder` for any mint under `root`. `attestor`, if given, co-signs to
+/// vouch for the root.
+pub fn make_trait_offer(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    amount: u64,
+    root: [u8; 32],
+    attestor: Option<&Pubkey>,
+) -> Instruction {
+    let (offer_pubkey, _) = trait_offer::find_trait_offer_address(program_id, bidder, &root);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(*bidder, true),
+        AccountMeta::new(offer_pubkey, false),
+        AccountMeta::new_readonly(state_pubkey, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if let Some(attestor) = attestor {
+        accounts.push(AccountMeta::new_readonly(*attestor, true));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::MakeTraitOffer(MakeTraitOffer{amount, root}).pack(),
+    }
+}
+
+/// Creates an `AcceptTraitOffer` instruction selling `mint` from `seller`'s
+/// `token_account` into `bidder`'s offer on `root`, with `proof` from
+/// `merkle::proof`. The NFT goes to the bidder's associated token account.
+#[allow(clippy::too_many_arguments)]
+pub fn accept_trait_offer(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    bidder: &Pubkey,
+    root: &[u8; 32],
+    proof: Vec<[u8; 32]>,
+    fee_recipient: &Pubkey,
+) -> Instruction {
+    let (offer_pubkey, _) = trait_offer::find_trait_offer_address(program_id, bidder, root);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*seller, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(offer_pubkey, false),
+            AccountMeta::new(*bidder, false),
+            AccountMeta::new(spl_associated_token_account::get_associated_token_address(bidder, mint), false),
+            AccountMeta::new(state_pubkey, false),
+            AccountMeta::new(*fee_recipient, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        ],
+        data: NFTInstruction::AcceptTraitOffer(AcceptTraitOffer{proof}).pack(),
+    }
+}
+
+/// Creates a `CancelTraitOffer` instruction returning `bidder`'s offer on
+/// `root`.
+pub fn cancel_trait_offer(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    root: &[u8; 32],
+) -> Instruction {
+    let (offer_pubkey, _) = trait_offer::find_trait_offer_address(program_id, bidder, root);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*bidder, true),
+            AccountMeta::new(offer_pubkey, false),
+        ],
+        data: NFTInstruction::CancelTraitOffer(CancelTraitOffer{}).pack(),
+    }
+}
+
+/// Creates a `SetRewards` instruction minting `buyer_reward` and
+/// `seller_reward` of `mint` to the two sides of each sale, at most
+/// `epoch_cap` per epoch. `mint`'s authority must already be the rewards
+/// authority PDA.
+pub fn set_rewards(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    buyer_reward: u64,
+    seller_reward: u64,
+    epoch_cap: u64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (config_pubkey, _) = rewards::find_rewards_config_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(state_pubkey, false),
+            AccountMeta::new(config_pubkey, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::SetRewards(SetRewards{buyer_reward, seller_reward, epoch_cap}).pack(),
+    }
+}
+
+/// Appends the accounts that mint trade rewards of `mint` to `buyer_token`
+/// and `seller_token`, the two sides' token accounts for it, to an
+/// `AcceptBid` or `ExecuteSale` instruction. Apply it after
+/// `with_collection_stats` and before `with_leaderboard`.
+pub fn with_trade_rewards(mut instruction: Instruction, mint: &Pubkey, buyer_token: &Pubkey, seller_token: &Pubkey) -> Instruction {
+    let (config_pubkey, _) = rewards::find_rewards_config_address(&instruction.program_id);
+    let (rewards_authority_pubkey, _) = rewards::find_rewards_authority_address(&instruction.program_id);
+    instruction.accounts.extend(vec![
+        AccountMeta::new(config_pubkey, false),
+        AccountMeta::new(*mint, false),
+        AccountMeta::new_readonly(rewards_authority_pubkey, false),
+        AccountMeta::new(*buyer_token, false),
+        AccountMeta::new(*seller_token, false),
+    ]);
+    instruction
+}
+
+/// Creates a `CreateLeaderboard` instruction opening `season`'s
+/// leaderboard, counting sales from `starts_at` up to `ends_at`.
+pub fn create_leaderboard(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    season: u64,
+    starts_at: i64,
+    ends_at: i64,
+) -> Instruction {
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let (leaderboard_pubkey, _) = leaderboard::find_leaderboard_address(program_id, season);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(state_pubkey, false),
+            AccountMeta::new(leaderboard_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::CreateLeaderboard(CreateLeaderboard{season, starts_at, ends_at}).pack(),
+    }
+}
+
+/// Appends `season`'s leaderboard to an `AcceptBid` or `ExecuteSale`
+/// instruction so the sale counts towards it. Apply it after
+/// `with_trade_rewards` and before `with_fee_discount`.
+pub fn with_leaderboard(mut instruction: Instruction, season: u64) -> Instruction {
+    let (leaderboard_pubkey, _) = leaderboard::find_leaderboard_address(&instruction.program_id, season);
+    instruction.accounts.push(AccountMeta::new(leaderboard_pubkey, false));
+    instruction
+}
+
+/// Creates a `ListCore` instruction moving the Metaplex Core `asset` from
+/// `seller` into a listing PDA at `price` lamports. `collection` is the
+/// asset's collection, if it has one; its collection state is passed along
+/// for curated platforms.
+pub fn list_core(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    asset: &Pubkey,
+    collection: Option<&Pubkey>,
+    price: u64,
+) -> Instruction {
+    let (listing_pubkey, _) = core_listing::find_core_listing_address(program_id, asset);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(*seller, true),
+        AccountMeta::new(*asset, false),
+        AccountMeta::new(listing_pubkey, false),
+        AccountMeta::new_readonly(*program_id, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(mpl_core::mpl_core_program::id(), false),
+        AccountMeta::new(state_pubkey, false),
+    ];
+    if let Some(collection) = collection {
+        let (collection_state_pubkey, _) = state::find_collection_state_address(program_id, collection);
+        accounts.push(AccountMeta::new_readonly(*collection, false));
+        accounts.push(AccountMeta::new_readonly(collection_state_pubkey, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::ListCore(ListCore{price}).pack(),
+    }
+}
+
+/// Creates a `DelistCore` instruction returning `asset` to `seller`.
+pub fn delist_core(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    asset: &Pubkey,
+    collection: Option<&Pubkey>,
+) -> Instruction {
+    let (listing_pubkey, _) = core_listing::find_core_listing_address(program_id, asset);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(*seller, true),
+        AccountMeta::new(*asset, false),
+        AccountMeta::new(listing_pubkey, false),
+        AccountMeta::new(state_pubkey, false),
+        AccountMeta::new_readonly(mpl_core::mpl_core_program::id(), false),
+    ];
+    if let Some(collection) = collection {
+        accounts.push(AccountMeta::new_readonly(*collection, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::DelistCore(DelistCore{}).pack(),
+    }
+}
+
+/// Creates a `BuyCore` instruction paying `seller`'s listing price for
+/// `asset` and transferring it to `buyer`. It fails if the price has risen
+/// above `max_price`.
+pub fn buy_core(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    asset: &Pubkey,
+    collection: Option<&Pubkey>,
+    fee_recipient: &Pubkey,
+    max_price: u64,
+) -> Instruction {
+    let (listing_pubkey, _) = core_listing::find_core_listing_address(program_id, asset);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(*buyer, true),
+        AccountMeta::new(*seller, false),
+        AccountMeta::new(*asset, false),
+        AccountMeta::new(listing_pubkey, false),
+        AccountMeta::new(state_pubkey, false),
+        AccountMeta::new(*fee_recipient, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(mpl_core::mpl_core_program::id(), false),
+    ];
+    if let Some(collection) = collection {
+        accounts.push(AccountMeta::new_readonly(*collection, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: NFTInstruction::BuyCore(BuyCore{max_price}).pack(),
+    }
+}
+
+/// Creates a `SetPaymentMint` instruction adding `quote_mint` to the
+/// payment-mint registry, or enabling or disabling it.
+pub fn set_payment_mint(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    quote_mint: &Pubkey,
+    enabled: bool,
+) -> Instruction {
+    let (payment_mint_pubkey, _) = payment::find_payment_mint_address(program_id, quote_mint);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(*quote_mint, false),
+            AccountMeta::new(payment_mint_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(state_pubkey, false),
+        ],
+        data: NFTInstruction::SetPaymentMint(SetPaymentMint{enabled}).pack(),
+    }
+}
+
+/// Creates a `ListForToken` instruction escrowing `mint` from
+/// `token_account` at `price` base units of `quote_mint`.
+pub fn list_for_token(
+    program_id: &Pubkey,
+    lister: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    quote_mint: &Pubkey,
+    price: u64,
+) -> Instruction {
+    let (payment_mint_pubkey, _) = payment::find_payment_mint_address(program_id, quote_mint);
+    let (listing_pubkey, _) = payment::find_token_listing_address(program_id, mint, lister);
+    let (listing_vault_pubkey, _) = payment::find_token_listing_vault_address(program_id, mint, lister);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*lister, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*quote_mint, false),
+            AccountMeta::new_readonly(payment_mint_pubkey, false),
+            AccountMeta::new(listing_pubkey, false),
+            AccountMeta::new(listing_vault_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::ListForToken(ListForToken{price}).pack(),
+    }
+}
+
+/// Creates a `CancelTokenListing` instruction returning `mint` to
+/// `token_account`.
+pub fn cancel_token_listing(
+    program_id: &Pubkey,
+    lister: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    let (listing_pubkey, _) = payment::find_token_listing_address(program_id, mint, lister);
+    let (listing_vault_pubkey, _) = payment::find_token_listing_vault_address(program_id, mint, lister);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*lister, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(listing_pubkey, false),
+            AccountMeta::new(listing_vault_pubkey, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(state_pubkey, false),
+        ],
+        data: NFTInstruction::CancelTokenListing(CancelTokenListing{}).pack(),
+    }
+}
+
+/// Creates a `BidWithToken` instruction escrowing `amount` base units of
+/// `quote_mint`, plus the taker fee, from `quote_token_account`.
+pub fn bid_with_token(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    quote_token_account: &Pubkey,
+    mint: &Pubkey,
+    quote_mint: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let (payment_mint_pubkey, _) = payment::find_payment_mint_address(program_id, quote_mint);
+    let (bid_pubkey, _) = payment::find_token_bid_address(program_id, mint, bidder);
+    let (bid_vault_pubkey, _) = payment::find_token_bid_vault_address(program_id, mint, bidder);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*bidder, true),
+            AccountMeta::new(*quote_token_account, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*quote_mint, false),
+            AccountMeta::new_readonly(payment_mint_pubkey, false),
+            AccountMeta::new(bid_pubkey, false),
+            AccountMeta::new(bid_vault_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(state_pubkey, false),
+        ],
+        data: NFTInstruction::BidWithToken(BidWithToken{amount}).pack(),
+    }
+}
+
+/// Creates a `WithdrawTokenBid` instruction refunding the bid vault to
+/// `quote_token_account`.
+pub fn withdraw_token_bid(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    quote_token_account: &Pubkey,
+    mint: &Pubkey,
+    quote_mint: &Pubkey,
+) -> Instruction {
+    let (bid_pubkey, _) = payment::find_token_bid_address(program_id, mint, bidder);
+    let (bid_vault_pubkey, _) = payment::find_token_bid_vault_address(program_id, mint, bidder);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*bidder, true),
+            AccountMeta::new(*quote_token_account, false),
+            AccountMeta::new(bid_pubkey, false),
+            AccountMeta::new(bid_vault_pubkey, false),
+            AccountMeta::new_readonly(*quote_mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::WithdrawTokenBid(WithdrawTokenBid{}).pack(),
+    }
+}
+
+/// Creates an `AcceptTokenBid` instruction selling `lister`'s token listing
+/// of `mint` to `bidder`'s token bid. Proceeds go to `lister_quote_account`
+/// and the platform fee to `fee_recipient_quote_account`, both holding
+/// `quote_mint`; the NFT goes to `bidder_token_account`.
+#[allow(clippy::too_many_arguments)]
+pub fn accept_token_bid(
+    program_id: &Pubkey,
+    lister: &Pubkey,
+    lister_quote_account: &Pubkey,
+    bidder: &Pubkey,
+    bidder_token_account: &Pubkey,
+    mint: &Pubkey,
+    quote_mint: &Pubkey,
+    fee_recipient_quote_account: &Pubkey,
+) -> Instruction {
+    let (listing_pubkey, _) = payment::find_token_listing_address(program_id, mint, lister);
+    let (listing_vault_pubkey, _) = payment::find_token_listing_vault_address(program_id, mint, lister);
+    let (bid_pubkey, _) = payment::find_token_bid_address(program_id, mint, bidder);
+    let (bid_vault_pubkey, _) = payment::find_token_bid_vault_address(program_id, mint, bidder);
+    let (state_pubkey, _) = state::find_platform_state_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*lister, true),
+            AccountMeta::new(*lister_quote_account, false),
+            AccountMeta::new(listing_pubkey, false),
+            AccountMeta::new(listing_vault_pubkey, false),
+            AccountMeta::new(*bidder, false),
+            AccountMeta::new(*bidder_token_account, false),
+            AccountMeta::new(bid_pubkey, false),
+            AccountMeta::new(bid_vault_pubkey, false),
+            AccountMeta::new_readonly(*quote_mint, false),
+            AccountMeta::new(state_pubkey, false),
+            AccountMeta::new(*fee_recipient_quote_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: NFTInstruction::AcceptTokenBid(AcceptTokenBid{}).pack(),
+    }
+}
+
+/// Creates a `CreateSession` instruction letting `session_key` sign the
+/// `allowed` instructions (`session::ALLOW_*` bits) for `owner` until
+/// `expires_at`.
+pub fn create_session(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    session_key: &Pubkey,
+    expires_at: i64,
+    allowed: u8,
+) -> Instruction {
+    let (session_pubkey, _) = session::find_session_address(program_id, owner, session_key);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(session_pubkey, false),
+            AccountMeta::new_readonly(*program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data: NFTInstruction::CreateSession(CreateSession{session_key: *session_key, expires_at, allowed}).pack(),
+    }
+}
+
+/// Creates a `RevokeSession` instruction closing `owner`'s session for
+/// `session_key`.
+pub fn revoke_session(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    session_key: &Pubkey,
+) -> Instruction {
+    let (session_pubkey, _) = session::find_session_address(program_id, owner, session_key);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(session_pubkey, false),
+        ],
+        data: NFTInstruction::RevokeSession(RevokeSession{}).pack(),
+    }
+}
@@ -0,0 +1,298 @@
+//! Trading competitions ranked on-chain.
+//!
+//! The platform authority opens a season with `CreateLeaderboard`, which
+//! creates a leaderboard PDA for the season's time window. A sale settled
+//! through `AcceptBid`, `ExecuteSale` or a matched `Bid` whose accounts end
+//! with a season's leaderboard adds its price to the buyer's and the
+//! seller's volume while the season is running. The board keeps the top
+//! `LEADERBOARD_DEPTH` traders sorted by volume: a trader not on a full
+//! board takes the last place only when this sale alone beats its volume,
+//! and a trader who drops off starts over from their next sale.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{error::NFTError, processor::Processor, state::{self, Discriminator, PlatformState}};
+
+pub const LEADERBOARD_DEPTH: usize = 16usize;
+pub const LEADERBOARDSIZE: usize = 34usize + LEADERBOARD_DEPTH * 40;
+
+pub fn find_leaderboard_address(program_id: &Pubkey, season: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[&season.to_be_bytes(), b"Leader", b"Board"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LeaderboardEntry {
+    pub trader: Pubkey,
+    /// Lamports bought and sold during the season.
+    pub volume: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Leaderboard {
+    pub is_initialized: bool,
+    pub season: u64,
+    /// Unix timestamp sales start counting from.
+    pub starts_at: i64,
+    /// Unix timestamp sales stop counting at.
+    pub ends_at: i64,
+    /// Highest volume first.
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    pub fn is_running(&self, now: i64) -> bool {
+        self.starts_at <= now && now < self.ends_at
+    }
+
+    /// Adds `volume` to `trader`'s entry, or enters them in place of the
+    /// last one if the board is full and `volume` beats it.
+    pub fn record(&mut self, trader: &Pubkey, volume: u64) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.trader.eq(trader)) {
+            entry.volume = entry.volume.saturating_add(volume);
+        } else if self.entries.len() < LEADERBOARD_DEPTH {
+            self.entries.push(LeaderboardEntry{trader: *trader, volume});
+        } else if self.entries.last().map_or(false, |last| volume > last.volume) {
+            self.entries.pop();
+            self.entries.push(LeaderboardEntry{trader: *trader, volume});
+        } else {
+            return;
+        }
+        // Stable, so traders tied on volume keep their earlier place.
+        self.entries.sort_by(|a, b| b.volume.cmp(&a.volume));
+    }
+}
+
+impl Sealed for Leaderboard{}
+
+impl Discriminator for Leaderboard{
+    const DISCRIMINATOR: [u8; 8] = *b"leaders\0";
+}
+
+impl IsInitialized for Leaderboard{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Leaderboard {
+    const LEN: usize = LEADERBOARDSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Leaderboard::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, Leaderboard::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            season,
+            starts_at,
+            ends_at,
+            count,
+            entries,
+        ) = array_refs![src, 8, 1, 8, 8, 8, 1, LEADERBOARD_DEPTH * 40];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let count = count[0] as usize;
+        if count > LEADERBOARD_DEPTH {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(Leaderboard{
+            is_initialized,
+            season: u64::from_be_bytes(*season),
+            starts_at: i64::from_be_bytes(*starts_at),
+            ends_at: i64::from_be_bytes(*ends_at),
+            entries: entries
+                .chunks_exact(40)
+                .take(count)
+                .map(|chunk| {
+                    let (trader, volume) = array_refs![array_ref![chunk, 0, 40], 32, 8];
+                    LeaderboardEntry{
+                        trader: Pubkey::new_from_array(*trader),
+                        volume: u64::from_be_bytes(*volume),
+                    }
+                })
+                .collect(),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Leaderboard::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            season_dst,
+            starts_at_dst,
+            ends_at_dst,
+            count_dst,
+            entries_dst,
+        ) = mut_array_refs![dst, 8, 1, 8, 8, 8, 1, LEADERBOARD_DEPTH * 40];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+        is_initialized_dst[0] = self.is_initialized as u8;
+        *season_dst = self.season.to_be_bytes();
+        *starts_at_dst = self.starts_at.to_be_bytes();
+        *ends_at_dst = self.ends_at.to_be_bytes();
+        count_dst[0] = self.entries.len() as u8;
+        for byte in entries_dst.iter_mut() {
+            *byte = 0;
+        }
+        for (entry, chunk) in self.entries.iter().zip(entries_dst.chunks_exact_mut(40)) {
+            let (trader_dst, volume_dst) = mut_array_refs![array_mut_ref![chunk, 0, 40], 32, 8];
+            trader_dst.copy_from_slice(entry.trader.as_ref());
+            *volume_dst = entry.volume.to_be_bytes();
+        }
+    }
+}
+
+/// Opens `season`'s empty leaderboard, counting sales from `starts_at` up
+/// to `ends_at`.
+pub fn process_create_leaderboard(
+    accounts: &[AccountInfo],
+    season: u64,
+    starts_at: i64,
+    ends_at: i64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let authority_info = next_account_info(account_info_iter)?;
+    if !authority_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let state_info = load_platform_state(next_account_info(account_info_iter)?, program_id)?;
+    if !state_info.authority.eq(authority_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    if starts_at >= ends_at {
+        return Err(NFTError::InvalidInstructionData.into());
+    }
+
+    let leaderboard_account_info = next_account_info(account_info_iter)?;
+    let (leaderboard_pubkey, nonce) = find_leaderboard_address(program_id, season);
+    if !(leaderboard_account_info.key.eq(&leaderboard_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !leaderboard_account_info.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Processor::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::get()?;
+    let create_leaderboard_account_ix = system_instruction::create_account(
+        authority_info.key,
+        &leaderboard_pubkey,
+        rent.minimum_balance(LEADERBOARDSIZE),
+        LEADERBOARDSIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived leaderboard account");
+    invoke_signed(
+        &create_leaderboard_account_ix,
+        &[
+            authority_info.clone(),
+            leaderboard_account_info.clone(),
+            system_program_info.clone(),
+            program_info.clone(),
+        ],
+        &[&[
+            &season.to_be_bytes(),
+            &b"Leader"[..],
+            &b"Board"[..],
+            &[nonce]
+        ]],
+    )?;
+
+    let leaderboard = Leaderboard{
+        is_initialized: true,
+        season,
+        starts_at,
+        ends_at,
+        entries: vec![],
+    };
+    Leaderboard::pack(leaderboard, &mut leaderboard_account_info.data.borrow_mut())
+}
+
+/// Strips a leaderboard appended to a sale's accounts, if its last account
+/// is one. Returns the remaining accounts and the leaderboard for
+/// `record_trade`.
+pub(crate) fn split_leaderboard<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+    program_id: &Pubkey,
+) -> (&'a [AccountInfo<'b>], Option<&'a AccountInfo<'b>>) {
+    match accounts.split_last() {
+        Some((leaderboard_account_info, rest))
+            if leaderboard_account_info.owner.eq(program_id)
+                && leaderboard_account_info.data.borrow().starts_with(&Leaderboard::DISCRIMINATOR) =>
+        {
+            (rest, Some(leaderboard_account_info))
+        }
+        _ => (accounts, None),
+    }
+}
+
+/// Adds a sale at `price` to `buyer`'s and `seller`'s volume if the
+/// season is running; sales outside it leave the board alone.
+pub(crate) fn record_trade(
+    leaderboard_account_info: &AccountInfo,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    price: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let mut leaderboard = Leaderboard::unpack(&leaderboard_account_info.data.borrow())?;
+    let (leaderboard_pubkey, _) = find_leaderboard_address(program_id, leaderboard.season);
+    if !(leaderboard_account_info.key.eq(&leaderboard_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !leaderboard.is_running(Clock::get()?.unix_timestamp) {
+        return Ok(());
+    }
+    leaderboard.record(buyer, price);
+    leaderboard.record(seller, price);
+    Leaderboard::pack(leaderboard, &mut leaderboard_account_info.data.borrow_mut())
+}
+
+fn load_platform_state(state_account_info: &AccountInfo, program_id: &Pubkey) -> Result<PlatformState, ProgramError> {
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(state_info)
+}
@@ -1,8 +1,31 @@
 
+pub mod attestation;
+pub mod core_listing;
+pub mod crowdfund;
 pub mod error;
+pub mod fraction;
+pub mod index;
 pub mod instruction;
+pub mod leaderboard;
+pub mod memo;
+pub mod merkle;
+pub mod metadata;
+pub mod mpl_core;
+pub mod oracle;
+pub mod order;
+pub mod otc;
+pub mod payment;
+pub mod pool;
 pub mod processor;
+pub mod raffle;
+pub mod rental;
+pub mod rewards;
+pub mod session;
+pub mod staking;
 pub mod state;
+pub mod swap;
+pub mod token_2022;
+pub mod trait_offer;
 pub mod types;
 
 use solana_program::{
@@ -12,6 +35,7 @@ use solana_program::{
     pubkey::Pubkey,
 };
 
+#[cfg(not(feature = "no-entrypoint"))]
 entrypoint!(process_instruction);
 
 pub fn process_instruction(
@@ -0,0 +1,110 @@
+//! SPL Memo records of settled trades.
+//!
+//! Every sale CPIs a canonical `sale:<mint>:<price>` memo into the Memo
+//! program so explorers and accounting tools can find trades without
+//! parsing this program's logs. A listing's charity split is recorded next
+//! as `charity:<mint>:<charity>:<lamports>`, each marketplace credited with
+//! the sale as `marketplace:<mint>:<marketplace>:<lamports>`, and a buyer's
+//! tip to the creator as `tip:<mint>:<lamports>`. A client may attach its own memo to
+//! the settling instruction, which is recorded after the canonical ones.
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program::invoke,
+    pubkey::Pubkey,
+};
+
+use crate::error::NFTError;
+
+pub mod spl_memo_program {
+    solana_program::declare_id!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+}
+
+/// Longest client memo, in bytes, a settling instruction may carry.
+pub const MAX_MEMO_LEN: usize = 128;
+
+/// Prefix of the canonical sale memo, which client memos may not use.
+pub const SALE_MEMO_PREFIX: &str = "sale:";
+
+/// Prefix of the canonical tip memo, which client memos may not use.
+pub const TIP_MEMO_PREFIX: &str = "tip:";
+
+/// Prefix of the canonical charity memo, which client memos may not use.
+pub const CHARITY_MEMO_PREFIX: &str = "charity:";
+
+/// Prefix of the canonical marketplace memo, which client memos may not use.
+pub const MARKETPLACE_MEMO_PREFIX: &str = "marketplace:";
+
+/// Fails unless `memo` is UTF-8 of at most `MAX_MEMO_LEN` bytes, as the
+/// Memo program would only reject it later. A client memo cannot pose as
+/// a canonical sale, charity, marketplace or tip record either.
+pub fn check_memo(memo: &[u8]) -> ProgramResult {
+    if memo.len() > MAX_MEMO_LEN {
+        return Err(NFTError::MemoTooLong.into());
+    }
+    if std::str::from_utf8(memo).is_err()
+        || memo.starts_with(SALE_MEMO_PREFIX.as_bytes())
+        || memo.starts_with(TIP_MEMO_PREFIX.as_bytes())
+        || memo.starts_with(CHARITY_MEMO_PREFIX.as_bytes())
+        || memo.starts_with(MARKETPLACE_MEMO_PREFIX.as_bytes())
+    {
+        return Err(NFTError::InvalidInstructionData.into());
+    }
+    Ok(())
+}
+
+pub fn sale_memo(mint: &Pubkey, price: u64) -> String {
+    format!("{}{}:{}", SALE_MEMO_PREFIX, mint, price)
+}
+
+pub fn charity_memo(mint: &Pubkey, charity: &Pubkey, lamports: u64) -> String {
+    format!("{}{}:{}:{}", CHARITY_MEMO_PREFIX, mint, charity, lamports)
+}
+
+pub fn marketplace_memo(mint: &Pubkey, marketplace: &Pubkey, lamports: u64) -> String {
+    format!("{}{}:{}:{}", MARKETPLACE_MEMO_PREFIX, mint, marketplace, lamports)
+}
+
+pub fn tip_memo(mint: &Pubkey, lamports: u64) -> String {
+    format!("{}{}:{}", TIP_MEMO_PREFIX, mint, lamports)
+}
+
+/// Creates a Memo instruction recording `memo` with no signers.
+pub fn memo(memo: &[u8]) -> Instruction {
+    Instruction {
+        program_id: spl_memo_program::id(),
+        accounts: vec![],
+        data: memo.to_vec(),
+    }
+}
+
+/// Records the canonical memo for a sale of `mint` at `price`, followed by
+/// the lamports given to a charity, those paid to each credited
+/// marketplace and `client_memo` if there are any.
+pub(crate) fn record_sale(
+    memo_program_info: &AccountInfo,
+    mint: &Pubkey,
+    price: u64,
+    charity_payout: Option<(&Pubkey, u64)>,
+    marketplace_payouts: &[(&Pubkey, u64)],
+    client_memo: &[u8],
+) -> ProgramResult {
+    invoke(&memo(sale_memo(mint, price).as_bytes()), &[memo_program_info.clone()])?;
+    if let Some((charity, lamports)) = charity_payout {
+        invoke(&memo(charity_memo(mint, charity, lamports).as_bytes()), &[memo_program_info.clone()])?;
+    }
+    for (marketplace, lamports) in marketplace_payouts {
+        invoke(&memo(marketplace_memo(mint, marketplace, *lamports).as_bytes()), &[memo_program_info.clone()])?;
+    }
+    if !client_memo.is_empty() {
+        check_memo(client_memo)?;
+        invoke(&memo(client_memo), &[memo_program_info.clone()])?;
+    }
+    Ok(())
+}
+
+/// Records the canonical memo for a `lamports` tip on the sale of `mint`.
+pub(crate) fn record_tip(memo_program_info: &AccountInfo, mint: &Pubkey, lamports: u64) -> ProgramResult {
+    invoke(&memo(tip_memo(mint, lamports).as_bytes()), &[memo_program_info.clone()])
+}
@@ -0,0 +1,66 @@
+//! Merkle trees over sets of keys, such as the mints a trait offer covers or
+//! the wallets on a listing's allowlist.
+//!
+//! A set is committed to by the root of a tree whose leaves are
+//! `leaf(key)`. Each pair of nodes is hashed in sorted order, so a proof is
+//! only the sibling hashes from the leaf up and doesn't say which side each
+//! is on. Leaves and inner nodes are hashed behind different prefixes, so an
+//! inner node can't be passed off as a leaf. A node without a sibling moves
+//! up a level unchanged.
+use solana_program::{hash::hashv, pubkey::Pubkey};
+
+/// Longest proof accepted on chain, enough for a tree of 2^20 keys.
+pub const MAX_PROOF_LEN: usize = 20usize;
+
+pub fn leaf(key: &Pubkey) -> [u8; 32] {
+    hashv(&[&[0u8], key.as_ref()]).to_bytes()
+}
+
+fn node(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[&[1u8], left, right]).to_bytes()
+}
+
+/// Whether `proof` connects `leaf` to `root`.
+pub fn verify(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    proof.iter().fold(leaf, |hash, sibling| node(&hash, sibling)) == *root
+}
+
+/// Root of the tree over `keys`, in order. Empty sets have the zero root,
+/// which no proof reaches.
+pub fn root(keys: &[Pubkey]) -> [u8; 32] {
+    let mut level: Vec<[u8; 32]> = keys.iter().map(leaf).collect();
+    if level.is_empty() {
+        return [0u8; 32];
+    }
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// Proof for `keys[index]` against `root(keys)`.
+pub fn proof(keys: &[Pubkey], index: usize) -> Vec<[u8; 32]> {
+    let mut level: Vec<[u8; 32]> = keys.iter().map(leaf).collect();
+    let mut index = index;
+    let mut proof = Vec::new();
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        level = next_level(&level);
+        index /= 2;
+    }
+    proof
+}
+
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [a, b] => node(a, b),
+            [a] => *a,
+            _ => unreachable!(),
+        })
+        .collect()
+}
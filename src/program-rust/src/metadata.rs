@@ -0,0 +1,161 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+
+pub mod token_metadata_program {
+    solana_program::declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+}
+
+pub const MAX_CREATOR_LIMIT: usize = 5;
+
+const CREATE_METADATA_ACCOUNT_V2: u8 = 16;
+
+/// Subset of the Metaplex Token Metadata account layout needed to settle
+/// royalties and check collections. Metadata accounts are zero-padded, so
+/// the optional fields decode as `None` on accounts written before they
+/// existed. Trailing fields past `collection` are ignored.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct Metadata {
+    pub key: u8,
+    pub update_authority: [u8; 32],
+    pub mint: [u8; 32],
+    pub data: Data,
+    pub primary_sale_happened: bool,
+    pub is_mutable: bool,
+    pub edition_nonce: Option<u8>,
+    pub token_standard: Option<u8>,
+    pub collection: Option<Collection>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct Data {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub seller_fee_basis_points: u16,
+    pub creators: Option<Vec<Creator>>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct Creator {
+    pub address: [u8; 32],
+    pub verified: bool,
+    pub share: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct Collection {
+    pub verified: bool,
+    pub key: [u8; 32],
+}
+
+impl Metadata {
+    pub fn verified_creators(&self) -> Vec<&Creator> {
+        self.data.creators
+            .iter()
+            .flatten()
+            .filter(|creator| creator.verified)
+            .collect()
+    }
+
+    pub fn verified_collection(&self) -> Option<Pubkey> {
+        self.collection
+            .as_ref()
+            .filter(|collection| collection.verified)
+            .map(|collection| Pubkey::new_from_array(collection.key))
+    }
+
+    /// Whether the mint has a verified collection or any verified creator.
+    pub fn is_verified(&self) -> bool {
+        self.verified_collection().is_some() || !self.verified_creators().is_empty()
+    }
+}
+
+pub fn find_metadata_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"metadata", token_metadata_program::id().as_ref(), mint.as_ref()],
+        &token_metadata_program::id(),
+    )
+}
+
+/// Loads the metadata for `mint`, or `None` if the mint has no metadata
+/// account.
+pub fn load_metadata(metadata_info: &AccountInfo, mint: &Pubkey) -> Result<Option<Metadata>, ProgramError> {
+    let (metadata_pubkey, _) = find_metadata_address(mint);
+    if !(metadata_info.key.eq(&metadata_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if metadata_info.data_is_empty() {
+        return Ok(None);
+    }
+    if !(metadata_info.owner.eq(&token_metadata_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let metadata = Metadata::deserialize(&mut &metadata_info.data.borrow()[..])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if metadata.mint != mint.to_bytes() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if metadata.data.creators.as_ref().map_or(0, Vec::len) > MAX_CREATOR_LIMIT {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(Some(metadata))
+}
+
+/// Arguments of Token Metadata's `CreateMetadataAccountV2`, the `DataV2`
+/// fields followed by `is_mutable`. Collections and uses are left unset;
+/// an unset option encodes as a lone zero whatever it holds.
+#[derive(BorshSerialize)]
+struct CreateMetadataAccountArgsV2 {
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    creators: Option<Vec<Creator>>,
+    collection: Option<Collection>,
+    uses: Option<u8>,
+    is_mutable: bool,
+}
+
+/// Creates a Token Metadata `CreateMetadataAccountV2` instruction for
+/// `mint`. `authority` is mint authority, payer and update authority, and
+/// is listed as the sole verified creator, which needs its signature.
+pub fn create_metadata_account(
+    mint: &Pubkey,
+    authority: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+) -> Instruction {
+    let args = CreateMetadataAccountArgsV2 {
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points,
+        creators: Some(vec![Creator { address: authority.to_bytes(), verified: true, share: 100 }]),
+        collection: None,
+        uses: None,
+        is_mutable: true,
+    };
+    let mut data = vec![CREATE_METADATA_ACCOUNT_V2];
+    data.extend_from_slice(&args.try_to_vec().expect("metadata arguments serialize"));
+    Instruction {
+        program_id: token_metadata_program::id(),
+        accounts: vec![
+            AccountMeta::new(find_metadata_address(mint).0, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data,
+    }
+}
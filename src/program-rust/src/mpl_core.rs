@@ -0,0 +1,81 @@
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+pub mod mpl_core_program {
+    solana_program::declare_id!("CoREENxT6tW1HoK8ypY1SxRMZTcVPm7R94rH4PZNhX7d");
+}
+
+const ASSET_V1_KEY: u8 = 1;
+const TRANSFER_V1: u8 = 14;
+
+/// Leading fields of a Metaplex Core `AssetV1` account. The name, uri and
+/// plugins that follow are ignored.
+#[derive(BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct AssetV1 {
+    pub key: u8,
+    pub owner: Pubkey,
+    pub update_authority: UpdateAuthority,
+}
+
+#[derive(BorshDeserialize, Clone, Debug, PartialEq)]
+pub enum UpdateAuthority {
+    None,
+    Address(Pubkey),
+    Collection(Pubkey),
+}
+
+impl AssetV1 {
+    /// The collection the asset belongs to. Core only lets a collection's
+    /// authority add assets to it, so membership needs no separate
+    /// verification.
+    pub fn collection(&self) -> Option<Pubkey> {
+        match self.update_authority {
+            UpdateAuthority::Collection(collection) => Some(collection),
+            _ => None,
+        }
+    }
+}
+
+pub fn load_asset(asset_info: &AccountInfo) -> Result<AssetV1, ProgramError> {
+    if !(asset_info.owner.eq(&mpl_core_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let asset = AssetV1::deserialize(&mut &asset_info.data.borrow()[..])
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if asset.key != ASSET_V1_KEY {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(asset)
+}
+
+/// Creates a Core `TransferV1` instruction moving `asset` to `new_owner`,
+/// signed by its current owner `authority`. Core takes its own program id in
+/// place of optional accounts that are left out.
+pub fn transfer(
+    asset: &Pubkey,
+    collection: Option<&Pubkey>,
+    payer: &Pubkey,
+    authority: &Pubkey,
+    new_owner: &Pubkey,
+) -> Instruction {
+    let program_id = mpl_core_program::id();
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(*asset, false),
+            AccountMeta::new_readonly(*collection.unwrap_or(&program_id), false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(*new_owner, false),
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new_readonly(program_id, false),
+        ],
+        // No compression proof.
+        data: vec![TRANSFER_V1, 0],
+    }
+}
@@ -0,0 +1,118 @@
+//! SOL/USD prices for listings priced in dollars.
+//!
+//! The platform authority names a Pyth SOL/USD price account with
+//! `SetPriceFeed`, and a listing priced in USD keeps the feed it was listed
+//! under, so a new feed only prices new listings. Its lamport ask is worked out when it fills, from the feed's
+//! aggregate price, which has to be recent, trading, and tight enough to
+//! trust. Only the fields needed for that are read, at their offsets in
+//! Pyth's version 2 price account layout; the feed's key is checked
+//! against the listing instead of its owner.
+use std::convert::TryFrom;
+
+use arrayref::{array_ref, array_refs};
+use solana_program::{account_info::AccountInfo, native_token::LAMPORTS_PER_SOL, program_error::ProgramError};
+
+use crate::{error::NFTError, state::MAX_BPS};
+
+pub const PYTH_MAGIC: u32 = 0xa1b2_c3d4;
+pub const PYTH_VERSION: u32 = 2;
+/// `atype` of a price account, as opposed to a mapping or product account.
+pub const PYTH_PRICE_ACCOUNT: u32 = 3;
+/// Aggregate status of a feed that is trading normally.
+pub const PYTH_STATUS_TRADING: u32 = 1;
+/// Bytes of a price account read, up to the end of the aggregate price.
+pub const PRICE_ACCOUNT_LEN: usize = 240;
+/// `price_usd` is in millionths of a dollar.
+pub const USD_DECIMALS: u32 = 6;
+/// Slots since the aggregate was published after which it is stale.
+pub const MAX_PRICE_AGE_SLOTS: u64 = 25;
+/// Widest confidence interval, in basis points of the price, a sale
+/// settles at.
+pub const MAX_CONFIDENCE_BPS: u64 = 200;
+
+/// Aggregate SOL/USD price: `price * 10^expo` dollars, give or take
+/// `conf * 10^expo`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Price {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub status: u32,
+    pub pub_slot: u64,
+}
+
+impl Price {
+    /// Reads the aggregate price from a Pyth price account's data.
+    pub fn unpack(src: &[u8]) -> Result<Price, ProgramError> {
+        if src.len() < PRICE_ACCOUNT_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, PRICE_ACCOUNT_LEN];
+        let (magic, version, atype, _, expo, _, aggregate) = array_refs![src, 4, 4, 4, 8, 4, 184, 32];
+        if u32::from_le_bytes(*magic) != PYTH_MAGIC
+            || u32::from_le_bytes(*version) != PYTH_VERSION
+            || u32::from_le_bytes(*atype) != PYTH_PRICE_ACCOUNT
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let (price, conf, status, _, pub_slot) = array_refs![aggregate, 8, 8, 4, 4, 8];
+        Ok(Price{
+            price: i64::from_le_bytes(*price),
+            conf: u64::from_le_bytes(*conf),
+            expo: i32::from_le_bytes(*expo),
+            status: u32::from_le_bytes(*status),
+            pub_slot: u64::from_le_bytes(*pub_slot),
+        })
+    }
+
+    /// Fails unless the price is positive, trading, published within
+    /// `MAX_PRICE_AGE_SLOTS` of `slot` and within `MAX_CONFIDENCE_BPS`.
+    pub fn check_reliable(&self, slot: u64) -> Result<(), ProgramError> {
+        let stale = self.pub_slot.saturating_add(MAX_PRICE_AGE_SLOTS) < slot;
+        if self.price <= 0 || self.status != PYTH_STATUS_TRADING || stale {
+            return Err(NFTError::UnreliablePrice.into());
+        }
+        if (self.conf as u128) * (MAX_BPS as u128) > (self.price as u128) * (MAX_CONFIDENCE_BPS as u128) {
+            return Err(NFTError::UnreliablePrice.into());
+        }
+        Ok(())
+    }
+
+    /// Lamports worth `price_usd` millionths of a dollar, rounded up so the
+    /// seller never gets less than the asking price.
+    pub fn usd_to_lamports(&self, price_usd: u64) -> Result<u64, ProgramError> {
+        if self.price <= 0 {
+            return Err(NFTError::UnreliablePrice.into());
+        }
+        let scale = |exponent: u32| 10u128.checked_pow(exponent).ok_or(NFTError::NumericalOverflow);
+        let mut numerator = (price_usd as u128)
+            .checked_mul(LAMPORTS_PER_SOL as u128)
+            .ok_or(NFTError::NumericalOverflow)?;
+        let mut denominator = (self.price as u128)
+            .checked_mul(scale(USD_DECIMALS)?)
+            .ok_or(NFTError::NumericalOverflow)?;
+        if self.expo < 0 {
+            numerator = numerator.checked_mul(scale(self.expo.unsigned_abs())?).ok_or(NFTError::NumericalOverflow)?;
+        } else {
+            denominator = denominator.checked_mul(scale(self.expo as u32)?).ok_or(NFTError::NumericalOverflow)?;
+        }
+        let lamports = numerator
+            .checked_add(denominator - 1)
+            .ok_or(NFTError::NumericalOverflow)?
+            / denominator;
+        u64::try_from(lamports).map_err(|_| NFTError::NumericalOverflow.into())
+    }
+}
+
+/// Strips a price feed appended to a sale's accounts, if its last account
+/// is a Pyth price account. Returns the remaining accounts and the feed.
+pub(crate) fn split_price_feed<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+) -> (&'a [AccountInfo<'b>], Option<&'a AccountInfo<'b>>) {
+    match accounts.split_last() {
+        Some((price_feed_info, rest)) if Price::unpack(&price_feed_info.data.borrow()).is_ok() => {
+            (rest, Some(price_feed_info))
+        }
+        _ => (accounts, None),
+    }
+}
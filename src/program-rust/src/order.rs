@@ -0,0 +1,122 @@
+use std::convert::TryInto;
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::instructions,
+};
+use crate::error::NFTError;
+
+pub mod ed25519_program {
+    solana_program::declare_id!("Ed25519SigVerify111111111111111111111111111");
+}
+
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_LEN: usize = 14;
+const DATA_START: usize = SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN;
+const PUBKEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+
+/// Bytes a seller signs to authorize a fill of their `mint` at `price`
+/// lamports until `expiry` (unix timestamp).
+pub fn order_message(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    mint: &Pubkey,
+    price: u64,
+    expiry: i64,
+    nonce: u64,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(120);
+    message.extend_from_slice(program_id.as_ref());
+    message.extend_from_slice(seller.as_ref());
+    message.extend_from_slice(mint.as_ref());
+    message.extend_from_slice(&price.to_be_bytes());
+    message.extend_from_slice(&expiry.to_be_bytes());
+    message.extend_from_slice(&nonce.to_be_bytes());
+    message
+}
+
+/// Creates an ed25519 program instruction verifying `signature` by `signer`
+/// over `message`. It must immediately precede the `FillOrder` instruction.
+pub fn ed25519_instruction(signer: &Pubkey, signature: &[u8; 64], message: &[u8]) -> Instruction {
+    let public_key_offset = DATA_START;
+    let signature_offset = public_key_offset + PUBKEY_LEN;
+    let message_data_offset = signature_offset + SIGNATURE_LEN;
+
+    let mut data = Vec::with_capacity(message_data_offset + message.len());
+    data.push(1);
+    data.push(0);
+    for offset in &[
+        signature_offset as u16,
+        u16::MAX,
+        public_key_offset as u16,
+        u16::MAX,
+        message_data_offset as u16,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ed25519_program::id(),
+        accounts: vec![],
+        data,
+    }
+}
+
+/// Checks that the instruction before the current one is an ed25519
+/// verification of exactly `message` signed by `signer`. The runtime has
+/// already rejected the transaction if that signature is invalid.
+pub fn verify_signed_order(
+    instructions_info: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> ProgramResult {
+    if !(instructions_info.key.eq(&instructions::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let data = instructions_info.data.borrow();
+    let current_index = instructions::load_current_index(&data);
+    if current_index == 0 {
+        return Err(NFTError::InvalidOrderSignature.into());
+    }
+    let ed25519_index = current_index - 1;
+    let ed25519_ix = instructions::load_instruction_at(ed25519_index as usize, &data)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if !(ed25519_ix.program_id.eq(&ed25519_program::id())) {
+        return Err(NFTError::InvalidOrderSignature.into());
+    }
+
+    let ix_data = &ed25519_ix.data;
+    if ix_data.len() < DATA_START || ix_data[0] != 1 {
+        return Err(NFTError::InvalidOrderSignature.into());
+    }
+    let offset = |i: usize| -> u16 {
+        let start = SIGNATURE_OFFSETS_START + i * 2;
+        u16::from_le_bytes(ix_data[start..start + 2].try_into().unwrap())
+    };
+    let local = |index: u16| index == u16::MAX || index == ed25519_index;
+    if !(local(offset(1)) && local(offset(3)) && local(offset(6))) {
+        return Err(NFTError::InvalidOrderSignature.into());
+    }
+
+    let public_key_offset = offset(2) as usize;
+    let message_data_offset = offset(4) as usize;
+    let message_data_size = offset(5) as usize;
+    let public_key = ix_data.get(public_key_offset..public_key_offset + PUBKEY_LEN)
+        .ok_or(NFTError::InvalidOrderSignature)?;
+    let signed_message = ix_data.get(message_data_offset..message_data_offset + message_data_size)
+        .ok_or(NFTError::InvalidOrderSignature)?;
+    if public_key != signer.as_ref() || signed_message != message {
+        return Err(NFTError::InvalidOrderSignature.into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,498 @@
+//! Two-party OTC trades.
+//!
+//! The maker escrows an NFT and names the only counterparty allowed to take
+//! it and the exact price. The counterparty's payment is the second leg:
+//! `AcceptOtc` pays the maker and releases the NFT in the same
+//! instruction, so the trade settles atomically once both legs are in.
+//! Until then either side can cancel, which returns the NFT to the maker.
+//! Fees match `FillOrder`: the maker fee comes out of the price and the
+//! taker fee is charged on top.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{attestation, error::NFTError, processor::Processor, state::{self, Discriminator, PlatformState}};
+
+pub const OTCSIZE: usize = 113usize;
+
+pub fn find_otc_state_address(program_id: &Pubkey, mint: &Pubkey, maker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), maker.as_ref(), b"Otc", b"State"], program_id)
+}
+
+pub fn find_otc_vault_address(program_id: &Pubkey, mint: &Pubkey, maker: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), maker.as_ref(), b"Otc", b"Vault"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OtcState {
+    pub is_initialized: bool,
+    pub maker: Pubkey,
+    pub counterparty: Pubkey,
+    pub mint: Pubkey,
+    pub price: u64,
+}
+
+impl Sealed for OtcState{}
+
+impl Discriminator for OtcState{
+    const DISCRIMINATOR: [u8; 8] = *b"otc\0\0\0\0\0";
+}
+
+impl IsInitialized for OtcState{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for OtcState {
+    const LEN: usize = OTCSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < OtcState::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, OtcState::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            maker,
+            counterparty,
+            mint,
+            price,
+        ) = array_refs![src, 8, 1, 32, 32, 32, 8];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(OtcState{
+            is_initialized,
+            maker: Pubkey::new_from_array(*maker),
+            counterparty: Pubkey::new_from_array(*counterparty),
+            mint: Pubkey::new_from_array(*mint),
+            price: u64::from_be_bytes(*price),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, OtcState::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            maker_dst,
+            counterparty_dst,
+            mint_dst,
+            price_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 32, 32, 8];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+
+        let OtcState {
+            is_initialized,
+            maker,
+            counterparty,
+            mint,
+            price,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        maker_dst.copy_from_slice(maker.as_ref());
+        counterparty_dst.copy_from_slice(counterparty.as_ref());
+        mint_dst.copy_from_slice(mint.as_ref());
+        *price_dst = price.to_be_bytes();
+    }
+}
+
+pub fn process_create_otc(
+    accounts: &[AccountInfo],
+    price: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let maker_info = next_account_info(account_info_iter)?;
+    if !maker_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let token_account_info = next_account_info(account_info_iter)?;
+    let mint_account_info = next_account_info(account_info_iter)?;
+    if !(mint_account_info.owner.eq(&spl_token::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let token_account = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+    if !(token_account.owner.eq(maker_info.key)) || !(token_account.mint.eq(mint_account_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let counterparty_info = next_account_info(account_info_iter)?;
+    if counterparty_info.key.eq(maker_info.key) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let otc_state_account_info = next_account_info(account_info_iter)?;
+    let otc_vault_account_info = next_account_info(account_info_iter)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+    let rent = &Rent::get()?;
+
+    let (otc_state_pubkey, state_nonce) = find_otc_state_address(program_id, mint_account_info.key, maker_info.key);
+    if !(otc_state_account_info.key.eq(&otc_state_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_state_account_ix = system_instruction::create_account(
+        maker_info.key,
+        &otc_state_pubkey,
+        rent.minimum_balance(OTCSIZE),
+        OTCSIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived otc state account");
+    invoke_signed(
+        &create_state_account_ix,
+        &[
+            maker_info.clone(),
+            otc_state_account_info.clone(),
+            system_program_info.clone(),
+            program_info.clone(),
+        ],
+        &[&[
+            mint_account_info.key.as_ref(),
+            maker_info.key.as_ref(),
+            &b"Otc"[..],
+            &b"State"[..],
+            &[state_nonce]
+        ]],
+    )?;
+
+    let (otc_vault_pubkey, vault_nonce) = find_otc_vault_address(program_id, mint_account_info.key, maker_info.key);
+    if !(otc_vault_account_info.key.eq(&otc_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_vault_account_ix = system_instruction::create_account(
+        maker_info.key,
+        &otc_vault_pubkey,
+        rent.minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN as u64,
+        &spl_token::id());
+    invoke_signed(
+        &create_vault_account_ix,
+        &[
+            maker_info.clone(),
+            otc_vault_account_info.clone(),
+            system_program_info.clone(),
+            token_program_info.clone(),
+        ],
+        &[&[
+            mint_account_info.key.as_ref(),
+            maker_info.key.as_ref(),
+            &b"Otc"[..],
+            &b"Vault"[..],
+            &[vault_nonce]
+        ]],
+    )?;
+
+    let initialize_vault_account_ix = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &otc_vault_pubkey,
+        mint_account_info.key,
+        &otc_state_pubkey
+    )?;
+    invoke(
+        &initialize_vault_account_ix,
+        &[
+            token_program_info.clone(),
+            otc_vault_account_info.clone(),
+            mint_account_info.clone(),
+            otc_state_account_info.clone(),
+            rent_account_info.clone()
+        ],
+    )?;
+
+    let transfer_token_ix = spl_token::instruction::transfer_checked(
+        &spl_token::id(),
+        token_account_info.key,
+        mint_account_info.key,
+        &otc_vault_pubkey,
+        maker_info.key,
+        &[
+            maker_info.key
+        ],
+        1,
+        0
+    )?;
+    invoke(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            token_account_info.clone(),
+            mint_account_info.clone(),
+            otc_vault_account_info.clone(),
+            maker_info.clone()
+        ],
+    )?;
+
+    OtcState::pack(
+        OtcState{
+            is_initialized: true,
+            maker: *maker_info.key,
+            counterparty: *counterparty_info.key,
+            mint: *mint_account_info.key,
+            price,
+        },
+        &mut otc_state_account_info.data.borrow_mut()
+    )?;
+
+    Ok(())
+}
+
+/// Pays the agreed price and takes the escrowed NFT.
+pub fn process_accept_otc(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let taker_info = next_account_info(account_info_iter)?;
+    if !taker_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let taker_token_account_info = next_account_info(account_info_iter)?;
+
+    let otc_state_account_info = next_account_info(account_info_iter)?;
+    let otc_state = load_otc_state(otc_state_account_info, program_id)?;
+    if !(otc_state.counterparty.eq(taker_info.key)) {
+        return Err(NFTError::BuyerNotAllowed.into());
+    }
+
+    let otc_vault_account_info = next_account_info(account_info_iter)?;
+    let maker_info = next_account_info(account_info_iter)?;
+    if !(maker_info.key.eq(&otc_state.maker)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    attestation::check_not_required(&state_info)?;
+
+    let fee_recipient_info = next_account_info(account_info_iter)?;
+    if !state_info.fee_recipient.eq(fee_recipient_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let maker_fee = Processor::calculate_fee(otc_state.price, state_info.maker_fee_bps)?;
+    let taker_fee = Processor::calculate_fee(otc_state.price, state_info.taker_fee_bps)?;
+    let platform_fee = maker_fee
+        .checked_add(taker_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let maker_proceeds = otc_state.price
+        .checked_sub(maker_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let payouts = vec![(maker_info, maker_proceeds), (fee_recipient_info, platform_fee)];
+    for (recipient_info, lamports) in payouts {
+        if lamports == 0 {
+            continue;
+        }
+        invoke(
+            &system_instruction::transfer(taker_info.key, recipient_info.key, lamports),
+            &[
+                taker_info.clone(),
+                recipient_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
+    close_otc(
+        &otc_state,
+        otc_state_account_info,
+        otc_vault_account_info,
+        taker_token_account_info,
+        maker_info,
+        token_program_info,
+        program_id,
+    )?;
+
+    state_info.sale_count = state_info.sale_count
+        .checked_add(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.total_volume = state_info.total_volume
+        .checked_add(otc_state.price)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.total_fees = state_info.total_fees
+        .checked_add(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+/// Called by either party before the trade settles; returns the NFT to the
+/// maker.
+pub fn process_cancel_otc(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let signer_info = next_account_info(account_info_iter)?;
+    if !signer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let maker_info = next_account_info(account_info_iter)?;
+    let maker_token_account_info = next_account_info(account_info_iter)?;
+
+    let otc_state_account_info = next_account_info(account_info_iter)?;
+    let otc_state = load_otc_state(otc_state_account_info, program_id)?;
+    if !(otc_state.maker.eq(signer_info.key)) && !(otc_state.counterparty.eq(signer_info.key)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+    if !(maker_info.key.eq(&otc_state.maker)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let maker_token_account = spl_token::state::Account::unpack(&maker_token_account_info.data.borrow())?;
+    if !(maker_token_account.owner.eq(maker_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let otc_vault_account_info = next_account_info(account_info_iter)?;
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    close_otc(
+        &otc_state,
+        otc_state_account_info,
+        otc_vault_account_info,
+        maker_token_account_info,
+        maker_info,
+        token_program_info,
+        program_id,
+    )
+}
+
+fn load_otc_state(otc_state_account_info: &AccountInfo, program_id: &Pubkey) -> Result<OtcState, ProgramError> {
+    Processor::assert_owned_by(otc_state_account_info, program_id)?;
+    let otc_state = OtcState::unpack(&otc_state_account_info.data.borrow())?;
+    let (otc_state_pubkey, _) = find_otc_state_address(program_id, &otc_state.mint, &otc_state.maker);
+    if !(otc_state_account_info.key.eq(&otc_state_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(otc_state)
+}
+
+/// Sends the escrowed NFT to `token_account_info` and closes the vault and
+/// the state account, returning their rent to the maker.
+fn close_otc<'a>(
+    otc_state: &OtcState,
+    otc_state_account_info: &AccountInfo<'a>,
+    otc_vault_account_info: &AccountInfo<'a>,
+    token_account_info: &AccountInfo<'a>,
+    maker_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (otc_vault_pubkey, _) = find_otc_vault_address(program_id, &otc_state.mint, &otc_state.maker);
+    if !(otc_vault_account_info.key.eq(&otc_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (otc_state_pubkey, state_nonce) = find_otc_state_address(program_id, &otc_state.mint, &otc_state.maker);
+    let state_seeds: &[&[u8]] = &[
+        otc_state.mint.as_ref(),
+        otc_state.maker.as_ref(),
+        &b"Otc"[..],
+        &b"State"[..],
+        &[state_nonce]
+    ];
+
+    let transfer_token_ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &otc_vault_pubkey,
+        token_account_info.key,
+        &otc_state_pubkey,
+        &[&otc_state_pubkey],
+        1
+    )?;
+    invoke_signed(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            otc_vault_account_info.clone(),
+            token_account_info.clone(),
+            otc_state_account_info.clone()
+        ],
+        &[state_seeds],
+    )?;
+
+    let close_ix = spl_token::instruction::close_account(
+        &spl_token::id(),
+        &otc_vault_pubkey,
+        maker_info.key,
+        &otc_state_pubkey,
+        &[&otc_state_pubkey]
+    )?;
+    invoke_signed(
+        &close_ix,
+        &[
+            token_program_info.clone(),
+            otc_vault_account_info.clone(),
+            maker_info.clone(),
+            otc_state_account_info.clone(),
+        ],
+        &[state_seeds],
+    )?;
+
+    Processor::close_account(otc_state_account_info, maker_info, program_id)?;
+
+    Ok(())
+}
@@ -0,0 +1,1154 @@
+//! Trades quoted in SPL tokens instead of SOL.
+//!
+//! The authority keeps a registry of payment mints, one `PaymentMint`
+//! account per mint, and only enabled mints can be quoted, so listings and
+//! bids cannot be denominated in arbitrary tokens. A token listing escrows
+//! the NFT and declares its quote mint; a token bid escrows the bid and its
+//! taker fee in that mint. `AcceptTokenBid` only pairs a listing and a bid
+//! with the same quote mint, and pays out with `transfer_checked` against
+//! the mint's own decimals. Prices are in the quote mint's base units.
+//! Fees match `AcceptOtc`. SOL trades keep using `List` and `Bid`; the
+//! platform minimums and volume totals are in lamports and do not apply
+//! here, and no royalties are paid on token-quoted sales.
+//!
+//! Every transfer of the quote mint is checked against the receiving
+//! account's balance afterwards. A mint that withholds a fee in transit,
+//! as Token-2022 transfer-fee mints do, fails the trade with
+//! `TransferShortfall` instead of leaving the vault or the seller short.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{attestation, error::NFTError, processor::Processor, state::{self, Discriminator, PlatformState}};
+
+pub const PAYMENTMINTSIZE: usize = 43usize;
+pub const TOKENLISTINGSIZE: usize = 113usize;
+pub const TOKENBIDSIZE: usize = 121usize;
+
+pub fn find_payment_mint_address(program_id: &Pubkey, quote_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[quote_mint.as_ref(), b"Payment", b"Mint"], program_id)
+}
+
+pub fn find_token_listing_address(program_id: &Pubkey, mint: &Pubkey, lister: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), lister.as_ref(), b"Token", b"List"], program_id)
+}
+
+pub fn find_token_listing_vault_address(program_id: &Pubkey, mint: &Pubkey, lister: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), lister.as_ref(), b"Token", b"ListVault"], program_id)
+}
+
+pub fn find_token_bid_address(program_id: &Pubkey, mint: &Pubkey, bidder: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), bidder.as_ref(), b"Token", b"Bid"], program_id)
+}
+
+pub fn find_token_bid_vault_address(program_id: &Pubkey, mint: &Pubkey, bidder: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), bidder.as_ref(), b"Token", b"BidVault"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PaymentMint {
+    pub is_initialized: bool,
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub enabled: bool,
+}
+
+impl Sealed for PaymentMint{}
+
+impl Discriminator for PaymentMint{
+    const DISCRIMINATOR: [u8; 8] = *b"paymint\0";
+}
+
+impl IsInitialized for PaymentMint{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for PaymentMint {
+    const LEN: usize = PAYMENTMINTSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < PaymentMint::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, PaymentMint::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            mint,
+            decimals,
+            enabled,
+        ) = array_refs![src, 8, 1, 32, 1, 1];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let enabled = match enabled {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(PaymentMint{
+            is_initialized,
+            mint: Pubkey::new_from_array(*mint),
+            decimals: decimals[0],
+            enabled,
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, PaymentMint::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            mint_dst,
+            decimals_dst,
+            enabled_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 1, 1];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+        is_initialized_dst[0] = self.is_initialized as u8;
+        mint_dst.copy_from_slice(self.mint.as_ref());
+        decimals_dst[0] = self.decimals;
+        enabled_dst[0] = self.enabled as u8;
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TokenListing {
+    pub is_initialized: bool,
+    pub lister: Pubkey,
+    pub mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub price: u64,
+}
+
+impl Sealed for TokenListing{}
+
+impl Discriminator for TokenListing{
+    const DISCRIMINATOR: [u8; 8] = *b"tokenlst";
+}
+
+impl IsInitialized for TokenListing{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for TokenListing {
+    const LEN: usize = TOKENLISTINGSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < TokenListing::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, TokenListing::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            lister,
+            mint,
+            quote_mint,
+            price,
+        ) = array_refs![src, 8, 1, 32, 32, 32, 8];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(TokenListing{
+            is_initialized,
+            lister: Pubkey::new_from_array(*lister),
+            mint: Pubkey::new_from_array(*mint),
+            quote_mint: Pubkey::new_from_array(*quote_mint),
+            price: u64::from_be_bytes(*price),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, TokenListing::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            lister_dst,
+            mint_dst,
+            quote_mint_dst,
+            price_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 32, 32, 8];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+        is_initialized_dst[0] = self.is_initialized as u8;
+        lister_dst.copy_from_slice(self.lister.as_ref());
+        mint_dst.copy_from_slice(self.mint.as_ref());
+        quote_mint_dst.copy_from_slice(self.quote_mint.as_ref());
+        *price_dst = self.price.to_be_bytes();
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TokenBid {
+    pub is_initialized: bool,
+    pub bidder: Pubkey,
+    pub mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub amount: u64,
+    pub taker_fee: u64,
+}
+
+impl Sealed for TokenBid{}
+
+impl Discriminator for TokenBid{
+    const DISCRIMINATOR: [u8; 8] = *b"tokenbid";
+}
+
+impl IsInitialized for TokenBid{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for TokenBid {
+    const LEN: usize = TOKENBIDSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < TokenBid::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, TokenBid::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            bidder,
+            mint,
+            quote_mint,
+            amount,
+            taker_fee,
+        ) = array_refs![src, 8, 1, 32, 32, 32, 8, 8];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(TokenBid{
+            is_initialized,
+            bidder: Pubkey::new_from_array(*bidder),
+            mint: Pubkey::new_from_array(*mint),
+            quote_mint: Pubkey::new_from_array(*quote_mint),
+            amount: u64::from_be_bytes(*amount),
+            taker_fee: u64::from_be_bytes(*taker_fee),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, TokenBid::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            bidder_dst,
+            mint_dst,
+            quote_mint_dst,
+            amount_dst,
+            taker_fee_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 32, 32, 8, 8];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+        is_initialized_dst[0] = self.is_initialized as u8;
+        bidder_dst.copy_from_slice(self.bidder.as_ref());
+        mint_dst.copy_from_slice(self.mint.as_ref());
+        quote_mint_dst.copy_from_slice(self.quote_mint.as_ref());
+        *amount_dst = self.amount.to_be_bytes();
+        *taker_fee_dst = self.taker_fee.to_be_bytes();
+    }
+}
+
+/// Adds `quote_mint` to the registry or toggles it. Disabling a mint stops
+/// new listings and bids in it; open ones can still settle or be withdrawn.
+pub fn process_set_payment_mint(
+    accounts: &[AccountInfo],
+    enabled: bool,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let authority_info = next_account_info(account_info_iter)?;
+    if !authority_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let quote_mint_info = next_account_info(account_info_iter)?;
+    if !(quote_mint_info.owner.eq(&spl_token::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let quote_mint = spl_token::state::Mint::unpack(&quote_mint_info.data.borrow())?;
+
+    let payment_mint_account_info = next_account_info(account_info_iter)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Processor::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::get()?;
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if !state_info.authority.eq(authority_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let (payment_mint_pubkey, nonce) = find_payment_mint_address(program_id, quote_mint_info.key);
+    if !(payment_mint_account_info.key.eq(&payment_mint_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if payment_mint_account_info.data_is_empty() {
+        let create_payment_mint_account_ix = system_instruction::create_account(
+            authority_info.key,
+            &payment_mint_pubkey,
+            rent.minimum_balance(PAYMENTMINTSIZE),
+            PAYMENTMINTSIZE as u64,
+            program_id);
+        msg!("submitting tx to create program derived payment mint account");
+        invoke_signed(
+            &create_payment_mint_account_ix,
+            &[
+                authority_info.clone(),
+                payment_mint_account_info.clone(),
+                system_program_info.clone(),
+                program_info.clone(),
+            ],
+            &[&[
+                quote_mint_info.key.as_ref(),
+                &b"Payment"[..],
+                &b"Mint"[..],
+                &[nonce]
+            ]],
+        )?;
+    }
+
+    PaymentMint::pack(
+        PaymentMint{
+            is_initialized: true,
+            mint: *quote_mint_info.key,
+            decimals: quote_mint.decimals,
+            enabled,
+        },
+        &mut payment_mint_account_info.data.borrow_mut()
+    )?;
+
+    Ok(())
+}
+
+pub fn process_list_for_token(
+    accounts: &[AccountInfo],
+    price: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lister_info = next_account_info(account_info_iter)?;
+    if !lister_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let token_account_info = next_account_info(account_info_iter)?;
+    let mint_account_info = next_account_info(account_info_iter)?;
+    Processor::check_nft_mint(mint_account_info)?;
+    let token_account = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+    if !(token_account.owner.eq(lister_info.key)) || !(token_account.mint.eq(mint_account_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::check_seller_token_account(&token_account)?;
+
+    let quote_mint_info = next_account_info(account_info_iter)?;
+    let payment_mint_account_info = next_account_info(account_info_iter)?;
+    load_enabled_payment_mint(payment_mint_account_info, quote_mint_info.key, program_id)?;
+
+    let listing_account_info = next_account_info(account_info_iter)?;
+    let listing_vault_account_info = next_account_info(account_info_iter)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+    let rent = &Rent::get()?;
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if state_info.is_paused(state::PAUSE_LIST) {
+        return Err(NFTError::InstructionPaused.into());
+    }
+
+    let (listing_pubkey, listing_nonce) = find_token_listing_address(program_id, mint_account_info.key, lister_info.key);
+    if !(listing_account_info.key.eq(&listing_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !listing_account_info.data_is_empty() {
+        return Err(NFTError::ListingAlreadyExists.into());
+    }
+    let create_listing_account_ix = system_instruction::create_account(
+        lister_info.key,
+        &listing_pubkey,
+        rent.minimum_balance(TOKENLISTINGSIZE),
+        TOKENLISTINGSIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived token listing account");
+    invoke_signed(
+        &create_listing_account_ix,
+        &[
+            lister_info.clone(),
+            listing_account_info.clone(),
+            system_program_info.clone(),
+            program_info.clone(),
+        ],
+        &[&[
+            mint_account_info.key.as_ref(),
+            lister_info.key.as_ref(),
+            &b"Token"[..],
+            &b"List"[..],
+            &[listing_nonce]
+        ]],
+    )?;
+
+    let (listing_vault_pubkey, vault_nonce) = find_token_listing_vault_address(program_id, mint_account_info.key, lister_info.key);
+    if !(listing_vault_account_info.key.eq(&listing_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    create_vault(
+        lister_info,
+        listing_vault_account_info,
+        mint_account_info,
+        listing_account_info,
+        system_program_info,
+        token_program_info,
+        rent_account_info,
+        &[
+            mint_account_info.key.as_ref(),
+            lister_info.key.as_ref(),
+            &b"Token"[..],
+            &b"ListVault"[..],
+            &[vault_nonce]
+        ],
+    )?;
+
+    let transfer_token_ix = spl_token::instruction::transfer_checked(
+        &spl_token::id(),
+        token_account_info.key,
+        mint_account_info.key,
+        &listing_vault_pubkey,
+        lister_info.key,
+        &[
+            lister_info.key
+        ],
+        1,
+        0
+    )?;
+    invoke(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            token_account_info.clone(),
+            mint_account_info.clone(),
+            listing_vault_account_info.clone(),
+            lister_info.clone()
+        ],
+    )?;
+
+    TokenListing::pack(
+        TokenListing{
+            is_initialized: true,
+            lister: *lister_info.key,
+            mint: *mint_account_info.key,
+            quote_mint: *quote_mint_info.key,
+            price,
+        },
+        &mut listing_account_info.data.borrow_mut()
+    )?;
+
+    state_info.active_listings = state_info.active_listings
+        .checked_add(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_cancel_token_listing(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lister_info = next_account_info(account_info_iter)?;
+    if !lister_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let token_account_info = next_account_info(account_info_iter)?;
+
+    let listing_account_info = next_account_info(account_info_iter)?;
+    let listing = load_token_listing(listing_account_info, program_id)?;
+    if !(listing.lister.eq(lister_info.key)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+    let token_account = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+    if !(token_account.owner.eq(lister_info.key)) || !(token_account.mint.eq(&listing.mint)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let listing_vault_account_info = next_account_info(account_info_iter)?;
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if state_info.is_paused(state::PAUSE_DELIST) {
+        return Err(NFTError::InstructionPaused.into());
+    }
+
+    release_listing(
+        &listing,
+        listing_account_info,
+        listing_vault_account_info,
+        token_account_info,
+        lister_info,
+        token_program_info,
+        program_id,
+    )?;
+
+    state_info.active_listings = state_info.active_listings.saturating_sub(1);
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_bid_with_token(
+    accounts: &[AccountInfo],
+    amount: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let bidder_info = next_account_info(account_info_iter)?;
+    if !bidder_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let bidder_quote_account_info = next_account_info(account_info_iter)?;
+
+    let mint_account_info = next_account_info(account_info_iter)?;
+    if !(mint_account_info.owner.eq(&spl_token::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let quote_mint_info = next_account_info(account_info_iter)?;
+    let payment_mint_account_info = next_account_info(account_info_iter)?;
+    let payment_mint = load_enabled_payment_mint(payment_mint_account_info, quote_mint_info.key, program_id)?;
+
+    let bid_account_info = next_account_info(account_info_iter)?;
+    let bid_vault_account_info = next_account_info(account_info_iter)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+    let rent = &Rent::get()?;
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if state_info.is_paused(state::PAUSE_BID) {
+        return Err(NFTError::InstructionPaused.into());
+    }
+    let taker_fee = Processor::calculate_fee(amount, state_info.taker_fee_bps)?;
+    let escrow_amount = amount
+        .checked_add(taker_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+
+    let (bid_pubkey, bid_nonce) = find_token_bid_address(program_id, mint_account_info.key, bidder_info.key);
+    if !(bid_account_info.key.eq(&bid_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !bid_account_info.data_is_empty() {
+        return Err(NFTError::BidAlreadyExists.into());
+    }
+    let create_bid_account_ix = system_instruction::create_account(
+        bidder_info.key,
+        &bid_pubkey,
+        rent.minimum_balance(TOKENBIDSIZE),
+        TOKENBIDSIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived token bid account");
+    invoke_signed(
+        &create_bid_account_ix,
+        &[
+            bidder_info.clone(),
+            bid_account_info.clone(),
+            system_program_info.clone(),
+            program_info.clone(),
+        ],
+        &[&[
+            mint_account_info.key.as_ref(),
+            bidder_info.key.as_ref(),
+            &b"Token"[..],
+            &b"Bid"[..],
+            &[bid_nonce]
+        ]],
+    )?;
+
+    let (bid_vault_pubkey, vault_nonce) = find_token_bid_vault_address(program_id, mint_account_info.key, bidder_info.key);
+    if !(bid_vault_account_info.key.eq(&bid_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    create_vault(
+        bidder_info,
+        bid_vault_account_info,
+        quote_mint_info,
+        bid_account_info,
+        system_program_info,
+        token_program_info,
+        rent_account_info,
+        &[
+            mint_account_info.key.as_ref(),
+            bidder_info.key.as_ref(),
+            &b"Token"[..],
+            &b"BidVault"[..],
+            &[vault_nonce]
+        ],
+    )?;
+
+    let transfer_token_ix = spl_token::instruction::transfer_checked(
+        &spl_token::id(),
+        bidder_quote_account_info.key,
+        quote_mint_info.key,
+        &bid_vault_pubkey,
+        bidder_info.key,
+        &[
+            bidder_info.key
+        ],
+        escrow_amount,
+        payment_mint.decimals
+    )?;
+    invoke(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            bidder_quote_account_info.clone(),
+            quote_mint_info.clone(),
+            bid_vault_account_info.clone(),
+            bidder_info.clone()
+        ],
+    )?;
+    if token_amount(bid_vault_account_info)? != escrow_amount {
+        return Err(NFTError::TransferShortfall.into());
+    }
+
+    TokenBid::pack(
+        TokenBid{
+            is_initialized: true,
+            bidder: *bidder_info.key,
+            mint: *mint_account_info.key,
+            quote_mint: *quote_mint_info.key,
+            amount,
+            taker_fee,
+        },
+        &mut bid_account_info.data.borrow_mut()
+    )?;
+
+    Ok(())
+}
+
+pub fn process_withdraw_token_bid(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let bidder_info = next_account_info(account_info_iter)?;
+    if !bidder_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let bidder_quote_account_info = next_account_info(account_info_iter)?;
+
+    let bid_account_info = next_account_info(account_info_iter)?;
+    let bid = load_token_bid(bid_account_info, program_id)?;
+    if !(bid.bidder.eq(bidder_info.key)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let bid_vault_account_info = next_account_info(account_info_iter)?;
+
+    let quote_mint_info = next_account_info(account_info_iter)?;
+    if !(quote_mint_info.key.eq(&bid.quote_mint)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let decimals = spl_token::state::Mint::unpack(&quote_mint_info.data.borrow())?.decimals;
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let vault_balance = spl_token::state::Account::unpack(&bid_vault_account_info.data.borrow())?.amount;
+    pay_from_bid_vault(
+        &bid,
+        bid_account_info,
+        bid_vault_account_info,
+        quote_mint_info,
+        token_program_info,
+        &[(bidder_quote_account_info, vault_balance)],
+        decimals,
+        program_id,
+    )?;
+    close_bid(&bid, bid_account_info, bid_vault_account_info, bidder_info, token_program_info, program_id)
+}
+
+/// Called by the lister: sells the listed NFT to a token bid in the same
+/// quote mint.
+pub fn process_accept_token_bid(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let lister_info = next_account_info(account_info_iter)?;
+    if !lister_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let lister_quote_account_info = next_account_info(account_info_iter)?;
+
+    let listing_account_info = next_account_info(account_info_iter)?;
+    let listing = load_token_listing(listing_account_info, program_id)?;
+    if !(listing.lister.eq(lister_info.key)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+    let listing_vault_account_info = next_account_info(account_info_iter)?;
+
+    let bidder_info = next_account_info(account_info_iter)?;
+    let bidder_token_account_info = next_account_info(account_info_iter)?;
+    let bid_account_info = next_account_info(account_info_iter)?;
+    let bid = load_token_bid(bid_account_info, program_id)?;
+    if !(bid.bidder.eq(bidder_info.key)) || !(bid.mint.eq(&listing.mint)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !(bid.quote_mint.eq(&listing.quote_mint)) {
+        return Err(NFTError::QuoteMintMismatch.into());
+    }
+    let bidder_token_account = spl_token::state::Account::unpack(&bidder_token_account_info.data.borrow())?;
+    if !(bidder_token_account.owner.eq(bidder_info.key)) || !(bidder_token_account.mint.eq(&listing.mint)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let bid_vault_account_info = next_account_info(account_info_iter)?;
+
+    let quote_mint_info = next_account_info(account_info_iter)?;
+    if !(quote_mint_info.key.eq(&bid.quote_mint)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let decimals = spl_token::state::Mint::unpack(&quote_mint_info.data.borrow())?.decimals;
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if state_info.is_paused(state::PAUSE_SALE) {
+        return Err(NFTError::InstructionPaused.into());
+    }
+    attestation::check_not_required(&state_info)?;
+
+    let fee_recipient_quote_account_info = next_account_info(account_info_iter)?;
+    let fee_recipient_quote_account = spl_token::state::Account::unpack(&fee_recipient_quote_account_info.data.borrow())?;
+    if !(fee_recipient_quote_account.owner.eq(&state_info.fee_recipient)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Anything sent to the vault beyond the escrowed bid goes to the seller.
+    let vault_balance = spl_token::state::Account::unpack(&bid_vault_account_info.data.borrow())?.amount;
+    let maker_fee = Processor::calculate_fee(bid.amount, state_info.maker_fee_bps)?;
+    let platform_fee = maker_fee
+        .checked_add(bid.taker_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let seller_proceeds = vault_balance
+        .checked_sub(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    pay_from_bid_vault(
+        &bid,
+        bid_account_info,
+        bid_vault_account_info,
+        quote_mint_info,
+        token_program_info,
+        &[
+            (lister_quote_account_info, seller_proceeds),
+            (fee_recipient_quote_account_info, platform_fee),
+        ],
+        decimals,
+        program_id,
+    )?;
+    close_bid(&bid, bid_account_info, bid_vault_account_info, bidder_info, token_program_info, program_id)?;
+
+    release_listing(
+        &listing,
+        listing_account_info,
+        listing_vault_account_info,
+        bidder_token_account_info,
+        lister_info,
+        token_program_info,
+        program_id,
+    )?;
+
+    state_info.sale_count = state_info.sale_count
+        .checked_add(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.active_listings = state_info.active_listings.saturating_sub(1);
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn load_enabled_payment_mint(
+    payment_mint_account_info: &AccountInfo,
+    quote_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<PaymentMint, ProgramError> {
+    let (payment_mint_pubkey, _) = find_payment_mint_address(program_id, quote_mint);
+    if !(payment_mint_account_info.key.eq(&payment_mint_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !(payment_mint_account_info.owner.eq(program_id)) {
+        return Err(NFTError::PaymentMintNotAllowed.into());
+    }
+    let payment_mint = PaymentMint::unpack(&payment_mint_account_info.data.borrow())?;
+    if !payment_mint.enabled {
+        return Err(NFTError::PaymentMintNotAllowed.into());
+    }
+    Ok(payment_mint)
+}
+
+fn load_token_listing(listing_account_info: &AccountInfo, program_id: &Pubkey) -> Result<TokenListing, ProgramError> {
+    Processor::assert_owned_by(listing_account_info, program_id)?;
+    let listing = TokenListing::unpack(&listing_account_info.data.borrow())?;
+    let (listing_pubkey, _) = find_token_listing_address(program_id, &listing.mint, &listing.lister);
+    if !(listing_account_info.key.eq(&listing_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(listing)
+}
+
+fn load_token_bid(bid_account_info: &AccountInfo, program_id: &Pubkey) -> Result<TokenBid, ProgramError> {
+    Processor::assert_owned_by(bid_account_info, program_id)?;
+    let bid = TokenBid::unpack(&bid_account_info.data.borrow())?;
+    let (bid_pubkey, _) = find_token_bid_address(program_id, &bid.mint, &bid.bidder);
+    if !(bid_account_info.key.eq(&bid_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(bid)
+}
+
+/// Creates a token account for `vault_mint_info` at the PDA signed for by
+/// `vault_seeds`, owned by the escrow state `owner_info`.
+#[allow(clippy::too_many_arguments)]
+fn create_vault<'a>(
+    payer_info: &AccountInfo<'a>,
+    vault_account_info: &AccountInfo<'a>,
+    vault_mint_info: &AccountInfo<'a>,
+    owner_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    rent_account_info: &AccountInfo<'a>,
+    vault_seeds: &[&[u8]],
+) -> ProgramResult {
+    let rent = &Rent::get()?;
+    let create_vault_account_ix = system_instruction::create_account(
+        payer_info.key,
+        vault_account_info.key,
+        rent.minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN as u64,
+        &spl_token::id());
+    invoke_signed(
+        &create_vault_account_ix,
+        &[
+            payer_info.clone(),
+            vault_account_info.clone(),
+            system_program_info.clone(),
+            token_program_info.clone(),
+        ],
+        &[vault_seeds],
+    )?;
+
+    let initialize_vault_account_ix = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        vault_account_info.key,
+        vault_mint_info.key,
+        owner_info.key
+    )?;
+    invoke(
+        &initialize_vault_account_ix,
+        &[
+            token_program_info.clone(),
+            vault_account_info.clone(),
+            vault_mint_info.clone(),
+            owner_info.clone(),
+            rent_account_info.clone()
+        ],
+    )
+}
+
+/// Pays each `(token account, amount)` out of the bid vault.
+#[allow(clippy::too_many_arguments)]
+fn pay_from_bid_vault<'a>(
+    bid: &TokenBid,
+    bid_account_info: &AccountInfo<'a>,
+    bid_vault_account_info: &AccountInfo<'a>,
+    quote_mint_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    payouts: &[(&AccountInfo<'a>, u64)],
+    decimals: u8,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (bid_vault_pubkey, _) = find_token_bid_vault_address(program_id, &bid.mint, &bid.bidder);
+    if !(bid_vault_account_info.key.eq(&bid_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let (bid_pubkey, bid_nonce) = find_token_bid_address(program_id, &bid.mint, &bid.bidder);
+    let bid_seeds: &[&[u8]] = &[
+        bid.mint.as_ref(),
+        bid.bidder.as_ref(),
+        &b"Token"[..],
+        &b"Bid"[..],
+        &[bid_nonce]
+    ];
+    for (recipient_info, amount) in payouts {
+        if *amount == 0 {
+            continue;
+        }
+        let balance_before = token_amount(recipient_info)?;
+        let transfer_token_ix = spl_token::instruction::transfer_checked(
+            &spl_token::id(),
+            &bid_vault_pubkey,
+            quote_mint_info.key,
+            recipient_info.key,
+            &bid_pubkey,
+            &[&bid_pubkey],
+            *amount,
+            decimals
+        )?;
+        invoke_signed(
+            &transfer_token_ix,
+            &[
+                token_program_info.clone(),
+                bid_vault_account_info.clone(),
+                quote_mint_info.clone(),
+                (*recipient_info).clone(),
+                bid_account_info.clone()
+            ],
+            &[bid_seeds],
+        )?;
+        let received = token_amount(recipient_info)?.saturating_sub(balance_before);
+        if received != *amount {
+            return Err(NFTError::TransferShortfall.into());
+        }
+    }
+    Ok(())
+}
+
+/// The balance of a token account of the quote mint.
+fn token_amount(token_account_info: &AccountInfo) -> Result<u64, ProgramError> {
+    Ok(spl_token::state::Account::unpack(&token_account_info.data.borrow())?.amount)
+}
+
+/// Closes the emptied bid vault and the bid, returning their rent to the
+/// bidder.
+fn close_bid<'a>(
+    bid: &TokenBid,
+    bid_account_info: &AccountInfo<'a>,
+    bid_vault_account_info: &AccountInfo<'a>,
+    bidder_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (bid_pubkey, bid_nonce) = find_token_bid_address(program_id, &bid.mint, &bid.bidder);
+    let close_ix = spl_token::instruction::close_account(
+        &spl_token::id(),
+        bid_vault_account_info.key,
+        bidder_info.key,
+        &bid_pubkey,
+        &[&bid_pubkey]
+    )?;
+    invoke_signed(
+        &close_ix,
+        &[
+            token_program_info.clone(),
+            bid_vault_account_info.clone(),
+            bidder_info.clone(),
+            bid_account_info.clone(),
+        ],
+        &[&[
+            bid.mint.as_ref(),
+            bid.bidder.as_ref(),
+            &b"Token"[..],
+            &b"Bid"[..],
+            &[bid_nonce]
+        ]],
+    )?;
+
+    Processor::close_account(bid_account_info, bidder_info, program_id)
+}
+
+/// Sends the escrowed NFT to `token_account_info` and closes the vault and
+/// the listing, returning their rent to the lister.
+fn release_listing<'a>(
+    listing: &TokenListing,
+    listing_account_info: &AccountInfo<'a>,
+    listing_vault_account_info: &AccountInfo<'a>,
+    token_account_info: &AccountInfo<'a>,
+    lister_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (listing_vault_pubkey, _) = find_token_listing_vault_address(program_id, &listing.mint, &listing.lister);
+    if !(listing_vault_account_info.key.eq(&listing_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (listing_pubkey, listing_nonce) = find_token_listing_address(program_id, &listing.mint, &listing.lister);
+    let listing_seeds: &[&[u8]] = &[
+        listing.mint.as_ref(),
+        listing.lister.as_ref(),
+        &b"Token"[..],
+        &b"List"[..],
+        &[listing_nonce]
+    ];
+
+    let transfer_token_ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &listing_vault_pubkey,
+        token_account_info.key,
+        &listing_pubkey,
+        &[&listing_pubkey],
+        1
+    )?;
+    invoke_signed(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            listing_vault_account_info.clone(),
+            token_account_info.clone(),
+            listing_account_info.clone()
+        ],
+        &[listing_seeds],
+    )?;
+
+    let close_ix = spl_token::instruction::close_account(
+        &spl_token::id(),
+        &listing_vault_pubkey,
+        lister_info.key,
+        &listing_pubkey,
+        &[&listing_pubkey]
+    )?;
+    invoke_signed(
+        &close_ix,
+        &[
+            token_program_info.clone(),
+            listing_vault_account_info.clone(),
+            lister_info.clone(),
+            listing_account_info.clone(),
+        ],
+        &[listing_seeds],
+    )?;
+
+    Processor::close_account(listing_account_info, lister_info, program_id)
+}
@@ -0,0 +1,853 @@
+//! Two-sided NFT liquidity pools.
+//!
+//! An LP deposits NFTs from one verified collection plus SOL into a pool
+//! PDA. Traders buy NFTs from the pool or sell NFTs into it at the price
+//! set by the pool's bonding curve: every buy moves the spot price one step
+//! up and every sell one step down. The pool keeps `fee_bps` of each trade
+//! for the LP; the platform takes its taker fee on top.
+//!
+//! Each deposited NFT sits in its own vault token account, derived from the
+//! pool and the mint and owned by the pool PDA. Whoever opens a vault pays
+//! its rent and whoever empties it gets the rent back.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{attestation, error::NFTError, metadata, processor::Processor, state::{self, Discriminator, PlatformState}};
+
+pub const POOLSIZE: usize = 114usize;
+
+pub fn find_pool_address(program_id: &Pubkey, owner: &Pubkey, collection: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[owner.as_ref(), collection.as_ref(), b"Pool", b"State"], program_id)
+}
+
+pub fn find_pool_vault_address(program_id: &Pubkey, pool: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[pool.as_ref(), mint.as_ref(), b"Pool", b"Vault"], program_id)
+}
+
+/// How the spot price moves by `delta` after each trade.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Curve {
+    /// Adds or subtracts `delta` lamports.
+    Linear,
+    /// Multiplies or divides by `1 + delta / MAX_BPS`.
+    Exponential,
+}
+
+impl Curve {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Curve::Linear),
+            1 => Some(Curve::Exponential),
+            _ => None,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pool {
+    pub is_initialized: bool,
+    pub owner: Pubkey,
+    pub collection: Pubkey,
+    pub curve: Curve,
+    pub spot_price: u64,
+    pub delta: u64,
+    pub fee_bps: u64,
+    pub nft_count: u64,
+    pub sol_balance: u64,
+}
+
+impl Pool {
+    /// Price of the next NFT bought from the pool, before fees.
+    pub fn buy_price(&self) -> Result<u64, ProgramError> {
+        let price = match self.curve {
+            Curve::Linear => (self.spot_price as u128).checked_add(self.delta as u128),
+            Curve::Exponential => (self.spot_price as u128)
+                .checked_mul(state::MAX_BPS as u128 + self.delta as u128)
+                .map(|v| v / state::MAX_BPS as u128),
+        };
+        price
+            .filter(|price| *price <= u64::MAX as u128)
+            .map(|price| price as u64)
+            .ok_or_else(|| NFTError::NumericalOverflow.into())
+    }
+
+    /// Price the pool pays for the next NFT sold into it, before fees.
+    pub fn sell_price(&self) -> u64 {
+        self.spot_price
+    }
+
+    fn next_sell_spot(&self) -> u64 {
+        match self.curve {
+            Curve::Linear => self.spot_price.saturating_sub(self.delta),
+            Curve::Exponential => {
+                ((self.spot_price as u128) * (state::MAX_BPS as u128)
+                    / (state::MAX_BPS as u128 + self.delta as u128)) as u64
+            }
+        }
+    }
+}
+
+impl Sealed for Pool{}
+
+impl Discriminator for Pool{
+    const DISCRIMINATOR: [u8; 8] = *b"pool\0\0\0\0";
+}
+
+impl IsInitialized for Pool{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Pool {
+    const LEN: usize = POOLSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Pool::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, Pool::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            owner,
+            collection,
+            curve,
+            spot_price,
+            delta,
+            fee_bps,
+            nft_count,
+            sol_balance,
+        ) = array_refs![src, 8, 1, 32, 32, 1, 8, 8, 8, 8, 8];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let curve = Curve::from_u8(curve[0]).ok_or(ProgramError::InvalidAccountData)?;
+        Ok(Pool{
+            is_initialized,
+            owner: Pubkey::new_from_array(*owner),
+            collection: Pubkey::new_from_array(*collection),
+            curve,
+            spot_price: u64::from_be_bytes(*spot_price),
+            delta: u64::from_be_bytes(*delta),
+            fee_bps: u64::from_be_bytes(*fee_bps),
+            nft_count: u64::from_be_bytes(*nft_count),
+            sol_balance: u64::from_be_bytes(*sol_balance),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Pool::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            owner_dst,
+            collection_dst,
+            curve_dst,
+            spot_price_dst,
+            delta_dst,
+            fee_bps_dst,
+            nft_count_dst,
+            sol_balance_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 32, 1, 8, 8, 8, 8, 8];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+
+        let Pool {
+            is_initialized,
+            owner,
+            collection,
+            curve,
+            spot_price,
+            delta,
+            fee_bps,
+            nft_count,
+            sol_balance,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        owner_dst.copy_from_slice(owner.as_ref());
+        collection_dst.copy_from_slice(collection.as_ref());
+        curve_dst[0] = *curve as u8;
+        *spot_price_dst = spot_price.to_be_bytes();
+        *delta_dst = delta.to_be_bytes();
+        *fee_bps_dst = fee_bps.to_be_bytes();
+        *nft_count_dst = nft_count.to_be_bytes();
+        *sol_balance_dst = sol_balance.to_be_bytes();
+    }
+}
+
+pub fn process_create_pool(
+    accounts: &[AccountInfo],
+    collection: Pubkey,
+    curve: Curve,
+    spot_price: u64,
+    delta: u64,
+    fee_bps: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if fee_bps > state::MAX_BPS {
+        return Err(NFTError::InvalidPlatformFee.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_account_info = next_account_info(account_info_iter)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (pool_pubkey, nonce) = find_pool_address(program_id, owner_info.key, &collection);
+    if !(pool_account_info.key.eq(&pool_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Processor::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::get()?;
+    let create_pool_account_ix = system_instruction::create_account(
+        owner_info.key,
+        &pool_pubkey,
+        rent.minimum_balance(POOLSIZE),
+        POOLSIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived pool account");
+    invoke_signed(
+        &create_pool_account_ix,
+        &[
+            owner_info.clone(),
+            pool_account_info.clone(),
+            system_program_info.clone(),
+            program_info.clone(),
+        ],
+        &[&[
+            owner_info.key.as_ref(),
+            collection.as_ref(),
+            &b"Pool"[..],
+            &b"State"[..],
+            &[nonce]
+        ]],
+    )?;
+
+    Pool::pack(
+        Pool{
+            is_initialized: true,
+            owner: *owner_info.key,
+            collection,
+            curve,
+            spot_price,
+            delta,
+            fee_bps,
+            nft_count: 0,
+            sol_balance: 0,
+        },
+        &mut pool_account_info.data.borrow_mut()
+    )?;
+
+    Ok(())
+}
+
+pub fn process_deposit_nft(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_account_info = next_account_info(account_info_iter)?;
+    let mut pool = load_pool(pool_account_info, program_id)?;
+    if !(pool.owner.eq(owner_info.key)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let owner_token_account_info = next_account_info(account_info_iter)?;
+    let mint_account_info = next_account_info(account_info_iter)?;
+    let pool_vault_account_info = next_account_info(account_info_iter)?;
+    let metadata_account_info = next_account_info(account_info_iter)?;
+    check_collection(&pool, metadata_account_info, mint_account_info)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+
+    deposit_into_vault(
+        owner_info,
+        owner_token_account_info,
+        mint_account_info,
+        pool_account_info,
+        pool_vault_account_info,
+        program_info,
+        token_program_info,
+        system_program_info,
+        rent_account_info,
+        program_id,
+    )?;
+
+    pool.nft_count = pool.nft_count
+        .checked_add(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    Pool::pack(pool, &mut pool_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_deposit_sol(
+    accounts: &[AccountInfo],
+    amount: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_account_info = next_account_info(account_info_iter)?;
+    let mut pool = load_pool(pool_account_info, program_id)?;
+    if !(pool.owner.eq(owner_info.key)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    invoke(
+        &system_instruction::transfer(owner_info.key, pool_account_info.key, amount),
+        &[
+            owner_info.clone(),
+            pool_account_info.clone(),
+            system_program_info.clone(),
+        ],
+    )?;
+
+    pool.sol_balance = pool.sol_balance
+        .checked_add(amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    Pool::pack(pool, &mut pool_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_withdraw_nft(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_account_info = next_account_info(account_info_iter)?;
+    let mut pool = load_pool(pool_account_info, program_id)?;
+    if !(pool.owner.eq(owner_info.key)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let owner_token_account_info = next_account_info(account_info_iter)?;
+    let mint_account_info = next_account_info(account_info_iter)?;
+    let pool_vault_account_info = next_account_info(account_info_iter)?;
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    release_from_vault(
+        &pool,
+        pool_account_info,
+        pool_vault_account_info,
+        mint_account_info,
+        owner_token_account_info,
+        owner_info,
+        token_program_info,
+        program_id,
+    )?;
+
+    pool.nft_count = pool.nft_count
+        .checked_sub(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    Pool::pack(pool, &mut pool_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_withdraw_sol(
+    accounts: &[AccountInfo],
+    amount: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_account_info = next_account_info(account_info_iter)?;
+    let mut pool = load_pool(pool_account_info, program_id)?;
+    if !(pool.owner.eq(owner_info.key)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    pool.sol_balance = pool.sol_balance
+        .checked_sub(amount)
+        .ok_or(NFTError::InsufficientPoolFunds)?;
+    Pool::pack(pool, &mut pool_account_info.data.borrow_mut())?;
+
+    let pool_lamports = pool_account_info.lamports()
+        .checked_sub(amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let owner_lamports = owner_info.lamports()
+        .checked_add(amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    **pool_account_info.try_borrow_mut_lamports()? = pool_lamports;
+    **owner_info.try_borrow_mut_lamports()? = owner_lamports;
+
+    Ok(())
+}
+
+pub fn process_buy_from_pool(
+    accounts: &[AccountInfo],
+    max_price: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let buyer_info = next_account_info(account_info_iter)?;
+    if !buyer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_account_info = next_account_info(account_info_iter)?;
+    let mut pool = load_pool(pool_account_info, program_id)?;
+
+    let buyer_token_account_info = next_account_info(account_info_iter)?;
+    let mint_account_info = next_account_info(account_info_iter)?;
+    let pool_vault_account_info = next_account_info(account_info_iter)?;
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let mut state_info = load_platform_state(state_account_info, program_id)?;
+    attestation::check_not_required(&state_info)?;
+
+    let fee_recipient_info = next_account_info(account_info_iter)?;
+    if !state_info.fee_recipient.eq(fee_recipient_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let price = pool.buy_price()?;
+    let pool_fee = Processor::calculate_fee(price, pool.fee_bps)?;
+    let platform_fee = Processor::calculate_fee(price, state_info.taker_fee_bps)?;
+    let pool_proceeds = price
+        .checked_add(pool_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let total_cost = pool_proceeds
+        .checked_add(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    if total_cost > max_price {
+        return Err(NFTError::PoolPriceSlippage.into());
+    }
+
+    let payouts = vec![(pool_account_info, pool_proceeds), (fee_recipient_info, platform_fee)];
+    for (recipient_info, lamports) in payouts {
+        if lamports == 0 {
+            continue;
+        }
+        invoke(
+            &system_instruction::transfer(buyer_info.key, recipient_info.key, lamports),
+            &[
+                buyer_info.clone(),
+                recipient_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
+    release_from_vault(
+        &pool,
+        pool_account_info,
+        pool_vault_account_info,
+        mint_account_info,
+        buyer_token_account_info,
+        buyer_info,
+        token_program_info,
+        program_id,
+    )?;
+
+    pool.spot_price = price;
+    pool.nft_count = pool.nft_count
+        .checked_sub(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    pool.sol_balance = pool.sol_balance
+        .checked_add(pool_proceeds)
+        .ok_or(NFTError::NumericalOverflow)?;
+    Pool::pack(pool, &mut pool_account_info.data.borrow_mut())?;
+
+    record_pool_trade(&mut state_info, price, platform_fee)?;
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_sell_to_pool(
+    accounts: &[AccountInfo],
+    min_price: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let seller_info = next_account_info(account_info_iter)?;
+    if !seller_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let pool_account_info = next_account_info(account_info_iter)?;
+    let mut pool = load_pool(pool_account_info, program_id)?;
+
+    let seller_token_account_info = next_account_info(account_info_iter)?;
+    let mint_account_info = next_account_info(account_info_iter)?;
+    let pool_vault_account_info = next_account_info(account_info_iter)?;
+    let metadata_account_info = next_account_info(account_info_iter)?;
+    check_collection(&pool, metadata_account_info, mint_account_info)?;
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let mut state_info = load_platform_state(state_account_info, program_id)?;
+    attestation::check_not_required(&state_info)?;
+
+    let fee_recipient_info = next_account_info(account_info_iter)?;
+    if !state_info.fee_recipient.eq(fee_recipient_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+
+    let price = pool.sell_price();
+    let pool_fee = Processor::calculate_fee(price, pool.fee_bps)?;
+    let platform_fee = Processor::calculate_fee(price, state_info.taker_fee_bps)?;
+    let pool_payment = price
+        .checked_sub(pool_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let seller_proceeds = pool_payment
+        .checked_sub(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    if seller_proceeds < min_price {
+        return Err(NFTError::PoolPriceSlippage.into());
+    }
+
+    deposit_into_vault(
+        seller_info,
+        seller_token_account_info,
+        mint_account_info,
+        pool_account_info,
+        pool_vault_account_info,
+        program_info,
+        token_program_info,
+        system_program_info,
+        rent_account_info,
+        program_id,
+    )?;
+
+    pool.sol_balance = pool.sol_balance
+        .checked_sub(pool_payment)
+        .ok_or(NFTError::InsufficientPoolFunds)?;
+    pool.spot_price = pool.next_sell_spot();
+    pool.nft_count = pool.nft_count
+        .checked_add(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    Pool::pack(pool, &mut pool_account_info.data.borrow_mut())?;
+
+    let pool_lamports = pool_account_info.lamports()
+        .checked_sub(pool_payment)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let seller_lamports = seller_info.lamports()
+        .checked_add(seller_proceeds)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let fee_recipient_lamports = fee_recipient_info.lamports()
+        .checked_add(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    **pool_account_info.try_borrow_mut_lamports()? = pool_lamports;
+    **seller_info.try_borrow_mut_lamports()? = seller_lamports;
+    **fee_recipient_info.try_borrow_mut_lamports()? = fee_recipient_lamports;
+
+    record_pool_trade(&mut state_info, price, platform_fee)?;
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn load_pool(pool_account_info: &AccountInfo, program_id: &Pubkey) -> Result<Pool, ProgramError> {
+    Processor::assert_owned_by(pool_account_info, program_id)?;
+    let pool = Pool::unpack(&pool_account_info.data.borrow())?;
+    let (pool_pubkey, _) = find_pool_address(program_id, &pool.owner, &pool.collection);
+    if !(pool_account_info.key.eq(&pool_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(pool)
+}
+
+fn load_platform_state(state_account_info: &AccountInfo, program_id: &Pubkey) -> Result<PlatformState, ProgramError> {
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(state_info)
+}
+
+fn check_collection(pool: &Pool, metadata_account_info: &AccountInfo, mint_account_info: &AccountInfo) -> ProgramResult {
+    if !(mint_account_info.owner.eq(&spl_token::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let metadata = metadata::load_metadata(metadata_account_info, mint_account_info.key)?;
+    let collection = metadata.and_then(|metadata| metadata.verified_collection());
+    if collection != Some(pool.collection) {
+        return Err(NFTError::CollectionNotAllowed.into());
+    }
+    Ok(())
+}
+
+fn record_pool_trade(state_info: &mut PlatformState, price: u64, platform_fee: u64) -> ProgramResult {
+    state_info.sale_count = state_info.sale_count
+        .checked_add(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.total_volume = state_info.total_volume
+        .checked_add(price)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.total_fees = state_info.total_fees
+        .checked_add(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    Ok(())
+}
+
+/// Creates the pool's vault for the mint, paid for by `depositor`, and
+/// moves the NFT in from `depositor`'s token account.
+#[allow(clippy::too_many_arguments)]
+fn deposit_into_vault<'a>(
+    depositor_info: &AccountInfo<'a>,
+    token_account_info: &AccountInfo<'a>,
+    mint_account_info: &AccountInfo<'a>,
+    pool_account_info: &AccountInfo<'a>,
+    pool_vault_account_info: &AccountInfo<'a>,
+    program_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    rent_account_info: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let token_account = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+    if !(token_account.owner.eq(depositor_info.key)) || !(token_account.mint.eq(mint_account_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (pool_vault_pubkey, nonce) = find_pool_vault_address(program_id, pool_account_info.key, mint_account_info.key);
+    if !(pool_vault_account_info.key.eq(&pool_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let rent = &Rent::get()?;
+    let create_vault_account_ix = system_instruction::create_account(
+        depositor_info.key,
+        &pool_vault_pubkey,
+        rent.minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN as u64,
+        &spl_token::id());
+    invoke_signed(
+        &create_vault_account_ix,
+        &[
+            depositor_info.clone(),
+            pool_vault_account_info.clone(),
+            system_program_info.clone(),
+            token_program_info.clone(),
+        ],
+        &[&[
+            pool_account_info.key.as_ref(),
+            mint_account_info.key.as_ref(),
+            &b"Pool"[..],
+            &b"Vault"[..],
+            &[nonce]
+        ]],
+    )?;
+
+    let initialize_vault_account_ix = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &pool_vault_pubkey,
+        mint_account_info.key,
+        pool_account_info.key
+    )?;
+    invoke(
+        &initialize_vault_account_ix,
+        &[
+            token_program_info.clone(),
+            pool_vault_account_info.clone(),
+            pool_account_info.clone(),
+            mint_account_info.clone(),
+            program_info.clone(),
+            rent_account_info.clone()
+        ],
+    )?;
+
+    let transfer_token_ix = spl_token::instruction::transfer_checked(
+        &spl_token::id(),
+        token_account_info.key,
+        mint_account_info.key,
+        &pool_vault_pubkey,
+        depositor_info.key,
+        &[
+            depositor_info.key
+        ],
+        1,
+        0
+    )?;
+    invoke(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            token_account_info.clone(),
+            mint_account_info.clone(),
+            pool_vault_account_info.clone(),
+            depositor_info.clone()
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Moves the NFT out of the pool's vault for the mint into
+/// `token_account_info` and closes the vault, returning its rent to
+/// `recipient_info`.
+#[allow(clippy::too_many_arguments)]
+fn release_from_vault<'a>(
+    pool: &Pool,
+    pool_account_info: &AccountInfo<'a>,
+    pool_vault_account_info: &AccountInfo<'a>,
+    mint_account_info: &AccountInfo<'a>,
+    token_account_info: &AccountInfo<'a>,
+    recipient_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (pool_vault_pubkey, _) = find_pool_vault_address(program_id, pool_account_info.key, mint_account_info.key);
+    if !(pool_vault_account_info.key.eq(&pool_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let (_, pool_nonce) = find_pool_address(program_id, &pool.owner, &pool.collection);
+    let pool_seeds: &[&[u8]] = &[
+        pool.owner.as_ref(),
+        pool.collection.as_ref(),
+        &b"Pool"[..],
+        &b"State"[..],
+        &[pool_nonce]
+    ];
+
+    let transfer_token_ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &pool_vault_pubkey,
+        token_account_info.key,
+        pool_account_info.key,
+        &[pool_account_info.key],
+        1
+    )?;
+    invoke_signed(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            pool_vault_account_info.clone(),
+            token_account_info.clone(),
+            pool_account_info.clone()
+        ],
+        &[pool_seeds],
+    )?;
+
+    let close_ix = spl_token::instruction::close_account(
+        &spl_token::id(),
+        &pool_vault_pubkey,
+        recipient_info.key,
+        pool_account_info.key,
+        &[pool_account_info.key]
+    )?;
+    invoke_signed(
+        &close_ix,
+        &[
+            token_program_info.clone(),
+            pool_vault_account_info.clone(),
+            recipient_info.clone(),
+            pool_account_info.clone(),
+        ],
+        &[pool_seeds],
+    )?;
+
+    Ok(())
+}
@@ -9,49 +9,65 @@ use solana_program::{
     system_instruction,
     system_program,
     rent::Rent,
-    sysvar::Sysvar,
+    clock::Clock,
+    sysvar::{self, Sysvar},
 };
 use spl_token;
 
-use crate::{error::NFTError, instruction, instruction::NFTInstruction, state, state::{BidEscrowState, ListEscrowState, PlatformState}};
+use crate::{attestation, core_listing, crowdfund, error::NFTError, fraction, index, instruction, instruction::{Bumps, NFTInstruction}, leaderboard, memo, metadata, oracle, order, otc, payment, pool, raffle, rental, rewards, session, staking, state, swap, token_2022, trait_offer, state::{BidEscrowState, BookEntry, BookSide, CollectionState, CollectionStats, FeeDiscount, ListEscrowState, OrderBook, ParamChange, PlatformState, UserStats}};
 
 pub struct Processor;
+
+/// Who a bid refund is sent by, and so what it has to wait for.
+#[derive(Clone, Copy, PartialEq)]
+enum Refunder {
+    /// `RefundUser` from the platform authority, at any time.
+    Authority,
+    /// `ThreadRefundBid` from the automation thread, once the bid is stale.
+    Thread,
+    /// `ExpireAndRefund` from anyone, once the bid has expired.
+    Crank,
+}
 impl Processor {
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
         let instruction = NFTInstruction::unpack(instruction_data)?;
 
         match instruction {
-            NFTInstruction::Initialize(instruction::Initialize{authority, platform_fee}) => {
+            NFTInstruction::Initialize(instruction::Initialize{authority, maker_bps, taker_bps}) => {
                 msg!("Instruction: Initialize Platform");
-                Self::process_init_platform(accounts, authority, platform_fee, program_id)
+                Self::process_init_platform(accounts, authority, maker_bps, taker_bps, program_id)
             }
             NFTInstruction::ChangeAuthority(instruction::ChangeAuthority{authority}) => {
                 msg!("Instruction: Change Authority");
                 Self::process_change_authority(accounts, authority, program_id)
             }
-            NFTInstruction::ChangeFee(instruction::ChangeFee{platform_fee}) => {
-                msg!("Instruction: Change Fee");
-                Self::process_change_fee(accounts, platform_fee, program_id)
+            NFTInstruction::ChangeFees(instruction::ChangeFees{maker_bps, taker_bps}) => {
+                msg!("Instruction: Change Fees");
+                Self::process_change_fees(accounts, maker_bps, taker_bps, program_id)
             }
-            NFTInstruction::List(instruction::List{amount}) => {
+            NFTInstruction::List(instruction::List{amount, auction, allowed_buyer, starts_at, royalty_bps_override, allowlist_root, charity, charity_bps, marketplace, price_usd, settlement_delay_slots, arbiter, visibility}) => {
                 msg!("Instruction: List");
-                Self::process_list(accounts, amount, program_id)
+                Self::process_list(accounts, amount, auction, allowed_buyer, starts_at, royalty_bps_override, allowlist_root, charity, charity_bps, marketplace, 0, price_usd, settlement_delay_slots, arbiter, visibility, program_id)
             }
-            NFTInstruction::DeList(instruction::DeList{}) => {
+            NFTInstruction::DeList(instruction::DeList{bumps}) => {
                 msg!("Instruction: Delist");
-                Self::process_delist(accounts, program_id)
+                Self::process_delist(accounts, &bumps, program_id)
             }
-            NFTInstruction::Bid(instruction::Bid{amount}) => {
+            NFTInstruction::Bid(instruction::Bid{amount, wrapped, match_accounts, tip_lamports, marketplace, proof}) => {
                 msg!("Instruction: Bid");
-                Self::process_bid(accounts, amount, program_id)
+                Self::process_bid(accounts, amount, wrapped, false, match_accounts, tip_lamports, marketplace, &proof, program_id)
             }
-            NFTInstruction::WithdrawBid(instruction::WithdrawBid{}) => {
+            NFTInstruction::WithdrawBid(instruction::WithdrawBid{bumps}) => {
                 msg!("Instruction: WithdrawBid");
-                Self::process_withdraw_bid(accounts, program_id)
+                Self::process_withdraw_bid(accounts, &bumps, program_id)
             }
-            NFTInstruction::AcceptBid(instruction::AcceptBid{}) => {
+            NFTInstruction::AcceptBid(instruction::AcceptBid{memo: client_memo, bumps, expected_amount}) => {
                 msg!("Instruction: AcceptBid");
-                Self::process_accept_bid(accounts, program_id)
+                Self::process_accept_bid(accounts, false, &client_memo, &bumps, expected_amount, program_id)
+            }
+            NFTInstruction::ExecuteSale(instruction::ExecuteSale{memo: client_memo, bumps, expected_amount}) => {
+                msg!("Instruction: ExecuteSale");
+                Self::process_accept_bid(accounts, true, &client_memo, &bumps, expected_amount, program_id)
             }
             NFTInstruction::WithdrawNFTOnSuccess(instruction::WithdrawNFTOnSuccess{}) => {
                 msg!("Instruction: WithdrawNFTOnSuccess");
@@ -59,7 +75,379 @@ impl Processor {
             }
             NFTInstruction::RefundUser(instruction::RefundUser{}) => {
                 msg!("Instruction: RefundUser");
-                Self::process_refund(accounts, program_id)
+                Self::process_refund(accounts, Refunder::Authority, program_id)
+            }
+            NFTInstruction::ChangeReferralFee(instruction::ChangeReferralFee{referral_fee_bps}) => {
+                msg!("Instruction: Change Referral Fee");
+                Self::process_change_referral_fee(accounts, referral_fee_bps, program_id)
+            }
+            NFTInstruction::SetCuratedMode(instruction::SetCuratedMode{curated}) => {
+                msg!("Instruction: Set Curated Mode");
+                Self::process_set_curated_mode(accounts, curated, program_id)
+            }
+            NFTInstruction::AllowCollection(instruction::AllowCollection{collection}) => {
+                msg!("Instruction: Allow Collection");
+                Self::process_set_collection_allowed(accounts, collection, true, program_id)
+            }
+            NFTInstruction::RevokeCollection(instruction::RevokeCollection{collection}) => {
+                msg!("Instruction: Revoke Collection");
+                Self::process_set_collection_allowed(accounts, collection, false, program_id)
+            }
+            NFTInstruction::CreateUserStats(instruction::CreateUserStats{}) => {
+                msg!("Instruction: Create User Stats");
+                Self::process_create_user_stats(accounts, program_id)
+            }
+            NFTInstruction::AuctionBid(instruction::AuctionBid{amount, proof}) => {
+                msg!("Instruction: Auction Bid");
+                Self::process_bid(accounts, amount, false, true, 0, 0, Pubkey::default(), &proof, program_id)
+            }
+            NFTInstruction::FillOrder(instruction::FillOrder{price, expiry, nonce}) => {
+                msg!("Instruction: Fill Order");
+                Self::process_fill_order(accounts, price, expiry, nonce, program_id)
+            }
+            NFTInstruction::CancelOrder(instruction::CancelOrder{nonce}) => {
+                msg!("Instruction: Cancel Order");
+                Self::process_cancel_order(accounts, nonce, program_id)
+            }
+            NFTInstruction::CreateOrderBook(instruction::CreateOrderBook{collection}) => {
+                msg!("Instruction: Create Order Book");
+                Self::process_create_order_book(accounts, collection, program_id)
+            }
+            NFTInstruction::UpdateOrderBook(instruction::UpdateOrderBook{side, maker}) => {
+                msg!("Instruction: Update Order Book");
+                Self::process_update_order_book(accounts, side, maker, program_id)
+            }
+            NFTInstruction::CreatePool(instruction::CreatePool{collection, curve, spot_price, delta, fee_bps}) => {
+                msg!("Instruction: Create Pool");
+                pool::process_create_pool(accounts, collection, curve, spot_price, delta, fee_bps, program_id)
+            }
+            NFTInstruction::DepositPoolNFT(instruction::DepositPoolNFT{}) => {
+                msg!("Instruction: Deposit Pool NFT");
+                pool::process_deposit_nft(accounts, program_id)
+            }
+            NFTInstruction::DepositPoolSol(instruction::DepositPoolSol{amount}) => {
+                msg!("Instruction: Deposit Pool Sol");
+                pool::process_deposit_sol(accounts, amount, program_id)
+            }
+            NFTInstruction::WithdrawPoolNFT(instruction::WithdrawPoolNFT{}) => {
+                msg!("Instruction: Withdraw Pool NFT");
+                pool::process_withdraw_nft(accounts, program_id)
+            }
+            NFTInstruction::WithdrawPoolSol(instruction::WithdrawPoolSol{amount}) => {
+                msg!("Instruction: Withdraw Pool Sol");
+                pool::process_withdraw_sol(accounts, amount, program_id)
+            }
+            NFTInstruction::BuyFromPool(instruction::BuyFromPool{max_price}) => {
+                msg!("Instruction: Buy From Pool");
+                pool::process_buy_from_pool(accounts, max_price, program_id)
+            }
+            NFTInstruction::SellToPool(instruction::SellToPool{min_price}) => {
+                msg!("Instruction: Sell To Pool");
+                pool::process_sell_to_pool(accounts, min_price, program_id)
+            }
+            NFTInstruction::InitStakePool(instruction::InitStakePool{}) => {
+                msg!("Instruction: Init Stake Pool");
+                staking::process_init_stake_pool(accounts, program_id)
+            }
+            NFTInstruction::Stake(instruction::Stake{amount}) => {
+                msg!("Instruction: Stake");
+                staking::process_stake(accounts, amount, program_id)
+            }
+            NFTInstruction::Unstake(instruction::Unstake{amount}) => {
+                msg!("Instruction: Unstake");
+                staking::process_unstake(accounts, amount, program_id)
+            }
+            NFTInstruction::ClaimRewards(instruction::ClaimRewards{}) => {
+                msg!("Instruction: Claim Rewards");
+                staking::process_claim_rewards(accounts, program_id)
+            }
+            NFTInstruction::FundStakingRewards(instruction::FundStakingRewards{amount}) => {
+                msg!("Instruction: Fund Staking Rewards");
+                staking::process_fund_staking_rewards(accounts, amount, program_id)
+            }
+            NFTInstruction::CreateRaffle(instruction::CreateRaffle{ticket_price, total_tickets, end_time}) => {
+                msg!("Instruction: Create Raffle");
+                raffle::process_create_raffle(accounts, ticket_price, total_tickets, end_time, program_id)
+            }
+            NFTInstruction::BuyRaffleTicket(instruction::BuyRaffleTicket{}) => {
+                msg!("Instruction: Buy Raffle Ticket");
+                raffle::process_buy_raffle_ticket(accounts, program_id)
+            }
+            NFTInstruction::DrawRaffle(instruction::DrawRaffle{}) => {
+                msg!("Instruction: Draw Raffle");
+                raffle::process_draw_raffle(accounts, program_id)
+            }
+            NFTInstruction::ClaimRaffle(instruction::ClaimRaffle{}) => {
+                msg!("Instruction: Claim Raffle");
+                raffle::process_claim_raffle(accounts, program_id)
+            }
+            NFTInstruction::Fractionalize(instruction::Fractionalize{share_supply, buyout_price}) => {
+                msg!("Instruction: Fractionalize");
+                fraction::process_fractionalize(accounts, share_supply, buyout_price, program_id)
+            }
+            NFTInstruction::Buyout(instruction::Buyout{}) => {
+                msg!("Instruction: Buyout");
+                fraction::process_buyout(accounts, program_id)
+            }
+            NFTInstruction::RedeemShares(instruction::RedeemShares{amount}) => {
+                msg!("Instruction: Redeem Shares");
+                fraction::process_redeem_shares(accounts, amount, program_id)
+            }
+            NFTInstruction::RedeemNFT(instruction::RedeemNFT{}) => {
+                msg!("Instruction: Redeem NFT");
+                fraction::process_redeem_nft(accounts, program_id)
+            }
+            NFTInstruction::ListForRent(instruction::ListForRent{price, duration_slots}) => {
+                msg!("Instruction: List For Rent");
+                rental::process_list_for_rent(accounts, price, duration_slots, program_id)
+            }
+            NFTInstruction::Rent(instruction::Rent{}) => {
+                msg!("Instruction: Rent");
+                rental::process_rent(accounts, program_id)
+            }
+            NFTInstruction::ReclaimRental(instruction::ReclaimRental{}) => {
+                msg!("Instruction: Reclaim Rental");
+                rental::process_reclaim_rental(accounts, program_id)
+            }
+            NFTInstruction::SetFeeDiscount(instruction::SetFeeDiscount{discount_mint, threshold, discount_bps}) => {
+                msg!("Instruction: Set Fee Discount");
+                Self::process_set_fee_discount(accounts, discount_mint, threshold, discount_bps, program_id)
+            }
+            NFTInstruction::CreateOtc(instruction::CreateOtc{price}) => {
+                msg!("Instruction: Create OTC");
+                otc::process_create_otc(accounts, price, program_id)
+            }
+            NFTInstruction::AcceptOtc(instruction::AcceptOtc{}) => {
+                msg!("Instruction: Accept OTC");
+                otc::process_accept_otc(accounts, program_id)
+            }
+            NFTInstruction::CancelOtc(instruction::CancelOtc{}) => {
+                msg!("Instruction: Cancel OTC");
+                otc::process_cancel_otc(accounts, program_id)
+            }
+            NFTInstruction::ListMany(instruction::ListMany{amounts}) => {
+                msg!("Instruction: ListMany");
+                Self::process_list_many(accounts, amounts, program_id)
+            }
+            NFTInstruction::DelistMany(instruction::DelistMany{}) => {
+                msg!("Instruction: DelistMany");
+                Self::process_delist_many(accounts, program_id)
+            }
+            NFTInstruction::BatchExecuteSale(instruction::BatchExecuteSale{account_counts}) => {
+                msg!("Instruction: BatchExecuteSale");
+                Self::process_batch_execute_sale(accounts, account_counts, program_id)
+            }
+            NFTInstruction::ChangeFeeRecipient(instruction::ChangeFeeRecipient{fee_recipient}) => {
+                msg!("Instruction: Change Fee Recipient");
+                Self::process_change_fee_recipient(accounts, fee_recipient, program_id)
+            }
+            NFTInstruction::SetStrictMode(instruction::SetStrictMode{strict}) => {
+                msg!("Instruction: Set Strict Mode");
+                Self::process_set_strict_mode(accounts, strict, program_id)
+            }
+            NFTInstruction::SetMinimums(instruction::SetMinimums{min_listing_lamports, min_bid_lamports}) => {
+                msg!("Instruction: Set Minimums");
+                Self::process_set_minimums(accounts, min_listing_lamports, min_bid_lamports, program_id)
+            }
+            NFTInstruction::ScheduleParamChange(instruction::ScheduleParamChange{change, execute_slot}) => {
+                msg!("Instruction: Schedule Param Change");
+                Self::process_schedule_param_change(accounts, change, execute_slot, program_id)
+            }
+            NFTInstruction::ExecuteParamChange(instruction::ExecuteParamChange{}) => {
+                msg!("Instruction: Execute Param Change");
+                Self::process_execute_param_change(accounts, program_id)
+            }
+            NFTInstruction::CreateCollectionStats(instruction::CreateCollectionStats{collection}) => {
+                msg!("Instruction: CreateCollectionStats");
+                Self::process_create_collection_stats(accounts, collection, program_id)
+            }
+            NFTInstruction::CreateIndex(instruction::CreateIndex{kind, key}) => {
+                msg!("Instruction: CreateIndex");
+                index::process_create_index(accounts, kind, key, program_id)
+            }
+            NFTInstruction::SyncIndex(instruction::SyncIndex{}) => {
+                msg!("Instruction: SyncIndex");
+                index::process_sync_index(accounts, program_id)
+            }
+            NFTInstruction::ListCore(instruction::ListCore{price}) => {
+                msg!("Instruction: List Core");
+                core_listing::process_list_core(accounts, price, program_id)
+            }
+            NFTInstruction::DelistCore(instruction::DelistCore{}) => {
+                msg!("Instruction: Delist Core");
+                core_listing::process_delist_core(accounts, program_id)
+            }
+            NFTInstruction::BuyCore(instruction::BuyCore{max_price}) => {
+                msg!("Instruction: Buy Core");
+                core_listing::process_buy_core(accounts, max_price, program_id)
+            }
+            NFTInstruction::SetPaymentMint(instruction::SetPaymentMint{enabled}) => {
+                msg!("Instruction: Set Payment Mint");
+                payment::process_set_payment_mint(accounts, enabled, program_id)
+            }
+            NFTInstruction::ListForToken(instruction::ListForToken{price}) => {
+                msg!("Instruction: List For Token");
+                payment::process_list_for_token(accounts, price, program_id)
+            }
+            NFTInstruction::CancelTokenListing(instruction::CancelTokenListing{}) => {
+                msg!("Instruction: Cancel Token Listing");
+                payment::process_cancel_token_listing(accounts, program_id)
+            }
+            NFTInstruction::BidWithToken(instruction::BidWithToken{amount}) => {
+                msg!("Instruction: Bid With Token");
+                payment::process_bid_with_token(accounts, amount, program_id)
+            }
+            NFTInstruction::WithdrawTokenBid(instruction::WithdrawTokenBid{}) => {
+                msg!("Instruction: Withdraw Token Bid");
+                payment::process_withdraw_token_bid(accounts, program_id)
+            }
+            NFTInstruction::AcceptTokenBid(instruction::AcceptTokenBid{}) => {
+                msg!("Instruction: Accept Token Bid");
+                payment::process_accept_token_bid(accounts, program_id)
+            }
+            NFTInstruction::CreateSession(instruction::CreateSession{session_key, expires_at, allowed}) => {
+                msg!("Instruction: Create Session");
+                session::process_create_session(accounts, session_key, expires_at, allowed, program_id)
+            }
+            NFTInstruction::RevokeSession(instruction::RevokeSession{}) => {
+                msg!("Instruction: Revoke Session");
+                session::process_revoke_session(accounts, program_id)
+            }
+            NFTInstruction::SetBidCooldown(instruction::SetBidCooldown{slots}) => {
+                msg!("Instruction: Set Bid Cooldown");
+                Self::process_set_bid_cooldown(accounts, slots, program_id)
+            }
+            NFTInstruction::SetPaused(instruction::SetPaused{paused}) => {
+                msg!("Instruction: Set Paused");
+                Self::process_set_paused(accounts, paused, program_id)
+            }
+            NFTInstruction::SetAutomationThread(instruction::SetAutomationThread{thread, stale_bid_slots}) => {
+                msg!("Instruction: Set Automation Thread");
+                Self::process_set_automation_thread(accounts, thread, stale_bid_slots, program_id)
+            }
+            NFTInstruction::ThreadRefundBid(instruction::ThreadRefundBid{}) => {
+                msg!("Instruction: Thread Refund Bid");
+                Self::process_refund(accounts, Refunder::Thread, program_id)
+            }
+            NFTInstruction::MintAndList(instruction::MintAndList{price, metadata}) => {
+                msg!("Instruction: Mint And List");
+                Self::process_mint_and_list(accounts, price, metadata, program_id)
+            }
+            NFTInstruction::SetMaxRoyalty(instruction::SetMaxRoyalty{max_royalty_bps}) => {
+                msg!("Instruction: Set Max Royalty");
+                Self::process_set_max_royalty(accounts, max_royalty_bps, program_id)
+            }
+            NFTInstruction::SetCurator(instruction::SetCurator{curator, curator_fee_bps}) => {
+                msg!("Instruction: Set Curator");
+                Self::process_set_curator(accounts, curator, curator_fee_bps, program_id)
+            }
+            NFTInstruction::ApproveListing(instruction::ApproveListing{}) => {
+                msg!("Instruction: Approve Listing");
+                Self::process_approve_listing(accounts, program_id)
+            }
+            NFTInstruction::CreateCrowdfund(instruction::CreateCrowdfund{buyout_price}) => {
+                msg!("Instruction: Create Crowdfund");
+                crowdfund::process_create_crowdfund(accounts, buyout_price, program_id)
+            }
+            NFTInstruction::Contribute(instruction::Contribute{amount}) => {
+                msg!("Instruction: Contribute");
+                crowdfund::process_contribute(accounts, amount, program_id)
+            }
+            NFTInstruction::ExecuteCrowdfund(instruction::ExecuteCrowdfund{}) => {
+                msg!("Instruction: Execute Crowdfund");
+                crowdfund::process_execute_crowdfund(accounts, program_id)
+            }
+            NFTInstruction::ClaimCrowdfundShares(instruction::ClaimCrowdfundShares{}) => {
+                msg!("Instruction: Claim Crowdfund Shares");
+                crowdfund::process_claim_crowdfund_shares(accounts, program_id)
+            }
+            NFTInstruction::RefundContribution(instruction::RefundContribution{}) => {
+                msg!("Instruction: Refund Contribution");
+                crowdfund::process_refund_contribution(accounts, program_id)
+            }
+            NFTInstruction::SweepBuy(instruction::SweepBuy{max_spend, purchases}) => {
+                msg!("Instruction: Sweep Buy");
+                Self::process_sweep_buy(accounts, max_spend, purchases, program_id)
+            }
+            NFTInstruction::MakeTraitOffer(instruction::MakeTraitOffer{amount, root}) => {
+                msg!("Instruction: Make Trait Offer");
+                trait_offer::process_make_trait_offer(accounts, amount, root, program_id)
+            }
+            NFTInstruction::AcceptTraitOffer(instruction::AcceptTraitOffer{proof}) => {
+                msg!("Instruction: Accept Trait Offer");
+                trait_offer::process_accept_trait_offer(accounts, &proof, program_id)
+            }
+            NFTInstruction::CancelTraitOffer(instruction::CancelTraitOffer{}) => {
+                msg!("Instruction: Cancel Trait Offer");
+                trait_offer::process_cancel_trait_offer(accounts, program_id)
+            }
+            NFTInstruction::SetRewards(instruction::SetRewards{buyer_reward, seller_reward, epoch_cap}) => {
+                msg!("Instruction: Set Rewards");
+                rewards::process_set_rewards(accounts, buyer_reward, seller_reward, epoch_cap, program_id)
+            }
+            NFTInstruction::CreateLeaderboard(instruction::CreateLeaderboard{season, starts_at, ends_at}) => {
+                msg!("Instruction: Create Leaderboard");
+                leaderboard::process_create_leaderboard(accounts, season, starts_at, ends_at, program_id)
+            }
+            NFTInstruction::SetMaxCharity(instruction::SetMaxCharity{max_charity_bps}) => {
+                msg!("Instruction: Set Max Charity");
+                Self::process_set_max_charity(accounts, max_charity_bps, program_id)
+            }
+            NFTInstruction::SetMarketplaceFee(instruction::SetMarketplaceFee{marketplace_fee_bps}) => {
+                msg!("Instruction: Set Marketplace Fee");
+                Self::process_set_marketplace_fee(accounts, marketplace_fee_bps, program_id)
+            }
+            NFTInstruction::CreateSwap(instruction::CreateSwap{id, maker_lamports, taker_lamports, taker_mints}) => {
+                msg!("Instruction: Create Swap");
+                swap::process_create_swap(accounts, id, maker_lamports, taker_lamports, taker_mints, program_id)
+            }
+            NFTInstruction::DepositSwap(instruction::DepositSwap{}) => {
+                msg!("Instruction: Deposit Swap");
+                swap::process_deposit_swap(accounts, program_id)
+            }
+            NFTInstruction::ExecuteSwap(instruction::ExecuteSwap{}) => {
+                msg!("Instruction: Execute Swap");
+                swap::process_execute_swap(accounts, program_id)
+            }
+            NFTInstruction::CancelSwap(instruction::CancelSwap{}) => {
+                msg!("Instruction: Cancel Swap");
+                swap::process_cancel_swap(accounts, program_id)
+            }
+            NFTInstruction::Gift(instruction::Gift{recipient, reveal_at, reclaimable_at}) => {
+                msg!("Instruction: Gift");
+                Self::process_gift(accounts, recipient, reveal_at, reclaimable_at, program_id)
+            }
+            NFTInstruction::ClaimGift(instruction::ClaimGift{}) => {
+                msg!("Instruction: Claim Gift");
+                Self::process_claim_gift(accounts, program_id)
+            }
+            NFTInstruction::SetPriceFeed(instruction::SetPriceFeed{price_feed}) => {
+                msg!("Instruction: Set Price Feed");
+                Self::process_set_price_feed(accounts, price_feed, program_id)
+            }
+            NFTInstruction::SetBidExpiry(instruction::SetBidExpiry{expires_at}) => {
+                msg!("Instruction: Set Bid Expiry");
+                Self::process_set_bid_expiry(accounts, expires_at, program_id)
+            }
+            NFTInstruction::ExpireAndRefund(instruction::ExpireAndRefund{}) => {
+                msg!("Instruction: Expire And Refund");
+                Self::process_refund(accounts, Refunder::Crank, program_id)
+            }
+            NFTInstruction::ReverseSettlement(instruction::ReverseSettlement{}) => {
+                msg!("Instruction: Reverse Settlement");
+                Self::process_reverse_settlement(accounts, program_id)
+            }
+            NFTInstruction::FinalizeSettlement(instruction::FinalizeSettlement{}) => {
+                msg!("Instruction: Finalize Settlement");
+                Self::process_finalize_settlement(accounts, program_id)
+            }
+            NFTInstruction::SetAttestation(instruction::SetAttestation{attestor, required}) => {
+                msg!("Instruction: Set Attestation");
+                attestation::process_set_attestation(accounts, attestor, required, program_id)
+            }
+            NFTInstruction::SetVisibility(instruction::SetVisibility{visibility}) => {
+                msg!("Instruction: Set Visibility");
+                Self::process_set_visibility(accounts, visibility, program_id)
             }
         }
     }
@@ -67,9 +455,14 @@ impl Processor {
     fn process_init_platform(
         accounts: &[AccountInfo],
         authority: Pubkey,
-        platform_fee: u64,
+        maker_bps: u64,
+        taker_bps: u64,
         program_id: &Pubkey,
     ) -> ProgramResult {  
+        if maker_bps > state::MAX_BPS || taker_bps > state::MAX_BPS {
+            return Err(NFTError::InvalidPlatformFee.into());
+        }
+
         let account_info_iter = &mut accounts.iter();
         let initializer_info = next_account_info(account_info_iter)?;
         if !initializer_info.is_signer {
@@ -94,7 +487,8 @@ impl Processor {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+        Self::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+        let rent = &Rent::get()?;
         let required_balance = rent.minimum_balance(state::STATESIZE);
 
         let create_state_account_ix = system_instruction::create_account(initializer_info.key, &state_account_pubkey, required_balance, state::STATESIZE as u64, program_id);
@@ -114,10 +508,37 @@ impl Processor {
 
         PlatformState::pack(
             PlatformState{
+                layout_version: state::LAYOUT_VERSION,
                 is_initialized: true,
                 authority: authority,
-                platform_fee: platform_fee,
-                nonce: 0
+                maker_fee_bps: maker_bps,
+                nonce: 0,
+                referral_fee_bps: 0,
+                taker_fee_bps: taker_bps,
+                curated: false,
+                sale_count: 0,
+                total_volume: 0,
+                total_fees: 0,
+                active_listings: 0,
+                fee_recipient: authority,
+                strict: false,
+                min_listing_lamports: 0,
+                min_bid_lamports: 0,
+                timelock_slots: 0,
+                pending_change: None,
+                pending_change_slot: 0,
+                min_bid_lifetime_slots: 0,
+                paused: 0,
+                automation_thread: Pubkey::default(),
+                stale_bid_slots: 0,
+                max_royalty_bps: 0,
+                curator: Pubkey::default(),
+                curator_fee_bps: 0,
+                max_charity_bps: 0,
+                marketplace_fee_bps: 0,
+                price_feed: Pubkey::default(),
+                attestation_required: false,
+                reserved: Default::default(),
             }, 
             &mut state_account_info.data.borrow_mut()
         )?;
@@ -138,6 +559,7 @@ impl Processor {
 
         let state_account_info = next_account_info(account_info_iter)?;
         let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        Self::assert_owned_by(state_account_info, program_id)?;
         let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
         if !(state_account_info.key.eq(&state_account_pubkey)) {
             return Err(ProgramError::InvalidInstructionData);
@@ -149,17 +571,53 @@ impl Processor {
             return Err(NFTError::InvalidAuthority.into()); 
         }
 
-        state_info.authority = authority;
+        Self::change_or_schedule(&mut state_info, ParamChange::Authority{authority})?;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_change_fee_recipient(
+        accounts: &[AccountInfo],
+        fee_recipient: Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        Self::change_or_schedule(&mut state_info, ParamChange::FeeRecipient{fee_recipient})?;
         PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
 
         Ok(())
     }
 
-    fn process_change_fee(
+    fn process_change_fees(
         accounts: &[AccountInfo],
-        platform_fee: u64,
+        maker_bps: u64,
+        taker_bps: u64,
         program_id: &Pubkey,
     ) -> ProgramResult {
+        if maker_bps > state::MAX_BPS || taker_bps > state::MAX_BPS {
+            return Err(NFTError::InvalidPlatformFee.into());
+        }
+
         let account_info_iter = &mut accounts.iter();
         let initializer_info = next_account_info(account_info_iter)?;
         if !initializer_info.is_signer {
@@ -168,6 +626,7 @@ impl Processor {
 
         let state_account_info = next_account_info(account_info_iter)?;
         let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        Self::assert_owned_by(state_account_info, program_id)?;
         let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
         if !(state_account_info.key.eq(&state_account_pubkey)) {
             return Err(ProgramError::InvalidInstructionData);
@@ -179,196 +638,2969 @@ impl Processor {
             return Err(NFTError::InvalidAuthority.into()); 
         }
 
-        state_info.platform_fee = platform_fee;
+        Self::change_or_schedule(&mut state_info, ParamChange::Fees{maker_bps, taker_bps})?;
         PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
 
         Ok(())
     }
 
-    fn process_list(
+    /// Applies `change` right away when the platform has no timelock, and
+    /// otherwise queues it to execute once the timelock has passed.
+    fn change_or_schedule(state_info: &mut PlatformState, change: ParamChange) -> ProgramResult {
+        if state_info.timelock_slots == 0 {
+            change.apply(state_info);
+            return Ok(());
+        }
+        state_info.pending_change_slot = Clock::get()?.slot
+            .checked_add(state_info.timelock_slots)
+            .ok_or(NFTError::NumericalOverflow)?;
+        state_info.pending_change = Some(change);
+        Ok(())
+    }
+
+    fn process_schedule_param_change(
         accounts: &[AccountInfo],
-        amount: u64,
+        change: ParamChange,
+        execute_slot: u64,
         program_id: &Pubkey,
     ) -> ProgramResult {
+        let valid = match change {
+            ParamChange::Fees{maker_bps, taker_bps} => maker_bps <= state::MAX_BPS && taker_bps <= state::MAX_BPS,
+            ParamChange::ReferralFee{referral_fee_bps} => referral_fee_bps <= state::MAX_BPS,
+            ParamChange::MaxRoyalty{max_royalty_bps} => max_royalty_bps <= state::MAX_BPS,
+            // A sale may credit two marketplaces, both paid from the same fee.
+            ParamChange::MarketplaceFee{marketplace_fee_bps} => marketplace_fee_bps <= state::MAX_BPS / 2,
+            ParamChange::Curator{curator_fee_bps, ..} => curator_fee_bps <= state::MAX_BPS,
+            ParamChange::Authority{..} | ParamChange::Timelock{..}
+                | ParamChange::FeeRecipient{..} | ParamChange::PriceFeed{..} => true,
+        };
+        if !valid {
+            return Err(NFTError::InvalidPlatformFee.into());
+        }
+
         let account_info_iter = &mut accounts.iter();
         let initializer_info = next_account_info(account_info_iter)?;
         if !initializer_info.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
-        let token_account_info = next_account_info(account_info_iter)?;
-        let token_account_data = spl_token::state::Account::unpack_unchecked(&token_account_info.data.borrow())?;
-        if !(token_account_data.owner.eq(&initializer_info.key)) {
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
             return Err(ProgramError::InvalidAccountData);
         }
-
-        let mint_account_info = next_account_info(account_info_iter)?;
-        if !(mint_account_info.owner.eq(&spl_token::id())) {
-            return Err(ProgramError::InvalidAccountData);
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
         }
 
-        if !(token_account_data.mint.eq(&mint_account_info.key)) {
-            return Err(ProgramError::InvalidAccountData);
+        let earliest_slot = Clock::get()?.slot
+            .checked_add(state_info.timelock_slots)
+            .ok_or(NFTError::NumericalOverflow)?;
+        if execute_slot < earliest_slot {
+            return Err(NFTError::TimelockTooShort.into());
         }
 
-        let escrow_state_account_info = next_account_info(account_info_iter)?;
-        let escrow_vault_account_info = next_account_info(account_info_iter)?;
+        state_info.pending_change = Some(change);
+        state_info.pending_change_slot = execute_slot;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
 
-        let program_info = next_account_info(account_info_iter)?;
-        if !(program_info.key.eq(program_id)) {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        Ok(())
+    }
 
-        let token_program_info = next_account_info(account_info_iter)?;
-        if !(spl_token::id().eq(token_program_info.key)) {
+    fn process_execute_param_change(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
             return Err(ProgramError::InvalidAccountData);
         }
-        
-        let system_program_info = next_account_info(account_info_iter)?;
-        if !(system_program_info.key.eq(&system_program::id())) {
-            return Err(ProgramError::InvalidAccountData);
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
         }
 
-        let rent_account_info = next_account_info(account_info_iter)?;
-
-        let (escrow_state_account_pubkey, nonce1) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            initializer_info.key.as_ref(),
-            b"List",
-            b"State"
-            ],
-            program_id
-        );
-        if !(escrow_state_account_info.key.eq(&escrow_state_account_pubkey)) {
-            return Err(ProgramError::InvalidAccountData);
+        let change = state_info.pending_change.ok_or(NFTError::NoPendingChange)?;
+        if Clock::get()?.slot < state_info.pending_change_slot {
+            return Err(NFTError::TimelockNotElapsed.into());
         }
-        let rent = &Rent::from_account_info(rent_account_info)?;
-        let required_balance = rent.minimum_balance(state::LISTESCROWSTATE);
-        let create_state_account_ix = system_instruction::create_account(
-            initializer_info.key, 
-            &escrow_state_account_pubkey, 
-            required_balance, 
-            state::LISTESCROWSTATE as u64, 
-            program_id);
-        msg!("submitting tx to create program derived state account");
-        invoke_signed(
-            &create_state_account_ix,
-            &[
-                initializer_info.clone(),
-                escrow_state_account_info.clone(),
-                system_program_info.clone(),
-                program_info.clone(),
-            ],
-            &[&[
-                mint_account_info.key.as_ref(),
-                initializer_info.key.as_ref(),
-                &b"List"[..],
-                &b"State"[..],
-                &[nonce1]
-            ]],
-        )?;
-        msg!("state account pubkey: {}", escrow_state_account_pubkey);
+        change.apply(&mut state_info);
+        state_info.pending_change = None;
+        state_info.pending_change_slot = 0;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
 
+        Ok(())
+    }
 
-        let (escrow_vault_account_pubkey, nonce2) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            initializer_info.key.as_ref(),
-            b"List",
-            b"Vault"
-            ],
-            program_id
-        );
-        if !(escrow_vault_account_info.key.eq(&escrow_vault_account_pubkey)) {
-            return Err(ProgramError::InvalidAccountData);
+    fn process_change_referral_fee(
+        accounts: &[AccountInfo],
+        referral_fee_bps: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if referral_fee_bps > state::MAX_BPS {
+            return Err(NFTError::InvalidPlatformFee.into());
         }
-        let required_balance = rent.minimum_balance(spl_token::state::Account::LEN);
-        let create_vault_account_ix = system_instruction::create_account(initializer_info.key, &escrow_vault_account_pubkey, required_balance, spl_token::state::Account::LEN as u64, &spl_token::id());
-        invoke_signed(
-            &create_vault_account_ix,
-            &[
-                initializer_info.clone(),
-                escrow_vault_account_info.clone(),
-                system_program_info.clone(),
-                token_program_info.clone(),
-            ],
-            &[&[
-                mint_account_info.key.as_ref(),
-                initializer_info.key.as_ref(),
-                &b"List"[..],
-                &b"Vault"[..],
-                &[nonce2]
-            ]],
-        )?;
-        msg!("vault account pubkey: {}", escrow_vault_account_pubkey);
 
-        let initialize_vault_account_ix = spl_token::instruction::initialize_account(
-            &spl_token::id(), 
-            &escrow_vault_account_pubkey, 
-            mint_account_info.key, 
-            &escrow_state_account_pubkey
-        )?;
-        invoke_signed(
-            &initialize_vault_account_ix,
-            &[
-                token_program_info.clone(),
-                escrow_vault_account_info.clone(),
-                escrow_state_account_info.clone(),
-                mint_account_info.clone(),
-                program_info.clone(),
-                rent_account_info.clone()
-            ],
-            &[&[
-                mint_account_info.key.as_ref(),
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into()); 
+        }
+
+        Self::change_or_schedule(&mut state_info, ParamChange::ReferralFee{referral_fee_bps})?;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_set_curated_mode(
+        accounts: &[AccountInfo],
+        curated: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into()); 
+        }
+
+        state_info.curated = curated;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_set_strict_mode(
+        accounts: &[AccountInfo],
+        strict: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        state_info.strict = strict;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_set_minimums(
+        accounts: &[AccountInfo],
+        min_listing_lamports: u64,
+        min_bid_lamports: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        state_info.min_listing_lamports = min_listing_lamports;
+        state_info.min_bid_lamports = min_bid_lamports;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_set_bid_cooldown(
+        accounts: &[AccountInfo],
+        slots: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        state_info.min_bid_lifetime_slots = slots;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_set_paused(
+        accounts: &[AccountInfo],
+        paused: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        // Pausing is an emergency switch, so it skips the timelock.
+        state_info.paused = paused;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_set_max_royalty(
+        accounts: &[AccountInfo],
+        max_royalty_bps: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if max_royalty_bps > state::MAX_BPS {
+            return Err(NFTError::InvalidPlatformFee.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        Self::change_or_schedule(&mut state_info, ParamChange::MaxRoyalty{max_royalty_bps})?;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_set_max_charity(
+        accounts: &[AccountInfo],
+        max_charity_bps: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if max_charity_bps > state::MAX_BPS {
+            return Err(NFTError::InvalidPlatformFee.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        // Listings keep the charity split they were listed with.
+        state_info.max_charity_bps = max_charity_bps;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_set_marketplace_fee(
+        accounts: &[AccountInfo],
+        marketplace_fee_bps: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // A sale may credit two marketplaces, both paid from the same fee.
+        if marketplace_fee_bps > state::MAX_BPS / 2 {
+            return Err(NFTError::InvalidPlatformFee.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        Self::change_or_schedule(&mut state_info, ParamChange::MarketplaceFee{marketplace_fee_bps})?;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_set_price_feed(
+        accounts: &[AccountInfo],
+        price_feed: Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        Self::change_or_schedule(&mut state_info, ParamChange::PriceFeed{price_feed})?;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_set_curator(
+        accounts: &[AccountInfo],
+        curator: Pubkey,
+        curator_fee_bps: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if curator_fee_bps > state::MAX_BPS {
+            return Err(NFTError::InvalidPlatformFee.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        Self::change_or_schedule(&mut state_info, ParamChange::Curator{curator, curator_fee_bps})?;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Opens a pending listing to sales. Only the platform's current
+    /// curator can approve, and it becomes the curator paid on the sale.
+    fn process_approve_listing(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let curator_info = next_account_info(account_info_iter)?;
+        if !curator_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if state_info.curator == Pubkey::default() || !state_info.curator.eq(curator_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        let list_state_account_info = next_account_info(account_info_iter)?;
+        Self::assert_owned_by(list_state_account_info, program_id)?;
+        let mut list_state = ListEscrowState::unpack(&list_state_account_info.data.borrow())?;
+        Self::check_program_address(list_state_account_info, &[
+            list_state.mint.as_ref(),
+            list_state.lister.as_ref(),
+            b"List",
+            b"State"
+            ],
+            list_state.state_bump,
+            program_id
+        )?;
+        if !list_state.is_pending() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        list_state.curator = *curator_info.key;
+        list_state.approved = true;
+        list_state.updated_at_slot = Clock::get()?.slot;
+        ListEscrowState::pack(list_state, &mut list_state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Shows the lister's listing in public feeds or keeps it unlisted. An
+    /// unlisted floor listing is taken off the floor of the collection
+    /// whose stats account follows; listings made public again count once
+    /// they are relisted.
+    fn process_set_visibility(
+        accounts: &[AccountInfo],
+        visibility: u8,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if visibility > state::VISIBILITY_UNLISTED {
+            return Err(NFTError::InvalidInstructionData.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let lister_info = next_account_info(account_info_iter)?;
+        if !lister_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let list_state_account_info = next_account_info(account_info_iter)?;
+        Self::assert_owned_by(list_state_account_info, program_id)?;
+        let mut list_state = ListEscrowState::unpack(&list_state_account_info.data.borrow())?;
+        Self::check_program_address(list_state_account_info, &[
+            list_state.mint.as_ref(),
+            list_state.lister.as_ref(),
+            b"List",
+            b"State"
+            ],
+            list_state.state_bump,
+            program_id
+        )?;
+        if !list_state.lister.eq(lister_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        list_state.visibility = visibility;
+        list_state.updated_at_slot = Clock::get()?.slot;
+        ListEscrowState::pack(list_state, &mut list_state_account_info.data.borrow_mut())?;
+
+        if list_state.is_unlisted() {
+            if let Some(collection_stats_account_info) = account_info_iter.next() {
+                Self::assert_owned_by(collection_stats_account_info, program_id)?;
+                let collection = CollectionStats::unpack(&collection_stats_account_info.data.borrow())?.collection;
+                Self::update_collection_stats(collection_stats_account_info, &collection, program_id, |stats| {
+                    stats.remove_listing(list_state_account_info.key);
+                    Ok(())
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn process_set_automation_thread(
+        accounts: &[AccountInfo],
+        thread: Pubkey,
+        stale_bid_slots: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+        if !initializer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(initializer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        state_info.automation_thread = thread;
+        state_info.stale_bid_slots = stale_bid_slots;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_set_collection_allowed(
+        accounts: &[AccountInfo],
+        collection: Pubkey,
+        allowed: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority_info = next_account_info(account_info_iter)?;
+        if !authority_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(authority_info.key) {
+            return Err(NFTError::InvalidAuthority.into()); 
+        }
+
+        let collection_state_account_info = next_account_info(account_info_iter)?;
+        let (collection_state_pubkey, nonce) = Pubkey::find_program_address(&[
+            collection.as_ref(),
+            b"Collection",
+            b"State"
+            ],
+            program_id
+        );
+        if !(collection_state_account_info.key.eq(&collection_state_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if collection_state_account_info.data_is_empty() {
+            if !allowed {
+                return Err(ProgramError::UninitializedAccount);
+            }
+
+            let program_info = next_account_info(account_info_iter)?;
+            if !(program_info.key.eq(program_id)) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            let system_program_info = next_account_info(account_info_iter)?;
+            if !(system_program_info.key.eq(&system_program::id())) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            Self::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+            let rent = &Rent::get()?;
+            let required_balance = rent.minimum_balance(state::COLLECTIONSTATE);
+            let create_state_account_ix = system_instruction::create_account(
+                authority_info.key,
+                &collection_state_pubkey,
+                required_balance,
+                state::COLLECTIONSTATE as u64,
+                program_id);
+            msg!("submitting tx to create program derived collection account");
+            invoke_signed(
+                &create_state_account_ix,
+                &[
+                    authority_info.clone(),
+                    collection_state_account_info.clone(),
+                    system_program_info.clone(),
+                    program_info.clone(),
+                ],
+                &[&[
+                    collection.as_ref(),
+                    &b"Collection"[..],
+                    &b"State"[..],
+                    &[nonce]
+                ]],
+            )?;
+        } else if !(collection_state_account_info.owner.eq(program_id)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        CollectionState::pack(
+            CollectionState{
+                layout_version: state::LAYOUT_VERSION,
+                is_initialized: true,
+                collection: collection,
+                allowed: allowed,
+                reserved: Default::default(),
+            },
+            &mut collection_state_account_info.data.borrow_mut()
+        )?;
+
+        Ok(())
+    }
+
+    fn process_set_fee_discount(
+        accounts: &[AccountInfo],
+        discount_mint: Pubkey,
+        threshold: u64,
+        discount_bps: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if discount_bps > state::MAX_BPS {
+            return Err(NFTError::InvalidPlatformFee.into());
+        }
+
+        let account_info_iter = &mut accounts.iter();
+        let authority_info = next_account_info(account_info_iter)?;
+        if !authority_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if !state_info.authority.eq(authority_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        let fee_discount_account_info = next_account_info(account_info_iter)?;
+        let (fee_discount_pubkey, nonce) = state::find_fee_discount_address(program_id);
+        if !(fee_discount_account_info.key.eq(&fee_discount_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if fee_discount_account_info.data_is_empty() {
+            let program_info = next_account_info(account_info_iter)?;
+            if !(program_info.key.eq(program_id)) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            let system_program_info = next_account_info(account_info_iter)?;
+            if !(system_program_info.key.eq(&system_program::id())) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            Self::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+            let rent = &Rent::get()?;
+            let required_balance = rent.minimum_balance(state::FEEDISCOUNTSIZE);
+            let create_discount_account_ix = system_instruction::create_account(
+                authority_info.key,
+                &fee_discount_pubkey,
+                required_balance,
+                state::FEEDISCOUNTSIZE as u64,
+                program_id);
+            msg!("submitting tx to create program derived fee discount account");
+            invoke_signed(
+                &create_discount_account_ix,
+                &[
+                    authority_info.clone(),
+                    fee_discount_account_info.clone(),
+                    system_program_info.clone(),
+                    program_info.clone(),
+                ],
+                &[&[
+                    &b"Fee"[..],
+                    &b"Discount"[..],
+                    &[nonce]
+                ]],
+            )?;
+        } else if !(fee_discount_account_info.owner.eq(program_id)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        FeeDiscount::pack(
+            FeeDiscount{
+                layout_version: state::LAYOUT_VERSION,
+                is_initialized: true,
+                discount_mint,
+                threshold,
+                discount_bps,
+                reserved: Default::default(),
+            },
+            &mut fee_discount_account_info.data.borrow_mut()
+        )?;
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn process_list(
+        accounts: &[AccountInfo],
+        amount: u64,
+        auction: bool,
+        allowed_buyer: Pubkey,
+        starts_at: i64,
+        royalty_bps_override: u16,
+        allowlist_root: [u8; 32],
+        charity: Pubkey,
+        charity_bps: u16,
+        marketplace: Pubkey,
+        reclaimable_at: i64,
+        price_usd: u64,
+        settlement_delay_slots: u64,
+        arbiter: Pubkey,
+        visibility: u8,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let (accounts, authority_info) = session::split_session(accounts, session::ALLOW_LIST, program_id)?;
+        let (accounts, attestation_accounts) = attestation::split_attestation(accounts, program_id);
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+
+        // Funds the escrow accounts' rent, so a relayer can list on the
+        // lister's behalf while the lister signs only as owner.
+        let payer_info = next_account_info(account_info_iter)?;
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let token_account_info = next_account_info(account_info_iter)?;
+        // The mint comes first, so a soulbound one is named as such rather
+        // than failing on its holder's token account.
+        let mint_account_info = next_account_info(account_info_iter)?;
+        Self::check_nft_mint(mint_account_info)?;
+
+        let token_account_data = spl_token::state::Account::unpack_unchecked(&token_account_info.data.borrow())?;
+        if !(token_account_data.owner.eq(&initializer_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !(token_account_data.mint.eq(&mint_account_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::check_seller_token_account(&token_account_data)?;
+
+        let escrow_state_account_info = next_account_info(account_info_iter)?;
+        let escrow_vault_account_info = next_account_info(account_info_iter)?;
+
+        let associated_token_program_info = next_account_info(account_info_iter)?;
+        if !(spl_associated_token_account::id().eq(associated_token_program_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let token_program_info = next_account_info(account_info_iter)?;
+        if !(spl_token::id().eq(token_program_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        
+        let system_program_info = next_account_info(account_info_iter)?;
+        if !(system_program_info.key.eq(&system_program::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let rent_account_info = next_account_info(account_info_iter)?;
+        Self::check_rent_sysvar(rent_account_info)?;
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if state_info.is_paused(state::PAUSE_LIST) {
+            return Err(NFTError::InstructionPaused.into());
+        }
+        attestation::check_attested(attestation_accounts, &state_info, initializer_info.key, program_id)?;
+        // A USD price is the whole ask, and is only worth lamports once the
+        // listing fills, so there is nothing to hold to the minimum.
+        if price_usd != 0 {
+            if state_info.price_feed == Pubkey::default() {
+                return Err(NFTError::PriceFeedNotSet.into());
+            }
+            if amount != 0 || auction || reclaimable_at != 0 {
+                return Err(NFTError::InvalidInstructionData.into());
+            }
+        }
+        // Held sales are accepted bids, which neither auctions nor gifts
+        // settle through.
+        if settlement_delay_slots > state::MAX_SETTLEMENT_DELAY_SLOTS
+            || (settlement_delay_slots == 0 && arbiter != Pubkey::default())
+            || (settlement_delay_slots != 0 && (auction || reclaimable_at != 0))
+        {
+            return Err(NFTError::InvalidInstructionData.into());
+        }
+        if visibility > state::VISIBILITY_UNLISTED {
+            return Err(NFTError::InvalidInstructionData.into());
+        }
+        // Gifts have no price to hold to the minimum.
+        if reclaimable_at == 0 && price_usd == 0 && amount < state_info.min_listing_lamports {
+            return Err(NFTError::BelowMinimumPrice.into());
+        }
+        // A listing is reserved either for one buyer or for an allowlist.
+        if allowed_buyer != Pubkey::default() && allowlist_root != [0u8; 32] {
+            return Err(NFTError::InvalidInstructionData.into());
+        }
+        let royalty_bps = (royalty_bps_override as u64).min(state_info.max_royalty_bps) as u16;
+        // A charity split names both the charity and a share of the
+        // proceeds, or neither.
+        if (charity == Pubkey::default()) != (charity_bps == 0) {
+            return Err(NFTError::InvalidInstructionData.into());
+        }
+        if charity_bps as u64 > state_info.max_charity_bps {
+            return Err(NFTError::CharityShareTooHigh.into());
+        }
+
+        let metadata = match account_info_iter.next() {
+            Some(metadata_account_info) => metadata::load_metadata(metadata_account_info, mint_account_info.key)?,
+            None if state_info.curated || state_info.strict => return Err(ProgramError::NotEnoughAccountKeys),
+            None => None,
+        };
+        if state_info.strict && !metadata.as_ref().map_or(false, |metadata| metadata.is_verified()) {
+            return Err(NFTError::UnverifiedCollection.into());
+        }
+        if state_info.curated {
+            let collection_state_account_info = next_account_info(account_info_iter)?;
+            Self::check_collection_allowed(metadata.as_ref().and_then(|metadata| metadata.verified_collection()), collection_state_account_info, program_id)?;
+        }
+        let collection_stats_account_info = account_info_iter.next();
+
+        let (escrow_state_account_pubkey, nonce1) = Pubkey::find_program_address(&[
+            mint_account_info.key.as_ref(),
+            initializer_info.key.as_ref(),
+            b"List",
+            b"State"
+            ],
+            program_id
+        );
+        if !(escrow_state_account_info.key.eq(&escrow_state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !escrow_state_account_info.data_is_empty() {
+            return Err(NFTError::ListingAlreadyExists.into());
+        }
+        let rent = &Rent::get()?;
+        let required_balance = rent.minimum_balance(state::LISTESCROWSTATE);
+        let create_state_account_ix = system_instruction::create_account(
+            payer_info.key, 
+            &escrow_state_account_pubkey, 
+            required_balance, 
+            state::LISTESCROWSTATE as u64, 
+            program_id);
+        msg!("submitting tx to create program derived state account");
+        invoke_signed(
+            &create_state_account_ix,
+            &[
+                payer_info.clone(),
+                escrow_state_account_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[&[
+                mint_account_info.key.as_ref(),
+                initializer_info.key.as_ref(),
+                &b"List"[..],
+                &b"State"[..],
+                &[nonce1]
+            ]],
+        )?;
+        msg!("state account pubkey: {}", escrow_state_account_pubkey);
+
+
+        let (escrow_vault_account_pubkey, nonce2) = Pubkey::find_program_address(
+            &[escrow_state_account_pubkey.as_ref(), spl_token::id().as_ref(), mint_account_info.key.as_ref()],
+            &spl_associated_token_account::id(),
+        );
+        if !(escrow_vault_account_info.key.eq(&escrow_vault_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        // Only the associated token account program can create this address,
+        // so an existing account is already a vault owned by the list state.
+        if escrow_vault_account_info.data_is_empty() {
+            invoke(
+                &spl_associated_token_account::create_associated_token_account(
+                    payer_info.key,
+                    &escrow_state_account_pubkey,
+                    mint_account_info.key,
+                ),
+                &[
+                    payer_info.clone(),
+                    escrow_vault_account_info.clone(),
+                    escrow_state_account_info.clone(),
+                    mint_account_info.clone(),
+                    system_program_info.clone(),
+                    token_program_info.clone(),
+                    rent_account_info.clone(),
+                    associated_token_program_info.clone(),
+                ],
+            )?;
+        }
+        msg!("vault account pubkey: {}", escrow_vault_account_pubkey);
+
+        let transfer_token_ix = spl_token::instruction::transfer_checked(
+            &spl_token::id(),
+            token_account_info.key, 
+            mint_account_info.key, 
+            &escrow_vault_account_pubkey, 
+            authority_info.key,
+            &[
+                authority_info.key
+            ], 
+            1, 
+            0
+        )?;
+
+        invoke(
+            &transfer_token_ix,
+            &[
+                token_program_info.clone(),
+                token_account_info.clone(),
+                mint_account_info.clone(),
+                escrow_vault_account_info.clone(),
+                authority_info.clone()
+            ],
+        )?;
+
+        let slot = Clock::get()?.slot;
+        ListEscrowState::pack(
+            ListEscrowState{
+                layout_version: state::LAYOUT_VERSION,
+                is_initialized: true,
+                lister: *initializer_info.key,
+                amount: amount,
+                mint: *mint_account_info.key,
+                success: false,
+                successful_buyer: Pubkey::new_from_array([0; 32]),
+                auction: auction,
+                highest_bid: 0,
+                highest_bidder: Pubkey::new_from_array([0; 32]),
+                allowed_buyer,
+                starts_at,
+                payer: *payer_info.key,
+                state_bump: nonce1,
+                vault_bump: nonce2,
+                royalty_bps,
+                curator: state_info.curator,
+                approved: false,
+                created_at_slot: slot,
+                updated_at_slot: slot,
+                bid_count: 0,
+                last_bid_slot: 0,
+                allowlist_root,
+                charity,
+                charity_bps,
+                marketplace,
+                reclaimable_at,
+                price_usd,
+                settlement_delay_slots,
+                arbiter,
+                settles_at_slot: 0,
+                visibility,
+                price_feed: if price_usd != 0 { state_info.price_feed } else { Pubkey::default() },
+                reserved: Default::default(),
+            },
+            &mut escrow_state_account_info.data.borrow_mut()
+        )?;
+
+        if let Some(collection_stats_account_info) = collection_stats_account_info {
+            let collection = metadata
+                .as_ref()
+                .and_then(|metadata| metadata.verified_collection())
+                .ok_or(ProgramError::InvalidAccountData)?;
+            Self::update_collection_stats(collection_stats_account_info, &collection, program_id, |stats| {
+                if !auction && price_usd == 0 && allowed_buyer == Pubkey::default() && allowlist_root == [0u8; 32] && visibility == state::VISIBILITY_PUBLIC {
+                    stats.record_listing(&escrow_state_account_pubkey, amount);
+                    stats.observe_listing(amount, slot);
+                }
+                Ok(())
+            })?;
+        }
+
+        // Gifts are not for sale, so they don't count as active listings.
+        if reclaimable_at == 0 {
+            state_info.active_listings = state_info.active_listings
+                .checked_add(1)
+                .ok_or(NFTError::NumericalOverflow)?;
+            PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+        }
+
+        Ok(())
+    }
+
+    fn process_delist(
+        accounts: &[AccountInfo],
+        bumps: &Bumps,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let signer_info = next_account_info(account_info_iter)?;
+        if !signer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let rent_payer_info = next_account_info(account_info_iter)?;
+
+        let token_account_info = next_account_info(account_info_iter)?;
+
+        let mint_account_info = next_account_info(account_info_iter)?;
+        if !(mint_account_info.owner.eq(&spl_token::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let escrow_state_account_info = next_account_info(account_info_iter)?;
+        let escrow_vault_account_info = next_account_info(account_info_iter)?;
+
+        let program_info = next_account_info(account_info_iter)?;
+        if !(program_info.key.eq(program_id)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let token_program_info = next_account_info(account_info_iter)?;
+        if !(spl_token::id().eq(token_program_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        Self::check_program_address(state_account_info, &[b"Platform", b"State"], bumps.platform_state, program_id)?;
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if state_info.is_paused(state::PAUSE_DELIST) {
+            return Err(NFTError::InstructionPaused.into());
+        }
+
+        let system_program_info = next_account_info(account_info_iter)?;
+        let rent_info = next_account_info(account_info_iter)?;
+        Self::check_rent_sysvar(rent_info)?;
+        let associated_token_program_info = next_account_info(account_info_iter)?;
+
+        // A lister who closed their token account after listing gets their
+        // associated token account back here, paying the rent themselves.
+        if token_account_info.data_is_empty() {
+            Self::create_associated_token_account(
+                signer_info,
+                token_account_info,
+                mint_account_info,
+                system_program_info,
+                token_program_info,
+                rent_info,
+                associated_token_program_info,
+            )?;
+        }
+        let token_account_data = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+        if !(token_account_data.owner.eq(signer_info.key) && token_account_data.mint.eq(mint_account_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Self::assert_owned_by(escrow_state_account_info, program_id)?;
+        let list_state = ListEscrowState::unpack(&escrow_state_account_info.data.borrow())?;
+        let nonce1 = Self::check_program_address(escrow_state_account_info, &[
+            mint_account_info.key.as_ref(),
+            signer_info.key.as_ref(),
+            b"List",
+            b"State"
+            ],
+            Self::escrow_bump(list_state.state_bump, bumps.list_state),
+            program_id
+        )?;
+        let escrow_state_account_pubkey = *escrow_state_account_info.key;
+        if !list_state.payer.eq(rent_payer_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if list_state.is_gift() && Clock::get()?.unix_timestamp < list_state.reclaimable_at {
+            return Err(NFTError::GiftNotReclaimable.into());
+        }
+        // A sold listing's token belongs to its buyer.
+        if list_state.success || list_state.is_settling() {
+            return Err(NFTError::SettlementPending.into());
+        }
+
+        Self::check_list_vault(
+            escrow_vault_account_info,
+            &escrow_state_account_pubkey,
+            mint_account_info.key,
+            Self::escrow_bump(list_state.vault_bump, bumps.list_vault),
+        )?;
+        let escrow_vault_account_pubkey = *escrow_vault_account_info.key;
+
+        let token_tansfer_ix = spl_token::instruction::transfer(
+            &spl_token::id(), 
+            &escrow_vault_account_pubkey, 
+            token_account_info.key, 
+            &escrow_state_account_pubkey, 
+            &[&escrow_state_account_pubkey],
+            1
+        )?; 
+        invoke_signed(
+            &token_tansfer_ix,
+            &[
+                token_program_info.clone(),
+                escrow_vault_account_info.clone(),
+                token_account_info.clone(),
+                escrow_state_account_info.clone()
+            ],
+            &[&[
+                mint_account_info.key.as_ref(),
+                signer_info.key.as_ref(),
+                &b"List"[..],
+                &b"State"[..],
+                &[nonce1]
+            ]],
+        )?;
+
+        let close_ix = spl_token::instruction::close_account(
+            &spl_token::id(), 
+            &escrow_vault_account_pubkey, 
+            &escrow_state_account_pubkey, 
+            &escrow_state_account_pubkey, 
+            &[&escrow_state_account_pubkey]
+        )?;
+
+        invoke_signed(
+            &close_ix,
+            &[
+                token_program_info.clone(),
+                escrow_vault_account_info.clone(),
+                signer_info.clone(),
+                escrow_state_account_info.clone(),
+            ],
+            &[&[
+                mint_account_info.key.as_ref(),
+                signer_info.key.as_ref(),
+                &b"List"[..],
+                &b"State"[..],
+                &[nonce1]
+            ]],
+        )?;
+
+        Self::close_account(escrow_state_account_info, rent_payer_info, program_id)?;
+
+        if let Some(collection_stats_account_info) = account_info_iter.next() {
+            Self::assert_owned_by(collection_stats_account_info, program_id)?;
+            let collection = CollectionStats::unpack(&collection_stats_account_info.data.borrow())?.collection;
+            Self::update_collection_stats(collection_stats_account_info, &collection, program_id, |stats| {
+                stats.remove_listing(&escrow_state_account_pubkey);
+                Ok(())
+            })?;
+        }
+
+        // Listings opened before the counter existed were never counted,
+        // and gifts never are.
+        if !list_state.is_gift() {
+            state_info.active_listings = state_info.active_listings.saturating_sub(1);
+            PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+        }
+
+        Ok(())
+    }
+
+    /// Lists one NFT per `[token, mint, list state, list vault, metadata]`
+    /// group in the remaining accounts, each at the matching entry of
+    /// `amounts`. Curated platforms also take the collection state after
+    /// each group, as `List` does. An attestation ending the accounts
+    /// covers every group.
+    fn process_list_many(
+        accounts: &[AccountInfo],
+        amounts: Vec<u64>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let (accounts, attestation_accounts) = attestation::split_attestation(accounts, program_id);
+        let account_info_iter = &mut accounts.iter();
+        let signer_info = next_account_info(account_info_iter)?;
+        let associated_token_program_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let rent_account_info = next_account_info(account_info_iter)?;
+        Self::check_rent_sysvar(rent_account_info)?;
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let curated = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?.curated;
+
+        let group_len = if curated { 6 } else { 5 };
+        let groups = account_info_iter.as_slice();
+        if groups.len() != amounts.len() * group_len {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        for (group, amount) in groups.chunks(group_len).zip(amounts) {
+            let mut list_accounts = vec![signer_info.clone(), signer_info.clone()];
+            list_accounts.extend_from_slice(&group[..4]);
+            list_accounts.extend_from_slice(&[
+                associated_token_program_info.clone(),
+                token_program_info.clone(),
+                system_program_info.clone(),
+                rent_account_info.clone(),
+                state_account_info.clone(),
+            ]);
+            list_accounts.extend_from_slice(&group[4..]);
+            list_accounts.extend_from_slice(attestation_accounts.unwrap_or(&[]));
+            Self::process_list(&list_accounts, amount, false, Pubkey::default(), 0, 0, [0u8; 32], Pubkey::default(), 0, Pubkey::default(), 0, 0, 0, Pubkey::default(), state::VISIBILITY_PUBLIC, program_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Mints a one-of-one NFT to the seller, optionally creates its Token
+    /// Metadata, and lists it as `List` would, in one instruction. The mint
+    /// authority is dropped once the token is minted, so the supply stays
+    /// at one.
+    fn process_mint_and_list(
+        accounts: &[AccountInfo],
+        price: u64,
+        new_metadata: Option<instruction::NewMetadata>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let (accounts, attestation_accounts) = attestation::split_attestation(accounts, program_id);
+        let account_info_iter = &mut accounts.iter();
+        let seller_info = next_account_info(account_info_iter)?;
+        let mint_account_info = next_account_info(account_info_iter)?;
+        if !seller_info.is_signer || !mint_account_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let token_account_info = next_account_info(account_info_iter)?;
+        let list_state_account_info = next_account_info(account_info_iter)?;
+        let list_vault_account_info = next_account_info(account_info_iter)?;
+        let state_account_info = next_account_info(account_info_iter)?;
+
+        let token_program_info = next_account_info(account_info_iter)?;
+        if !(spl_token::id().eq(token_program_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let associated_token_program_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        if !(system_program_info.key.eq(&system_program::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let rent_info = next_account_info(account_info_iter)?;
+        Self::check_rent_sysvar(rent_info)?;
+        let rent = &Rent::get()?;
+
+        invoke(
+            &system_instruction::create_account(
+                seller_info.key,
+                mint_account_info.key,
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            &[
+                seller_info.clone(),
+                mint_account_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+        invoke(
+            &spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                mint_account_info.key,
+                seller_info.key,
+                Some(seller_info.key),
+                0,
+            )?,
+            &[
+                token_program_info.clone(),
+                mint_account_info.clone(),
+                rent_info.clone(),
+            ],
+        )?;
+        Self::create_associated_token_account(
+            seller_info,
+            token_account_info,
+            mint_account_info,
+            system_program_info,
+            token_program_info,
+            rent_info,
+            associated_token_program_info,
+        )?;
+        invoke(
+            &spl_token::instruction::mint_to(
+                &spl_token::id(),
+                mint_account_info.key,
+                token_account_info.key,
+                seller_info.key,
+                &[],
+                1,
+            )?,
+            &[
+                token_program_info.clone(),
+                mint_account_info.clone(),
+                token_account_info.clone(),
+                seller_info.clone(),
+            ],
+        )?;
+
+        let metadata_account_info = match new_metadata {
+            Some(new_metadata) => {
+                let metadata_account_info = next_account_info(account_info_iter)?;
+                let token_metadata_program_info = next_account_info(account_info_iter)?;
+                if !(metadata::token_metadata_program::id().eq(token_metadata_program_info.key)) {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                invoke(
+                    &metadata::create_metadata_account(
+                        mint_account_info.key,
+                        seller_info.key,
+                        new_metadata.name,
+                        new_metadata.symbol,
+                        new_metadata.uri,
+                        new_metadata.seller_fee_basis_points,
+                    ),
+                    &[
+                        token_metadata_program_info.clone(),
+                        metadata_account_info.clone(),
+                        mint_account_info.clone(),
+                        seller_info.clone(),
+                        system_program_info.clone(),
+                    ],
+                )?;
+                Some(metadata_account_info)
+            }
+            None => None,
+        };
+
+        invoke(
+            &spl_token::instruction::set_authority(
+                &spl_token::id(),
+                mint_account_info.key,
+                None,
+                spl_token::instruction::AuthorityType::MintTokens,
+                seller_info.key,
+                &[],
+            )?,
+            &[
+                token_program_info.clone(),
+                mint_account_info.clone(),
+                seller_info.clone(),
+            ],
+        )?;
+
+        let mut list_accounts = vec![
+            seller_info.clone(),
+            seller_info.clone(),
+            token_account_info.clone(),
+            mint_account_info.clone(),
+            list_state_account_info.clone(),
+            list_vault_account_info.clone(),
+            associated_token_program_info.clone(),
+            token_program_info.clone(),
+            system_program_info.clone(),
+            rent_info.clone(),
+            state_account_info.clone(),
+        ];
+        list_accounts.extend(metadata_account_info.cloned());
+        list_accounts.extend_from_slice(attestation_accounts.unwrap_or(&[]));
+        Self::process_list(&list_accounts, price, false, Pubkey::default(), 0, 0, [0u8; 32], Pubkey::default(), 0, Pubkey::default(), 0, 0, 0, Pubkey::default(), state::VISIBILITY_PUBLIC, program_id)
+    }
+
+    /// Escrows an NFT as a zero-price listing only `recipient` can take,
+    /// with `ClaimGift` from `reveal_at`. The lister can `DeList` it from
+    /// `reclaimable_at` if it is still unclaimed. Takes the same accounts
+    /// as `List`.
+    fn process_gift(
+        accounts: &[AccountInfo],
+        recipient: Pubkey,
+        reveal_at: i64,
+        reclaimable_at: i64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if recipient == Pubkey::default() || reclaimable_at <= reveal_at.max(0) {
+            return Err(NFTError::InvalidInstructionData.into());
+        }
+        Self::process_list(accounts, 0, false, recipient, reveal_at, 0, [0u8; 32], Pubkey::default(), 0, Pubkey::default(), reclaimable_at, 0, 0, Pubkey::default(), state::VISIBILITY_PUBLIC, program_id)
+    }
+
+    /// Hands a revealed gift to its recipient and closes the listing as
+    /// `DeList` would.
+    fn process_claim_gift(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let (accounts, attestation_accounts) = attestation::split_attestation(accounts, program_id);
+        let account_info_iter = &mut accounts.iter();
+        let recipient_info = next_account_info(account_info_iter)?;
+        if !recipient_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let token_account_info = next_account_info(account_info_iter)?;
+        let mint_account_info = next_account_info(account_info_iter)?;
+        let lister_info = next_account_info(account_info_iter)?;
+        let rent_payer_info = next_account_info(account_info_iter)?;
+        let escrow_state_account_info = next_account_info(account_info_iter)?;
+        let escrow_vault_account_info = next_account_info(account_info_iter)?;
+
+        let token_program_info = next_account_info(account_info_iter)?;
+        if !(spl_token::id().eq(token_program_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        attestation::check_attested(attestation_accounts, &state_info, recipient_info.key, program_id)?;
+
+        let token_account_data = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+        if !(token_account_data.owner.eq(recipient_info.key) && token_account_data.mint.eq(mint_account_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Self::assert_owned_by(escrow_state_account_info, program_id)?;
+        let list_state = ListEscrowState::unpack(&escrow_state_account_info.data.borrow())?;
+        let nonce1 = Self::check_program_address(escrow_state_account_info, &[
+            mint_account_info.key.as_ref(),
+            lister_info.key.as_ref(),
+            b"List",
+            b"State"
+            ],
+            list_state.state_bump,
+            program_id
+        )?;
+        if !list_state.is_gift() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !list_state.allowed_buyer.eq(recipient_info.key) {
+            return Err(NFTError::BuyerNotAllowed.into());
+        }
+        if !list_state.has_started(Clock::get()?.unix_timestamp) {
+            return Err(NFTError::ListingNotStarted.into());
+        }
+        if !list_state.payer.eq(rent_payer_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let escrow_state_account_pubkey = *escrow_state_account_info.key;
+        Self::check_list_vault(escrow_vault_account_info, &escrow_state_account_pubkey, mint_account_info.key, list_state.vault_bump)?;
+        let escrow_vault_account_pubkey = *escrow_vault_account_info.key;
+        let state_seeds: &[&[u8]] = &[
+            mint_account_info.key.as_ref(),
+            lister_info.key.as_ref(),
+            &b"List"[..],
+            &b"State"[..],
+            &[nonce1]
+        ];
+
+        let token_transfer_ix = spl_token::instruction::transfer(
+            &spl_token::id(),
+            &escrow_vault_account_pubkey,
+            token_account_info.key,
+            &escrow_state_account_pubkey,
+            &[&escrow_state_account_pubkey],
+            1
+        )?;
+        invoke_signed(
+            &token_transfer_ix,
+            &[
+                token_program_info.clone(),
+                escrow_vault_account_info.clone(),
+                token_account_info.clone(),
+                escrow_state_account_info.clone()
+            ],
+            &[state_seeds],
+        )?;
+
+        let close_ix = spl_token::instruction::close_account(
+            &spl_token::id(),
+            &escrow_vault_account_pubkey,
+            &escrow_state_account_pubkey,
+            &escrow_state_account_pubkey,
+            &[&escrow_state_account_pubkey]
+        )?;
+        invoke_signed(
+            &close_ix,
+            &[
+                token_program_info.clone(),
+                escrow_vault_account_info.clone(),
+                escrow_state_account_info.clone(),
+            ],
+            &[state_seeds],
+        )?;
+
+        Self::close_account(escrow_state_account_info, rent_payer_info, program_id)
+    }
+
+    /// Delists one NFT per `[token, mint, list state, list vault]` group in
+    /// the remaining accounts. The signer must have funded each listing.
+    fn process_delist_many(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let signer_info = next_account_info(account_info_iter)?;
+        let program_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let state_account_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let rent_info = next_account_info(account_info_iter)?;
+        Self::check_rent_sysvar(rent_info)?;
+        let associated_token_program_info = next_account_info(account_info_iter)?;
+
+        let groups = account_info_iter.as_slice();
+        if groups.is_empty() || groups.len() % 4 != 0 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        for group in groups.chunks(4) {
+            let mut delist_accounts = vec![signer_info.clone(), signer_info.clone()];
+            delist_accounts.extend_from_slice(group);
+            delist_accounts.extend_from_slice(&[
+                program_info.clone(),
+                token_program_info.clone(),
+                state_account_info.clone(),
+                system_program_info.clone(),
+                rent_info.clone(),
+                associated_token_program_info.clone(),
+            ]);
+            Self::process_delist(&delist_accounts, &Bumps::default(), program_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Escrows a bid. The last `match_accounts` accounts, if any, are an
+    /// `ExecuteSale` account list for the mint's listing; when that listing
+    /// is open to the bidder and asks no more than `amount`, the new bid is
+    /// settled against it straight away, along with `tip_lamports` to the
+    /// creator.
+    #[allow(clippy::too_many_arguments)]
+    fn process_bid(
+        accounts: &[AccountInfo],
+        amount: u64,
+        wrapped: bool,
+        auction: bool,
+        match_accounts: u8,
+        tip_lamports: u64,
+        marketplace: Pubkey,
+        proof: &[[u8; 32]],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let (accounts, authority_info) = session::split_session(accounts, session::ALLOW_BID, program_id)?;
+        let bid_account_count = accounts.len()
+            .checked_sub(match_accounts as usize)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let (accounts, sale_accounts) = accounts.split_at(bid_account_count);
+        let (accounts, fee_discount, discount_holders) = Self::split_fee_discounts(accounts, program_id)?;
+        let (accounts, attestation_accounts) = attestation::split_attestation(accounts, program_id);
+        let account_info_iter = &mut accounts.iter();
+        let initializer_info = next_account_info(account_info_iter)?;
+
+        // Funds the escrow accounts' rent; the bid itself always comes from
+        // the bidder.
+        let payer_info = next_account_info(account_info_iter)?;
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mint_account_info = next_account_info(account_info_iter)?;
+        if !(mint_account_info.owner.eq(&spl_token::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let escrow_state_account_info = next_account_info(account_info_iter)?;
+        let escrow_vault_account_info = next_account_info(account_info_iter)?;
+
+        let program_info = next_account_info(account_info_iter)?;
+        if !(program_info.key.eq(program_id)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        
+        let system_program_info = next_account_info(account_info_iter)?;
+        if !(system_program_info.key.eq(&system_program::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let rent_account_info = next_account_info(account_info_iter)?;
+        Self::check_rent_sysvar(rent_account_info)?;
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if state_info.is_paused(state::PAUSE_BID) {
+            return Err(NFTError::InstructionPaused.into());
+        }
+        attestation::check_attested(attestation_accounts, &state_info, initializer_info.key, program_id)?;
+        if amount < state_info.min_bid_lamports {
+            return Err(NFTError::BelowMinimumPrice.into());
+        }
+        let taker_fee_bps = Self::discounted_fee_bps(state_info.taker_fee_bps, initializer_info.key, &fee_discount, &discount_holders);
+        let taker_fee = Self::calculate_fee(amount, taker_fee_bps)?;
+        let escrow_amount = amount
+            .checked_add(taker_fee)
+            .ok_or(NFTError::NumericalOverflow)?;
+        let slot = Clock::get()?.slot;
+        let withdrawable_slot = slot
+            .checked_add(state_info.min_bid_lifetime_slots)
+            .ok_or(NFTError::NumericalOverflow)?;
+
+        let wrapped_accounts = if wrapped {
+            let token_program_info = next_account_info(account_info_iter)?;
+            if !(spl_token::id().eq(token_program_info.key)) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let native_mint_info = next_account_info(account_info_iter)?;
+            if !(spl_token::native_mint::id().eq(native_mint_info.key)) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            Some((token_program_info, native_mint_info))
+        } else {
+            None
+        };
+
+        let mut auction_listing = Pubkey::default();
+        if auction {
+            let list_state_account_info = next_account_info(account_info_iter)?;
+            Self::assert_owned_by(list_state_account_info, program_id)?;
+            let mut list_state = ListEscrowState::unpack_unchecked(&list_state_account_info.data.borrow())?;
+            let (list_state_pubkey, _) = Pubkey::find_program_address(&[
+                mint_account_info.key.as_ref(),
+                list_state.lister.as_ref(),
+                b"List",
+                b"State"
+                ],
+                program_id
+            );
+            if !(list_state_account_info.key.eq(&list_state_pubkey)) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            if !list_state.auction || list_state.success {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            if list_state.is_pending() {
+                return Err(NFTError::ListingPendingApproval.into());
+            }
+            if !list_state.allows_buyer_with_proof(initializer_info.key, proof) {
+                return Err(NFTError::BuyerNotAllowed.into());
+            }
+            if !list_state.has_started(Clock::get()?.unix_timestamp) {
+                return Err(NFTError::ListingNotStarted.into());
+            }
+            if amount < list_state.amount {
+                return Err(NFTError::BidTooLow.into());
+            }
+
+            if list_state.highest_bidder != Pubkey::default() {
+                let leader_info = next_account_info(account_info_iter)?;
+                if !list_state.highest_bidder.eq(leader_info.key) {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                let leader_rent_payer_info = next_account_info(account_info_iter)?;
+                let leader_bid_state_account_info = next_account_info(account_info_iter)?;
+                let leader_bid_vault_account_info = next_account_info(account_info_iter)?;
+                let (leader_bid_state_pubkey, _) = Pubkey::find_program_address(&[
+                    mint_account_info.key.as_ref(),
+                    leader_info.key.as_ref(),
+                    b"Bid",
+                    b"State"
+                    ],
+                    program_id
+                );
+                if !(leader_bid_state_account_info.key.eq(&leader_bid_state_pubkey)) {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                let (leader_bid_vault_pubkey, _) = Pubkey::find_program_address(&[
+                    mint_account_info.key.as_ref(),
+                    leader_info.key.as_ref(),
+                    b"Bid",
+                    b"Vault"
+                    ],
+                    program_id
+                );
+                if !(leader_bid_vault_account_info.key.eq(&leader_bid_vault_pubkey)) {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+
+                // A leader that already withdrew no longer holds the auction.
+                if !leader_bid_state_account_info.data_is_empty() {
+                    if amount <= list_state.highest_bid {
+                        return Err(NFTError::BidTooLow.into());
+                    }
+                    msg!("refunding outbid bidder {}", leader_info.key);
+                    Self::assert_owned_by(leader_bid_state_account_info, program_id)?;
+                    let leader_bid_state = BidEscrowState::unpack(&leader_bid_state_account_info.data.borrow())?;
+                    Self::refund_bid_escrow(
+                        &leader_bid_state,
+                        leader_bid_state_account_info,
+                        leader_bid_vault_account_info,
+                        leader_info,
+                        leader_rent_payer_info,
+                        None,
+                        program_id,
+                    )?;
+                }
+            }
+
+            list_state.highest_bid = amount;
+            list_state.highest_bidder = *initializer_info.key;
+            list_state.record_bid(slot);
+            auction_listing = list_state_pubkey;
+            ListEscrowState::pack(list_state, &mut list_state_account_info.data.borrow_mut())?;
+        }
+
+        let referrer = next_account_info(account_info_iter)
+            .map(|referrer_info| *referrer_info.key)
+            .unwrap_or_default();
+
+        let (escrow_state_account_pubkey, nonce1) = Pubkey::find_program_address(&[
+            mint_account_info.key.as_ref(),
+            initializer_info.key.as_ref(),
+            b"Bid",
+            b"State"
+            ],
+            program_id
+        );
+        if !(escrow_state_account_info.key.eq(&escrow_state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !escrow_state_account_info.data_is_empty() {
+            return Err(NFTError::BidAlreadyExists.into());
+        }
+        let rent = &Rent::get()?;
+        let required_balance = rent.minimum_balance(state::BIDESCROWSTATE);
+        let create_state_account_ix = system_instruction::create_account(payer_info.key, &escrow_state_account_pubkey, required_balance, state::BIDESCROWSTATE as u64, program_id);
+        msg!("submitting tx to create program derived state account");
+        invoke_signed(
+            &create_state_account_ix,
+            &[
+                payer_info.clone(),
+                escrow_state_account_info.clone(),
+                system_program_info.clone(),
+                program_info.clone(),
+            ],
+            &[&[
+                mint_account_info.key.as_ref(),
                 initializer_info.key.as_ref(),
-                &b"List"[..],
-                &b"Vault"[..],
-                &[nonce2]
+                &b"Bid"[..],
+                &b"State"[..],
+                &[nonce1]
             ]],
         )?;
-        msg!("initialized vault account");
+        msg!("state account pubkey: {}", escrow_state_account_pubkey);
+
+        let (escrow_vault_account_pubkey, nonce2) = Pubkey::find_program_address(&[
+            mint_account_info.key.as_ref(),
+            initializer_info.key.as_ref(),
+            b"Bid",
+            b"Vault"
+            ],
+            program_id
+        );
+        if !(escrow_vault_account_info.key.eq(&escrow_vault_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        msg!("amount is {}, taker fee is {}", amount, taker_fee);
+        if let Some((token_program_info, native_mint_info)) = wrapped_accounts {
+            let required_balance = rent.minimum_balance(spl_token::state::Account::LEN);
+            let create_vault_account_ix = system_instruction::create_account(payer_info.key, &escrow_vault_account_pubkey, required_balance, spl_token::state::Account::LEN as u64, &spl_token::id());
+            msg!("submitting tx to create program derived wrapped vault account");
+            invoke_signed(
+                &create_vault_account_ix,
+                &[
+                    payer_info.clone(),
+                    escrow_vault_account_info.clone(),
+                    system_program_info.clone(),
+                    token_program_info.clone(),
+                ],
+                &[&[
+                    mint_account_info.key.as_ref(),
+                    initializer_info.key.as_ref(),
+                    &b"Bid"[..],
+                    &b"Vault"[..],
+                    &[nonce2]
+                ]],
+            )?;
+            // A native token account's balance is its lamports above the rent
+            // reserve, so funding it before initializing wraps the escrow
+            // amount.
+            let transfer_lamports_ix = system_instruction::transfer(authority_info.key, &escrow_vault_account_pubkey, escrow_amount);
+            invoke(
+                &transfer_lamports_ix,
+                &[
+                    authority_info.clone(),
+                    escrow_vault_account_info.clone(),
+                ]
+            )?;
+            msg!("vault account pubkey: {}", escrow_vault_account_pubkey);
+
+            let initialize_vault_account_ix = spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &escrow_vault_account_pubkey,
+                native_mint_info.key,
+                &escrow_state_account_pubkey
+            )?;
+            invoke(
+                &initialize_vault_account_ix,
+                &[
+                    token_program_info.clone(),
+                    escrow_vault_account_info.clone(),
+                    native_mint_info.clone(),
+                    escrow_state_account_info.clone(),
+                    rent_account_info.clone()
+                ],
+            )?;
+            msg!("initialized wrapped vault account");
+        } else {
+            let required_balance = rent.minimum_balance(0);
+            let create_vault_account_ix = system_instruction::create_account(payer_info.key, &escrow_vault_account_pubkey, required_balance, 0, program_id);
+            msg!("submitting tx to create program derived vault account");
+            invoke_signed(
+                &create_vault_account_ix,
+                &[
+                    payer_info.clone(),
+                    escrow_vault_account_info.clone(),
+                    system_program_info.clone(),
+                    program_info.clone(),
+                ],
+                &[&[
+                    mint_account_info.key.as_ref(),
+                    initializer_info.key.as_ref(),
+                    &b"Bid"[..],
+                    &b"Vault"[..],
+                    &[nonce2]
+                ]],
+            )?;
+            msg!("vault account pubkey: {}", escrow_vault_account_pubkey);
+
+            let transfer_lamports_ix = system_instruction::transfer(authority_info.key, &escrow_vault_account_pubkey, escrow_amount);
+            invoke(
+                &transfer_lamports_ix,
+                &[
+                    authority_info.clone(),
+                    escrow_vault_account_info.clone(),
+                ]
+            )?;
+        }
+
+        BidEscrowState::pack(
+            BidEscrowState{
+                layout_version: state::LAYOUT_VERSION,
+                is_initialized: true,
+                bidder: *initializer_info.key,
+                amount: amount,
+                mint: *mint_account_info.key,
+                referrer: referrer,
+                taker_fee: taker_fee,
+                wrapped: wrapped,
+                payer: *payer_info.key,
+                withdrawable_slot: withdrawable_slot,
+                state_bump: nonce1,
+                vault_bump: nonce2,
+                created_at_slot: slot,
+                updated_at_slot: slot,
+                auction_listing,
+                marketplace,
+                expires_at: 0,
+                reserved: Default::default(),
+            },
+            &mut escrow_state_account_info.data.borrow_mut()
+        )?;
+
+        match sale_accounts {
+            [] => {}
+            [lister_info, _, _, bidder_info, _, bid_state_account_info, _, list_state_account_info, ..] => {
+                // Only the bid just placed may be settled against the listing.
+                if !bidder_info.key.eq(initializer_info.key) || !bid_state_account_info.key.eq(escrow_state_account_info.key) {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                let matches = Self::listing_matches(list_state_account_info, mint_account_info.key, lister_info.key, initializer_info.key, amount, proof, 0, None, program_id)?;
+                if list_state_account_info.owner.eq(program_id) && !list_state_account_info.data_is_empty() {
+                    let mut list_state = ListEscrowState::unpack(&list_state_account_info.data.borrow())?;
+                    list_state.record_bid(slot);
+                    ListEscrowState::pack(list_state, &mut list_state_account_info.data.borrow_mut())?;
+                }
+                if matches {
+                    msg!("bid meets the ask, settling against the listing");
+                    Self::settle_bid(sale_accounts, true, false, &[], proof, &fee_discount, &discount_holders, None, &Bumps::default(), program_id)?;
+                    if tip_lamports > 0 {
+                        Self::tip_creator(authority_info, sale_accounts, mint_account_info.key, tip_lamports, system_program_info)?;
+                    }
+                }
+            }
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
+        }
+
+        Ok(())
+    }
+
+    /// Sends `tip_lamports` from `buyer_info` to the first verified creator
+    /// of `mint`, whose metadata and account are in the settled
+    /// `sale_accounts`, and records the tip after the sale's memo.
+    fn tip_creator<'a>(
+        buyer_info: &AccountInfo<'a>,
+        sale_accounts: &[AccountInfo<'a>],
+        mint: &Pubkey,
+        tip_lamports: u64,
+        system_program_info: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let memo_program_info = sale_accounts.get(12).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if !(memo::spl_memo_program::id().eq(memo_program_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let (metadata_pubkey, _) = metadata::find_metadata_address(mint);
+        let metadata_account_info = sale_accounts
+            .iter()
+            .find(|account_info| account_info.key.eq(&metadata_pubkey))
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let creator = metadata::load_metadata(metadata_account_info, mint)?
+            .and_then(|metadata| metadata.verified_creators().first().map(|creator| Pubkey::new_from_array(creator.address)))
+            .ok_or(NFTError::NoCreatorToTip)?;
+        let creator_account_info = sale_accounts
+            .iter()
+            .find(|account_info| account_info.key.eq(&creator))
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        invoke(
+            &system_instruction::transfer(buyer_info.key, creator_account_info.key, tip_lamports),
+            &[
+                buyer_info.clone(),
+                creator_account_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+        memo::record_tip(memo_program_info, mint, tip_lamports)
+    }
+
+    /// Whether `lister`'s listing of `mint` exists, sells at a fixed price
+    /// to `bidder`, with `proof` of a place on its allowlist, right now,
+    /// and asks no more than `amount`. A listing priced in USD only
+    /// matches given the platform's price feed, which converts its ask to
+    /// lamports.
+    /// Listings with delayed settlement never match; their lister accepts.
+    #[allow(clippy::too_many_arguments)]
+    fn listing_matches(
+        list_state_account_info: &AccountInfo,
+        mint: &Pubkey,
+        lister: &Pubkey,
+        bidder: &Pubkey,
+        amount: u64,
+        proof: &[[u8; 32]],
+        list_state_bump: u8,
+        price_feed_info: Option<&AccountInfo>,
+        program_id: &Pubkey,
+    ) -> Result<bool, ProgramError> {
+        Self::check_program_address(
+            list_state_account_info,
+            &[mint.as_ref(), lister.as_ref(), b"List", b"State"],
+            list_state_bump,
+            program_id,
+        )?;
+        if !(list_state_account_info.owner.eq(program_id)) || list_state_account_info.data_is_empty() {
+            return Ok(false);
+        }
+        let list_state = ListEscrowState::unpack(&list_state_account_info.data.borrow())?;
+        let ask = if list_state.is_usd_priced() {
+            match price_feed_info.filter(|info| info.key.eq(&list_state.price_feed)) {
+                Some(price_feed_info) => Self::usd_ask(price_feed_info, list_state.price_usd)?,
+                None => return Ok(false),
+            }
+        } else {
+            list_state.amount
+        };
+        Ok(list_state.is_fillable()
+            && list_state.allows_buyer_with_proof(bidder, proof)
+            && list_state.has_started(Clock::get()?.unix_timestamp)
+            && amount >= ask)
+    }
+
+    /// Lamports `price_usd` is worth at the feed's current price, which has
+    /// to pass `oracle::Price::check_reliable`.
+    fn usd_ask(price_feed_info: &AccountInfo, price_usd: u64) -> Result<u64, ProgramError> {
+        let price = oracle::Price::unpack(&price_feed_info.data.borrow())?;
+        price.check_reliable(Clock::get()?.slot)?;
+        price.usd_to_lamports(price_usd)
+    }
+
+    fn process_withdraw_bid(
+        accounts: &[AccountInfo],
+        bumps: &Bumps,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let signer_info = next_account_info(account_info_iter)?;
+        if !signer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let rent_payer_info = next_account_info(account_info_iter)?;
+
+        let mint_account_info = next_account_info(account_info_iter)?;
+        if !(mint_account_info.owner.eq(&spl_token::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let escrow_state_account_info = next_account_info(account_info_iter)?;
+        let escrow_vault_account_info = next_account_info(account_info_iter)?;
+
+        let program_info = next_account_info(account_info_iter)?;
+        if !(program_info.key.eq(program_id)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Self::assert_owned_by(escrow_state_account_info, program_id)?;
+        let bid_state = BidEscrowState::unpack(&escrow_state_account_info.data.borrow())?;
+        let bid_state_bump = Self::check_program_address(escrow_state_account_info, &[
+            mint_account_info.key.as_ref(),
+            signer_info.key.as_ref(),
+            b"Bid",
+            b"State"
+            ],
+            Self::escrow_bump(bid_state.state_bump, bumps.bid_state),
+            program_id
+        )?;
 
-        let transfer_token_ix = spl_token::instruction::transfer_checked(
-            &spl_token::id(),
-            token_account_info.key, 
-            mint_account_info.key, 
-            &escrow_vault_account_pubkey, 
-            initializer_info.key,
-            &[
-                initializer_info.key
-            ], 
-            1, 
-            0
+        Self::check_program_address(escrow_vault_account_info, &[
+            mint_account_info.key.as_ref(),
+            signer_info.key.as_ref(),
+            b"Bid",
+            b"Vault"
+            ],
+            Self::escrow_bump(bid_state.vault_bump, bumps.bid_vault),
+            program_id
+        )?;
+
+        if Clock::get()?.slot < bid_state.withdrawable_slot {
+            return Err(NFTError::BidCooldownActive.into());
+        }
+
+        let token_program_info = next_account_info(account_info_iter)?;
+        if !(spl_token::id().eq(token_program_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::unwrap_bid_vault(
+            token_program_info,
+            escrow_vault_account_info,
+            escrow_state_account_info,
+            mint_account_info.key,
+            signer_info.key,
+            bid_state_bump,
+        )?;
+
+        Self::refund_bid_escrow(
+            &bid_state,
+            escrow_state_account_info,
+            escrow_vault_account_info,
+            signer_info,
+            rent_payer_info,
+            None,
+            program_id,
+        )?;
+
+        Ok(())
+    }
+
+    /// Sets when the signer's bid on a mint expires, letting anyone refund
+    /// it from then on; zero takes the expiry off.
+    fn process_set_bid_expiry(
+        accounts: &[AccountInfo],
+        expires_at: i64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bidder_info = next_account_info(account_info_iter)?;
+        if !bidder_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let mint_account_info = next_account_info(account_info_iter)?;
+        let bid_state_account_info = next_account_info(account_info_iter)?;
+        Self::assert_owned_by(bid_state_account_info, program_id)?;
+        let mut bid_state = BidEscrowState::unpack(&bid_state_account_info.data.borrow())?;
+        Self::check_program_address(bid_state_account_info, &[
+            mint_account_info.key.as_ref(),
+            bidder_info.key.as_ref(),
+            b"Bid",
+            b"State"
+            ],
+            bid_state.state_bump,
+            program_id
+        )?;
+        if !bid_state.bidder.eq(bidder_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        bid_state.expires_at = expires_at;
+        bid_state.updated_at_slot = Clock::get()?.slot;
+        BidEscrowState::pack(bid_state, &mut bid_state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Settles a bid against the signer's listing. With `deliver` set, as
+    /// for `ExecuteSale`, the bidder's token account is required and the
+    /// NFT, payouts and all four escrow accounts are settled together. A
+    /// nonzero `expected_amount` is the least bid the lister signed for, so
+    /// a bid withdrawn and placed again lower in the meantime fails instead
+    /// of selling cheap.
+    fn process_accept_bid(
+        accounts: &[AccountInfo],
+        deliver: bool,
+        client_memo: &[u8],
+        bumps: &Bumps,
+        expected_amount: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let (accounts, price_feed_info) = oracle::split_price_feed(accounts);
+        let (accounts, fee_discount, discount_holders) = Self::split_fee_discounts(accounts, program_id)?;
+        let signer_info = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if !signer_info.is_signer && !(deliver && Self::sale_meets_ask(accounts, bumps, price_feed_info, program_id)?) {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if expected_amount != 0 {
+            // settle_bid checks this is the bid state before paying out.
+            let bid_state_account_info = accounts.get(5).ok_or(ProgramError::NotEnoughAccountKeys)?;
+            Self::assert_owned_by(bid_state_account_info, program_id)?;
+            let bid_state = BidEscrowState::unpack(&bid_state_account_info.data.borrow())?;
+            if bid_state.amount < expected_amount {
+                return Err(NFTError::PriceMismatch.into());
+            }
+        }
+        let list_state_account_info = accounts.get(7).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        Self::assert_owned_by(list_state_account_info, program_id)?;
+        if ListEscrowState::unpack(&list_state_account_info.data.borrow())?.has_delayed_settlement() {
+            return Self::hold_settlement(accounts, bumps, program_id);
+        }
+        Self::settle_bid(accounts, deliver, false, client_memo, &[], &fee_discount, &discount_holders, price_feed_info, bumps, program_id)
+    }
+
+    /// Holds the sale of a listing with delayed settlement to the bid in an
+    /// `AcceptBid` account list, instead of settling it. Nothing moves: the
+    /// NFT stays in the list vault and the payment in the bid vault, with
+    /// the bid locked against withdrawal and refunds, until
+    /// `FinalizeSettlement` or `ReverseSettlement`.
+    fn hold_settlement(accounts: &[AccountInfo], bumps: &Bumps, program_id: &Pubkey) -> ProgramResult {
+        let (lister_info, mint_account_info, bidder_info, bid_state_account_info, list_state_account_info) = match accounts {
+            [lister_info, _, mint_account_info, bidder_info, _, bid_state_account_info, _, list_state_account_info, ..] => {
+                (lister_info, mint_account_info, bidder_info, bid_state_account_info, list_state_account_info)
+            }
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
+        };
+        Self::assert_owned_by(list_state_account_info, program_id)?;
+        let mut list_state = ListEscrowState::unpack(&list_state_account_info.data.borrow())?;
+        Self::check_program_address(
+            list_state_account_info,
+            &[mint_account_info.key.as_ref(), lister_info.key.as_ref(), b"List", b"State"],
+            Self::escrow_bump(list_state.state_bump, bumps.list_state),
+            program_id,
+        )?;
+        Self::assert_owned_by(bid_state_account_info, program_id)?;
+        let mut bid_state = BidEscrowState::unpack(&bid_state_account_info.data.borrow())?;
+        Self::check_program_address(
+            bid_state_account_info,
+            &[mint_account_info.key.as_ref(), bidder_info.key.as_ref(), b"Bid", b"State"],
+            Self::escrow_bump(bid_state.state_bump, bumps.bid_state),
+            program_id,
+        )?;
+        if !list_state.lister.eq(lister_info.key) || !bid_state.bidder.eq(bidder_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if list_state.success || list_state.is_settling() {
+            return Err(NFTError::SettlementPending.into());
+        }
+        if list_state.is_pending() {
+            return Err(NFTError::ListingPendingApproval.into());
+        }
+        if !list_state.allows_buyer(bidder_info.key) {
+            return Err(NFTError::BuyerNotAllowed.into());
+        }
+        let clock = Clock::get()?;
+        if !list_state.has_started(clock.unix_timestamp) {
+            return Err(NFTError::ListingNotStarted.into());
+        }
+
+        list_state.successful_buyer = *bidder_info.key;
+        list_state.settles_at_slot = clock.slot
+            .checked_add(list_state.settlement_delay_slots)
+            .ok_or(NFTError::NumericalOverflow)?;
+        list_state.updated_at_slot = clock.slot;
+        ListEscrowState::pack(list_state, &mut list_state_account_info.data.borrow_mut())?;
+        bid_state.withdrawable_slot = u64::MAX;
+        bid_state.updated_at_slot = clock.slot;
+        BidEscrowState::pack(bid_state, &mut bid_state_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Settles a held sale, taking an `ExecuteSale` account list, once its
+    /// dispute window has closed. Anyone can send it; the lister agreed to
+    /// the sale when accepting the bid.
+    fn process_finalize_settlement(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let (accounts, fee_discount, discount_holders) = Self::split_fee_discounts(accounts, program_id)?;
+        let (bidder_info, bid_state_account_info, list_state_account_info) = match accounts {
+            [_, _, _, bidder_info, _, bid_state_account_info, _, list_state_account_info, ..] => {
+                (bidder_info, bid_state_account_info, list_state_account_info)
+            }
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
+        };
+        Self::assert_owned_by(list_state_account_info, program_id)?;
+        let mut list_state = ListEscrowState::unpack(&list_state_account_info.data.borrow())?;
+        if !list_state.is_settling() || !list_state.successful_buyer.eq(bidder_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if Clock::get()?.slot < list_state.settles_at_slot {
+            return Err(NFTError::SettlementPending.into());
+        }
+        list_state.settles_at_slot = 0;
+        ListEscrowState::pack(list_state, &mut list_state_account_info.data.borrow_mut())?;
+        // settle_bid checks both addresses before paying out.
+        Self::assert_owned_by(bid_state_account_info, program_id)?;
+        let mut bid_state = BidEscrowState::unpack(&bid_state_account_info.data.borrow())?;
+        bid_state.withdrawable_slot = 0;
+        BidEscrowState::pack(bid_state, &mut bid_state_account_info.data.borrow_mut())?;
+
+        Self::settle_bid(accounts, true, true, &[], &[], &fee_discount, &discount_holders, None, &Bumps::default(), program_id)
+    }
+
+    /// Reverses a held sale within its dispute window, on behalf of the
+    /// platform authority or the listing's arbiter: the bid is refunded and
+    /// the listing is open again.
+    fn process_reverse_settlement(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let signer_info = next_account_info(account_info_iter)?;
+        if !signer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let mint_account_info = next_account_info(account_info_iter)?;
+        let bidder_account_info = next_account_info(account_info_iter)?;
+        let rent_payer_info = next_account_info(account_info_iter)?;
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let bid_state_account_info = next_account_info(account_info_iter)?;
+        let bid_vault_account_info = next_account_info(account_info_iter)?;
+        Self::assert_owned_by(bid_state_account_info, program_id)?;
+        let bid_state = BidEscrowState::unpack(&bid_state_account_info.data.borrow())?;
+        let bid_state_bump = Self::check_program_address(bid_state_account_info, &[
+            mint_account_info.key.as_ref(),
+            bidder_account_info.key.as_ref(),
+            b"Bid",
+            b"State"
+            ],
+            bid_state.state_bump,
+            program_id
+        )?;
+        Self::check_program_address(bid_vault_account_info, &[
+            mint_account_info.key.as_ref(),
+            bidder_account_info.key.as_ref(),
+            b"Bid",
+            b"Vault"
+            ],
+            bid_state.vault_bump,
+            program_id
+        )?;
+        if !bid_state.bidder.eq(bidder_account_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let token_program_info = next_account_info(account_info_iter)?;
+        if !(spl_token::id().eq(token_program_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let list_state_account_info = next_account_info(account_info_iter)?;
+        Self::assert_owned_by(list_state_account_info, program_id)?;
+        let mut list_state = ListEscrowState::unpack(&list_state_account_info.data.borrow())?;
+        Self::check_program_address(list_state_account_info, &[
+            mint_account_info.key.as_ref(),
+            list_state.lister.as_ref(),
+            b"List",
+            b"State"
+            ],
+            list_state.state_bump,
+            program_id
+        )?;
+        let is_arbiter = list_state.arbiter != Pubkey::default() && list_state.arbiter.eq(signer_info.key);
+        if !is_arbiter && !state_info.authority.eq(signer_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+        if !list_state.is_settling() || !list_state.successful_buyer.eq(bidder_account_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let slot = Clock::get()?.slot;
+        if slot >= list_state.settles_at_slot {
+            return Err(NFTError::DisputeWindowClosed.into());
+        }
+
+        list_state.successful_buyer = Pubkey::default();
+        list_state.settles_at_slot = 0;
+        list_state.updated_at_slot = slot;
+        ListEscrowState::pack(list_state, &mut list_state_account_info.data.borrow_mut())?;
+
+        Self::unwrap_bid_vault(
+            token_program_info,
+            bid_vault_account_info,
+            bid_state_account_info,
+            mint_account_info.key,
+            bidder_account_info.key,
+            bid_state_bump,
+        )?;
+        Self::refund_bid_escrow(
+            &bid_state,
+            bid_state_account_info,
+            bid_vault_account_info,
+            bidder_account_info,
+            rent_payer_info,
+            None,
+            program_id,
+        )?;
+
+        Ok(())
+    }
+
+    /// Whether the bid in an `ExecuteSale` account list meets the listing's
+    /// ask. Such a sale needs no lister signature, so aggregators and other
+    /// programs can cross standing orders by CPI the way Auction House
+    /// executes matching trade states. A listing priced in USD is converted
+    /// at `price_feed_info`'s price, if that is the feed it was listed under.
+    fn sale_meets_ask(
+        accounts: &[AccountInfo],
+        bumps: &Bumps,
+        price_feed_info: Option<&AccountInfo>,
+        program_id: &Pubkey,
+    ) -> Result<bool, ProgramError> {
+        match accounts {
+            [lister_info, _, mint_account_info, bidder_info, _, bid_state_account_info, _, list_state_account_info, ..] => {
+                Self::check_program_address(
+                    bid_state_account_info,
+                    &[mint_account_info.key.as_ref(), bidder_info.key.as_ref(), b"Bid", b"State"],
+                    bumps.bid_state,
+                    program_id,
+                )?;
+                Self::assert_owned_by(bid_state_account_info, program_id)?;
+                let bid_state = BidEscrowState::unpack(&bid_state_account_info.data.borrow())?;
+                Self::listing_matches(list_state_account_info, mint_account_info.key, lister_info.key, bidder_info.key, bid_state.amount, &[], bumps.list_state, price_feed_info, program_id)
+            }
+            _ => Err(ProgramError::NotEnoughAccountKeys),
+        }
+    }
+
+    /// Settles a bid against a listing, taking an `AcceptBid` account list,
+    /// and records the sale and `client_memo` with the Memo program. The
+    /// caller vouches for the lister: `AcceptBid` by its signature, an
+    /// auto-matched `Bid` by the listing's own asking price, which is
+    /// checked here again for any sale the lister did not sign. `held`
+    /// marks a sale the lister accepted before its dispute window. `proof`
+    /// puts the bidder on a fixed-price listing's allowlist; an auction's
+    /// highest bidder showed theirs when bidding.
+    #[allow(clippy::too_many_arguments)]
+    fn settle_bid(
+        accounts: &[AccountInfo],
+        deliver: bool,
+        held: bool,
+        client_memo: &[u8],
+        proof: &[[u8; 32]],
+        fee_discount: &FeeDiscount,
+        discount_holders: &[Pubkey],
+        price_feed_info: Option<&AccountInfo>,
+        bumps: &Bumps,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let (accounts, leaderboard_account_info) = leaderboard::split_leaderboard(accounts, program_id);
+        let (accounts, rewards_accounts) = rewards::split_rewards(accounts, program_id);
+        let account_info_iter = &mut accounts.iter();
+        let lister_info = next_account_info(account_info_iter)?;
+        let list_rent_payer_info = next_account_info(account_info_iter)?;
+
+        let mint_account_info = next_account_info(account_info_iter)?;
+        if !(mint_account_info.owner.eq(&spl_token::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let bidder_account_info = next_account_info(account_info_iter)?;
+        let bid_rent_payer_info = next_account_info(account_info_iter)?;
+
+        let escrow_bid_state_account_info = next_account_info(account_info_iter)?;
+        let escrow_bid_vault_account_info = next_account_info(account_info_iter)?;
+        Self::assert_owned_by(escrow_bid_state_account_info, program_id)?;
+        let bid_state = BidEscrowState::unpack(&escrow_bid_state_account_info.data.borrow())?;
+        let bid_state_bump = Self::check_program_address(escrow_bid_state_account_info, &[
+            mint_account_info.key.as_ref(),
+            bidder_account_info.key.as_ref(),
+            b"Bid",
+            b"State"
+            ],
+            Self::escrow_bump(bid_state.state_bump, bumps.bid_state),
+            program_id
+        )?;
+        Self::check_program_address(escrow_bid_vault_account_info, &[
+            mint_account_info.key.as_ref(),
+            bidder_account_info.key.as_ref(),
+            b"Bid",
+            b"Vault"
+            ],
+            Self::escrow_bump(bid_state.vault_bump, bumps.bid_vault),
+            program_id
+        )?;
+
+        let escrow_list_state_account_info = next_account_info(account_info_iter)?;
+        let escrow_list_vault_account_info = next_account_info(account_info_iter)?;
+        Self::assert_owned_by(escrow_list_state_account_info, program_id)?;
+        let mut list_state = ListEscrowState::unpack(&escrow_list_state_account_info.data.borrow())?;
+        let list_state_bump = Self::check_program_address(escrow_list_state_account_info, &[
+            mint_account_info.key.as_ref(),
+            lister_info.key.as_ref(),
+            b"List",
+            b"State"
+            ],
+            Self::escrow_bump(list_state.state_bump, bumps.list_state),
+            program_id
+        )?;
+        let escrow_list_state_account_pubkey = *escrow_list_state_account_info.key;
+        Self::check_list_vault(
+            escrow_list_vault_account_info,
+            &escrow_list_state_account_pubkey,
+            mint_account_info.key,
+            Self::escrow_bump(list_state.vault_bump, bumps.list_vault),
         )?;
 
-        invoke(
-            &transfer_token_ix,
-            &[
-                token_program_info.clone(),
-                token_account_info.clone(),
-                mint_account_info.clone(),
-                escrow_vault_account_info.clone(),
-                initializer_info.clone()
-            ],
+        if !list_state.lister.eq(lister_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if !bid_state.bidder.eq(bidder_account_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !list_state.payer.eq(list_rent_payer_info.key) || !bid_state.payer.eq(bid_rent_payer_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if list_state.success {
+            return Err(NFTError::SettlementPending.into());
+        }
+        if list_state.auction && !list_state.highest_bidder.eq(bidder_account_info.key) {
+            return Err(NFTError::NotHighestBid.into());
+        }
+        // Gifts are claimed, never sold.
+        if list_state.is_gift() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if list_state.is_pending() {
+            return Err(NFTError::ListingPendingApproval.into());
+        }
+        let vetted = list_state.auction && list_state.has_allowlist();
+        if !vetted && !list_state.allows_buyer_with_proof(bidder_account_info.key, proof) {
+            return Err(NFTError::BuyerNotAllowed.into());
+        }
+        if !list_state.has_started(Clock::get()?.unix_timestamp) {
+            return Err(NFTError::ListingNotStarted.into());
+        }
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        Self::check_program_address(state_account_info, &[b"Platform", b"State"], bumps.platform_state, program_id)?;
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if state_info.is_paused(state::PAUSE_SALE) {
+            return Err(NFTError::InstructionPaused.into());
+        }
+        if !lister_info.is_signer && !held {
+            let ask = if list_state.is_usd_priced() {
+                match price_feed_info.filter(|info| info.key.eq(&list_state.price_feed)) {
+                    Some(price_feed_info) => Self::usd_ask(price_feed_info, list_state.price_usd)?,
+                    None => return Err(NFTError::PriceFeedNotSet.into()),
+                }
+            } else {
+                list_state.amount
+            };
+            if bid_state.amount < ask {
+                return Err(NFTError::BidTooLow.into());
+            }
+        }
+
+        let fee_recipient_info = next_account_info(account_info_iter)?;
+        if !state_info.fee_recipient.eq(fee_recipient_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
+
+        let token_program_info = next_account_info(account_info_iter)?;
+        if !(spl_token::id().eq(token_program_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let memo_program_info = next_account_info(account_info_iter)?;
+        if !(memo::spl_memo_program::id().eq(memo_program_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let referrer_info = if bid_state.referrer == Pubkey::default() {
+            None
+        } else {
+            let referrer_info = next_account_info(account_info_iter)?;
+            if !bid_state.referrer.eq(referrer_info.key) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            Some(referrer_info)
+        };
+
+        let maker_fee_bps = Self::discounted_fee_bps(state_info.maker_fee_bps, lister_info.key, fee_discount, discount_holders);
+        let maker_fee = Self::calculate_fee(bid_state.amount, maker_fee_bps)?;
+        let platform_fee = maker_fee
+            .checked_add(bid_state.taker_fee)
+            .ok_or(NFTError::NumericalOverflow)?;
+        let referral_fee = match referrer_info {
+            Some(_) => Self::calculate_fee(platform_fee, state_info.referral_fee_bps)?,
+            None => 0,
+        };
+
+        let curator_info = if list_state.curator == Pubkey::default() {
+            None
+        } else {
+            let curator_info = next_account_info(account_info_iter)?;
+            if !list_state.curator.eq(curator_info.key) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            Some(curator_info)
+        };
+        let curator_fee = match curator_info {
+            Some(_) => {
+                let fee_after_referral = platform_fee
+                    .checked_sub(referral_fee)
+                    .ok_or(NFTError::NumericalOverflow)?;
+                Self::calculate_fee(fee_after_referral, state_info.curator_fee_bps)?
+            }
+            None => 0,
+        };
+
+        let charity_info = if list_state.has_charity() {
+            let charity_info = next_account_info(account_info_iter)?;
+            if !list_state.charity.eq(charity_info.key) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            Some(charity_info)
+        } else {
+            None
+        };
+
+        // The marketplaces credited with the listing and the bid, each paid
+        // a share of what is left of the platform fee.
+        let fee_after_curator = platform_fee
+            .checked_sub(referral_fee)
+            .and_then(|fee| fee.checked_sub(curator_fee))
+            .ok_or(NFTError::NumericalOverflow)?;
+        let marketplace_fee = Self::calculate_fee(fee_after_curator, state_info.marketplace_fee_bps)?;
+        let mut marketplace_infos = Vec::with_capacity(2);
+        for marketplace in [list_state.marketplace, bid_state.marketplace].iter() {
+            if *marketplace == Pubkey::default() {
+                continue;
+            }
+            let marketplace_info = next_account_info(account_info_iter)?;
+            if !marketplace.eq(marketplace_info.key) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            marketplace_infos.push(marketplace_info);
+        }
+
+        let metadata_account_info = next_account_info(account_info_iter)?;
+        let metadata = metadata::load_metadata(metadata_account_info, mint_account_info.key)?;
+        let royalty = match &metadata {
+            Some(metadata) => {
+                let royalty_bps = list_state.effective_royalty_bps(metadata.data.seller_fee_basis_points);
+                Self::calculate_fee(bid_state.amount, royalty_bps as u64)?
+            }
+            None => 0,
+        };
+        let royalty_payouts = Self::royalty_payouts(account_info_iter, metadata.as_ref(), royalty)?;
+        let royalty_paid = royalty_payouts
+            .iter()
+            .try_fold(0u64, |total, (_, creator_royalty)| total.checked_add(*creator_royalty))
+            .ok_or(NFTError::NumericalOverflow)?;
+
+        let proceeds = bid_state.amount
+            .checked_sub(maker_fee)
+            .and_then(|l| l.checked_sub(royalty_paid))
+            .ok_or(NFTError::NumericalOverflow)?;
+        let charity_share = match charity_info {
+            Some(_) => Self::calculate_fee(proceeds, list_state.charity_bps as u64)?,
+            None => 0,
+        };
+        let seller_proceeds = proceeds
+            .checked_sub(charity_share)
+            .ok_or(NFTError::NumericalOverflow)?;
+
+        let buyer_stats_account_info = next_account_info(account_info_iter)?;
+        let seller_stats_account_info = next_account_info(account_info_iter)?;
+
+        let collection = metadata.as_ref().and_then(|metadata| metadata.verified_collection());
+        let (remaining_accounts, collection_stats_account_info) = match (collection, account_info_iter.as_slice()) {
+            (Some(collection), [rest @ .., last])
+                if last.key.eq(&state::find_collection_stats_address(program_id, &collection).0) => (rest, Some(last)),
+            (_, rest) => (rest, None),
+        };
+
+        // With the bidder's token account the sale settles here; without it
+        // the bidder still collects through WithdrawNFTOnSuccess.
+        let bidder_token_account_info = match remaining_accounts.first() {
+            Some(token_account_info) => {
+                if !(token_account_info.owner.eq(&spl_token::id())) {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                let token_account = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+                if !(token_account.owner.eq(bidder_account_info.key) && token_account.mint.eq(mint_account_info.key)) {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                Some(token_account_info)
+            }
+            None if deliver => return Err(ProgramError::NotEnoughAccountKeys),
+            None => None,
+        };
+
+        let slot = Clock::get()?.slot;
+        list_state.amount = bid_state.amount;
+        list_state.success = true;
+        list_state.successful_buyer = *bidder_account_info.key;
+        list_state.updated_at_slot = slot;
+
+        ListEscrowState::pack(
+            list_state,
+            &mut escrow_list_state_account_info.data.borrow_mut()
         )?;
 
-        ListEscrowState::pack(
-            ListEscrowState{
-                lister: *initializer_info.key,
-                amount: amount,
-                mint: *mint_account_info.key,
-                success: false,
-                successful_buyer: Pubkey::new_from_array([0; 32])
-            },
-            &mut escrow_state_account_info.data.borrow_mut()
+        state_info.sale_count = state_info.sale_count
+            .checked_add(1)
+            .ok_or(NFTError::NumericalOverflow)?;
+        state_info.total_volume = state_info.total_volume
+            .checked_add(bid_state.amount)
+            .ok_or(NFTError::NumericalOverflow)?;
+        state_info.total_fees = state_info.total_fees
+            .checked_add(platform_fee)
+            .ok_or(NFTError::NumericalOverflow)?;
+        state_info.active_listings = state_info.active_listings.saturating_sub(1);
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        Self::unwrap_bid_vault(
+            token_program_info,
+            escrow_bid_vault_account_info,
+            escrow_bid_state_account_info,
+            mint_account_info.key,
+            bidder_account_info.key,
+            bid_state_bump,
         )?;
+        if let Some(bidder_token_account_info) = bidder_token_account_info {
+            Self::release_list_vault(
+                token_program_info,
+                escrow_list_vault_account_info,
+                escrow_list_state_account_info,
+                mint_account_info,
+                bidder_token_account_info,
+                lister_info.key,
+                list_state_bump,
+            )?;
+        }
+        let total_lamports = escrow_bid_vault_account_info.lamports()
+            .checked_add(escrow_bid_state_account_info.lamports())
+            .ok_or(NFTError::NumericalOverflow)?;
+        let rent_refund = total_lamports
+            .checked_sub(bid_state.amount)
+            .and_then(|l| l.checked_sub(bid_state.taker_fee))
+            .ok_or(NFTError::NumericalOverflow)?;
+        Self::check_bid_vault_owner(&bid_state, escrow_bid_vault_account_info, program_id)?;
+        **escrow_bid_state_account_info.try_borrow_mut_lamports()? = 0;
+        **escrow_bid_vault_account_info.try_borrow_mut_lamports()? = 0;
+        Self::wipe_account(escrow_bid_state_account_info, program_id)?;
+        Self::wipe_account(escrow_bid_vault_account_info, program_id)?;
+        let lister_lamports = lister_info.lamports()
+            .checked_add(seller_proceeds)
+            .ok_or(NFTError::NumericalOverflow)?;
+        **lister_info.try_borrow_mut_lamports()? = lister_lamports;
+        let bid_rent_payer_lamports = bid_rent_payer_info.lamports()
+            .checked_add(rent_refund)
+            .ok_or(NFTError::NumericalOverflow)?;
+        **bid_rent_payer_info.try_borrow_mut_lamports()? = bid_rent_payer_lamports;
+        let marketplace_fees = marketplace_fee
+            .checked_mul(marketplace_infos.len() as u64)
+            .ok_or(NFTError::NumericalOverflow)?;
+        let fee_recipient_lamports = fee_after_curator
+            .checked_sub(marketplace_fees)
+            .and_then(|fee| fee_recipient_info.lamports().checked_add(fee))
+            .ok_or(NFTError::NumericalOverflow)?;
+        **fee_recipient_info.try_borrow_mut_lamports()? = fee_recipient_lamports;
+        if let Some(referrer_info) = referrer_info {
+            let referrer_lamports = referrer_info.lamports()
+                .checked_add(referral_fee)
+                .ok_or(NFTError::NumericalOverflow)?;
+            **referrer_info.try_borrow_mut_lamports()? = referrer_lamports;
+        }
+        if let Some(curator_info) = curator_info {
+            let curator_lamports = curator_info.lamports()
+                .checked_add(curator_fee)
+                .ok_or(NFTError::NumericalOverflow)?;
+            **curator_info.try_borrow_mut_lamports()? = curator_lamports;
+        }
+        if let Some(charity_info) = charity_info {
+            let charity_lamports = charity_info.lamports()
+                .checked_add(charity_share)
+                .ok_or(NFTError::NumericalOverflow)?;
+            **charity_info.try_borrow_mut_lamports()? = charity_lamports;
+        }
+        for marketplace_info in marketplace_infos.iter() {
+            let marketplace_lamports = marketplace_info.lamports()
+                .checked_add(marketplace_fee)
+                .ok_or(NFTError::NumericalOverflow)?;
+            **marketplace_info.try_borrow_mut_lamports()? = marketplace_lamports;
+        }
+        for (creator_account_info, creator_royalty) in royalty_payouts {
+            let creator_lamports = creator_account_info.lamports()
+                .checked_add(creator_royalty)
+                .ok_or(NFTError::NumericalOverflow)?;
+            **creator_account_info.try_borrow_mut_lamports()? = creator_lamports;
+        }
+
+        if bidder_token_account_info.is_some() {
+            Self::close_account(escrow_list_state_account_info, list_rent_payer_info, program_id)?;
+        }
+
+        Self::record_user_trade(buyer_stats_account_info, bidder_account_info.key, bid_state.amount, true, slot, program_id)?;
+        Self::record_user_trade(seller_stats_account_info, lister_info.key, bid_state.amount, false, slot, program_id)?;
+        if let (Some(collection), Some(collection_stats_account_info)) = (collection, collection_stats_account_info) {
+            Self::update_collection_stats(collection_stats_account_info, &collection, program_id, |stats| {
+                stats.last_sale_price = bid_state.amount;
+                stats.total_volume = stats.total_volume
+                    .checked_add(bid_state.amount)
+                    .ok_or(NFTError::NumericalOverflow)?;
+                stats.sale_count = stats.sale_count
+                    .checked_add(1)
+                    .ok_or(NFTError::NumericalOverflow)?;
+                stats.remove_listing(&escrow_list_state_account_pubkey);
+                stats.observe_floor(bid_state.amount, slot);
+                Ok(())
+            })?;
+        }
+
+        if let Some(rewards_accounts) = rewards_accounts {
+            rewards::mint_trade_rewards(rewards_accounts, token_program_info, bidder_account_info.key, lister_info.key, program_id)?;
+        }
+        if let Some(leaderboard_account_info) = leaderboard_account_info {
+            leaderboard::record_trade(leaderboard_account_info, bidder_account_info.key, lister_info.key, bid_state.amount, program_id)?;
+        }
+
+        let charity_payout = charity_info.map(|charity_info| (charity_info.key, charity_share));
+        let marketplace_payouts: Vec<(&Pubkey, u64)> = marketplace_infos
+            .iter()
+            .map(|marketplace_info| (marketplace_info.key, marketplace_fee))
+            .collect();
+        memo::record_sale(memo_program_info, mint_account_info.key, bid_state.amount, charity_payout, &marketplace_payouts, client_memo)?;
 
         Ok(())
     }
 
-    fn process_delist(
+    /// Settles several bids at once. `account_counts` splits the accounts
+    /// into consecutive `AcceptBid` account lists, each handled in turn.
+    fn process_batch_execute_sale(
+        accounts: &[AccountInfo],
+        account_counts: Vec<u8>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let mut rest = accounts;
+        for count in account_counts {
+            if rest.len() < count as usize {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            }
+            let (sale_accounts, remaining) = rest.split_at(count as usize);
+            Self::process_accept_bid(sale_accounts, false, &[], &Bumps::default(), 0, program_id)?;
+            rest = remaining;
+        }
+        if !rest.is_empty() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(())
+    }
+
+    /// Buys several listings at their asks. `purchases` splits the accounts
+    /// after the platform state into auto-matched `Bid` account lists, each
+    /// ending in its sale's accounts. A purchase is skipped when its listing
+    /// no longer sells to the buyer, or when its ask plus the undiscounted
+    /// taker fee would take the total past `max_spend`. An ask above the
+    /// purchase's `max_price` fails the sweep.
+    fn process_sweep_buy(
         accounts: &[AccountInfo],
+        max_spend: u64,
+        purchases: Vec<instruction::SweepPurchase>,
         program_id: &Pubkey,
     ) -> ProgramResult {
+        let (state_account_info, mut rest) = accounts.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let mut spent: u64 = 0;
+        for instruction::SweepPurchase{account_count: count, match_accounts: match_count, max_price} in purchases {
+            if rest.len() < count as usize || count < match_count {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            }
+            let (purchase_accounts, remaining) = rest.split_at(count as usize);
+            rest = remaining;
+
+            let buyer_info = purchase_accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+            if !buyer_info.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            let sale_accounts = &purchase_accounts[(count - match_count) as usize..];
+            let (lister_info, mint_account_info, list_state_account_info) = match sale_accounts {
+                [lister_info, _, mint_account_info, bidder_info, _, bid_state_account_info, _, list_state_account_info, ..] => {
+                    // Each purchase settles the buyer's own new bid.
+                    let (bid_state_pubkey, _) = state::find_bid_state_address(program_id, mint_account_info.key, buyer_info.key);
+                    if !bidder_info.key.eq(buyer_info.key) || !bid_state_account_info.key.eq(&bid_state_pubkey) {
+                        return Err(ProgramError::InvalidAccountData);
+                    }
+                    (lister_info, mint_account_info, list_state_account_info)
+                }
+                _ => return Err(ProgramError::NotEnoughAccountKeys),
+            };
+            if !Self::listing_matches(list_state_account_info, mint_account_info.key, lister_info.key, buyer_info.key, u64::MAX, &[], 0, None, program_id)? {
+                msg!("listing of {} is no longer available, skipping", mint_account_info.key);
+                continue;
+            }
+            let price = ListEscrowState::unpack(&list_state_account_info.data.borrow())?.amount;
+            if price > max_price {
+                return Err(NFTError::PriceMismatch.into());
+            }
+            let cost = price
+                .checked_add(Self::calculate_fee(price, state_info.taker_fee_bps)?)
+                .and_then(|cost| cost.checked_add(spent))
+                .ok_or(NFTError::NumericalOverflow)?;
+            if cost > max_spend {
+                msg!("listing of {} exceeds the remaining budget, skipping", mint_account_info.key);
+                continue;
+            }
+            spent = cost;
+            Self::process_bid(purchase_accounts, price, false, false, match_count, 0, Pubkey::default(), &[], program_id)?;
+        }
+        if !rest.is_empty() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(())
+    }
+
+    fn process_withdraw_nft_on_success(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult{
         let account_info_iter = &mut accounts.iter();
         let signer_info = next_account_info(account_info_iter)?;
         if !signer_info.is_signer {
@@ -376,579 +3608,1327 @@ impl Processor {
         }
 
         let token_account_info = next_account_info(account_info_iter)?;
-        let token_account_data = spl_token::state::Account::unpack_unchecked(&token_account_info.data.borrow())?;
+
+        msg!("checking mint");
+        let mint_account_info = next_account_info(account_info_iter)?;
+        if !(mint_account_info.owner.eq(&spl_token::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        msg!("checking lister");
+        let lister_account_info = next_account_info(account_info_iter)?;
+        let list_rent_payer_info = next_account_info(account_info_iter)?;
+
+        msg!("checking lister state");
+        let escrow_list_state_account_info = next_account_info(account_info_iter)?;
+        let escrow_list_vault_account_info = next_account_info(account_info_iter)?;
+        Self::assert_owned_by(escrow_list_state_account_info, program_id)?;
+        let list_state = ListEscrowState::unpack(&escrow_list_state_account_info.data.borrow())?;
+        let list_state_bump = Self::check_program_address(escrow_list_state_account_info, &[
+            mint_account_info.key.as_ref(),
+            lister_account_info.key.as_ref(),
+            b"List",
+            b"State"
+            ],
+            list_state.state_bump,
+            program_id
+        )?;
+        msg!("checking lister vault");
+        Self::check_list_vault(
+            escrow_list_vault_account_info,
+            escrow_list_state_account_info.key,
+            mint_account_info.key,
+            list_state.vault_bump,
+        )?;
+
+        msg!("checking token program");
+        let token_program_info = next_account_info(account_info_iter)?;
+        if !(spl_token::id().eq(token_program_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        msg!("checking lister state:lister");
+        if !list_state.lister.eq(lister_account_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if !list_state.payer.eq(list_rent_payer_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        msg!("checking lister state:success");
+        if !list_state.success {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        msg!("checking lister state:successful_buyer");
+        if !list_state.successful_buyer.eq(signer_info.key) {
+            msg!("Expected {}, got {}", list_state.successful_buyer, signer_info.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Buyers without a token account for the mint get their associated
+        // token account created here, paying the rent themselves.
+        if token_account_info.data_is_empty() {
+            let system_program_info = next_account_info(account_info_iter)?;
+            let rent_info = next_account_info(account_info_iter)?;
+            Self::check_rent_sysvar(rent_info)?;
+            let associated_token_program_info = next_account_info(account_info_iter)?;
+            Self::create_associated_token_account(
+                signer_info,
+                token_account_info,
+                mint_account_info,
+                system_program_info,
+                token_program_info,
+                rent_info,
+                associated_token_program_info,
+            )?;
+        }
+
+        msg!("checking token account");
+        let token_account_data = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
         if !(token_account_data.owner.eq(&signer_info.key)) {
             return Err(ProgramError::InvalidAccountData);
         }
+        if !(token_account_data.mint.eq(&mint_account_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        msg!("all checks completed succesfully");
+
+        Self::release_list_vault(
+            token_program_info,
+            escrow_list_vault_account_info,
+            escrow_list_state_account_info,
+            mint_account_info,
+            token_account_info,
+            lister_account_info.key,
+            list_state_bump,
+        )?;
+
+        Self::close_account(escrow_list_state_account_info, list_rent_payer_info, program_id)?;
+
+        Ok(())
+    }
+
+    /// Closes a program-owned account, moving all its lamports to
+    /// `destination_info`.
+    pub(crate) fn close_account(
+        account_info: &AccountInfo,
+        destination_info: &AccountInfo,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        Self::assert_owned_by(account_info, program_id)?;
+        let destination_lamports = destination_info.lamports()
+            .checked_add(account_info.lamports())
+            .ok_or(NFTError::NumericalOverflow)?;
+        **account_info.try_borrow_mut_lamports()? = 0;
+        **destination_info.try_borrow_mut_lamports()? = destination_lamports;
+        Self::wipe_account(account_info, program_id)
+    }
+
+    /// Closes a bid's state and vault, returning the escrowed bid and taker
+    /// fee to the bidder and the rest, the accounts' rent, to the recorded
+    /// payer, less `state::EXPIRE_REWARD_BPS` of it for `cranker_info` if
+    /// given. A wrapped vault must already be unwrapped into the state.
+    fn refund_bid_escrow(
+        bid_state: &BidEscrowState,
+        bid_state_account_info: &AccountInfo,
+        bid_vault_account_info: &AccountInfo,
+        bidder_info: &AccountInfo,
+        rent_payer_info: &AccountInfo,
+        cranker_info: Option<&AccountInfo>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if !bid_state.payer.eq(rent_payer_info.key) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(bid_state_account_info, program_id)?;
+        Self::check_bid_vault_owner(bid_state, bid_vault_account_info, program_id)?;
+        let escrow_amount = bid_state.amount
+            .checked_add(bid_state.taker_fee)
+            .ok_or(NFTError::NumericalOverflow)?;
+        let rent_refund = bid_vault_account_info.lamports()
+            .checked_add(bid_state_account_info.lamports())
+            .and_then(|total| total.checked_sub(escrow_amount))
+            .ok_or(NFTError::NumericalOverflow)?;
+        **bid_state_account_info.try_borrow_mut_lamports()? = 0;
+        **bid_vault_account_info.try_borrow_mut_lamports()? = 0;
+        Self::wipe_account(bid_state_account_info, program_id)?;
+        Self::wipe_account(bid_vault_account_info, program_id)?;
+        let bidder_lamports = bidder_info.lamports()
+            .checked_add(escrow_amount)
+            .ok_or(NFTError::NumericalOverflow)?;
+        **bidder_info.try_borrow_mut_lamports()? = bidder_lamports;
+        let mut rent_refund = rent_refund;
+        if let Some(cranker_info) = cranker_info {
+            let reward = Self::calculate_fee(rent_refund, state::EXPIRE_REWARD_BPS)?;
+            rent_refund = rent_refund
+                .checked_sub(reward)
+                .ok_or(NFTError::NumericalOverflow)?;
+            let cranker_lamports = cranker_info.lamports()
+                .checked_add(reward)
+                .ok_or(NFTError::NumericalOverflow)?;
+            **cranker_info.try_borrow_mut_lamports()? = cranker_lamports;
+        }
+        let rent_payer_lamports = rent_payer_info.lamports()
+            .checked_add(rent_refund)
+            .ok_or(NFTError::NumericalOverflow)?;
+        **rent_payer_info.try_borrow_mut_lamports()? = rent_payer_lamports;
+        Ok(())
+    }
+
+    /// Checks that `account_info` is the address `program_id` derives from
+    /// `seeds` and returns its bump. A nonzero `bump`, from the instruction
+    /// or stored in an escrow, is checked with `create_program_address`
+    /// rather than searched for, but only once the account has left the
+    /// system program: only `program_id` can sign for the address to move
+    /// it, and it only ever signs with the canonical bump. System-owned
+    /// addresses, which anyone can fund, are always searched.
+    pub(crate) fn check_program_address(
+        account_info: &AccountInfo,
+        seeds: &[&[u8]],
+        bump: u8,
+        program_id: &Pubkey,
+    ) -> Result<u8, ProgramError> {
+        if bump != 0 && !(account_info.owner.eq(&system_program::id())) {
+            let bump_seed = [bump];
+            let mut bumped_seeds = seeds.to_vec();
+            bumped_seeds.push(&bump_seed);
+            return match Pubkey::create_program_address(&bumped_seeds, program_id) {
+                Ok(address) if address.eq(account_info.key) => Ok(bump),
+                _ => Err(ProgramError::InvalidAccountData),
+            };
+        }
+        let (address, canonical_bump) = Pubkey::find_program_address(seeds, program_id);
+        if !(account_info.key.eq(&address)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(canonical_bump)
+    }
+
+    /// Checks that `account_info` is owned by `owner`. Addresses derived
+    /// from the program's seeds are only trusted once this holds, before
+    /// their data is read or their lamports moved.
+    pub(crate) fn assert_owned_by(account_info: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+        if !(account_info.owner.eq(owner)) {
+            return Err(NFTError::IncorrectOwner.into());
+        }
+        Ok(())
+    }
+
+    /// Checks that a bid vault is owned by the program, or by the token
+    /// program for a wrapped bid.
+    fn check_bid_vault_owner(
+        bid_state: &BidEscrowState,
+        bid_vault_account_info: &AccountInfo,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        if bid_state.wrapped {
+            Self::assert_owned_by(bid_vault_account_info, &spl_token::id())
+        } else {
+            Self::assert_owned_by(bid_vault_account_info, program_id)
+        }
+    }
+
+    /// The bump to check an escrow's address with: the one stored in the
+    /// escrow, or the instruction's for escrows that predate stored bumps.
+    fn escrow_bump(stored: u8, supplied: u8) -> u8 {
+        if stored != 0 {
+            stored
+        } else {
+            supplied
+        }
+    }
+
+    /// Checks that `account_info` is the rent sysvar. Instructions still list
+    /// it for the token programs they invoke, but rent is read with
+    /// `Rent::get`.
+    pub(crate) fn check_rent_sysvar(account_info: &AccountInfo) -> ProgramResult {
+        if !(account_info.key.eq(&sysvar::rent::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(())
+    }
+
+    /// Checks that `mint_account_info` is an SPL mint of exactly one token
+    /// with no decimals. Soulbound Token-2022 mints get their own error.
+    pub(crate) fn check_nft_mint(mint_account_info: &AccountInfo) -> ProgramResult {
+        if mint_account_info.owner.eq(&token_2022::spl_token_2022_program::id())
+            && token_2022::is_non_transferable(&mint_account_info.data.borrow())
+        {
+            return Err(NFTError::NonTransferableMint.into());
+        }
+        if !(mint_account_info.owner.eq(&spl_token::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mint = spl_token::state::Mint::unpack(&mint_account_info.data.borrow())?;
+        if mint.decimals != 0 || mint.supply != 1 {
+            return Err(NFTError::NotAnNFT.into());
+        }
+        Ok(())
+    }
+
+    /// Checks that a seller's token account holds the token and can move
+    /// it, before any escrow accounts are created for the listing.
+    pub(crate) fn check_seller_token_account(token_account: &spl_token::state::Account) -> ProgramResult {
+        if token_account.is_frozen() {
+            return Err(NFTError::TokenAccountFrozen.into());
+        }
+        if token_account.amount < 1 {
+            return Err(NFTError::InsufficientTokenBalance.into());
+        }
+        Ok(())
+    }
+
+    /// Checks that `account_info` is the list state's associated token
+    /// account for `mint`, the list vault.
+    pub(crate) fn check_list_vault(
+        account_info: &AccountInfo,
+        list_state: &Pubkey,
+        mint: &Pubkey,
+        bump: u8,
+    ) -> Result<u8, ProgramError> {
+        Self::check_program_address(
+            account_info,
+            &[list_state.as_ref(), spl_token::id().as_ref(), mint.as_ref()],
+            bump,
+            &spl_associated_token_account::id(),
+        )
+    }
+
+    /// Zeroes a drained account's data, so nothing later in the transaction
+    /// can revive or misread it before the runtime reclaims it. `AccountInfo`
+    /// cannot reassign its owner on this runtime, so the account stays with
+    /// the program until it is purged. Accounts the program does not own,
+    /// like an already closed wrapped bid vault, are left alone.
+    fn wipe_account(account_info: &AccountInfo, program_id: &Pubkey) -> ProgramResult {
+        if !(account_info.owner.eq(program_id)) {
+            return Ok(());
+        }
+        for byte in account_info.try_borrow_mut_data()?.iter_mut() {
+            *byte = 0;
+        }
+        Ok(())
+    }
+
+    /// Creates `owner_info`'s associated token account for the mint at
+    /// `token_account_info`, with the owner paying the rent.
+    fn create_associated_token_account<'a>(
+        owner_info: &AccountInfo<'a>,
+        token_account_info: &AccountInfo<'a>,
+        mint_account_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        token_program_info: &AccountInfo<'a>,
+        rent_info: &AccountInfo<'a>,
+        associated_token_program_info: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        if !(spl_associated_token_account::id().eq(associated_token_program_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let associated_token_pubkey = spl_associated_token_account::get_associated_token_address(
+            owner_info.key,
+            mint_account_info.key,
+        );
+        if !(token_account_info.key.eq(&associated_token_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        invoke(
+            &spl_associated_token_account::create_associated_token_account(
+                owner_info.key,
+                owner_info.key,
+                mint_account_info.key,
+            ),
+            &[
+                owner_info.clone(),
+                token_account_info.clone(),
+                owner_info.clone(),
+                mint_account_info.clone(),
+                system_program_info.clone(),
+                token_program_info.clone(),
+                rent_info.clone(),
+                associated_token_program_info.clone(),
+            ],
+        )
+    }
+
+    /// Refunds a bid on behalf of `refunder`. The automation thread may
+    /// only refund bids that have gone stale, and anyone else only bids
+    /// past their expiry, for a share of their rent.
+    fn process_refund(
+        accounts: &[AccountInfo],
+        refunder: Refunder,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let signer_info = next_account_info(account_info_iter)?;
+        if !signer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
 
         let mint_account_info = next_account_info(account_info_iter)?;
         if !(mint_account_info.owner.eq(&spl_token::id())) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let escrow_state_account_info = next_account_info(account_info_iter)?;
-        let escrow_vault_account_info = next_account_info(account_info_iter)?;
+        let bidder_account_info = next_account_info(account_info_iter)?;
+        let rent_payer_info = next_account_info(account_info_iter)?;
 
-        let program_info = next_account_info(account_info_iter)?;
-        if !(program_info.key.eq(program_id)) {
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        match refunder {
+            Refunder::Authority if !state_info.authority.eq(signer_info.key) => {
+                return Err(NFTError::InvalidAuthority.into());
+            }
+            Refunder::Thread if state_info.automation_thread == Pubkey::default() || !state_info.automation_thread.eq(signer_info.key) => {
+                return Err(NFTError::InvalidThreadAuthority.into());
+            }
+            _ => {}
+        }
+
+        let escrow_bid_state_account_info = next_account_info(account_info_iter)?;
+        let escrow_bid_vault_account_info = next_account_info(account_info_iter)?;
+        Self::assert_owned_by(escrow_bid_state_account_info, program_id)?;
+        let bid_state = BidEscrowState::unpack(&escrow_bid_state_account_info.data.borrow())?;
+        let bid_state_bump = Self::check_program_address(escrow_bid_state_account_info, &[
+            mint_account_info.key.as_ref(),
+            bidder_account_info.key.as_ref(),
+            b"Bid",
+            b"State"
+            ],
+            bid_state.state_bump,
+            program_id
+        )?;
+        Self::check_program_address(escrow_bid_vault_account_info, &[
+            mint_account_info.key.as_ref(),
+            bidder_account_info.key.as_ref(),
+            b"Bid",
+            b"Vault"
+            ],
+            bid_state.vault_bump,
+            program_id
+        )?;
+        if !bid_state.bidder.eq(bidder_account_info.key) {
             return Err(ProgramError::InvalidAccountData);
         }
+        // A held sale is only undone with `ReverseSettlement`.
+        if bid_state.is_settling() {
+            return Err(NFTError::SettlementPending.into());
+        }
+        match refunder {
+            Refunder::Authority => {}
+            Refunder::Thread => {
+                let stale_slot = bid_state.withdrawable_slot
+                    .checked_add(state_info.stale_bid_slots)
+                    .ok_or(NFTError::NumericalOverflow)?;
+                if Clock::get()?.slot < stale_slot {
+                    return Err(NFTError::BidNotStale.into());
+                }
+            }
+            Refunder::Crank => {
+                // Expiry can't cut short the lifetime withdrawals are held to.
+                let clock = Clock::get()?;
+                if !bid_state.has_expired(clock.unix_timestamp) || clock.slot < bid_state.withdrawable_slot {
+                    return Err(NFTError::BidNotExpired.into());
+                }
+            }
+        }
+        let cranker_info = match refunder {
+            Refunder::Crank => Some(signer_info),
+            _ => None,
+        };
 
         let token_program_info = next_account_info(account_info_iter)?;
         if !(spl_token::id().eq(token_program_info.key)) {
             return Err(ProgramError::InvalidAccountData);
         }
+        // A refund must not pull the leading bid out from under an
+        // auction's lister; the bidder can still withdraw it themselves.
+        if bid_state.auction_listing != Pubkey::default() {
+            let list_state_account_info = next_account_info(account_info_iter)?;
+            if !bid_state.auction_listing.eq(list_state_account_info.key) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            if list_state_account_info.owner.eq(program_id) && !list_state_account_info.data_is_empty() {
+                let list_state = ListEscrowState::unpack(&list_state_account_info.data.borrow())?;
+                if !list_state.success && list_state.highest_bidder.eq(bidder_account_info.key) {
+                    return Err(NFTError::BidLeadsAuction.into());
+                }
+            }
+        }
 
-        let (escrow_state_account_pubkey, nonce1) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            signer_info.key.as_ref(),
-            b"List",
-            b"State"
-            ],
-            program_id
-        );
-        if !(escrow_state_account_info.key.eq(&escrow_state_account_pubkey)) {
-            return Err(ProgramError::InvalidAccountData);
+        Self::unwrap_bid_vault(
+            token_program_info,
+            escrow_bid_vault_account_info,
+            escrow_bid_state_account_info,
+            mint_account_info.key,
+            bidder_account_info.key,
+            bid_state_bump,
+        )?;
+
+        Self::refund_bid_escrow(
+            &bid_state,
+            escrow_bid_state_account_info,
+            escrow_bid_vault_account_info,
+            bidder_account_info,
+            rent_payer_info,
+            cranker_info,
+            program_id,
+        )?;
+
+        Ok(())
+    }
+
+    /// Pairs each verified creator in `metadata`, in order, with the next
+    /// account and its share of `royalty`.
+    fn royalty_payouts<'a, 'b>(
+        account_info_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+        metadata: Option<&metadata::Metadata>,
+        royalty: u64,
+    ) -> Result<Vec<(&'a AccountInfo<'b>, u64)>, ProgramError> {
+        let mut royalty_payouts = Vec::new();
+        for creator in metadata.iter().flat_map(|metadata| metadata.verified_creators()) {
+            let creator_account_info = next_account_info(account_info_iter)?;
+            if creator.address != creator_account_info.key.to_bytes() {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let creator_royalty = (royalty as u128)
+                .checked_mul(creator.share as u128)
+                .map(|v| v / 100)
+                .ok_or(NFTError::NumericalOverflow)? as u64;
+            royalty_payouts.push((creator_account_info, creator_royalty));
         }
+        Ok(royalty_payouts)
+    }
 
-        let (escrow_vault_account_pubkey, _) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            signer_info.key.as_ref(),
-            b"List",
-            b"Vault"
+    /// Fails unless `collection` is set and allowed in
+    /// `collection_state_account_info`.
+    pub(crate) fn check_collection_allowed(
+        collection: Option<Pubkey>,
+        collection_state_account_info: &AccountInfo,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let collection = collection.ok_or(NFTError::CollectionNotAllowed)?;
+
+        let (collection_state_pubkey, _) = Pubkey::find_program_address(&[
+            collection.as_ref(),
+            b"Collection",
+            b"State"
             ],
             program_id
         );
-        if !(escrow_vault_account_info.key.eq(&escrow_vault_account_pubkey)) {
+        if !(collection_state_account_info.key.eq(&collection_state_pubkey)) {
             return Err(ProgramError::InvalidAccountData);
         }
+        if collection_state_account_info.data_is_empty() {
+            return Err(NFTError::CollectionNotAllowed.into());
+        }
+        Self::assert_owned_by(collection_state_account_info, program_id)?;
+        let collection_state = CollectionState::unpack(&collection_state_account_info.data.borrow())?;
+        if !collection_state.allowed {
+            return Err(NFTError::CollectionNotAllowed.into());
+        }
 
-        let token_tansfer_ix = spl_token::instruction::transfer(
-            &spl_token::id(), 
-            &escrow_vault_account_pubkey, 
-            token_account_info.key, 
-            &escrow_state_account_pubkey, 
-            &[&escrow_state_account_pubkey],
-            1
-        )?; 
-        invoke_signed(
-            &token_tansfer_ix,
-            &[
-                token_program_info.clone(),
-                escrow_vault_account_info.clone(),
-                token_account_info.clone(),
-                escrow_state_account_info.clone()
-            ],
-            &[&[
-                mint_account_info.key.as_ref(),
-                signer_info.key.as_ref(),
-                &b"List"[..],
-                &b"State"[..],
-                &[nonce1]
-            ]],
-        )?;
-
-        let close_ix = spl_token::instruction::close_account(
-            &spl_token::id(), 
-            &escrow_vault_account_pubkey, 
-            &escrow_state_account_pubkey, 
-            &escrow_state_account_pubkey, 
-            &[&escrow_state_account_pubkey]
-        )?;
+        Ok(())
+    }
 
-        invoke_signed(
-            &close_ix,
-            &[
-                token_program_info.clone(),
-                escrow_vault_account_info.clone(),
-                signer_info.clone(),
-                escrow_state_account_info.clone(),
-            ],
-            &[&[
-                mint_account_info.key.as_ref(),
-                signer_info.key.as_ref(),
-                &b"List"[..],
-                &b"State"[..],
-                &[nonce1]
-            ]],
-        )?;
+    /// Strips the `[fee discount, token account]` pairs appended to an
+    /// instruction's accounts. Returns the remaining accounts, the discount
+    /// tier and the owners of the token accounts that meet its threshold.
+    fn split_fee_discounts<'a, 'b>(
+        accounts: &'a [AccountInfo<'b>],
+        program_id: &Pubkey,
+    ) -> Result<(&'a [AccountInfo<'b>], FeeDiscount, Vec<Pubkey>), ProgramError> {
+        let (fee_discount_pubkey, _) = state::find_fee_discount_address(program_id);
+        let mut accounts = accounts;
+        let mut fee_discount = FeeDiscount::default();
+        let mut discount_holders = Vec::new();
+        while let [rest @ .., fee_discount_account_info, token_account_info] = accounts {
+            if !(fee_discount_account_info.key.eq(&fee_discount_pubkey)) {
+                break;
+            }
+            if !(fee_discount_account_info.owner.eq(program_id)) || !(token_account_info.owner.eq(&spl_token::id())) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            fee_discount = FeeDiscount::unpack(&fee_discount_account_info.data.borrow())?;
+            let token_account = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+            if !(token_account.mint.eq(&fee_discount.discount_mint)) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            if token_account.amount >= fee_discount.threshold {
+                discount_holders.push(token_account.owner);
+            }
+            accounts = rest;
+        }
+        Ok((accounts, fee_discount, discount_holders))
+    }
 
-        let lamports = escrow_state_account_info.lamports();
-        **escrow_state_account_info.try_borrow_mut_lamports()? = 0;
-        **signer_info.try_borrow_mut_lamports()? += lamports;
+    fn discounted_fee_bps(fee_bps: u64, trader: &Pubkey, fee_discount: &FeeDiscount, discount_holders: &[Pubkey]) -> u64 {
+        if discount_holders.contains(trader) {
+            fee_discount.apply(fee_bps)
+        } else {
+            fee_bps
+        }
+    }
 
-        Ok(())
+    /// Returns `bps` basis points of `amount`, rounded down.
+    pub(crate) fn calculate_fee(amount: u64, bps: u64) -> Result<u64, ProgramError> {
+        let fee = (amount as u128)
+            .checked_mul(bps as u128)
+            .and_then(|v| v.checked_div(state::MAX_BPS as u128))
+            .ok_or(NFTError::NumericalOverflow)?;
+        Ok(fee as u64)
     }
 
-    fn process_bid(
+    fn process_create_user_stats(
         accounts: &[AccountInfo],
-        amount: u64,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        let initializer_info = next_account_info(account_info_iter)?;
-        if !initializer_info.is_signer {
+        let owner_info = next_account_info(account_info_iter)?;
+        if !owner_info.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
-        let mint_account_info = next_account_info(account_info_iter)?;
-        if !(mint_account_info.owner.eq(&spl_token::id())) {
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let escrow_state_account_info = next_account_info(account_info_iter)?;
-        let escrow_vault_account_info = next_account_info(account_info_iter)?;
+        let user_stats_account_info = next_account_info(account_info_iter)?;
 
         let program_info = next_account_info(account_info_iter)?;
         if !(program_info.key.eq(program_id)) {
             return Err(ProgramError::InvalidAccountData);
         }
-        
+
         let system_program_info = next_account_info(account_info_iter)?;
         if !(system_program_info.key.eq(&system_program::id())) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let rent_account_info = next_account_info(account_info_iter)?;
-        
-
-        let (escrow_state_account_pubkey, nonce1) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            initializer_info.key.as_ref(),
-            b"Bid",
-            b"State"
+        let (user_stats_pubkey, nonce) = Pubkey::find_program_address(&[
+            owner_info.key.as_ref(),
+            b"User",
+            b"Stats"
             ],
             program_id
         );
-        if !(escrow_state_account_info.key.eq(&escrow_state_account_pubkey)) {
+        if !(user_stats_account_info.key.eq(&user_stats_pubkey)) {
             return Err(ProgramError::InvalidAccountData);
         }
-        let rent = &Rent::from_account_info(rent_account_info)?;
-        let required_balance = rent.minimum_balance(state::BIDESCROWSTATE);
-        let create_state_account_ix = system_instruction::create_account(initializer_info.key, &escrow_state_account_pubkey, required_balance, state::BIDESCROWSTATE as u64, program_id);
-        msg!("submitting tx to create program derived state account");
+
+        Self::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+        let rent = &Rent::get()?;
+        let required_balance = rent.minimum_balance(state::USERSTATSSIZE);
+        let create_stats_account_ix = system_instruction::create_account(
+            owner_info.key,
+            &user_stats_pubkey,
+            required_balance,
+            state::USERSTATSSIZE as u64,
+            program_id);
+        msg!("submitting tx to create program derived user stats account");
         invoke_signed(
-            &create_state_account_ix,
+            &create_stats_account_ix,
             &[
-                initializer_info.clone(),
-                escrow_state_account_info.clone(),
+                owner_info.clone(),
+                user_stats_account_info.clone(),
                 system_program_info.clone(),
                 program_info.clone(),
             ],
             &[&[
-                mint_account_info.key.as_ref(),
-                initializer_info.key.as_ref(),
-                &b"Bid"[..],
-                &b"State"[..],
-                &[nonce1]
+                owner_info.key.as_ref(),
+                &b"User"[..],
+                &b"Stats"[..],
+                &[nonce]
             ]],
         )?;
-        msg!("state account pubkey: {}", escrow_state_account_pubkey);
 
-        let (escrow_vault_account_pubkey, nonce2) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            initializer_info.key.as_ref(),
-            b"Bid",
-            b"Vault"
-            ],
-            program_id
-        );
-        if !(escrow_vault_account_info.key.eq(&escrow_vault_account_pubkey)) {
-            return Err(ProgramError::InvalidAccountData);
+        UserStats::pack(
+            UserStats{
+                layout_version: state::LAYOUT_VERSION,
+                is_initialized: true,
+                owner: *owner_info.key,
+                total_volume: 0,
+                purchases: 0,
+                sales: 0,
+                last_activity_slot: Clock::get()?.slot,
+                reserved: Default::default(),
+            },
+            &mut user_stats_account_info.data.borrow_mut()
+        )?;
+
+        Ok(())
+    }
+
+    /// Closes a wSOL bid vault into its bid state account so the escrow can
+    /// be paid out as lamports. Lamport vaults are left untouched. `nonce`
+    /// is the bump of the already checked bid state address.
+    fn unwrap_bid_vault<'a>(
+        token_program_info: &AccountInfo<'a>,
+        bid_vault_account_info: &AccountInfo<'a>,
+        bid_state_account_info: &AccountInfo<'a>,
+        mint: &Pubkey,
+        bidder: &Pubkey,
+        nonce: u8,
+    ) -> ProgramResult {
+        if !(bid_vault_account_info.owner.eq(&spl_token::id())) {
+            return Ok(());
         }
-        let required_balance = rent.minimum_balance(0);
-        let create_vault_account_ix = system_instruction::create_account(initializer_info.key, &escrow_vault_account_pubkey, required_balance, 0, program_id);
-        msg!("submitting tx to create program derived vault account");
+
+        let bid_state_pubkey = *bid_state_account_info.key;
+        let close_ix = spl_token::instruction::close_account(
+            &spl_token::id(),
+            bid_vault_account_info.key,
+            &bid_state_pubkey,
+            &bid_state_pubkey,
+            &[&bid_state_pubkey]
+        )?;
         invoke_signed(
-            &create_vault_account_ix,
+            &close_ix,
             &[
-                initializer_info.clone(),
-                escrow_vault_account_info.clone(),
-                system_program_info.clone(),
-                program_info.clone(),
+                token_program_info.clone(),
+                bid_vault_account_info.clone(),
+                bid_state_account_info.clone(),
             ],
             &[&[
-                mint_account_info.key.as_ref(),
-                initializer_info.key.as_ref(),
+                mint.as_ref(),
+                bidder.as_ref(),
                 &b"Bid"[..],
-                &b"Vault"[..],
-                &[nonce2]
+                &b"State"[..],
+                &[nonce]
             ]],
         )?;
-        msg!("vault account pubkey: {}", escrow_vault_account_pubkey);
 
-        msg!("amount is {}", amount);
-        let transfer_lamports_ix = system_instruction::transfer(initializer_info.key, &escrow_vault_account_pubkey, amount);
-        invoke(
-            &transfer_lamports_ix,
+        Ok(())
+    }
+
+    /// Moves a listed NFT to `destination_info` and closes the list vault
+    /// into the list state account, which the caller then drains. `nonce`
+    /// is the bump of the already checked list state address.
+    pub(crate) fn release_list_vault<'a>(
+        token_program_info: &AccountInfo<'a>,
+        list_vault_account_info: &AccountInfo<'a>,
+        list_state_account_info: &AccountInfo<'a>,
+        mint_account_info: &AccountInfo<'a>,
+        destination_info: &AccountInfo<'a>,
+        lister: &Pubkey,
+        nonce: u8,
+    ) -> ProgramResult {
+        let list_state_pubkey = *list_state_account_info.key;
+        let signer_seeds: &[&[u8]] = &[
+            mint_account_info.key.as_ref(),
+            lister.as_ref(),
+            &b"List"[..],
+            &b"State"[..],
+            &[nonce]
+        ];
+
+        let nft_transfer_ix = spl_token::instruction::transfer_checked(
+            &spl_token::id(),
+            list_vault_account_info.key,
+            mint_account_info.key,
+            destination_info.key,
+            &list_state_pubkey,
+            &[&list_state_pubkey],
+            1,
+            0
+        )?;
+        invoke_signed(
+            &nft_transfer_ix,
             &[
-                initializer_info.clone(),
-                escrow_vault_account_info.clone(),
-            ]
+                token_program_info.clone(),
+                list_vault_account_info.clone(),
+                mint_account_info.clone(),
+                destination_info.clone(),
+                list_state_account_info.clone(),
+            ],
+            &[signer_seeds],
         )?;
 
-        BidEscrowState::pack(
-            BidEscrowState{
-                bidder: *initializer_info.key,
-                amount: amount,
-                mint: *mint_account_info.key
-            },
-            &mut escrow_state_account_info.data.borrow_mut()
+        let close_ix = spl_token::instruction::close_account(
+            &spl_token::id(),
+            list_vault_account_info.key,
+            &list_state_pubkey,
+            &list_state_pubkey,
+            &[&list_state_pubkey]
+        )?;
+        invoke_signed(
+            &close_ix,
+            &[
+                token_program_info.clone(),
+                list_vault_account_info.clone(),
+                list_state_account_info.clone(),
+            ],
+            &[signer_seeds],
         )?;
 
         Ok(())
     }
 
-    fn process_withdraw_bid(
-        accounts: &[AccountInfo],
+    /// Adds a trade to `owner`'s stats account. Users that never created
+    /// one are skipped.
+    fn record_user_trade(
+        user_stats_account_info: &AccountInfo,
+        owner: &Pubkey,
+        amount: u64,
+        is_purchase: bool,
+        slot: u64,
         program_id: &Pubkey,
     ) -> ProgramResult {
-        let account_info_iter = &mut accounts.iter();
-        let signer_info = next_account_info(account_info_iter)?;
-        if !signer_info.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+        let (user_stats_pubkey, _) = Pubkey::find_program_address(&[
+            owner.as_ref(),
+            b"User",
+            b"Stats"
+            ],
+            program_id
+        );
+        if !(user_stats_account_info.key.eq(&user_stats_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
         }
+        if user_stats_account_info.data_is_empty() {
+            return Ok(());
+        }
+        Self::assert_owned_by(user_stats_account_info, program_id)?;
 
-        let mint_account_info = next_account_info(account_info_iter)?;
-        if !(mint_account_info.owner.eq(&spl_token::id())) {
+        let mut user_stats = UserStats::unpack(&user_stats_account_info.data.borrow())?;
+        user_stats.total_volume = user_stats.total_volume
+            .checked_add(amount)
+            .ok_or(NFTError::NumericalOverflow)?;
+        if is_purchase {
+            user_stats.purchases = user_stats.purchases
+                .checked_add(1)
+                .ok_or(NFTError::NumericalOverflow)?;
+        } else {
+            user_stats.sales = user_stats.sales
+                .checked_add(1)
+                .ok_or(NFTError::NumericalOverflow)?;
+        }
+        user_stats.last_activity_slot = slot;
+        UserStats::pack(user_stats, &mut user_stats_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Applies `update` to `collection`'s stats account. Collections
+    /// nobody created stats for are skipped.
+    fn update_collection_stats<F>(
+        collection_stats_account_info: &AccountInfo,
+        collection: &Pubkey,
+        program_id: &Pubkey,
+        update: F,
+    ) -> ProgramResult
+    where
+        F: FnOnce(&mut CollectionStats) -> ProgramResult,
+    {
+        let (collection_stats_pubkey, _) = state::find_collection_stats_address(program_id, collection);
+        if !(collection_stats_account_info.key.eq(&collection_stats_pubkey)) {
             return Err(ProgramError::InvalidAccountData);
         }
+        if collection_stats_account_info.data_is_empty() {
+            return Ok(());
+        }
+        Self::assert_owned_by(collection_stats_account_info, program_id)?;
 
-        let escrow_state_account_info = next_account_info(account_info_iter)?;
-        let escrow_vault_account_info = next_account_info(account_info_iter)?;
+        let mut collection_stats = CollectionStats::unpack(&collection_stats_account_info.data.borrow())?;
+        update(&mut collection_stats)?;
+        CollectionStats::pack(collection_stats, &mut collection_stats_account_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_create_collection_stats(
+        accounts: &[AccountInfo],
+        collection: Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let payer_info = next_account_info(account_info_iter)?;
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let collection_stats_account_info = next_account_info(account_info_iter)?;
 
         let program_info = next_account_info(account_info_iter)?;
         if !(program_info.key.eq(program_id)) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let (escrow_state_account_pubkey, _) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            signer_info.key.as_ref(),
-            b"Bid",
-            b"State"
-            ],
-            program_id
-        );
-        if !(escrow_state_account_info.key.eq(&escrow_state_account_pubkey)) {
+        let system_program_info = next_account_info(account_info_iter)?;
+        if !(system_program_info.key.eq(&system_program::id())) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let (escrow_vault_account_pubkey, _) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            signer_info.key.as_ref(),
-            b"Bid",
-            b"Vault"
+        let (collection_stats_pubkey, nonce) = Pubkey::find_program_address(&[
+            collection.as_ref(),
+            b"Collection",
+            b"Stats"
             ],
             program_id
         );
-        if !(escrow_vault_account_info.key.eq(&escrow_vault_account_pubkey)) {
+        if !(collection_stats_account_info.key.eq(&collection_stats_pubkey)) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let lamports = escrow_state_account_info.lamports() + escrow_vault_account_info.lamports();
-        **escrow_state_account_info.try_borrow_mut_lamports()? = 0;
-        **escrow_vault_account_info.try_borrow_mut_lamports()? = 0;
-        **signer_info.try_borrow_mut_lamports()? += lamports;
+        Self::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+        let rent = &Rent::get()?;
+        let required_balance = rent.minimum_balance(state::COLLECTIONSTATSSIZE);
+        let create_stats_account_ix = system_instruction::create_account(
+            payer_info.key,
+            &collection_stats_pubkey,
+            required_balance,
+            state::COLLECTIONSTATSSIZE as u64,
+            program_id);
+        invoke_signed(
+            &create_stats_account_ix,
+            &[
+                payer_info.clone(),
+                collection_stats_account_info.clone(),
+                system_program_info.clone(),
+                program_info.clone(),
+            ],
+            &[&[
+                collection.as_ref(),
+                &b"Collection"[..],
+                &b"Stats"[..],
+                &[nonce]
+            ]],
+        )?;
+
+        CollectionStats::pack(
+            CollectionStats{
+                layout_version: state::LAYOUT_VERSION,
+                is_initialized: true,
+                collection,
+                ..CollectionStats::default()
+            },
+            &mut collection_stats_account_info.data.borrow_mut()
+        )?;
 
         Ok(())
     }
 
-    fn process_accept_bid(
+    fn process_fill_order(
         accounts: &[AccountInfo],
+        price: u64,
+        expiry: i64,
+        nonce: u64,
         program_id: &Pubkey,
     ) -> ProgramResult {
+        let (accounts, fee_discount, discount_holders) = Self::split_fee_discounts(accounts, program_id)?;
         let account_info_iter = &mut accounts.iter();
-        let signer_info = next_account_info(account_info_iter)?;
-        if !signer_info.is_signer {
+        let buyer_info = next_account_info(account_info_iter)?;
+        if !buyer_info.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
+        let seller_info = next_account_info(account_info_iter)?;
+        let seller_token_account_info = next_account_info(account_info_iter)?;
+        let buyer_token_account_info = next_account_info(account_info_iter)?;
+
         let mint_account_info = next_account_info(account_info_iter)?;
         if !(mint_account_info.owner.eq(&spl_token::id())) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let bidder_account_info = next_account_info(account_info_iter)?;
-
-        let escrow_bid_state_account_info = next_account_info(account_info_iter)?;
-        let escrow_bid_vault_account_info = next_account_info(account_info_iter)?;
-        let (escrow_bid_state_account_pubkey, _) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            bidder_account_info.key.as_ref(),
-            b"Bid",
-            b"State"
-            ],
-            program_id
-        );
-        if !(escrow_bid_state_account_info.key.eq(&escrow_bid_state_account_pubkey)) {
+        let seller_token_account = spl_token::state::Account::unpack(&seller_token_account_info.data.borrow())?;
+        if !(seller_token_account.owner.eq(seller_info.key)) || !(seller_token_account.mint.eq(mint_account_info.key)) {
             return Err(ProgramError::InvalidAccountData);
         }
-        let (escrow_bid_vault_account_pubkey, _) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            bidder_account_info.key.as_ref(),
-            b"Bid",
-            b"Vault"
-            ],
-            program_id
-        );
-        if !(escrow_bid_vault_account_info.key.eq(&escrow_bid_vault_account_pubkey)) {
+
+        let delegate_info = next_account_info(account_info_iter)?;
+        let (delegate_pubkey, delegate_nonce) = Pubkey::find_program_address(&[b"Order", b"Delegate"], program_id);
+        if !(delegate_info.key.eq(&delegate_pubkey)) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let escrow_list_state_account_info = next_account_info(account_info_iter)?;
-        let escrow_list_vault_account_info = next_account_info(account_info_iter)?;
-        let (escrow_list_state_account_pubkey, _) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            signer_info.key.as_ref(),
-            b"List",
-            b"State"
-            ],
-            program_id
-        );
-        if !(escrow_list_state_account_info.key.eq(&escrow_list_state_account_pubkey)) {
+        let receipt_account_info = next_account_info(account_info_iter)?;
+
+        let program_info = next_account_info(account_info_iter)?;
+        if !(program_info.key.eq(program_id)) {
             return Err(ProgramError::InvalidAccountData);
         }
-        let (escrow_list_vault_account_pubkey, _) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            signer_info.key.as_ref(),
-            b"List",
-            b"Vault"
-            ],
-            program_id
-        );
-        if !(escrow_list_vault_account_info.key.eq(&escrow_list_vault_account_pubkey)) {
+
+        let state_account_info = next_account_info(account_info_iter)?;
+        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
+        if !(state_account_info.key.eq(&state_account_pubkey)) {
             return Err(ProgramError::InvalidAccountData);
         }
+        Self::assert_owned_by(state_account_info, program_id)?;
+        let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+        if !state_info.is_initialized(){
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if state_info.is_paused(state::PAUSE_SALE) {
+            return Err(NFTError::InstructionPaused.into());
+        }
+        attestation::check_not_required(&state_info)?;
+
+        let fee_recipient_info = next_account_info(account_info_iter)?;
+        if !state_info.fee_recipient.eq(fee_recipient_info.key) {
+            return Err(NFTError::InvalidAuthority.into());
+        }
 
-        let mut list_state = ListEscrowState::unpack_unchecked(&escrow_list_state_account_info.data.borrow())?;
-        if !list_state.lister.eq(signer_info.key) {
+        let instructions_info = next_account_info(account_info_iter)?;
+        let message = order::order_message(program_id, seller_info.key, mint_account_info.key, price, expiry, nonce);
+        order::verify_signed_order(instructions_info, seller_info.key, &message)?;
+
+        if Clock::get()?.unix_timestamp > expiry {
+            return Err(NFTError::OrderExpired.into());
+        }
+
+        let token_program_info = next_account_info(account_info_iter)?;
+        if !(spl_token::id().eq(token_program_info.key)) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let bid_state = BidEscrowState::unpack_unchecked(&escrow_bid_state_account_info.data.borrow())?;
-        if !bid_state.bidder.eq(bidder_account_info.key) {
+        let system_program_info = next_account_info(account_info_iter)?;
+        if !(system_program_info.key.eq(&system_program::id())) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        list_state.amount = bid_state.amount;
-        list_state.success = true;
-        list_state.successful_buyer = *bidder_account_info.key;
+        Self::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+        let rent = &Rent::get()?;
+        Self::create_order_receipt(
+            buyer_info,
+            receipt_account_info,
+            seller_info.key,
+            nonce,
+            system_program_info,
+            program_info,
+            rent,
+            program_id,
+        )?;
 
-        ListEscrowState::pack(
-            list_state,
-            &mut escrow_list_state_account_info.data.borrow_mut()
+        let metadata_account_info = next_account_info(account_info_iter)?;
+        let metadata = metadata::load_metadata(metadata_account_info, mint_account_info.key)?;
+        if state_info.curated {
+            let collection_state_account_info = next_account_info(account_info_iter)?;
+            Self::check_collection_allowed(metadata.as_ref().and_then(|metadata| metadata.verified_collection()), collection_state_account_info, program_id)?;
+        }
+
+        let maker_fee_bps = Self::discounted_fee_bps(state_info.maker_fee_bps, seller_info.key, &fee_discount, &discount_holders);
+        let taker_fee_bps = Self::discounted_fee_bps(state_info.taker_fee_bps, buyer_info.key, &fee_discount, &discount_holders);
+        let maker_fee = Self::calculate_fee(price, maker_fee_bps)?;
+        let taker_fee = Self::calculate_fee(price, taker_fee_bps)?;
+        let platform_fee = maker_fee
+            .checked_add(taker_fee)
+            .ok_or(NFTError::NumericalOverflow)?;
+        let royalty = match &metadata {
+            Some(metadata) => Self::calculate_fee(price, metadata.data.seller_fee_basis_points as u64)?,
+            None => 0,
+        };
+        let royalty_payouts = Self::royalty_payouts(account_info_iter, metadata.as_ref(), royalty)?;
+        let royalty_paid = royalty_payouts
+            .iter()
+            .try_fold(0u64, |total, (_, creator_royalty)| total.checked_add(*creator_royalty))
+            .ok_or(NFTError::NumericalOverflow)?;
+        let seller_proceeds = price
+            .checked_sub(maker_fee)
+            .and_then(|l| l.checked_sub(royalty_paid))
+            .ok_or(NFTError::NumericalOverflow)?;
+
+        let mut payouts = vec![(seller_info, seller_proceeds), (fee_recipient_info, platform_fee)];
+        payouts.extend(royalty_payouts);
+        for (recipient_info, lamports) in payouts {
+            if lamports == 0 {
+                continue;
+            }
+            invoke(
+                &system_instruction::transfer(buyer_info.key, recipient_info.key, lamports),
+                &[
+                    buyer_info.clone(),
+                    recipient_info.clone(),
+                    system_program_info.clone(),
+                ],
+            )?;
+        }
+
+        let transfer_token_ix = spl_token::instruction::transfer(
+            &spl_token::id(),
+            seller_token_account_info.key,
+            buyer_token_account_info.key,
+            &delegate_pubkey,
+            &[&delegate_pubkey],
+            1
+        )?;
+        invoke_signed(
+            &transfer_token_ix,
+            &[
+                token_program_info.clone(),
+                seller_token_account_info.clone(),
+                buyer_token_account_info.clone(),
+                delegate_info.clone(),
+            ],
+            &[&[&b"Order"[..], &b"Delegate"[..], &[delegate_nonce]]],
         )?;
 
-        let total_lamports = escrow_bid_vault_account_info.lamports() + escrow_bid_state_account_info.lamports();
-        **escrow_bid_state_account_info.try_borrow_mut_lamports()? = 0;
-        **escrow_bid_vault_account_info.try_borrow_mut_lamports()? = 0;
-        **signer_info.try_borrow_mut_lamports()? += bid_state.amount;
-        **bidder_account_info.try_borrow_mut_lamports()? += total_lamports - bid_state.amount;
+        state_info.sale_count = state_info.sale_count
+            .checked_add(1)
+            .ok_or(NFTError::NumericalOverflow)?;
+        state_info.total_volume = state_info.total_volume
+            .checked_add(price)
+            .ok_or(NFTError::NumericalOverflow)?;
+        state_info.total_fees = state_info.total_fees
+            .checked_add(platform_fee)
+            .ok_or(NFTError::NumericalOverflow)?;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+        let slot = Clock::get()?.slot;
+        let buyer_stats_account_info = next_account_info(account_info_iter)?;
+        Self::record_user_trade(buyer_stats_account_info, buyer_info.key, price, true, slot, program_id)?;
+        let seller_stats_account_info = next_account_info(account_info_iter)?;
+        Self::record_user_trade(seller_stats_account_info, seller_info.key, price, false, slot, program_id)?;
 
         Ok(())
     }
 
-    fn process_withdraw_nft_on_success(
+    fn process_cancel_order(
         accounts: &[AccountInfo],
+        nonce: u64,
         program_id: &Pubkey,
-    ) -> ProgramResult{
+    ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        let signer_info = next_account_info(account_info_iter)?;
-        if !signer_info.is_signer {
+        let seller_info = next_account_info(account_info_iter)?;
+        if !seller_info.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
-        let token_account_info = next_account_info(account_info_iter)?;
-        let token_account_data = spl_token::state::Account::unpack_unchecked(&token_account_info.data.borrow())?;
-        if !(token_account_data.owner.eq(&signer_info.key)) {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let receipt_account_info = next_account_info(account_info_iter)?;
 
-        msg!("checking mint");
-        let mint_account_info = next_account_info(account_info_iter)?;
-        if !(mint_account_info.owner.eq(&spl_token::id())) {
+        let program_info = next_account_info(account_info_iter)?;
+        if !(program_info.key.eq(program_id)) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        if !(token_account_data.mint.eq(&mint_account_info.key)) {
+        let system_program_info = next_account_info(account_info_iter)?;
+        if !(system_program_info.key.eq(&system_program::id())) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        msg!("checking lister");
-        let lister_account_info = next_account_info(account_info_iter)?;
+        Self::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+        let rent = &Rent::get()?;
+        Self::create_order_receipt(
+            seller_info,
+            receipt_account_info,
+            seller_info.key,
+            nonce,
+            system_program_info,
+            program_info,
+            rent,
+            program_id,
+        )
+    }
 
-        msg!("checking lister state");
-        let escrow_list_state_account_info = next_account_info(account_info_iter)?;
-        let escrow_list_vault_account_info = next_account_info(account_info_iter)?;
-        let (escrow_list_state_account_pubkey, nonce1) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            lister_account_info.key.as_ref(),
-            b"List",
-            b"State"
-            ],
-            program_id
-        );
-        if !(escrow_list_state_account_info.key.eq(&escrow_list_state_account_pubkey)) {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        msg!("checking lister vault");
-        let (escrow_list_vault_account_pubkey, _) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            lister_account_info.key.as_ref(),
-            b"List",
-            b"Vault"
+    /// Creates the empty receipt account that marks `seller`'s `nonce` as
+    /// used. Fails if it already exists.
+    #[allow(clippy::too_many_arguments)]
+    fn create_order_receipt<'a>(
+        payer_info: &AccountInfo<'a>,
+        receipt_account_info: &AccountInfo<'a>,
+        seller: &Pubkey,
+        nonce: u64,
+        system_program_info: &AccountInfo<'a>,
+        program_info: &AccountInfo<'a>,
+        rent: &Rent,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let nonce_bytes = nonce.to_be_bytes();
+        let (receipt_pubkey, receipt_nonce) = Pubkey::find_program_address(&[
+            seller.as_ref(),
+            &nonce_bytes,
+            b"Order",
+            b"Receipt"
             ],
             program_id
         );
-        if !(escrow_list_vault_account_info.key.eq(&escrow_list_vault_account_pubkey)) {
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        msg!("checking token program");
-        let token_program_info = next_account_info(account_info_iter)?;
-        if !(spl_token::id().eq(token_program_info.key)) {
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let list_state = ListEscrowState::unpack_unchecked(&escrow_list_state_account_info.data.borrow())?;
-        msg!("checking lister state:lister");
-        if !list_state.lister.eq(lister_account_info.key) {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        msg!("checking lister state:success");
-        if !list_state.success {
+        if !(receipt_account_info.key.eq(&receipt_pubkey)) {
             return Err(ProgramError::InvalidAccountData);
         }
-        msg!("checking lister state:successful_buyer");
-        if !list_state.successful_buyer.eq(signer_info.key) {
-            msg!("Expected {}, got {}", list_state.successful_buyer, signer_info.key);
-            return Err(ProgramError::InvalidAccountData);
+        if receipt_account_info.lamports() > 0 {
+            return Err(NFTError::OrderAlreadyFilled.into());
         }
 
-        msg!("all checks completed succesfully");
-
-        let nft_transfer_ix = spl_token::instruction::transfer_checked(
-            &spl_token::id(),
-            &escrow_list_vault_account_pubkey,
-            mint_account_info.key,
-            token_account_info.key,
-            &escrow_list_state_account_pubkey,
-            &[&escrow_list_state_account_pubkey],
-            1,
-            0
-        )?;
-        
-        invoke_signed(
-            &nft_transfer_ix,
-            &[
-                token_program_info.clone(),
-                escrow_list_vault_account_info.clone(),
-                mint_account_info.clone(),
-                token_account_info.clone(),
-                escrow_list_state_account_info.clone(),
-            ],
-
-            &[&[
-                mint_account_info.key.as_ref(),
-                lister_account_info.key.as_ref(),
-                &b"List"[..],
-                &b"State"[..],
-                &[nonce1]
-            ]]
-        )?;
-
-        let close_ix = spl_token::instruction::close_account(
-            &spl_token::id(), 
-            &escrow_list_vault_account_pubkey, 
-            &escrow_list_state_account_pubkey, 
-            &escrow_list_state_account_pubkey, 
-            &[&escrow_list_state_account_pubkey]
-        )?;
-
+        let create_receipt_account_ix = system_instruction::create_account(
+            payer_info.key,
+            &receipt_pubkey,
+            rent.minimum_balance(0),
+            0,
+            program_id);
         invoke_signed(
-            &close_ix,
+            &create_receipt_account_ix,
             &[
-                token_program_info.clone(),
-                escrow_list_vault_account_info.clone(),
-                signer_info.clone(),
-                escrow_list_state_account_info.clone(),
+                payer_info.clone(),
+                receipt_account_info.clone(),
+                system_program_info.clone(),
+                program_info.clone(),
             ],
             &[&[
-                mint_account_info.key.as_ref(),
-                lister_account_info.key.as_ref(),
-                &b"List"[..],
-                &b"State"[..],
-                &[nonce1]
+                seller.as_ref(),
+                &nonce_bytes,
+                &b"Order"[..],
+                &b"Receipt"[..],
+                &[receipt_nonce]
             ]],
         )?;
 
-        let lamports = escrow_list_state_account_info.lamports();
-        **escrow_list_state_account_info.try_borrow_mut_lamports()? = 0;
-        **lister_account_info.try_borrow_mut_lamports()? += lamports;
-
         Ok(())
     }
 
-    fn process_refund(
+    fn process_create_order_book(
         accounts: &[AccountInfo],
+        collection: Pubkey,
         program_id: &Pubkey,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        let signer_info = next_account_info(account_info_iter)?;
-        if !signer_info.is_signer {
+        let payer_info = next_account_info(account_info_iter)?;
+        if !payer_info.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
-        let mint_account_info = next_account_info(account_info_iter)?;
-        if !(mint_account_info.owner.eq(&spl_token::id())) {
+        let order_book_account_info = next_account_info(account_info_iter)?;
+
+        let program_info = next_account_info(account_info_iter)?;
+        if !(program_info.key.eq(program_id)) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let bidder_account_info = next_account_info(account_info_iter)?;
-
-        let state_account_info = next_account_info(account_info_iter)?;
-        let (state_account_pubkey, _) = Pubkey::find_program_address(&[b"Platform", b"State"], program_id);
-        if !(state_account_info.key.eq(&state_account_pubkey)) {
+        let system_program_info = next_account_info(account_info_iter)?;
+        if !(system_program_info.key.eq(&system_program::id())) {
             return Err(ProgramError::InvalidAccountData);
         }
-        let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
-        if !state_info.is_initialized(){
-            return Err(ProgramError::UninitializedAccount);
-        }
-        if !state_info.authority.eq(signer_info.key) {
-            return Err(NFTError::InvalidAuthority.into()); 
+
+        let (order_book_pubkey, nonce) = Pubkey::find_program_address(&[
+            collection.as_ref(),
+            b"OrderBook"
+            ],
+            program_id
+        );
+        if !(order_book_account_info.key.eq(&order_book_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
         }
 
-        let escrow_bid_state_account_info = next_account_info(account_info_iter)?;
-        let escrow_bid_vault_account_info = next_account_info(account_info_iter)?;
-        let (escrow_bid_state_account_pubkey, _) = Pubkey::find_program_address(&[
-            mint_account_info.key.as_ref(),
-            bidder_account_info.key.as_ref(),
-            b"Bid",
-            b"State"
+        Self::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+        let rent = &Rent::get()?;
+        let required_balance = rent.minimum_balance(state::ORDERBOOKSIZE);
+        let create_order_book_account_ix = system_instruction::create_account(
+            payer_info.key,
+            &order_book_pubkey,
+            required_balance,
+            state::ORDERBOOKSIZE as u64,
+            program_id);
+        msg!("submitting tx to create program derived order book account");
+        invoke_signed(
+            &create_order_book_account_ix,
+            &[
+                payer_info.clone(),
+                order_book_account_info.clone(),
+                system_program_info.clone(),
+                program_info.clone(),
+            ],
+            &[&[
+                collection.as_ref(),
+                &b"OrderBook"[..],
+                &[nonce]
+            ]],
+        )?;
+
+        OrderBook::pack(
+            OrderBook{
+                layout_version: state::LAYOUT_VERSION,
+                is_initialized: true,
+                collection,
+                asks: vec![],
+                bids: vec![],
+                reserved: Default::default(),
+            },
+            &mut order_book_account_info.data.borrow_mut()
+        )?;
+
+        Ok(())
+    }
+
+    /// Re-reads `maker`'s escrow on `side` and replaces their entry for the
+    /// mint in the order book. Anyone may call it; the entry only ever
+    /// mirrors the escrow account, which is either live or closed.
+    fn process_update_order_book(
+        accounts: &[AccountInfo],
+        side: BookSide,
+        maker: Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let order_book_account_info = next_account_info(account_info_iter)?;
+        Self::assert_owned_by(order_book_account_info, program_id)?;
+        let mut order_book = OrderBook::unpack(&order_book_account_info.data.borrow())?;
+        let (order_book_pubkey, _) = Pubkey::find_program_address(&[
+            order_book.collection.as_ref(),
+            b"OrderBook"
             ],
             program_id
         );
-        if !(escrow_bid_state_account_info.key.eq(&escrow_bid_state_account_pubkey)) {
+        if !(order_book_account_info.key.eq(&order_book_pubkey)) {
             return Err(ProgramError::InvalidAccountData);
         }
-        let (escrow_bid_vault_account_pubkey, _) = Pubkey::find_program_address(&[
+
+        let mint_account_info = next_account_info(account_info_iter)?;
+        let escrow_state_account_info = next_account_info(account_info_iter)?;
+        let seed: &[u8] = match side {
+            BookSide::Ask => b"List",
+            BookSide::Bid => b"Bid",
+        };
+        let (escrow_state_pubkey, _) = Pubkey::find_program_address(&[
             mint_account_info.key.as_ref(),
-            bidder_account_info.key.as_ref(),
-            b"Bid",
-            b"Vault"
+            maker.as_ref(),
+            seed,
+            b"State"
             ],
             program_id
         );
-        if !(escrow_bid_vault_account_info.key.eq(&escrow_bid_vault_account_pubkey)) {
+        if !(escrow_state_account_info.key.eq(&escrow_state_pubkey)) {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let lamports = escrow_bid_state_account_info.lamports() + escrow_bid_vault_account_info.lamports();
-        **escrow_bid_state_account_info.try_borrow_mut_lamports()? = 0;
-        **escrow_bid_vault_account_info.try_borrow_mut_lamports()? = 0;
-        **bidder_account_info.try_borrow_mut_lamports()? += lamports;
+        let price = if escrow_state_account_info.data_is_empty() {
+            None
+        } else {
+            Self::assert_owned_by(escrow_state_account_info, program_id)?;
+            match side {
+                BookSide::Ask => {
+                    let list_state = ListEscrowState::unpack_unchecked(&escrow_state_account_info.data.borrow())?;
+                    // The public book offers only what anyone can fill at
+                    // a lamport price, as the collection's floor does.
+                    Some(list_state.amount).filter(|_| {
+                        list_state.is_fillable()
+                            && !list_state.is_usd_priced()
+                            && !list_state.is_private()
+                            && !list_state.is_unlisted()
+                    })
+                }
+                BookSide::Bid => {
+                    let bid_state = BidEscrowState::unpack_unchecked(&escrow_state_account_info.data.borrow())?;
+                    Some(bid_state.amount)
+                }
+            }
+        };
+
+        order_book.remove(side, &maker, mint_account_info.key);
+        if let Some(price) = price {
+            let metadata_account_info = next_account_info(account_info_iter)?;
+            let metadata = metadata::load_metadata(metadata_account_info, mint_account_info.key)?;
+            let collection = metadata.and_then(|metadata| metadata.verified_collection());
+            if collection != Some(order_book.collection) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            order_book.insert(side, BookEntry{
+                price,
+                maker,
+                mint: *mint_account_info.key,
+            });
+        }
+
+        OrderBook::pack(order_book, &mut order_book_account_info.data.borrow_mut())?;
 
         Ok(())
     }
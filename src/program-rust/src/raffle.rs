@@ -0,0 +1,558 @@
+//! NFT raffles.
+//!
+//! A holder escrows an NFT and sells up to `total_tickets` tickets at
+//! `ticket_price` lamports until `end_time`. Once the raffle has ended or
+//! sold out anyone can draw it: the creator is paid the ticket proceeds
+//! minus the platform's maker fee, and one ticket is picked as the winner,
+//! who then claims the NFT. A raffle that sold no tickets is won by its
+//! creator.
+//!
+//! The draw is seeded from the most recent slot hash. That is good enough
+//! for low-stakes raffles but a block producer can bias it.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    hash::hashv,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::{self, Sysvar},
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{attestation, error::NFTError, processor::Processor, state::{self, Discriminator, PlatformState}};
+
+pub const MAX_RAFFLE_TICKETS: usize = 256usize;
+pub const RAFFLESIZE: usize = 138usize + MAX_RAFFLE_TICKETS * 32;
+
+pub fn find_raffle_state_address(program_id: &Pubkey, mint: &Pubkey, creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), creator.as_ref(), b"Raffle", b"State"], program_id)
+}
+
+pub fn find_raffle_vault_address(program_id: &Pubkey, mint: &Pubkey, creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), creator.as_ref(), b"Raffle", b"Vault"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RaffleState {
+    pub is_initialized: bool,
+    pub creator: Pubkey,
+    pub mint: Pubkey,
+    pub ticket_price: u64,
+    pub total_tickets: u64,
+    pub end_time: i64,
+    pub drawn: bool,
+    pub winner: Pubkey,
+    /// Ticket holders in purchase order; one entry per ticket.
+    pub entrants: Vec<Pubkey>,
+}
+
+impl RaffleState {
+    fn is_closed(&self, now: i64) -> bool {
+        now >= self.end_time || self.entrants.len() as u64 >= self.total_tickets
+    }
+}
+
+impl Sealed for RaffleState{}
+
+impl Discriminator for RaffleState{
+    const DISCRIMINATOR: [u8; 8] = *b"raffle\0\0";
+}
+
+impl IsInitialized for RaffleState{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for RaffleState {
+    const LEN: usize = RAFFLESIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < RaffleState::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, RaffleState::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            creator,
+            mint,
+            ticket_price,
+            total_tickets,
+            tickets_sold,
+            end_time,
+            drawn,
+            winner,
+            entrants,
+        ) = array_refs![src, 8, 1, 32, 32, 8, 8, 8, 8, 1, 32, MAX_RAFFLE_TICKETS * 32];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let drawn = match drawn {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let tickets_sold = u64::from_be_bytes(*tickets_sold);
+        if tickets_sold > MAX_RAFFLE_TICKETS as u64 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(RaffleState{
+            is_initialized,
+            creator: Pubkey::new_from_array(*creator),
+            mint: Pubkey::new_from_array(*mint),
+            ticket_price: u64::from_be_bytes(*ticket_price),
+            total_tickets: u64::from_be_bytes(*total_tickets),
+            end_time: i64::from_be_bytes(*end_time),
+            drawn,
+            winner: Pubkey::new_from_array(*winner),
+            entrants: entrants
+                .chunks_exact(32)
+                .take(tickets_sold as usize)
+                .map(|chunk| Pubkey::new_from_array(*array_ref![chunk, 0, 32]))
+                .collect(),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, RaffleState::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            creator_dst,
+            mint_dst,
+            ticket_price_dst,
+            total_tickets_dst,
+            tickets_sold_dst,
+            end_time_dst,
+            drawn_dst,
+            winner_dst,
+            entrants_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 32, 8, 8, 8, 8, 1, 32, MAX_RAFFLE_TICKETS * 32];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+        is_initialized_dst[0] = self.is_initialized as u8;
+        creator_dst.copy_from_slice(self.creator.as_ref());
+        mint_dst.copy_from_slice(self.mint.as_ref());
+        *ticket_price_dst = self.ticket_price.to_be_bytes();
+        *total_tickets_dst = self.total_tickets.to_be_bytes();
+        *tickets_sold_dst = (self.entrants.len() as u64).to_be_bytes();
+        *end_time_dst = self.end_time.to_be_bytes();
+        drawn_dst[0] = self.drawn as u8;
+        winner_dst.copy_from_slice(self.winner.as_ref());
+        for byte in entrants_dst.iter_mut() {
+            *byte = 0;
+        }
+        for (entrant, chunk) in self.entrants.iter().zip(entrants_dst.chunks_exact_mut(32)) {
+            chunk.copy_from_slice(entrant.as_ref());
+        }
+    }
+}
+
+pub fn process_create_raffle(
+    accounts: &[AccountInfo],
+    ticket_price: u64,
+    total_tickets: u64,
+    end_time: i64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if total_tickets == 0 || total_tickets > MAX_RAFFLE_TICKETS as u64 {
+        return Err(NFTError::InvalidInstructionData.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let creator_info = next_account_info(account_info_iter)?;
+    if !creator_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let token_account_info = next_account_info(account_info_iter)?;
+    let mint_account_info = next_account_info(account_info_iter)?;
+    if !(mint_account_info.owner.eq(&spl_token::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let token_account = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+    if !(token_account.owner.eq(creator_info.key)) || !(token_account.mint.eq(mint_account_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let raffle_state_account_info = next_account_info(account_info_iter)?;
+    let raffle_vault_account_info = next_account_info(account_info_iter)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+    let rent = &Rent::get()?;
+
+    let (raffle_state_pubkey, state_nonce) = find_raffle_state_address(program_id, mint_account_info.key, creator_info.key);
+    if !(raffle_state_account_info.key.eq(&raffle_state_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_state_account_ix = system_instruction::create_account(
+        creator_info.key,
+        &raffle_state_pubkey,
+        rent.minimum_balance(RAFFLESIZE),
+        RAFFLESIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived raffle state account");
+    invoke_signed(
+        &create_state_account_ix,
+        &[
+            creator_info.clone(),
+            raffle_state_account_info.clone(),
+            system_program_info.clone(),
+            program_info.clone(),
+        ],
+        &[&[
+            mint_account_info.key.as_ref(),
+            creator_info.key.as_ref(),
+            &b"Raffle"[..],
+            &b"State"[..],
+            &[state_nonce]
+        ]],
+    )?;
+
+    let (raffle_vault_pubkey, vault_nonce) = find_raffle_vault_address(program_id, mint_account_info.key, creator_info.key);
+    if !(raffle_vault_account_info.key.eq(&raffle_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_vault_account_ix = system_instruction::create_account(
+        creator_info.key,
+        &raffle_vault_pubkey,
+        rent.minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN as u64,
+        &spl_token::id());
+    invoke_signed(
+        &create_vault_account_ix,
+        &[
+            creator_info.clone(),
+            raffle_vault_account_info.clone(),
+            system_program_info.clone(),
+            token_program_info.clone(),
+        ],
+        &[&[
+            mint_account_info.key.as_ref(),
+            creator_info.key.as_ref(),
+            &b"Raffle"[..],
+            &b"Vault"[..],
+            &[vault_nonce]
+        ]],
+    )?;
+
+    let initialize_vault_account_ix = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &raffle_vault_pubkey,
+        mint_account_info.key,
+        &raffle_state_pubkey
+    )?;
+    invoke(
+        &initialize_vault_account_ix,
+        &[
+            token_program_info.clone(),
+            raffle_vault_account_info.clone(),
+            mint_account_info.clone(),
+            raffle_state_account_info.clone(),
+            rent_account_info.clone()
+        ],
+    )?;
+
+    let transfer_token_ix = spl_token::instruction::transfer_checked(
+        &spl_token::id(),
+        token_account_info.key,
+        mint_account_info.key,
+        &raffle_vault_pubkey,
+        creator_info.key,
+        &[
+            creator_info.key
+        ],
+        1,
+        0
+    )?;
+    invoke(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            token_account_info.clone(),
+            mint_account_info.clone(),
+            raffle_vault_account_info.clone(),
+            creator_info.clone()
+        ],
+    )?;
+
+    RaffleState::pack(
+        RaffleState{
+            is_initialized: true,
+            creator: *creator_info.key,
+            mint: *mint_account_info.key,
+            ticket_price,
+            total_tickets,
+            end_time,
+            drawn: false,
+            winner: Pubkey::new_from_array([0; 32]),
+            entrants: vec![],
+        },
+        &mut raffle_state_account_info.data.borrow_mut()
+    )?;
+
+    Ok(())
+}
+
+pub fn process_buy_raffle_ticket(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let buyer_info = next_account_info(account_info_iter)?;
+    if !buyer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let raffle_state_account_info = next_account_info(account_info_iter)?;
+    let mut raffle_state = load_raffle_state(raffle_state_account_info, program_id)?;
+    if raffle_state.is_closed(Clock::get()?.unix_timestamp) {
+        return Err(NFTError::RaffleClosed.into());
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    invoke(
+        &system_instruction::transfer(buyer_info.key, raffle_state_account_info.key, raffle_state.ticket_price),
+        &[
+            buyer_info.clone(),
+            raffle_state_account_info.clone(),
+            system_program_info.clone(),
+        ],
+    )?;
+
+    raffle_state.entrants.push(*buyer_info.key);
+    RaffleState::pack(raffle_state, &mut raffle_state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+/// Picks the winning ticket and pays the creator. Anyone may call it once
+/// the raffle has ended or sold out.
+pub fn process_draw_raffle(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let raffle_state_account_info = next_account_info(account_info_iter)?;
+    let mut raffle_state = load_raffle_state(raffle_state_account_info, program_id)?;
+    if raffle_state.drawn {
+        return Err(NFTError::RaffleClosed.into());
+    }
+    if !raffle_state.is_closed(Clock::get()?.unix_timestamp) {
+        return Err(NFTError::RaffleNotEnded.into());
+    }
+
+    let creator_info = next_account_info(account_info_iter)?;
+    if !(creator_info.key.eq(&raffle_state.creator)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    attestation::check_not_required(&state_info)?;
+
+    let fee_recipient_info = next_account_info(account_info_iter)?;
+    if !state_info.fee_recipient.eq(fee_recipient_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let slot_hashes_info = next_account_info(account_info_iter)?;
+    if !(slot_hashes_info.key.eq(&sysvar::slot_hashes::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    raffle_state.winner = if raffle_state.entrants.is_empty() {
+        raffle_state.creator
+    } else {
+        // Skip the vector length and take the newest (slot, hash) entry.
+        let slot_hashes = slot_hashes_info.data.borrow();
+        let recent_slot_hash = slot_hashes.get(8..48).unwrap_or(&[]);
+        let seed = hashv(&[recent_slot_hash, raffle_state_account_info.key.as_ref()]);
+        let draw = u64::from_be_bytes(*array_ref![seed.as_ref(), 0, 8]);
+        raffle_state.entrants[(draw % raffle_state.entrants.len() as u64) as usize]
+    };
+    raffle_state.drawn = true;
+
+    let proceeds = raffle_state.ticket_price
+        .checked_mul(raffle_state.entrants.len() as u64)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let platform_fee = Processor::calculate_fee(proceeds, state_info.maker_fee_bps)?;
+    let creator_proceeds = proceeds
+        .checked_sub(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    RaffleState::pack(raffle_state, &mut raffle_state_account_info.data.borrow_mut())?;
+
+    let raffle_state_lamports = raffle_state_account_info.lamports()
+        .checked_sub(proceeds)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let creator_lamports = creator_info.lamports()
+        .checked_add(creator_proceeds)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let fee_recipient_lamports = fee_recipient_info.lamports()
+        .checked_add(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    **raffle_state_account_info.try_borrow_mut_lamports()? = raffle_state_lamports;
+    **creator_info.try_borrow_mut_lamports()? = creator_lamports;
+    **fee_recipient_info.try_borrow_mut_lamports()? = fee_recipient_lamports;
+
+    if proceeds > 0 {
+        state_info.sale_count = state_info.sale_count
+            .checked_add(1)
+            .ok_or(NFTError::NumericalOverflow)?;
+        state_info.total_volume = state_info.total_volume
+            .checked_add(proceeds)
+            .ok_or(NFTError::NumericalOverflow)?;
+        state_info.total_fees = state_info.total_fees
+            .checked_add(platform_fee)
+            .ok_or(NFTError::NumericalOverflow)?;
+        PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+    }
+
+    Ok(())
+}
+
+/// Sends the NFT to the drawn winner and closes the raffle, returning its
+/// rent to the creator.
+pub fn process_claim_raffle(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let winner_info = next_account_info(account_info_iter)?;
+    if !winner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let winner_token_account_info = next_account_info(account_info_iter)?;
+    let mint_account_info = next_account_info(account_info_iter)?;
+
+    let raffle_state_account_info = next_account_info(account_info_iter)?;
+    let raffle_state = load_raffle_state(raffle_state_account_info, program_id)?;
+    if !raffle_state.drawn {
+        return Err(NFTError::RaffleNotEnded.into());
+    }
+    if !(raffle_state.winner.eq(winner_info.key)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+    if !(raffle_state.mint.eq(mint_account_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let raffle_vault_account_info = next_account_info(account_info_iter)?;
+    let (raffle_vault_pubkey, _) = find_raffle_vault_address(program_id, &raffle_state.mint, &raffle_state.creator);
+    if !(raffle_vault_account_info.key.eq(&raffle_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let creator_info = next_account_info(account_info_iter)?;
+    if !(creator_info.key.eq(&raffle_state.creator)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (raffle_state_pubkey, state_nonce) = find_raffle_state_address(program_id, &raffle_state.mint, &raffle_state.creator);
+    let state_seeds: &[&[u8]] = &[
+        raffle_state.mint.as_ref(),
+        raffle_state.creator.as_ref(),
+        &b"Raffle"[..],
+        &b"State"[..],
+        &[state_nonce]
+    ];
+
+    let transfer_token_ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &raffle_vault_pubkey,
+        winner_token_account_info.key,
+        &raffle_state_pubkey,
+        &[&raffle_state_pubkey],
+        1
+    )?;
+    invoke_signed(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            raffle_vault_account_info.clone(),
+            winner_token_account_info.clone(),
+            raffle_state_account_info.clone()
+        ],
+        &[state_seeds],
+    )?;
+
+    let close_ix = spl_token::instruction::close_account(
+        &spl_token::id(),
+        &raffle_vault_pubkey,
+        creator_info.key,
+        &raffle_state_pubkey,
+        &[&raffle_state_pubkey]
+    )?;
+    invoke_signed(
+        &close_ix,
+        &[
+            token_program_info.clone(),
+            raffle_vault_account_info.clone(),
+            creator_info.clone(),
+            raffle_state_account_info.clone(),
+        ],
+        &[state_seeds],
+    )?;
+
+    Processor::close_account(raffle_state_account_info, creator_info, program_id)?;
+
+    Ok(())
+}
+
+fn load_raffle_state(raffle_state_account_info: &AccountInfo, program_id: &Pubkey) -> Result<RaffleState, ProgramError> {
+    Processor::assert_owned_by(raffle_state_account_info, program_id)?;
+    let raffle_state = RaffleState::unpack(&raffle_state_account_info.data.borrow())?;
+    let (raffle_state_pubkey, _) = find_raffle_state_address(program_id, &raffle_state.mint, &raffle_state.creator);
+    if !(raffle_state_account_info.key.eq(&raffle_state_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(raffle_state)
+}
@@ -0,0 +1,476 @@
+//! Time-boxed NFT rentals.
+//!
+//! An owner escrows an NFT and offers it for `duration_slots` slots at a
+//! fixed `price`. A renter pays the price (less the platform's maker fee,
+//! which goes to the fee recipient) and is recorded on the rental state
+//! together with the slot the rental expires at. Once a rental has expired
+//! anyone can crank the NFT back to the owner; while no rental is running
+//! the owner can take it back themselves.
+//!
+//! The NFT stays in the program vault for the whole rental. An SPL token
+//! delegate could move the token outright, and freezing it in the renter's
+//! wallet needs the mint's freeze authority, so integrations check
+//! `renter` and `expires_slot` on the rental state instead.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{attestation, error::NFTError, processor::Processor, state::{self, Discriminator, PlatformState}};
+
+pub const RENTALSIZE: usize = 129usize;
+
+pub fn find_rental_state_address(program_id: &Pubkey, mint: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), owner.as_ref(), b"Rental", b"State"], program_id)
+}
+
+pub fn find_rental_vault_address(program_id: &Pubkey, mint: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), owner.as_ref(), b"Rental", b"Vault"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RentalState {
+    pub is_initialized: bool,
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub price: u64,
+    pub duration_slots: u64,
+    /// Current or most recent renter, zeroed until the first rental.
+    pub renter: Pubkey,
+    pub expires_slot: u64,
+}
+
+impl RentalState {
+    pub fn is_rented(&self, slot: u64) -> bool {
+        slot < self.expires_slot
+    }
+}
+
+impl Sealed for RentalState{}
+
+impl Discriminator for RentalState{
+    const DISCRIMINATOR: [u8; 8] = *b"rental\0\0";
+}
+
+impl IsInitialized for RentalState{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for RentalState {
+    const LEN: usize = RENTALSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < RentalState::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, RentalState::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            owner,
+            mint,
+            price,
+            duration_slots,
+            renter,
+            expires_slot,
+        ) = array_refs![src, 8, 1, 32, 32, 8, 8, 32, 8];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(RentalState{
+            is_initialized,
+            owner: Pubkey::new_from_array(*owner),
+            mint: Pubkey::new_from_array(*mint),
+            price: u64::from_be_bytes(*price),
+            duration_slots: u64::from_be_bytes(*duration_slots),
+            renter: Pubkey::new_from_array(*renter),
+            expires_slot: u64::from_be_bytes(*expires_slot),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, RentalState::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            owner_dst,
+            mint_dst,
+            price_dst,
+            duration_slots_dst,
+            renter_dst,
+            expires_slot_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 32, 8, 8, 32, 8];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+
+        let RentalState {
+            is_initialized,
+            owner,
+            mint,
+            price,
+            duration_slots,
+            renter,
+            expires_slot,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        owner_dst.copy_from_slice(owner.as_ref());
+        mint_dst.copy_from_slice(mint.as_ref());
+        *price_dst = price.to_be_bytes();
+        *duration_slots_dst = duration_slots.to_be_bytes();
+        renter_dst.copy_from_slice(renter.as_ref());
+        *expires_slot_dst = expires_slot.to_be_bytes();
+    }
+}
+
+pub fn process_list_for_rent(
+    accounts: &[AccountInfo],
+    price: u64,
+    duration_slots: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let token_account_info = next_account_info(account_info_iter)?;
+    let mint_account_info = next_account_info(account_info_iter)?;
+    if !(mint_account_info.owner.eq(&spl_token::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let token_account = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+    if !(token_account.owner.eq(owner_info.key)) || !(token_account.mint.eq(mint_account_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rental_state_account_info = next_account_info(account_info_iter)?;
+    let rental_vault_account_info = next_account_info(account_info_iter)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+    let rent = &Rent::get()?;
+
+    let (rental_state_pubkey, state_nonce) = find_rental_state_address(program_id, mint_account_info.key, owner_info.key);
+    if !(rental_state_account_info.key.eq(&rental_state_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_state_account_ix = system_instruction::create_account(
+        owner_info.key,
+        &rental_state_pubkey,
+        rent.minimum_balance(RENTALSIZE),
+        RENTALSIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived rental state account");
+    invoke_signed(
+        &create_state_account_ix,
+        &[
+            owner_info.clone(),
+            rental_state_account_info.clone(),
+            system_program_info.clone(),
+            program_info.clone(),
+        ],
+        &[&[
+            mint_account_info.key.as_ref(),
+            owner_info.key.as_ref(),
+            &b"Rental"[..],
+            &b"State"[..],
+            &[state_nonce]
+        ]],
+    )?;
+
+    let (rental_vault_pubkey, vault_nonce) = find_rental_vault_address(program_id, mint_account_info.key, owner_info.key);
+    if !(rental_vault_account_info.key.eq(&rental_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_vault_account_ix = system_instruction::create_account(
+        owner_info.key,
+        &rental_vault_pubkey,
+        rent.minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN as u64,
+        &spl_token::id());
+    invoke_signed(
+        &create_vault_account_ix,
+        &[
+            owner_info.clone(),
+            rental_vault_account_info.clone(),
+            system_program_info.clone(),
+            token_program_info.clone(),
+        ],
+        &[&[
+            mint_account_info.key.as_ref(),
+            owner_info.key.as_ref(),
+            &b"Rental"[..],
+            &b"Vault"[..],
+            &[vault_nonce]
+        ]],
+    )?;
+
+    let initialize_vault_account_ix = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &rental_vault_pubkey,
+        mint_account_info.key,
+        &rental_state_pubkey
+    )?;
+    invoke(
+        &initialize_vault_account_ix,
+        &[
+            token_program_info.clone(),
+            rental_vault_account_info.clone(),
+            mint_account_info.clone(),
+            rental_state_account_info.clone(),
+            rent_account_info.clone()
+        ],
+    )?;
+
+    let transfer_token_ix = spl_token::instruction::transfer_checked(
+        &spl_token::id(),
+        token_account_info.key,
+        mint_account_info.key,
+        &rental_vault_pubkey,
+        owner_info.key,
+        &[
+            owner_info.key
+        ],
+        1,
+        0
+    )?;
+    invoke(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            token_account_info.clone(),
+            mint_account_info.clone(),
+            rental_vault_account_info.clone(),
+            owner_info.clone()
+        ],
+    )?;
+
+    RentalState::pack(
+        RentalState{
+            is_initialized: true,
+            owner: *owner_info.key,
+            mint: *mint_account_info.key,
+            price,
+            duration_slots,
+            renter: Pubkey::new_from_array([0; 32]),
+            expires_slot: 0,
+        },
+        &mut rental_state_account_info.data.borrow_mut()
+    )?;
+
+    Ok(())
+}
+
+pub fn process_rent(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let renter_info = next_account_info(account_info_iter)?;
+    if !renter_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let rental_state_account_info = next_account_info(account_info_iter)?;
+    let mut rental_state = load_rental_state(rental_state_account_info, program_id)?;
+    let slot = Clock::get()?.slot;
+    if rental_state.is_rented(slot) {
+        return Err(NFTError::RentalActive.into());
+    }
+
+    let owner_info = next_account_info(account_info_iter)?;
+    if !(owner_info.key.eq(&rental_state.owner)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let mut state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    attestation::check_not_required(&state_info)?;
+
+    let fee_recipient_info = next_account_info(account_info_iter)?;
+    if !state_info.fee_recipient.eq(fee_recipient_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let platform_fee = Processor::calculate_fee(rental_state.price, state_info.maker_fee_bps)?;
+    let owner_proceeds = rental_state.price
+        .checked_sub(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let payouts = vec![(owner_info, owner_proceeds), (fee_recipient_info, platform_fee)];
+    for (recipient_info, lamports) in payouts {
+        if lamports == 0 {
+            continue;
+        }
+        invoke(
+            &system_instruction::transfer(renter_info.key, recipient_info.key, lamports),
+            &[
+                renter_info.clone(),
+                recipient_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
+    rental_state.renter = *renter_info.key;
+    rental_state.expires_slot = slot
+        .checked_add(rental_state.duration_slots)
+        .ok_or(NFTError::NumericalOverflow)?;
+    RentalState::pack(rental_state, &mut rental_state_account_info.data.borrow_mut())?;
+
+    state_info.total_fees = state_info.total_fees
+        .checked_add(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+/// Returns the NFT to the owner and closes the rental. Anyone may call this
+/// once a rental has expired; the owner has to sign while the NFT has
+/// never been rented.
+pub fn process_reclaim_rental(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    let token_account_info = next_account_info(account_info_iter)?;
+
+    let rental_state_account_info = next_account_info(account_info_iter)?;
+    let rental_state = load_rental_state(rental_state_account_info, program_id)?;
+    if !(owner_info.key.eq(&rental_state.owner)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if rental_state.is_rented(Clock::get()?.slot) {
+        return Err(NFTError::RentalActive.into());
+    }
+    let never_rented = rental_state.renter.eq(&Pubkey::new_from_array([0; 32]));
+    if never_rented && !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let token_account = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+    if !(token_account.owner.eq(owner_info.key)) || !(token_account.mint.eq(&rental_state.mint)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rental_vault_account_info = next_account_info(account_info_iter)?;
+    let (rental_vault_pubkey, _) = find_rental_vault_address(program_id, &rental_state.mint, &rental_state.owner);
+    if !(rental_vault_account_info.key.eq(&rental_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (rental_state_pubkey, state_nonce) = find_rental_state_address(program_id, &rental_state.mint, &rental_state.owner);
+    let state_seeds: &[&[u8]] = &[
+        rental_state.mint.as_ref(),
+        rental_state.owner.as_ref(),
+        &b"Rental"[..],
+        &b"State"[..],
+        &[state_nonce]
+    ];
+
+    let transfer_token_ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &rental_vault_pubkey,
+        token_account_info.key,
+        &rental_state_pubkey,
+        &[&rental_state_pubkey],
+        1
+    )?;
+    invoke_signed(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            rental_vault_account_info.clone(),
+            token_account_info.clone(),
+            rental_state_account_info.clone()
+        ],
+        &[state_seeds],
+    )?;
+
+    let close_ix = spl_token::instruction::close_account(
+        &spl_token::id(),
+        &rental_vault_pubkey,
+        owner_info.key,
+        &rental_state_pubkey,
+        &[&rental_state_pubkey]
+    )?;
+    invoke_signed(
+        &close_ix,
+        &[
+            token_program_info.clone(),
+            rental_vault_account_info.clone(),
+            owner_info.clone(),
+            rental_state_account_info.clone(),
+        ],
+        &[state_seeds],
+    )?;
+
+    Processor::close_account(rental_state_account_info, owner_info, program_id)?;
+
+    Ok(())
+}
+
+fn load_rental_state(rental_state_account_info: &AccountInfo, program_id: &Pubkey) -> Result<RentalState, ProgramError> {
+    Processor::assert_owned_by(rental_state_account_info, program_id)?;
+    let rental_state = RentalState::unpack(&rental_state_account_info.data.borrow())?;
+    let (rental_state_pubkey, _) = find_rental_state_address(program_id, &rental_state.mint, &rental_state.owner);
+    if !(rental_state_account_info.key.eq(&rental_state_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(rental_state)
+}
@@ -0,0 +1,335 @@
+//! Trade mining: rewards in a platform SPL token for every sale.
+//!
+//! The platform authority hands the rewards mint's authority to the PDA
+//! from `find_rewards_authority_address` and configures the emission with
+//! `SetRewards`: a fixed amount for the buyer and for the seller of each
+//! sale, and a cap on how much may be minted per epoch. A sale settled
+//! through `AcceptBid`, `ExecuteSale` or a matched `Bid` mints the rewards
+//! when its accounts end with the rewards config, mint and authority and
+//! the buyer's and seller's token accounts for the rewards mint. Rewards
+//! past the epoch's cap are skipped rather than failing the sale.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{error::NFTError, processor::Processor, state::{self, Discriminator, PlatformState}};
+
+pub const REWARDSCONFIGSIZE: usize = 81usize;
+
+pub fn find_rewards_config_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"Rewards", b"Config"], program_id)
+}
+
+/// Mint authority of the rewards token.
+pub fn find_rewards_authority_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"Rewards", b"Authority"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RewardsConfig {
+    pub is_initialized: bool,
+    pub mint: Pubkey,
+    /// Rewards, in base units, minted to the buyer of each sale.
+    pub buyer_reward: u64,
+    /// Rewards, in base units, minted to the seller of each sale.
+    pub seller_reward: u64,
+    /// Most rewards that may be minted in one epoch.
+    pub epoch_cap: u64,
+    /// Epoch `epoch_minted` counts for.
+    pub epoch: u64,
+    pub epoch_minted: u64,
+}
+
+impl RewardsConfig {
+    /// Takes up to `reward` from what is left of `epoch`'s cap, starting
+    /// the count over on a new epoch. Returns the amount to mint.
+    pub fn take(&mut self, reward: u64, epoch: u64) -> u64 {
+        if self.epoch != epoch {
+            self.epoch = epoch;
+            self.epoch_minted = 0;
+        }
+        let amount = reward.min(self.epoch_cap.saturating_sub(self.epoch_minted));
+        self.epoch_minted += amount;
+        amount
+    }
+}
+
+impl Sealed for RewardsConfig{}
+
+impl Discriminator for RewardsConfig{
+    const DISCRIMINATOR: [u8; 8] = *b"rewards\0";
+}
+
+impl IsInitialized for RewardsConfig{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for RewardsConfig {
+    const LEN: usize = REWARDSCONFIGSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < RewardsConfig::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, RewardsConfig::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            mint,
+            buyer_reward,
+            seller_reward,
+            epoch_cap,
+            epoch,
+            epoch_minted,
+        ) = array_refs![src, 8, 1, 32, 8, 8, 8, 8, 8];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(RewardsConfig{
+            is_initialized,
+            mint: Pubkey::new_from_array(*mint),
+            buyer_reward: u64::from_be_bytes(*buyer_reward),
+            seller_reward: u64::from_be_bytes(*seller_reward),
+            epoch_cap: u64::from_be_bytes(*epoch_cap),
+            epoch: u64::from_be_bytes(*epoch),
+            epoch_minted: u64::from_be_bytes(*epoch_minted),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, RewardsConfig::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            mint_dst,
+            buyer_reward_dst,
+            seller_reward_dst,
+            epoch_cap_dst,
+            epoch_dst,
+            epoch_minted_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 8, 8, 8, 8, 8];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+
+        let RewardsConfig {
+            is_initialized,
+            mint,
+            buyer_reward,
+            seller_reward,
+            epoch_cap,
+            epoch,
+            epoch_minted,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        mint_dst.copy_from_slice(mint.as_ref());
+        *buyer_reward_dst = buyer_reward.to_be_bytes();
+        *seller_reward_dst = seller_reward.to_be_bytes();
+        *epoch_cap_dst = epoch_cap.to_be_bytes();
+        *epoch_dst = epoch.to_be_bytes();
+        *epoch_minted_dst = epoch_minted.to_be_bytes();
+    }
+}
+
+/// Creates or updates the rewards config. The mint's authority must
+/// already be the rewards authority PDA. Changing the config keeps the
+/// current epoch's count.
+pub fn process_set_rewards(
+    accounts: &[AccountInfo],
+    buyer_reward: u64,
+    seller_reward: u64,
+    epoch_cap: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let authority_info = next_account_info(account_info_iter)?;
+    if !authority_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let state_info = load_platform_state(next_account_info(account_info_iter)?, program_id)?;
+    if !state_info.authority.eq(authority_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let config_account_info = next_account_info(account_info_iter)?;
+    let (config_pubkey, nonce) = find_rewards_config_address(program_id);
+    if !(config_account_info.key.eq(&config_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mint_account_info = next_account_info(account_info_iter)?;
+    if !(mint_account_info.owner.eq(&spl_token::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let mint = spl_token::state::Mint::unpack(&mint_account_info.data.borrow())?;
+    let (rewards_authority_pubkey, _) = find_rewards_authority_address(program_id);
+    if mint.mint_authority != COption::Some(rewards_authority_pubkey) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let mut config = if config_account_info.data_is_empty() {
+        let program_info = next_account_info(account_info_iter)?;
+        if !(program_info.key.eq(program_id)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let system_program_info = next_account_info(account_info_iter)?;
+        if !(system_program_info.key.eq(&system_program::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Processor::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+        let rent = &Rent::get()?;
+        let create_config_account_ix = system_instruction::create_account(
+            authority_info.key,
+            &config_pubkey,
+            rent.minimum_balance(REWARDSCONFIGSIZE),
+            REWARDSCONFIGSIZE as u64,
+            program_id);
+        msg!("submitting tx to create program derived rewards config account");
+        invoke_signed(
+            &create_config_account_ix,
+            &[
+                authority_info.clone(),
+                config_account_info.clone(),
+                system_program_info.clone(),
+                program_info.clone(),
+            ],
+            &[&[
+                &b"Rewards"[..],
+                &b"Config"[..],
+                &[nonce]
+            ]],
+        )?;
+        RewardsConfig{
+            is_initialized: true,
+            epoch: Clock::get()?.epoch,
+            ..RewardsConfig::default()
+        }
+    } else {
+        Processor::assert_owned_by(config_account_info, program_id)?;
+        RewardsConfig::unpack(&config_account_info.data.borrow())?
+    };
+
+    config.mint = *mint_account_info.key;
+    config.buyer_reward = buyer_reward;
+    config.seller_reward = seller_reward;
+    config.epoch_cap = epoch_cap;
+    RewardsConfig::pack(config, &mut config_account_info.data.borrow_mut())
+}
+
+/// Strips the rewards accounts appended to a sale's accounts, if its last
+/// five start with the rewards config. Returns the remaining accounts and
+/// the rewards accounts for `mint_trade_rewards`.
+pub(crate) fn split_rewards<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+    program_id: &Pubkey,
+) -> (&'a [AccountInfo<'b>], Option<&'a [AccountInfo<'b>]>) {
+    let (config_pubkey, _) = find_rewards_config_address(program_id);
+    match accounts.len().checked_sub(5).map(|len| accounts.split_at(len)) {
+        Some((rest, rewards_accounts)) if rewards_accounts[0].key.eq(&config_pubkey) => (rest, Some(rewards_accounts)),
+        _ => (accounts, None),
+    }
+}
+
+/// Mints the configured rewards for a sale to `buyer` and `seller`, within
+/// what is left of the epoch's cap.
+pub(crate) fn mint_trade_rewards<'a>(
+    rewards_accounts: &[AccountInfo<'a>],
+    token_program_info: &AccountInfo<'a>,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut rewards_accounts.iter();
+    let config_account_info = next_account_info(account_info_iter)?;
+    Processor::assert_owned_by(config_account_info, program_id)?;
+    let mut config = RewardsConfig::unpack(&config_account_info.data.borrow())?;
+
+    let mint_account_info = next_account_info(account_info_iter)?;
+    if !config.mint.eq(mint_account_info.key) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rewards_authority_info = next_account_info(account_info_iter)?;
+    let (rewards_authority_pubkey, nonce) = find_rewards_authority_address(program_id);
+    if !(rewards_authority_info.key.eq(&rewards_authority_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let buyer_token_account_info = next_account_info(account_info_iter)?;
+    let seller_token_account_info = next_account_info(account_info_iter)?;
+    let epoch = Clock::get()?.epoch;
+    let rewards = [
+        (buyer_token_account_info, buyer, config.buyer_reward),
+        (seller_token_account_info, seller, config.seller_reward),
+    ];
+    for (token_account_info, owner, reward) in rewards.iter() {
+        if !(token_account_info.owner.eq(&spl_token::id())) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let token_account = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+        if !(token_account.owner.eq(owner) && token_account.mint.eq(mint_account_info.key)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let amount = config.take(*reward, epoch);
+        if amount == 0 {
+            continue;
+        }
+        let mint_to_ix = spl_token::instruction::mint_to(
+            &spl_token::id(),
+            mint_account_info.key,
+            token_account_info.key,
+            &rewards_authority_pubkey,
+            &[],
+            amount,
+        )?;
+        invoke_signed(
+            &mint_to_ix,
+            &[
+                token_program_info.clone(),
+                mint_account_info.clone(),
+                (*token_account_info).clone(),
+                rewards_authority_info.clone(),
+            ],
+            &[&[&b"Rewards"[..], &b"Authority"[..], &[nonce]]],
+        )?;
+    }
+
+    RewardsConfig::pack(config, &mut config_account_info.data.borrow_mut())
+}
+
+fn load_platform_state(state_account_info: &AccountInfo, program_id: &Pubkey) -> Result<PlatformState, ProgramError> {
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(state_info)
+}
@@ -0,0 +1,216 @@
+//! Session keys: delegated trading authority.
+//!
+//! A wallet authorizes an ephemeral key with `CreateSession`, recording it
+//! in a session PDA with an expiry and a mask of the instructions it may
+//! sign. `List`, `Bid` and `AuctionBid` then accept the session key as
+//! signer in place of the owner, so bots and mobile apps can trade without
+//! holding the main key. The owner's account is still passed where it
+//! always was, unsigned, and the session PDA and key are appended last.
+//!
+//! A session key lists an NFT as the token account's delegate, so the
+//! owner approves it on each token account it may list, and it funds bids
+//! from its own lamports. Escrow state and refunds stay with the owner.
+//! The owner can revoke a session at any time with `RevokeSession`.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::{error::NFTError, processor::Processor, state::{self, Discriminator, RESERVED_WORDS}};
+
+pub const SESSIONSIZE: usize = 147usize;
+
+/// The session key may sign `List`.
+pub const ALLOW_LIST: u8 = 1 << 0;
+/// The session key may sign `Bid` and `AuctionBid`.
+pub const ALLOW_BID: u8 = 1 << 1;
+
+pub fn find_session_address(program_id: &Pubkey, owner: &Pubkey, session_key: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[owner.as_ref(), session_key.as_ref(), b"Session"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct Session {
+    pub layout_version: u8,
+    pub is_initialized: bool,
+    pub owner: Pubkey,
+    pub session_key: Pubkey,
+    /// Unix timestamp from which the session key is no longer accepted.
+    pub expires_at: i64,
+    /// `ALLOW_*` bits for the instructions the session key may sign.
+    pub allowed: u8,
+    pub reserved: [u64; RESERVED_WORDS],
+}
+
+impl Session {
+    pub fn allows(&self, instruction: u8, now: i64) -> bool {
+        now < self.expires_at && self.allowed & instruction == instruction
+    }
+}
+
+impl Sealed for Session{}
+
+impl Discriminator for Session{
+    const DISCRIMINATOR: [u8; 8] = *b"session\0";
+}
+
+impl IsInitialized for Session{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for Session {
+    const LEN: usize = SESSIONSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        state::unpack_borsh(src)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        state::pack_borsh(self, dst)
+    }
+}
+
+/// Creates the session PDA for `session_key`, or replaces the expiry and
+/// mask of an existing one.
+pub fn process_create_session(
+    accounts: &[AccountInfo],
+    session_key: Pubkey,
+    expires_at: i64,
+    allowed: u8,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let session_account_info = next_account_info(account_info_iter)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Processor::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::get()?;
+
+    if expires_at <= Clock::get()?.unix_timestamp {
+        return Err(NFTError::SessionExpired.into());
+    }
+
+    let (session_pubkey, nonce) = find_session_address(program_id, owner_info.key, &session_key);
+    if !(session_account_info.key.eq(&session_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if session_account_info.data_is_empty() {
+        let create_session_account_ix = system_instruction::create_account(
+            owner_info.key,
+            &session_pubkey,
+            rent.minimum_balance(SESSIONSIZE),
+            SESSIONSIZE as u64,
+            program_id);
+        msg!("submitting tx to create program derived session account");
+        invoke_signed(
+            &create_session_account_ix,
+            &[
+                owner_info.clone(),
+                session_account_info.clone(),
+                system_program_info.clone(),
+                program_info.clone(),
+            ],
+            &[&[
+                owner_info.key.as_ref(),
+                session_key.as_ref(),
+                &b"Session"[..],
+                &[nonce]
+            ]],
+        )?;
+    }
+
+    Session::pack(
+        Session{
+            layout_version: state::LAYOUT_VERSION,
+            is_initialized: true,
+            owner: *owner_info.key,
+            session_key,
+            expires_at,
+            allowed,
+            reserved: Default::default(),
+        },
+        &mut session_account_info.data.borrow_mut()
+    )?;
+
+    Ok(())
+}
+
+pub fn process_revoke_session(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let session_account_info = next_account_info(account_info_iter)?;
+    Processor::assert_owned_by(session_account_info, program_id)?;
+    let session = Session::unpack(&session_account_info.data.borrow())?;
+    if !session.owner.eq(owner_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    Processor::close_account(session_account_info, owner_info, program_id)
+}
+
+/// Resolves who authorizes an instruction whose first account is the
+/// owner. An owner that signed acts for itself. Otherwise the accounts must
+/// end with the owner's session PDA and the session key as signer, and the
+/// session must allow `instruction`; those two accounts are split off and
+/// the session key is returned as the authority.
+pub(crate) fn split_session<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+    instruction: u8,
+    program_id: &Pubkey,
+) -> Result<(&'a [AccountInfo<'b>], &'a AccountInfo<'b>), ProgramError> {
+    let owner_info = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if owner_info.is_signer {
+        return Ok((accounts, owner_info));
+    }
+    let (accounts, session_account_info, session_key_info) = match accounts {
+        [rest @ .., session_account_info, session_key_info] if !rest.is_empty() => (rest, session_account_info, session_key_info),
+        _ => return Err(ProgramError::MissingRequiredSignature),
+    };
+    if !session_key_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let (session_pubkey, _) = find_session_address(program_id, owner_info.key, session_key_info.key);
+    if !(session_account_info.key.eq(&session_pubkey)) || !(session_account_info.owner.eq(program_id)) {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let session = Session::unpack(&session_account_info.data.borrow())?;
+    if !session.allows(instruction, Clock::get()?.unix_timestamp) {
+        return Err(NFTError::SessionNotAllowed.into());
+    }
+    Ok((accounts, session_key_info))
+}
@@ -0,0 +1,648 @@
+//! Fee-sharing staking.
+//!
+//! Users stake the platform's SPL token into a vault owned by the stake
+//! pool PDA. Collected trading fees are paid into the pool with
+//! `FundStakingRewards` and shared pro rata across everything staked at that
+//! moment, using a running rewards-per-token accumulator: each stake account
+//! remembers the accumulator value its stake was last settled at, and is
+//! owed `amount * (reward_per_token - reward_checkpoint)` on the next
+//! stake, unstake or claim.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{error::NFTError, processor::Processor, state::{self, Discriminator, PlatformState}};
+
+pub const STAKEPOOLSIZE: usize = 81usize;
+pub const STAKEACCOUNTSIZE: usize = 65usize;
+/// Fixed-point scale of `StakePool::reward_per_token`.
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000u128;
+
+pub fn find_stake_pool_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"Stake", b"Pool"], program_id)
+}
+
+pub fn find_stake_vault_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"Stake", b"Vault"], program_id)
+}
+
+pub fn find_stake_account_address(program_id: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[owner.as_ref(), b"Stake", b"Account"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StakePool {
+    pub is_initialized: bool,
+    pub stake_mint: Pubkey,
+    pub total_staked: u64,
+    /// Lamports earned per staked token, scaled by `REWARD_PRECISION`.
+    pub reward_per_token: u128,
+    pub total_rewards: u64,
+    pub unclaimed_rewards: u64,
+}
+
+impl Sealed for StakePool{}
+
+impl Discriminator for StakePool{
+    const DISCRIMINATOR: [u8; 8] = *b"stkpool\0";
+}
+
+impl IsInitialized for StakePool{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for StakePool {
+    const LEN: usize = STAKEPOOLSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < StakePool::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, StakePool::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            stake_mint,
+            total_staked,
+            reward_per_token,
+            total_rewards,
+            unclaimed_rewards,
+        ) = array_refs![src, 8, 1, 32, 8, 16, 8, 8];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(StakePool{
+            is_initialized,
+            stake_mint: Pubkey::new_from_array(*stake_mint),
+            total_staked: u64::from_be_bytes(*total_staked),
+            reward_per_token: u128::from_be_bytes(*reward_per_token),
+            total_rewards: u64::from_be_bytes(*total_rewards),
+            unclaimed_rewards: u64::from_be_bytes(*unclaimed_rewards),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, StakePool::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            stake_mint_dst,
+            total_staked_dst,
+            reward_per_token_dst,
+            total_rewards_dst,
+            unclaimed_rewards_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 8, 16, 8, 8];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+
+        let StakePool {
+            is_initialized,
+            stake_mint,
+            total_staked,
+            reward_per_token,
+            total_rewards,
+            unclaimed_rewards,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        stake_mint_dst.copy_from_slice(stake_mint.as_ref());
+        *total_staked_dst = total_staked.to_be_bytes();
+        *reward_per_token_dst = reward_per_token.to_be_bytes();
+        *total_rewards_dst = total_rewards.to_be_bytes();
+        *unclaimed_rewards_dst = unclaimed_rewards.to_be_bytes();
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StakeAccount {
+    pub is_initialized: bool,
+    pub owner: Pubkey,
+    pub amount: u64,
+    /// `StakePool::reward_per_token` when this stake was last settled.
+    pub reward_checkpoint: u128,
+}
+
+impl StakeAccount {
+    /// Lamports earned since the last settlement at `reward_per_token`.
+    pub fn pending_rewards(&self, reward_per_token: u128) -> Result<u64, ProgramError> {
+        let earned = reward_per_token
+            .checked_sub(self.reward_checkpoint)
+            .and_then(|delta| delta.checked_mul(self.amount as u128))
+            .map(|scaled| scaled / REWARD_PRECISION)
+            .ok_or(NFTError::NumericalOverflow)?;
+        if earned > u64::MAX as u128 {
+            return Err(NFTError::NumericalOverflow.into());
+        }
+        Ok(earned as u64)
+    }
+}
+
+impl Sealed for StakeAccount{}
+
+impl Discriminator for StakeAccount{
+    const DISCRIMINATOR: [u8; 8] = *b"stake\0\0\0";
+}
+
+impl IsInitialized for StakeAccount{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for StakeAccount {
+    const LEN: usize = STAKEACCOUNTSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < StakeAccount::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, StakeAccount::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            owner,
+            amount,
+            reward_checkpoint,
+        ) = array_refs![src, 8, 1, 32, 8, 16];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(StakeAccount{
+            is_initialized,
+            owner: Pubkey::new_from_array(*owner),
+            amount: u64::from_be_bytes(*amount),
+            reward_checkpoint: u128::from_be_bytes(*reward_checkpoint),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, StakeAccount::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            owner_dst,
+            amount_dst,
+            reward_checkpoint_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 8, 16];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+        is_initialized_dst[0] = self.is_initialized as u8;
+        owner_dst.copy_from_slice(self.owner.as_ref());
+        *amount_dst = self.amount.to_be_bytes();
+        *reward_checkpoint_dst = self.reward_checkpoint.to_be_bytes();
+    }
+}
+
+pub fn process_init_stake_pool(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let authority_info = next_account_info(account_info_iter)?;
+    if !authority_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if !state_info.authority.eq(authority_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let stake_pool_account_info = next_account_info(account_info_iter)?;
+    let stake_vault_account_info = next_account_info(account_info_iter)?;
+
+    let stake_mint_account_info = next_account_info(account_info_iter)?;
+    if !(stake_mint_account_info.owner.eq(&spl_token::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+    let rent = &Rent::get()?;
+
+    let (stake_pool_pubkey, pool_nonce) = find_stake_pool_address(program_id);
+    if !(stake_pool_account_info.key.eq(&stake_pool_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_pool_account_ix = system_instruction::create_account(
+        authority_info.key,
+        &stake_pool_pubkey,
+        rent.minimum_balance(STAKEPOOLSIZE),
+        STAKEPOOLSIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived stake pool account");
+    invoke_signed(
+        &create_pool_account_ix,
+        &[
+            authority_info.clone(),
+            stake_pool_account_info.clone(),
+            system_program_info.clone(),
+            program_info.clone(),
+        ],
+        &[&[&b"Stake"[..], &b"Pool"[..], &[pool_nonce]]],
+    )?;
+
+    let (stake_vault_pubkey, vault_nonce) = find_stake_vault_address(program_id);
+    if !(stake_vault_account_info.key.eq(&stake_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_vault_account_ix = system_instruction::create_account(
+        authority_info.key,
+        &stake_vault_pubkey,
+        rent.minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN as u64,
+        &spl_token::id());
+    invoke_signed(
+        &create_vault_account_ix,
+        &[
+            authority_info.clone(),
+            stake_vault_account_info.clone(),
+            system_program_info.clone(),
+            token_program_info.clone(),
+        ],
+        &[&[&b"Stake"[..], &b"Vault"[..], &[vault_nonce]]],
+    )?;
+
+    let initialize_vault_account_ix = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &stake_vault_pubkey,
+        stake_mint_account_info.key,
+        &stake_pool_pubkey
+    )?;
+    invoke(
+        &initialize_vault_account_ix,
+        &[
+            token_program_info.clone(),
+            stake_vault_account_info.clone(),
+            stake_mint_account_info.clone(),
+            stake_pool_account_info.clone(),
+            rent_account_info.clone()
+        ],
+    )?;
+
+    StakePool::pack(
+        StakePool{
+            is_initialized: true,
+            stake_mint: *stake_mint_account_info.key,
+            total_staked: 0,
+            reward_per_token: 0,
+            total_rewards: 0,
+            unclaimed_rewards: 0,
+        },
+        &mut stake_pool_account_info.data.borrow_mut()
+    )?;
+
+    Ok(())
+}
+
+pub fn process_stake(
+    accounts: &[AccountInfo],
+    amount: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let stake_account_info = next_account_info(account_info_iter)?;
+    let owner_token_account_info = next_account_info(account_info_iter)?;
+
+    let stake_pool_account_info = next_account_info(account_info_iter)?;
+    let mut stake_pool = load_stake_pool(stake_pool_account_info, program_id)?;
+
+    let stake_vault_account_info = next_account_info(account_info_iter)?;
+    let (stake_vault_pubkey, _) = find_stake_vault_address(program_id);
+    if !(stake_vault_account_info.key.eq(&stake_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Processor::check_rent_sysvar(next_account_info(account_info_iter)?)?;
+    let rent = &Rent::get()?;
+
+    let (stake_account_pubkey, nonce) = find_stake_account_address(program_id, owner_info.key);
+    if !(stake_account_info.key.eq(&stake_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if stake_account_info.data_is_empty() {
+        let create_stake_account_ix = system_instruction::create_account(
+            owner_info.key,
+            &stake_account_pubkey,
+            rent.minimum_balance(STAKEACCOUNTSIZE),
+            STAKEACCOUNTSIZE as u64,
+            program_id);
+        msg!("submitting tx to create program derived stake account");
+        invoke_signed(
+            &create_stake_account_ix,
+            &[
+                owner_info.clone(),
+                stake_account_info.clone(),
+                system_program_info.clone(),
+                program_info.clone(),
+            ],
+            &[&[
+                owner_info.key.as_ref(),
+                &b"Stake"[..],
+                &b"Account"[..],
+                &[nonce]
+            ]],
+        )?;
+        StakeAccount::pack(
+            StakeAccount{
+                is_initialized: true,
+                owner: *owner_info.key,
+                amount: 0,
+                reward_checkpoint: stake_pool.reward_per_token,
+            },
+            &mut stake_account_info.data.borrow_mut()
+        )?;
+    }
+    let mut stake_account = load_stake_account(stake_account_info, owner_info.key, program_id)?;
+
+    let transfer_token_ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        owner_token_account_info.key,
+        &stake_vault_pubkey,
+        owner_info.key,
+        &[owner_info.key],
+        amount
+    )?;
+    invoke(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            owner_token_account_info.clone(),
+            stake_vault_account_info.clone(),
+            owner_info.clone()
+        ],
+    )?;
+
+    settle_rewards(&mut stake_pool, &mut stake_account, stake_pool_account_info, owner_info)?;
+    stake_account.amount = stake_account.amount
+        .checked_add(amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    stake_pool.total_staked = stake_pool.total_staked
+        .checked_add(amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    StakeAccount::pack(stake_account, &mut stake_account_info.data.borrow_mut())?;
+    StakePool::pack(stake_pool, &mut stake_pool_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_unstake(
+    accounts: &[AccountInfo],
+    amount: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let stake_account_info = next_account_info(account_info_iter)?;
+    let mut stake_account = load_stake_account(stake_account_info, owner_info.key, program_id)?;
+
+    let owner_token_account_info = next_account_info(account_info_iter)?;
+
+    let stake_pool_account_info = next_account_info(account_info_iter)?;
+    let mut stake_pool = load_stake_pool(stake_pool_account_info, program_id)?;
+
+    let stake_vault_account_info = next_account_info(account_info_iter)?;
+    let (stake_vault_pubkey, _) = find_stake_vault_address(program_id);
+    if !(stake_vault_account_info.key.eq(&stake_vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if amount > stake_account.amount {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    let (stake_pool_pubkey, pool_nonce) = find_stake_pool_address(program_id);
+    let transfer_token_ix = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &stake_vault_pubkey,
+        owner_token_account_info.key,
+        &stake_pool_pubkey,
+        &[&stake_pool_pubkey],
+        amount
+    )?;
+    invoke_signed(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            stake_vault_account_info.clone(),
+            owner_token_account_info.clone(),
+            stake_pool_account_info.clone()
+        ],
+        &[&[&b"Stake"[..], &b"Pool"[..], &[pool_nonce]]],
+    )?;
+
+    settle_rewards(&mut stake_pool, &mut stake_account, stake_pool_account_info, owner_info)?;
+    stake_account.amount = stake_account.amount
+        .checked_sub(amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    stake_pool.total_staked = stake_pool.total_staked
+        .checked_sub(amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    StakeAccount::pack(stake_account, &mut stake_account_info.data.borrow_mut())?;
+    StakePool::pack(stake_pool, &mut stake_pool_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+pub fn process_claim_rewards(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let owner_info = next_account_info(account_info_iter)?;
+    if !owner_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let stake_account_info = next_account_info(account_info_iter)?;
+    let mut stake_account = load_stake_account(stake_account_info, owner_info.key, program_id)?;
+
+    let stake_pool_account_info = next_account_info(account_info_iter)?;
+    let mut stake_pool = load_stake_pool(stake_pool_account_info, program_id)?;
+
+    settle_rewards(&mut stake_pool, &mut stake_account, stake_pool_account_info, owner_info)?;
+
+    StakeAccount::pack(stake_account, &mut stake_account_info.data.borrow_mut())?;
+    StakePool::pack(stake_pool, &mut stake_pool_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+/// Pays `amount` lamports of collected fees into the stake pool, shared
+/// across everything currently staked.
+pub fn process_fund_staking_rewards(
+    accounts: &[AccountInfo],
+    amount: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let funder_info = next_account_info(account_info_iter)?;
+    if !funder_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let stake_pool_account_info = next_account_info(account_info_iter)?;
+    let mut stake_pool = load_stake_pool(stake_pool_account_info, program_id)?;
+    if stake_pool.total_staked == 0 {
+        return Err(NFTError::NothingStaked.into());
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    invoke(
+        &system_instruction::transfer(funder_info.key, stake_pool_account_info.key, amount),
+        &[
+            funder_info.clone(),
+            stake_pool_account_info.clone(),
+            system_program_info.clone(),
+        ],
+    )?;
+
+    let reward_per_token = (amount as u128)
+        .checked_mul(REWARD_PRECISION)
+        .map(|scaled| scaled / stake_pool.total_staked as u128)
+        .ok_or(NFTError::NumericalOverflow)?;
+    stake_pool.reward_per_token = stake_pool.reward_per_token
+        .checked_add(reward_per_token)
+        .ok_or(NFTError::NumericalOverflow)?;
+    stake_pool.total_rewards = stake_pool.total_rewards
+        .checked_add(amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    stake_pool.unclaimed_rewards = stake_pool.unclaimed_rewards
+        .checked_add(amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    StakePool::pack(stake_pool, &mut stake_pool_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn load_stake_pool(stake_pool_account_info: &AccountInfo, program_id: &Pubkey) -> Result<StakePool, ProgramError> {
+    let (stake_pool_pubkey, _) = find_stake_pool_address(program_id);
+    if !(stake_pool_account_info.key.eq(&stake_pool_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !(stake_pool_account_info.owner.eq(program_id)) {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    StakePool::unpack(&stake_pool_account_info.data.borrow())
+}
+
+fn load_stake_account(stake_account_info: &AccountInfo, owner: &Pubkey, program_id: &Pubkey) -> Result<StakeAccount, ProgramError> {
+    let (stake_account_pubkey, _) = find_stake_account_address(program_id, owner);
+    if !(stake_account_info.key.eq(&stake_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !(stake_account_info.owner.eq(program_id)) {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    StakeAccount::unpack(&stake_account_info.data.borrow())
+}
+
+/// Pays out everything `stake_account` has earned so far and moves its
+/// checkpoint up to the pool's current accumulator.
+fn settle_rewards(
+    stake_pool: &mut StakePool,
+    stake_account: &mut StakeAccount,
+    stake_pool_account_info: &AccountInfo,
+    owner_info: &AccountInfo,
+) -> ProgramResult {
+    let pending = stake_account.pending_rewards(stake_pool.reward_per_token)?;
+    stake_account.reward_checkpoint = stake_pool.reward_per_token;
+    if pending == 0 {
+        return Ok(());
+    }
+
+    stake_pool.unclaimed_rewards = stake_pool.unclaimed_rewards
+        .checked_sub(pending)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let stake_pool_lamports = stake_pool_account_info.lamports()
+        .checked_sub(pending)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let owner_lamports = owner_info.lamports()
+        .checked_add(pending)
+        .ok_or(NFTError::NumericalOverflow)?;
+    **stake_pool_account_info.try_borrow_mut_lamports()? = stake_pool_lamports;
+    **owner_info.try_borrow_mut_lamports()? = owner_lamports;
+
+    Ok(())
+}
@@ -1,27 +1,286 @@
+use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     program_pack::{IsInitialized, Pack, Sealed},
     program_error::ProgramError,
     pubkey::Pubkey,
 };
 
-use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use arrayref::{array_ref, array_refs};
 
-pub const STATESIZE: usize = 49usize;
-pub const LISTESCROWSTATE: usize = 105usize;
-pub const BIDESCROWSTATE: usize = 72usize;
+use crate::merkle;
+
+pub const STATESIZE: usize = 374usize;
+pub const LISTESCROWSTATE: usize = 516usize;
+pub const BIDESCROWSTATE: usize = 309usize;
+pub const COLLECTIONSTATE: usize = 107usize;
+pub const USERSTATSSIZE: usize = 138usize;
+pub const ORDER_BOOK_DEPTH: usize = 16usize;
+pub const BOOKENTRYSIZE: usize = 72usize;
+pub const ORDERBOOKSIZE: usize = 114usize + 2 * ORDER_BOOK_DEPTH * BOOKENTRYSIZE;
+pub const FEEDISCOUNTSIZE: usize = 122usize;
+pub const COLLECTIONSTATSSIZE: usize = 170usize;
+pub const MAX_BPS: u64 = 10_000u64;
+/// Farthest, in basis points of the floor estimate, a single price can pull
+/// it; prices beyond are clamped to this band.
+pub const FLOOR_OUTLIER_BPS: u64 = 2_000u64;
+/// Share, in basis points, of a clamped price's distance from the estimate
+/// that the estimate moves by when it was updated the slot before.
+pub const FLOOR_SMOOTHING_BPS: u64 = 2_500u64;
+/// Slots over which the estimate's own weight decays, until a price it was
+/// not updated with for that long replaces it, within the outlier band.
+pub const FLOOR_DECAY_SLOTS: u64 = 216_000u64;
+/// Share, in basis points, of an expired bid's rent paid to whoever sends
+/// its `ExpireAndRefund`; the rest goes back to the bid's rent payer.
+pub const EXPIRE_REWARD_BPS: u64 = 1_000u64;
+/// Longest dispute window, in slots, a listing may hold its sale for;
+/// about a week.
+pub const MAX_SETTLEMENT_DELAY_SLOTS: u64 = 1_512_000u64;
+/// Listing shown in public feeds.
+pub const VISIBILITY_PUBLIC: u8 = 0u8;
+/// Listing reachable only by its address: indexers keep it out of public
+/// feeds and it is not counted towards its collection's floor.
+pub const VISIBILITY_UNLISTED: u8 = 1u8;
+/// Layout written by this version of the program. Bumped whenever a field
+/// takes over some of an account's reserved bytes, so readers can tell
+/// zeroed padding from a field that was written as zero.
+pub const LAYOUT_VERSION: u8 = 16u8;
+/// Zeroed eight byte words at the end of every state account for fields
+/// added later. New fields are carved out of them, so accounts don't need
+/// to be reallocated or migrated when they grow.
+pub const RESERVED_WORDS: usize = 8usize;
+
+// `PlatformState::paused` bits, each switching off a group of entry points.
+/// `List`, `ListMany`, `MintAndList`, `ListCore` and `ListForToken`.
+pub const PAUSE_LIST: u64 = 1 << 0;
+/// `DeList`, `DelistMany`, `DelistCore` and `CancelTokenListing`.
+pub const PAUSE_DELIST: u64 = 1 << 1;
+/// `Bid`, `AuctionBid` and `BidWithToken`.
+pub const PAUSE_BID: u64 = 1 << 2;
+/// `AcceptBid`, `ExecuteSale`, `BatchExecuteSale`, auto-matched bids,
+/// `BuyCore`, `AcceptTokenBid` and `FillOrder`.
+pub const PAUSE_SALE: u64 = 1 << 3;
+/// Every entry point that checks a pause bit.
+pub const PAUSE_ALL: u64 = u64::MAX;
 // pub const LOGSIZE: usize = 73usize;
 
+/// Every state account starts with an eight byte tag naming its type, so
+/// accounts can be told apart without relying on their length and indexers
+/// can filter them with a memcmp at offset 0. Unpacking checks the tag.
+pub trait Discriminator {
+    const DISCRIMINATOR: [u8; 8];
+}
+
+/// Accounts written by the first release, before the Borsh migration: a
+/// few fixed big-endian fields and no discriminator. Only the platform,
+/// listings and bids existed then. This runtime cannot realloc, so they keep
+/// their old size and the account length tells the two layouts apart.
+pub trait LegacyLayout: Sized {
+    const LEGACY_LEN: usize;
+    fn unpack_legacy(src: &[u8]) -> Result<Self, ProgramError>;
+}
+
+/// Unpacks `src` as `T`, falling back to the legacy layout for accounts
+/// still sized for it. Those can be read and closed but not written back,
+/// since `Pack::pack` only takes accounts of the current size.
+pub fn unpack_compat<T: Pack + LegacyLayout + Discriminator + BorshDeserialize>(src: &[u8]) -> Result<T, ProgramError> {
+    if src.len() == T::LEGACY_LEN {
+        return T::unpack_legacy(src);
+    }
+    if src.len() != T::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    unpack_borsh(src)
+}
+
+pub(crate) fn unpack_borsh<T: Pack + Discriminator + BorshDeserialize>(src: &[u8]) -> Result<T, ProgramError> {
+    if src.len() < T::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let (discriminator, mut data) = src[..T::LEN].split_at(8);
+    if discriminator != T::DISCRIMINATOR {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    T::deserialize(&mut data).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+pub(crate) fn pack_borsh<T: Pack + Discriminator + BorshSerialize>(value: &T, dst: &mut [u8]) {
+    let dst = &mut dst[..T::LEN];
+    for byte in dst.iter_mut() {
+        *byte = 0;
+    }
+    let (discriminator_dst, mut data_dst) = dst.split_at_mut(8);
+    discriminator_dst.copy_from_slice(&T::DISCRIMINATOR);
+    value.serialize(&mut data_dst).expect("state does not fit its account");
+}
+
+pub fn find_platform_state_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"Platform", b"State"], program_id)
+}
+
+pub fn find_list_state_address(program_id: &Pubkey, mint: &Pubkey, lister: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), lister.as_ref(), b"List", b"State"], program_id)
+}
+
+/// The list vault is the list state's associated token account for `mint`.
+pub fn find_list_vault_address(program_id: &Pubkey, mint: &Pubkey, lister: &Pubkey) -> (Pubkey, u8) {
+    let (list_state, _) = find_list_state_address(program_id, mint, lister);
+    Pubkey::find_program_address(
+        &[list_state.as_ref(), spl_token::id().as_ref(), mint.as_ref()],
+        &spl_associated_token_account::id(),
+    )
+}
+
+pub fn find_bid_state_address(program_id: &Pubkey, mint: &Pubkey, bidder: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), bidder.as_ref(), b"Bid", b"State"], program_id)
+}
+
+pub fn find_collection_state_address(program_id: &Pubkey, collection: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[collection.as_ref(), b"Collection", b"State"], program_id)
+}
+
+pub fn find_collection_stats_address(program_id: &Pubkey, collection: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[collection.as_ref(), b"Collection", b"Stats"], program_id)
+}
+
+pub fn find_order_book_address(program_id: &Pubkey, collection: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[collection.as_ref(), b"OrderBook"], program_id)
+}
+
+pub fn find_order_delegate_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"Order", b"Delegate"], program_id)
+}
+
+pub fn find_order_receipt_address(program_id: &Pubkey, seller: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[seller.as_ref(), &nonce.to_be_bytes(), b"Order", b"Receipt"], program_id)
+}
+
+pub fn find_user_stats_address(program_id: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[owner.as_ref(), b"User", b"Stats"], program_id)
+}
+
+pub fn find_fee_discount_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"Fee", b"Discount"], program_id)
+}
+
+pub fn find_bid_vault_address(program_id: &Pubkey, mint: &Pubkey, bidder: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[mint.as_ref(), bidder.as_ref(), b"Bid", b"Vault"], program_id)
+}
+
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
 pub struct PlatformState {
+    pub layout_version: u8,
     pub is_initialized: bool,
     pub authority: Pubkey,
-    pub platform_fee: u64,
-    pub nonce: u64
+    pub maker_fee_bps: u64,
+    pub nonce: u64,
+    pub referral_fee_bps: u64,
+    pub taker_fee_bps: u64,
+    pub curated: bool,
+    pub sale_count: u64,
+    pub total_volume: u64,
+    pub total_fees: u64,
+    pub active_listings: u64,
+    /// Receives platform fees; defaults to the authority at initialization.
+    pub fee_recipient: Pubkey,
+    /// Rejects listings of mints without a verified collection or creator.
+    pub strict: bool,
+    /// Smallest price a listing may ask.
+    pub min_listing_lamports: u64,
+    /// Smallest amount a bid may offer.
+    pub min_bid_lamports: u64,
+    /// Minimum delay, in slots, between scheduling a parameter change and
+    /// executing it. Zero applies changes immediately.
+    pub timelock_slots: u64,
+    pub pending_change: Option<ParamChange>,
+    /// Slot from which `pending_change` can be executed.
+    pub pending_change_slot: u64,
+    /// Slots a bid must stay open before its bidder can withdraw it.
+    pub min_bid_lifetime_slots: u64,
+    /// `PAUSE_*` bits for the entry points the authority has switched off.
+    pub paused: u64,
+    /// Automation thread allowed to run the platform's recurring jobs.
+    /// The default key disables them.
+    pub automation_thread: Pubkey,
+    /// Slots a bid has to stay withdrawable before the automation thread
+    /// may refund it as stale.
+    pub stale_bid_slots: u64,
+    /// Highest royalty, in basis points, a listing may opt into paying
+    /// above its metadata's. Zero turns royalty overrides off.
+    pub max_royalty_bps: u64,
+    /// Approves new listings before they can sell. The default key turns
+    /// curation off.
+    pub curator: Pubkey,
+    /// Share of the platform fee, after any referral, paid to the curator
+    /// of a sold listing.
+    pub curator_fee_bps: u64,
+    /// Highest share of their proceeds, in basis points, a lister may give
+    /// to charity. Zero turns charity splits off.
+    pub max_charity_bps: u64,
+    /// Share of the platform fee, after any referral and curator fee, paid
+    /// to each marketplace credited with a sale.
+    pub marketplace_fee_bps: u64,
+    /// Pyth SOL/USD price account USD priced listings are filled against.
+    /// The default key turns USD pricing off.
+    pub price_feed: Pubkey,
+    /// Whether listers and bidders need an attestation from the attestor
+    /// named in the `attestation` config.
+    pub attestation_required: bool,
+    /// The last reserved word went to `pending_change`, which grew to fit
+    /// `ParamChange::Curator`.
+    pub reserved: [u64; RESERVED_WORDS - 8],
+}
+
+/// Platform parameter change that has to wait out the timelock.
+#[derive(Clone, Copy, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+pub enum ParamChange {
+    Fees { maker_bps: u64, taker_bps: u64 },
+    Authority { authority: Pubkey },
+    Timelock { slots: u64 },
+    FeeRecipient { fee_recipient: Pubkey },
+    ReferralFee { referral_fee_bps: u64 },
+    MaxRoyalty { max_royalty_bps: u64 },
+    MarketplaceFee { marketplace_fee_bps: u64 },
+    PriceFeed { price_feed: Pubkey },
+    Curator { curator: Pubkey, curator_fee_bps: u64 },
+}
+
+impl ParamChange {
+    pub fn apply(&self, state: &mut PlatformState) {
+        match *self {
+            ParamChange::Fees { maker_bps, taker_bps } => {
+                state.maker_fee_bps = maker_bps;
+                state.taker_fee_bps = taker_bps;
+            }
+            ParamChange::Authority { authority } => state.authority = authority,
+            ParamChange::Timelock { slots } => state.timelock_slots = slots,
+            ParamChange::FeeRecipient { fee_recipient } => state.fee_recipient = fee_recipient,
+            ParamChange::ReferralFee { referral_fee_bps } => state.referral_fee_bps = referral_fee_bps,
+            // Listings keep the royalty they were capped to when listed.
+            ParamChange::MaxRoyalty { max_royalty_bps } => state.max_royalty_bps = max_royalty_bps,
+            ParamChange::MarketplaceFee { marketplace_fee_bps } => state.marketplace_fee_bps = marketplace_fee_bps,
+            // Listings keep the feed they were listed under.
+            ParamChange::PriceFeed { price_feed } => state.price_feed = price_feed,
+            ParamChange::Curator { curator, curator_fee_bps } => {
+                state.curator = curator;
+                state.curator_fee_bps = curator_fee_bps;
+            }
+        }
+    }
+}
+
+impl PlatformState {
+    pub fn is_paused(&self, pause_bit: u64) -> bool {
+        self.paused & pause_bit != 0
+    }
 }
 
 impl Sealed for PlatformState{}
 
+impl Discriminator for PlatformState{
+    const DISCRIMINATOR: [u8; 8] = *b"platform";
+}
+
 impl IsInitialized for PlatformState{
     fn is_initialized(&self) -> bool {
         self.is_initialized
@@ -31,7 +290,22 @@ impl IsInitialized for PlatformState{
 impl Pack for PlatformState {
     const LEN: usize = STATESIZE;
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let src = array_ref![src, 0, PlatformState::LEN];
+        unpack_borsh(src)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        pack_borsh(self, dst)
+    }
+
+    fn unpack_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
+        unpack_compat(input)
+    }
+}
+
+impl LegacyLayout for PlatformState {
+    const LEGACY_LEN: usize = 49usize;
+    fn unpack_legacy(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, PlatformState::LEGACY_LEN];
         let (
             is_initialized,
             authority,
@@ -43,144 +317,646 @@ impl Pack for PlatformState {
             [1] => true,
             _ => return Err(ProgramError::InvalidAccountData),
         };
+        let authority = Pubkey::new_from_array(*authority);
         Ok(PlatformState{
-            is_initialized,
-            authority: Pubkey::new_from_array(*authority),
-            platform_fee: u64::from_be_bytes(*platform_fee),
-            nonce: u64::from_be_bytes(*nonce)
-        })
-    }
-
-    fn pack_into_slice(&self, dst: &mut [u8]) {
-        let dst = array_mut_ref![dst, 0, PlatformState::LEN];
-        let (
-            is_initialized_dst,
-            authority_dst,
-            platform_fee_dst,
-            nonce_dst,
-        ) = mut_array_refs![dst, 1, 32, 8, 8];
-
-        let PlatformState {
+            layout_version: 0,
             is_initialized,
             authority,
-            platform_fee,
-            nonce,
-        } = self;
-
-        is_initialized_dst[0] = *is_initialized as u8;
-        authority_dst.copy_from_slice(authority.as_ref());
-        *platform_fee_dst = platform_fee.to_be_bytes();
-        *nonce_dst = nonce.to_be_bytes();
+            // The single platform fee became the maker fee.
+            maker_fee_bps: u64::from_be_bytes(*platform_fee),
+            nonce: u64::from_be_bytes(*nonce),
+            fee_recipient: authority,
+            ..PlatformState::default()
+        })
     }
 }
 
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
 pub struct ListEscrowState {
+    pub layout_version: u8,
+    pub is_initialized: bool,
     pub lister: Pubkey,
     pub mint: Pubkey,
     pub amount: u64,
     pub success: bool,
     pub successful_buyer: Pubkey,
+    pub auction: bool,
+    pub highest_bid: u64,
+    pub highest_bidder: Pubkey,
+    /// Only buyer the listing can settle with; zeroed for public listings.
+    pub allowed_buyer: Pubkey,
+    /// Unix timestamp before which the listing cannot be bought.
+    pub starts_at: i64,
+    /// Funded the list state and vault; their rent goes back here on close.
+    pub payer: Pubkey,
+    /// Bump of the list state address, for signing without a search. Zero
+    /// for listings opened before it was stored.
+    pub state_bump: u8,
+    /// Bump of the list vault under the associated token account program.
+    pub vault_bump: u8,
+    /// Royalty the lister chose to pay, capped by the platform when
+    /// listed. Sales pay the higher of this and the metadata's royalty.
+    pub royalty_bps: u16,
+    /// Curator that has to approve the listing and is paid on its sale.
+    /// The default key for listings made while curation was off.
+    pub curator: Pubkey,
+    /// Whether the curator has approved the listing.
+    pub approved: bool,
+    /// Slot the listing was opened at. Zero for listings opened before it
+    /// was stored.
+    pub created_at_slot: u64,
+    /// Slot the listing was last written at.
+    pub updated_at_slot: u64,
+    /// Bids that have referenced the listing, through `AuctionBid` or a
+    /// `Bid` matched against it.
+    pub bid_count: u64,
+    /// Slot of the latest of those bids.
+    pub last_bid_slot: u64,
+    /// Root of a `merkle` tree of the wallets allowed to buy; zeroed for
+    /// listings without an allowlist.
+    pub allowlist_root: [u8; 32],
+    /// Receives `charity_bps` of the lister's proceeds; the default key for
+    /// listings without a charity split.
+    pub charity: Pubkey,
+    /// Share of the lister's proceeds, in basis points, paid to `charity`.
+    pub charity_bps: u16,
+    /// Frontend the listing came through, credited and paid on its sale;
+    /// the default key for none.
+    pub marketplace: Pubkey,
+    /// Unix timestamp from which the lister can take back an unclaimed
+    /// gift; zero for listings that are not gifts.
+    pub reclaimable_at: i64,
+    /// Asking price in millionths of a dollar, converted to lamports with
+    /// `price_feed` when the listing fills; zero for listings priced in
+    /// lamports.
+    pub price_usd: u64,
+    /// Slots an accepted bid is held for before the sale can be finalized,
+    /// during which it can be reversed; zero for sales that settle at once.
+    pub settlement_delay_slots: u64,
+    /// Can reverse a held sale besides the platform authority; the default
+    /// key for none.
+    pub arbiter: Pubkey,
+    /// Slot from which the held sale to `successful_buyer` can be
+    /// finalized; zero while no sale is held.
+    pub settles_at_slot: u64,
+    /// `VISIBILITY_PUBLIC` or `VISIBILITY_UNLISTED`; the lister can change
+    /// it with `SetVisibility`.
+    pub visibility: u8,
+    /// Price account `price_usd` is converted with: the platform's price
+    /// feed when the listing was made, or the default key for listings
+    /// priced in lamports.
+    pub price_feed: Pubkey,
+    pub reserved: [u64; RESERVED_WORDS - 5],
+}
+
+impl ListEscrowState {
+    pub fn is_private(&self) -> bool {
+        self.allowed_buyer != Pubkey::default() || self.has_allowlist()
+    }
+
+    pub fn has_charity(&self) -> bool {
+        self.charity != Pubkey::default()
+    }
+
+    pub fn has_allowlist(&self) -> bool {
+        self.allowlist_root != [0u8; 32]
+    }
+
+    pub fn allows_buyer(&self, buyer: &Pubkey) -> bool {
+        self.allows_buyer_with_proof(buyer, &[])
+    }
+
+    /// Whether `buyer` may buy the listing, with `proof` of their place on
+    /// its allowlist if it has one.
+    pub fn allows_buyer_with_proof(&self, buyer: &Pubkey, proof: &[[u8; 32]]) -> bool {
+        if self.has_allowlist() {
+            return merkle::verify(proof, &self.allowlist_root, merkle::leaf(buyer));
+        }
+        !self.is_private() || self.allowed_buyer.eq(buyer)
+    }
+
+    pub fn has_started(&self, now: i64) -> bool {
+        now >= self.starts_at
+    }
+
+    /// Whether the listing is a `Gift`, claimed by its recipient instead of
+    /// sold.
+    pub fn is_gift(&self) -> bool {
+        self.reclaimable_at != 0
+    }
+
+    /// Whether the listing's sales are held for a dispute window.
+    pub fn has_delayed_settlement(&self) -> bool {
+        self.settlement_delay_slots != 0
+    }
+
+    /// Whether a sale of the listing is held, waiting to be finalized or
+    /// reversed.
+    pub fn is_settling(&self) -> bool {
+        self.settles_at_slot != 0
+    }
+
+    /// Whether the listing is kept out of public feeds.
+    pub fn is_unlisted(&self) -> bool {
+        self.visibility == VISIBILITY_UNLISTED
+    }
+
+    /// Whether the listing asks `price_usd` instead of `amount`.
+    pub fn is_usd_priced(&self) -> bool {
+        self.price_usd != 0
+    }
+
+    /// Whether the listing still waits on its curator's approval.
+    pub fn is_pending(&self) -> bool {
+        self.curator != Pubkey::default() && !self.approved
+    }
+
+    /// Whether a bid meeting the ask settles the listing at once: it is
+    /// unsold, approved, and neither an auction, a gift nor held for a
+    /// dispute window.
+    pub fn is_fillable(&self) -> bool {
+        !self.success && !self.auction && !self.is_gift() && !self.has_delayed_settlement() && !self.is_pending()
+    }
+
+    /// Counts a bid placed against the listing at `slot`.
+    pub fn record_bid(&mut self, slot: u64) {
+        self.bid_count = self.bid_count.saturating_add(1);
+        self.last_bid_slot = slot;
+        self.updated_at_slot = slot;
+    }
+
+    /// Royalty, in basis points, owed on a sale given the metadata's own.
+    pub fn effective_royalty_bps(&self, metadata_bps: u16) -> u16 {
+        self.royalty_bps.max(metadata_bps)
+    }
 }
 
 impl Sealed for ListEscrowState{}
 
+impl IsInitialized for ListEscrowState{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Discriminator for ListEscrowState{
+    const DISCRIMINATOR: [u8; 8] = *b"listing\0";
+}
+
 impl Pack for ListEscrowState {
     const LEN: usize = LISTESCROWSTATE;
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let src = array_ref![src, 0, ListEscrowState::LEN];
+        unpack_borsh(src)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        pack_borsh(self, dst)
+    }
+
+    fn unpack_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
+        unpack_compat(input)
+    }
+}
+
+impl LegacyLayout for ListEscrowState {
+    const LEGACY_LEN: usize = 105usize;
+    fn unpack_legacy(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, ListEscrowState::LEGACY_LEN];
         let (
             lister,
-            mint, 
+            mint,
             amount,
             success,
-            successful_buyer
+            successful_buyer,
         ) = array_refs![src, 32, 32, 8, 1, 32];
         let success = match success {
             [0] => false,
             [1] => true,
             _ => return Err(ProgramError::InvalidAccountData),
         };
+        let lister = Pubkey::new_from_array(*lister);
         Ok(ListEscrowState{
-            lister: Pubkey::new_from_array(*lister),
+            layout_version: 0,
+            // Legacy escrows predate the flag; any still open are live.
+            is_initialized: true,
+            lister,
             mint: Pubkey::new_from_array(*mint),
             amount: u64::from_be_bytes(*amount),
-            success: success,
+            success,
             successful_buyer: Pubkey::new_from_array(*successful_buyer),
+            payer: lister,
+            visibility: VISIBILITY_PUBLIC,
+            ..ListEscrowState::default()
         })
     }
-
-    fn pack_into_slice(&self, dst: &mut [u8]) {
-        let dst = array_mut_ref![dst, 0, ListEscrowState::LEN];
-        let (
-            lister_dst,
-            mint_dst,
-            amount_dst,
-            success_dst,
-            successful_buyer_dst
-        ) = mut_array_refs![dst, 32, 32, 8, 1, 32];
-
-        let ListEscrowState {
-            lister,
-            mint,
-            amount,
-            success,
-            successful_buyer
-        } = self;
-
-        lister_dst.copy_from_slice(lister.as_ref());
-        mint_dst.copy_from_slice(mint.as_ref());
-        *amount_dst = amount.to_be_bytes();
-        success_dst[0] = *success as u8;
-        successful_buyer_dst.copy_from_slice(successful_buyer.as_ref());
-    }
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
 pub struct BidEscrowState {
+    pub layout_version: u8,
+    pub is_initialized: bool,
     pub bidder: Pubkey,
     pub mint: Pubkey,
     pub amount: u64,
+    pub referrer: Pubkey,
+    pub taker_fee: u64,
+    pub wrapped: bool,
+    /// Funded the bid state and vault rent; it goes back here on close.
+    pub payer: Pubkey,
+    /// First slot at which `WithdrawBid` may close the bid; `u64::MAX`
+    /// while a delayed settlement holds it.
+    pub withdrawable_slot: u64,
+    /// Bump of the bid state address, for signing without a search. Zero
+    /// for bids placed before it was stored.
+    pub state_bump: u8,
+    /// Bump of the bid vault address.
+    pub vault_bump: u8,
+    /// Slot the bid was placed at. Zero for bids placed before it was
+    /// stored.
+    pub created_at_slot: u64,
+    /// Slot the bid was last written at.
+    pub updated_at_slot: u64,
+    /// Auction listing the bid was placed on through `AuctionBid`; the
+    /// default key for other bids.
+    pub auction_listing: Pubkey,
+    /// Frontend the bid came through, credited and paid if it settles;
+    /// the default key for none.
+    pub marketplace: Pubkey,
+    /// Unix timestamp from which anyone can refund the bid with
+    /// `ExpireAndRefund`; zero for bids that don't expire.
+    pub expires_at: i64,
+    pub reserved: [u64; RESERVED_WORDS - 1],
+}
+
+impl BidEscrowState {
+    /// Whether a delayed settlement holds the bid.
+    pub fn is_settling(&self) -> bool {
+        self.withdrawable_slot == u64::MAX
+    }
+
+    /// Whether the bid has an expiry and `now` is past it.
+    pub fn has_expired(&self, now: i64) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
 }
 
 impl Sealed for BidEscrowState{}
 
+impl IsInitialized for BidEscrowState{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Discriminator for BidEscrowState{
+    const DISCRIMINATOR: [u8; 8] = *b"bid\0\0\0\0\0";
+}
+
 impl Pack for BidEscrowState {
     const LEN: usize = BIDESCROWSTATE;
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let src = array_ref![src, 0, BidEscrowState::LEN];
+        unpack_borsh(src)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        pack_borsh(self, dst)
+    }
+
+    fn unpack_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
+        unpack_compat(input)
+    }
+}
+
+impl LegacyLayout for BidEscrowState {
+    const LEGACY_LEN: usize = 72usize;
+    fn unpack_legacy(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, BidEscrowState::LEGACY_LEN];
         let (
             bidder,
-            mint, 
+            mint,
             amount,
         ) = array_refs![src, 32, 32, 8];
+        let bidder = Pubkey::new_from_array(*bidder);
         Ok(BidEscrowState{
-            bidder: Pubkey::new_from_array(*bidder),
+            layout_version: 0,
+            // Legacy escrows predate the flag; any still open are live.
+            is_initialized: true,
+            bidder,
             mint: Pubkey::new_from_array(*mint),
             amount: u64::from_be_bytes(*amount),
+            payer: bidder,
+            ..BidEscrowState::default()
         })
     }
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct CollectionState {
+    pub layout_version: u8,
+    pub is_initialized: bool,
+    pub collection: Pubkey,
+    pub allowed: bool,
+    pub reserved: [u64; RESERVED_WORDS],
+}
+
+impl Sealed for CollectionState{}
+
+impl Discriminator for CollectionState{
+    const DISCRIMINATOR: [u8; 8] = *b"curation";
+}
+
+impl IsInitialized for CollectionState{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for CollectionState {
+    const LEN: usize = COLLECTIONSTATE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        unpack_borsh(src)
+    }
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let dst = array_mut_ref![dst, 0, BidEscrowState::LEN];
-        let (
-            bidder_dst,
-            mint_dst,
-            amount_dst,
-        ) = mut_array_refs![dst, 32, 32, 8];
+        pack_borsh(self, dst)
+    }
+}
 
-        let BidEscrowState {
-            bidder,
-            mint,
-            amount,
-        } = self;
 
-        bidder_dst.copy_from_slice(bidder.as_ref());
-        mint_dst.copy_from_slice(mint.as_ref());
-        *amount_dst = amount.to_be_bytes();
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct UserStats {
+    pub layout_version: u8,
+    pub is_initialized: bool,
+    pub owner: Pubkey,
+    pub total_volume: u64,
+    pub purchases: u64,
+    pub sales: u64,
+    pub last_activity_slot: u64,
+    pub reserved: [u64; RESERVED_WORDS],
+}
+
+impl Sealed for UserStats{}
+
+impl Discriminator for UserStats{
+    const DISCRIMINATOR: [u8; 8] = *b"userstat";
+}
+
+impl IsInitialized for UserStats{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for UserStats {
+    const LEN: usize = USERSTATSSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        unpack_borsh(src)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        pack_borsh(self, dst)
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BookSide {
+    Ask,
+    Bid,
+}
+
+/// Per-collection trading stats. The floor is the cheapest public
+/// fixed-price listing seen since it was last cleared; it resets to zero
+/// when that listing is delisted or sold.
+///
+/// `floor_estimate` is a smoothed floor for other programs, such as lending
+/// protocols, to read straight from the account. Sales and public listings
+/// under it feed it at most once a slot, each clamped to
+/// `FLOOR_OUTLIER_BPS` of it, so one wash trade can't drag it far.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct CollectionStats {
+    pub layout_version: u8,
+    pub is_initialized: bool,
+    pub collection: Pubkey,
+    pub last_sale_price: u64,
+    pub total_volume: u64,
+    pub sale_count: u64,
+    pub floor_price: u64,
+    pub floor_listing: Pubkey,
+    /// Smoothed floor in lamports; zero until the first price is seen.
+    pub floor_estimate: u64,
+    /// Slot `floor_estimate` last moved at.
+    pub floor_updated_slot: u64,
+    pub reserved: [u64; RESERVED_WORDS - 2],
+}
+
+impl Sealed for CollectionStats{}
+
+impl Discriminator for CollectionStats{
+    const DISCRIMINATOR: [u8; 8] = *b"colstats";
+}
+
+impl IsInitialized for CollectionStats{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl CollectionStats {
+    pub fn record_listing(&mut self, list_state: &Pubkey, price: u64) {
+        if self.floor_price == 0 || price < self.floor_price {
+            self.floor_price = price;
+            self.floor_listing = *list_state;
+        }
+    }
+
+    pub fn remove_listing(&mut self, list_state: &Pubkey) {
+        if self.floor_listing.eq(list_state) {
+            self.floor_price = 0;
+            self.floor_listing = Pubkey::default();
+        }
+    }
+
+    /// Feeds a public listing's price to `floor_estimate`. Asks only bound
+    /// the floor from above, so only those under the estimate count.
+    pub fn observe_listing(&mut self, price: u64, slot: u64) {
+        if self.floor_estimate == 0 || price < self.floor_estimate {
+            self.observe_floor(price, slot);
+        }
+    }
+
+    /// Moves `floor_estimate` towards `price`, clamped to the outlier band.
+    /// The estimate keeps `MAX_BPS - FLOOR_SMOOTHING_BPS` of its weight if it
+    /// was updated the slot before, losing it linearly over
+    /// `FLOOR_DECAY_SLOTS`. Later prices in the slot it moved at are ignored.
+    pub fn observe_floor(&mut self, price: u64, slot: u64) {
+        if price == 0 {
+            return;
+        }
+        if self.floor_estimate == 0 {
+            self.floor_estimate = price;
+            self.floor_updated_slot = slot;
+            return;
+        }
+        if slot <= self.floor_updated_slot {
+            return;
+        }
+        let estimate = self.floor_estimate as u128;
+        let band = estimate * FLOOR_OUTLIER_BPS as u128 / MAX_BPS as u128;
+        let clamped = (price as u128).max(estimate - band).min(estimate + band);
+        let elapsed = (slot - self.floor_updated_slot - 1).min(FLOOR_DECAY_SLOTS) as u128;
+        let kept_bps = (MAX_BPS - FLOOR_SMOOTHING_BPS) as u128 * (FLOOR_DECAY_SLOTS as u128 - elapsed) / FLOOR_DECAY_SLOTS as u128;
+        let next = (estimate * kept_bps + clamped * (MAX_BPS as u128 - kept_bps)) / MAX_BPS as u128;
+        // Never past the band, so it fits in a u64 whenever the estimate did.
+        self.floor_estimate = next.min(u64::MAX as u128) as u64;
+        self.floor_updated_slot = slot;
+    }
+
+    /// `floor_estimate`, unless nothing has fed it within
+    /// `FLOOR_DECAY_SLOTS` of `slot`.
+    pub fn floor(&self, slot: u64) -> Option<u64> {
+        if self.floor_estimate == 0 || slot.saturating_sub(self.floor_updated_slot) > FLOOR_DECAY_SLOTS {
+            return None;
+        }
+        Some(self.floor_estimate)
+    }
+}
+
+impl Pack for CollectionStats {
+    const LEN: usize = COLLECTIONSTATSSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        unpack_borsh(src)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        pack_borsh(self, dst)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct BookEntry {
+    pub price: u64,
+    pub maker: Pubkey,
+    pub mint: Pubkey,
+}
+
+/// Best `ORDER_BOOK_DEPTH` asks (cheapest first) and bids (highest first)
+/// for one collection.
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct OrderBook {
+    pub layout_version: u8,
+    pub is_initialized: bool,
+    pub collection: Pubkey,
+    pub asks: Vec<BookEntry>,
+    pub bids: Vec<BookEntry>,
+    pub reserved: [u64; RESERVED_WORDS],
+}
+
+impl OrderBook {
+    fn side_mut(&mut self, side: BookSide) -> &mut Vec<BookEntry> {
+        match side {
+            BookSide::Ask => &mut self.asks,
+            BookSide::Bid => &mut self.bids,
+        }
+    }
+
+    /// Drops `maker`'s entry for `mint` on `side`, if present.
+    pub fn remove(&mut self, side: BookSide, maker: &Pubkey, mint: &Pubkey) {
+        self.side_mut(side).retain(|entry| !(entry.maker.eq(maker) && entry.mint.eq(mint)));
+    }
+
+    /// Inserts `entry` in price order, dropping the worst entry if the side
+    /// is full. Entries worse than a full side are ignored.
+    pub fn insert(&mut self, side: BookSide, entry: BookEntry) {
+        let entries = self.side_mut(side);
+        let position = entries
+            .iter()
+            .position(|existing| match side {
+                BookSide::Ask => entry.price < existing.price,
+                BookSide::Bid => entry.price > existing.price,
+            })
+            .unwrap_or_else(|| entries.len());
+        entries.insert(position, entry);
+        entries.truncate(ORDER_BOOK_DEPTH);
+    }
+}
+
+impl Sealed for OrderBook{}
+
+impl Discriminator for OrderBook{
+    const DISCRIMINATOR: [u8; 8] = *b"book\0\0\0\0";
+}
+
+impl IsInitialized for OrderBook{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for OrderBook {
+    const LEN: usize = ORDERBOOKSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let book: OrderBook = unpack_borsh(src)?;
+        if book.asks.len() > ORDER_BOOK_DEPTH || book.bids.len() > ORDER_BOOK_DEPTH {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(book)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        pack_borsh(self, dst)
+    }
+}
+
+/// Fee tier for holders of the platform's discount mint. Traders holding at
+/// least `threshold` tokens pay `discount_bps` less of each platform fee.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct FeeDiscount {
+    pub layout_version: u8,
+    pub is_initialized: bool,
+    pub discount_mint: Pubkey,
+    pub threshold: u64,
+    pub discount_bps: u64,
+    pub reserved: [u64; RESERVED_WORDS],
+}
+
+impl FeeDiscount {
+    pub fn apply(&self, fee_bps: u64) -> u64 {
+        let kept_bps = MAX_BPS.saturating_sub(self.discount_bps);
+        (fee_bps as u128 * kept_bps as u128 / MAX_BPS as u128) as u64
+    }
+}
+
+impl Sealed for FeeDiscount{}
+
+impl Discriminator for FeeDiscount{
+    const DISCRIMINATOR: [u8; 8] = *b"discount";
+}
+
+impl IsInitialized for FeeDiscount{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for FeeDiscount {
+    const LEN: usize = FEEDISCOUNTSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        unpack_borsh(src)
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        pack_borsh(self, dst)
     }
 }
 
@@ -0,0 +1,662 @@
+//! Multi-asset swaps between two wallets.
+//!
+//! Each side of a swap is up to `SWAP_MAX_ASSETS` NFTs plus any lamports.
+//! `CreateSwap` escrows the maker's side and names the taker and the side
+//! it wants in return; `DepositSwap` escrows the taker's. Once both sides
+//! are in, either party can `ExecuteSwap`, which hands each side to the
+//! other in one instruction. Until then each party can cancel its own
+//! side: the taker takes back its deposit and the swap stays open, while
+//! the maker's cancel returns both sides and closes the swap. Swaps carry
+//! no platform fee.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{attestation, error::NFTError, processor::Processor, state::{self, Discriminator, PlatformState}};
+
+pub const SWAP_MAX_ASSETS: usize = 4usize;
+pub const SWAPSIZE: usize = 100usize + 2 * SWAP_MAX_ASSETS * 32;
+
+pub fn find_swap_state_address(program_id: &Pubkey, maker: &Pubkey, id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[maker.as_ref(), &id.to_be_bytes(), b"Swap", b"State"], program_id)
+}
+
+pub fn find_swap_vault_address(program_id: &Pubkey, swap_state: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[swap_state.as_ref(), mint.as_ref(), b"Swap", b"Vault"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SwapState {
+    pub is_initialized: bool,
+    /// Chosen by the maker to tell its open swaps apart.
+    pub id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub maker_lamports: u64,
+    pub taker_lamports: u64,
+    /// Whether the taker's side is escrowed; the maker's always is.
+    pub taker_deposited: bool,
+    pub maker_mints: Vec<Pubkey>,
+    pub taker_mints: Vec<Pubkey>,
+}
+
+impl Sealed for SwapState{}
+
+impl Discriminator for SwapState{
+    const DISCRIMINATOR: [u8; 8] = *b"swap\0\0\0\0";
+}
+
+impl IsInitialized for SwapState{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for SwapState {
+    const LEN: usize = SWAPSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < SwapState::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, SwapState::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            id,
+            maker,
+            taker,
+            maker_lamports,
+            taker_lamports,
+            taker_deposited,
+            maker_count,
+            maker_mints,
+            taker_count,
+            taker_mints,
+        ) = array_refs![src, 8, 1, 8, 32, 32, 8, 8, 1, 1, SWAP_MAX_ASSETS * 32, 1, SWAP_MAX_ASSETS * 32];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let taker_deposited = match taker_deposited {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let (maker_count, taker_count) = (maker_count[0] as usize, taker_count[0] as usize);
+        if maker_count > SWAP_MAX_ASSETS || taker_count > SWAP_MAX_ASSETS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let unpack_mints = |mints: &[u8], count: usize| -> Vec<Pubkey> {
+            mints
+                .chunks_exact(32)
+                .take(count)
+                .map(|mint| Pubkey::new_from_array(*array_ref![mint, 0, 32]))
+                .collect()
+        };
+        Ok(SwapState{
+            is_initialized,
+            id: u64::from_be_bytes(*id),
+            maker: Pubkey::new_from_array(*maker),
+            taker: Pubkey::new_from_array(*taker),
+            maker_lamports: u64::from_be_bytes(*maker_lamports),
+            taker_lamports: u64::from_be_bytes(*taker_lamports),
+            taker_deposited,
+            maker_mints: unpack_mints(maker_mints, maker_count),
+            taker_mints: unpack_mints(taker_mints, taker_count),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, SwapState::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            id_dst,
+            maker_dst,
+            taker_dst,
+            maker_lamports_dst,
+            taker_lamports_dst,
+            taker_deposited_dst,
+            maker_count_dst,
+            maker_mints_dst,
+            taker_count_dst,
+            taker_mints_dst,
+        ) = mut_array_refs![dst, 8, 1, 8, 32, 32, 8, 8, 1, 1, SWAP_MAX_ASSETS * 32, 1, SWAP_MAX_ASSETS * 32];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+        is_initialized_dst[0] = self.is_initialized as u8;
+        *id_dst = self.id.to_be_bytes();
+        maker_dst.copy_from_slice(self.maker.as_ref());
+        taker_dst.copy_from_slice(self.taker.as_ref());
+        *maker_lamports_dst = self.maker_lamports.to_be_bytes();
+        *taker_lamports_dst = self.taker_lamports.to_be_bytes();
+        taker_deposited_dst[0] = self.taker_deposited as u8;
+        maker_count_dst[0] = self.maker_mints.len() as u8;
+        taker_count_dst[0] = self.taker_mints.len() as u8;
+        for (mints, mints_dst) in [(&self.maker_mints, maker_mints_dst), (&self.taker_mints, taker_mints_dst)] {
+            for byte in mints_dst.iter_mut() {
+                *byte = 0;
+            }
+            for (mint, chunk) in mints.iter().zip(mints_dst.chunks_exact_mut(32)) {
+                chunk.copy_from_slice(mint.as_ref());
+            }
+        }
+    }
+}
+
+/// Opens swap `id`, escrowing `maker_lamports` and each
+/// `(token_account, mint, vault)` that follows the fixed accounts, in
+/// return for `taker_mints` and `taker_lamports` from the taker.
+pub fn process_create_swap(
+    accounts: &[AccountInfo],
+    id: u64,
+    maker_lamports: u64,
+    taker_lamports: u64,
+    taker_mints: Vec<Pubkey>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let maker_info = next_account_info(account_info_iter)?;
+    if !maker_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let taker_info = next_account_info(account_info_iter)?;
+    if taker_info.key.eq(maker_info.key) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let swap_state_account_info = next_account_info(account_info_iter)?;
+
+    let program_info = next_account_info(account_info_iter)?;
+    if !(program_info.key.eq(program_id)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+    let rent = &Rent::get()?;
+
+    let asset_accounts = account_info_iter.as_slice();
+    if asset_accounts.len() % 3 != 0 || asset_accounts.len() / 3 > SWAP_MAX_ASSETS {
+        return Err(NFTError::InvalidInstructionData.into());
+    }
+    if taker_mints.len() > SWAP_MAX_ASSETS
+        || taker_mints.iter().enumerate().any(|(i, mint)| taker_mints[..i].contains(mint))
+    {
+        return Err(NFTError::InvalidInstructionData.into());
+    }
+    // Each side has to give something.
+    if (asset_accounts.is_empty() && maker_lamports == 0) || (taker_mints.is_empty() && taker_lamports == 0) {
+        return Err(NFTError::InvalidInstructionData.into());
+    }
+
+    let (swap_state_pubkey, state_nonce) = find_swap_state_address(program_id, maker_info.key, id);
+    if !(swap_state_account_info.key.eq(&swap_state_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let lamports = rent.minimum_balance(SWAPSIZE)
+        .checked_add(maker_lamports)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let create_state_account_ix = system_instruction::create_account(
+        maker_info.key,
+        &swap_state_pubkey,
+        lamports,
+        SWAPSIZE as u64,
+        program_id);
+    msg!("submitting tx to create program derived swap state account");
+    invoke_signed(
+        &create_state_account_ix,
+        &[
+            maker_info.clone(),
+            swap_state_account_info.clone(),
+            system_program_info.clone(),
+            program_info.clone(),
+        ],
+        &[&[
+            maker_info.key.as_ref(),
+            &id.to_be_bytes(),
+            &b"Swap"[..],
+            &b"State"[..],
+            &[state_nonce]
+        ]],
+    )?;
+
+    let mut maker_mints = Vec::with_capacity(asset_accounts.len() / 3);
+    for asset in asset_accounts.chunks_exact(3) {
+        escrow_asset(
+            &asset[0],
+            &asset[1],
+            &asset[2],
+            maker_info,
+            swap_state_account_info,
+            token_program_info,
+            system_program_info,
+            rent_account_info,
+            rent,
+            program_id,
+        )?;
+        maker_mints.push(*asset[1].key);
+    }
+
+    SwapState::pack(
+        SwapState{
+            is_initialized: true,
+            id,
+            maker: *maker_info.key,
+            taker: *taker_info.key,
+            maker_lamports,
+            taker_lamports,
+            taker_deposited: false,
+            maker_mints,
+            taker_mints,
+        },
+        &mut swap_state_account_info.data.borrow_mut()
+    )
+}
+
+/// Escrows the taker's side: its lamports and a `(token_account, mint,
+/// vault)` for each of the swap's `taker_mints`, in order.
+pub fn process_deposit_swap(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let taker_info = next_account_info(account_info_iter)?;
+    if !taker_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let swap_state_account_info = next_account_info(account_info_iter)?;
+    let mut swap_state = load_swap_state(swap_state_account_info, program_id)?;
+    if !(swap_state.taker.eq(taker_info.key)) {
+        return Err(NFTError::BuyerNotAllowed.into());
+    }
+    if swap_state.taker_deposited {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let rent_account_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_account_info)?;
+    let rent = &Rent::get()?;
+
+    let asset_accounts = account_info_iter.as_slice();
+    if asset_accounts.len() != swap_state.taker_mints.len() * 3 {
+        return Err(NFTError::InvalidInstructionData.into());
+    }
+    for (asset, mint) in asset_accounts.chunks_exact(3).zip(swap_state.taker_mints.iter()) {
+        if !(asset[1].key.eq(mint)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        escrow_asset(
+            &asset[0],
+            &asset[1],
+            &asset[2],
+            taker_info,
+            swap_state_account_info,
+            token_program_info,
+            system_program_info,
+            rent_account_info,
+            rent,
+            program_id,
+        )?;
+    }
+
+    if swap_state.taker_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(taker_info.key, swap_state_account_info.key, swap_state.taker_lamports),
+            &[
+                taker_info.clone(),
+                swap_state_account_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
+    swap_state.taker_deposited = true;
+    SwapState::pack(swap_state, &mut swap_state_account_info.data.borrow_mut())
+}
+
+/// Called by either party once both sides are escrowed. After the fixed
+/// accounts come a `(vault, token_account)` for each of the maker's mints,
+/// with the taker's token accounts, then one for each of the taker's
+/// mints, with the maker's.
+pub fn process_execute_swap(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let signer_info = next_account_info(account_info_iter)?;
+    if !signer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let swap_state_account_info = next_account_info(account_info_iter)?;
+    let swap_state = load_swap_state(swap_state_account_info, program_id)?;
+    if !(swap_state.maker.eq(signer_info.key)) && !(swap_state.taker.eq(signer_info.key)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+    if !swap_state.taker_deposited {
+        return Err(NFTError::SwapNotFunded.into());
+    }
+
+    let maker_info = next_account_info(account_info_iter)?;
+    let taker_info = next_account_info(account_info_iter)?;
+    if !(maker_info.key.eq(&swap_state.maker)) || !(taker_info.key.eq(&swap_state.taker)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    attestation::check_not_required(&state_info)?;
+
+    let asset_accounts = account_info_iter.as_slice();
+    if asset_accounts.len() != (swap_state.maker_mints.len() + swap_state.taker_mints.len()) * 2 {
+        return Err(NFTError::InvalidInstructionData.into());
+    }
+    let (maker_assets, taker_assets) = asset_accounts.split_at(swap_state.maker_mints.len() * 2);
+    release_assets(&swap_state, &swap_state.maker_mints, maker_assets, taker_info.key, maker_info, swap_state_account_info, token_program_info, program_id)?;
+    release_assets(&swap_state, &swap_state.taker_mints, taker_assets, maker_info.key, taker_info, swap_state_account_info, token_program_info, program_id)?;
+
+    move_lamports(swap_state_account_info, taker_info, swap_state.maker_lamports)?;
+    move_lamports(swap_state_account_info, maker_info, swap_state.taker_lamports)?;
+    Processor::close_account(swap_state_account_info, maker_info, program_id)
+}
+
+/// Called by either party before the swap executes. The taker takes back
+/// its deposit, passing a `(vault, token_account)` for each of its mints,
+/// and the swap stays open. The maker passes one for each of its own mints
+/// and, if the taker has deposited, then each of the taker's; both sides
+/// are returned and the swap closes.
+pub fn process_cancel_swap(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let signer_info = next_account_info(account_info_iter)?;
+    if !signer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let swap_state_account_info = next_account_info(account_info_iter)?;
+    let mut swap_state = load_swap_state(swap_state_account_info, program_id)?;
+
+    let maker_info = next_account_info(account_info_iter)?;
+    let taker_info = next_account_info(account_info_iter)?;
+    if !(maker_info.key.eq(&swap_state.maker)) || !(taker_info.key.eq(&swap_state.taker)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let asset_accounts = account_info_iter.as_slice();
+    if signer_info.key.eq(&swap_state.taker) {
+        if !swap_state.taker_deposited {
+            return Err(NFTError::SwapNotFunded.into());
+        }
+        if asset_accounts.len() != swap_state.taker_mints.len() * 2 {
+            return Err(NFTError::InvalidInstructionData.into());
+        }
+        release_assets(&swap_state, &swap_state.taker_mints, asset_accounts, taker_info.key, taker_info, swap_state_account_info, token_program_info, program_id)?;
+        move_lamports(swap_state_account_info, taker_info, swap_state.taker_lamports)?;
+        swap_state.taker_deposited = false;
+        return SwapState::pack(swap_state, &mut swap_state_account_info.data.borrow_mut());
+    }
+    if !signer_info.key.eq(&swap_state.maker) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let taker_count = if swap_state.taker_deposited { swap_state.taker_mints.len() } else { 0 };
+    if asset_accounts.len() != (swap_state.maker_mints.len() + taker_count) * 2 {
+        return Err(NFTError::InvalidInstructionData.into());
+    }
+    let (maker_assets, taker_assets) = asset_accounts.split_at(swap_state.maker_mints.len() * 2);
+    release_assets(&swap_state, &swap_state.maker_mints, maker_assets, maker_info.key, maker_info, swap_state_account_info, token_program_info, program_id)?;
+    if swap_state.taker_deposited {
+        release_assets(&swap_state, &swap_state.taker_mints, taker_assets, taker_info.key, taker_info, swap_state_account_info, token_program_info, program_id)?;
+        move_lamports(swap_state_account_info, taker_info, swap_state.taker_lamports)?;
+    }
+    Processor::close_account(swap_state_account_info, maker_info, program_id)
+}
+
+fn load_swap_state(swap_state_account_info: &AccountInfo, program_id: &Pubkey) -> Result<SwapState, ProgramError> {
+    Processor::assert_owned_by(swap_state_account_info, program_id)?;
+    let swap_state = SwapState::unpack(&swap_state_account_info.data.borrow())?;
+    let (swap_state_pubkey, _) = find_swap_state_address(program_id, &swap_state.maker, swap_state.id);
+    if !(swap_state_account_info.key.eq(&swap_state_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(swap_state)
+}
+
+/// Creates `vault_account_info` for `mint_account_info`, owned by the swap,
+/// and moves `owner_info`'s NFT into it. The owner pays the vault's rent.
+#[allow(clippy::too_many_arguments)]
+fn escrow_asset<'a>(
+    token_account_info: &AccountInfo<'a>,
+    mint_account_info: &AccountInfo<'a>,
+    vault_account_info: &AccountInfo<'a>,
+    owner_info: &AccountInfo<'a>,
+    swap_state_account_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    rent_account_info: &AccountInfo<'a>,
+    rent: &Rent,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if !(mint_account_info.owner.eq(&spl_token::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let token_account = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+    if !(token_account.owner.eq(owner_info.key)) || !(token_account.mint.eq(mint_account_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (vault_pubkey, vault_nonce) = find_swap_vault_address(program_id, swap_state_account_info.key, mint_account_info.key);
+    if !(vault_account_info.key.eq(&vault_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let create_vault_account_ix = system_instruction::create_account(
+        owner_info.key,
+        &vault_pubkey,
+        rent.minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN as u64,
+        &spl_token::id());
+    invoke_signed(
+        &create_vault_account_ix,
+        &[
+            owner_info.clone(),
+            vault_account_info.clone(),
+            system_program_info.clone(),
+            token_program_info.clone(),
+        ],
+        &[&[
+            swap_state_account_info.key.as_ref(),
+            mint_account_info.key.as_ref(),
+            &b"Swap"[..],
+            &b"Vault"[..],
+            &[vault_nonce]
+        ]],
+    )?;
+
+    let initialize_vault_account_ix = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &vault_pubkey,
+        mint_account_info.key,
+        swap_state_account_info.key
+    )?;
+    invoke(
+        &initialize_vault_account_ix,
+        &[
+            token_program_info.clone(),
+            vault_account_info.clone(),
+            mint_account_info.clone(),
+            swap_state_account_info.clone(),
+            rent_account_info.clone()
+        ],
+    )?;
+
+    let transfer_token_ix = spl_token::instruction::transfer_checked(
+        &spl_token::id(),
+        token_account_info.key,
+        mint_account_info.key,
+        &vault_pubkey,
+        owner_info.key,
+        &[
+            owner_info.key
+        ],
+        1,
+        0
+    )?;
+    invoke(
+        &transfer_token_ix,
+        &[
+            token_program_info.clone(),
+            token_account_info.clone(),
+            mint_account_info.clone(),
+            vault_account_info.clone(),
+            owner_info.clone()
+        ],
+    )
+}
+
+/// Sends the NFT in each `(vault, token_account)` of `asset_accounts`, in
+/// the order of `mints`, to `recipient`'s token account and closes the
+/// vault, returning its rent to `depositor_info`, who paid it.
+#[allow(clippy::too_many_arguments)]
+fn release_assets<'a>(
+    swap_state: &SwapState,
+    mints: &[Pubkey],
+    asset_accounts: &[AccountInfo<'a>],
+    recipient: &Pubkey,
+    depositor_info: &AccountInfo<'a>,
+    swap_state_account_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (swap_state_pubkey, state_nonce) = find_swap_state_address(program_id, &swap_state.maker, swap_state.id);
+    let id_bytes = swap_state.id.to_be_bytes();
+    let state_seeds: &[&[u8]] = &[
+        swap_state.maker.as_ref(),
+        &id_bytes,
+        &b"Swap"[..],
+        &b"State"[..],
+        &[state_nonce]
+    ];
+
+    for (asset, mint) in asset_accounts.chunks_exact(2).zip(mints.iter()) {
+        let (vault_account_info, token_account_info) = (&asset[0], &asset[1]);
+        let (vault_pubkey, _) = find_swap_vault_address(program_id, &swap_state_pubkey, mint);
+        if !(vault_account_info.key.eq(&vault_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let token_account = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+        if !(token_account.owner.eq(recipient)) || !(token_account.mint.eq(mint)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let transfer_token_ix = spl_token::instruction::transfer(
+            &spl_token::id(),
+            &vault_pubkey,
+            token_account_info.key,
+            &swap_state_pubkey,
+            &[&swap_state_pubkey],
+            1
+        )?;
+        invoke_signed(
+            &transfer_token_ix,
+            &[
+                token_program_info.clone(),
+                vault_account_info.clone(),
+                token_account_info.clone(),
+                swap_state_account_info.clone()
+            ],
+            &[state_seeds],
+        )?;
+
+        let close_ix = spl_token::instruction::close_account(
+            &spl_token::id(),
+            &vault_pubkey,
+            depositor_info.key,
+            &swap_state_pubkey,
+            &[&swap_state_pubkey]
+        )?;
+        invoke_signed(
+            &close_ix,
+            &[
+                token_program_info.clone(),
+                vault_account_info.clone(),
+                depositor_info.clone(),
+                swap_state_account_info.clone(),
+            ],
+            &[state_seeds],
+        )?;
+    }
+    Ok(())
+}
+
+/// Moves escrowed lamports out of the program-owned swap state.
+fn move_lamports(swap_state_account_info: &AccountInfo, recipient_info: &AccountInfo, lamports: u64) -> ProgramResult {
+    let swap_lamports = swap_state_account_info.lamports()
+        .checked_sub(lamports)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let recipient_lamports = recipient_info.lamports()
+        .checked_add(lamports)
+        .ok_or(NFTError::NumericalOverflow)?;
+    **swap_state_account_info.try_borrow_mut_lamports()? = swap_lamports;
+    **recipient_info.try_borrow_mut_lamports()? = recipient_lamports;
+    Ok(())
+}
@@ -0,0 +1,40 @@
+//! Just enough of Token-2022's mint layout to turn soulbound mints away.
+//!
+//! Listings only escrow SPL Token NFTs, but a Token-2022 mint with the
+//! non-transferable extension is worth its own error: it can never move,
+//! so its holder is told as much instead of that the mint is unsupported.
+use std::convert::TryInto;
+
+pub mod spl_token_2022_program {
+    solana_program::declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+}
+
+/// Extended mints are padded to a token account's length, so the account
+/// type byte sits at the same offset in both.
+const ACCOUNT_TYPE_OFFSET: usize = 165;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+const EXTENSION_UNINITIALIZED: u16 = 0;
+const EXTENSION_NON_TRANSFERABLE: u16 = 9;
+
+/// Whether the Token-2022 mint in `data` carries the non-transferable
+/// extension, found by walking its type-length-value extensions.
+pub fn is_non_transferable(data: &[u8]) -> bool {
+    if data.get(ACCOUNT_TYPE_OFFSET) != Some(&ACCOUNT_TYPE_MINT) {
+        return false;
+    }
+    let mut extensions = &data[ACCOUNT_TYPE_OFFSET + 1..];
+    while extensions.len() >= 4 {
+        let extension_type = u16::from_le_bytes(extensions[0..2].try_into().unwrap());
+        let length = u16::from_le_bytes(extensions[2..4].try_into().unwrap()) as usize;
+        match extension_type {
+            EXTENSION_UNINITIALIZED => return false,
+            EXTENSION_NON_TRANSFERABLE => return true,
+            _ => {}
+        }
+        extensions = match extensions.get(4 + length..) {
+            Some(rest) => rest,
+            None => return false,
+        };
+    }
+    false
+}
@@ -0,0 +1,396 @@
+//! Trait offers: bids on any NFT from a set of mints.
+//!
+//! The bidder escrows an offer against the root of a Merkle tree of mints,
+//! typically every mint in a collection with some trait, and any holder of
+//! one of those mints can take it with `AcceptTraitOffer` by proving their
+//! mint is in the tree. The root is the bidder's own unless an attestor,
+//! like a trait indexer, co-signs `MakeTraitOffer`; the attestor is kept in
+//! the offer so sellers can tell which roots someone vouched for. An offer
+//! is filled once and can be cancelled by the bidder until then.
+//!
+//! Lamports are escrowed in the offer account itself. Fees match `AcceptOtc`:
+//! the taker fee is escrowed on top of the amount and the maker fee comes
+//! out of the seller's proceeds. The seller funds the bidder's associated
+//! token account if it doesn't exist yet.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    system_program,
+    sysvar::Sysvar,
+};
+
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+
+use crate::{attestation, error::NFTError, merkle, processor::Processor, state::{self, Discriminator, PlatformState}};
+
+pub const TRAITOFFERSIZE: usize = 121usize;
+
+pub fn find_trait_offer_address(program_id: &Pubkey, bidder: &Pubkey, root: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[bidder.as_ref(), root, b"Trait", b"Offer"], program_id)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TraitOffer {
+    pub is_initialized: bool,
+    pub bidder: Pubkey,
+    /// Root of the Merkle tree of mints the offer is good for.
+    pub root: [u8; 32],
+    /// Who vouched for `root`; the default key if the bidder supplied it.
+    pub attestor: Pubkey,
+    pub amount: u64,
+    pub taker_fee: u64,
+}
+
+impl Sealed for TraitOffer{}
+
+impl Discriminator for TraitOffer{
+    const DISCRIMINATOR: [u8; 8] = *b"trtoffer";
+}
+
+impl IsInitialized for TraitOffer{
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for TraitOffer {
+    const LEN: usize = TRAITOFFERSIZE;
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < TraitOffer::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, TraitOffer::LEN];
+        let (
+            discriminator,
+            is_initialized,
+            bidder,
+            root,
+            attestor,
+            amount,
+            taker_fee,
+        ) = array_refs![src, 8, 1, 32, 32, 32, 8, 8];
+        if *discriminator != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(TraitOffer{
+            is_initialized,
+            bidder: Pubkey::new_from_array(*bidder),
+            root: *root,
+            attestor: Pubkey::new_from_array(*attestor),
+            amount: u64::from_be_bytes(*amount),
+            taker_fee: u64::from_be_bytes(*taker_fee),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, TraitOffer::LEN];
+        let (
+            discriminator_dst,
+            is_initialized_dst,
+            bidder_dst,
+            root_dst,
+            attestor_dst,
+            amount_dst,
+            taker_fee_dst,
+        ) = mut_array_refs![dst, 8, 1, 32, 32, 32, 8, 8];
+
+        *discriminator_dst = Self::DISCRIMINATOR;
+
+        let TraitOffer {
+            is_initialized,
+            bidder,
+            root,
+            attestor,
+            amount,
+            taker_fee,
+        } = self;
+
+        is_initialized_dst[0] = *is_initialized as u8;
+        bidder_dst.copy_from_slice(bidder.as_ref());
+        *root_dst = *root;
+        attestor_dst.copy_from_slice(attestor.as_ref());
+        *amount_dst = amount.to_be_bytes();
+        *taker_fee_dst = taker_fee.to_be_bytes();
+    }
+}
+
+/// Escrows `amount` lamports plus the taker fee in a new offer for any mint
+/// under `root`. An attestor, if any, signs as the last account.
+pub fn process_make_trait_offer(
+    accounts: &[AccountInfo],
+    amount: u64,
+    root: [u8; 32],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let bidder_info = next_account_info(account_info_iter)?;
+    if !bidder_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let offer_account_info = next_account_info(account_info_iter)?;
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let state_info = load_platform_state(state_account_info, program_id)?;
+    if state_info.is_paused(state::PAUSE_BID) {
+        return Err(NFTError::InstructionPaused.into());
+    }
+    if amount < state_info.min_bid_lamports {
+        return Err(NFTError::BelowMinimumPrice.into());
+    }
+
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let attestor = match account_info_iter.next() {
+        Some(attestor_info) if attestor_info.is_signer => *attestor_info.key,
+        Some(_) => return Err(ProgramError::MissingRequiredSignature),
+        None => Pubkey::default(),
+    };
+
+    let (offer_pubkey, offer_nonce) = find_trait_offer_address(program_id, bidder_info.key, &root);
+    if !(offer_account_info.key.eq(&offer_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let taker_fee = Processor::calculate_fee(amount, state_info.taker_fee_bps)?;
+    let lamports = Rent::get()?.minimum_balance(TRAITOFFERSIZE)
+        .checked_add(amount)
+        .and_then(|lamports| lamports.checked_add(taker_fee))
+        .ok_or(NFTError::NumericalOverflow)?;
+    msg!("submitting tx to create program derived trait offer account");
+    invoke_signed(
+        &system_instruction::create_account(bidder_info.key, &offer_pubkey, lamports, TRAITOFFERSIZE as u64, program_id),
+        &[
+            bidder_info.clone(),
+            offer_account_info.clone(),
+            system_program_info.clone(),
+        ],
+        &[&[
+            bidder_info.key.as_ref(),
+            &root,
+            &b"Trait"[..],
+            &b"Offer"[..],
+            &[offer_nonce]
+        ]],
+    )?;
+
+    TraitOffer::pack(
+        TraitOffer{
+            is_initialized: true,
+            bidder: *bidder_info.key,
+            root,
+            attestor,
+            amount,
+            taker_fee,
+        },
+        &mut offer_account_info.data.borrow_mut()
+    )?;
+
+    Ok(())
+}
+
+/// Sells the signer's NFT into an offer, given the Merkle proof that its
+/// mint is under the offer's root.
+pub fn process_accept_trait_offer(
+    accounts: &[AccountInfo],
+    proof: &[[u8; 32]],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let seller_info = next_account_info(account_info_iter)?;
+    if !seller_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let seller_token_account_info = next_account_info(account_info_iter)?;
+    let mint_account_info = next_account_info(account_info_iter)?;
+    Processor::check_nft_mint(mint_account_info)?;
+    let seller_token_account = spl_token::state::Account::unpack(&seller_token_account_info.data.borrow())?;
+    if !(seller_token_account.owner.eq(seller_info.key)) || !(seller_token_account.mint.eq(mint_account_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::check_seller_token_account(&seller_token_account)?;
+
+    let offer_account_info = next_account_info(account_info_iter)?;
+    let offer = load_trait_offer(offer_account_info, program_id)?;
+    if !merkle::verify(proof, &offer.root, merkle::leaf(mint_account_info.key)) {
+        return Err(NFTError::InvalidMerkleProof.into());
+    }
+
+    let bidder_info = next_account_info(account_info_iter)?;
+    if !(bidder_info.key.eq(&offer.bidder)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let bidder_token_account_info = next_account_info(account_info_iter)?;
+
+    let state_account_info = next_account_info(account_info_iter)?;
+    let mut state_info = load_platform_state(state_account_info, program_id)?;
+    if state_info.is_paused(state::PAUSE_SALE) {
+        return Err(NFTError::InstructionPaused.into());
+    }
+    attestation::check_not_required(&state_info)?;
+
+    let fee_recipient_info = next_account_info(account_info_iter)?;
+    if !state_info.fee_recipient.eq(fee_recipient_info.key) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    let token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_token::id().eq(token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let system_program_info = next_account_info(account_info_iter)?;
+    if !(system_program_info.key.eq(&system_program::id())) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let rent_info = next_account_info(account_info_iter)?;
+    Processor::check_rent_sysvar(rent_info)?;
+    let associated_token_program_info = next_account_info(account_info_iter)?;
+    if !(spl_associated_token_account::id().eq(associated_token_program_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if bidder_token_account_info.data_is_empty() {
+        let associated_token_pubkey = spl_associated_token_account::get_associated_token_address(
+            bidder_info.key,
+            mint_account_info.key,
+        );
+        if !(bidder_token_account_info.key.eq(&associated_token_pubkey)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        invoke(
+            &spl_associated_token_account::create_associated_token_account(
+                seller_info.key,
+                bidder_info.key,
+                mint_account_info.key,
+            ),
+            &[
+                seller_info.clone(),
+                bidder_token_account_info.clone(),
+                bidder_info.clone(),
+                mint_account_info.clone(),
+                system_program_info.clone(),
+                token_program_info.clone(),
+                rent_info.clone(),
+                associated_token_program_info.clone(),
+            ],
+        )?;
+    }
+    let bidder_token_account = spl_token::state::Account::unpack(&bidder_token_account_info.data.borrow())?;
+    if !(bidder_token_account.owner.eq(bidder_info.key)) || !(bidder_token_account.mint.eq(mint_account_info.key)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    invoke(
+        &spl_token::instruction::transfer_checked(
+            &spl_token::id(),
+            seller_token_account_info.key,
+            mint_account_info.key,
+            bidder_token_account_info.key,
+            seller_info.key,
+            &[seller_info.key],
+            1,
+            0
+        )?,
+        &[
+            token_program_info.clone(),
+            seller_token_account_info.clone(),
+            mint_account_info.clone(),
+            bidder_token_account_info.clone(),
+            seller_info.clone(),
+        ],
+    )?;
+
+    let maker_fee = Processor::calculate_fee(offer.amount, state_info.maker_fee_bps)?;
+    let platform_fee = maker_fee
+        .checked_add(offer.taker_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let seller_proceeds = offer.amount
+        .checked_sub(maker_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let offer_lamports = offer_account_info.lamports()
+        .checked_sub(seller_proceeds)
+        .and_then(|lamports| lamports.checked_sub(platform_fee))
+        .ok_or(NFTError::NumericalOverflow)?;
+    let seller_lamports = seller_info.lamports()
+        .checked_add(seller_proceeds)
+        .ok_or(NFTError::NumericalOverflow)?;
+    let fee_recipient_lamports = fee_recipient_info.lamports()
+        .checked_add(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    **offer_account_info.try_borrow_mut_lamports()? = offer_lamports;
+    **seller_info.try_borrow_mut_lamports()? = seller_lamports;
+    **fee_recipient_info.try_borrow_mut_lamports()? = fee_recipient_lamports;
+    Processor::close_account(offer_account_info, bidder_info, program_id)?;
+
+    state_info.sale_count = state_info.sale_count
+        .checked_add(1)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.total_volume = state_info.total_volume
+        .checked_add(offer.amount)
+        .ok_or(NFTError::NumericalOverflow)?;
+    state_info.total_fees = state_info.total_fees
+        .checked_add(platform_fee)
+        .ok_or(NFTError::NumericalOverflow)?;
+    PlatformState::pack(state_info, &mut state_account_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+/// Called by the bidder; closes the offer and returns everything in it.
+pub fn process_cancel_trait_offer(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let bidder_info = next_account_info(account_info_iter)?;
+    if !bidder_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let offer_account_info = next_account_info(account_info_iter)?;
+    let offer = load_trait_offer(offer_account_info, program_id)?;
+    if !(offer.bidder.eq(bidder_info.key)) {
+        return Err(NFTError::InvalidAuthority.into());
+    }
+
+    Processor::close_account(offer_account_info, bidder_info, program_id)
+}
+
+fn load_trait_offer(offer_account_info: &AccountInfo, program_id: &Pubkey) -> Result<TraitOffer, ProgramError> {
+    Processor::assert_owned_by(offer_account_info, program_id)?;
+    let offer = TraitOffer::unpack(&offer_account_info.data.borrow())?;
+    let (offer_pubkey, _) = find_trait_offer_address(program_id, &offer.bidder, &offer.root);
+    if !(offer_account_info.key.eq(&offer_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(offer)
+}
+
+fn load_platform_state(state_account_info: &AccountInfo, program_id: &Pubkey) -> Result<PlatformState, ProgramError> {
+    let (state_account_pubkey, _) = state::find_platform_state_address(program_id);
+    if !(state_account_info.key.eq(&state_account_pubkey)) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Processor::assert_owned_by(state_account_info, program_id)?;
+    let state_info = PlatformState::unpack_unchecked(&state_account_info.data.borrow())?;
+    if !state_info.is_initialized(){
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(state_info)
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, rent::Rent};
+use solana_sdk::{account::Account, signature::{Keypair, Signer}};
+
+use nft_trading::{attestation, error::NFTError, instruction};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_only_attested_wallets_list_and_bid() {
+    let program_id = Pubkey::new_unique();
+    let attestor = Pubkey::new_unique();
+    let lister = Keypair::new();
+    let (attestation_pubkey, _) = attestation::find_attestation_address(&attestor, &lister.pubkey());
+    let mut program_test = common::program_test(program_id);
+    program_test.add_account(attestation_pubkey, Account {
+        lamports: Rent::default().minimum_balance(1),
+        data: vec![1],
+        owner: attestor,
+        ..Account::default()
+    });
+    program_test.add_account(lister.pubkey(), Account {
+        lamports: 10 * LAMPORTS_PER_SOL,
+        ..Account::default()
+    });
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+    let authority = ctx.payer.pubkey();
+
+    let stranger = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let ix = instruction::set_attestation(&program_id, &stranger.pubkey(), &attestor, true);
+    let result = ctx.process(&[ix], &[&stranger]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidAuthority as u32));
+    let ix = instruction::set_attestation(&program_id, &authority, &Pubkey::default(), true);
+    let result = ctx.process(&[ix], &[]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidInstructionData as u32));
+    let ix = instruction::set_attestation(&program_id, &authority, &attestor, true);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let list = || instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let result = ctx.process(&[list()], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::AttestationRequired as u32));
+    let ix = instruction::with_attestation(list(), &attestor, &lister.pubkey());
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    // The bidder holds no attestation, so naming its address does not help.
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bid = || instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    let result = ctx.process(&[bid()], &[&bidder]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::AttestationRequired as u32));
+    let ix = instruction::with_attestation(bid(), &attestor, &bidder.pubkey());
+    let result = ctx.process(&[ix], &[&bidder]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::AttestationRequired as u32));
+
+    let ix = instruction::set_attestation(&program_id, &authority, &attestor, false);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE + 1, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_entry_points_without_attestations_are_refused() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+    let attestor = Pubkey::new_unique();
+
+    let maker = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let taker = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, maker_token) = ctx.create_nft(&maker.pubkey()).await;
+    let taker_token = ctx.create_token_account(&mint, &taker.pubkey()).await;
+    let ix = instruction::create_otc(&program_id, &maker.pubkey(), &maker_token, &mint, &taker.pubkey(), PRICE);
+    ctx.process(&[ix], &[&maker]).await.unwrap();
+
+    let ix = instruction::set_attestation(&program_id, &authority, &attestor, true);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let accept = instruction::accept_otc(&program_id, &taker.pubkey(), &taker_token, &mint, &maker.pubkey(), &authority);
+    let result = ctx.process(&[accept.clone()], &[&taker]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::AttestationRequired as u32));
+
+    let ix = instruction::set_attestation(&program_id, &authority, &attestor, false);
+    ctx.process(&[ix, accept], &[&taker]).await.unwrap();
+    assert_eq!(ctx.token_balance(&taker_token).await, 1);
+}
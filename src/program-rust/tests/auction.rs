@@ -0,0 +1,86 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{instruction, state::{self, ListEscrowState}};
+
+const RESERVE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_auction_bid_refunds_outbid_leader() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let alice = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bob = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, RESERVE, true, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+
+    let ix = instruction::auction_bid(&program_id, &alice.pubkey(), &mint, &lister.pubkey(), RESERVE - 1, None, None);
+    assert!(ctx.process(&[ix], &[&alice]).await.is_err());
+
+    let alice_balance = ctx.balance(&alice.pubkey()).await;
+    let ix = instruction::auction_bid(&program_id, &alice.pubkey(), &mint, &lister.pubkey(), RESERVE, None, None);
+    ctx.process(&[ix], &[&alice]).await.unwrap();
+    let list_state = ListEscrowState::unpack_unchecked(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.highest_bid, RESERVE);
+    assert_eq!(list_state.highest_bidder, alice.pubkey());
+    assert_eq!(list_state.layout_version, state::LAYOUT_VERSION);
+    assert!(list_state.updated_at_slot >= list_state.created_at_slot);
+    assert_eq!(list_state.bid_count, 1);
+
+    let ix = instruction::auction_bid(&program_id, &bob.pubkey(), &mint, &lister.pubkey(), RESERVE, Some(&alice.pubkey()), None);
+    assert!(ctx.process(&[ix], &[&bob]).await.is_err());
+
+    let ix = instruction::auction_bid(&program_id, &bob.pubkey(), &mint, &lister.pubkey(), 2 * RESERVE, Some(&alice.pubkey()), None);
+    ctx.process(&[ix], &[&bob]).await.unwrap();
+    let list_state = ListEscrowState::unpack_unchecked(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.highest_bid, 2 * RESERVE);
+    assert_eq!(list_state.highest_bidder, bob.pubkey());
+    assert_eq!(list_state.bid_count, 2);
+    assert_eq!(list_state.last_bid_slot, list_state.updated_at_slot);
+    assert_eq!(ctx.balance(&alice.pubkey()).await, alice_balance);
+    let (alice_bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &alice.pubkey());
+    assert!(ctx.get_account(&alice_bid_state_pubkey).await.is_none());
+
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bob.pubkey(), &authority, None, &[], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let list_state = ListEscrowState::unpack_unchecked(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert!(list_state.success);
+    assert_eq!(list_state.successful_buyer, bob.pubkey());
+}
+
+#[tokio::test]
+async fn test_auction_accepts_only_highest_bid() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let alice = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bob = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, RESERVE, true, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::auction_bid(&program_id, &alice.pubkey(), &mint, &lister.pubkey(), RESERVE, None, None);
+    ctx.process(&[ix], &[&alice]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bob.pubkey(), &mint, 2 * RESERVE, false, None);
+    ctx.process(&[ix], &[&bob]).await.unwrap();
+
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bob.pubkey(), &authority, None, &[], None);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let list_state = ListEscrowState::unpack_unchecked(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert!(!list_state.success);
+    assert_ne!(list_state.highest_bidder, Pubkey::default());
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{error::NFTError, instruction, state::{self, ListEscrowState, PlatformState}};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_list_many_and_delist_many() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint_a, token_a) = ctx.create_nft(&lister.pubkey()).await;
+    let (mint_b, token_b) = ctx.create_nft(&lister.pubkey()).await;
+    let (platform_state_pubkey, _) = state::find_platform_state_address(&program_id);
+
+    let ix = instruction::list_many(&program_id, &lister.pubkey(), &[(token_a, mint_a, PRICE), (token_b, mint_b, 2 * PRICE)], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&token_a).await, 0);
+    assert_eq!(ctx.token_balance(&token_b).await, 0);
+    let (list_state_b, _) = state::find_list_state_address(&program_id, &mint_b, &lister.pubkey());
+    let list_state = ListEscrowState::unpack_unchecked(&ctx.get_account(&list_state_b).await.unwrap().data).unwrap();
+    assert_eq!(list_state.amount, 2 * PRICE);
+    let platform_state = PlatformState::unpack(&ctx.get_account(&platform_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform_state.active_listings, 2);
+
+    let ix = instruction::delist_many(&program_id, &lister.pubkey(), &[(token_a, mint_a), (token_b, mint_b)]);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&token_a).await, 1);
+    assert_eq!(ctx.token_balance(&token_b).await, 1);
+    assert!(ctx.get_account(&list_state_b).await.is_none());
+    let platform_state = PlatformState::unpack(&ctx.get_account(&platform_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform_state.active_listings, 0);
+}
+
+#[tokio::test]
+async fn test_batch_execute_sale_settles_each_bid() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint_a, token_a) = ctx.create_nft(&lister.pubkey()).await;
+    let (mint_b, token_b) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list_many(&program_id, &lister.pubkey(), &[(token_a, mint_a, PRICE), (token_b, mint_b, PRICE)], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let bid_a = instruction::bid(&program_id, &bidder.pubkey(), &mint_a, PRICE, false, None);
+    let bid_b = instruction::bid(&program_id, &bidder.pubkey(), &mint_b, PRICE, false, None);
+    ctx.process(&[bid_a, bid_b], &[&bidder]).await.unwrap();
+
+    let bidder_token_a = ctx.create_token_account(&mint_a, &bidder.pubkey()).await;
+    let bidder_token_b = ctx.create_token_account(&mint_b, &bidder.pubkey()).await;
+    let ix = instruction::batch_execute_sale(&program_id, &[
+        instruction::accept_bid(&program_id, &lister.pubkey(), &mint_a, &bidder.pubkey(), &authority, None, &[], Some(&bidder_token_a)),
+        instruction::accept_bid(&program_id, &lister.pubkey(), &mint_b, &bidder.pubkey(), &authority, None, &[], Some(&bidder_token_b)),
+    ]);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&bidder_token_a).await, 1);
+    assert_eq!(ctx.token_balance(&bidder_token_b).await, 1);
+
+    let (platform_state_pubkey, _) = state::find_platform_state_address(&program_id);
+    let platform_state = PlatformState::unpack(&ctx.get_account(&platform_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform_state.sale_count, 2);
+    assert_eq!(platform_state.active_listings, 0);
+}
+
+#[tokio::test]
+async fn test_sweep_buy_stops_at_the_budget() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let buyer = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint_a, token_a) = ctx.create_nft(&lister.pubkey()).await;
+    let (mint_b, token_b) = ctx.create_nft(&lister.pubkey()).await;
+    let (mint_c, token_c) = ctx.create_nft(&lister.pubkey()).await;
+    let ix = instruction::list_many(&program_id, &lister.pubkey(), &[(token_a, mint_a, PRICE), (token_b, mint_b, 3 * PRICE), (token_c, mint_c, PRICE)], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let mut buyer_tokens = Vec::new();
+    for mint in &[mint_a, mint_b, mint_c] {
+        buyer_tokens.push(ctx.create_token_account(mint, &buyer.pubkey()).await);
+    }
+    let purchases = |max_price: u64| -> Vec<_> {
+        [mint_a, mint_b, mint_c].iter().zip(&buyer_tokens).map(|(mint, buyer_token)| {
+            let bid = instruction::bid(&program_id, &buyer.pubkey(), mint, max_price, false, None);
+            let sale = instruction::execute_matched_sale(&program_id, &lister.pubkey(), mint, &buyer.pubkey(), &authority, None, &[], buyer_token);
+            instruction::with_auto_match(bid, sale)
+        }).collect()
+    };
+
+    // A listing priced above what the buyer agreed to pay fails the sweep.
+    let ix = instruction::sweep_buy(&program_id, 10 * PRICE, &purchases(PRICE));
+    let result = ctx.process(&[ix], &[&buyer]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::PriceMismatch as u32));
+
+    // The budget covers the two cheap listings but not the expensive one.
+    let taker_fee = PRICE * common::TAKER_FEE_BPS / state::MAX_BPS;
+    let ix = instruction::sweep_buy(&program_id, 2 * (PRICE + taker_fee), &purchases(3 * PRICE));
+    ctx.process(&[ix], &[&buyer]).await.unwrap();
+    assert_eq!(ctx.token_balance(&buyer_tokens[0]).await, 1);
+    assert_eq!(ctx.token_balance(&buyer_tokens[1]).await, 0);
+    assert_eq!(ctx.token_balance(&buyer_tokens[2]).await, 1);
+    let (bid_state_b, _) = state::find_bid_state_address(&program_id, &mint_b, &buyer.pubkey());
+    assert!(ctx.get_account(&bid_state_b).await.is_none());
+
+    let (platform_state_pubkey, _) = state::find_platform_state_address(&program_id);
+    let platform_state = PlatformState::unpack(&ctx.get_account(&platform_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform_state.sale_count, 2);
+    assert_eq!(platform_state.active_listings, 1);
+}
+
+#[tokio::test]
+async fn test_sweep_buy_settles_only_the_buyers_bids() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let buyer = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE / 2, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+    let bid = instruction::bid(&program_id, &buyer.pubkey(), &mint, PRICE, false, None);
+    let sale = instruction::execute_matched_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    let ix = instruction::sweep_buy(&program_id, 10 * PRICE, &[instruction::with_auto_match(bid, sale)]);
+    assert!(ctx.process(&[ix], &[&buyer]).await.is_err());
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_some());
+    assert_eq!(ctx.token_balance(&bidder_token).await, 0);
+}
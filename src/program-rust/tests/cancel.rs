@@ -0,0 +1,389 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::{account::Account, signature::{Keypair, Signer}};
+
+use nft_trading::{error::NFTError, instruction, state::{self, BidEscrowState, ListEscrowState, PlatformState}};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_delist_returns_nft() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let program_id = ctx.program_id;
+
+    let (platform_state_pubkey, _) = state::find_platform_state_address(&program_id);
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&lister_token).await, 0);
+    let platform_state = PlatformState::unpack(&ctx.get_account(&platform_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform_state.active_listings, 1);
+
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&lister_token).await, 1);
+    let platform_state = PlatformState::unpack(&ctx.get_account(&platform_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform_state.active_listings, 0);
+
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let (list_vault_pubkey, _) = state::find_list_vault_address(&program_id, &mint, &lister.pubkey());
+    assert!(ctx.get_account(&list_state_pubkey).await.is_none());
+    assert!(ctx.get_account(&list_vault_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_escrows_store_their_bumps() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let program_id = ctx.program_id;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE / 2, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let (list_state_pubkey, list_state_bump) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let list_state = ListEscrowState::unpack(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.state_bump, list_state_bump);
+    assert_eq!(list_state.vault_bump, state::find_list_vault_address(&program_id, &mint, &lister.pubkey()).1);
+
+    let (bid_state_pubkey, bid_state_bump) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    let bid_state = BidEscrowState::unpack(&ctx.get_account(&bid_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(bid_state.state_bump, bid_state_bump);
+    assert_eq!(bid_state.vault_bump, state::find_bid_vault_address(&program_id, &mint, &bidder.pubkey()).1);
+
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_delist_checks_supplied_bumps() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let program_id = ctx.program_id;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let bumps = instruction::Bumps::find(&program_id, &mint, Some(&lister.pubkey()), None);
+    let wrong_bumps = instruction::Bumps{list_state: bumps.list_state.wrapping_sub(1), ..bumps};
+    let ix = instruction::with_bumps(instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint), wrong_bumps);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+
+    let ix = instruction::with_bumps(instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint), bumps);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&lister_token).await, 1);
+}
+
+#[tokio::test]
+async fn test_delist_recreates_closed_token_account() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let program_id = ctx.program_id;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = spl_token::instruction::close_account(&spl_token::id(), &lister_token, &lister.pubkey(), &lister.pubkey(), &[]).unwrap();
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let lister_ata = spl_associated_token_account::get_associated_token_address(&lister.pubkey(), &mint);
+    assert!(ctx.get_account(&lister_ata).await.is_none());
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &lister_ata, &mint);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&lister_ata).await, 1);
+}
+
+#[tokio::test]
+async fn test_withdraw_bid_returns_lamports() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, _) = ctx.create_nft(&ctx.payer.pubkey()).await;
+    let program_id = ctx.program_id;
+
+    let balance_before = ctx.balance(&bidder.pubkey()).await;
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    assert!(ctx.balance(&bidder.pubkey()).await < balance_before - PRICE);
+
+    let ix = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, balance_before);
+}
+
+#[tokio::test]
+async fn test_withdraw_bid_rejects_foreign_owned_escrow() {
+    let program_id = Pubkey::new_unique();
+    let mint = Keypair::new();
+    let bidder = Keypair::new();
+
+    // Escrow-shaped data at the bid's addresses, but owned by another program.
+    let mut program_test = common::program_test(program_id);
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint.pubkey(), &bidder.pubkey());
+    let (bid_vault_pubkey, _) = state::find_bid_vault_address(&program_id, &mint.pubkey(), &bidder.pubkey());
+    let mut data = vec![0u8; BidEscrowState::LEN];
+    BidEscrowState {
+        is_initialized: true,
+        bidder: bidder.pubkey(),
+        mint: mint.pubkey(),
+        amount: PRICE,
+        payer: bidder.pubkey(),
+        ..BidEscrowState::default()
+    }.pack_into_slice(&mut data);
+    let owner = Pubkey::new_unique();
+    program_test.add_account(bid_state_pubkey, Account { lamports: LAMPORTS_PER_SOL, data, owner, ..Account::default() });
+    program_test.add_account(bid_vault_pubkey, Account { lamports: PRICE, owner, ..Account::default() });
+    program_test.add_account(bidder.pubkey(), Account { lamports: LAMPORTS_PER_SOL, ..Account::default() });
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+    let payer = ctx.payer.pubkey();
+    let (mint, _) = ctx.create_nft_with_mint(mint, &payer).await;
+
+    let ix = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    let result = ctx.process(&[ix], &[&bidder]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::IncorrectOwner as u32));
+    assert_eq!(ctx.balance(&bid_vault_pubkey).await, PRICE);
+}
+
+#[tokio::test]
+async fn test_withdraw_wrapped_bid_returns_lamports() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, _) = ctx.create_nft(&ctx.payer.pubkey()).await;
+    let program_id = ctx.program_id;
+
+    let balance_before = ctx.balance(&bidder.pubkey()).await;
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, true, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let (bid_vault_pubkey, _) = state::find_bid_vault_address(&program_id, &mint, &bidder.pubkey());
+    let taker_fee = PRICE * common::TAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.token_balance(&bid_vault_pubkey).await, PRICE + taker_fee);
+
+    let ix = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, balance_before);
+    assert!(ctx.get_account(&bid_vault_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_refund_by_authority() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, _) = ctx.create_nft(&ctx.payer.pubkey()).await;
+    let program_id = ctx.program_id;
+
+    let balance_before = ctx.balance(&bidder.pubkey()).await;
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let authority = ctx.payer.pubkey();
+    let ix = instruction::refund_user(&program_id, &authority, &mint, &bidder.pubkey());
+    ctx.process(&[ix], &[]).await.unwrap();
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, balance_before);
+
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    let (bid_vault_pubkey, _) = state::find_bid_vault_address(&program_id, &mint, &bidder.pubkey());
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_none());
+    assert!(ctx.get_account(&bid_vault_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_refund_requires_authority() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, _) = ctx.create_nft(&ctx.payer.pubkey()).await;
+    let program_id = ctx.program_id;
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let impostor = Keypair::new();
+    let ix = instruction::refund_user(&program_id, &impostor.pubkey(), &mint, &bidder.pubkey());
+    assert!(ctx.process(&[ix], &[&impostor]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_refund_rejects_settled_bid() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, _) = ctx.create_nft(&ctx.payer.pubkey()).await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let ix = instruction::refund_user(&program_id, &authority, &mint, &bidder.pubkey());
+    assert!(ctx.process(&[ix], &[]).await.is_err());
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let withdraw_ix = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    let refund_ix = instruction::refund_user(&program_id, &authority, &mint, &bidder.pubkey());
+    assert!(ctx.process(&[withdraw_ix, refund_ix], &[&bidder]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_refund_spares_leading_auction_bid() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, true, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let balance_before = ctx.balance(&bidder.pubkey()).await;
+    let ix = instruction::auction_bid(&program_id, &bidder.pubkey(), &mint, &lister.pubkey(), PRICE, None, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    let bid_state = BidEscrowState::unpack(&ctx.get_account(&bid_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(bid_state.auction_listing, list_state_pubkey);
+
+    let ix = instruction::refund_user(&program_id, &authority, &mint, &bidder.pubkey());
+    assert!(ctx.process(&[ix], &[]).await.is_err());
+    let ix = instruction::with_auction_listing(
+        instruction::refund_user(&program_id, &authority, &mint, &bidder.pubkey()),
+        &mint,
+        &lister.pubkey(),
+    );
+    let result = ctx.process(&[ix], &[]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::BidLeadsAuction as u32));
+
+    // Once the auction is gone the bid no longer backs anything.
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::with_auction_listing(
+        instruction::refund_user(&program_id, &authority, &mint, &bidder.pubkey()),
+        &mint,
+        &lister.pubkey(),
+    );
+    ctx.process(&[ix], &[]).await.unwrap();
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, balance_before);
+}
+
+#[tokio::test]
+async fn test_withdraw_bid_waits_out_cooldown() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, _) = ctx.create_nft(&authority).await;
+
+    let ix = instruction::set_bid_cooldown(&program_id, &bidder.pubkey(), 1_000);
+    assert!(ctx.process(&[ix], &[&bidder]).await.is_err());
+    let ix = instruction::set_bid_cooldown(&program_id, &authority, 1_000);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let balance_before = ctx.balance(&bidder.pubkey()).await;
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    let bid_state = BidEscrowState::unpack(&ctx.get_account(&bid_state_pubkey).await.unwrap().data).unwrap();
+    assert!(bid_state.withdrawable_slot >= 1_000);
+
+    let ix = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    let result = ctx.process(&[ix], &[&bidder]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::BidCooldownActive as u32));
+
+    // The cooldown only binds the bidder; the authority can still refund.
+    let ix = instruction::refund_user(&program_id, &authority, &mint, &bidder.pubkey());
+    ctx.process(&[ix], &[]).await.unwrap();
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, balance_before);
+}
+
+#[tokio::test]
+async fn test_automation_thread_refunds_stale_bid() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let thread = Keypair::new();
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, _) = ctx.create_nft(&authority).await;
+
+    let balance_before = ctx.balance(&bidder.pubkey()).await;
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    // No thread is registered yet.
+    let refund = || instruction::thread_refund_bid(&program_id, &thread.pubkey(), &mint, &bidder.pubkey(), &bidder.pubkey());
+    let result = ctx.process(&[refund()], &[&thread]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidThreadAuthority as u32));
+
+    let ix = instruction::set_automation_thread(&program_id, &bidder.pubkey(), &thread.pubkey(), 1_000);
+    assert!(ctx.process(&[ix], &[&bidder]).await.is_err());
+    let ix = instruction::set_automation_thread(&program_id, &authority, &thread.pubkey(), 1_000);
+    let result = ctx.process(&[ix, refund()], &[&thread]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::BidNotStale as u32));
+
+    let ix = instruction::set_automation_thread(&program_id, &authority, &thread.pubkey(), 0);
+    ctx.process(&[ix, refund()], &[&thread]).await.unwrap();
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, balance_before);
+
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_anyone_refunds_expired_bid_for_a_share_of_its_rent() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let caller = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, _) = ctx.create_nft(&ctx.payer.pubkey()).await;
+    let program_id = ctx.program_id;
+
+    let balance_before = ctx.balance(&bidder.pubkey()).await;
+    let bid_ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    let expiry_ix = instruction::set_bid_expiry(&program_id, &bidder.pubkey(), &mint, i64::MAX);
+    ctx.process(&[bid_ix, expiry_ix], &[&bidder]).await.unwrap();
+
+    let ix = instruction::expire_and_refund(&program_id, &ctx.payer.pubkey(), &mint, &bidder.pubkey(), &bidder.pubkey());
+    let result = ctx.process(&[ix], &[]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::BidNotExpired as u32));
+
+    let ix = instruction::set_bid_expiry(&program_id, &bidder.pubkey(), &mint, 1);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let caller_before = ctx.balance(&caller.pubkey()).await;
+    let ix = instruction::expire_and_refund(&program_id, &caller.pubkey(), &mint, &bidder.pubkey(), &bidder.pubkey());
+    ctx.process(&[ix], &[&caller]).await.unwrap();
+
+    let reward = ctx.balance(&caller.pubkey()).await - caller_before;
+    assert!(reward > 0);
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, balance_before - reward);
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_none());
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::signature::{Keypair, Signer};
+
+use nft_trading::{instruction, metadata::Collection, state::{self, CollectionStats}};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_collection_stats_track_floor_and_sales() {
+    let program_id = Pubkey::new_unique();
+    let collection = Pubkey::new_unique();
+    let mint_a = Keypair::new();
+    let mint_b = Keypair::new();
+
+    let mut program_test = common::program_test(program_id);
+    for mint in &[&mint_a, &mint_b] {
+        let (metadata_pubkey, metadata_account) = common::metadata_account(
+            &mint.pubkey(),
+            0,
+            None,
+            Some(Collection { verified: true, key: collection.to_bytes() }),
+        );
+        program_test.add_account(metadata_pubkey, metadata_account);
+    }
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+    let authority = ctx.payer.pubkey();
+
+    let ix = instruction::create_collection_stats(&program_id, &authority, &collection);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let (collection_stats_pubkey, _) = state::find_collection_stats_address(&program_id, &collection);
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint_a, token_a) = ctx.create_nft_with_mint(mint_a, &lister.pubkey()).await;
+    let (mint_b, token_b) = ctx.create_nft_with_mint(mint_b, &lister.pubkey()).await;
+
+    let list_a = instruction::list(&program_id, &lister.pubkey(), &token_a, &mint_a, PRICE, false, None, None, 0, 0);
+    let list_b = instruction::list(&program_id, &lister.pubkey(), &token_b, &mint_b, 2 * PRICE, false, None, None, 0, 0);
+    ctx.process(
+        &[instruction::with_collection_stats(list_b, &collection), instruction::with_collection_stats(list_a, &collection)],
+        &[&lister],
+    ).await.unwrap();
+    let (list_state_a, _) = state::find_list_state_address(&program_id, &mint_a, &lister.pubkey());
+    let stats = CollectionStats::unpack(&ctx.get_account(&collection_stats_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(stats.floor_price, PRICE);
+    assert_eq!(stats.floor_listing, list_state_a);
+
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &token_a, &mint_a);
+    ctx.process(&[instruction::with_collection_stats(ix, &collection)], &[&lister]).await.unwrap();
+    let stats = CollectionStats::unpack(&ctx.get_account(&collection_stats_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(stats.floor_price, 0);
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint_b, 2 * PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint_b, &bidder.pubkey(), &authority, None, &[], None);
+    ctx.process(&[instruction::with_collection_stats(ix, &collection)], &[&lister]).await.unwrap();
+    let stats = CollectionStats::unpack(&ctx.get_account(&collection_stats_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(stats.sale_count, 1);
+    assert_eq!(stats.last_sale_price, 2 * PRICE);
+    assert_eq!(stats.total_volume, 2 * PRICE);
+}
+
+#[test]
+fn test_floor_estimate_resists_outliers_and_decays() {
+    let mut stats = CollectionStats::default();
+    assert_eq!(stats.floor(0), None);
+    stats.observe_floor(PRICE, 10);
+    assert_eq!(stats.floor(10), Some(PRICE));
+
+    // A wash trade at a hundred times the floor is clamped to the band and
+    // smoothed, and a second one in the same slot does nothing.
+    stats.observe_floor(100 * PRICE, 11);
+    let band = PRICE * state::FLOOR_OUTLIER_BPS / state::MAX_BPS;
+    assert_eq!(stats.floor_estimate, PRICE + band * state::FLOOR_SMOOTHING_BPS / state::MAX_BPS);
+    let moved = stats.floor_estimate;
+    stats.observe_floor(100 * PRICE, 11);
+    assert_eq!(stats.floor_estimate, moved);
+
+    // Listings above the estimate don't say the floor went up.
+    stats.observe_listing(2 * PRICE, 12);
+    assert_eq!(stats.floor_estimate, moved);
+
+    // Long quiet, the estimate's weight has decayed and a price within the
+    // band replaces it.
+    let slot = 11 + state::FLOOR_DECAY_SLOTS + 1;
+    assert_eq!(stats.floor(slot), None);
+    stats.observe_listing(PRICE, slot);
+    assert_eq!(stats.floor(slot), Some(PRICE));
+}
@@ -0,0 +1,186 @@
+#![allow(dead_code)]
+
+use borsh::BorshSerialize;
+use solana_program::{
+    hash::Hash,
+    instruction::Instruction,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{
+    account::Account,
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+    transport::TransportError,
+};
+
+use nft_trading::{
+    metadata::{self, Collection, Creator, Data, Metadata},
+    process_instruction,
+};
+
+pub const MAKER_FEE_BPS: u64 = 250;
+pub const TAKER_FEE_BPS: u64 = 100;
+
+pub struct TestContext {
+    pub banks_client: BanksClient,
+    pub payer: Keypair,
+    pub recent_blockhash: Hash,
+    pub program_id: Pubkey,
+}
+
+pub fn program_test(program_id: Pubkey) -> ProgramTest {
+    ProgramTest::new("nft_trading", program_id, processor!(process_instruction))
+}
+
+/// Builds a Metaplex metadata account for `mint` that can be preloaded with
+/// `ProgramTest::add_account`.
+pub fn metadata_account(
+    mint: &Pubkey,
+    seller_fee_basis_points: u16,
+    creators: Option<Vec<Creator>>,
+    collection: Option<Collection>,
+) -> (Pubkey, Account) {
+    let metadata = Metadata {
+        key: 4,
+        update_authority: Pubkey::new_unique().to_bytes(),
+        mint: mint.to_bytes(),
+        data: Data {
+            name: "Test".to_string(),
+            symbol: "TST".to_string(),
+            uri: "".to_string(),
+            seller_fee_basis_points,
+            creators,
+        },
+        primary_sale_happened: true,
+        is_mutable: true,
+        edition_nonce: None,
+        token_standard: None,
+        collection,
+    };
+    let account = Account {
+        lamports: Rent::default().minimum_balance(1024),
+        data: metadata.try_to_vec().unwrap(),
+        owner: metadata::token_metadata_program::id(),
+        ..Account::default()
+    };
+    (metadata::find_metadata_address(mint).0, account)
+}
+
+pub async fn start() -> TestContext {
+    let program_id = Pubkey::new_unique();
+    start_with(program_test(program_id), program_id).await
+}
+
+pub async fn start_with(program_test: ProgramTest, program_id: Pubkey) -> TestContext {
+    let (banks_client, payer, recent_blockhash) = program_test.start().await;
+    TestContext {
+        banks_client,
+        payer,
+        recent_blockhash,
+        program_id,
+    }
+}
+
+/// The custom program error a failed transaction returned, if any.
+pub fn custom_error(result: Result<(), TransportError>) -> Option<u32> {
+    match result {
+        Err(TransportError::TransactionError(TransactionError::InstructionError(_, InstructionError::Custom(code)))) => Some(code),
+        _ => None,
+    }
+}
+
+impl TestContext {
+    pub async fn process(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), TransportError> {
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&self.payer.pubkey()));
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend_from_slice(signers);
+        transaction.sign(&all_signers, self.recent_blockhash);
+        self.banks_client.process_transaction(transaction).await
+    }
+
+    pub async fn get_account(&mut self, pubkey: &Pubkey) -> Option<Account> {
+        self.banks_client.get_account(*pubkey).await.unwrap()
+    }
+
+    pub async fn balance(&mut self, pubkey: &Pubkey) -> u64 {
+        self.get_account(pubkey).await.map(|a| a.lamports).unwrap_or(0)
+    }
+
+    pub async fn token_balance(&mut self, token_account: &Pubkey) -> u64 {
+        let account = self.get_account(token_account).await.unwrap();
+        spl_token::state::Account::unpack(&account.data).unwrap().amount
+    }
+
+    /// Creates a system account funded with `lamports` from the payer.
+    pub async fn create_wallet(&mut self, lamports: u64) -> Keypair {
+        let wallet = Keypair::new();
+        let ix = system_instruction::transfer(&self.payer.pubkey(), &wallet.pubkey(), lamports);
+        self.process(&[ix], &[]).await.unwrap();
+        wallet
+    }
+
+    pub async fn initialize_platform(&mut self) {
+        let authority = self.payer.pubkey();
+        let ix = nft_trading::instruction::initialize(&self.program_id, &authority, &authority, MAKER_FEE_BPS, TAKER_FEE_BPS);
+        self.process(&[ix], &[]).await.unwrap();
+    }
+
+    pub async fn create_token_account(&mut self, mint: &Pubkey, owner: &Pubkey) -> Pubkey {
+        let token_account = Keypair::new();
+        let rent = Rent::default();
+        let ixs = [
+            system_instruction::create_account(
+                &self.payer.pubkey(),
+                &token_account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(&spl_token::id(), &token_account.pubkey(), mint, owner).unwrap(),
+        ];
+        self.process(&ixs, &[&token_account]).await.unwrap();
+        token_account.pubkey()
+    }
+
+    /// Mints a fresh zero-decimal token with a supply of one into a new token
+    /// account owned by `owner`. Returns `(mint, token_account)`.
+    pub async fn create_nft(&mut self, owner: &Pubkey) -> (Pubkey, Pubkey) {
+        self.create_nft_with_mint(Keypair::new(), owner).await
+    }
+
+    pub async fn create_nft_with_mint(&mut self, mint: Keypair, owner: &Pubkey) -> (Pubkey, Pubkey) {
+        let mint = self.create_mint(mint, 0).await;
+        let token_account = self.create_token_account(&mint, owner).await;
+        self.mint_to(&mint, &token_account, 1).await;
+        (mint, token_account)
+    }
+
+    /// Creates a mint with `decimals`, using the payer as mint authority.
+    pub async fn create_mint(&mut self, mint: Keypair, decimals: u8) -> Pubkey {
+        let rent = Rent::default();
+        let payer = self.payer.pubkey();
+        let ixs = [
+            system_instruction::create_account(
+                &payer,
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(&spl_token::id(), &mint.pubkey(), &payer, None, decimals).unwrap(),
+        ];
+        self.process(&ixs, &[&mint]).await.unwrap();
+        mint.pubkey()
+    }
+
+    pub async fn mint_to(&mut self, mint: &Pubkey, token_account: &Pubkey, amount: u64) {
+        let payer = self.payer.pubkey();
+        let ix = spl_token::instruction::mint_to(&spl_token::id(), mint, token_account, &payer, &[], amount).unwrap();
+        self.process(&[ix], &[]).await.unwrap();
+    }
+}
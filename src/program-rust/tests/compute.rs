@@ -0,0 +1,128 @@
+mod common;
+
+use std::sync::Mutex;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use solana_program::{instruction::Instruction, native_token::LAMPORTS_PER_SOL};
+use solana_sdk::signature::{Keypair, Signer};
+
+use nft_trading::instruction;
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+/// Compute units each instruction may use in this bench. A change that
+/// pushes one past its ceiling fails the bench, so lower a ceiling
+/// whenever an optimization frees headroom.
+const CEILINGS: &[(&str, u64)] = &[
+    ("Initialize", 30_000),
+    ("List", 60_000),
+    ("DeList", 40_000),
+    ("Bid", 40_000),
+    ("WithdrawBid", 25_000),
+    ("ExecuteSale", 110_000),
+];
+
+/// Collects the runtime's `Program ... consumed N of M compute units` log
+/// lines, which are only written for programs run in the BPF VM.
+struct ComputeLog {
+    lines: Mutex<Vec<String>>,
+}
+
+impl ComputeLog {
+    fn install() -> &'static ComputeLog {
+        let logger: &'static ComputeLog = Box::leak(Box::new(ComputeLog { lines: Mutex::new(vec![]) }));
+        log::set_logger(logger).expect("no logger is installed before the bench");
+        logger
+    }
+
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.lines.lock().unwrap())
+    }
+}
+
+impl Log for ComputeLog {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Debug
+    }
+
+    fn log(&self, record: &Record) {
+        let line = record.args().to_string();
+        if line.contains(" compute units") {
+            self.lines.lock().unwrap().push(line);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Processes `instructions` and returns the compute units the program's
+/// top-level instruction consumed.
+async fn measure(ctx: &mut common::TestContext, logger: &ComputeLog, instructions: &[Instruction], signers: &[&Keypair]) -> u64 {
+    logger.take();
+    ctx.process(instructions, signers).await.unwrap();
+    let prefix = format!("Program {} consumed ", ctx.program_id);
+    logger
+        .take()
+        .iter()
+        .rev()
+        .find_map(|line| {
+            let rest = &line[line.find(&prefix)? + prefix.len()..];
+            rest.split(' ').next()?.parse().ok()
+        })
+        .expect("the runtime logged no compute units for the program")
+}
+
+#[tokio::test]
+async fn bench_compute_units() {
+    // `cargo test-bpf` sets this and loads the built program into the VM.
+    // Natively run processors are not metered, so there is nothing to
+    // measure under a plain `cargo test`.
+    if std::env::var("BPF_OUT_DIR").is_err() {
+        println!("compute units are only metered under `cargo test-bpf`, skipping");
+        return;
+    }
+
+    let logger = ComputeLog::install();
+    let mut ctx = common::start().await;
+    // Starting the test validator resets the level from `RUST_LOG`.
+    log::set_max_level(LevelFilter::Debug);
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+    let mut usage = vec![];
+
+    let ix = instruction::initialize(&program_id, &authority, &authority, common::MAKER_FEE_BPS, common::TAKER_FEE_BPS);
+    usage.push(("Initialize", measure(&mut ctx, logger, &[ix], &[]).await));
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    usage.push(("List", measure(&mut ctx, logger, &[ix], &[&lister]).await));
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint);
+    usage.push(("DeList", measure(&mut ctx, logger, &[ix], &[&lister]).await));
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    usage.push(("Bid", measure(&mut ctx, logger, &[ix], &[&bidder]).await));
+    let ix = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    usage.push(("WithdrawBid", measure(&mut ctx, logger, &[ix], &[&bidder]).await));
+
+    // Relist and rebid at new prices so the transactions are not replays.
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE + 1, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE + 1, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let ix = instruction::execute_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    usage.push(("ExecuteSale", measure(&mut ctx, logger, &[ix], &[&lister]).await));
+
+    let mut regressions = vec![];
+    for (name, units) in &usage {
+        let ceiling = CEILINGS.iter().find(|(ceiling_name, _)| ceiling_name == name).unwrap().1;
+        println!("{:<12} {:>7} CU (ceiling {})", name, units, ceiling);
+        if *units > ceiling {
+            regressions.push(format!("{} used {} CU, over its ceiling of {}", name, units, ceiling));
+        }
+    }
+    assert!(regressions.is_empty(), "{}", regressions.join("\n"));
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{crowdfund, error::NFTError, fraction::{self, FractionState}, instruction, state};
+
+const PRICE: u64 = 2 * LAMPORTS_PER_SOL;
+const BUYOUT_PRICE: u64 = 5 * LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_crowdfund_buys_listing_and_mints_shares_to_contributors() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let alice = ctx.create_wallet(5 * LAMPORTS_PER_SOL).await;
+    let bob = ctx.create_wallet(5 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let ix = instruction::create_crowdfund(&program_id, &alice.pubkey(), &mint, &lister.pubkey(), BUYOUT_PRICE);
+    ctx.process(&[ix], &[&alice]).await.unwrap();
+    let taker_fee = PRICE * common::TAKER_FEE_BPS / state::MAX_BPS;
+    let target = PRICE + taker_fee;
+
+    let ix = instruction::contribute(&program_id, &alice.pubkey(), &mint, &lister.pubkey(), LAMPORTS_PER_SOL);
+    ctx.process(&[ix], &[&alice]).await.unwrap();
+    let ix = instruction::execute_crowdfund(&program_id, &alice.pubkey(), &mint, &lister.pubkey(), &lister.pubkey(), &authority, None);
+    let result = ctx.process(&[ix], &[&alice]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::CrowdfundTargetNotMet as u32));
+
+    let ix = instruction::contribute(&program_id, &bob.pubkey(), &mint, &lister.pubkey(), target);
+    let result = ctx.process(&[ix], &[&bob]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::ContributionExceedsTarget as u32));
+    let ix = instruction::contribute(&program_id, &bob.pubkey(), &mint, &lister.pubkey(), target - LAMPORTS_PER_SOL);
+    ctx.process(&[ix], &[&bob]).await.unwrap();
+
+    let lister_balance = ctx.balance(&lister.pubkey()).await;
+    let ix = instruction::execute_crowdfund(&program_id, &bob.pubkey(), &mint, &lister.pubkey(), &lister.pubkey(), &authority, None);
+    ctx.process(&[ix], &[&bob]).await.unwrap();
+    let maker_fee = PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    assert!(ctx.balance(&lister.pubkey()).await >= lister_balance + PRICE - maker_fee);
+
+    let (fraction_vault_pubkey, _) = fraction::find_fraction_vault_address(&program_id, &mint);
+    let (fraction_state_pubkey, _) = fraction::find_fraction_state_address(&program_id, &mint);
+    assert_eq!(ctx.token_balance(&fraction_vault_pubkey).await, 1);
+    let fraction_state = FractionState::unpack(&ctx.get_account(&fraction_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(fraction_state.share_supply, target);
+    assert_eq!(fraction_state.buyout_price, BUYOUT_PRICE);
+
+    let (share_mint, _) = fraction::find_share_mint_address(&program_id, &mint);
+    let alice_shares = ctx.create_token_account(&share_mint, &alice.pubkey()).await;
+    let bob_shares = ctx.create_token_account(&share_mint, &bob.pubkey()).await;
+    let ix = instruction::claim_crowdfund_shares(&program_id, &alice.pubkey(), &alice_shares, &alice.pubkey(), &mint, &lister.pubkey());
+    ctx.process(&[ix], &[]).await.unwrap();
+    let ix = instruction::claim_crowdfund_shares(&program_id, &bob.pubkey(), &bob_shares, &alice.pubkey(), &mint, &lister.pubkey());
+    ctx.process(&[ix], &[]).await.unwrap();
+    assert_eq!(ctx.token_balance(&alice_shares).await, LAMPORTS_PER_SOL);
+    assert_eq!(ctx.token_balance(&bob_shares).await, target - LAMPORTS_PER_SOL);
+
+    let (crowdfund_pubkey, _) = crowdfund::find_crowdfund_address(&program_id, &mint, &lister.pubkey());
+    assert!(ctx.get_account(&crowdfund_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_contributions_are_refunded_once_listing_is_gone() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let lister = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let alice = ctx.create_wallet(5 * LAMPORTS_PER_SOL).await;
+    let bob = ctx.create_wallet(5 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let ixs = [
+        instruction::create_crowdfund(&program_id, &alice.pubkey(), &mint, &lister.pubkey(), BUYOUT_PRICE),
+        instruction::contribute(&program_id, &alice.pubkey(), &mint, &lister.pubkey(), LAMPORTS_PER_SOL),
+    ];
+    ctx.process(&ixs, &[&alice]).await.unwrap();
+    let ix = instruction::contribute(&program_id, &bob.pubkey(), &mint, &lister.pubkey(), LAMPORTS_PER_SOL / 2);
+    ctx.process(&[ix], &[&bob]).await.unwrap();
+
+    let ixs = [
+        instruction::refund_contribution(&program_id, &bob.pubkey(), &alice.pubkey(), &mint, &lister.pubkey()),
+        instruction::refund_contribution(&program_id, &alice.pubkey(), &alice.pubkey(), &mint, &lister.pubkey()),
+    ];
+    let result = ctx.process(&ixs, &[]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::ListingStillAvailable as u32));
+
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let alice_balance = ctx.balance(&alice.pubkey()).await;
+    let ix = instruction::refund_contribution(&program_id, &alice.pubkey(), &alice.pubkey(), &mint, &lister.pubkey());
+    ctx.process(&[ix], &[]).await.unwrap();
+    assert!(ctx.balance(&alice.pubkey()).await > alice_balance + LAMPORTS_PER_SOL);
+    let (crowdfund_pubkey, _) = crowdfund::find_crowdfund_address(&program_id, &mint, &lister.pubkey());
+    assert!(ctx.get_account(&crowdfund_pubkey).await.is_some());
+
+    // The last refund closes the crowdfund to its creator.
+    let bob_balance = ctx.balance(&bob.pubkey()).await;
+    let ix = instruction::refund_contribution(&program_id, &bob.pubkey(), &alice.pubkey(), &mint, &lister.pubkey());
+    ctx.process(&[ix], &[]).await.unwrap();
+    assert!(ctx.balance(&bob.pubkey()).await > bob_balance + LAMPORTS_PER_SOL / 2);
+    assert!(ctx.get_account(&crowdfund_pubkey).await.is_none());
+}
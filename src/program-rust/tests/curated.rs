@@ -0,0 +1,93 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use solana_sdk::signature::{Keypair, Signer};
+
+use nft_trading::{instruction, metadata::Collection};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_curated_mode_requires_allowed_collection() {
+    let program_id = Pubkey::new_unique();
+    let collection = Pubkey::new_unique();
+    let mint_a = Keypair::new();
+    let mint_b = Keypair::new();
+
+    let mut program_test = common::program_test(program_id);
+    for mint in &[&mint_a, &mint_b] {
+        let (metadata_pubkey, metadata_account) = common::metadata_account(
+            &mint.pubkey(),
+            0,
+            None,
+            Some(Collection { verified: true, key: collection.to_bytes() }),
+        );
+        program_test.add_account(metadata_pubkey, metadata_account);
+    }
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+    let authority = ctx.payer.pubkey();
+
+    let ix = instruction::set_curated_mode(&program_id, &authority, true);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint_a, token_a) = ctx.create_nft_with_mint(mint_a, &lister.pubkey()).await;
+    let (mint_b, token_b) = ctx.create_nft_with_mint(mint_b, &lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &token_a, &mint_a, PRICE, false, None, None, 0, 0);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+    let ix = instruction::list(&program_id, &lister.pubkey(), &token_a, &mint_a, PRICE, false, Some(&collection), None, 0, 0);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+
+    let ix = instruction::allow_collection(&program_id, &authority, &collection);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let ix = instruction::list(&program_id, &lister.pubkey(), &token_a, &mint_a, PRICE + 1, false, Some(&collection), None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let ix = instruction::revoke_collection(&program_id, &authority, &collection);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let ix = instruction::list(&program_id, &lister.pubkey(), &token_b, &mint_b, PRICE, false, Some(&collection), None, 0, 0);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_strict_mode_requires_verified_mint() {
+    let program_id = Pubkey::new_unique();
+    let verified_mint = Keypair::new();
+    let unverified_mint = Keypair::new();
+
+    let mut program_test = common::program_test(program_id);
+    let (metadata_pubkey, metadata_account) = common::metadata_account(
+        &verified_mint.pubkey(),
+        0,
+        None,
+        Some(Collection { verified: true, key: Pubkey::new_unique().to_bytes() }),
+    );
+    program_test.add_account(metadata_pubkey, metadata_account);
+    let (metadata_pubkey, metadata_account) = common::metadata_account(
+        &unverified_mint.pubkey(),
+        0,
+        None,
+        Some(Collection { verified: false, key: Pubkey::new_unique().to_bytes() }),
+    );
+    program_test.add_account(metadata_pubkey, metadata_account);
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+    let authority = ctx.payer.pubkey();
+
+    let ix = instruction::set_strict_mode(&program_id, &authority, true);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (verified_mint, verified_token) = ctx.create_nft_with_mint(verified_mint, &lister.pubkey()).await;
+    let (unverified_mint, unverified_token) = ctx.create_nft_with_mint(unverified_mint, &lister.pubkey()).await;
+    let (bare_mint, bare_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &unverified_token, &unverified_mint, PRICE, false, None, None, 0, 0);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+    let ix = instruction::list(&program_id, &lister.pubkey(), &bare_token, &bare_mint, PRICE, false, None, None, 0, 0);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+    let ix = instruction::list(&program_id, &lister.pubkey(), &verified_token, &verified_mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::{Keypair, Signer};
+
+use nft_trading::{instruction, state::{self, BidEscrowState}};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+const THRESHOLD: u64 = 100;
+const DISCOUNT_BPS: u64 = 5_000;
+
+#[tokio::test]
+async fn test_discount_holders_pay_reduced_fees() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let discount_mint = ctx.create_mint(Keypair::new(), 0).await;
+
+    let ix = instruction::set_fee_discount(&program_id, &lister.pubkey(), &discount_mint, THRESHOLD, DISCOUNT_BPS);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+    let ix = instruction::set_fee_discount(&program_id, &authority, &discount_mint, THRESHOLD, DISCOUNT_BPS);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let lister_discount_token = ctx.create_token_account(&discount_mint, &lister.pubkey()).await;
+    let bidder_discount_token = ctx.create_token_account(&discount_mint, &bidder.pubkey()).await;
+    ctx.mint_to(&discount_mint, &lister_discount_token, THRESHOLD).await;
+    ctx.mint_to(&discount_mint, &bidder_discount_token, THRESHOLD - 1).await;
+
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    let ix = instruction::with_fee_discount(ix, &bidder_discount_token);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    let bid_state = BidEscrowState::unpack_unchecked(&ctx.get_account(&bid_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(bid_state.taker_fee, PRICE * common::TAKER_FEE_BPS / state::MAX_BPS);
+
+    let lister_balance = ctx.balance(&lister.pubkey()).await;
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    let ix = instruction::with_fee_discount(ix, &lister_discount_token);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let maker_fee = PRICE * (common::MAKER_FEE_BPS * (state::MAX_BPS - DISCOUNT_BPS) / state::MAX_BPS) / state::MAX_BPS;
+    assert_eq!(ctx.balance(&lister.pubkey()).await, lister_balance + PRICE - maker_fee);
+}
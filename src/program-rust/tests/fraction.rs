@@ -0,0 +1,108 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack, rent::Rent, system_instruction};
+use solana_sdk::signature::{Keypair, Signer};
+
+use nft_trading::{fraction::{self, FractionState}, instruction, state};
+
+const SHARE_SUPPLY: u64 = 100;
+const BUYOUT_PRICE: u64 = 4 * LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_buyout_proceeds_are_redeemed_pro_rata() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let curator = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let alice = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let buyer = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, curator_token) = ctx.create_nft(&curator.pubkey()).await;
+    let (share_mint, _) = fraction::find_share_mint_address(&program_id, &mint);
+
+    let curator_shares = Keypair::new();
+    let ixs = [
+        system_instruction::create_account(
+            &authority,
+            &curator_shares.pubkey(),
+            Rent::default().minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        instruction::fractionalize(&program_id, &curator.pubkey(), &curator_token, &mint, &curator_shares.pubkey(), SHARE_SUPPLY, BUYOUT_PRICE),
+    ];
+    ctx.process(&ixs, &[&curator, &curator_shares]).await.unwrap();
+    assert_eq!(ctx.token_balance(&curator_token).await, 0);
+    assert_eq!(ctx.token_balance(&curator_shares.pubkey()).await, SHARE_SUPPLY);
+
+    let alice_shares = ctx.create_token_account(&share_mint, &alice.pubkey()).await;
+    let ix = spl_token::instruction::transfer(
+        &spl_token::id(), &curator_shares.pubkey(), &alice_shares, &curator.pubkey(), &[], 25,
+    ).unwrap();
+    ctx.process(&[ix], &[&curator]).await.unwrap();
+
+    let ix = instruction::redeem_shares(&program_id, &alice.pubkey(), &alice_shares, &mint, 1);
+    assert!(ctx.process(&[ix], &[&alice]).await.is_err());
+    let ix = instruction::redeem_nft(&program_id, &curator.pubkey(), &curator_shares.pubkey(), &curator_token, &mint);
+    assert!(ctx.process(&[ix], &[&curator]).await.is_err());
+
+    let buyer_token = ctx.create_token_account(&mint, &buyer.pubkey()).await;
+    let buyer_balance = ctx.balance(&buyer.pubkey()).await;
+    let ix = instruction::buyout(&program_id, &buyer.pubkey(), &buyer_token, &mint, &curator.pubkey(), &authority);
+    ctx.process(&[ix], &[&buyer]).await.unwrap();
+    assert_eq!(ctx.token_balance(&buyer_token).await, 1);
+    let platform_fee = BUYOUT_PRICE * common::TAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.balance(&buyer.pubkey()).await, buyer_balance - BUYOUT_PRICE - platform_fee);
+
+    let alice_balance = ctx.balance(&alice.pubkey()).await;
+    let ix = instruction::redeem_shares(&program_id, &alice.pubkey(), &alice_shares, &mint, 25);
+    ctx.process(&[ix], &[&alice]).await.unwrap();
+    assert_eq!(ctx.balance(&alice.pubkey()).await, alice_balance + BUYOUT_PRICE / 4);
+    assert_eq!(ctx.token_balance(&alice_shares).await, 0);
+
+    let curator_balance = ctx.balance(&curator.pubkey()).await;
+    let ix = instruction::redeem_shares(&program_id, &curator.pubkey(), &curator_shares.pubkey(), &mint, 75);
+    ctx.process(&[ix], &[&curator]).await.unwrap();
+    assert_eq!(ctx.balance(&curator.pubkey()).await, curator_balance + 3 * BUYOUT_PRICE / 4);
+
+    let (fraction_state_pubkey, _) = fraction::find_fraction_state_address(&program_id, &mint);
+    let fraction_state = FractionState::unpack(&ctx.get_account(&fraction_state_pubkey).await.unwrap().data).unwrap();
+    assert!(fraction_state.bought_out);
+    assert_eq!(fraction_state.outstanding_shares, 0);
+    assert_eq!(fraction_state.proceeds, 0);
+}
+
+#[tokio::test]
+async fn test_holder_of_all_shares_redeems_nft() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let curator = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, curator_token) = ctx.create_nft(&curator.pubkey()).await;
+
+    let curator_shares = Keypair::new();
+    let ixs = [
+        system_instruction::create_account(
+            &authority,
+            &curator_shares.pubkey(),
+            Rent::default().minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        instruction::fractionalize(&program_id, &curator.pubkey(), &curator_token, &mint, &curator_shares.pubkey(), SHARE_SUPPLY, BUYOUT_PRICE),
+    ];
+    ctx.process(&ixs, &[&curator, &curator_shares]).await.unwrap();
+
+    let ix = instruction::redeem_nft(&program_id, &curator.pubkey(), &curator_shares.pubkey(), &curator_token, &mint);
+    ctx.process(&[ix], &[&curator]).await.unwrap();
+    assert_eq!(ctx.token_balance(&curator_token).await, 1);
+    assert_eq!(ctx.token_balance(&curator_shares.pubkey()).await, 0);
+
+    let (fraction_state_pubkey, _) = fraction::find_fraction_state_address(&program_id, &mint);
+    let (fraction_vault_pubkey, _) = fraction::find_fraction_vault_address(&program_id, &mint);
+    assert!(ctx.get_account(&fraction_state_pubkey).await.is_none());
+    assert!(ctx.get_account(&fraction_vault_pubkey).await.is_none());
+}
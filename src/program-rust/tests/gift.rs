@@ -0,0 +1,89 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{error::NFTError, instruction, state::{self, PlatformState}};
+
+#[tokio::test]
+async fn test_only_recipient_claims_revealed_gift() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let sender = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let recipient = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let stranger = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, sender_token) = ctx.create_nft(&sender.pubkey()).await;
+    let (early_mint, early_sender_token) = ctx.create_nft(&sender.pubkey()).await;
+
+    let ix = instruction::gift(&program_id, &sender.pubkey(), &sender_token, &mint, &recipient.pubkey(), 0, i64::MAX, None);
+    ctx.process(&[ix], &[&sender]).await.unwrap();
+    let ix = instruction::gift(&program_id, &sender.pubkey(), &early_sender_token, &early_mint, &recipient.pubkey(), i64::MAX - 1, i64::MAX, None);
+    ctx.process(&[ix], &[&sender]).await.unwrap();
+
+    // Gifts are not for sale, so only the listing counts as active.
+    let (listed_mint, listed_token) = ctx.create_nft(&sender.pubkey()).await;
+    let ix = instruction::list(&program_id, &sender.pubkey(), &listed_token, &listed_mint, LAMPORTS_PER_SOL, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&sender]).await.unwrap();
+    let (platform_state_pubkey, _) = state::find_platform_state_address(&program_id);
+    let platform_state = PlatformState::unpack(&ctx.get_account(&platform_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform_state.active_listings, 1);
+
+    // Still inside the claim window, so the sender cannot take it back.
+    let ix = instruction::delist(&program_id, &sender.pubkey(), &sender_token, &mint);
+    let result = ctx.process(&[ix], &[&sender]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::GiftNotReclaimable as u32));
+
+    let stranger_token = ctx.create_token_account(&mint, &stranger.pubkey()).await;
+    let ix = instruction::claim_gift(&program_id, &stranger.pubkey(), &stranger_token, &mint, &sender.pubkey());
+    let result = ctx.process(&[ix], &[&stranger]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::BuyerNotAllowed as u32));
+
+    let early_token = ctx.create_token_account(&early_mint, &recipient.pubkey()).await;
+    let ix = instruction::claim_gift(&program_id, &recipient.pubkey(), &early_token, &early_mint, &sender.pubkey());
+    let result = ctx.process(&[ix], &[&recipient]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::ListingNotStarted as u32));
+
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &sender.pubkey());
+    let sender_balance = ctx.balance(&sender.pubkey()).await;
+    let state_rent = ctx.balance(&list_state_pubkey).await;
+    let recipient_token = ctx.create_token_account(&mint, &recipient.pubkey()).await;
+    let ix = instruction::claim_gift(&program_id, &recipient.pubkey(), &recipient_token, &mint, &sender.pubkey());
+    ctx.process(&[ix], &[&recipient]).await.unwrap();
+    assert_eq!(ctx.token_balance(&recipient_token).await, 1);
+    assert!(ctx.get_account(&list_state_pubkey).await.is_none());
+    assert!(ctx.balance(&sender.pubkey()).await >= sender_balance + state_rent);
+    let platform_state = PlatformState::unpack(&ctx.get_account(&platform_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform_state.active_listings, 1);
+}
+
+#[tokio::test]
+async fn test_sender_reclaims_unclaimed_gift_after_deadline() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let sender = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let recipient = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, sender_token) = ctx.create_nft(&sender.pubkey()).await;
+
+    let ix = instruction::gift(&program_id, &sender.pubkey(), &sender_token, &mint, &recipient.pubkey(), 0, 0, None);
+    let result = ctx.process(&[ix], &[&sender]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidInstructionData as u32));
+
+    // A deadline already behind the cluster clock.
+    let ix = instruction::gift(&program_id, &sender.pubkey(), &sender_token, &mint, &recipient.pubkey(), 0, 1, None);
+    ctx.process(&[ix], &[&sender]).await.unwrap();
+    assert_eq!(ctx.token_balance(&sender_token).await, 0);
+
+    // Gifts are not for sale, even to the recipient.
+    let ix = instruction::bid(&program_id, &recipient.pubkey(), &mint, LAMPORTS_PER_SOL / 2, false, None);
+    ctx.process(&[ix], &[&recipient]).await.unwrap();
+    let ix = instruction::accept_bid(&program_id, &sender.pubkey(), &mint, &recipient.pubkey(), &ctx.payer.pubkey(), None, &[], None);
+    assert!(ctx.process(&[ix], &[&sender]).await.is_err());
+
+    let ix = instruction::delist(&program_id, &sender.pubkey(), &sender_token, &mint);
+    ctx.process(&[ix], &[&sender]).await.unwrap();
+    assert_eq!(ctx.token_balance(&sender_token).await, 1);
+}
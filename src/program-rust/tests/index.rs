@@ -0,0 +1,86 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{index::{self, EscrowIndex, IndexKind}, instruction, state};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_owner_index_tracks_open_listings() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let stranger = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint_a, token_a) = ctx.create_nft(&lister.pubkey()).await;
+    let (mint_b, token_b) = ctx.create_nft(&lister.pubkey()).await;
+    let (mint_c, token_c) = ctx.create_nft(&stranger.pubkey()).await;
+
+    let ix = instruction::create_index(&program_id, &authority, IndexKind::Owner, &lister.pubkey());
+    ctx.process(&[ix], &[]).await.unwrap();
+    let (index_pubkey, _) = index::find_owner_index_address(&program_id, &lister.pubkey());
+
+    let list_a = instruction::list(&program_id, &lister.pubkey(), &token_a, &mint_a, PRICE, false, None, None, 0, 0);
+    let list_b = instruction::list(&program_id, &lister.pubkey(), &token_b, &mint_b, PRICE, false, None, None, 0, 0);
+    let list_c = instruction::list(&program_id, &stranger.pubkey(), &token_c, &mint_c, PRICE, false, None, None, 0, 0);
+    ctx.process(&[list_a, list_b], &[&lister]).await.unwrap();
+    ctx.process(&[list_c], &[&stranger]).await.unwrap();
+
+    let (list_state_a, _) = state::find_list_state_address(&program_id, &mint_a, &lister.pubkey());
+    let (list_state_b, _) = state::find_list_state_address(&program_id, &mint_b, &lister.pubkey());
+    let (list_state_c, _) = state::find_list_state_address(&program_id, &mint_c, &stranger.pubkey());
+
+    let ix = instruction::sync_index(&program_id, IndexKind::Owner, &lister.pubkey(), &[list_state_c]);
+    assert!(ctx.process(&[ix], &[]).await.is_err());
+
+    let ix = instruction::sync_index(&program_id, IndexKind::Owner, &lister.pubkey(), &[list_state_a, list_state_b]);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let owner_index = EscrowIndex::unpack(&ctx.get_account(&index_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(owner_index.entries, vec![list_state_a, list_state_b]);
+
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &token_a, &mint_a);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::sync_index(&program_id, IndexKind::Owner, &lister.pubkey(), &[list_state_a]);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let owner_index = EscrowIndex::unpack(&ctx.get_account(&index_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(owner_index.entries, vec![list_state_b]);
+}
+
+#[tokio::test]
+async fn test_mint_index_tracks_open_bids() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let owner = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder_a = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder_b = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, _) = ctx.create_nft(&owner.pubkey()).await;
+
+    let ix = instruction::create_index(&program_id, &authority, IndexKind::Mint, &mint);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let (index_pubkey, _) = index::find_mint_index_address(&program_id, &mint);
+
+    let ix = instruction::bid(&program_id, &bidder_a.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder_a]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder_b.pubkey(), &mint, 2 * PRICE, false, None);
+    ctx.process(&[ix], &[&bidder_b]).await.unwrap();
+
+    let (bid_state_a, _) = state::find_bid_state_address(&program_id, &mint, &bidder_a.pubkey());
+    let (bid_state_b, _) = state::find_bid_state_address(&program_id, &mint, &bidder_b.pubkey());
+    let ix = instruction::sync_index(&program_id, IndexKind::Mint, &mint, &[bid_state_a, bid_state_b]);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let mint_index = EscrowIndex::unpack(&ctx.get_account(&index_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(mint_index.entries, vec![bid_state_a, bid_state_b]);
+
+    let withdraw = instruction::withdraw_bid(&program_id, &bidder_a.pubkey(), &mint);
+    let ix = instruction::sync_index(&program_id, IndexKind::Mint, &mint, &[bid_state_a, bid_state_b]);
+    ctx.process(&[withdraw, ix], &[&bidder_a]).await.unwrap();
+    let mint_index = EscrowIndex::unpack(&ctx.get_account(&index_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(mint_index.entries, vec![bid_state_b]);
+}
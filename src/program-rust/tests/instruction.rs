@@ -0,0 +1,157 @@
+use solana_program::pubkey::Pubkey;
+
+use nft_trading::{instruction::{self, AcceptBid, AcceptTraitOffer, AuctionBid, Bid, Bumps, BuyCore, Contribute, CreateCrowdfund, CreateSwap, DeList, Gift, List, MakeTraitOffer, MintAndList, NFTInstruction, NewMetadata, SetAttestation, SetBidExpiry, SetCurator, SetMarketplaceFee, SetMaxCharity, SetMaxRoyalty, SetMinimums, SetPriceFeed, SetVisibility, SweepBuy, SweepPurchase}, memo, merkle, swap};
+
+#[test]
+fn test_v1_and_v2_decode_alike() {
+    let instructions = [
+        NFTInstruction::List(List{amount: 5, auction: true, allowed_buyer: Pubkey::new_unique(), starts_at: -7, royalty_bps_override: 750, allowlist_root: [0; 32], charity: Pubkey::default(), charity_bps: 0, marketplace: Pubkey::default(), price_usd: 0, settlement_delay_slots: 0, arbiter: Pubkey::default(), visibility: 0}),
+        NFTInstruction::List(List{amount: 5, auction: false, allowed_buyer: Pubkey::default(), starts_at: 0, royalty_bps_override: 0, allowlist_root: [9; 32], charity: Pubkey::new_unique(), charity_bps: 500, marketplace: Pubkey::new_unique(), price_usd: 0, settlement_delay_slots: 216_000, arbiter: Pubkey::new_unique(), visibility: 1}),
+        NFTInstruction::List(List{amount: 0, auction: false, allowed_buyer: Pubkey::default(), starts_at: 0, royalty_bps_override: 0, allowlist_root: [0; 32], charity: Pubkey::default(), charity_bps: 0, marketplace: Pubkey::default(), price_usd: 25_000_000, settlement_delay_slots: 0, arbiter: Pubkey::default(), visibility: 0}),
+        NFTInstruction::Bid(Bid{amount: 9, wrapped: true, match_accounts: 3, tip_lamports: 0, marketplace: Pubkey::default(), proof: vec![]}),
+        NFTInstruction::Bid(Bid{amount: 9, wrapped: false, match_accounts: 11, tip_lamports: 0, marketplace: Pubkey::default(), proof: vec![[4; 32], [5; 32]]}),
+        NFTInstruction::Bid(Bid{amount: 9, wrapped: false, match_accounts: 11, tip_lamports: 250_000, marketplace: Pubkey::default(), proof: vec![]}),
+        NFTInstruction::Bid(Bid{amount: 9, wrapped: false, match_accounts: 0, tip_lamports: 0, marketplace: Pubkey::new_unique(), proof: vec![[8; 32]]}),
+        NFTInstruction::AuctionBid(AuctionBid{amount: 9, proof: vec![[6; 32]]}),
+        NFTInstruction::SetMinimums(SetMinimums{min_listing_lamports: 1, min_bid_lamports: 2}),
+        NFTInstruction::SetMaxRoyalty(SetMaxRoyalty{max_royalty_bps: 1_500}),
+        NFTInstruction::SetMaxCharity(SetMaxCharity{max_charity_bps: 1_000}),
+        NFTInstruction::SetMarketplaceFee(SetMarketplaceFee{marketplace_fee_bps: 2_500}),
+        NFTInstruction::SetPriceFeed(SetPriceFeed{price_feed: Pubkey::new_unique()}),
+        NFTInstruction::SetBidExpiry(SetBidExpiry{expires_at: 1_700_000_000}),
+        NFTInstruction::SetAttestation(SetAttestation{attestor: Pubkey::new_unique(), required: true}),
+        NFTInstruction::SetVisibility(SetVisibility{visibility: 1}),
+        NFTInstruction::SetCurator(SetCurator{curator: Pubkey::new_unique(), curator_fee_bps: 2_000}),
+        NFTInstruction::CreateCrowdfund(CreateCrowdfund{buyout_price: 5_000_000_000}),
+        NFTInstruction::Contribute(Contribute{amount: 1_000_000}),
+        NFTInstruction::BuyCore(BuyCore{max_price: 4_000_000}),
+        NFTInstruction::SweepBuy(SweepBuy{max_spend: 3_000_000_000, purchases: vec![
+            SweepPurchase{account_count: 20, match_accounts: 11, max_price: 1_000_000_000},
+            SweepPurchase{account_count: 24, match_accounts: 15, max_price: 2_000_000_000},
+        ]}),
+        NFTInstruction::MakeTraitOffer(MakeTraitOffer{amount: 2_000_000_000, root: [7; 32]}),
+        NFTInstruction::AcceptTraitOffer(AcceptTraitOffer{proof: vec![[1; 32], [2; 32], [3; 32]]}),
+        NFTInstruction::AcceptTraitOffer(AcceptTraitOffer{proof: vec![]}),
+        NFTInstruction::CreateSwap(CreateSwap{id: 1, maker_lamports: 0, taker_lamports: 0, taker_mints: vec![Pubkey::new_unique(), Pubkey::new_unique()]}),
+        NFTInstruction::CreateSwap(CreateSwap{id: 2, maker_lamports: 1_000_000_000, taker_lamports: 0, taker_mints: vec![]}),
+        NFTInstruction::Gift(Gift{recipient: Pubkey::new_unique(), reveal_at: -1, reclaimable_at: 1_700_000_000}),
+        NFTInstruction::MintAndList(MintAndList{price: 3, metadata: None}),
+        NFTInstruction::MintAndList(MintAndList{
+            price: 3,
+            metadata: Some(NewMetadata{name: "Crab #1".to_string(), symbol: "CRAB".to_string(), uri: "https://example.com/1.json".to_string(), seller_fee_basis_points: 500}),
+        }),
+    ];
+    for ix in instructions.iter() {
+        assert_eq!(&NFTInstruction::unpack(&ix.pack()).unwrap(), ix);
+        assert_eq!(&NFTInstruction::unpack(&ix.pack_v2()).unwrap(), ix);
+    }
+}
+
+#[test]
+fn test_v2_optional_fields_default() {
+    let mut data = vec![instruction::VERSION_2, 3];
+    data.extend_from_slice(&42u64.to_be_bytes());
+    data.push(0);
+    assert_eq!(
+        NFTInstruction::unpack(&data).unwrap(),
+        NFTInstruction::List(List{amount: 42, auction: false, allowed_buyer: Pubkey::default(), starts_at: 0, royalty_bps_override: 0, allowlist_root: [0; 32], charity: Pubkey::default(), charity_bps: 0, marketplace: Pubkey::default(), price_usd: 0, settlement_delay_slots: 0, arbiter: Pubkey::default(), visibility: 0}),
+    );
+
+    let mut data = vec![instruction::VERSION_2, 5];
+    data.extend_from_slice(&42u64.to_be_bytes());
+    data.push(1);
+    assert_eq!(
+        NFTInstruction::unpack(&data).unwrap(),
+        NFTInstruction::Bid(Bid{amount: 42, wrapped: true, match_accounts: 0, tip_lamports: 0, marketplace: Pubkey::default(), proof: vec![]}),
+    );
+}
+
+#[test]
+fn test_buy_core_without_max_price_takes_any_price() {
+    assert_eq!(
+        NFTInstruction::unpack(&[60]).unwrap(),
+        NFTInstruction::BuyCore(BuyCore{max_price: u64::MAX}),
+    );
+}
+
+#[test]
+fn test_v2_rejects_truncated_or_extra_fields() {
+    let mut data = vec![instruction::VERSION_2, 3];
+    data.extend_from_slice(&42u64.to_be_bytes());
+    assert!(NFTInstruction::unpack(&data).is_err());
+
+    data.push(0);
+    data.extend_from_slice(&[1; 16]);
+    assert!(NFTInstruction::unpack(&data).is_err());
+
+    let mut data = vec![instruction::VERSION_2, 5];
+    data.extend_from_slice(&42u64.to_be_bytes());
+    data.extend_from_slice(&[0, 0, 0]);
+    assert!(NFTInstruction::unpack(&data).is_err());
+}
+
+#[test]
+fn test_allowlist_proofs_are_bounded() {
+    let ix = NFTInstruction::AuctionBid(AuctionBid{amount: 42, proof: vec![[1; 32]; merkle::MAX_PROOF_LEN]});
+    assert_eq!(NFTInstruction::unpack(&ix.pack()).unwrap(), ix);
+
+    let ix = NFTInstruction::AuctionBid(AuctionBid{amount: 42, proof: vec![[1; 32]; merkle::MAX_PROOF_LEN + 1]});
+    assert!(NFTInstruction::unpack(&ix.pack()).is_err());
+
+    let ix = NFTInstruction::Bid(Bid{amount: 42, wrapped: false, match_accounts: 0, tip_lamports: 0, marketplace: Pubkey::default(), proof: vec![[1; 32]]});
+    let mut data = ix.pack();
+    data.pop();
+    assert!(NFTInstruction::unpack(&data).is_err());
+}
+
+#[test]
+fn test_swap_sides_are_bounded() {
+    let ix = NFTInstruction::CreateSwap(CreateSwap{id: 7, maker_lamports: 0, taker_lamports: 1, taker_mints: vec![Pubkey::new_unique(); swap::SWAP_MAX_ASSETS]});
+    assert_eq!(NFTInstruction::unpack(&ix.pack()).unwrap(), ix);
+
+    let ix = NFTInstruction::CreateSwap(CreateSwap{id: 7, maker_lamports: 0, taker_lamports: 1, taker_mints: vec![Pubkey::new_unique(); swap::SWAP_MAX_ASSETS + 1]});
+    assert!(NFTInstruction::unpack(&ix.pack()).is_err());
+
+    let mut data = ix.pack();
+    data.truncate(data.len() - 1);
+    assert!(NFTInstruction::unpack(&data).is_err());
+}
+
+#[test]
+fn test_bumps_travel_in_v2_only() {
+    let bumps = Bumps{platform_state: 255, list_state: 254, list_vault: 253, bid_state: 252, bid_vault: 251};
+    let ix = NFTInstruction::AcceptBid(AcceptBid{memo: b"thanks!".to_vec(), bumps, expected_amount: 7});
+    assert_eq!(NFTInstruction::unpack(&ix.pack_v2()).unwrap(), ix);
+    assert_eq!(
+        NFTInstruction::unpack(&ix.pack()).unwrap(),
+        NFTInstruction::AcceptBid(AcceptBid{memo: b"thanks!".to_vec(), bumps: Bumps::default(), expected_amount: 0}),
+    );
+
+    // `DeList` may leave its bumps off, but not cut them short.
+    let data = vec![instruction::VERSION_2, 4];
+    assert_eq!(NFTInstruction::unpack(&data).unwrap(), NFTInstruction::DeList(DeList{bumps: Bumps::default()}));
+    assert!(NFTInstruction::unpack(&[instruction::VERSION_2, 4, 255]).is_err());
+}
+
+#[test]
+fn test_client_memo_cannot_pose_as_sale_record() {
+    let mut data = vec![7];
+    data.extend_from_slice(b"thanks!");
+    assert!(NFTInstruction::unpack(&data).is_ok());
+
+    let mut data = vec![7];
+    data.extend_from_slice(memo::sale_memo(&Pubkey::new_unique(), 1).as_bytes());
+    assert!(NFTInstruction::unpack(&data).is_err());
+
+    let mut data = vec![7];
+    data.extend_from_slice(memo::tip_memo(&Pubkey::new_unique(), 1).as_bytes());
+    assert!(NFTInstruction::unpack(&data).is_err());
+
+    let mut data = vec![7];
+    data.extend_from_slice(memo::charity_memo(&Pubkey::new_unique(), &Pubkey::new_unique(), 1).as_bytes());
+    assert!(NFTInstruction::unpack(&data).is_err());
+
+    let mut data = vec![7];
+    data.extend_from_slice(memo::marketplace_memo(&Pubkey::new_unique(), &Pubkey::new_unique(), 1).as_bytes());
+    assert!(NFTInstruction::unpack(&data).is_err());
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{error::NFTError, instruction, leaderboard::{self, Leaderboard, LeaderboardEntry, LEADERBOARD_DEPTH}};
+
+const SEASON: u64 = 1;
+
+#[tokio::test]
+async fn test_sales_rank_traders_by_volume() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let ix = instruction::create_leaderboard(&program_id, &authority, SEASON, 0, i64::MAX);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let whale = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let sales = [(&bidder, LAMPORTS_PER_SOL), (&whale, 3 * LAMPORTS_PER_SOL)];
+    for (buyer, price) in sales.iter() {
+        let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+        let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, *price, false, None, None, 0, 0);
+        ctx.process(&[ix], &[&lister]).await.unwrap();
+        let ix = instruction::bid(&program_id, &buyer.pubkey(), &mint, *price, false, None);
+        ctx.process(&[ix], &[*buyer]).await.unwrap();
+        let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &buyer.pubkey(), &authority, None, &[], None);
+        let ix = instruction::with_leaderboard(ix, SEASON);
+        ctx.process(&[ix], &[&lister]).await.unwrap();
+    }
+
+    let (leaderboard_pubkey, _) = leaderboard::find_leaderboard_address(&program_id, SEASON);
+    let board = Leaderboard::unpack(&ctx.get_account(&leaderboard_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(board.entries, vec![
+        LeaderboardEntry { trader: lister.pubkey(), volume: 4 * LAMPORTS_PER_SOL },
+        LeaderboardEntry { trader: whale.pubkey(), volume: 3 * LAMPORTS_PER_SOL },
+        LeaderboardEntry { trader: bidder.pubkey(), volume: LAMPORTS_PER_SOL },
+    ]);
+}
+
+#[tokio::test]
+async fn test_create_leaderboard_needs_platform_authority() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let impostor = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let ix = instruction::create_leaderboard(&program_id, &impostor.pubkey(), SEASON, 0, i64::MAX);
+    let result = ctx.process(&[ix], &[&impostor]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidAuthority as u32));
+}
+
+#[test]
+fn test_full_leaderboard_evicts_lowest_volume() {
+    let mut board = Leaderboard::default();
+    let traders: Vec<Pubkey> = (0..LEADERBOARD_DEPTH).map(|_| Pubkey::new_unique()).collect();
+    for (i, trader) in traders.iter().enumerate() {
+        board.record(trader, 10 + i as u64);
+    }
+
+    let newcomer = Pubkey::new_unique();
+    board.record(&newcomer, 10);
+    assert!(!board.entries.iter().any(|entry| entry.trader == newcomer));
+
+    board.record(&newcomer, 11);
+    assert_eq!(board.entries.len(), LEADERBOARD_DEPTH);
+    assert!(!board.entries.iter().any(|entry| entry.trader == traders[0]));
+    // Ties keep the earlier trader ahead.
+    assert_eq!(board.entries[LEADERBOARD_DEPTH - 2].trader, traders[1]);
+    assert_eq!(board.entries.last().unwrap().trader, newcomer);
+
+    board.record(&traders[1], 100);
+    assert_eq!(board.entries[0], LeaderboardEntry { trader: traders[1], volume: 111 });
+}
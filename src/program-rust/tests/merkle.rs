@@ -0,0 +1,27 @@
+use solana_program::pubkey::Pubkey;
+
+use nft_trading::merkle;
+
+#[test]
+fn test_proofs_verify_for_every_mint() {
+    for len in 1..=9 {
+        let mints: Vec<Pubkey> = (0..len).map(|_| Pubkey::new_unique()).collect();
+        let root = merkle::root(&mints);
+        for (i, mint) in mints.iter().enumerate() {
+            let proof = merkle::proof(&mints, i);
+            assert!(merkle::verify(&proof, &root, merkle::leaf(mint)));
+        }
+    }
+}
+
+#[test]
+fn test_proofs_reject_other_mints_and_roots() {
+    let mints: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    let root = merkle::root(&mints);
+    let proof = merkle::proof(&mints, 2);
+
+    assert!(!merkle::verify(&proof, &root, merkle::leaf(&mints[3])));
+    assert!(!merkle::verify(&proof, &root, merkle::leaf(&Pubkey::new_unique())));
+    assert!(!merkle::verify(&proof, &merkle::root(&mints[..4]), merkle::leaf(&mints[2])));
+    assert!(!merkle::verify(&[], &merkle::root(&[]), merkle::leaf(&mints[0])));
+}
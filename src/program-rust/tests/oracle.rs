@@ -0,0 +1,112 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, rent::Rent};
+use solana_sdk::{account::Account, signature::Signer};
+
+use nft_trading::{error::NFTError, instruction, oracle::{self, Price}, state};
+
+/// $150.00000000 a SOL.
+const SOL_PRICE: i64 = 15_000_000_000;
+const EXPO: i32 = -8;
+
+/// A Pyth price account's data, with the aggregate price set.
+fn price_account_data(price: i64, conf: u64, status: u32, pub_slot: u64) -> Vec<u8> {
+    let mut data = vec![0u8; 3312];
+    data[0..4].copy_from_slice(&oracle::PYTH_MAGIC.to_le_bytes());
+    data[4..8].copy_from_slice(&oracle::PYTH_VERSION.to_le_bytes());
+    data[8..12].copy_from_slice(&oracle::PYTH_PRICE_ACCOUNT.to_le_bytes());
+    data[20..24].copy_from_slice(&EXPO.to_le_bytes());
+    data[208..216].copy_from_slice(&price.to_le_bytes());
+    data[216..224].copy_from_slice(&conf.to_le_bytes());
+    data[224..228].copy_from_slice(&status.to_le_bytes());
+    data[232..240].copy_from_slice(&pub_slot.to_le_bytes());
+    data
+}
+
+#[test]
+fn test_price_converts_usd_to_lamports() {
+    let price = Price::unpack(&price_account_data(SOL_PRICE, 0, oracle::PYTH_STATUS_TRADING, 100)).unwrap();
+    assert_eq!(price, Price{price: SOL_PRICE, conf: 0, expo: EXPO, status: oracle::PYTH_STATUS_TRADING, pub_slot: 100});
+    assert_eq!(price.usd_to_lamports(75_000_000).unwrap(), LAMPORTS_PER_SOL / 2);
+    // Rounded up, in the seller's favour.
+    assert_eq!(price.usd_to_lamports(1).unwrap(), 7);
+
+    let whole_dollars = Price{price: 150, expo: 0, ..price};
+    assert_eq!(whole_dollars.usd_to_lamports(75_000_000).unwrap(), LAMPORTS_PER_SOL / 2);
+
+    let mut data = price_account_data(SOL_PRICE, 0, oracle::PYTH_STATUS_TRADING, 100);
+    data[8] = 1;
+    assert!(Price::unpack(&data).is_err());
+    assert!(Price::unpack(&data[..oracle::PRICE_ACCOUNT_LEN - 1]).is_err());
+}
+
+#[test]
+fn test_only_fresh_tight_trading_prices_are_reliable() {
+    let price = Price{price: SOL_PRICE, conf: 0, expo: EXPO, status: oracle::PYTH_STATUS_TRADING, pub_slot: 100};
+    assert!(price.check_reliable(100 + oracle::MAX_PRICE_AGE_SLOTS).is_ok());
+    assert!(price.check_reliable(101 + oracle::MAX_PRICE_AGE_SLOTS).is_err());
+
+    let widest = SOL_PRICE as u64 * oracle::MAX_CONFIDENCE_BPS / state::MAX_BPS;
+    assert!(Price{conf: widest, ..price}.check_reliable(100).is_ok());
+    assert!(Price{conf: widest + 1, ..price}.check_reliable(100).is_err());
+
+    assert!(Price{status: 0, ..price}.check_reliable(100).is_err());
+    assert!(Price{price: 0, ..price}.check_reliable(100).is_err());
+}
+
+#[tokio::test]
+async fn test_usd_listing_fills_at_the_feed_price() {
+    let program_id = Pubkey::new_unique();
+    let price_feed = Pubkey::new_unique();
+    let other_feed = Pubkey::new_unique();
+    let mut program_test = common::program_test(program_id);
+    for feed in &[price_feed, other_feed] {
+        program_test.add_account(*feed, Account {
+            lamports: Rent::default().minimum_balance(3312),
+            data: price_account_data(SOL_PRICE, 0, oracle::PYTH_STATUS_TRADING, u64::MAX / 2),
+            owner: Pubkey::new_unique(),
+            ..Account::default()
+        });
+    }
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let low_bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let low_bidder_token = ctx.create_token_account(&mint, &low_bidder.pubkey()).await;
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+
+    // $75, listed before there is a feed to price it with.
+    let list = || instruction::with_usd_price(
+        instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, 0, false, None, None, 0, 0),
+        75_000_000,
+    );
+    let result = ctx.process(&[list()], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::PriceFeedNotSet as u32));
+
+    let ix = instruction::set_price_feed(&program_id, &authority, &price_feed);
+    ctx.process(&[ix, list()], &[&lister]).await.unwrap();
+
+    let ix = instruction::bid(&program_id, &low_bidder.pubkey(), &mint, LAMPORTS_PER_SOL / 2 - 1, false, None);
+    ctx.process(&[ix], &[&low_bidder]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, LAMPORTS_PER_SOL / 2, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    // The listing keeps its feed when the platform moves to another, and
+    // without it there is no ask to meet.
+    let ix = instruction::set_price_feed(&program_id, &authority, &other_feed);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let ix = instruction::execute_matched_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    assert!(ctx.process(&[ix.clone()], &[]).await.is_err());
+    assert!(ctx.process(&[instruction::with_price_feed(ix, &other_feed)], &[]).await.is_err());
+
+    let ix = instruction::execute_matched_sale(&program_id, &lister.pubkey(), &mint, &low_bidder.pubkey(), &authority, None, &[], &low_bidder_token);
+    assert!(ctx.process(&[instruction::with_price_feed(ix, &price_feed)], &[]).await.is_err());
+
+    let ix = instruction::execute_matched_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    ctx.process(&[instruction::with_price_feed(ix, &price_feed)], &[]).await.unwrap();
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use std::convert::TryFrom;
+
+use solana_program::{instruction::Instruction, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use solana_sdk::signature::{Keypair, Signer};
+
+use nft_trading::{instruction, order, state};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[allow(clippy::too_many_arguments)]
+fn signed_fill(
+    program_id: &Pubkey,
+    seller: &Keypair,
+    seller_token: &Pubkey,
+    buyer: &Pubkey,
+    buyer_token: &Pubkey,
+    mint: &Pubkey,
+    fee_recipient: &Pubkey,
+    expiry: i64,
+    nonce: u64,
+) -> Vec<Instruction> {
+    let message = order::order_message(program_id, &seller.pubkey(), mint, PRICE, expiry, nonce);
+    let signature = <[u8; 64]>::try_from(seller.sign_message(&message).as_ref()).unwrap();
+    vec![
+        order::ed25519_instruction(&seller.pubkey(), &signature, &message),
+        instruction::fill_order(
+            program_id,
+            buyer,
+            buyer_token,
+            &seller.pubkey(),
+            seller_token,
+            mint,
+            fee_recipient,
+            instruction::FillOrder { price: PRICE, expiry, nonce },
+            None,
+            &[],
+        ),
+    ]
+}
+
+async fn approve_delegate(ctx: &mut common::TestContext, seller: &Keypair, seller_token: &Pubkey) {
+    let (delegate, _) = state::find_order_delegate_address(&ctx.program_id);
+    let ix = spl_token::instruction::approve(&spl_token::id(), seller_token, &delegate, &seller.pubkey(), &[], 1).unwrap();
+    ctx.process(&[ix], &[seller]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_fill_signed_order() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let seller = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let buyer = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, seller_token) = ctx.create_nft(&seller.pubkey()).await;
+    let buyer_token = ctx.create_token_account(&mint, &buyer.pubkey()).await;
+    approve_delegate(&mut ctx, &seller, &seller_token).await;
+
+    let seller_balance = ctx.balance(&seller.pubkey()).await;
+    let ixs = signed_fill(&program_id, &seller, &seller_token, &buyer.pubkey(), &buyer_token, &mint, &authority, i64::MAX, 1);
+    ctx.process(&ixs, &[&buyer]).await.unwrap();
+
+    let maker_fee = PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.balance(&seller.pubkey()).await, seller_balance + PRICE - maker_fee);
+    assert_eq!(ctx.token_balance(&buyer_token).await, 1);
+    assert_eq!(ctx.token_balance(&seller_token).await, 0);
+    let (receipt, _) = state::find_order_receipt_address(&program_id, &seller.pubkey(), 1);
+    assert!(ctx.get_account(&receipt).await.is_some());
+}
+
+#[tokio::test]
+async fn test_fill_order_rejects_expired_cancelled_and_forged() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let seller = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let buyer = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, seller_token) = ctx.create_nft(&seller.pubkey()).await;
+    let buyer_token = ctx.create_token_account(&mint, &buyer.pubkey()).await;
+    approve_delegate(&mut ctx, &seller, &seller_token).await;
+
+    let ixs = signed_fill(&program_id, &seller, &seller_token, &buyer.pubkey(), &buyer_token, &mint, &authority, 0, 1);
+    assert!(ctx.process(&ixs, &[&buyer]).await.is_err());
+
+    let ix = instruction::cancel_order(&program_id, &seller.pubkey(), 2);
+    ctx.process(&[ix], &[&seller]).await.unwrap();
+    let ixs = signed_fill(&program_id, &seller, &seller_token, &buyer.pubkey(), &buyer_token, &mint, &authority, i64::MAX, 2);
+    assert!(ctx.process(&ixs, &[&buyer]).await.is_err());
+
+    let forger = Keypair::new();
+    let mut ixs = signed_fill(&program_id, &forger, &seller_token, &buyer.pubkey(), &buyer_token, &mint, &authority, i64::MAX, 3);
+    ixs[1] = instruction::fill_order(
+        &program_id,
+        &buyer.pubkey(),
+        &buyer_token,
+        &seller.pubkey(),
+        &seller_token,
+        &mint,
+        &authority,
+        instruction::FillOrder { price: PRICE, expiry: i64::MAX, nonce: 3 },
+        None,
+        &[],
+    );
+    assert!(ctx.process(&ixs, &[&buyer]).await.is_err());
+    assert_eq!(ctx.token_balance(&seller_token).await, 1);
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::signature::{Keypair, Signer};
+
+use nft_trading::{instruction, metadata::Collection, state::{self, BookEntry, BookSide, OrderBook}};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_order_book_tracks_listings_and_bids() {
+    let program_id = Pubkey::new_unique();
+    let collection = Pubkey::new_unique();
+    let mint_a = Keypair::new();
+    let mint_b = Keypair::new();
+    let stray_mint = Keypair::new();
+
+    let mut program_test = common::program_test(program_id);
+    for (mint, collection) in &[(&mint_a, collection), (&mint_b, collection), (&stray_mint, Pubkey::new_unique())] {
+        let (metadata_pubkey, metadata_account) = common::metadata_account(
+            &mint.pubkey(),
+            0,
+            None,
+            Some(Collection { verified: true, key: collection.to_bytes() }),
+        );
+        program_test.add_account(metadata_pubkey, metadata_account);
+    }
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+    let authority = ctx.payer.pubkey();
+
+    let ix = instruction::create_order_book(&program_id, &authority, &collection);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let (order_book_pubkey, _) = state::find_order_book_address(&program_id, &collection);
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint_a, token_a) = ctx.create_nft_with_mint(mint_a, &lister.pubkey()).await;
+    let (mint_b, token_b) = ctx.create_nft_with_mint(mint_b, &lister.pubkey()).await;
+    let (stray_mint, stray_token) = ctx.create_nft_with_mint(stray_mint, &lister.pubkey()).await;
+
+    let ixs = [
+        instruction::list(&program_id, &lister.pubkey(), &token_a, &mint_a, 2 * PRICE, false, None, None, 0, 0),
+        instruction::update_order_book(&program_id, &collection, &mint_a, BookSide::Ask, &lister.pubkey()),
+        instruction::list(&program_id, &lister.pubkey(), &token_b, &mint_b, PRICE, false, None, None, 0, 0),
+        instruction::update_order_book(&program_id, &collection, &mint_b, BookSide::Ask, &lister.pubkey()),
+        instruction::bid(&program_id, &bidder.pubkey(), &mint_a, PRICE, false, None),
+        instruction::update_order_book(&program_id, &collection, &mint_a, BookSide::Bid, &bidder.pubkey()),
+    ];
+    ctx.process(&ixs, &[&lister, &bidder]).await.unwrap();
+
+    let order_book = OrderBook::unpack(&ctx.get_account(&order_book_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(order_book.asks, vec![
+        BookEntry { price: PRICE, maker: lister.pubkey(), mint: mint_b },
+        BookEntry { price: 2 * PRICE, maker: lister.pubkey(), mint: mint_a },
+    ]);
+    assert_eq!(order_book.bids, vec![BookEntry { price: PRICE, maker: bidder.pubkey(), mint: mint_a }]);
+
+    let ixs = [
+        instruction::list(&program_id, &lister.pubkey(), &stray_token, &stray_mint, PRICE, false, None, None, 0, 0),
+        instruction::update_order_book(&program_id, &collection, &stray_mint, BookSide::Ask, &lister.pubkey()),
+    ];
+    assert!(ctx.process(&ixs, &[&lister]).await.is_err());
+
+    let ixs = [
+        instruction::delist(&program_id, &lister.pubkey(), &token_b, &mint_b),
+        instruction::update_order_book(&program_id, &collection, &mint_b, BookSide::Ask, &lister.pubkey()),
+        instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint_a),
+        instruction::update_order_book(&program_id, &collection, &mint_a, BookSide::Bid, &bidder.pubkey()),
+    ];
+    ctx.process(&ixs, &[&lister, &bidder]).await.unwrap();
+
+    let order_book = OrderBook::unpack(&ctx.get_account(&order_book_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(order_book.asks, vec![BookEntry { price: 2 * PRICE, maker: lister.pubkey(), mint: mint_a }]);
+    assert!(order_book.bids.is_empty());
+}
+
+#[tokio::test]
+async fn test_order_book_leaves_out_listings_a_bid_cannot_fill() {
+    let program_id = Pubkey::new_unique();
+    let collection = Pubkey::new_unique();
+    let auction_mint = Keypair::new();
+    let delayed_mint = Keypair::new();
+
+    let mut program_test = common::program_test(program_id);
+    for mint in &[&auction_mint, &delayed_mint] {
+        let (metadata_pubkey, metadata_account) = common::metadata_account(
+            &mint.pubkey(),
+            0,
+            None,
+            Some(Collection { verified: true, key: collection.to_bytes() }),
+        );
+        program_test.add_account(metadata_pubkey, metadata_account);
+    }
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+    let authority = ctx.payer.pubkey();
+
+    let ix = instruction::create_order_book(&program_id, &authority, &collection);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let (order_book_pubkey, _) = state::find_order_book_address(&program_id, &collection);
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (auction_mint, auction_token) = ctx.create_nft_with_mint(auction_mint, &lister.pubkey()).await;
+    let (delayed_mint, delayed_token) = ctx.create_nft_with_mint(delayed_mint, &lister.pubkey()).await;
+    let delayed_list = instruction::list(&program_id, &lister.pubkey(), &delayed_token, &delayed_mint, PRICE, false, None, None, 0, 0);
+    let ixs = [
+        instruction::list(&program_id, &lister.pubkey(), &auction_token, &auction_mint, PRICE, true, None, None, 0, 0),
+        instruction::update_order_book(&program_id, &collection, &auction_mint, BookSide::Ask, &lister.pubkey()),
+        instruction::with_delayed_settlement(delayed_list, 1_000, None),
+        instruction::update_order_book(&program_id, &collection, &delayed_mint, BookSide::Ask, &lister.pubkey()),
+    ];
+    ctx.process(&ixs, &[&lister]).await.unwrap();
+
+    let order_book = OrderBook::unpack(&ctx.get_account(&order_book_pubkey).await.unwrap().data).unwrap();
+    assert!(order_book.asks.is_empty());
+}
+
+#[test]
+fn test_order_book_keeps_best_entries() {
+    let mut order_book = OrderBook::default();
+    let mint = Pubkey::new_unique();
+    for price in 1..=(state::ORDER_BOOK_DEPTH as u64 + 4) {
+        order_book.insert(BookSide::Bid, BookEntry { price, maker: Pubkey::new_unique(), mint });
+    }
+    assert_eq!(order_book.bids.len(), state::ORDER_BOOK_DEPTH);
+    assert_eq!(order_book.bids[0].price, state::ORDER_BOOK_DEPTH as u64 + 4);
+    assert_eq!(order_book.bids.last().unwrap().price, 5);
+
+    let best = order_book.bids[0];
+    order_book.remove(BookSide::Bid, &best.maker, &best.mint);
+    assert_eq!(order_book.bids[0].price, state::ORDER_BOOK_DEPTH as u64 + 3);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use solana_program::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::Signer;
+
+use nft_trading::{instruction, otc, state};
+
+const PRICE: u64 = 3 * LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_otc_settles_only_with_counterparty() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let maker = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let taker = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let stranger = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, maker_token) = ctx.create_nft(&maker.pubkey()).await;
+
+    let ix = instruction::create_otc(&program_id, &maker.pubkey(), &maker_token, &mint, &taker.pubkey(), PRICE);
+    ctx.process(&[ix], &[&maker]).await.unwrap();
+    assert_eq!(ctx.token_balance(&maker_token).await, 0);
+
+    let stranger_token = ctx.create_token_account(&mint, &stranger.pubkey()).await;
+    let ix = instruction::accept_otc(&program_id, &stranger.pubkey(), &stranger_token, &mint, &maker.pubkey(), &authority);
+    assert!(ctx.process(&[ix], &[&stranger]).await.is_err());
+    let ix = instruction::cancel_otc(&program_id, &stranger.pubkey(), &maker.pubkey(), &maker_token, &mint);
+    assert!(ctx.process(&[ix], &[&stranger]).await.is_err());
+
+    let (otc_vault_pubkey, _) = otc::find_otc_vault_address(&program_id, &mint, &maker.pubkey());
+    let vault_rent = ctx.balance(&otc_vault_pubkey).await;
+    let (otc_state_pubkey, _) = otc::find_otc_state_address(&program_id, &mint, &maker.pubkey());
+    let state_rent = ctx.balance(&otc_state_pubkey).await;
+
+    let taker_token = ctx.create_token_account(&mint, &taker.pubkey()).await;
+    let maker_balance = ctx.balance(&maker.pubkey()).await;
+    let taker_balance = ctx.balance(&taker.pubkey()).await;
+    let ix = instruction::accept_otc(&program_id, &taker.pubkey(), &taker_token, &mint, &maker.pubkey(), &authority);
+    ctx.process(&[ix], &[&taker]).await.unwrap();
+    assert_eq!(ctx.token_balance(&taker_token).await, 1);
+
+    let maker_fee = PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    let taker_fee = PRICE * common::TAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.balance(&maker.pubkey()).await, maker_balance + PRICE - maker_fee + vault_rent + state_rent);
+    assert_eq!(ctx.balance(&taker.pubkey()).await, taker_balance - PRICE - taker_fee);
+    assert!(ctx.get_account(&otc_state_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_otc_counterparty_can_cancel() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let maker = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let taker = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, maker_token) = ctx.create_nft(&maker.pubkey()).await;
+
+    let ix = instruction::create_otc(&program_id, &maker.pubkey(), &maker_token, &mint, &taker.pubkey(), PRICE);
+    ctx.process(&[ix], &[&maker]).await.unwrap();
+
+    let ix = instruction::cancel_otc(&program_id, &taker.pubkey(), &maker.pubkey(), &maker_token, &mint);
+    ctx.process(&[ix], &[&taker]).await.unwrap();
+    assert_eq!(ctx.token_balance(&maker_token).await, 1);
+
+    let (otc_vault_pubkey, _) = otc::find_otc_vault_address(&program_id, &mint, &maker.pubkey());
+    assert!(ctx.get_account(&otc_vault_pubkey).await.is_none());
+}
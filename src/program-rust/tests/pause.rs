@@ -0,0 +1,74 @@
+mod common;
+
+use solana_program::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::Signer;
+
+use nft_trading::{error::NFTError, instruction, state};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_paused_bids_keep_withdrawals_live() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let ix = instruction::set_paused(&program_id, &bidder.pubkey(), state::PAUSE_BID);
+    assert!(ctx.process(&[ix], &[&bidder]).await.is_err());
+    let ix = instruction::set_paused(&program_id, &authority, state::PAUSE_BID);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let ix = instruction::bid(&program_id, &lister.pubkey(), &mint, PRICE, false, None);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InstructionPaused as u32));
+
+    let balance_before = ctx.balance(&bidder.pubkey()).await;
+    let ix = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    assert!(ctx.balance(&bidder.pubkey()).await > balance_before + PRICE);
+
+    // Other entry points are untouched.
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let ix = instruction::set_paused(&program_id, &authority, 0);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE + 1, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_paused_sales_block_accept_bid() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let ix = instruction::set_paused(&program_id, &authority, state::PAUSE_SALE | state::PAUSE_LIST);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let ix = instruction::execute_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InstructionPaused as u32));
+
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&lister_token).await, 1);
+}
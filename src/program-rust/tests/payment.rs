@@ -0,0 +1,137 @@
+mod common;
+
+use solana_program::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::{Keypair, Signer};
+
+use nft_trading::{error::NFTError, instruction, payment, state};
+
+const DECIMALS: u8 = 6;
+const PRICE: u64 = 25_000_000;
+
+#[tokio::test]
+async fn test_token_bid_settles_in_quote_mint() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let usdc = ctx.create_mint(Keypair::new(), DECIMALS).await;
+    let ix = instruction::set_payment_mint(&program_id, &authority, &usdc, true);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+    let lister_usdc = ctx.create_token_account(&usdc, &lister.pubkey()).await;
+    let bidder_usdc = ctx.create_token_account(&usdc, &bidder.pubkey()).await;
+    let fee_usdc = ctx.create_token_account(&usdc, &authority).await;
+    ctx.mint_to(&usdc, &bidder_usdc, 2 * PRICE).await;
+
+    let ix = instruction::list_for_token(&program_id, &lister.pubkey(), &lister_token, &mint, &usdc, PRICE);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&lister_token).await, 0);
+
+    let ix = instruction::bid_with_token(&program_id, &bidder.pubkey(), &bidder_usdc, &mint, &usdc, PRICE);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let taker_fee = PRICE * common::TAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.token_balance(&bidder_usdc).await, 2 * PRICE - PRICE - taker_fee);
+
+    let ix = instruction::accept_token_bid(
+        &program_id,
+        &lister.pubkey(),
+        &lister_usdc,
+        &bidder.pubkey(),
+        &bidder_token,
+        &mint,
+        &usdc,
+        &fee_usdc,
+    );
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let maker_fee = PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+    assert_eq!(ctx.token_balance(&lister_usdc).await, PRICE - maker_fee);
+    assert_eq!(ctx.token_balance(&fee_usdc).await, maker_fee + taker_fee);
+
+    let (listing_pubkey, _) = payment::find_token_listing_address(&program_id, &mint, &lister.pubkey());
+    let (bid_vault_pubkey, _) = payment::find_token_bid_vault_address(&program_id, &mint, &bidder.pubkey());
+    assert!(ctx.get_account(&listing_pubkey).await.is_none());
+    assert!(ctx.get_account(&bid_vault_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_unregistered_or_disabled_quote_mint_is_rejected() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let scam = ctx.create_mint(Keypair::new(), DECIMALS).await;
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list_for_token(&program_id, &lister.pubkey(), &lister_token, &mint, &scam, PRICE);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::PaymentMintNotAllowed as u32));
+
+    let ix = instruction::set_payment_mint(&program_id, &authority, &scam, true);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let ix = instruction::set_payment_mint(&program_id, &authority, &scam, false);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let ix = instruction::list_for_token(&program_id, &lister.pubkey(), &lister_token, &mint, &scam, PRICE + 1);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::PaymentMintNotAllowed as u32));
+    assert_eq!(ctx.token_balance(&lister_token).await, 1);
+}
+
+#[tokio::test]
+async fn test_accept_rejects_bid_in_another_quote_mint() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let usdc = ctx.create_mint(Keypair::new(), DECIMALS).await;
+    let other = ctx.create_mint(Keypair::new(), 9).await;
+    for quote_mint in [usdc, other].iter() {
+        let ix = instruction::set_payment_mint(&program_id, &authority, quote_mint, true);
+        ctx.process(&[ix], &[]).await.unwrap();
+    }
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+    let lister_usdc = ctx.create_token_account(&usdc, &lister.pubkey()).await;
+    let bidder_other = ctx.create_token_account(&other, &bidder.pubkey()).await;
+    let fee_usdc = ctx.create_token_account(&usdc, &authority).await;
+    ctx.mint_to(&other, &bidder_other, 2 * PRICE).await;
+
+    let ix = instruction::list_for_token(&program_id, &lister.pubkey(), &lister_token, &mint, &usdc, PRICE);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid_with_token(&program_id, &bidder.pubkey(), &bidder_other, &mint, &other, PRICE);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let ix = instruction::accept_token_bid(
+        &program_id,
+        &lister.pubkey(),
+        &lister_usdc,
+        &bidder.pubkey(),
+        &bidder_token,
+        &mint,
+        &other,
+        &fee_usdc,
+    );
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::QuoteMintMismatch as u32));
+
+    let ix = instruction::withdraw_token_bid(&program_id, &bidder.pubkey(), &bidder_other, &mint, &other);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    assert_eq!(ctx.token_balance(&bidder_other).await, 2 * PRICE);
+
+    let ix = instruction::cancel_token_listing(&program_id, &lister.pubkey(), &lister_token, &mint);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&lister_token).await, 1);
+}
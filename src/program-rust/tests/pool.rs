@@ -0,0 +1,118 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::signature::{Keypair, Signer};
+
+use nft_trading::{
+    instruction,
+    metadata::Collection,
+    pool::{self, Curve, Pool},
+    state,
+};
+
+const SPOT_PRICE: u64 = LAMPORTS_PER_SOL;
+const DELTA: u64 = LAMPORTS_PER_SOL / 10;
+const POOL_FEE_BPS: u64 = 200;
+
+#[tokio::test]
+async fn test_pool_buy_and_sell_move_spot_price() {
+    let program_id = Pubkey::new_unique();
+    let collection = Pubkey::new_unique();
+    let mint_a = Keypair::new();
+    let mint_b = Keypair::new();
+    let stray_mint = Keypair::new();
+
+    let mut program_test = common::program_test(program_id);
+    for (mint, collection) in &[(&mint_a, collection), (&mint_b, collection), (&stray_mint, Pubkey::new_unique())] {
+        let (metadata_pubkey, metadata_account) = common::metadata_account(
+            &mint.pubkey(),
+            0,
+            None,
+            Some(Collection { verified: true, key: collection.to_bytes() }),
+        );
+        program_test.add_account(metadata_pubkey, metadata_account);
+    }
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+    let authority = ctx.payer.pubkey();
+
+    let lp = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let trader = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint_a, lp_token_a) = ctx.create_nft_with_mint(mint_a, &lp.pubkey()).await;
+    let (mint_b, trader_token_b) = ctx.create_nft_with_mint(mint_b, &trader.pubkey()).await;
+    let (stray_mint, trader_stray_token) = ctx.create_nft_with_mint(stray_mint, &trader.pubkey()).await;
+    let trader_token_a = ctx.create_token_account(&mint_a, &trader.pubkey()).await;
+    let (pool_pubkey, _) = pool::find_pool_address(&program_id, &lp.pubkey(), &collection);
+
+    let ixs = [
+        instruction::create_pool(&program_id, &lp.pubkey(), &collection, Curve::Linear, SPOT_PRICE, DELTA, POOL_FEE_BPS),
+        instruction::deposit_pool_nft(&program_id, &lp.pubkey(), &collection, &lp_token_a, &mint_a),
+        instruction::deposit_pool_sol(&program_id, &lp.pubkey(), &collection, 2 * SPOT_PRICE),
+    ];
+    ctx.process(&ixs, &[&lp]).await.unwrap();
+
+    let buy_price = SPOT_PRICE + DELTA;
+    let buy_cost = buy_price + buy_price * POOL_FEE_BPS / state::MAX_BPS + buy_price * common::TAKER_FEE_BPS / state::MAX_BPS;
+    let ix = instruction::buy_from_pool(&program_id, &trader.pubkey(), &pool_pubkey, &trader_token_a, &mint_a, &authority, buy_cost - 1);
+    assert!(ctx.process(&[ix], &[&trader]).await.is_err());
+
+    let trader_balance = ctx.balance(&trader.pubkey()).await;
+    let vault_rent = ctx.balance(&pool::find_pool_vault_address(&program_id, &pool_pubkey, &mint_a).0).await;
+    let ix = instruction::buy_from_pool(&program_id, &trader.pubkey(), &pool_pubkey, &trader_token_a, &mint_a, &authority, buy_cost);
+    ctx.process(&[ix], &[&trader]).await.unwrap();
+    assert_eq!(ctx.token_balance(&trader_token_a).await, 1);
+    assert_eq!(ctx.balance(&trader.pubkey()).await, trader_balance - buy_cost + vault_rent);
+
+    let pool = Pool::unpack(&ctx.get_account(&pool_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(pool.spot_price, buy_price);
+    assert_eq!(pool.nft_count, 0);
+    assert_eq!(pool.sol_balance, 2 * SPOT_PRICE + buy_price + buy_price * POOL_FEE_BPS / state::MAX_BPS);
+
+    let ix = instruction::sell_to_pool(&program_id, &trader.pubkey(), &pool_pubkey, &trader_stray_token, &stray_mint, &authority, 0);
+    assert!(ctx.process(&[ix], &[&trader]).await.is_err());
+
+    let sell_price = buy_price;
+    let sell_proceeds = sell_price - sell_price * POOL_FEE_BPS / state::MAX_BPS - sell_price * common::TAKER_FEE_BPS / state::MAX_BPS;
+    let trader_balance = ctx.balance(&trader.pubkey()).await;
+    let ix = instruction::sell_to_pool(&program_id, &trader.pubkey(), &pool_pubkey, &trader_token_b, &mint_b, &authority, sell_proceeds);
+    ctx.process(&[ix], &[&trader]).await.unwrap();
+    assert_eq!(ctx.token_balance(&trader_token_b).await, 0);
+    assert_eq!(ctx.balance(&trader.pubkey()).await, trader_balance + sell_proceeds - vault_rent);
+
+    let pool = Pool::unpack(&ctx.get_account(&pool_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(pool.spot_price, SPOT_PRICE);
+    assert_eq!(pool.nft_count, 1);
+
+    let lp_token_b = ctx.create_token_account(&mint_b, &lp.pubkey()).await;
+    let lp_balance = ctx.balance(&lp.pubkey()).await;
+    let ixs = [
+        instruction::withdraw_pool_nft(&program_id, &lp.pubkey(), &collection, &lp_token_b, &mint_b),
+        instruction::withdraw_pool_sol(&program_id, &lp.pubkey(), &collection, pool.sol_balance),
+    ];
+    ctx.process(&ixs, &[&lp]).await.unwrap();
+    assert_eq!(ctx.token_balance(&lp_token_b).await, 1);
+    assert_eq!(ctx.balance(&lp.pubkey()).await, lp_balance + pool.sol_balance + vault_rent);
+
+    let ix = instruction::withdraw_pool_sol(&program_id, &lp.pubkey(), &collection, 1);
+    assert!(ctx.process(&[ix], &[&lp]).await.is_err());
+}
+
+#[test]
+fn test_exponential_curve_prices() {
+    let pool = Pool {
+        is_initialized: true,
+        owner: Pubkey::new_unique(),
+        collection: Pubkey::new_unique(),
+        curve: Curve::Exponential,
+        spot_price: SPOT_PRICE,
+        delta: 1_000,
+        fee_bps: 0,
+        nft_count: 0,
+        sol_balance: 0,
+    };
+    assert_eq!(pool.buy_price().unwrap(), SPOT_PRICE * 11 / 10);
+    assert_eq!(pool.sell_price(), SPOT_PRICE);
+
+    let pool = Pool { spot_price: u64::MAX, ..pool };
+    assert!(pool.buy_price().is_err());
+}
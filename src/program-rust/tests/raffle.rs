@@ -0,0 +1,60 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{instruction, raffle::{self, RaffleState}, state};
+
+const TICKET_PRICE: u64 = LAMPORTS_PER_SOL / 2;
+
+#[tokio::test]
+async fn test_raffle_draws_winner_after_sell_out() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let creator = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let alice = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bob = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let carol = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, creator_token) = ctx.create_nft(&creator.pubkey()).await;
+
+    let ix = instruction::create_raffle(&program_id, &creator.pubkey(), &creator_token, &mint, TICKET_PRICE, 2, i64::MAX);
+    ctx.process(&[ix], &[&creator]).await.unwrap();
+    assert_eq!(ctx.token_balance(&creator_token).await, 0);
+
+    let ix = instruction::buy_raffle_ticket(&program_id, &alice.pubkey(), &mint, &creator.pubkey());
+    let draw = instruction::draw_raffle(&program_id, &mint, &creator.pubkey(), &authority);
+    assert!(ctx.process(&[ix.clone(), draw], &[&alice]).await.is_err());
+    ctx.process(&[ix], &[&alice]).await.unwrap();
+
+    let ix = instruction::buy_raffle_ticket(&program_id, &bob.pubkey(), &mint, &creator.pubkey());
+    ctx.process(&[ix], &[&bob]).await.unwrap();
+    let ix = instruction::buy_raffle_ticket(&program_id, &carol.pubkey(), &mint, &creator.pubkey());
+    assert!(ctx.process(&[ix], &[&carol]).await.is_err());
+
+    let creator_balance = ctx.balance(&creator.pubkey()).await;
+    let ix = instruction::draw_raffle(&program_id, &mint, &creator.pubkey(), &authority);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let proceeds = 2 * TICKET_PRICE;
+    let platform_fee = proceeds * common::MAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.balance(&creator.pubkey()).await, creator_balance + proceeds - platform_fee);
+
+    let (raffle_state_pubkey, _) = raffle::find_raffle_state_address(&program_id, &mint, &creator.pubkey());
+    let raffle_state = RaffleState::unpack(&ctx.get_account(&raffle_state_pubkey).await.unwrap().data).unwrap();
+    assert!(raffle_state.drawn);
+    assert_eq!(raffle_state.entrants, vec![alice.pubkey(), bob.pubkey()]);
+    let (winner, loser) = if raffle_state.winner == alice.pubkey() { (&alice, &bob) } else { (&bob, &alice) };
+    assert_eq!(raffle_state.winner, winner.pubkey());
+
+    let loser_token = ctx.create_token_account(&mint, &loser.pubkey()).await;
+    let ix = instruction::claim_raffle(&program_id, &loser.pubkey(), &loser_token, &mint, &creator.pubkey());
+    assert!(ctx.process(&[ix], &[loser]).await.is_err());
+
+    let winner_token = ctx.create_token_account(&mint, &winner.pubkey()).await;
+    let ix = instruction::claim_raffle(&program_id, &winner.pubkey(), &winner_token, &mint, &creator.pubkey());
+    ctx.process(&[ix], &[winner]).await.unwrap();
+    assert_eq!(ctx.token_balance(&winner_token).await, 1);
+    assert!(ctx.get_account(&raffle_state_pubkey).await.is_none());
+}
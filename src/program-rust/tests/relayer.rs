@@ -0,0 +1,91 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{instruction, state::{self, BidEscrowState, ListEscrowState}};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_relayer_funds_and_recovers_escrow_rent() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let relayer = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let lister = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let relayer_balance = ctx.balance(&relayer.pubkey()).await;
+    let lister_balance = ctx.balance(&lister.pubkey()).await;
+    let bidder_balance = ctx.balance(&bidder.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let ix = instruction::with_payer(ix, &lister.pubkey(), &relayer.pubkey());
+    ctx.process(&[ix], &[&lister, &relayer]).await.unwrap();
+    assert_eq!(ctx.balance(&lister.pubkey()).await, lister_balance);
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let list_state = ListEscrowState::unpack(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.payer, relayer.pubkey());
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    let ix = instruction::with_payer(ix, &bidder.pubkey(), &relayer.pubkey());
+    ctx.process(&[ix], &[&bidder, &relayer]).await.unwrap();
+    let taker_fee = PRICE * common::TAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, bidder_balance - PRICE - taker_fee);
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    let bid_state = BidEscrowState::unpack(&ctx.get_account(&bid_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(bid_state.payer, relayer.pubkey());
+
+    // Rent can only go back to the account that paid it.
+    let ix = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    assert!(ctx.process(&[ix], &[&bidder]).await.is_err());
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+
+    let ix = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    let ix = instruction::with_payer(ix, &bidder.pubkey(), &relayer.pubkey());
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, bidder_balance);
+
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint);
+    let ix = instruction::with_payer(ix, &lister.pubkey(), &relayer.pubkey());
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&lister_token).await, 1);
+    assert_eq!(ctx.balance(&lister.pubkey()).await, lister_balance);
+    assert_eq!(ctx.balance(&relayer.pubkey()).await, relayer_balance);
+}
+
+#[tokio::test]
+async fn test_accept_bid_returns_rent_to_each_payer() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let relayer = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let lister = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+    let relayer_balance = ctx.balance(&relayer.pubkey()).await;
+    let lister_balance = ctx.balance(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let ix = instruction::with_payer(ix, &lister.pubkey(), &relayer.pubkey());
+    ctx.process(&[ix], &[&lister, &relayer]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    let ix = instruction::with_payer(ix, &bidder.pubkey(), &relayer.pubkey());
+    ctx.process(&[ix], &[&bidder, &relayer]).await.unwrap();
+
+    let ix = instruction::execute_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    let ix = instruction::with_payer(ix, &lister.pubkey(), &relayer.pubkey());
+    let ix = instruction::with_payer(ix, &bidder.pubkey(), &relayer.pubkey());
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let maker_fee = PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+    assert_eq!(ctx.balance(&lister.pubkey()).await, lister_balance + PRICE - maker_fee);
+    assert_eq!(ctx.balance(&relayer.pubkey()).await, relayer_balance);
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{instruction, rental::{self, RentalState}, state};
+
+const RENT_PRICE: u64 = LAMPORTS_PER_SOL / 10;
+
+#[tokio::test]
+async fn test_rental_blocks_reclaim_until_expiry() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let owner = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let renter = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let other = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, owner_token) = ctx.create_nft(&owner.pubkey()).await;
+
+    let ix = instruction::list_for_rent(&program_id, &owner.pubkey(), &owner_token, &mint, RENT_PRICE, 1_000_000);
+    ctx.process(&[ix], &[&owner]).await.unwrap();
+    assert_eq!(ctx.token_balance(&owner_token).await, 0);
+
+    let ix = instruction::reclaim_rental(&program_id, &owner.pubkey(), &owner_token, &mint, false);
+    assert!(ctx.process(&[ix], &[]).await.is_err());
+
+    let owner_balance = ctx.balance(&owner.pubkey()).await;
+    let renter_balance = ctx.balance(&renter.pubkey()).await;
+    let ix = instruction::rent(&program_id, &renter.pubkey(), &mint, &owner.pubkey(), &authority);
+    ctx.process(&[ix], &[&renter]).await.unwrap();
+    let platform_fee = RENT_PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.balance(&owner.pubkey()).await, owner_balance + RENT_PRICE - platform_fee);
+    assert_eq!(ctx.balance(&renter.pubkey()).await, renter_balance - RENT_PRICE);
+
+    let (rental_state_pubkey, _) = rental::find_rental_state_address(&program_id, &mint, &owner.pubkey());
+    let rental_state = RentalState::unpack(&ctx.get_account(&rental_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(rental_state.renter, renter.pubkey());
+    assert!(rental_state.expires_slot >= 1_000_000);
+
+    let ix = instruction::rent(&program_id, &other.pubkey(), &mint, &owner.pubkey(), &authority);
+    assert!(ctx.process(&[ix], &[&other]).await.is_err());
+    let ix = instruction::reclaim_rental(&program_id, &owner.pubkey(), &owner_token, &mint, true);
+    assert!(ctx.process(&[ix], &[&owner]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_expired_rental_is_reclaimed_by_crank() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let owner = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let renter = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, owner_token) = ctx.create_nft(&owner.pubkey()).await;
+
+    let ix = instruction::list_for_rent(&program_id, &owner.pubkey(), &owner_token, &mint, RENT_PRICE, 0);
+    ctx.process(&[ix], &[&owner]).await.unwrap();
+    let ix = instruction::rent(&program_id, &renter.pubkey(), &mint, &owner.pubkey(), &authority);
+    ctx.process(&[ix], &[&renter]).await.unwrap();
+
+    let ix = instruction::reclaim_rental(&program_id, &owner.pubkey(), &owner_token, &mint, false);
+    ctx.process(&[ix], &[]).await.unwrap();
+    assert_eq!(ctx.token_balance(&owner_token).await, 1);
+
+    let (rental_state_pubkey, _) = rental::find_rental_state_address(&program_id, &mint, &owner.pubkey());
+    let (rental_vault_pubkey, _) = rental::find_rental_vault_address(&program_id, &mint, &owner.pubkey());
+    assert!(ctx.get_account(&rental_state_pubkey).await.is_none());
+    assert!(ctx.get_account(&rental_vault_pubkey).await.is_none());
+}
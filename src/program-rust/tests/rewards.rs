@@ -0,0 +1,73 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::signature::{Keypair, Signer};
+
+use nft_trading::{error::NFTError, instruction, rewards::{self, RewardsConfig}};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+/// Creates a mint whose authority is the rewards authority PDA.
+async fn create_rewards_mint(ctx: &mut common::TestContext) -> Pubkey {
+    let mint = ctx.create_mint(Keypair::new(), 0).await;
+    let (rewards_authority, _) = rewards::find_rewards_authority_address(&ctx.program_id);
+    let payer = ctx.payer.pubkey();
+    let ix = spl_token::instruction::set_authority(
+        &spl_token::id(),
+        &mint,
+        Some(&rewards_authority),
+        spl_token::instruction::AuthorityType::MintTokens,
+        &payer,
+        &[],
+    ).unwrap();
+    ctx.process(&[ix], &[]).await.unwrap();
+    mint
+}
+
+#[tokio::test]
+async fn test_sales_mint_rewards_up_to_epoch_cap() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let rewards_mint = create_rewards_mint(&mut ctx).await;
+    let ix = instruction::set_rewards(&program_id, &authority, &rewards_mint, 100, 50, 220);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let lister_rewards = ctx.create_token_account(&rewards_mint, &lister.pubkey()).await;
+    let bidder_rewards = ctx.create_token_account(&rewards_mint, &bidder.pubkey()).await;
+
+    for _ in 0..2 {
+        let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+        let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+        ctx.process(&[ix], &[&lister]).await.unwrap();
+        let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+        ctx.process(&[ix], &[&bidder]).await.unwrap();
+        let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+        let ix = instruction::with_trade_rewards(ix, &rewards_mint, &bidder_rewards, &lister_rewards);
+        ctx.process(&[ix], &[&lister]).await.unwrap();
+    }
+
+    // The second sale only had 70 left under the cap, all to the buyer.
+    assert_eq!(ctx.token_balance(&bidder_rewards).await, 170);
+    assert_eq!(ctx.token_balance(&lister_rewards).await, 50);
+    let (config_pubkey, _) = rewards::find_rewards_config_address(&program_id);
+    let config = RewardsConfig::unpack(&ctx.get_account(&config_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(config.epoch_minted, 220);
+}
+
+#[tokio::test]
+async fn test_set_rewards_needs_mint_authority() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let mint = ctx.create_mint(Keypair::new(), 0).await;
+    let ix = instruction::set_rewards(&program_id, &authority, &mint, 100, 50, 1_000);
+    let result = ctx.process(&[ix], &[]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidAuthority as u32));
+}
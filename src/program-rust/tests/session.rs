@@ -0,0 +1,87 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{error::NFTError, instruction, session, state::{self, BidEscrowState, ListEscrowState}};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_session_key_lists_and_bids_for_owner() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let owner = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let session_key = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (listed_mint, owner_token) = ctx.create_nft(&owner.pubkey()).await;
+    let (bid_mint, _) = ctx.create_nft(&authority).await;
+
+    let ix = instruction::create_session(&program_id, &owner.pubkey(), &session_key.pubkey(), i64::MAX, session::ALLOW_LIST | session::ALLOW_BID);
+    ctx.process(&[ix], &[&owner]).await.unwrap();
+    let owner_balance = ctx.balance(&owner.pubkey()).await;
+
+    let ix = spl_token::instruction::approve(&spl_token::id(), &owner_token, &session_key.pubkey(), &owner.pubkey(), &[], 1).unwrap();
+    ctx.process(&[ix], &[&owner]).await.unwrap();
+    let ix = instruction::list(&program_id, &owner.pubkey(), &owner_token, &listed_mint, PRICE, false, None, None, 0, 0);
+    let ix = instruction::with_session(ix, &owner.pubkey(), &session_key.pubkey());
+    ctx.process(&[ix], &[&session_key]).await.unwrap();
+    assert_eq!(ctx.token_balance(&owner_token).await, 0);
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &listed_mint, &owner.pubkey());
+    let list_state = ListEscrowState::unpack(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.lister, owner.pubkey());
+    assert_eq!(list_state.payer, session_key.pubkey());
+
+    let ix = instruction::bid(&program_id, &owner.pubkey(), &bid_mint, PRICE, false, None);
+    let ix = instruction::with_session(ix, &owner.pubkey(), &session_key.pubkey());
+    ctx.process(&[ix], &[&session_key]).await.unwrap();
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &bid_mint, &owner.pubkey());
+    let bid_state = BidEscrowState::unpack(&ctx.get_account(&bid_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(bid_state.bidder, owner.pubkey());
+    assert_eq!(ctx.balance(&owner.pubkey()).await, owner_balance);
+
+    // The bid is the owner's: withdrawing it pays the escrow to the owner.
+    let ix = instruction::withdraw_bid(&program_id, &owner.pubkey(), &bid_mint);
+    let ix = instruction::with_payer(ix, &owner.pubkey(), &session_key.pubkey());
+    ctx.process(&[ix], &[&owner]).await.unwrap();
+    let taker_fee = PRICE * common::TAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.balance(&owner.pubkey()).await, owner_balance + PRICE + taker_fee);
+}
+
+#[tokio::test]
+async fn test_session_key_is_limited_to_its_mask_until_revoked() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let owner = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let session_key = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let stranger = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, _) = ctx.create_nft(&authority).await;
+
+    let ix = instruction::create_session(&program_id, &owner.pubkey(), &session_key.pubkey(), i64::MAX, session::ALLOW_LIST);
+    ctx.process(&[ix], &[&owner]).await.unwrap();
+
+    let ix = instruction::bid(&program_id, &owner.pubkey(), &mint, PRICE, false, None);
+    let ix = instruction::with_session(ix, &owner.pubkey(), &session_key.pubkey());
+    let result = ctx.process(&[ix], &[&session_key]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::SessionNotAllowed as u32));
+
+    let ix = instruction::bid(&program_id, &owner.pubkey(), &mint, PRICE + 1, false, None);
+    let ix = instruction::with_session(ix, &owner.pubkey(), &stranger.pubkey());
+    assert!(ctx.process(&[ix], &[&stranger]).await.is_err());
+
+    let ix = instruction::create_session(&program_id, &owner.pubkey(), &session_key.pubkey(), i64::MAX, session::ALLOW_BID);
+    ctx.process(&[ix], &[&owner]).await.unwrap();
+    let ix = instruction::revoke_session(&program_id, &owner.pubkey(), &session_key.pubkey());
+    ctx.process(&[ix], &[&owner]).await.unwrap();
+
+    let ix = instruction::bid(&program_id, &owner.pubkey(), &mint, PRICE + 2, false, None);
+    let ix = instruction::with_session(ix, &owner.pubkey(), &session_key.pubkey());
+    assert!(ctx.process(&[ix], &[&session_key]).await.is_err());
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &owner.pubkey());
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_none());
+}
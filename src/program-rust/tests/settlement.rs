@@ -0,0 +1,102 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{error::NFTError, instruction, state::{self, ListEscrowState}};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+const DELAY_SLOTS: u64 = 1_000_000;
+
+#[tokio::test]
+async fn test_arbiter_reverses_held_sale() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let arbiter = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let stranger = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let ix = instruction::with_delayed_settlement(ix, DELAY_SLOTS, Some(&arbiter.pubkey()));
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let bidder_balance = ctx.balance(&bidder.pubkey()).await;
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    // Neither side can back out on its own: the lister cannot delist a held sale...
+    let delist = instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint);
+    let result = ctx.process(&[ix.clone(), delist], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::SettlementPending as u32));
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    // ...nothing has moved yet, and the bidder cannot withdraw.
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let (list_vault_pubkey, _) = state::find_list_vault_address(&program_id, &mint, &lister.pubkey());
+    let list_state = ListEscrowState::unpack(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert!(list_state.is_settling());
+    assert_eq!(list_state.successful_buyer, bidder.pubkey());
+    assert_eq!(ctx.token_balance(&list_vault_pubkey).await, 1);
+    let ix = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    assert!(ctx.process(&[ix], &[&bidder]).await.is_err());
+
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+    let ix = instruction::finalize_settlement(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    let result = ctx.process(&[ix], &[]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::SettlementPending as u32));
+
+    let ix = instruction::reverse_settlement(&program_id, &stranger.pubkey(), &mint, &lister.pubkey(), &bidder.pubkey(), &bidder.pubkey());
+    let result = ctx.process(&[ix], &[&stranger]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidAuthority as u32));
+
+    let ix = instruction::reverse_settlement(&program_id, &arbiter.pubkey(), &mint, &lister.pubkey(), &bidder.pubkey(), &bidder.pubkey());
+    ctx.process(&[ix], &[&arbiter]).await.unwrap();
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, bidder_balance);
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_none());
+    let list_state = ListEscrowState::unpack(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert!(!list_state.is_settling());
+
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&lister_token).await, 1);
+}
+
+#[tokio::test]
+async fn test_delayed_settlement_bounds() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let ix = instruction::with_delayed_settlement(ix, state::MAX_SETTLEMENT_DELAY_SLOTS + 1, None);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidInstructionData as u32));
+
+    // Auctions settle with their highest bidder, never through a hold.
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, true, None, None, 0, 0);
+    let ix = instruction::with_delayed_settlement(ix, DELAY_SLOTS, None);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidInstructionData as u32));
+
+    // A bid at the ask does not cross without the lister accepting it.
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let ix = instruction::with_delayed_settlement(ix, DELAY_SLOTS, None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+    let ix = instruction::execute_matched_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    assert!(ctx.process(&[ix], &[]).await.is_err());
+    let (list_vault_pubkey, _) = state::find_list_vault_address(&program_id, &mint, &lister.pubkey());
+    assert_eq!(ctx.token_balance(&list_vault_pubkey).await, 1);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::{Keypair, Signer};
+
+use nft_trading::{instruction, staking::{self, StakeAccount, StakePool}};
+
+const REWARD: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_staking_rewards_are_shared_pro_rata() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let stake_mint = ctx.create_mint(Keypair::new(), 6).await;
+    let ix = instruction::init_stake_pool(&program_id, &authority, &stake_mint);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let ix = instruction::fund_staking_rewards(&program_id, &authority, REWARD);
+    assert!(ctx.process(&[ix], &[]).await.is_err());
+
+    let alice = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bob = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let alice_token = ctx.create_token_account(&stake_mint, &alice.pubkey()).await;
+    let bob_token = ctx.create_token_account(&stake_mint, &bob.pubkey()).await;
+    ctx.mint_to(&stake_mint, &alice_token, 100).await;
+    ctx.mint_to(&stake_mint, &bob_token, 300).await;
+
+    let ix = instruction::stake(&program_id, &alice.pubkey(), &alice_token, 100);
+    ctx.process(&[ix], &[&alice]).await.unwrap();
+    let stake = instruction::stake(&program_id, &bob.pubkey(), &bob_token, 300);
+    let ix = instruction::fund_staking_rewards(&program_id, &authority, REWARD);
+    ctx.process(&[stake, ix], &[&bob]).await.unwrap();
+
+    let alice_balance = ctx.balance(&alice.pubkey()).await;
+    let ix = instruction::claim_rewards(&program_id, &alice.pubkey());
+    ctx.process(&[ix], &[&alice]).await.unwrap();
+    assert_eq!(ctx.balance(&alice.pubkey()).await, alice_balance + REWARD / 4);
+
+    let ix = instruction::unstake(&program_id, &bob.pubkey(), &bob_token, 301);
+    assert!(ctx.process(&[ix], &[&bob]).await.is_err());
+
+    let bob_balance = ctx.balance(&bob.pubkey()).await;
+    let ix = instruction::unstake(&program_id, &bob.pubkey(), &bob_token, 300);
+    ctx.process(&[ix], &[&bob]).await.unwrap();
+    assert_eq!(ctx.balance(&bob.pubkey()).await, bob_balance + 3 * REWARD / 4);
+    assert_eq!(ctx.token_balance(&bob_token).await, 300);
+
+    let (stake_pool_pubkey, _) = staking::find_stake_pool_address(&program_id);
+    let stake_pool = StakePool::unpack(&ctx.get_account(&stake_pool_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(stake_pool.total_staked, 100);
+    assert_eq!(stake_pool.total_rewards, REWARD);
+    assert_eq!(stake_pool.unclaimed_rewards, 0);
+
+    let (bob_stake_pubkey, _) = staking::find_stake_account_address(&program_id, &bob.pubkey());
+    let bob_stake = StakeAccount::unpack(&ctx.get_account(&bob_stake_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(bob_stake.amount, 0);
+    assert_eq!(bob_stake.reward_checkpoint, stake_pool.reward_per_token);
+}
@@ -0,0 +1,834 @@
+use proptest::prelude::*;
+use solana_program::{program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+
+use nft_trading::attestation::{self, AttestationConfig};
+use nft_trading::core_listing::{self, CoreListing};
+use nft_trading::fraction::{self, FractionState};
+use nft_trading::index::{self, EscrowIndex, IndexKind};
+use nft_trading::leaderboard::{self, Leaderboard, LeaderboardEntry};
+use nft_trading::otc::{self, OtcState};
+use nft_trading::payment::{self, PaymentMint, TokenBid, TokenListing};
+use nft_trading::pool::{self, Curve, Pool};
+use nft_trading::raffle::{self, RaffleState};
+use nft_trading::rental::{self, RentalState};
+use nft_trading::rewards::{self, RewardsConfig};
+use nft_trading::session::{self, Session};
+use nft_trading::staking::{self, StakeAccount, StakePool};
+use nft_trading::state::{self, BidEscrowState, BookEntry, CollectionState, CollectionStats, Discriminator, FeeDiscount, LegacyLayout, ListEscrowState, OrderBook, ParamChange, PlatformState, UserStats};
+use nft_trading::swap::{self, SwapState};
+use nft_trading::trait_offer::{self, TraitOffer};
+
+const LIST_SUCCESS_OFFSET: usize = 82;
+const ORDER_BOOK_ASK_COUNT_OFFSET: usize = 42;
+const INDEX_COUNT_OFFSET: usize = 42;
+const LEADERBOARD_COUNT_OFFSET: usize = 33;
+const SWAP_MAKER_COUNT_OFFSET: usize = 98;
+const SWAP_TAKER_COUNT_OFFSET: usize = 99 + swap::SWAP_MAX_ASSETS * 32;
+const POOL_CURVE_OFFSET: usize = 73;
+const IS_INITIALIZED_OFFSET: usize = 9;
+
+fn arb_pubkey() -> impl Strategy<Value = Pubkey> {
+    any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+}
+
+prop_compose! {
+    fn arb_platform_state()(
+        (layout_version, is_initialized) in any::<(u8, bool)>(),
+        authority in arb_pubkey(),
+        (maker_fee_bps, referral_fee_bps, taker_fee_bps) in any::<(u64, u64, u64)>(),
+        (nonce, curated) in any::<(u64, bool)>(),
+        (sale_count, total_volume, total_fees, active_listings) in any::<(u64, u64, u64, u64)>(),
+        (fee_recipient, strict, attestation_required) in (arb_pubkey(), any::<bool>(), any::<bool>()),
+        (min_listing_lamports, min_bid_lamports) in any::<(u64, u64)>(),
+        (timelock_slots, pending_change_slot) in any::<(u64, u64)>(),
+        pending_change in prop::option::of(arb_param_change()),
+        (min_bid_lifetime_slots, paused) in any::<(u64, u64)>(),
+        (automation_thread, stale_bid_slots, max_royalty_bps) in (arb_pubkey(), any::<u64>(), any::<u64>()),
+        (curator, curator_fee_bps, max_charity_bps, marketplace_fee_bps, price_feed, reserved) in
+            (arb_pubkey(), any::<u64>(), any::<u64>(), any::<u64>(), arb_pubkey(), any::<[u64; state::RESERVED_WORDS - 8]>()),
+    ) -> PlatformState {
+        PlatformState {
+            layout_version, is_initialized, authority, maker_fee_bps, nonce, referral_fee_bps, taker_fee_bps, curated,
+            sale_count, total_volume, total_fees, active_listings, fee_recipient, strict,
+            min_listing_lamports, min_bid_lamports, timelock_slots, pending_change, pending_change_slot,
+            min_bid_lifetime_slots, paused, automation_thread, stale_bid_slots, max_royalty_bps,
+            curator, curator_fee_bps, max_charity_bps, marketplace_fee_bps, price_feed, attestation_required, reserved,
+        }
+    }
+}
+
+fn arb_param_change() -> impl Strategy<Value = ParamChange> {
+    prop_oneof![
+        any::<(u64, u64)>().prop_map(|(maker_bps, taker_bps)| ParamChange::Fees { maker_bps, taker_bps }),
+        arb_pubkey().prop_map(|authority| ParamChange::Authority { authority }),
+        any::<u64>().prop_map(|slots| ParamChange::Timelock { slots }),
+        arb_pubkey().prop_map(|fee_recipient| ParamChange::FeeRecipient { fee_recipient }),
+        any::<u64>().prop_map(|referral_fee_bps| ParamChange::ReferralFee { referral_fee_bps }),
+        any::<u64>().prop_map(|max_royalty_bps| ParamChange::MaxRoyalty { max_royalty_bps }),
+        any::<u64>().prop_map(|marketplace_fee_bps| ParamChange::MarketplaceFee { marketplace_fee_bps }),
+        arb_pubkey().prop_map(|price_feed| ParamChange::PriceFeed { price_feed }),
+        (arb_pubkey(), any::<u64>()).prop_map(|(curator, curator_fee_bps)| ParamChange::Curator { curator, curator_fee_bps }),
+    ]
+}
+
+prop_compose! {
+    fn arb_collection_state()(
+        layout_version in any::<u8>(),
+        is_initialized in any::<bool>(),
+        collection in arb_pubkey(),
+        allowed in any::<bool>(),
+        reserved in any::<[u64; state::RESERVED_WORDS]>(),
+    ) -> CollectionState {
+        CollectionState { layout_version, is_initialized, collection, allowed, reserved }
+    }
+}
+
+prop_compose! {
+    fn arb_user_stats()(
+        layout_version in any::<u8>(),
+        is_initialized in any::<bool>(),
+        owner in arb_pubkey(),
+        total_volume in any::<u64>(),
+        purchases in any::<u64>(),
+        sales in any::<u64>(),
+        last_activity_slot in any::<u64>(),
+        reserved in any::<[u64; state::RESERVED_WORDS]>(),
+    ) -> UserStats {
+        UserStats { layout_version, is_initialized, owner, total_volume, purchases, sales, last_activity_slot, reserved }
+    }
+}
+
+prop_compose! {
+    fn arb_list_escrow_state()(
+        (layout_version, is_initialized) in any::<(u8, bool)>(),
+        (lister, mint) in (arb_pubkey(), arb_pubkey()),
+        (amount, success) in any::<(u64, bool)>(),
+        (successful_buyer, arbiter) in (arb_pubkey(), arb_pubkey()),
+        (auction, settlement_delay_slots, settles_at_slot) in any::<(bool, u64, u64)>(),
+        (highest_bid, highest_bidder) in (any::<u64>(), arb_pubkey()),
+        allowed_buyer in arb_pubkey(),
+        (starts_at, payer) in (any::<i64>(), arb_pubkey()),
+        (state_bump, vault_bump, royalty_bps, visibility) in any::<(u8, u8, u16, u8)>(),
+        (curator, approved) in (arb_pubkey(), any::<bool>()),
+        (created_at_slot, updated_at_slot, bid_count, last_bid_slot) in any::<(u64, u64, u64, u64)>(),
+        (allowlist_root, charity, charity_bps) in (any::<[u8; 32]>(), arb_pubkey(), any::<u16>()),
+        (marketplace, reclaimable_at, price_usd, price_feed) in (arb_pubkey(), any::<i64>(), any::<u64>(), arb_pubkey()),
+    ) -> ListEscrowState {
+        ListEscrowState {
+            layout_version, is_initialized, lister, mint, amount, success, successful_buyer, auction, highest_bid, highest_bidder,
+            allowed_buyer, starts_at, payer, state_bump, vault_bump, royalty_bps, curator, approved,
+            created_at_slot, updated_at_slot, bid_count, last_bid_slot, allowlist_root, charity, charity_bps,
+            marketplace, reclaimable_at, price_usd, settlement_delay_slots, arbiter, settles_at_slot, visibility, price_feed,
+            reserved: Default::default(),
+        }
+    }
+}
+
+prop_compose! {
+    fn arb_bid_escrow_state()(
+        layout_version in any::<u8>(),
+        is_initialized in any::<bool>(),
+        bidder in arb_pubkey(),
+        mint in arb_pubkey(),
+        amount in any::<u64>(),
+        (referrer, auction_listing, marketplace) in (arb_pubkey(), arb_pubkey(), arb_pubkey()),
+        taker_fee in any::<u64>(),
+        wrapped in any::<bool>(),
+        payer in arb_pubkey(),
+        withdrawable_slot in any::<u64>(),
+        (state_bump, vault_bump) in any::<(u8, u8)>(),
+        (created_at_slot, updated_at_slot, expires_at, reserved) in any::<(u64, u64, i64, [u64; state::RESERVED_WORDS - 1])>(),
+    ) -> BidEscrowState {
+        BidEscrowState {
+            layout_version, is_initialized, bidder, mint, amount, referrer, taker_fee, wrapped, payer, withdrawable_slot,
+            state_bump, vault_bump, created_at_slot, updated_at_slot, auction_listing, marketplace, expires_at, reserved,
+        }
+    }
+}
+
+prop_compose! {
+    fn arb_book_entry()(
+        price in any::<u64>(),
+        maker in arb_pubkey(),
+        mint in arb_pubkey(),
+    ) -> BookEntry {
+        BookEntry { price, maker, mint }
+    }
+}
+
+prop_compose! {
+    fn arb_order_book()(
+        layout_version in any::<u8>(),
+        is_initialized in any::<bool>(),
+        collection in arb_pubkey(),
+        asks in prop::collection::vec(arb_book_entry(), 0..=state::ORDER_BOOK_DEPTH),
+        bids in prop::collection::vec(arb_book_entry(), 0..=state::ORDER_BOOK_DEPTH),
+        reserved in any::<[u64; state::RESERVED_WORDS]>(),
+    ) -> OrderBook {
+        OrderBook { layout_version, is_initialized, collection, asks, bids, reserved }
+    }
+}
+
+prop_compose! {
+    fn arb_pool()(
+        is_initialized in any::<bool>(),
+        owner in arb_pubkey(),
+        collection in arb_pubkey(),
+        exponential in any::<bool>(),
+        spot_price in any::<u64>(),
+        delta in any::<u64>(),
+        fee_bps in any::<u64>(),
+        nft_count in any::<u64>(),
+        sol_balance in any::<u64>(),
+    ) -> Pool {
+        let curve = if exponential { Curve::Exponential } else { Curve::Linear };
+        Pool { is_initialized, owner, collection, curve, spot_price, delta, fee_bps, nft_count, sol_balance }
+    }
+}
+
+prop_compose! {
+    fn arb_stake_pool()(
+        is_initialized in any::<bool>(),
+        stake_mint in arb_pubkey(),
+        total_staked in any::<u64>(),
+        reward_per_token in any::<u128>(),
+        total_rewards in any::<u64>(),
+        unclaimed_rewards in any::<u64>(),
+    ) -> StakePool {
+        StakePool { is_initialized, stake_mint, total_staked, reward_per_token, total_rewards, unclaimed_rewards }
+    }
+}
+
+prop_compose! {
+    fn arb_stake_account()(
+        is_initialized in any::<bool>(),
+        owner in arb_pubkey(),
+        amount in any::<u64>(),
+        reward_checkpoint in any::<u128>(),
+    ) -> StakeAccount {
+        StakeAccount { is_initialized, owner, amount, reward_checkpoint }
+    }
+}
+
+prop_compose! {
+    fn arb_raffle_state()(
+        is_initialized in any::<bool>(),
+        creator in arb_pubkey(),
+        mint in arb_pubkey(),
+        ticket_price in any::<u64>(),
+        total_tickets in any::<u64>(),
+        end_time in any::<i64>(),
+        drawn in any::<bool>(),
+        winner in arb_pubkey(),
+        entrants in prop::collection::vec(arb_pubkey(), 0..=raffle::MAX_RAFFLE_TICKETS),
+    ) -> RaffleState {
+        RaffleState { is_initialized, creator, mint, ticket_price, total_tickets, end_time, drawn, winner, entrants }
+    }
+}
+
+prop_compose! {
+    fn arb_fraction_state()(
+        is_initialized in any::<bool>(),
+        curator in arb_pubkey(),
+        mint in arb_pubkey(),
+        share_mint in arb_pubkey(),
+        share_supply in any::<u64>(),
+        buyout_price in any::<u64>(),
+        bought_out in any::<bool>(),
+        outstanding_shares in any::<u64>(),
+        proceeds in any::<u64>(),
+    ) -> FractionState {
+        FractionState { is_initialized, curator, mint, share_mint, share_supply, buyout_price, bought_out, outstanding_shares, proceeds }
+    }
+}
+
+prop_compose! {
+    fn arb_rental_state()(
+        is_initialized in any::<bool>(),
+        owner in arb_pubkey(),
+        mint in arb_pubkey(),
+        price in any::<u64>(),
+        duration_slots in any::<u64>(),
+        renter in arb_pubkey(),
+        expires_slot in any::<u64>(),
+    ) -> RentalState {
+        RentalState { is_initialized, owner, mint, price, duration_slots, renter, expires_slot }
+    }
+}
+
+prop_compose! {
+    fn arb_fee_discount()(
+        layout_version in any::<u8>(),
+        is_initialized in any::<bool>(),
+        discount_mint in arb_pubkey(),
+        threshold in any::<u64>(),
+        discount_bps in any::<u64>(),
+        reserved in any::<[u64; state::RESERVED_WORDS]>(),
+    ) -> FeeDiscount {
+        FeeDiscount { layout_version, is_initialized, discount_mint, threshold, discount_bps, reserved }
+    }
+}
+
+prop_compose! {
+    fn arb_collection_stats()(
+        layout_version in any::<u8>(),
+        is_initialized in any::<bool>(),
+        collection in arb_pubkey(),
+        last_sale_price in any::<u64>(),
+        total_volume in any::<u64>(),
+        sale_count in any::<u64>(),
+        floor_price in any::<u64>(),
+        (floor_listing, reserved) in (arb_pubkey(), any::<[u64; state::RESERVED_WORDS - 2]>()),
+        (floor_estimate, floor_updated_slot) in any::<(u64, u64)>(),
+    ) -> CollectionStats {
+        CollectionStats {
+            layout_version, is_initialized, collection, last_sale_price, total_volume, sale_count, floor_price, floor_listing,
+            floor_estimate, floor_updated_slot, reserved,
+        }
+    }
+}
+
+prop_compose! {
+    fn arb_otc_state()(
+        is_initialized in any::<bool>(),
+        maker in arb_pubkey(),
+        counterparty in arb_pubkey(),
+        mint in arb_pubkey(),
+        price in any::<u64>(),
+    ) -> OtcState {
+        OtcState { is_initialized, maker, counterparty, mint, price }
+    }
+}
+
+prop_compose! {
+    fn arb_core_listing()(
+        is_initialized in any::<bool>(),
+        seller in arb_pubkey(),
+        asset in arb_pubkey(),
+        price in any::<u64>(),
+    ) -> CoreListing {
+        CoreListing { is_initialized, seller, asset, price }
+    }
+}
+
+prop_compose! {
+    fn arb_payment_mint()(
+        is_initialized in any::<bool>(),
+        mint in arb_pubkey(),
+        decimals in any::<u8>(),
+        enabled in any::<bool>(),
+    ) -> PaymentMint {
+        PaymentMint { is_initialized, mint, decimals, enabled }
+    }
+}
+
+prop_compose! {
+    fn arb_token_listing()(
+        is_initialized in any::<bool>(),
+        lister in arb_pubkey(),
+        mint in arb_pubkey(),
+        quote_mint in arb_pubkey(),
+        price in any::<u64>(),
+    ) -> TokenListing {
+        TokenListing { is_initialized, lister, mint, quote_mint, price }
+    }
+}
+
+prop_compose! {
+    fn arb_token_bid()(
+        is_initialized in any::<bool>(),
+        bidder in arb_pubkey(),
+        mint in arb_pubkey(),
+        quote_mint in arb_pubkey(),
+        amount in any::<u64>(),
+        taker_fee in any::<u64>(),
+    ) -> TokenBid {
+        TokenBid { is_initialized, bidder, mint, quote_mint, amount, taker_fee }
+    }
+}
+
+prop_compose! {
+    fn arb_session()(
+        layout_version in any::<u8>(),
+        is_initialized in any::<bool>(),
+        owner in arb_pubkey(),
+        session_key in arb_pubkey(),
+        expires_at in any::<i64>(),
+        allowed in any::<u8>(),
+        reserved in any::<[u64; state::RESERVED_WORDS]>(),
+    ) -> Session {
+        Session { layout_version, is_initialized, owner, session_key, expires_at, allowed, reserved }
+    }
+}
+
+prop_compose! {
+    fn arb_trait_offer()(
+        is_initialized in any::<bool>(),
+        bidder in arb_pubkey(),
+        root in any::<[u8; 32]>(),
+        attestor in arb_pubkey(),
+        amount in any::<u64>(),
+        taker_fee in any::<u64>(),
+    ) -> TraitOffer {
+        TraitOffer { is_initialized, bidder, root, attestor, amount, taker_fee }
+    }
+}
+
+prop_compose! {
+    fn arb_rewards_config()(
+        is_initialized in any::<bool>(),
+        mint in arb_pubkey(),
+        (buyer_reward, seller_reward, epoch_cap) in any::<(u64, u64, u64)>(),
+        (epoch, epoch_minted) in any::<(u64, u64)>(),
+    ) -> RewardsConfig {
+        RewardsConfig { is_initialized, mint, buyer_reward, seller_reward, epoch_cap, epoch, epoch_minted }
+    }
+}
+
+prop_compose! {
+    fn arb_attestation_config()(
+        layout_version in any::<u8>(),
+        is_initialized in any::<bool>(),
+        attestor in arb_pubkey(),
+        reserved in any::<[u64; state::RESERVED_WORDS]>(),
+    ) -> AttestationConfig {
+        AttestationConfig { layout_version, is_initialized, attestor, reserved }
+    }
+}
+
+prop_compose! {
+    fn arb_escrow_index()(
+        is_initialized in any::<bool>(),
+        mint in any::<bool>(),
+        key in arb_pubkey(),
+        entries in prop::collection::vec(arb_pubkey(), 0..=index::INDEX_CAPACITY),
+    ) -> EscrowIndex {
+        let kind = if mint { IndexKind::Mint } else { IndexKind::Owner };
+        EscrowIndex { is_initialized, kind, key, entries }
+    }
+}
+
+prop_compose! {
+    fn arb_leaderboard()(
+        is_initialized in any::<bool>(),
+        season in any::<u64>(),
+        (starts_at, ends_at) in any::<(i64, i64)>(),
+        entries in prop::collection::vec(
+            (arb_pubkey(), any::<u64>()).prop_map(|(trader, volume)| LeaderboardEntry { trader, volume }),
+            0..=leaderboard::LEADERBOARD_DEPTH,
+        ),
+    ) -> Leaderboard {
+        Leaderboard { is_initialized, season, starts_at, ends_at, entries }
+    }
+}
+
+prop_compose! {
+    fn arb_swap_state()(
+        is_initialized in any::<bool>(),
+        id in any::<u64>(),
+        maker in arb_pubkey(),
+        taker in arb_pubkey(),
+        (maker_lamports, taker_lamports) in any::<(u64, u64)>(),
+        taker_deposited in any::<bool>(),
+        maker_mints in prop::collection::vec(arb_pubkey(), 0..=swap::SWAP_MAX_ASSETS),
+        taker_mints in prop::collection::vec(arb_pubkey(), 0..=swap::SWAP_MAX_ASSETS),
+    ) -> SwapState {
+        SwapState { is_initialized, id, maker, taker, maker_lamports, taker_lamports, taker_deposited, maker_mints, taker_mints }
+    }
+}
+
+fn round_trip<T: Pack + PartialEq + std::fmt::Debug>(value: T) {
+    let mut packed = vec![0u8; T::LEN];
+    value.pack_into_slice(&mut packed);
+    let unpacked = T::unpack_from_slice(&packed).unwrap();
+    assert_eq!(unpacked, value);
+
+    let mut repacked = vec![0u8; T::LEN];
+    unpacked.pack_into_slice(&mut repacked);
+    assert_eq!(repacked, packed);
+}
+
+#[test]
+fn test_state_sizes() {
+    assert_eq!(PlatformState::LEN, state::STATESIZE);
+    assert_eq!(ListEscrowState::LEN, state::LISTESCROWSTATE);
+    assert_eq!(BidEscrowState::LEN, state::BIDESCROWSTATE);
+    assert_eq!(CollectionState::LEN, state::COLLECTIONSTATE);
+    assert_eq!(UserStats::LEN, state::USERSTATSSIZE);
+    assert_eq!(OrderBook::LEN, state::ORDERBOOKSIZE);
+    assert_eq!(Pool::LEN, pool::POOLSIZE);
+    assert_eq!(StakePool::LEN, staking::STAKEPOOLSIZE);
+    assert_eq!(StakeAccount::LEN, staking::STAKEACCOUNTSIZE);
+    assert_eq!(RaffleState::LEN, raffle::RAFFLESIZE);
+    assert_eq!(FractionState::LEN, fraction::FRACTIONSIZE);
+    assert_eq!(RentalState::LEN, rental::RENTALSIZE);
+    assert_eq!(FeeDiscount::LEN, state::FEEDISCOUNTSIZE);
+    assert_eq!(OtcState::LEN, otc::OTCSIZE);
+    assert_eq!(CollectionStats::LEN, state::COLLECTIONSTATSSIZE);
+    assert_eq!(EscrowIndex::LEN, index::INDEXSIZE);
+    assert_eq!(CoreListing::LEN, core_listing::CORELISTINGSIZE);
+    assert_eq!(PaymentMint::LEN, payment::PAYMENTMINTSIZE);
+    assert_eq!(TokenListing::LEN, payment::TOKENLISTINGSIZE);
+    assert_eq!(TokenBid::LEN, payment::TOKENBIDSIZE);
+    assert_eq!(Session::LEN, session::SESSIONSIZE);
+    assert_eq!(TraitOffer::LEN, trait_offer::TRAITOFFERSIZE);
+    assert_eq!(RewardsConfig::LEN, rewards::REWARDSCONFIGSIZE);
+    assert_eq!(Leaderboard::LEN, leaderboard::LEADERBOARDSIZE);
+    assert_eq!(SwapState::LEN, swap::SWAPSIZE);
+    assert_eq!(AttestationConfig::LEN, attestation::ATTESTATIONCONFIGSIZE);
+}
+
+#[test]
+fn test_reserved_words_end_escrow_accounts() {
+    let reserved = [u64::MAX; state::RESERVED_WORDS - 5];
+    let mut packed = vec![0u8; ListEscrowState::LEN];
+    ListEscrowState { reserved, ..ListEscrowState::default() }.pack_into_slice(&mut packed);
+    assert!(packed[ListEscrowState::LEN - reserved.len() * 8..].iter().all(|byte| *byte == 0xff));
+
+    let reserved = [u64::MAX; state::RESERVED_WORDS - 1];
+    let mut packed = vec![0u8; BidEscrowState::LEN];
+    BidEscrowState { reserved, ..BidEscrowState::default() }.pack_into_slice(&mut packed);
+    assert!(packed[BidEscrowState::LEN - reserved.len() * 8..].iter().all(|byte| *byte == 0xff));
+}
+
+#[test]
+fn test_discriminators_are_unique() {
+    let discriminators = [
+        PlatformState::DISCRIMINATOR,
+        ListEscrowState::DISCRIMINATOR,
+        BidEscrowState::DISCRIMINATOR,
+        CollectionState::DISCRIMINATOR,
+        UserStats::DISCRIMINATOR,
+        CollectionStats::DISCRIMINATOR,
+        OrderBook::DISCRIMINATOR,
+        FeeDiscount::DISCRIMINATOR,
+        Pool::DISCRIMINATOR,
+        StakePool::DISCRIMINATOR,
+        StakeAccount::DISCRIMINATOR,
+        RaffleState::DISCRIMINATOR,
+        FractionState::DISCRIMINATOR,
+        RentalState::DISCRIMINATOR,
+        OtcState::DISCRIMINATOR,
+        EscrowIndex::DISCRIMINATOR,
+        CoreListing::DISCRIMINATOR,
+        PaymentMint::DISCRIMINATOR,
+        TokenListing::DISCRIMINATOR,
+        TokenBid::DISCRIMINATOR,
+        Session::DISCRIMINATOR,
+        TraitOffer::DISCRIMINATOR,
+        RewardsConfig::DISCRIMINATOR,
+        Leaderboard::DISCRIMINATOR,
+        SwapState::DISCRIMINATOR,
+        AttestationConfig::DISCRIMINATOR,
+    ];
+    for (i, a) in discriminators.iter().enumerate() {
+        for b in &discriminators[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+}
+
+#[test]
+fn test_platform_state_reads_first_release_layout() {
+    let authority = Pubkey::new_unique();
+    let mut legacy = vec![1u8];
+    legacy.extend_from_slice(authority.as_ref());
+    legacy.extend_from_slice(&250u64.to_be_bytes());
+    legacy.extend_from_slice(&0u64.to_be_bytes());
+    assert_eq!(legacy.len(), 49);
+    assert_eq!(legacy.len(), PlatformState::LEGACY_LEN);
+
+    let expected = PlatformState {
+        is_initialized: true,
+        authority,
+        maker_fee_bps: 250,
+        fee_recipient: authority,
+        ..PlatformState::default()
+    };
+    assert_eq!(PlatformState::unpack(&legacy), Ok(expected));
+    assert_eq!(PlatformState::unpack(&legacy[..48]), Err(ProgramError::InvalidAccountData));
+    assert_eq!(PlatformState::pack(expected, &mut legacy), Err(ProgramError::InvalidAccountData));
+
+    let mut packed = vec![0u8; PlatformState::LEN];
+    PlatformState::pack(expected, &mut packed).unwrap();
+    assert_eq!(state::unpack_compat::<PlatformState>(&packed), Ok(expected));
+}
+
+#[test]
+fn test_escrow_states_read_first_release_layout() {
+    let lister = Pubkey::new_unique();
+    let buyer = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let mut legacy = lister.to_bytes().to_vec();
+    legacy.extend_from_slice(mint.as_ref());
+    legacy.extend_from_slice(&700u64.to_be_bytes());
+    legacy.push(1);
+    legacy.extend_from_slice(buyer.as_ref());
+    assert_eq!(legacy.len(), 105);
+
+    let expected = ListEscrowState {
+        is_initialized: true,
+        lister,
+        mint,
+        amount: 700,
+        success: true,
+        successful_buyer: buyer,
+        payer: lister,
+        ..ListEscrowState::default()
+    };
+    assert_eq!(ListEscrowState::unpack(&legacy), Ok(expected));
+    assert_eq!(ListEscrowState::pack(expected, &mut legacy), Err(ProgramError::InvalidAccountData));
+
+    let mut legacy = buyer.to_bytes().to_vec();
+    legacy.extend_from_slice(mint.as_ref());
+    legacy.extend_from_slice(&500u64.to_be_bytes());
+    assert_eq!(legacy.len(), 72);
+
+    let expected = BidEscrowState {
+        is_initialized: true,
+        bidder: buyer,
+        mint,
+        amount: 500,
+        payer: buyer,
+        ..BidEscrowState::default()
+    };
+    assert_eq!(BidEscrowState::unpack(&legacy), Ok(expected));
+    assert_eq!(BidEscrowState::pack(expected, &mut legacy), Err(ProgramError::InvalidAccountData));
+}
+
+proptest! {
+    #[test]
+    fn test_platform_state_round_trip(value in arb_platform_state()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_list_escrow_state_round_trip(value in arb_list_escrow_state()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_bid_escrow_state_round_trip(value in arb_bid_escrow_state()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_collection_state_round_trip(value in arb_collection_state()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_user_stats_round_trip(value in arb_user_stats()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_order_book_round_trip(value in arb_order_book()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_pool_round_trip(value in arb_pool()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_stake_pool_round_trip(value in arb_stake_pool()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_stake_account_round_trip(value in arb_stake_account()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_raffle_state_round_trip(value in arb_raffle_state()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_fraction_state_round_trip(value in arb_fraction_state()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_rental_state_round_trip(value in arb_rental_state()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_fee_discount_round_trip(value in arb_fee_discount()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_otc_state_round_trip(value in arb_otc_state()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_core_listing_round_trip(value in arb_core_listing()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_payment_mint_round_trip(value in arb_payment_mint()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_token_listing_round_trip(value in arb_token_listing()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_token_bid_round_trip(value in arb_token_bid()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_session_round_trip(value in arb_session()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_trait_offer_round_trip(value in arb_trait_offer()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_rewards_config_round_trip(value in arb_rewards_config()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_leaderboard_round_trip(value in arb_leaderboard()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_swap_state_round_trip(value in arb_swap_state()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_attestation_config_round_trip(value in arb_attestation_config()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_collection_stats_round_trip(value in arb_collection_stats()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_escrow_index_round_trip(value in arb_escrow_index()) {
+        round_trip(value);
+    }
+
+    #[test]
+    fn test_pool_rejects_bad_curve(value in arb_pool(), curve in 2u8..) {
+        let mut packed = vec![0u8; Pool::LEN];
+        value.pack_into_slice(&mut packed);
+        packed[POOL_CURVE_OFFSET] = curve;
+        prop_assert_eq!(Pool::unpack_from_slice(&packed), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn test_order_book_rejects_bad_count(value in arb_order_book(), count in (state::ORDER_BOOK_DEPTH as u8 + 1)..) {
+        let mut packed = vec![0u8; OrderBook::LEN];
+        value.pack_into_slice(&mut packed);
+        packed[ORDER_BOOK_ASK_COUNT_OFFSET] = count;
+        prop_assert_eq!(OrderBook::unpack_from_slice(&packed), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn test_escrow_index_rejects_bad_count(value in arb_escrow_index(), count in (index::INDEX_CAPACITY as u8 + 1)..) {
+        let mut packed = vec![0u8; EscrowIndex::LEN];
+        value.pack_into_slice(&mut packed);
+        packed[INDEX_COUNT_OFFSET] = count;
+        prop_assert_eq!(EscrowIndex::unpack_from_slice(&packed), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn test_leaderboard_rejects_bad_count(value in arb_leaderboard(), count in (leaderboard::LEADERBOARD_DEPTH as u8 + 1)..) {
+        let mut packed = vec![0u8; Leaderboard::LEN];
+        value.pack_into_slice(&mut packed);
+        packed[LEADERBOARD_COUNT_OFFSET] = count;
+        prop_assert_eq!(Leaderboard::unpack_from_slice(&packed), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn test_swap_state_rejects_bad_counts(value in arb_swap_state(), count in (swap::SWAP_MAX_ASSETS as u8 + 1)..) {
+        for offset in [SWAP_MAKER_COUNT_OFFSET, SWAP_TAKER_COUNT_OFFSET].iter() {
+            let mut packed = vec![0u8; SwapState::LEN];
+            value.pack_into_slice(&mut packed);
+            packed[*offset] = count;
+            prop_assert_eq!(SwapState::unpack_from_slice(&packed), Err(ProgramError::InvalidAccountData));
+        }
+    }
+
+    #[test]
+    fn test_platform_state_rejects_bad_is_initialized(value in arb_platform_state(), flag in 2u8..) {
+        let mut packed = vec![0u8; PlatformState::LEN];
+        value.pack_into_slice(&mut packed);
+        packed[IS_INITIALIZED_OFFSET] = flag;
+        prop_assert_eq!(PlatformState::unpack_from_slice(&packed), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn test_list_escrow_state_rejects_bad_success(value in arb_list_escrow_state(), flag in 2u8..) {
+        let mut packed = vec![0u8; ListEscrowState::LEN];
+        value.pack_into_slice(&mut packed);
+        packed[LIST_SUCCESS_OFFSET] = flag;
+        prop_assert_eq!(ListEscrowState::unpack_from_slice(&packed), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn test_list_escrow_state_rejects_bad_discriminator(value in arb_list_escrow_state(), tag in any::<[u8; 8]>()) {
+        prop_assume!(tag != ListEscrowState::DISCRIMINATOR);
+        let mut packed = vec![0u8; ListEscrowState::LEN];
+        value.pack_into_slice(&mut packed);
+        packed[..8].copy_from_slice(&tag);
+        prop_assert_eq!(ListEscrowState::unpack_from_slice(&packed), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn test_list_escrow_state_is_not_a_bid_escrow_state(value in arb_list_escrow_state()) {
+        let mut packed = vec![0u8; ListEscrowState::LEN];
+        value.pack_into_slice(&mut packed);
+        prop_assert_eq!(BidEscrowState::unpack_from_slice(&packed), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn test_unpack_rejects_short_input(len in 0usize..CollectionState::LEN) {
+        let data = vec![0u8; len];
+        prop_assert!(PlatformState::unpack_from_slice(&data).is_err());
+        prop_assert!(ListEscrowState::unpack_from_slice(&data).is_err());
+        prop_assert!(BidEscrowState::unpack_from_slice(&data).is_err());
+        prop_assert!(CollectionState::unpack_from_slice(&data).is_err());
+        prop_assert!(UserStats::unpack_from_slice(&data).is_err());
+        prop_assert!(OrderBook::unpack_from_slice(&data).is_err());
+        prop_assert!(Pool::unpack_from_slice(&data).is_err());
+        prop_assert!(StakePool::unpack_from_slice(&data).is_err());
+        prop_assert!(StakeAccount::unpack_from_slice(&data).is_err());
+        prop_assert!(RaffleState::unpack_from_slice(&data).is_err());
+        prop_assert!(FractionState::unpack_from_slice(&data).is_err());
+        prop_assert!(RentalState::unpack_from_slice(&data).is_err());
+        prop_assert!(FeeDiscount::unpack_from_slice(&data).is_err());
+        prop_assert!(OtcState::unpack_from_slice(&data).is_err());
+        prop_assert!(CollectionStats::unpack_from_slice(&data).is_err());
+        prop_assert!(EscrowIndex::unpack_from_slice(&data).is_err());
+        prop_assert!(CoreListing::unpack_from_slice(&data).is_err());
+        prop_assert!(PaymentMint::unpack_from_slice(&data).is_err());
+        prop_assert!(TokenListing::unpack_from_slice(&data).is_err());
+        prop_assert!(TokenBid::unpack_from_slice(&data).is_err());
+        prop_assert!(Session::unpack_from_slice(&data).is_err());
+        prop_assert!(TraitOffer::unpack_from_slice(&data).is_err());
+        prop_assert!(RewardsConfig::unpack_from_slice(&data).is_err());
+        prop_assert!(Leaderboard::unpack_from_slice(&data).is_err());
+        prop_assert!(SwapState::unpack_from_slice(&data).is_err());
+        prop_assert!(AttestationConfig::unpack_from_slice(&data).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{error::NFTError, instruction, swap::{self, SwapState}};
+
+const ID: u64 = 1;
+
+#[tokio::test]
+async fn test_swap_settles_both_sides_at_once() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let maker = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let taker = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (floor_a, maker_token_a) = ctx.create_nft(&maker.pubkey()).await;
+    let (floor_b, maker_token_b) = ctx.create_nft(&maker.pubkey()).await;
+    let (rare, taker_rare_token) = ctx.create_nft(&taker.pubkey()).await;
+
+    // Two floor NFTs and a SOL for the rare.
+    let ix = instruction::create_swap(
+        &program_id,
+        &maker.pubkey(),
+        &taker.pubkey(),
+        ID,
+        &[(maker_token_a, floor_a), (maker_token_b, floor_b)],
+        LAMPORTS_PER_SOL,
+        &[rare],
+        0,
+    );
+    ctx.process(&[ix], &[&maker]).await.unwrap();
+    assert_eq!(ctx.token_balance(&maker_token_a).await, 0);
+    assert_eq!(ctx.token_balance(&maker_token_b).await, 0);
+
+    let taker_token_a = ctx.create_token_account(&floor_a, &taker.pubkey()).await;
+    let taker_token_b = ctx.create_token_account(&floor_b, &taker.pubkey()).await;
+    let maker_rare_token = ctx.create_token_account(&rare, &maker.pubkey()).await;
+    let maker_assets = [(taker_token_a, floor_a), (taker_token_b, floor_b)];
+    let taker_assets = [(maker_rare_token, rare)];
+
+    let ix = instruction::execute_swap(&program_id, &maker.pubkey(), &maker.pubkey(), &taker.pubkey(), ID, &maker_assets, &taker_assets);
+    let result = ctx.process(&[ix], &[&maker]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::SwapNotFunded as u32));
+
+    let ix = instruction::deposit_swap(&program_id, &taker.pubkey(), &maker.pubkey(), ID, &[(taker_rare_token, rare)]);
+    ctx.process(&[ix], &[&taker]).await.unwrap();
+    assert_eq!(ctx.token_balance(&taker_rare_token).await, 0);
+
+    let (swap_state_pubkey, _) = swap::find_swap_state_address(&program_id, &maker.pubkey(), ID);
+    let (rare_vault_pubkey, _) = swap::find_swap_vault_address(&program_id, &swap_state_pubkey, &rare);
+    let vault_rent = ctx.balance(&rare_vault_pubkey).await;
+    let taker_balance = ctx.balance(&taker.pubkey()).await;
+
+    let ix = instruction::execute_swap(&program_id, &taker.pubkey(), &maker.pubkey(), &taker.pubkey(), ID, &maker_assets, &taker_assets);
+    ctx.process(&[ix], &[&taker]).await.unwrap();
+    assert_eq!(ctx.token_balance(&taker_token_a).await, 1);
+    assert_eq!(ctx.token_balance(&taker_token_b).await, 1);
+    assert_eq!(ctx.token_balance(&maker_rare_token).await, 1);
+    assert_eq!(ctx.balance(&taker.pubkey()).await, taker_balance + LAMPORTS_PER_SOL + vault_rent);
+    assert!(ctx.get_account(&swap_state_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_each_side_cancels_its_own_deposit() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let maker = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let taker = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, maker_token) = ctx.create_nft(&maker.pubkey()).await;
+    let (wanted, taker_token) = ctx.create_nft(&taker.pubkey()).await;
+
+    let ix = instruction::create_swap(&program_id, &maker.pubkey(), &taker.pubkey(), ID, &[(maker_token, mint)], 0, &[wanted], LAMPORTS_PER_SOL);
+    ctx.process(&[ix], &[&maker]).await.unwrap();
+    let taker_balance = ctx.balance(&taker.pubkey()).await;
+    let ix = instruction::deposit_swap(&program_id, &taker.pubkey(), &maker.pubkey(), ID, &[(taker_token, wanted)]);
+    ctx.process(&[ix], &[&taker]).await.unwrap();
+
+    // The taker backs out; the maker's side stays escrowed.
+    let ix = instruction::cancel_swap(&program_id, &taker.pubkey(), &maker.pubkey(), &taker.pubkey(), ID, &[], &[(taker_token, wanted)]);
+    ctx.process(&[ix], &[&taker]).await.unwrap();
+    assert_eq!(ctx.token_balance(&taker_token).await, 1);
+    assert_eq!(ctx.balance(&taker.pubkey()).await, taker_balance);
+    let (swap_state_pubkey, _) = swap::find_swap_state_address(&program_id, &maker.pubkey(), ID);
+    let swap_state = SwapState::unpack(&ctx.get_account(&swap_state_pubkey).await.unwrap().data).unwrap();
+    assert!(!swap_state.taker_deposited);
+    assert_eq!(ctx.token_balance(&maker_token).await, 0);
+
+    // The maker's cancel hands both sides back and closes the swap.
+    let deposit = instruction::deposit_swap(&program_id, &taker.pubkey(), &maker.pubkey(), ID, &[(taker_token, wanted)]);
+    let ix = instruction::cancel_swap(&program_id, &maker.pubkey(), &maker.pubkey(), &taker.pubkey(), ID, &[(maker_token, mint)], &[(taker_token, wanted)]);
+    ctx.process(&[deposit, ix], &[&taker, &maker]).await.unwrap();
+    assert_eq!(ctx.token_balance(&maker_token).await, 1);
+    assert_eq!(ctx.token_balance(&taker_token).await, 1);
+    assert_eq!(ctx.balance(&taker.pubkey()).await, taker_balance);
+    assert!(ctx.get_account(&swap_state_pubkey).await.is_none());
+}
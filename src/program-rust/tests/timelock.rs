@@ -0,0 +1,79 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{instruction, state::{self, ParamChange, PlatformState}};
+
+const TIMELOCK_SLOTS: u64 = 1_000_000;
+
+#[tokio::test]
+async fn test_fee_changes_wait_out_the_timelock() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+    let (state_pubkey, _) = state::find_platform_state_address(&program_id);
+
+    let ix = instruction::change_fees(&program_id, &authority, 100, 200);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let platform = PlatformState::unpack(&ctx.get_account(&state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!((platform.maker_fee_bps, platform.taker_fee_bps), (100, 200));
+
+    let stranger = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let change = ParamChange::Timelock { slots: TIMELOCK_SLOTS };
+    let ix = instruction::schedule_param_change(&program_id, &stranger.pubkey(), change, 0);
+    assert!(ctx.process(&[ix], &[&stranger]).await.is_err());
+
+    let ix = instruction::schedule_param_change(&program_id, &authority, change, 0);
+    ctx.process(&[ix, instruction::execute_param_change(&program_id)], &[]).await.unwrap();
+    let platform = PlatformState::unpack(&ctx.get_account(&state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform.timelock_slots, TIMELOCK_SLOTS);
+    assert_eq!(platform.pending_change, None);
+
+    let ix = instruction::change_fees(&program_id, &authority, 300, 300);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let platform = PlatformState::unpack(&ctx.get_account(&state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!((platform.maker_fee_bps, platform.taker_fee_bps), (100, 200));
+    assert_eq!(platform.pending_change, Some(ParamChange::Fees { maker_bps: 300, taker_bps: 300 }));
+    assert!(platform.pending_change_slot >= TIMELOCK_SLOTS);
+
+    let ix = instruction::execute_param_change(&program_id);
+    assert!(ctx.process(&[ix], &[]).await.is_err());
+
+    let change = ParamChange::Authority { authority: stranger.pubkey() };
+    let ix = instruction::schedule_param_change(&program_id, &authority, change, 1);
+    assert!(ctx.process(&[ix], &[]).await.is_err());
+    let platform = PlatformState::unpack(&ctx.get_account(&state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform.authority, authority);
+}
+
+#[tokio::test]
+async fn test_platform_setters_wait_out_the_timelock() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+    let (state_pubkey, _) = state::find_platform_state_address(&program_id);
+
+    let change = ParamChange::Timelock { slots: TIMELOCK_SLOTS };
+    let ix = instruction::schedule_param_change(&program_id, &authority, change, 0);
+    ctx.process(&[ix, instruction::execute_param_change(&program_id)], &[]).await.unwrap();
+
+    let curator = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let ix = instruction::set_curator(&program_id, &authority, &curator.pubkey(), 500);
+    ctx.process(&[ix], &[]).await.unwrap();
+    let platform = PlatformState::unpack(&ctx.get_account(&state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform.curator, Default::default());
+    assert_eq!(platform.pending_change, Some(ParamChange::Curator { curator: curator.pubkey(), curator_fee_bps: 500 }));
+
+    let ix = instruction::change_fee_recipient(&program_id, &authority, &curator.pubkey());
+    ctx.process(&[ix], &[]).await.unwrap();
+    let platform = PlatformState::unpack(&ctx.get_account(&state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform.fee_recipient, authority);
+    assert_eq!(platform.pending_change, Some(ParamChange::FeeRecipient { fee_recipient: curator.pubkey() }));
+
+    let change = ParamChange::MarketplaceFee { marketplace_fee_bps: state::MAX_BPS };
+    let ix = instruction::schedule_param_change(&program_id, &authority, change, u64::MAX);
+    assert!(ctx.process(&[ix], &[]).await.is_err());
+}
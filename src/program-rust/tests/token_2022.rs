@@ -0,0 +1,62 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, rent::Rent};
+use solana_sdk::{account::Account, signature::Signer};
+
+use nft_trading::{error::NFTError, instruction, token_2022::{self, spl_token_2022_program}};
+
+/// A Token-2022 mint's data with one token, no decimals and the given
+/// extensions, each a type and the length of its zeroed value.
+fn mint_data(extensions: &[(u16, u16)]) -> Vec<u8> {
+    let mut data = vec![0u8; 166];
+    data[36..44].copy_from_slice(&1u64.to_le_bytes());
+    data[45] = 1;
+    data[165] = 1;
+    for (extension_type, length) in extensions {
+        data.extend_from_slice(&extension_type.to_le_bytes());
+        data.extend_from_slice(&length.to_le_bytes());
+        data.resize(data.len() + *length as usize, 0);
+    }
+    data
+}
+
+#[test]
+fn test_finds_non_transferable_extension() {
+    // Metadata pointer, then non-transferable.
+    assert!(token_2022::is_non_transferable(&mint_data(&[(18, 64), (9, 0)])));
+    assert!(!token_2022::is_non_transferable(&mint_data(&[(18, 64)])));
+    assert!(!token_2022::is_non_transferable(&mint_data(&[])));
+    assert!(!token_2022::is_non_transferable(&mint_data(&[(9, 0)])[..165]));
+
+    // A token account with the non-transferable account extension.
+    let mut data = mint_data(&[(13, 0)]);
+    data[165] = 2;
+    assert!(!token_2022::is_non_transferable(&data));
+
+    // A length running past the end of the data.
+    let mut data = mint_data(&[(18, 64), (9, 0)]);
+    data[168] = 0xff;
+    assert!(!token_2022::is_non_transferable(&data));
+}
+
+#[tokio::test]
+async fn test_list_rejects_soulbound_mints() {
+    let program_id = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let data = mint_data(&[(9, 0)]);
+    let mut program_test = common::program_test(program_id);
+    program_test.add_account(mint, Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: spl_token_2022_program::id(),
+        ..Account::default()
+    });
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let lister_token = Pubkey::new_unique();
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, LAMPORTS_PER_SOL, false, None, None, 0, 0);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::NonTransferableMint as u32));
+}
@@ -0,0 +1,1060 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::{account::Account, signature::{Keypair, Signer}};
+
+use nft_trading::{
+    error::NFTError,
+    instruction,
+    memo,
+    merkle,
+    metadata::Creator,
+    state::{self, BidEscrowState, ListEscrowState, PlatformState, UserStats},
+};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_list_bid_accept_withdraw() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let program_id = ctx.program_id;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&lister_token).await, 0);
+
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let (list_vault_pubkey, _) = state::find_list_vault_address(&program_id, &mint, &lister.pubkey());
+    assert_eq!(ctx.token_balance(&list_vault_pubkey).await, 1);
+    let list_state = ListEscrowState::unpack_unchecked(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.lister, lister.pubkey());
+    assert_eq!(list_state.amount, PRICE);
+    assert!(!list_state.success);
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    let bid_state = BidEscrowState::unpack_unchecked(&ctx.get_account(&bid_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(bid_state.bidder, bidder.pubkey());
+    assert_eq!(bid_state.amount, PRICE);
+
+    let authority = ctx.payer.pubkey();
+    let lister_balance = ctx.balance(&lister.pubkey()).await;
+    let bidder_balance = ctx.balance(&bidder.pubkey()).await;
+    let authority_balance = ctx.balance(&authority).await;
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let maker_fee = PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    let taker_fee = PRICE * common::TAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.balance(&lister.pubkey()).await, lister_balance + PRICE - maker_fee);
+    assert!(ctx.balance(&bidder.pubkey()).await > bidder_balance);
+    assert!(ctx.balance(&authority).await > authority_balance);
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_none());
+
+    let (platform_state_pubkey, _) = state::find_platform_state_address(&program_id);
+    let platform_state = PlatformState::unpack(&ctx.get_account(&platform_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform_state.sale_count, 1);
+    assert_eq!(platform_state.total_volume, PRICE);
+    assert_eq!(platform_state.total_fees, maker_fee + taker_fee);
+    assert_eq!(platform_state.active_listings, 0);
+
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+    let ix = instruction::withdraw_nft_on_success(&program_id, &bidder.pubkey(), &bidder_token, &mint, &lister.pubkey());
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+    assert!(ctx.get_account(&list_state_pubkey).await.is_none());
+    assert!(ctx.get_account(&list_vault_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_accepted_bid_cannot_be_withdrawn() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+
+    // Accepting wipes the bid, so withdrawing it in the same transaction
+    // fails and the whole transaction rolls back.
+    let accept = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    let withdraw = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    assert!(ctx.process(&[accept.clone(), withdraw.clone()], &[&lister, &bidder]).await.is_err());
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_some());
+
+    let bidder_balance = ctx.balance(&bidder.pubkey()).await;
+    ctx.process(&[accept], &[&lister]).await.unwrap();
+    assert!(ctx.process(&[withdraw], &[&bidder]).await.is_err());
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, bidder_balance);
+}
+
+#[tokio::test]
+async fn test_sold_listing_cannot_be_delisted() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let ix = instruction::delist(&program_id, &lister.pubkey(), &lister_token, &mint);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::SettlementPending as u32));
+    let (list_vault_pubkey, _) = state::find_list_vault_address(&program_id, &mint, &lister.pubkey());
+    assert_eq!(ctx.token_balance(&list_vault_pubkey).await, 1);
+    assert_eq!(ctx.token_balance(&lister_token).await, 0);
+}
+
+#[tokio::test]
+async fn test_sold_listing_cannot_be_sold_again() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let second_bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let ix = instruction::bid(&program_id, &second_bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&second_bidder]).await.unwrap();
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let second_bidder_token = ctx.create_token_account(&mint, &second_bidder.pubkey()).await;
+    let ix = instruction::execute_sale(&program_id, &lister.pubkey(), &mint, &second_bidder.pubkey(), &authority, None, &[], &second_bidder_token);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::SettlementPending as u32));
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &second_bidder.pubkey());
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_some());
+    assert_eq!(ctx.token_balance(&second_bidder_token).await, 0);
+}
+
+#[tokio::test]
+async fn test_list_rejects_fungible_mints() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    ctx.mint_to(&mint, &lister_token, 2).await;
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::NotAnNFT as u32));
+
+    let mint = ctx.create_mint(Keypair::new(), 6).await;
+    let lister_token = ctx.create_token_account(&mint, &lister.pubkey()).await;
+    ctx.mint_to(&mint, &lister_token, 1).await;
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::NotAnNFT as u32));
+}
+
+#[tokio::test]
+async fn test_list_checks_seller_token_account() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let payer = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, _) = ctx.create_nft(&payer).await;
+    let empty_token = ctx.create_token_account(&mint, &lister.pubkey()).await;
+    let ix = instruction::list(&program_id, &lister.pubkey(), &empty_token, &mint, PRICE, false, None, None, 0, 0);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InsufficientTokenBalance as u32));
+
+    let mint = Keypair::new();
+    let ixs = [
+        solana_program::system_instruction::create_account(
+            &payer,
+            &mint.pubkey(),
+            solana_program::rent::Rent::default().minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(&spl_token::id(), &mint.pubkey(), &payer, Some(&payer), 0).unwrap(),
+    ];
+    ctx.process(&ixs, &[&mint]).await.unwrap();
+    let mint = mint.pubkey();
+    let lister_token = ctx.create_token_account(&mint, &lister.pubkey()).await;
+    ctx.mint_to(&mint, &lister_token, 1).await;
+    let ix = spl_token::instruction::freeze_account(&spl_token::id(), &lister_token, &mint, &payer, &[]).unwrap();
+    ctx.process(&[ix], &[]).await.unwrap();
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::TokenAccountFrozen as u32));
+}
+
+#[tokio::test]
+async fn test_list_rejects_spoofed_rent_sysvar() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let mut ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    for meta in ix.accounts.iter_mut().filter(|meta| meta.pubkey.eq(&solana_program::sysvar::rent::id())) {
+        meta.pubkey = solana_program::sysvar::clock::id();
+    }
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert!(result.is_err());
+    assert!(ctx.get_account(&state::find_list_state_address(&program_id, &mint, &lister.pubkey()).0).await.is_none());
+}
+
+#[tokio::test]
+async fn test_withdraw_nft_requires_successful_buyer() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let stranger = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let program_id = ctx.program_id;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let stranger_token = ctx.create_token_account(&mint, &stranger.pubkey()).await;
+    let ix = instruction::withdraw_nft_on_success(&program_id, &stranger.pubkey(), &stranger_token, &mint, &lister.pubkey());
+    assert!(ctx.process(&[ix], &[&stranger]).await.is_err());
+    assert_eq!(ctx.token_balance(&stranger_token).await, 0);
+}
+
+#[tokio::test]
+async fn test_accept_bid_pays_referrer() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let ix = instruction::change_referral_fee(&program_id, &authority, 5_000);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let referrer = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, Some(&referrer.pubkey()));
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+
+    let referrer_balance = ctx.balance(&referrer.pubkey()).await;
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, Some(&referrer.pubkey()), &[], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let fee = PRICE * (common::MAKER_FEE_BPS + common::TAKER_FEE_BPS) / state::MAX_BPS;
+    assert_eq!(ctx.balance(&referrer.pubkey()).await, referrer_balance + fee / 2);
+}
+
+#[tokio::test]
+async fn test_accept_wrapped_bid() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, true, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    let bid_state = BidEscrowState::unpack_unchecked(&ctx.get_account(&bid_state_pubkey).await.unwrap().data).unwrap();
+    assert!(bid_state.wrapped);
+
+    let lister_balance = ctx.balance(&lister.pubkey()).await;
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let maker_fee = PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.balance(&lister.pubkey()).await, lister_balance + PRICE - maker_fee);
+    let (bid_vault_pubkey, _) = state::find_bid_vault_address(&program_id, &mint, &bidder.pubkey());
+    assert!(ctx.get_account(&bid_vault_pubkey).await.is_none());
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_accept_bid_updates_user_stats() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::create_user_stats(&program_id, &bidder.pubkey());
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let (bidder_stats_pubkey, _) = state::find_user_stats_address(&program_id, &bidder.pubkey());
+    let (lister_stats_pubkey, _) = state::find_user_stats_address(&program_id, &lister.pubkey());
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let bidder_stats = UserStats::unpack(&ctx.get_account(&bidder_stats_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(bidder_stats.owner, bidder.pubkey());
+    assert_eq!(bidder_stats.total_volume, PRICE);
+    assert_eq!(bidder_stats.purchases, 1);
+    assert_eq!(bidder_stats.sales, 0);
+    assert!(ctx.get_account(&lister_stats_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_accept_bid_pays_verified_creators() {
+    let program_id = Pubkey::new_unique();
+    let mint = Keypair::new();
+    let verified_a = Pubkey::new_unique();
+    let verified_b = Pubkey::new_unique();
+    let unverified = Pubkey::new_unique();
+
+    let creators = vec![
+        Creator { address: verified_a.to_bytes(), verified: true, share: 60 },
+        Creator { address: unverified.to_bytes(), verified: false, share: 10 },
+        Creator { address: verified_b.to_bytes(), verified: true, share: 30 },
+    ];
+    let mut program_test = common::program_test(program_id);
+    let (metadata_pubkey, metadata_account) = common::metadata_account(&mint.pubkey(), 1_000, Some(creators), None);
+    program_test.add_account(metadata_pubkey, metadata_account);
+    for creator in &[verified_a, verified_b] {
+        program_test.add_account(*creator, Account {
+            lamports: LAMPORTS_PER_SOL,
+            ..Account::default()
+        });
+    }
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft_with_mint(mint, &lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[verified_b, verified_a], None);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+
+    let lister_balance = ctx.balance(&lister.pubkey()).await;
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[verified_a, verified_b], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let royalty = PRICE / 10;
+    let maker_fee = PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.balance(&verified_a).await, LAMPORTS_PER_SOL + royalty * 60 / 100);
+    assert_eq!(ctx.balance(&verified_b).await, LAMPORTS_PER_SOL + royalty * 30 / 100);
+    assert_eq!(ctx.balance(&unverified).await, 0);
+    assert_eq!(ctx.balance(&lister.pubkey()).await, lister_balance + PRICE - maker_fee - royalty * 90 / 100);
+}
+
+#[tokio::test]
+async fn test_royalty_override_is_capped_and_paid() {
+    let program_id = Pubkey::new_unique();
+    let mint = Keypair::new();
+    let creator = Pubkey::new_unique();
+
+    let creators = vec![Creator { address: creator.to_bytes(), verified: true, share: 100 }];
+    let mut program_test = common::program_test(program_id);
+    let (metadata_pubkey, metadata_account) = common::metadata_account(&mint.pubkey(), 500, Some(creators), None);
+    program_test.add_account(metadata_pubkey, metadata_account);
+    program_test.add_account(creator, Account {
+        lamports: LAMPORTS_PER_SOL,
+        ..Account::default()
+    });
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+    let authority = ctx.payer.pubkey();
+
+    let ix = instruction::set_max_royalty(&program_id, &authority, 1_500);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft_with_mint(mint, &lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 2_000);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let list_state = ListEscrowState::unpack_unchecked(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.royalty_bps, 1_500);
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[creator], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    assert_eq!(ctx.balance(&creator).await, LAMPORTS_PER_SOL + PRICE * 1_500 / state::MAX_BPS);
+}
+
+#[tokio::test]
+async fn test_charity_split_is_capped_and_paid() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let ix = instruction::set_max_charity(&program_id, &authority, 1_000);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let charity = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let list = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let ix = instruction::with_charity(list.clone(), &charity.pubkey(), 1_500);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::CharityShareTooHigh as u32));
+    let ix = instruction::with_charity(list, &charity.pubkey(), 1_000);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let accept = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    assert!(ctx.process(&[accept.clone()], &[&lister]).await.is_err());
+
+    let charity_balance = ctx.balance(&charity.pubkey()).await;
+    let ix = instruction::with_charity_payout(accept, &charity.pubkey());
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let proceeds = PRICE - PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.balance(&charity.pubkey()).await, charity_balance + proceeds * 1_000 / state::MAX_BPS);
+}
+
+#[tokio::test]
+async fn test_private_listing_only_sells_to_allowed_buyer() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let buyer = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let stranger = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, Some(&buyer.pubkey()), 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let list_state = ListEscrowState::unpack_unchecked(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.allowed_buyer, buyer.pubkey());
+
+    let ix = instruction::bid(&program_id, &stranger.pubkey(), &mint, 2 * PRICE, false, None);
+    ctx.process(&[ix], &[&stranger]).await.unwrap();
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &stranger.pubkey(), &authority, None, &[], None);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+
+    let ix = instruction::bid(&program_id, &buyer.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&buyer]).await.unwrap();
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &buyer.pubkey(), &authority, None, &[], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let list_state = ListEscrowState::unpack_unchecked(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.successful_buyer, buyer.pubkey());
+}
+
+#[tokio::test]
+async fn test_allowlisted_listing_sells_with_proof() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let buyer = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let stranger = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let buyer_token = ctx.create_token_account(&mint, &buyer.pubkey()).await;
+    let allowlist = vec![Pubkey::new_unique(), buyer.pubkey(), Pubkey::new_unique()];
+    let root = merkle::root(&allowlist);
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let ix = instruction::with_allowlist(ix, root);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let list_state = ListEscrowState::unpack(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.allowlist_root, root);
+
+    // A wallet off the list can't borrow the buyer's proof, so its bid is
+    // only escrowed, and the lister can't accept it either.
+    let execute_sale = |bidder: &Pubkey, token: &Pubkey| instruction::execute_sale(&program_id, &lister.pubkey(), &mint, bidder, &authority, None, &[], token);
+    let stranger_token = ctx.create_token_account(&mint, &stranger.pubkey()).await;
+    let ix = instruction::bid(&program_id, &stranger.pubkey(), &mint, PRICE, false, None);
+    let ix = instruction::with_allowlist_proof(instruction::with_auto_match(ix, execute_sale(&stranger.pubkey(), &stranger_token)), merkle::proof(&allowlist, 1));
+    ctx.process(&[ix], &[&stranger]).await.unwrap();
+    assert_eq!(ctx.token_balance(&stranger_token).await, 0);
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &stranger.pubkey(), &authority, None, &[], None);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::BuyerNotAllowed as u32));
+
+    let ix = instruction::bid(&program_id, &buyer.pubkey(), &mint, PRICE, false, None);
+    let ix = instruction::with_allowlist_proof(instruction::with_auto_match(ix, execute_sale(&buyer.pubkey(), &buyer_token)), merkle::proof(&allowlist, 1));
+    ctx.process(&[ix], &[&buyer]).await.unwrap();
+    assert_eq!(ctx.token_balance(&buyer_token).await, 1);
+    assert!(ctx.get_account(&list_state_pubkey).await.is_none());
+}
+
+#[tokio::test]
+async fn test_scheduled_listing_cannot_sell_before_start() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let buyer = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, i64::MAX, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let list_state = ListEscrowState::unpack_unchecked(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.starts_at, i64::MAX);
+
+    let ix = instruction::bid(&program_id, &buyer.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&buyer]).await.unwrap();
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &buyer.pubkey(), &authority, None, &[], None);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+    assert_eq!(ctx.token_balance(&lister_token).await, 0);
+}
+
+#[tokio::test]
+async fn test_accept_bid_fails_if_bid_was_lowered() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, 2 * PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    // The lister signs for the bid it saw; the bidder swaps in a lower one.
+    let accept = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], Some(&bidder_token));
+    let ixs = [
+        instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint),
+        instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE / 2, false, None),
+    ];
+    ctx.process(&ixs, &[&bidder]).await.unwrap();
+    let result = ctx.process(&[instruction::with_expected_amount(accept.clone(), PRICE)], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::PriceMismatch as u32));
+    assert_eq!(ctx.token_balance(&bidder_token).await, 0);
+
+    // Signing for the lower bid it now sees goes through.
+    ctx.process(&[instruction::with_expected_amount(accept, PRICE / 2)], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+}
+
+#[tokio::test]
+async fn test_accept_bid_delivers_nft_in_one_step() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let stranger_token = ctx.create_token_account(&mint, &lister.pubkey()).await;
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], Some(&stranger_token));
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+    let lister_balance = ctx.balance(&lister.pubkey()).await;
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], Some(&bidder_token));
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let (list_vault_pubkey, _) = state::find_list_vault_address(&program_id, &mint, &lister.pubkey());
+    assert!(ctx.get_account(&list_state_pubkey).await.is_none());
+    assert!(ctx.get_account(&list_vault_pubkey).await.is_none());
+    let maker_fee = PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    assert!(ctx.balance(&lister.pubkey()).await > lister_balance + PRICE - maker_fee);
+}
+
+#[tokio::test]
+async fn test_execute_sale_settles_every_escrow() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let mut ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    ix.data = instruction::execute_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &Pubkey::default()).data;
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+    let ix = instruction::execute_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+
+    let escrows = [
+        state::find_list_state_address(&program_id, &mint, &lister.pubkey()).0,
+        state::find_list_vault_address(&program_id, &mint, &lister.pubkey()).0,
+        state::find_bid_state_address(&program_id, &mint, &bidder.pubkey()).0,
+        state::find_bid_vault_address(&program_id, &mint, &bidder.pubkey()).0,
+    ];
+    for escrow in &escrows {
+        assert!(ctx.get_account(escrow).await.is_none());
+    }
+}
+
+#[tokio::test]
+async fn test_duplicate_listing_and_bid_are_reported() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, 2 * PRICE, false, None, None, 0, 0);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::ListingAlreadyExists as u32));
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, 2 * PRICE, false, None);
+    let result = ctx.process(&[ix], &[&bidder]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::BidAlreadyExists as u32));
+}
+
+#[tokio::test]
+async fn test_withdraw_nft_creates_buyer_associated_token_account() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let bidder_ata = spl_associated_token_account::get_associated_token_address(&bidder.pubkey(), &mint);
+    assert!(ctx.get_account(&bidder_ata).await.is_none());
+    let ix = instruction::withdraw_nft_on_success(&program_id, &bidder.pubkey(), &bidder_ata, &mint, &lister.pubkey());
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    assert_eq!(ctx.token_balance(&bidder_ata).await, 1);
+}
+
+#[tokio::test]
+async fn test_fees_go_to_fee_recipient() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let treasury = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::change_fee_recipient(&program_id, &lister.pubkey(), &lister.pubkey());
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+    let ix = instruction::change_fee_recipient(&program_id, &authority, &treasury.pubkey());
+    ctx.process(&[ix], &[]).await.unwrap();
+    let (platform_state_pubkey, _) = state::find_platform_state_address(&program_id);
+    let platform_state = PlatformState::unpack(&ctx.get_account(&platform_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(platform_state.fee_recipient, treasury.pubkey());
+    assert_eq!(platform_state.authority, authority);
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+    let treasury_balance = ctx.balance(&treasury.pubkey()).await;
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &treasury.pubkey(), None, &[], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let maker_fee = PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    let taker_fee = PRICE * common::TAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.balance(&treasury.pubkey()).await, treasury_balance + maker_fee + taker_fee);
+}
+
+#[tokio::test]
+async fn test_minimums_reject_dust_listings_and_bids() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::set_minimums(&program_id, &lister.pubkey(), PRICE, PRICE);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+    let ix = instruction::set_minimums(&program_id, &authority, PRICE, PRICE / 2);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, 1, false, None, None, 0, 0);
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, 1, false, None);
+    assert!(ctx.process(&[ix], &[&bidder]).await.is_err());
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE / 2, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_bid_meeting_ask_settles_immediately() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+    let lister_balance = ctx.balance(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let execute_sale = || instruction::execute_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+
+    // Below the ask the bid is escrowed as usual.
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE / 2, false, None);
+    let ix = instruction::with_auto_match(ix, execute_sale());
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_some());
+    assert_eq!(ctx.token_balance(&bidder_token).await, 0);
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let list_state = ListEscrowState::unpack(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.bid_count, 1);
+    let ix = instruction::withdraw_bid(&program_id, &bidder.pubkey(), &mint);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    let ix = instruction::with_auto_match(ix, execute_sale());
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let maker_fee = PRICE * common::MAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+    assert_eq!(ctx.balance(&lister.pubkey()).await, lister_balance + PRICE - maker_fee);
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_none());
+    assert!(ctx.get_account(&state::find_list_state_address(&program_id, &mint, &lister.pubkey()).0).await.is_none());
+}
+
+#[tokio::test]
+async fn test_bid_cannot_settle_someone_elses_bid() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let stranger = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE / 2, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    // The stranger's bid meets the ask, but the match accounts name the
+    // bidder's lower bid.
+    let sale = instruction::execute_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    let ix = instruction::bid(&program_id, &stranger.pubkey(), &mint, PRICE, false, None);
+    let ix = instruction::with_auto_match(ix, sale.clone());
+    assert!(ctx.process(&[ix], &[&stranger]).await.is_err());
+
+    // Nor can anyone execute the lower bid without the lister.
+    let mut ix = sale;
+    ix.accounts[0].is_signer = false;
+    assert!(ctx.process(&[ix], &[]).await.is_err());
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    assert!(ctx.get_account(&bid_state_pubkey).await.is_some());
+    assert_eq!(ctx.token_balance(&bidder_token).await, 0);
+}
+
+#[tokio::test]
+async fn test_matched_bid_tips_first_verified_creator() {
+    let program_id = Pubkey::new_unique();
+    let mint = Keypair::new();
+    let unverified = Pubkey::new_unique();
+    let creator = Pubkey::new_unique();
+
+    let creators = vec![
+        Creator { address: unverified.to_bytes(), verified: false, share: 50 },
+        Creator { address: creator.to_bytes(), verified: true, share: 50 },
+    ];
+    let mut program_test = common::program_test(program_id);
+    let (metadata_pubkey, metadata_account) = common::metadata_account(&mint.pubkey(), 0, Some(creators), None);
+    program_test.add_account(metadata_pubkey, metadata_account);
+    program_test.add_account(creator, Account {
+        lamports: LAMPORTS_PER_SOL,
+        ..Account::default()
+    });
+    let mut ctx = common::start_with(program_test, program_id).await;
+    ctx.initialize_platform().await;
+    let authority = ctx.payer.pubkey();
+    let tip = LAMPORTS_PER_SOL / 4;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft_with_mint(mint, &lister.pubkey()).await;
+    let (plain_mint, plain_token) = ctx.create_nft(&lister.pubkey()).await;
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+    let plain_bidder_token = ctx.create_token_account(&plain_mint, &bidder.pubkey()).await;
+
+    // Without a verified creator there is nobody to tip.
+    let ix = instruction::list(&program_id, &lister.pubkey(), &plain_token, &plain_mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let execute_sale = instruction::execute_sale(&program_id, &lister.pubkey(), &plain_mint, &bidder.pubkey(), &authority, None, &[], &plain_bidder_token);
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &plain_mint, PRICE, false, None);
+    let ix = instruction::with_creator_tip(instruction::with_auto_match(ix, execute_sale), tip);
+    let result = ctx.process(&[ix], &[&bidder]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::NoCreatorToTip as u32));
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let execute_sale = instruction::execute_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[creator], &bidder_token);
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    let ix = instruction::with_creator_tip(instruction::with_auto_match(ix, execute_sale), tip);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+    assert_eq!(ctx.balance(&creator).await, LAMPORTS_PER_SOL + tip);
+    assert_eq!(ctx.balance(&unverified).await, 0);
+}
+
+#[tokio::test]
+async fn test_anyone_can_execute_a_bid_that_meets_the_ask() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let low_bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let low_bidder_token = ctx.create_token_account(&mint, &low_bidder.pubkey()).await;
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &low_bidder.pubkey(), &mint, PRICE / 2, false, None);
+    ctx.process(&[ix], &[&low_bidder]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    // Below the ask only the lister can accept.
+    let ix = instruction::execute_matched_sale(&program_id, &lister.pubkey(), &mint, &low_bidder.pubkey(), &authority, None, &[], &low_bidder_token);
+    assert!(ctx.process(&[ix], &[]).await.is_err());
+
+    let ix = instruction::execute_matched_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    ctx.process(&[ix], &[]).await.unwrap();
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+    assert!(ctx.get_account(&state::find_list_state_address(&program_id, &mint, &lister.pubkey()).0).await.is_none());
+}
+
+#[tokio::test]
+async fn test_execute_sale_records_client_memo() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let bidder_token = ctx.create_token_account(&mint, &bidder.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let execute_sale = || instruction::execute_sale(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], &bidder_token);
+    let ix = instruction::with_memo(execute_sale(), &"x".repeat(memo::MAX_MEMO_LEN + 1));
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::MemoTooLong as u32));
+
+    let ix = instruction::with_memo(execute_sale(), "invoice 42");
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+}
+
+#[tokio::test]
+async fn test_mint_and_list_lists_a_fresh_one_of_one() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+
+    let seller = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let mint = Keypair::new();
+    let program_id = ctx.program_id;
+
+    let ix = instruction::mint_and_list(&program_id, &seller.pubkey(), &mint.pubkey(), PRICE, None);
+    ctx.process(&[ix], &[&seller, &mint]).await.unwrap();
+
+    let mint_state = spl_token::state::Mint::unpack(&ctx.get_account(&mint.pubkey()).await.unwrap().data).unwrap();
+    assert_eq!(mint_state.supply, 1);
+    assert!(mint_state.mint_authority.is_none());
+
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint.pubkey(), &seller.pubkey());
+    let list_state = ListEscrowState::unpack(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.lister, seller.pubkey());
+    assert_eq!(list_state.amount, PRICE);
+    let (list_vault_pubkey, _) = state::find_list_vault_address(&program_id, &mint.pubkey(), &seller.pubkey());
+    assert_eq!(ctx.token_balance(&list_vault_pubkey).await, 1);
+}
+
+#[tokio::test]
+async fn test_curated_listing_sells_once_approved_and_pays_curator() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let curator = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::set_curator(&program_id, &authority, &curator.pubkey(), 2_000);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+
+    let accept = instruction::with_curator(
+        instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None),
+        &curator.pubkey(),
+    );
+    // A distinct memo keeps the retry below from repeating this transaction.
+    let result = ctx.process(&[instruction::with_memo(accept.clone(), "early")], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::ListingPendingApproval as u32));
+
+    let ix = instruction::approve_listing(&program_id, &lister.pubkey(), &mint, &lister.pubkey());
+    assert!(ctx.process(&[ix], &[&lister]).await.is_err());
+    let ix = instruction::approve_listing(&program_id, &curator.pubkey(), &mint, &lister.pubkey());
+    ctx.process(&[ix], &[&curator]).await.unwrap();
+
+    let curator_balance = ctx.balance(&curator.pubkey()).await;
+    ctx.process(&[accept], &[&lister]).await.unwrap();
+    let platform_fee = PRICE * (common::MAKER_FEE_BPS + common::TAKER_FEE_BPS) / state::MAX_BPS;
+    assert_eq!(ctx.balance(&curator.pubkey()).await, curator_balance + platform_fee * 2_000 / state::MAX_BPS);
+}
+
+#[tokio::test]
+async fn test_marketplaces_are_credited_and_paid() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let ix = instruction::set_marketplace_fee(&program_id, &authority, state::MAX_BPS / 2 + 1);
+    assert!(ctx.process(&[ix], &[]).await.is_err());
+    let ix = instruction::set_marketplace_fee(&program_id, &authority, 1_000);
+    ctx.process(&[ix], &[]).await.unwrap();
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let listing_marketplace = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let bidding_marketplace = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+
+    let ix = instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let ix = instruction::with_marketplace(ix, &listing_marketplace.pubkey());
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    let ix = instruction::with_marketplace(ix, &bidding_marketplace.pubkey());
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let list_state = ListEscrowState::unpack(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.marketplace, listing_marketplace.pubkey());
+    let (bid_state_pubkey, _) = state::find_bid_state_address(&program_id, &mint, &bidder.pubkey());
+    let bid_state = BidEscrowState::unpack(&ctx.get_account(&bid_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(bid_state.marketplace, bidding_marketplace.pubkey());
+
+    let listing_balance = ctx.balance(&listing_marketplace.pubkey()).await;
+    let bidding_balance = ctx.balance(&bidding_marketplace.pubkey()).await;
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    let ix = instruction::with_marketplace_payout(ix, &listing_marketplace.pubkey());
+    let ix = instruction::with_marketplace_payout(ix, &bidding_marketplace.pubkey());
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let platform_fee = PRICE * (common::MAKER_FEE_BPS + common::TAKER_FEE_BPS) / state::MAX_BPS;
+    let marketplace_fee = platform_fee * 1_000 / state::MAX_BPS;
+    assert_eq!(ctx.balance(&listing_marketplace.pubkey()).await, listing_balance + marketplace_fee);
+    assert_eq!(ctx.balance(&bidding_marketplace.pubkey()).await, bidding_balance + marketplace_fee);
+}
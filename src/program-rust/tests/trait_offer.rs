@@ -0,0 +1,81 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack, pubkey::Pubkey, rent::Rent};
+use solana_sdk::signature::{Keypair, Signer};
+
+use nft_trading::{error::NFTError, instruction, merkle, state, trait_offer::{self, TraitOffer}};
+
+const AMOUNT: u64 = 2 * LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_trait_offer_fills_with_proof() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+    let authority = ctx.payer.pubkey();
+
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let seller = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, seller_token) = ctx.create_nft(&seller.pubkey()).await;
+    let (other_mint, other_token) = ctx.create_nft(&seller.pubkey()).await;
+    let mints = vec![Pubkey::new_unique(), mint, Pubkey::new_unique()];
+    let root = merkle::root(&mints);
+
+    let bidder_balance = ctx.balance(&bidder.pubkey()).await;
+    let ix = instruction::make_trait_offer(&program_id, &bidder.pubkey(), AMOUNT, root, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let (offer_pubkey, _) = trait_offer::find_trait_offer_address(&program_id, &bidder.pubkey(), &root);
+    let offer = TraitOffer::unpack(&ctx.get_account(&offer_pubkey).await.unwrap().data).unwrap();
+    let taker_fee = AMOUNT * common::TAKER_FEE_BPS / state::MAX_BPS;
+    assert_eq!(offer.attestor, Pubkey::default());
+    assert_eq!(offer.taker_fee, taker_fee);
+    let offer_rent = ctx.balance(&offer_pubkey).await - AMOUNT - taker_fee;
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, bidder_balance - AMOUNT - taker_fee - offer_rent);
+
+    // A mint outside the tree can't borrow another mint's proof.
+    let ix = instruction::accept_trait_offer(&program_id, &seller.pubkey(), &other_token, &other_mint, &bidder.pubkey(), &root, merkle::proof(&mints, 1), &authority);
+    let result = ctx.process(&[ix], &[&seller]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidMerkleProof as u32));
+
+    let seller_balance = ctx.balance(&seller.pubkey()).await;
+    let ix = instruction::accept_trait_offer(&program_id, &seller.pubkey(), &seller_token, &mint, &bidder.pubkey(), &root, merkle::proof(&mints, 1), &authority);
+    ctx.process(&[ix], &[&seller]).await.unwrap();
+
+    let bidder_token = spl_associated_token_account::get_associated_token_address(&bidder.pubkey(), &mint);
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+    assert_eq!(ctx.token_balance(&seller_token).await, 0);
+    assert!(ctx.get_account(&offer_pubkey).await.is_none());
+    let maker_fee = AMOUNT * common::MAKER_FEE_BPS / state::MAX_BPS;
+    let token_account_rent = Rent::default().minimum_balance(spl_token::state::Account::LEN);
+    assert_eq!(ctx.balance(&seller.pubkey()).await, seller_balance + AMOUNT - maker_fee - token_account_rent);
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, bidder_balance - AMOUNT - taker_fee);
+}
+
+#[tokio::test]
+async fn test_only_bidder_cancels_trait_offer() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let stranger = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let attestor = Keypair::new();
+    let root = merkle::root(&[Pubkey::new_unique(), Pubkey::new_unique()]);
+
+    let bidder_balance = ctx.balance(&bidder.pubkey()).await;
+    let ix = instruction::make_trait_offer(&program_id, &bidder.pubkey(), AMOUNT, root, Some(&attestor.pubkey()));
+    ctx.process(&[ix], &[&bidder, &attestor]).await.unwrap();
+    let (offer_pubkey, _) = trait_offer::find_trait_offer_address(&program_id, &bidder.pubkey(), &root);
+    let offer = TraitOffer::unpack(&ctx.get_account(&offer_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(offer.attestor, attestor.pubkey());
+
+    let mut ix = instruction::cancel_trait_offer(&program_id, &bidder.pubkey(), &root);
+    ix.accounts[0].pubkey = stranger.pubkey();
+    let result = ctx.process(&[ix], &[&stranger]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidAuthority as u32));
+
+    let ix = instruction::cancel_trait_offer(&program_id, &bidder.pubkey(), &root);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    assert!(ctx.get_account(&offer_pubkey).await.is_none());
+    assert_eq!(ctx.balance(&bidder.pubkey()).await, bidder_balance);
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use solana_sdk::signature::Signer;
+
+use nft_trading::{error::NFTError, instruction, state::{self, ListEscrowState}};
+
+const PRICE: u64 = LAMPORTS_PER_SOL;
+
+#[tokio::test]
+async fn test_lister_toggles_unlisted_listing() {
+    let mut ctx = common::start().await;
+    ctx.initialize_platform().await;
+    let program_id = ctx.program_id;
+
+    let lister = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let stranger = ctx.create_wallet(LAMPORTS_PER_SOL).await;
+    let (mint, lister_token) = ctx.create_nft(&lister.pubkey()).await;
+    let list = || instruction::list(&program_id, &lister.pubkey(), &lister_token, &mint, PRICE, false, None, None, 0, 0);
+    let ix = instruction::with_visibility(list(), state::VISIBILITY_UNLISTED + 1);
+    let result = ctx.process(&[ix], &[&lister]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidInstructionData as u32));
+    let ix = instruction::with_visibility(list(), state::VISIBILITY_UNLISTED);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+
+    let (list_state_pubkey, _) = state::find_list_state_address(&program_id, &mint, &lister.pubkey());
+    let list_state = ListEscrowState::unpack(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert!(list_state.is_unlisted());
+
+    let mut ix = instruction::set_visibility(&program_id, &lister.pubkey(), &mint, state::VISIBILITY_PUBLIC);
+    ix.accounts[0].pubkey = stranger.pubkey();
+    let result = ctx.process(&[ix], &[&stranger]).await;
+    assert_eq!(common::custom_error(result), Some(NFTError::InvalidAuthority as u32));
+
+    let ix = instruction::set_visibility(&program_id, &lister.pubkey(), &mint, state::VISIBILITY_PUBLIC);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let list_state = ListEscrowState::unpack(&ctx.get_account(&list_state_pubkey).await.unwrap().data).unwrap();
+    assert_eq!(list_state.visibility, state::VISIBILITY_PUBLIC);
+
+    // Unlisted listings still sell to anyone holding their address.
+    let ix = instruction::set_visibility(&program_id, &lister.pubkey(), &mint, state::VISIBILITY_UNLISTED);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let bidder = ctx.create_wallet(10 * LAMPORTS_PER_SOL).await;
+    let ix = instruction::bid(&program_id, &bidder.pubkey(), &mint, PRICE, false, None);
+    ctx.process(&[ix], &[&bidder]).await.unwrap();
+    let authority = ctx.payer.pubkey();
+    let ix = instruction::accept_bid(&program_id, &lister.pubkey(), &mint, &bidder.pubkey(), &authority, None, &[], None);
+    ctx.process(&[ix], &[&lister]).await.unwrap();
+    let bidder_token = spl_associated_token_account::get_associated_token_address(&bidder.pubkey(), &mint);
+    assert_eq!(ctx.token_balance(&bidder_token).await, 1);
+}